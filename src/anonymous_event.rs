@@ -0,0 +1,146 @@
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, ParseOptions, ParseWarning, Result, SealOptions,
+    duplicate_assertions, sealing,
+};
+
+/// An event sealed without a sender: encrypted to its recipients but neither
+/// signed nor carrying the sender's XID document, for reports whose senders
+/// must stay anonymous.
+///
+/// Nothing about where a parsed anonymous event came from is known, so it is
+/// kept a distinct type from [`SealedEvent`](crate::SealedEvent), and it
+/// carries no continuations, since there is no sender to return them to.
+/// Recipients must opt in to accepting anonymous events with
+/// [`ParseOptions::with_anonymous_events`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnonymousEvent<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    event: Event<T>,
+    // Anything questionable about the message that was accepted anyway when
+    // it was parsed.
+    warnings: Vec<ParseWarning>,
+}
+
+impl<T> AnonymousEvent<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    pub fn new(content: impl Into<T>, id: ARID) -> Self {
+        Self {
+            event: Event::new(content, id),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Anything questionable about the message that was accepted anyway when
+    /// it was parsed.
+    pub fn warnings(&self) -> &[ParseWarning] { &self.warnings }
+
+    /// Creates an envelope that can be decrypted by one or more recipients,
+    /// without identifying or authenticating the sender.
+    pub fn to_envelope_anonymous(
+        &self,
+        recipients: &[&XIDDocument],
+    ) -> Result<Envelope> {
+        self.to_envelope_anonymous_with_options(
+            recipients,
+            &SealOptions::default(),
+        )
+    }
+
+    /// Creates an envelope like [`Self::to_envelope_anonymous`], sealed
+    /// according to `options`.
+    ///
+    /// Fails with [`Error::AnonymousEventWithoutRecipients`] if there are no
+    /// recipients, as the event would then be neither authenticated nor
+    /// confidential.
+    pub fn to_envelope_anonymous_with_options(
+        &self,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        if recipients.is_empty() {
+            return Err(Error::AnonymousEventWithoutRecipients);
+        }
+        let mut event = self.event.clone();
+        if let Some(date) = event.date() {
+            event = event.with_date(options.normalize_date(date));
+        }
+        sealing::encrypt_to_recipients(
+            event.into_envelope(),
+            recipients,
+            None,
+            options,
+        )
+    }
+
+    /// Parses an anonymous event encrypted to `recipient`.
+    ///
+    /// Fails with [`Error::AnonymousEventsNotAccepted`] unless `options`
+    /// accept anonymous events.
+    pub fn try_from_anonymous_envelope(
+        encrypted_envelope: &Envelope,
+        recipient: &PrivateKeys,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        if !options.accepts_anonymous_events() {
+            return Err(Error::AnonymousEventsNotAccepted);
+        }
+        let (message, _) =
+            options.decrypt_to_recipient(encrypted_envelope, recipient)?;
+        let mut warnings = Vec::new();
+        let message = duplicate_assertions::check_singular_assertions(
+            message,
+            options.duplicate_assertions(),
+            &mut warnings,
+        )?;
+        options.check_fields(&message)?;
+        let event = Event::<T>::try_from(message)?;
+        Ok(Self { event, warnings })
+    }
+}
+
+impl<T> EventBehavior<T> for AnonymousEvent<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    fn with_note(self, note: impl Into<String>) -> Self {
+        Self {
+            event: self.event.with_note(note),
+            warnings: self.warnings,
+        }
+    }
+
+    fn with_date(self, date: Date) -> Self {
+        Self {
+            event: self.event.with_date(date),
+            warnings: self.warnings,
+        }
+    }
+
+    fn content(&self) -> &T { self.event.content() }
+
+    fn id(&self) -> ARID { self.event.id() }
+
+    fn note(&self) -> &str { self.event.note() }
+
+    fn date(&self) -> Option<Date> { self.event.date() }
+}
@@ -0,0 +1,256 @@
+//! An `axum` extractor and responder for GSTP endpoints.
+//!
+//! A handler takes a [`Gstp<SealedRequest>`] and returns the
+//! [`GstpResponse`] made by [`Gstp::respond`]:
+//!
+//! ```
+//! use bc_envelope::prelude::*;
+//! use gstp::{
+//!     axum::{Gstp, GstpResponse},
+//!     prelude::*,
+//! };
+//!
+//! async fn handler(request: Gstp<SealedRequest>) -> GstpResponse {
+//!     let response =
+//!         SealedResponse::new_success(request.id(), request.identity())
+//!             .with_result("Hello");
+//!     request.respond(response)
+//! }
+//! ```
+//!
+//! The extractor reads the body, decrypts and verifies the request with the
+//! [`GstpServerState`] added to the router as an [`Extension`], and keeps
+//! the sender's XID document so that the response is sealed back to it. As
+//! with [`GstpService`](crate::service::GstpService), errors are answered
+//! rather than returned: a request that cannot be extracted is answered with
+//! a sealed early failure, encrypted to the sender the request claims if it
+//! could be decrypted.
+//!
+//! [`Extension`]: ::axum::Extension
+
+use ::axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{StatusCode, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use bc_components::PrivateKeys;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, ParseOptions, Result, SealOptions, SealedRequest,
+    SealedRequestBehavior, SealedResponse, codec,
+    http::{MEDIA_TYPE, check_content_type, to_body},
+    sealing,
+};
+
+/// The identity, keys, and options with which GSTP endpoints parse requests
+/// and seal responses, shared with them as an [`Extension`].
+///
+/// [`Extension`]: ::axum::Extension
+#[derive(Clone, Debug)]
+pub struct GstpServerState {
+    identity: XIDDocument,
+    private_keys: PrivateKeys,
+    parse_options: ParseOptions,
+    seal_options: SealOptions,
+}
+
+impl GstpServerState {
+    /// Creates the state of endpoints answering as `identity`.
+    pub fn new(identity: &XIDDocument, private_keys: &PrivateKeys) -> Self {
+        Self {
+            identity: identity.clone(),
+            private_keys: private_keys.clone(),
+            parse_options: ParseOptions::default(),
+            seal_options: SealOptions::default(),
+        }
+    }
+
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    pub fn with_seal_options(mut self, options: SealOptions) -> Self {
+        self.seal_options = options;
+        self
+    }
+
+    pub fn identity(&self) -> &XIDDocument { &self.identity }
+
+    /// Answers a request that could not be extracted with an early failure
+    /// giving `error`.
+    fn early_failure(
+        &self,
+        status: StatusCode,
+        error: &Error,
+        envelope: Option<&Envelope>,
+    ) -> GstpResponse {
+        let recipient = envelope.and_then(|envelope| {
+            sealing::claimed_sender(envelope, &self.private_keys)
+        });
+        let sealed = SealedResponse::new_early_failure(&self.identity)
+            .with_error(error.to_string())
+            .to_envelope_unprotected_i_know_what_i_am_doing(
+                None,
+                Some(&self.private_keys),
+                &Vec::from_iter(recipient.as_ref()),
+                &self.seal_options,
+            );
+        GstpResponse { status, sealed }
+    }
+}
+
+/// The HTTP status of the early failure answering a request that failed
+/// with `error`.
+fn status_for(error: &Error) -> StatusCode {
+    match error {
+        Error::UnexpectedContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        Error::UnexpectedSender { .. }
+        | Error::SenderNotAuthorized(_)
+        | Error::SenderKeyChanged { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// A GSTP message extracted from the body of an HTTP request.
+///
+/// It dereferences to the message, and [`Gstp::respond`] seals a response
+/// back to the request's sender.
+#[derive(Clone, Debug)]
+pub struct Gstp<T> {
+    message: T,
+    state: GstpServerState,
+}
+
+impl<T> Gstp<T> {
+    pub fn into_inner(self) -> T { self.message }
+
+    /// The identity the endpoint answers as.
+    pub fn identity(&self) -> &XIDDocument { &self.state.identity }
+}
+
+impl<T> std::ops::Deref for Gstp<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.message }
+}
+
+impl Gstp<SealedRequest> {
+    /// Seals `response` to the sender of the request, as a successful HTTP
+    /// response.
+    ///
+    /// The response is bound to the request it answers and encrypted to the
+    /// request's ephemeral key if it carries one, unless it already says
+    /// otherwise.
+    pub fn respond(&self, response: SealedResponse) -> GstpResponse {
+        let request = &self.message;
+        let in_response_to =
+            response.in_response_to().or(request.envelope_digest());
+        let reply_key =
+            response.reply_key().or(request.ephemeral_key()).cloned();
+        let sealed = response
+            .with_optional_in_response_to(in_response_to)
+            .with_optional_reply_key(reply_key)
+            .to_envelope_with_options(
+                None,
+                Some(&self.state.private_keys),
+                &[request.sender()],
+                &self.state.seal_options,
+            );
+        GstpResponse {
+            status: StatusCode::OK,
+            sealed,
+        }
+    }
+}
+
+impl<S> FromRequest<S> for Gstp<SealedRequest>
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(
+        request: Request,
+        state: &S,
+    ) -> std::result::Result<Self, Response> {
+        let Some(server) = request.extensions().get::<GstpServerState>() else {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "missing GstpServerState extension",
+            )
+                .into_response());
+        };
+        let server = server.clone();
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        if let Err(error) = check_content_type(content_type.as_deref()) {
+            return Err(server
+                .early_failure(status_for(&error), &error, None)
+                .into_response());
+        }
+        let body = Bytes::from_request(request, state).await.map_err(|e| {
+            let error = Error::Io(std::io::Error::other(e.body_text()));
+            server
+                .early_failure(e.status(), &error, None)
+                .into_response()
+        })?;
+        let envelope = codec::from_cbor(&body).map_err(|error| {
+            server
+                .early_failure(status_for(&error), &error, None)
+                .into_response()
+        })?;
+        match SealedRequest::try_from_envelope_opt(
+            &envelope,
+            &server.parse_options,
+            &server.private_keys,
+        ) {
+            Ok(message) => Ok(Self {
+                message,
+                state: server,
+            }),
+            Err(error) => Err(server
+                .early_failure(status_for(&error), &error, Some(&envelope))
+                .into_response()),
+        }
+    }
+}
+
+/// A sealed response to be sent as the body of an HTTP response.
+///
+/// If the response could not be sealed, an empty
+/// `500 Internal Server Error` is sent instead.
+#[derive(Debug)]
+pub struct GstpResponse {
+    status: StatusCode,
+    sealed: Result<Envelope>,
+}
+
+impl GstpResponse {
+    pub fn status(&self) -> StatusCode { self.status }
+
+    /// The sealed response, or the error that kept it from being sealed.
+    pub fn sealed(&self) -> std::result::Result<&Envelope, &Error> {
+        self.sealed.as_ref()
+    }
+}
+
+impl IntoResponse for GstpResponse {
+    fn into_response(self) -> Response {
+        match self.sealed {
+            Ok(envelope) => (
+                self.status,
+                [(CONTENT_TYPE, MEDIA_TYPE)],
+                to_body(&envelope),
+            )
+                .into_response(),
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
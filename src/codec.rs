@@ -0,0 +1,167 @@
+//! Text and binary encodings of envelopes for carrying them in places such as
+//! HTTP headers, JSON fields, and command lines.
+//!
+//! Each encoding has a pair of functions: raw CBOR bytes with [`to_cbor`] and
+//! [`from_cbor`], lowercase hex with [`to_hex`] and [`from_hex`], and
+//! unpadded base64url with [`to_base64url`] and [`from_base64url`]. Decoding
+//! is strict: anything but the canonical encoding of a single envelope is
+//! rejected, including surrounding whitespace and trailing bytes.
+//! [`decode_any`] is the lenient exception, for input pasted by a user in
+//! whatever encoding they had at hand.
+
+use base64::{DecodeError, Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// An encoding of an envelope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// The CBOR encoding of the envelope, as raw bytes.
+    Cbor,
+    /// The CBOR encoding of the envelope in lowercase hex.
+    Hex,
+    /// The CBOR encoding of the envelope in base64url without padding.
+    Base64Url,
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Cbor => write!(f, "CBOR"),
+            Encoding::Hex => write!(f, "hex"),
+            Encoding::Base64Url => write!(f, "base64url"),
+        }
+    }
+}
+
+/// Encodes `envelope` as CBOR bytes.
+pub fn to_cbor(envelope: &Envelope) -> Vec<u8> { envelope.to_cbor_data() }
+
+/// Decodes an envelope from CBOR bytes.
+///
+/// Fails with [`Error::TrailingBytes`] if anything follows the envelope.
+pub fn from_cbor(data: &[u8]) -> Result<Envelope> {
+    Envelope::try_from_cbor_data(data.to_vec()).map_err(|e| match e {
+        dcbor::Error::UnusedData(count) => Error::TrailingBytes { count },
+        e => e.into(),
+    })
+}
+
+/// Encodes `envelope` as lowercase hex.
+pub fn to_hex(envelope: &Envelope) -> String {
+    hex::encode(envelope.to_cbor_data())
+}
+
+/// Decodes an envelope from lowercase hex.
+///
+/// Fails with [`Error::InvalidEncodedCharacter`] at the first character that
+/// is not a lowercase hex digit, and with [`Error::InvalidEncodedLength`] if
+/// the input has an odd number of digits.
+pub fn from_hex(text: &str) -> Result<Envelope> {
+    fn nibble(c: u8) -> Option<u8> {
+        match c {
+            b'0'..=b'9' => Some(c - b'0'),
+            b'a'..=b'f' => Some(c - b'a' + 10),
+            _ => None,
+        }
+    }
+
+    let text = text.as_bytes();
+    let digits = text
+        .iter()
+        .enumerate()
+        .map(|(position, &c)| {
+            nibble(c).ok_or(Error::InvalidEncodedCharacter {
+                encoding: Encoding::Hex,
+                position,
+            })
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    if digits.len() % 2 != 0 {
+        return Err(Error::InvalidEncodedLength {
+            encoding: Encoding::Hex,
+            length: text.len(),
+        });
+    }
+    let data: Vec<u8> = digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect();
+    from_cbor(&data)
+}
+
+/// Encodes `envelope` as base64url without padding.
+pub fn to_base64url(envelope: &Envelope) -> String {
+    URL_SAFE_NO_PAD.encode(envelope.to_cbor_data())
+}
+
+/// Decodes an envelope from base64url without padding.
+///
+/// Fails with [`Error::InvalidEncodedCharacter`] at the first character
+/// outside the base64url alphabet, including padding and a final character
+/// with unused bits set, and with [`Error::InvalidEncodedLength`] if the
+/// input has a length no encoding can have.
+pub fn from_base64url(text: &str) -> Result<Envelope> {
+    let data = URL_SAFE_NO_PAD.decode(text).map_err(|e| match e {
+        DecodeError::InvalidByte(position, _)
+        | DecodeError::InvalidLastSymbol(position, _) => {
+            Error::InvalidEncodedCharacter {
+                encoding: Encoding::Base64Url,
+                position,
+            }
+        }
+        DecodeError::InvalidPadding => Error::InvalidEncodedCharacter {
+            encoding: Encoding::Base64Url,
+            position: text.find('=').unwrap_or(text.len()),
+        },
+        DecodeError::InvalidLength(_) => Error::InvalidEncodedLength {
+            encoding: Encoding::Base64Url,
+            length: text.len(),
+        },
+    })?;
+    from_cbor(&data)
+}
+
+/// Guesses the encoding of `input`, or returns `None` if it is empty.
+///
+/// Every envelope's CBOR encoding starts with a tag, which is neither a hex
+/// digit nor a base64url character, and the base64url encoding of that tag
+/// is not hex, so the guess is right for any input that is a valid encoding
+/// at all. Surrounding whitespace of text encodings is ignored.
+pub fn detect(input: &[u8]) -> Option<Encoding> {
+    let first = *input.first()?;
+    if !first.is_ascii() {
+        return Some(Encoding::Cbor);
+    }
+    let text = input.trim_ascii();
+    if text.is_empty() {
+        None
+    } else if text.iter().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')) {
+        Some(Encoding::Hex)
+    } else {
+        Some(Encoding::Base64Url)
+    }
+}
+
+/// Decodes an envelope from `input` in whichever encoding [`detect`] finds.
+///
+/// Fails with [`Error::UnrecognizedEncoding`] if the input is empty, and
+/// otherwise as the decoding function of the detected encoding does.
+pub fn decode_any(input: &[u8]) -> Result<Envelope> {
+    let encoding = detect(input).ok_or(Error::UnrecognizedEncoding)?;
+    if encoding == Encoding::Cbor {
+        return from_cbor(input);
+    }
+    // Positions in errors are relative to the trimmed text.
+    let text = std::str::from_utf8(input.trim_ascii()).map_err(|e| {
+        Error::InvalidEncodedCharacter {
+            encoding,
+            position: e.valid_up_to(),
+        }
+    })?;
+    match encoding {
+        Encoding::Hex => from_hex(text),
+        _ => from_base64url(text),
+    }
+}
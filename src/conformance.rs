@@ -0,0 +1,339 @@
+//! Probes a live peer with well-known requests and scores its responses.
+//!
+//! A [`ConformanceSuite`] sends each of its [`Check`]s to the peer through a
+//! [`GstpTransport`] and collects a [`ConformanceReport`]. Checks relying on
+//! a function the peer does not implement are reported as skipped, with a
+//! note saying why.
+
+use std::time::Duration;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, Error, ParseOptions, Result, SealOptions, SealedRequest,
+    SealedRequestBehavior, SealedResponse, gstp_version,
+    transport::GstpTransport,
+};
+
+/// A single conformance check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// A `ping` request must get a successful response, signed by the peer,
+    /// answering the request's ID.
+    Ping,
+    /// A request padded to the given number of bytes must be refused for
+    /// its size, either by the transport with [`Error::FrameTooLarge`] or
+    /// with a failure response saying which limit the request exceeds.
+    OversizedMessage(usize),
+    /// A request returning a continuation to the peer that has expired must
+    /// be refused.
+    ExpiredContinuation,
+    /// A request declaring a protocol version newer than any this crate
+    /// supports must be refused.
+    UnsupportedVersion,
+    /// The peer must answer a `describe` request with its capabilities. The
+    /// check is skipped if the peer does not implement `describe`.
+    Describe,
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Check::Ping => write!(f, "ping"),
+            Check::OversizedMessage(size) => {
+                write!(f, "oversized message ({} bytes)", size)
+            }
+            Check::ExpiredContinuation => write!(f, "expired continuation"),
+            Check::UnsupportedVersion => write!(f, "unsupported version"),
+            Check::Describe => write!(f, "describe"),
+        }
+    }
+}
+
+/// The outcome of a single check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// The peer misbehaved, for the given reason.
+    Fail(String),
+    /// The check could not be run, for the given reason.
+    Skipped(String),
+}
+
+/// The result of running a single check against the peer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub check: Check,
+    pub outcome: Outcome,
+    /// A summary of the response that caused a failure, if there was one.
+    pub response_summary: Option<String>,
+}
+
+/// The results of running a [`ConformanceSuite`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    /// Returns `true` if no check failed. Skipped checks do not count as
+    /// failures.
+    pub fn passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| !matches!(result.outcome, Outcome::Fail(_)))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, Outcome::Fail(_)))
+    }
+}
+
+/// A configurable list of checks to run against a peer.
+#[derive(Debug)]
+pub struct ConformanceSuite<'a> {
+    client: &'a XIDDocument,
+    client_private_keys: &'a PrivateKeys,
+    peer: &'a XIDDocument,
+    checks: Vec<Check>,
+}
+
+impl<'a> ConformanceSuite<'a> {
+    /// Creates a suite with the default checks, run as `client` against
+    /// `peer`.
+    pub fn new(
+        client: &'a XIDDocument,
+        client_private_keys: &'a PrivateKeys,
+        peer: &'a XIDDocument,
+    ) -> Self {
+        Self {
+            client,
+            client_private_keys,
+            peer,
+            checks: vec![
+                Check::Ping,
+                Check::OversizedMessage(2 * 1024 * 1024),
+                Check::ExpiredContinuation,
+                Check::UnsupportedVersion,
+                Check::Describe,
+            ],
+        }
+    }
+
+    pub fn with_checks(mut self, checks: Vec<Check>) -> Self {
+        self.checks = checks;
+        self
+    }
+
+    pub fn checks(&self) -> &[Check] { &self.checks }
+
+    /// Runs every check in order.
+    pub async fn run(
+        &self,
+        transport: &mut impl GstpTransport,
+    ) -> ConformanceReport {
+        let mut results = Vec::with_capacity(self.checks.len());
+        for check in &self.checks {
+            results.push(self.run_check(check, transport).await);
+        }
+        ConformanceReport { results }
+    }
+
+    async fn run_check(
+        &self,
+        check: &Check,
+        transport: &mut impl GstpTransport,
+    ) -> CheckResult {
+        let (outcome, response_summary) = match check {
+            Check::Ping => self.check_ping(transport).await,
+            Check::OversizedMessage(size) => {
+                self.check_oversized(*size, transport).await
+            }
+            Check::ExpiredContinuation => {
+                self.check_expired_continuation(transport).await
+            }
+            Check::UnsupportedVersion => {
+                self.check_unsupported_version(transport).await
+            }
+            Check::Describe => self.check_describe(transport).await,
+        };
+        CheckResult {
+            check: check.clone(),
+            outcome,
+            response_summary,
+        }
+    }
+
+    /// Seals `request` with `options` and returns the peer's response, which
+    /// may be an early failure if the peer could not parse the request.
+    async fn exchange(
+        &self,
+        request: SealedRequest,
+        options: &SealOptions,
+        transport: &mut impl GstpTransport,
+    ) -> Result<SealedResponse> {
+        let envelope = request.to_envelope_with_options(
+            None,
+            Some(self.client_private_keys),
+            &[self.peer],
+            options,
+        )?;
+        let response = transport.exchange(envelope).await?;
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &response,
+            &ParseOptions::new()
+                .with_expected_id(request.id())
+                .with_expected_sender(self.peer)
+                .with_early_failures(true),
+            self.client_private_keys,
+        )
+    }
+
+    /// Sends a request the peer must refuse, passing if it does.
+    async fn expect_refusal(
+        &self,
+        request: SealedRequest,
+        options: &SealOptions,
+        transport: &mut impl GstpTransport,
+        accepted: &str,
+    ) -> (Outcome, Option<String>) {
+        match self.exchange(request, options, transport).await {
+            Err(error) => (Outcome::Fail(error.to_string()), None),
+            Ok(response) if response.is_err() => (Outcome::Pass, None),
+            Ok(response) => {
+                (Outcome::Fail(accepted.into()), Some(response.to_string()))
+            }
+        }
+    }
+
+    async fn check_ping(
+        &self,
+        transport: &mut impl GstpTransport,
+    ) -> (Outcome, Option<String>) {
+        let id = ARID::new();
+        let request = SealedRequest::new("ping", id, self.client);
+        match self.exchange(request, &SealOptions::new(), transport).await {
+            Err(error) => (Outcome::Fail(error.to_string()), None),
+            Ok(response) if response.is_err() => (
+                Outcome::Fail("ping was refused".into()),
+                Some(response.to_string()),
+            ),
+            Ok(response) if response.id() != Some(id) => (
+                Outcome::Fail("response answers a different request".into()),
+                Some(response.to_string()),
+            ),
+            Ok(_) => (Outcome::Pass, None),
+        }
+    }
+
+    /// Passes only if the request is refused because of its size: by the
+    /// transport with [`Error::FrameTooLarge`], or with a failure whose
+    /// error says what "exceeds the limit", as every size error does.
+    async fn check_oversized(
+        &self,
+        size: usize,
+        transport: &mut impl GstpTransport,
+    ) -> (Outcome, Option<String>) {
+        let request = SealedRequest::new("ping", ARID::new(), self.client)
+            .with_parameter("padding", ByteString::from(vec![0u8; size]));
+        match self.exchange(request, &SealOptions::new(), transport).await {
+            Err(Error::FrameTooLarge { .. }) => (Outcome::Pass, None),
+            Err(error) => (Outcome::Fail(error.to_string()), None),
+            Ok(response) if response.is_err() => {
+                let error =
+                    response.extract_error::<String>().unwrap_or_default();
+                if error.contains("exceeds the limit") {
+                    (Outcome::Pass, None)
+                } else {
+                    (
+                        Outcome::Fail(
+                            "oversized message was refused for another reason"
+                                .into(),
+                        ),
+                        Some(response.to_string()),
+                    )
+                }
+            }
+            Ok(response) => (
+                Outcome::Fail("oversized message was accepted".into()),
+                Some(response.to_string()),
+            ),
+        }
+    }
+
+    /// Returns to the peer a continuation encrypted to it that expired an
+    /// hour ago, as one it issued would be after its lifetime.
+    async fn check_expired_continuation(
+        &self,
+        transport: &mut impl GstpTransport,
+    ) -> (Outcome, Option<String>) {
+        let Some(key) = self.peer.encryption_key() else {
+            return (
+                Outcome::Skipped("the peer has no encryption key".into()),
+                None,
+            );
+        };
+        let now = Date::now();
+        let continuation = Continuation::new("conformance")
+            .with_issued_at(now - Duration::from_secs(2 * 60 * 60))
+            .with_valid_until(now - Duration::from_secs(60 * 60))
+            .to_envelope(Some(key));
+        let request = SealedRequest::new("ping", ARID::new(), self.client)
+            .with_peer_continuation(continuation);
+        self.expect_refusal(
+            request,
+            &SealOptions::new(),
+            transport,
+            "expired continuation was accepted",
+        )
+        .await
+    }
+
+    async fn check_unsupported_version(
+        &self,
+        transport: &mut impl GstpTransport,
+    ) -> (Outcome, Option<String>) {
+        let request = SealedRequest::new("ping", ARID::new(), self.client);
+        let options = SealOptions::new()
+            .with_gstp_version(gstp_version::max_supported_version() + 1);
+        self.expect_refusal(
+            request,
+            &options,
+            transport,
+            "unsupported version was accepted",
+        )
+        .await
+    }
+
+    async fn check_describe(
+        &self,
+        transport: &mut impl GstpTransport,
+    ) -> (Outcome, Option<String>) {
+        let request = SealedRequest::new("describe", ARID::new(), self.client);
+        match self.exchange(request, &SealOptions::new(), transport).await {
+            Err(error) => (Outcome::Fail(error.to_string()), None),
+            Ok(response) if response.is_err() => (
+                Outcome::Skipped(format!(
+                    "the peer does not implement describe: {}",
+                    response.extract_error::<String>().unwrap_or_default()
+                )),
+                None,
+            ),
+            Ok(response)
+                if response.result().is_ok_and(|result| {
+                    !result.is_null() && !result.is_known_value()
+                }) =>
+            {
+                (Outcome::Pass, None)
+            }
+            Ok(response) => (
+                Outcome::Fail("describe returned no capabilities".into()),
+                Some(response.to_string()),
+            ),
+        }
+    }
+}
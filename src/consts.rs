@@ -0,0 +1,99 @@
+//! The library's protocol constants and defaults.
+//!
+//! The `Default` implementations of the option types are built from these
+//! constants, so they describe the library's actual behavior and may be used
+//! to display or validate a configuration.
+
+use crate::{ContinuationPolicy, DatePrecision, FieldLimits, ParseLimits};
+
+/// The version of the protocol implemented by this crate.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The protocol versions this crate can parse.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+
+/// The limits used by [`ParseLimits::default`].
+pub const DEFAULT_PARSE_LIMITS: ParseLimits =
+    ParseLimits::from_parts(1024 * 1024);
+
+/// The limits used by [`FieldLimits::default`].
+pub const DEFAULT_FIELD_LIMITS: FieldLimits =
+    FieldLimits::from_parts(16 * 1024, 256, 256 * 1024, 1024 * 1024, 64 * 1024);
+
+/// The largest size in bytes that the compressed payload of a sealed
+/// message may expand to.
+pub const MAX_DECOMPRESSED_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The largest size in bytes that the compressed state of a continuation
+/// returned to us may expand to.
+pub const MAX_DECOMPRESSED_STATE_SIZE: usize = 10 * 1024 * 1024;
+
+/// The number of recent events, and of senders, an
+/// [`EventBus`](crate::EventBus) remembers for deduplication and sequence
+/// tracking by default.
+pub const DEFAULT_EVENT_WINDOW: usize = 4096;
+
+/// The continuation policy used by the defaults of
+/// [`SealOptions`](crate::SealOptions) and
+/// [`ParseOptions`](crate::ParseOptions).
+pub const DEFAULT_CONTINUATION_POLICY: ContinuationPolicy =
+    ContinuationPolicy {
+        expiry_hints: false,
+        max_peer_lifetime: None,
+        max_state_lifetime: None,
+    };
+
+/// The precision used by [`DatePrecision::default`].
+pub const DEFAULT_DATE_PRECISION: DatePrecision = DatePrecision::Seconds;
+
+/// A summary of the library's defaults, as returned by [`describe_defaults`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct DefaultsDescription {
+    pub crate_version: &'static str,
+    pub protocol_version: u32,
+    pub supported_protocol_versions: Vec<u32>,
+    pub frame_version: u8,
+    pub media_type: &'static str,
+    pub max_message_size: usize,
+    pub max_note_length: usize,
+    pub max_assertions: usize,
+    pub max_parameter_value_size: usize,
+    pub max_result_size: usize,
+    pub max_state_size: usize,
+    pub duplicate_assertions: String,
+    pub date_precision: String,
+    pub continuation_expiry_hints: bool,
+    /// The cap on the retention of peer continuations, in seconds.
+    pub max_peer_continuation_lifetime: Option<u64>,
+}
+
+/// Summarizes the library's defaults, for example for a health endpoint.
+#[cfg(feature = "serde")]
+pub fn describe_defaults() -> DefaultsDescription {
+    use crate::{DuplicateAssertionPolicy, framing};
+
+    DefaultsDescription {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        protocol_version: PROTOCOL_VERSION,
+        supported_protocol_versions: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
+        frame_version: framing::FRAME_VERSION,
+        media_type: framing::MEDIA_TYPE,
+        max_message_size: DEFAULT_PARSE_LIMITS.max_message_size(),
+        max_note_length: DEFAULT_FIELD_LIMITS.max_note_length(),
+        max_assertions: DEFAULT_FIELD_LIMITS.max_assertions(),
+        max_parameter_value_size: DEFAULT_FIELD_LIMITS
+            .max_parameter_value_size(),
+        max_result_size: DEFAULT_FIELD_LIMITS.max_result_size(),
+        max_state_size: DEFAULT_FIELD_LIMITS.max_state_size(),
+        duplicate_assertions: format!(
+            "{:?}",
+            DuplicateAssertionPolicy::default()
+        ),
+        date_precision: format!("{:?}", DEFAULT_DATE_PRECISION),
+        continuation_expiry_hints: DEFAULT_CONTINUATION_POLICY.expiry_hints,
+        max_peer_continuation_lifetime: DEFAULT_CONTINUATION_POLICY
+            .max_peer_lifetime
+            .map(|lifetime| lifetime.as_secs()),
+    }
+}
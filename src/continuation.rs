@@ -1,20 +1,39 @@
-use bc_components::{ARID, Encrypter, PrivateKeys};
+use std::time::Duration;
+
+use bc_components::{
+    ARID, Decrypter, Encrypter, PrivateKeys, SymmetricKey, XID,
+};
 use bc_envelope::prelude::*;
 
-use crate::{Error, Result};
+use crate::{Error, ParseOptions, ParsePolicy, Result, consts, sealing};
+
+const NONCE: &str = "nonce";
+const VALID_FUNCTION: &str = "validFunction";
+const VALID_SENDER: &str = "validSender";
 
 #[derive(Clone, Debug)]
 pub struct Continuation {
     state: Envelope,
     valid_id: Option<ARID>,
+    nonce: Option<ARID>,
+    valid_function: Option<Function>,
+    valid_sender: Option<XID>,
+    valid_from: Option<Date>,
     valid_until: Option<Date>,
+    issued_at: Option<Date>,
+    compress_state: bool,
 }
 
 impl PartialEq for Continuation {
     fn eq(&self, other: &Self) -> bool {
         self.state == other.state
             && self.valid_id == other.valid_id
+            && self.nonce == other.nonce
+            && self.valid_function == other.valid_function
+            && self.valid_sender == other.valid_sender
+            && self.valid_from == other.valid_from
             && self.valid_until == other.valid_until
+            && self.issued_at == other.issued_at
     }
 }
 
@@ -26,7 +45,13 @@ impl Continuation {
         Self {
             state: state.into_envelope(),
             valid_id: None,
+            nonce: None,
+            valid_function: None,
+            valid_sender: None,
+            valid_from: None,
             valid_until: None,
+            issued_at: None,
+            compress_state: false,
         }
     }
 
@@ -42,6 +67,69 @@ impl Continuation {
         self
     }
 
+    /// Sets a value unique to this continuation, so that a
+    /// [`ContinuationReplayGuard`](crate::ContinuationReplayGuard) can accept
+    /// it only once.
+    pub fn with_nonce(mut self, nonce: ARID) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn with_optional_nonce(self, nonce: Option<ARID>) -> Self {
+        if let Some(nonce) = nonce {
+            return self.with_nonce(nonce);
+        }
+        self
+    }
+
+    /// Binds the continuation to `function`, so that it is only accepted when
+    /// returned with a request to that function.
+    pub fn with_valid_function(
+        mut self,
+        function: impl Into<Function>,
+    ) -> Self {
+        self.valid_function = Some(function.into());
+        self
+    }
+
+    pub fn with_optional_valid_function(
+        self,
+        function: Option<Function>,
+    ) -> Self {
+        if let Some(function) = function {
+            return self.with_valid_function(function);
+        }
+        self
+    }
+
+    /// Binds the continuation to the peer `sender`, so that it is only
+    /// accepted when returned in a message that peer signed.
+    pub fn with_valid_sender(mut self, sender: XID) -> Self {
+        self.valid_sender = Some(sender);
+        self
+    }
+
+    pub fn with_optional_valid_sender(self, sender: Option<XID>) -> Self {
+        if let Some(sender) = sender {
+            return self.with_valid_sender(sender);
+        }
+        self
+    }
+
+    /// Sets the time before which the continuation is not yet valid, such
+    /// as the end of a cool-down the client must wait out.
+    pub fn with_valid_from(mut self, valid_from: Date) -> Self {
+        self.valid_from = Some(valid_from);
+        self
+    }
+
+    pub fn with_optional_valid_from(self, valid_from: Option<Date>) -> Self {
+        if let Some(valid_from) = valid_from {
+            return self.with_valid_from(valid_from);
+        }
+        self
+    }
+
     pub fn with_valid_until(mut self, valid_until: Date) -> Self {
         self.valid_until = Some(valid_until);
         self
@@ -54,9 +142,31 @@ impl Continuation {
         self
     }
 
-    pub fn with_valid_duration(self, duration: std::time::Duration) -> Self {
+    pub fn with_valid_duration(self, duration: Duration) -> Self {
         self.with_valid_until(Date::now() + duration)
     }
+
+    /// Records when the continuation was issued. The sealed message types set
+    /// this to the time of sealing.
+    pub fn with_issued_at(mut self, issued_at: Date) -> Self {
+        self.issued_at = Some(issued_at);
+        self
+    }
+
+    pub fn with_optional_issued_at(self, issued_at: Option<Date>) -> Self {
+        if let Some(issued_at) = issued_at {
+            return self.with_issued_at(issued_at);
+        }
+        self
+    }
+
+    /// Sets whether the state is compressed when the continuation is turned
+    /// into an envelope. The compressed form is only kept if it is actually
+    /// smaller. Compression does not change the digest of the continuation.
+    pub fn with_state_compression(mut self, compress: bool) -> Self {
+        self.compress_state = compress;
+        self
+    }
 }
 
 //
@@ -67,15 +177,80 @@ impl Continuation {
 
     pub fn id(&self) -> Option<ARID> { self.valid_id }
 
+    pub fn nonce(&self) -> Option<ARID> { self.nonce }
+
+    pub fn valid_function(&self) -> Option<&Function> {
+        self.valid_function.as_ref()
+    }
+
+    pub fn valid_sender(&self) -> Option<XID> { self.valid_sender }
+
+    pub fn valid_from(&self) -> Option<Date> { self.valid_from }
+
     pub fn valid_until(&self) -> Option<Date> { self.valid_until }
 
-    pub fn is_valid_date(&self, now: Option<Date>) -> bool {
-        match now {
-            Some(now) => self
-                .valid_until()
-                .is_none_or(|valid_until| valid_until > now),
-            None => true,
-        }
+    pub fn issued_at(&self) -> Option<Date> { self.issued_at }
+
+    pub(crate) fn with_state(mut self, state: Envelope) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Returns the size in bytes of the CBOR-encoded state.
+    pub fn state_size(&self) -> usize { self.state.to_cbor_data().len() }
+
+    /// Fails with [`Error::ContinuationStateTooLarge`] if the state is larger
+    /// than `limit` bytes.
+    pub fn check_state_size(&self, limit: usize) -> Result<()> {
+        check_state_size(&self.state, limit)
+    }
+
+    /// Returns how long before `now` the continuation was issued, or `None`
+    /// if it does not record when it was issued. A continuation issued after
+    /// `now` has an age of zero.
+    pub fn age(&self, now: Date) -> Option<Duration> {
+        self.issued_at.map(|issued_at| {
+            Duration::from_secs_f64(
+                (now.timestamp() - issued_at.timestamp()).max(0.0),
+            )
+        })
+    }
+
+    /// Returns how long after `now` the continuation expires, or `None` if it
+    /// does not expire. A continuation that has expired by `now` has no
+    /// validity remaining.
+    pub fn remaining_validity(&self, now: Date) -> Option<Duration> {
+        self.valid_until.map(|valid_until| {
+            Duration::from_secs_f64(
+                (valid_until.timestamp() - now.timestamp()).max(0.0),
+            )
+        })
+    }
+
+    /// Returns `true` if `now` is neither before the continuation becomes
+    /// valid nor at or after it expires, allowing for the clocks of the
+    /// issuer and the caller to differ by up to `tolerance`.
+    pub fn is_valid_date(
+        &self,
+        now: Option<Date>,
+        tolerance: Duration,
+    ) -> bool {
+        !self.is_not_yet_valid(now, tolerance)
+            && !self.is_expired(now, tolerance)
+    }
+
+    fn is_not_yet_valid(&self, now: Option<Date>, tolerance: Duration) -> bool {
+        now.is_some_and(|now| {
+            self.valid_from
+                .is_some_and(|valid_from| now + tolerance < valid_from)
+        })
+    }
+
+    fn is_expired(&self, now: Option<Date>, tolerance: Duration) -> bool {
+        now.is_some_and(|now| {
+            self.valid_until
+                .is_some_and(|valid_until| valid_until + tolerance <= now)
+        })
     }
 
     pub fn is_valid_id(&self, id: Option<ARID>) -> bool {
@@ -87,21 +262,73 @@ impl Continuation {
         }
     }
 
+    /// Returns `true` if the continuation is not bound to a function other
+    /// than `function`. A continuation bound to a function is never valid
+    /// when `function` is `None`.
+    pub fn is_valid_function(&self, function: Option<&Function>) -> bool {
+        match (function, &self.valid_function) {
+            (Some(function), Some(valid_function)) => {
+                function == valid_function
+            }
+            (None, Some(_)) => false,
+            (_, None) => true,
+        }
+    }
+
+    /// Returns `true` if the continuation is not bound to a sender other than
+    /// `sender`, which must be the XID of an authenticated sender.
+    pub fn is_valid_sender(&self, sender: XID) -> bool {
+        self.valid_sender
+            .is_none_or(|valid_sender| valid_sender == sender)
+    }
+
     pub fn is_valid(&self, now: Option<Date>, id: Option<ARID>) -> bool {
-        self.is_valid_date(now) && self.is_valid_id(id)
+        self.is_valid_date(now, Duration::ZERO) && self.is_valid_id(id)
     }
 }
 
+pub(crate) fn check_state_size(state: &Envelope, limit: usize) -> Result<()> {
+    let size = state.to_cbor_data().len();
+    if size > limit {
+        return Err(Error::ContinuationStateTooLarge { size, limit });
+    }
+    Ok(())
+}
+
+/// Decompresses the wrapped state of a continuation if it was compressed,
+/// refusing state that would expand beyond
+/// [`consts::MAX_DECOMPRESSED_STATE_SIZE`].
+///
+/// The declared size is checked before anything is decompressed, and the
+/// state is inflated into at most that many bytes, so state understating its
+/// size fails as corrupt rather than expanding without bound.
+fn decompress_state(envelope: Envelope) -> Result<Envelope> {
+    let subject = envelope.subject();
+    if !subject.is_compressed() {
+        return Ok(envelope);
+    }
+    let subject =
+        sealing::decompress(&subject, consts::MAX_DECOMPRESSED_STATE_SIZE)
+            .map_err(|error| match error {
+                Error::DecompressedTooLarge { size, limit } => {
+                    Error::ContinuationStateTooLarge { size, limit }
+                }
+                error => error,
+            })?;
+    Ok(envelope.replace_subject(subject))
+}
+
 impl Continuation {
     pub fn to_envelope(&self, recipient: Option<&dyn Encrypter>) -> Envelope {
         let mut result = self
-            .state
-            .wrap()
+            .wrapped_state()
             .add_optional_assertion(known_values::ID, self.valid_id)
-            .add_optional_assertion(
-                known_values::VALID_UNTIL,
-                self.valid_until,
-            );
+            .add_optional_assertion(NONCE, self.nonce)
+            .add_optional_assertion(VALID_FUNCTION, self.valid_function.clone())
+            .add_optional_assertion(VALID_SENDER, self.valid_sender)
+            .add_optional_assertion(known_values::VALID_FROM, self.valid_from)
+            .add_optional_assertion(known_values::VALID_UNTIL, self.valid_until)
+            .add_optional_assertion(known_values::DATE, self.issued_at);
 
         if let Some(sender) = recipient {
             result = result.encrypt_to_recipient(sender);
@@ -110,29 +337,163 @@ impl Continuation {
         result
     }
 
+    fn wrapped_state(&self) -> Envelope {
+        let wrapped = self.state.wrap();
+        if !self.compress_state {
+            return wrapped;
+        }
+        match wrapped.compress() {
+            Ok(compressed)
+                if compressed.to_cbor_data().len()
+                    < wrapped.to_cbor_data().len() =>
+            {
+                compressed
+            }
+            _ => wrapped,
+        }
+    }
+
+    /// Encrypts the continuation with `key`, a secret that only its issuer
+    /// holds, rather than to a recipient.
+    pub fn to_envelope_with_symmetric_key(
+        &self,
+        key: &SymmetricKey,
+    ) -> Envelope {
+        self.to_envelope(None).encrypt(key)
+    }
+
     pub fn try_from_envelope(
         encrypted_envelope: &Envelope,
         id: Option<ARID>,
         now: Option<Date>,
         recipient: Option<&PrivateKeys>,
     ) -> Result<Self> {
-        let envelope = if let Some(recipient) = recipient {
-            encrypted_envelope.decrypt_to_recipient(recipient)?
-        } else {
-            encrypted_envelope.clone()
+        let keys: Vec<&dyn Decrypter> = recipient
+            .into_iter()
+            .map(|recipient| recipient as &dyn Decrypter)
+            .collect();
+        Self::try_from_envelope_with_keys(encrypted_envelope, id, now, &keys)
+    }
+
+    /// Parses a continuation encrypted with `key`, as by
+    /// [`Self::to_envelope_with_symmetric_key`].
+    pub fn try_from_envelope_with_symmetric_key(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        key: &SymmetricKey,
+    ) -> Result<Self> {
+        if !encrypted_envelope.subject().is_encrypted() {
+            return Err(Error::ContinuationNotEncrypted);
+        }
+        Self::try_from_envelope_with_keys(
+            &encrypted_envelope.decrypt(key)?,
+            id,
+            now,
+            &[],
+        )
+    }
+
+    /// Parses a continuation that may be encrypted to any of several keys,
+    /// such as the currently valid keys of a
+    /// [`DelegationKeyring`](crate::DelegationKeyring).
+    ///
+    /// The keys are tried in order, and if more than one is given and none
+    /// decrypts the continuation, parsing fails with
+    /// [`Error::NoKeyDecrypts`]. If `keys` is empty the continuation is
+    /// expected to be unencrypted; otherwise an unencrypted continuation is
+    /// rejected with [`Error::ContinuationNotEncrypted`].
+    pub fn try_from_envelope_with_keys(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        keys: &[&dyn Decrypter],
+    ) -> Result<Self> {
+        Self::try_from_envelope_with_tolerance(
+            encrypted_envelope,
+            id,
+            now,
+            Duration::ZERO,
+            keys,
+        )
+    }
+
+    /// Parses a continuation like [`Self::try_from_envelope_with_keys`],
+    /// accepting it if it is valid at some time within `tolerance` of `now`.
+    pub fn try_from_envelope_with_tolerance(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        tolerance: Duration,
+        keys: &[&dyn Decrypter],
+    ) -> Result<Self> {
+        Self::try_from_envelope_opt(
+            encrypted_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(id)
+                .with_optional_now(now)
+                .with_clock_skew_tolerance(tolerance),
+            keys,
+        )
+    }
+
+    /// Parses a continuation like [`Self::try_from_envelope_with_keys`],
+    /// checking its validity against the expected ID, time, clock skew
+    /// tolerance, and parse policy of `options`.
+    pub fn try_from_envelope_opt(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        keys: &[&dyn Decrypter],
+    ) -> Result<Self> {
+        if !keys.is_empty() && !encrypted_envelope.subject().is_encrypted() {
+            return Err(Error::ContinuationNotEncrypted);
+        }
+        let envelope = match keys {
+            [] => encrypted_envelope.clone(),
+            [key] => encrypted_envelope.decrypt_to_recipient(*key)?,
+            keys => keys
+                .iter()
+                .find_map(|key| {
+                    encrypted_envelope.decrypt_to_recipient(*key).ok()
+                })
+                .ok_or(Error::NoKeyDecrypts {
+                    attempted: keys.len(),
+                })?,
         };
+        let envelope = decompress_state(envelope)?;
         let continuation = Self {
             state: envelope.try_unwrap()?,
             valid_id: envelope
                 .extract_optional_object_for_predicate(known_values::ID)?,
+            nonce: envelope.extract_optional_object_for_predicate(NONCE)?,
+            valid_function: envelope
+                .extract_optional_object_for_predicate(VALID_FUNCTION)?,
+            valid_sender: envelope
+                .extract_optional_object_for_predicate(VALID_SENDER)?,
+            valid_from: envelope.extract_optional_object_for_predicate(
+                known_values::VALID_FROM,
+            )?,
             valid_until: envelope.extract_optional_object_for_predicate(
                 known_values::VALID_UNTIL,
             )?,
+            issued_at: envelope
+                .extract_optional_object_for_predicate(known_values::DATE)?,
+            compress_state: false,
         };
-        if !continuation.is_valid_date(now) {
+        let now = options.validation_now();
+        let tolerance = options.clock_skew_tolerance();
+        if options.parse_policy() == ParsePolicy::Strict
+            && continuation.valid_until.is_none()
+        {
+            return Err(Error::ContinuationWithoutExpiry);
+        }
+        if continuation.is_not_yet_valid(now, tolerance) {
+            return Err(Error::ContinuationNotYetValid);
+        }
+        if continuation.is_expired(now, tolerance) {
             return Err(Error::ContinuationExpired);
         }
-        if !continuation.is_valid_id(id) {
+        if !continuation.is_valid_id(options.expected_id()) {
             return Err(Error::ContinuationIdInvalid);
         }
         Ok(continuation)
@@ -0,0 +1,69 @@
+use bc_envelope::prelude::*;
+
+use crate::{MessageKind, Result};
+
+/// Describes the message a continuation is being sealed into.
+#[derive(Clone, Copy, Debug)]
+pub struct ContinuationContext<'a> {
+    pub kind: MessageKind,
+    /// The function of the request, if the message is a request.
+    pub function: Option<&'a Function>,
+}
+
+/// A choke point that sees the state of every continuation before it is
+/// encrypted.
+///
+/// A filter may return the state unchanged, return a redacted version of it,
+/// or return an error to abort sealing, which is reported as
+/// [`Error::ContinuationRejectedByFilter`](crate::Error::ContinuationRejectedByFilter).
+pub trait ContinuationFilter: std::fmt::Debug + Send + Sync {
+    fn filter(
+        &self,
+        context: &ContinuationContext<'_>,
+        state: Envelope,
+    ) -> Result<Envelope>;
+}
+
+/// A filter that removes every assertion on the state whose predicate is one
+/// of a given set.
+#[derive(Clone, Debug)]
+pub struct AssertionStripFilter {
+    predicates: Vec<Envelope>,
+}
+
+impl AssertionStripFilter {
+    pub fn new<P>(predicates: impl IntoIterator<Item = P>) -> Self
+    where
+        P: EnvelopeEncodable,
+    {
+        Self {
+            predicates: predicates
+                .into_iter()
+                .map(|predicate| predicate.into_envelope())
+                .collect(),
+        }
+    }
+}
+
+impl ContinuationFilter for AssertionStripFilter {
+    fn filter(
+        &self,
+        _context: &ContinuationContext<'_>,
+        state: Envelope,
+    ) -> Result<Envelope> {
+        let mut result = state.clone();
+        for assertion in state.assertions() {
+            let Some(predicate) = assertion.as_predicate() else {
+                continue;
+            };
+            if self
+                .predicates
+                .iter()
+                .any(|stripped| stripped.digest() == predicate.digest())
+            {
+                result = result.remove_assertion(assertion);
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use bc_envelope::prelude::*;
+
+use crate::Error;
+
+/// Why a continuation returned to us was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RejectionReason {
+    /// The continuation had expired, or does not expire when it must.
+    Expired,
+    /// The continuation was returned before it became valid.
+    NotYetValid,
+    /// The continuation had already been returned to us once.
+    Replayed,
+    /// The continuation was bound to another function.
+    WrongFunction,
+    /// The continuation was returned by a peer it was not issued to.
+    WrongSender,
+    /// The continuation was issued for another message ID.
+    InvalidId,
+    /// The continuation was issued longer ago than the maximum age, or does
+    /// not record when it was issued.
+    TooOld,
+    /// The continuation claimed to be valid for longer than the maximum.
+    ValidityTooLong,
+    /// The state of the continuation exceeded the field limits.
+    TooLarge,
+    /// The state the continuation refers to was no longer stored.
+    StateUnavailable,
+    /// The continuation could not be decrypted or decoded.
+    Invalid,
+}
+
+impl RejectionReason {
+    /// Classifies an error returned when parsing a continuation.
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::ContinuationExpired | Error::ContinuationWithoutExpiry => {
+                RejectionReason::Expired
+            }
+            Error::ContinuationNotYetValid => RejectionReason::NotYetValid,
+            Error::ContinuationReplayed => RejectionReason::Replayed,
+            Error::ContinuationFunctionMismatch { .. }
+            | Error::ContinuationFunctionMissing(_) => {
+                RejectionReason::WrongFunction
+            }
+            Error::ContinuationSenderMismatch { .. } => {
+                RejectionReason::WrongSender
+            }
+            Error::ContinuationIdInvalid => RejectionReason::InvalidId,
+            Error::ContinuationTooOld { .. }
+            | Error::ContinuationAgeUnknown => RejectionReason::TooOld,
+            Error::ContinuationValidityTooLong { .. } => {
+                RejectionReason::ValidityTooLong
+            }
+            Error::ComponentTooLarge { .. } => RejectionReason::TooLarge,
+            Error::ContinuationStateUnavailable(_) => {
+                RejectionReason::StateUnavailable
+            }
+            _ => RejectionReason::Invalid,
+        }
+    }
+}
+
+/// Receives a count of every continuation issued, returned, and rejected,
+/// for monitoring.
+///
+/// The function is that of the request the continuation was issued with or
+/// returned in, and is unknown for responses and events.
+pub trait ContinuationMetrics: std::fmt::Debug + Send + Sync {
+    /// A continuation was issued, valid for `lifetime` if it expires.
+    fn record_issued(
+        &self,
+        function: Option<&Function>,
+        lifetime: Option<Duration>,
+    );
+
+    /// A continuation was returned to us and accepted, issued `age` ago if
+    /// it records when.
+    fn record_returned(
+        &self,
+        function: Option<&Function>,
+        age: Option<Duration>,
+    );
+
+    /// A continuation returned to us was rejected.
+    fn record_rejected(
+        &self,
+        function: Option<&Function>,
+        reason: RejectionReason,
+    );
+}
+
+/// The continuation counters for a single function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The function, or `None` for continuations of responses and events.
+    pub function: Option<Function>,
+    pub issued: u64,
+    /// The number of continuations issued without an expiry.
+    pub issued_without_expiry: u64,
+    /// The summed lifetimes of the continuations issued with an expiry.
+    pub total_lifetime: Duration,
+    pub returned: u64,
+    /// The summed ages of the continuations returned that record when they
+    /// were issued.
+    pub total_age: Duration,
+    pub rejected: HashMap<RejectionReason, u64>,
+}
+
+impl FunctionStats {
+    pub fn total_rejected(&self) -> u64 { self.rejected.values().sum() }
+}
+
+/// A [`ContinuationMetrics`] that aggregates counters in memory, per
+/// function.
+#[derive(Debug, Default)]
+pub struct MemoryContinuationMetrics {
+    stats: Mutex<HashMap<Option<Function>, FunctionStats>>,
+}
+
+impl MemoryContinuationMetrics {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns the counters of every function seen so far, ordered by
+    /// function name, with the counters of responses and events first.
+    pub fn snapshot(&self) -> Vec<FunctionStats> {
+        let mut stats: Vec<FunctionStats> =
+            self.stats.lock().unwrap().values().cloned().collect();
+        stats.sort_by_key(|stats| {
+            stats.function.as_ref().map(|function| function.name())
+        });
+        stats
+    }
+
+    fn update(
+        &self,
+        function: Option<&Function>,
+        update: impl FnOnce(&mut FunctionStats),
+    ) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry =
+            stats
+                .entry(function.cloned())
+                .or_insert_with(|| FunctionStats {
+                    function: function.cloned(),
+                    ..FunctionStats::default()
+                });
+        update(entry);
+    }
+}
+
+impl ContinuationMetrics for MemoryContinuationMetrics {
+    fn record_issued(
+        &self,
+        function: Option<&Function>,
+        lifetime: Option<Duration>,
+    ) {
+        self.update(function, |stats| {
+            stats.issued += 1;
+            match lifetime {
+                Some(lifetime) => stats.total_lifetime += lifetime,
+                None => stats.issued_without_expiry += 1,
+            }
+        });
+    }
+
+    fn record_returned(
+        &self,
+        function: Option<&Function>,
+        age: Option<Duration>,
+    ) {
+        self.update(function, |stats| {
+            stats.returned += 1;
+            if let Some(age) = age {
+                stats.total_age += age;
+            }
+        });
+    }
+
+    fn record_rejected(
+        &self,
+        function: Option<&Function>,
+        reason: RejectionReason,
+    ) {
+        self.update(function, |stats| {
+            *stats.rejected.entry(reason).or_default() += 1;
+        });
+    }
+}
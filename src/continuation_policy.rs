@@ -0,0 +1,17 @@
+use std::time::Duration;
+
+/// The defaults governing continuations, shared by [`SealOptions`] and
+/// [`ParseOptions`].
+///
+/// [`SealOptions`]: crate::SealOptions
+/// [`ParseOptions`]: crate::ParseOptions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContinuationPolicy {
+    /// Whether continuations we issue carry an expiry hint in the clear.
+    pub expiry_hints: bool,
+    /// The longest a continuation issued by the peer is retained, if capped.
+    pub max_peer_lifetime: Option<Duration>,
+    /// The longest lifetime granted for state we hold for the peer, if
+    /// capped. See [`Self::grant_state_lifetime`].
+    pub max_state_lifetime: Option<Duration>,
+}
@@ -0,0 +1,108 @@
+//! Nesting the states of workflows within a single continuation.
+//!
+//! A [`ContinuationStack`] is encoded as the state of an ordinary
+//! continuation, as an ordered list of layers, so that a sub-operation can
+//! carry its own state without losing that of the operation it belongs to.
+//! The layers share the validity of the continuation that carries them.
+
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+const STATE_STACK: &str = "stateStack";
+
+/// The states of nested workflows, innermost last.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContinuationStack {
+    layers: Vec<Envelope>,
+}
+
+impl ContinuationStack {
+    pub fn new() -> Self { Self::default() }
+
+    /// Reads the stack that `state` encodes. State that is not a stack is a
+    /// stack of one layer, so that a workflow can be nested within one that
+    /// was not.
+    pub fn from_state(state: &Envelope) -> Result<Self> {
+        if state.subject().as_known_value() != Some(&known_values::UNIT) {
+            return Ok(Self {
+                layers: vec![state.clone()],
+            });
+        }
+        let Some(list) = state.optional_object_for_predicate(STATE_STACK)?
+        else {
+            return Ok(Self {
+                layers: vec![state.clone()],
+            });
+        };
+        let layers = list
+            .try_leaf()?
+            .try_into_array()?
+            .into_iter()
+            .map(Envelope::from_tagged_cbor)
+            .collect::<dcbor::Result<_>>()
+            .map_err(Error::Cbor)?;
+        Ok(Self { layers })
+    }
+
+    /// Encodes the stack as the state of a continuation.
+    pub fn to_state(&self) -> Envelope {
+        let list: Vec<CBOR> = self
+            .layers
+            .iter()
+            .map(|layer| layer.tagged_cbor())
+            .collect();
+        Envelope::unit().add_assertion(STATE_STACK, CBOR::from(list))
+    }
+
+    /// Pushes the state of a nested workflow.
+    pub fn with_pushed(mut self, state: impl EnvelopeEncodable) -> Self {
+        self.push(state);
+        self
+    }
+
+    pub fn push(&mut self, state: impl EnvelopeEncodable) {
+        self.layers.push(state.into_envelope());
+    }
+
+    /// Removes and returns the state of the innermost workflow.
+    pub fn pop(&mut self) -> Option<Envelope> { self.layers.pop() }
+
+    /// The state of the innermost workflow.
+    pub fn top(&self) -> Option<&Envelope> { self.layers.last() }
+
+    /// The layers of the stack, outermost first.
+    pub fn layers(&self) -> &[Envelope] { &self.layers }
+
+    pub fn len(&self) -> usize { self.layers.len() }
+
+    pub fn is_empty(&self) -> bool { self.layers.is_empty() }
+}
+
+/// Pushes `state` onto the stack that `current` encodes, if any.
+pub(crate) fn push_state(
+    current: Option<&Envelope>,
+    state: impl EnvelopeEncodable,
+) -> Result<Envelope> {
+    let stack = match current {
+        Some(current) => ContinuationStack::from_state(current)?,
+        None => ContinuationStack::new(),
+    };
+    Ok(stack.with_pushed(state).to_state())
+}
+
+/// Pops the innermost state off the stack that `current` encodes, returning
+/// it with the state that remains, which is `None` once the stack is empty.
+pub(crate) fn pop_state(
+    current: Option<&Envelope>,
+) -> Result<Option<(Envelope, Option<Envelope>)>> {
+    let Some(current) = current else {
+        return Ok(None);
+    };
+    let mut stack = ContinuationStack::from_state(current)?;
+    let Some(top) = stack.pop() else {
+        return Ok(None);
+    };
+    let rest = (!stack.is_empty()).then(|| stack.to_state());
+    Ok(Some((top, rest)))
+}
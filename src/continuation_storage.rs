@@ -0,0 +1,162 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use bc_components::{ARID, Digest, DigestProvider, SymmetricKey};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// The predicate under which a request refers to a continuation held by its
+/// recipient, in place of [`known_values::RECIPIENT_CONTINUATION`].
+pub(crate) const RECIPIENT_CONTINUATION_REF: &str = "recipientContinuationRef";
+
+const CONTINUATION_DIGEST: &str = "continuationDigest";
+
+/// Wraps a continuation issued by a peer for storage outside our control,
+/// with the time of export and a MAC under the application's `key`, so that
+/// [`import_continuation`] can detect corruption before the continuation is
+/// sent back.
+pub fn export_continuation(
+    continuation: &Envelope,
+    key: &SymmetricKey,
+    now: Date,
+) -> Vec<u8> {
+    let body = CBOR::from(vec![now.to_cbor(), continuation.to_cbor()]);
+    let mac = mac(key, &body);
+    CBOR::from(vec![body, CBOR::to_byte_string(mac)]).to_cbor_data()
+}
+
+/// Checks and unwraps a continuation stored by [`export_continuation`].
+///
+/// Fails with [`Error::StoredContinuationTampered`] if the data is not intact
+/// or was exported under another key, and with
+/// [`Error::StoredContinuationStale`] if it was exported more than `max_age`
+/// before `now`.
+pub fn import_continuation(
+    data: &[u8],
+    key: &SymmetricKey,
+    now: Date,
+    max_age: Duration,
+) -> Result<Envelope> {
+    let tampered = |_| Error::StoredContinuationTampered;
+    let [body, stored_mac]: [CBOR; 2] = CBOR::try_from_data(data)
+        .and_then(|cbor| cbor.try_into_array())
+        .map_err(tampered)?
+        .try_into()
+        .map_err(|_| Error::StoredContinuationTampered)?;
+    let stored_mac = stored_mac.try_into_byte_string().map_err(tampered)?;
+    if !constant_time_eq(&stored_mac, &mac(key, &body)) {
+        return Err(Error::StoredContinuationTampered);
+    }
+    let [exported_at, continuation]: [CBOR; 2] = body
+        .try_into_array()?
+        .try_into()
+        .map_err(|_| Error::StoredContinuationTampered)?;
+    let exported_at = Date::try_from(exported_at)?;
+    let age = Duration::from_secs_f64(
+        (now.timestamp() - exported_at.timestamp()).max(0.0),
+    );
+    if age > max_age {
+        return Err(Error::StoredContinuationStale { age, max_age });
+    }
+    Ok(Envelope::try_from(continuation)?)
+}
+
+fn mac(key: &SymmetricKey, body: &CBOR) -> Vec<u8> {
+    bc_crypto::hmac_sha256(key.data(), body.to_cbor_data()).to_vec()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Continuations a server has issued and kept, so that clients can return
+/// them by reference rather than in full.
+///
+/// How the server tells the client the handle of a continuation is up to
+/// the application.
+pub trait ContinuationStorage: std::fmt::Debug + Send + Sync {
+    fn load(&self, handle: ARID) -> Result<Option<Envelope>>;
+}
+
+/// A [`ContinuationStorage`] held in memory.
+#[derive(Debug, Default)]
+pub struct MemoryContinuationStorage {
+    continuations: RwLock<HashMap<ARID, Envelope>>,
+}
+
+impl MemoryContinuationStorage {
+    pub fn new() -> Self { Self::default() }
+
+    /// Keeps `continuation`, as issued in a sealed message, under `handle`.
+    pub fn insert(&self, handle: ARID, continuation: Envelope) {
+        self.continuations
+            .write()
+            .unwrap()
+            .insert(handle, continuation);
+    }
+}
+
+impl ContinuationStorage for MemoryContinuationStorage {
+    fn load(&self, handle: ARID) -> Result<Option<Envelope>> {
+        Ok(self.continuations.read().unwrap().get(&handle).cloned())
+    }
+}
+
+/// A reference to a continuation the peer keeps in its
+/// [`ContinuationStorage`], sent back in place of the continuation itself to
+/// save space.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerContinuationRef {
+    handle: ARID,
+    digest: Digest,
+}
+
+impl PeerContinuationRef {
+    pub fn new(handle: ARID, digest: Digest) -> Self { Self { handle, digest } }
+
+    /// Refers to `continuation`, as received from the peer, under the
+    /// handle the peer stored it with.
+    pub fn for_continuation(handle: ARID, continuation: &Envelope) -> Self {
+        Self::new(handle, continuation.digest())
+    }
+
+    pub fn handle(&self) -> ARID { self.handle }
+
+    pub fn digest(&self) -> Digest { self.digest }
+
+    /// Loads the continuation referred to from `storage`, failing with
+    /// [`Error::UnknownContinuationHandle`] if there is none, and with
+    /// [`Error::ContinuationDigestMismatch`] if it is not the one the peer
+    /// meant.
+    pub(crate) fn resolve(
+        &self,
+        storage: &dyn ContinuationStorage,
+    ) -> Result<Envelope> {
+        let continuation = storage
+            .load(self.handle)?
+            .ok_or(Error::UnknownContinuationHandle)?;
+        if continuation.digest() != self.digest {
+            return Err(Error::ContinuationDigestMismatch);
+        }
+        Ok(continuation)
+    }
+}
+
+impl From<PeerContinuationRef> for Envelope {
+    fn from(reference: PeerContinuationRef) -> Self {
+        Envelope::new(reference.handle)
+            .add_assertion(CONTINUATION_DIGEST, reference.digest)
+    }
+}
+
+impl TryFrom<Envelope> for PeerContinuationRef {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        Ok(Self::new(
+            envelope.extract_subject()?,
+            envelope.extract_object_for_predicate(CONTINUATION_DIGEST)?,
+        ))
+    }
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use bc_components::{
+    Decrypter, EncapsulationPrivateKey, EncapsulationPublicKey,
+    EncapsulationScheme, PrivateKeys, Reference, ReferenceProvider, Signer,
+    Verifier, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{Error, Result};
+
+const KEY: &str = "key";
+const PUBLIC_KEY: &str = "publicKey";
+const CREATED: &str = "created";
+const EXPIRES: &str = "expires";
+const ACTIVE: &str = "active";
+
+/// A short-lived keypair that continuation state is encrypted to in place of
+/// the server's identity key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelegationKey {
+    private_key: EncapsulationPrivateKey,
+    public_key: EncapsulationPublicKey,
+    created: Date,
+    expires: Date,
+}
+
+impl DelegationKey {
+    /// The fingerprint of the key.
+    pub fn id(&self) -> Reference { self.public_key.reference() }
+
+    pub fn private_key(&self) -> &EncapsulationPrivateKey { &self.private_key }
+
+    pub fn public_key(&self) -> &EncapsulationPublicKey { &self.public_key }
+
+    pub fn created(&self) -> Date { self.created }
+
+    /// The time after which continuations encrypted to this key can no longer
+    /// be decrypted by holders of the keyring.
+    pub fn expires(&self) -> Date { self.expires }
+
+    fn is_expired(&self, now: Date) -> bool { self.expires <= now }
+
+    fn to_envelope(&self, active: bool) -> Envelope {
+        Envelope::new(self.private_key.to_cbor())
+            .add_assertion(PUBLIC_KEY, self.public_key.to_cbor())
+            .add_assertion(CREATED, self.created)
+            .add_assertion(EXPIRES, self.expires)
+            .add_assertion(ACTIVE, active)
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<(Self, bool)> {
+        let private_key =
+            EncapsulationPrivateKey::try_from(envelope.subject().try_leaf()?)?;
+        // ML-KEM public keys cannot be derived from their private keys.
+        let public_key =
+            match envelope.optional_object_for_predicate(PUBLIC_KEY)? {
+                Some(public_key) => {
+                    EncapsulationPublicKey::try_from(public_key.try_leaf()?)?
+                }
+                None => private_key.public_key()?,
+            };
+        let key = Self {
+            public_key,
+            private_key,
+            created: envelope.extract_object_for_predicate(CREATED)?,
+            expires: envelope.extract_object_for_predicate(EXPIRES)?,
+        };
+        Ok((key, envelope.extract_object_for_predicate(ACTIVE)?))
+    }
+}
+
+/// Manages the rotation of [`DelegationKey`]s.
+///
+/// A server issues continuations encrypted to the active key, and decrypts
+/// returned continuations with any key that has not yet expired, so a key
+/// should live at least as long as the continuations issued with it.
+///
+/// Keys are generated with the keyring's encapsulation scheme, which
+/// [`Self::for_identity`] takes from the server's own keys, so that a server
+/// with a post-quantum identity does not delegate to classical keys.
+///
+/// The keyring can be distributed to workers, letting them decrypt
+/// continuations without holding the identity key, signed by the server and
+/// encrypted to each worker with [`Self::to_encrypted_envelope`]. Nothing about
+/// delegation keys is ever published to peers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DelegationKeyring {
+    keys: Vec<DelegationKey>,
+    active: Option<Reference>,
+    scheme: EncapsulationScheme,
+}
+
+impl DelegationKeyring {
+    pub fn new() -> Self { Self::default() }
+
+    /// A keyring generating keys with the same encapsulation scheme as the
+    /// server's identity keys.
+    pub fn for_identity(private_keys: &PrivateKeys) -> Self {
+        Self::new().with_scheme(
+            private_keys
+                .enapsulation_private_key()
+                .encapsulation_scheme(),
+        )
+    }
+
+    /// Sets the encapsulation scheme of the keys generated from now on.
+    pub fn with_scheme(mut self, scheme: EncapsulationScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    pub fn scheme(&self) -> EncapsulationScheme { self.scheme }
+
+    /// Generates a new key valid for `lifetime` from `now`, returning its
+    /// fingerprint. The key is not used for issuing until it is activated.
+    pub fn generate(&mut self, now: Date, lifetime: Duration) -> Reference {
+        let (private_key, public_key) = self.scheme.keypair();
+        let key = DelegationKey {
+            private_key,
+            public_key,
+            created: now,
+            expires: now + lifetime,
+        };
+        let id = key.id();
+        self.keys.push(key);
+        id
+    }
+
+    /// Makes the key with the given fingerprint the one new continuations are
+    /// encrypted to.
+    pub fn activate(&mut self, id: Reference) -> Result<()> {
+        if !self.keys.iter().any(|key| key.id() == id) {
+            return Err(Error::UnknownDelegationKey(id));
+        }
+        self.active = Some(id);
+        Ok(())
+    }
+
+    /// Generates a new key and activates it.
+    pub fn rotate(&mut self, now: Date, lifetime: Duration) -> Reference {
+        let id = self.generate(now, lifetime);
+        self.active = Some(id);
+        id
+    }
+
+    /// Removes all keys that have expired by `now`, returning their
+    /// fingerprints.
+    pub fn expire(&mut self, now: Date) -> Vec<Reference> {
+        let expired: Vec<Reference> = self
+            .keys
+            .iter()
+            .filter(|key| key.is_expired(now))
+            .map(DelegationKey::id)
+            .collect();
+        self.keys.retain(|key| !key.is_expired(now));
+        if self.active.is_some_and(|active| expired.contains(&active)) {
+            self.active = None;
+        }
+        expired
+    }
+
+    /// The key new continuations are encrypted to, if one is active.
+    pub fn current(&self) -> Option<&DelegationKey> {
+        let active = self.active?;
+        self.keys.iter().find(|key| key.id() == active)
+    }
+
+    /// The keys that have not expired by `now`, most recent first.
+    pub fn valid_keys(&self, now: Date) -> Vec<&DelegationKey> {
+        self.keys
+            .iter()
+            .rev()
+            .filter(|key| !key.is_expired(now))
+            .collect()
+    }
+
+    /// The private keys that have not expired by `now`, most recent first,
+    /// for use with
+    /// [`ParseOptions::with_continuation_keys`](crate::ParseOptions::with_continuation_keys).
+    pub fn decryption_keys(&self, now: Date) -> Vec<EncapsulationPrivateKey> {
+        self.valid_keys(now)
+            .into_iter()
+            .map(|key| key.private_key.clone())
+            .collect()
+    }
+
+    /// Signs the keyring with the server's key and encrypts it to `worker`,
+    /// for distribution over a channel that is not itself secure.
+    ///
+    /// The signature is inside the encryption, so only the worker learns who
+    /// sent the keyring. Fails with [`Error::RecipientMissingEncryptionKey`]
+    /// if `worker` has no encryption key.
+    pub fn to_encrypted_envelope(
+        &self,
+        server: &dyn Signer,
+        worker: &XIDDocument,
+    ) -> Result<Envelope> {
+        let key = worker.encryption_key().ok_or_else(|| {
+            Error::RecipientMissingEncryptionKey(worker.xid())
+        })?;
+        Ok(Envelope::from(self.clone())
+            .sign(server)
+            .wrap()
+            .encrypt_subject_to_recipient(key)?)
+    }
+
+    /// Decrypts a keyring sealed with [`Self::to_encrypted_envelope`],
+    /// failing unless it was signed by `server`.
+    ///
+    /// Anyone can encrypt to a worker, so without the signature a worker
+    /// could be handed keys chosen by someone else and made to accept
+    /// continuations they encrypted.
+    pub fn try_from_encrypted_envelope(
+        envelope: &Envelope,
+        worker: &dyn Decrypter,
+        server: &dyn Verifier,
+    ) -> Result<Self> {
+        envelope
+            .decrypt_subject_to_recipient(worker)?
+            .try_unwrap()?
+            .verify(server)?
+            .try_into()
+    }
+}
+
+/// The keyring with its private keys in plaintext, which must only be sent
+/// over a secure channel. Use [`DelegationKeyring::to_encrypted_envelope`]
+/// otherwise.
+impl From<DelegationKeyring> for Envelope {
+    fn from(keyring: DelegationKeyring) -> Self {
+        keyring.keys.iter().fold(
+            Envelope::new(known_values::UNIT),
+            |envelope, key| {
+                envelope.add_assertion(
+                    KEY,
+                    key.to_envelope(Some(key.id()) == keyring.active),
+                )
+            },
+        )
+    }
+}
+
+impl TryFrom<Envelope> for DelegationKeyring {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        let mut keyring = Self::new();
+        for object in envelope.objects_for_predicate(KEY) {
+            let (key, active) = DelegationKey::try_from_envelope(&object)?;
+            if active {
+                keyring.active = Some(key.id());
+            }
+            keyring.keys.push(key);
+        }
+        keyring.keys.sort_by_key(|key| key.created);
+        if let Some(latest) = keyring.keys.last() {
+            keyring.scheme = latest.private_key.encapsulation_scheme();
+        }
+        Ok(keyring)
+    }
+}
@@ -0,0 +1,101 @@
+use bc_components::{Digest, DigestProvider, XID};
+use bc_envelope::{Signer, Verifier, prelude::*};
+
+use crate::{Error, Result};
+
+const RECIPIENT: &str = "recipient";
+const TRANSPORT: &str = "transport";
+const ACKNOWLEDGMENT: &str = "acknowledgment";
+
+/// A sender's account of an attempt to transmit a sealed message, for
+/// proving later that the message was sent.
+///
+/// The message itself is identified by its digest, so the record reveals
+/// nothing of its content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeliveryAttempt {
+    /// The digest of the sealed message as transmitted.
+    pub message_digest: Digest,
+    /// The XID of the recipient.
+    pub recipient: XID,
+    /// Identifies the transport used, such as a URL or queue name.
+    pub transport: String,
+    /// The time of the attempt.
+    pub timestamp: Date,
+    /// What the transport returned to acknowledge the message, if anything.
+    pub acknowledgment: Option<ByteString>,
+}
+
+impl DeliveryAttempt {
+    pub fn new(
+        message: &Envelope,
+        recipient: XID,
+        transport: impl Into<String>,
+        timestamp: Date,
+    ) -> Self {
+        Self {
+            message_digest: message.digest(),
+            recipient,
+            transport: transport.into(),
+            timestamp,
+            acknowledgment: None,
+        }
+    }
+
+    pub fn with_acknowledgment(
+        mut self,
+        acknowledgment: impl Into<ByteString>,
+    ) -> Self {
+        self.acknowledgment = Some(acknowledgment.into());
+        self
+    }
+
+    /// Checks that this attempt was to transmit `message`, failing with
+    /// [`Error::DeliveryRecordMismatch`] otherwise.
+    pub fn check_message(&self, message: &Envelope) -> Result<()> {
+        if message.digest() != self.message_digest {
+            return Err(Error::DeliveryRecordMismatch);
+        }
+        Ok(())
+    }
+
+    fn to_envelope(&self) -> Envelope {
+        Envelope::new(self.message_digest)
+            .add_assertion(RECIPIENT, self.recipient)
+            .add_assertion(TRANSPORT, self.transport.clone())
+            .add_assertion(known_values::DATE, self.timestamp)
+            .add_optional_assertion(ACKNOWLEDGMENT, self.acknowledgment.clone())
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<Self> {
+        Ok(Self {
+            message_digest: envelope.extract_subject()?,
+            recipient: envelope.extract_object_for_predicate(RECIPIENT)?,
+            transport: envelope.extract_object_for_predicate(TRANSPORT)?,
+            timestamp: envelope
+                .extract_object_for_predicate(known_values::DATE)?,
+            acknowledgment: envelope
+                .extract_optional_object_for_predicate(ACKNOWLEDGMENT)?,
+        })
+    }
+}
+
+/// Records `attempt` in an envelope signed by the sender.
+pub fn record_delivery_attempt(
+    attempt: &DeliveryAttempt,
+    sender: &dyn Signer,
+) -> Envelope {
+    attempt.to_envelope().sign(sender)
+}
+
+/// Verifies a record made by [`record_delivery_attempt`] against the
+/// sender's verification key, returning the attempt it records.
+///
+/// To prove that a particular message was sent, also check it with
+/// [`DeliveryAttempt::check_message`].
+pub fn verify_delivery_attempt(
+    record: &Envelope,
+    sender: &dyn Verifier,
+) -> Result<DeliveryAttempt> {
+    DeliveryAttempt::try_from_envelope(&record.verify(sender)?)
+}
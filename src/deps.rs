@@ -0,0 +1,21 @@
+//! The types of other crates that appear in the public API of this crate.
+//!
+//! Importing them from here rather than from their own crates keeps
+//! downstream code on the versions this crate was built against, so a newer
+//! release of one of those crates elsewhere in the dependency graph cannot
+//! cause a type mismatch.
+//!
+//! The crates themselves are re-exported too, for anything not listed.
+
+pub use bc_components::{
+    self, ARID, Decrypter, Digest, EncapsulationPrivateKey,
+    EncapsulationPublicKey, EncapsulationScheme, Encrypter,
+    KeyDerivationMethod, PrivateKeys, Reference, Signer, SymmetricKey,
+    Verifier, XID,
+};
+pub use bc_envelope::{
+    self, Envelope, EnvelopeEncodable, Expression, Function, Parameter,
+    prelude::KnownValue,
+};
+pub use bc_xid::{self, XIDDocument};
+pub use dcbor::{self, CBOR, Date};
@@ -0,0 +1,70 @@
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// The GSTP assertions a message may carry at most once.
+const SINGULAR_PREDICATES: [KnownValue; 5] = [
+    known_values::SENDER,
+    known_values::SENDER_CONTINUATION,
+    known_values::RECIPIENT_CONTINUATION,
+    known_values::NOTE,
+    known_values::DATE,
+];
+
+/// How a message that repeats an assertion it may carry only once is
+/// handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateAssertionPolicy {
+    /// Reject the message with [`Error::AmbiguousAssertion`].
+    #[default]
+    Reject,
+    /// Keep the first of the duplicated assertions in digest order, and
+    /// record a [`ParseWarning`] on the parsed message.
+    TakeFirst,
+}
+
+/// Something questionable about a message that was accepted anyway.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// The message repeated an assertion it may carry only once, and all but
+    /// the first were discarded.
+    DuplicateAssertion { predicate: KnownValue },
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::DuplicateAssertion { predicate } => {
+                write!(f, "discarded duplicate '{}' assertions", predicate)
+            }
+        }
+    }
+}
+
+/// Ensures `message` carries each singular GSTP assertion at most once,
+/// according to `policy`.
+pub(crate) fn check_singular_assertions(
+    message: Envelope,
+    policy: DuplicateAssertionPolicy,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Envelope> {
+    let mut message = message;
+    for predicate in SINGULAR_PREDICATES {
+        let assertions = message.assertions_with_predicate(predicate.clone());
+        if assertions.len() < 2 {
+            continue;
+        }
+        match policy {
+            DuplicateAssertionPolicy::Reject => {
+                return Err(Error::AmbiguousAssertion { predicate });
+            }
+            DuplicateAssertionPolicy::TakeFirst => {
+                for assertion in assertions.into_iter().skip(1) {
+                    message = message.remove_assertion(assertion);
+                }
+                warnings.push(ParseWarning::DuplicateAssertion { predicate });
+            }
+        }
+    }
+    Ok(message)
+}
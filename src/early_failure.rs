@@ -0,0 +1,19 @@
+use bc_components::XID;
+use bc_envelope::prelude::*;
+
+/// An early failure response, parsed with
+/// [`SealedResponse::try_parse_early_failure`](crate::SealedResponse::try_parse_early_failure).
+///
+/// Nothing about the sender of an early failure is trusted: the response
+/// may come from a peer we have never seen, and reports only why a request
+/// could not be processed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EarlyFailure {
+    /// The reason given for the failure.
+    pub error: Envelope,
+    /// The XID of the sender, as claimed by the response.
+    pub claimed_sender: Option<XID>,
+    /// Whether the response was signed by the inception key of the claimed
+    /// sender, from which its XID is derived, authenticating the claim.
+    pub signature_verified: bool,
+}
@@ -1,5 +1,11 @@
+use std::time::Duration;
+
+use bc_components::{ARID, EncapsulationScheme, Reference, XID};
+use bc_envelope::prelude::{Envelope, Function, KnownValue};
 use thiserror::Error;
 
+use crate::{MessageKind, codec::Encoding, inspect::DecryptionDiagnostics};
+
 /// Errors that can occur in GSTP operations.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -7,37 +13,562 @@ pub enum Error {
     #[error("sender must have an encryption key")]
     SenderMissingEncryptionKey,
 
-    /// Missing required encryption key for recipient.
-    #[error("recipient must have an encryption key")]
-    RecipientMissingEncryptionKey,
+    /// The recipient with the given XID has no encryption key.
+    #[error("recipient {0} must have an encryption key")]
+    RecipientMissingEncryptionKey(XID),
+
+    /// An anonymous event was received without having been accepted by the
+    /// parse options.
+    #[error("anonymous events are not accepted")]
+    AnonymousEventsNotAccepted,
+
+    /// An anonymous event was sealed to no recipients.
+    #[error("anonymous events must be encrypted to at least one recipient")]
+    AnonymousEventWithoutRecipients,
+
+    /// A parameter expected to be sealed to a third party is in the clear.
+    #[error("parameter {0} is not sealed")]
+    ParameterNotSealed(String),
 
     /// Missing required verification key for sender.
     #[error("sender must have a verification key")]
     SenderMissingVerificationKey,
 
+    /// The sender's document has no key from which the XID it claims is
+    /// derived, so its signature cannot authenticate that XID.
+    #[error("sender document has no key bound to its XID {0}")]
+    SenderKeyNotBound(XID),
+
     /// Continuation has expired.
     #[error("continuation expired")]
     ContinuationExpired,
 
+    /// Compressed content declares a size larger than it may expand to.
+    #[error("compressed content of {size} bytes exceeds the limit of {limit}")]
+    DecompressedTooLarge { size: usize, limit: usize },
+
+    /// The state of a continuation is larger than allowed.
+    #[error("continuation state of {size} bytes exceeds the limit of {limit}")]
+    ContinuationStateTooLarge { size: usize, limit: usize },
+
+    /// The state a continuation refers to is not in the state store, so the
+    /// workflow must be restarted.
+    #[error("continuation state {0} is unavailable")]
+    ContinuationStateUnavailable(ARID),
+
+    /// A continuation that does not expire was returned under
+    /// [`ParsePolicy::Strict`](crate::ParsePolicy::Strict).
+    #[error("continuation does not expire")]
+    ContinuationWithoutExpiry,
+
+    /// The state of a continuation is not of the type it was expected to
+    /// be decoded as.
+    #[error("continuation state could not be decoded as {0}")]
+    ContinuationStateDecoding(&'static str),
+
+    /// Continuation was returned before the time it becomes valid.
+    #[error("continuation not yet valid")]
+    ContinuationNotYetValid,
+
+    /// A single-use continuation was returned to us a second time.
+    #[error("continuation replayed")]
+    ContinuationReplayed,
+
+    /// A continuation bound to one function was returned with a request to
+    /// another.
+    #[error("continuation is bound to {expected}, not {found}")]
+    ContinuationFunctionMismatch { expected: Function, found: Function },
+
+    /// A continuation bound to a function was returned with a message that
+    /// names no function, and the parse options expect none.
+    #[error("continuation is bound to {0}, but no function was given")]
+    ContinuationFunctionMissing(Function),
+
+    /// A request calls a function other than the one the parse options
+    /// expect.
+    #[error("expected a request to {expected}, found {found}")]
+    UnexpectedFunction { expected: Function, found: Function },
+
+    /// None of several keys tried could decrypt a message or continuation.
+    #[error("none of the {attempted} keys tried could decrypt the envelope")]
+    NoKeyDecrypts { attempted: usize },
+
+    /// A continuation issued to one peer was returned in a message signed by
+    /// another.
+    #[error("continuation was issued to {expected}, not {found}")]
+    ContinuationSenderMismatch { expected: XID, found: XID },
+
+    /// Continuations can only be bound to the sender when sealing to exactly
+    /// one recipient.
+    #[error(
+        "cannot bind a continuation to the sender with {recipients} recipients"
+    )]
+    AmbiguousSenderBinding { recipients: usize },
+
+    /// A continuation was issued longer ago than the configured maximum age.
+    #[error(
+        "continuation issued {age:?} ago exceeds the maximum age of {max_age:?}"
+    )]
+    ContinuationTooOld { age: Duration, max_age: Duration },
+
+    /// A continuation would be, or claims to be, valid for longer than the
+    /// configured maximum, or does not expire.
+    #[error("continuation validity exceeds the maximum of {max:?}")]
+    ContinuationValidityTooLong { max: Duration },
+
+    /// A maximum continuation age is configured but the continuation does
+    /// not record when it was issued.
+    #[error("continuation does not record when it was issued")]
+    ContinuationAgeUnknown,
+
+    /// A continuation read back from storage is corrupt or was exported under
+    /// another key.
+    #[error("stored continuation failed its integrity check")]
+    StoredContinuationTampered,
+
+    /// A continuation read back from storage was exported too long ago.
+    #[error(
+        "stored continuation exported {age:?} ago exceeds the maximum age of {max_age:?}"
+    )]
+    StoredContinuationStale { age: Duration, max_age: Duration },
+
+    /// A continuation referred to by handle is not in storage.
+    #[error("no stored continuation has the referenced handle")]
+    UnknownContinuationHandle,
+
+    /// A continuation referred to by handle is not the one referred to.
+    #[error("stored continuation does not match the referenced digest")]
+    ContinuationDigestMismatch,
+
+    /// A continuation was referred to by handle, but no storage to resolve
+    /// it was configured.
+    #[error("continuation references are not accepted")]
+    ContinuationRefUnsupported,
+
+    /// A message returns both a continuation and a reference to one, so it
+    /// is unclear which to honour.
+    #[error("message carries both a continuation and a continuation reference")]
+    ConflictingRecipientContinuations,
+
+    /// A message would be sealed unsigned or unencrypted under
+    /// [`Strictness::Production`](crate::Strictness::Production).
+    #[error("unprotected sealing is forbidden under production strictness")]
+    UnprotectedSealForbidden,
+
+    /// An early failure gives no reason under
+    /// [`Strictness::Production`](crate::Strictness::Production).
+    #[error("early failure gives no reason")]
+    EarlyFailureWithoutReason,
+
+    /// An error envelope is not a recovery hint.
+    #[error("not a continuation recovery hint")]
+    InvalidRecoveryHint,
+
+    /// Recovery from an expired continuation was not allowed, or its grace
+    /// token has expired.
+    #[error("recovery from the expired continuation is not possible")]
+    RecoveryNotPossible,
+
     /// Continuation ID is invalid.
     #[error("continuation ID invalid")]
     ContinuationIdInvalid,
 
+    /// A one-way request was received by an interactive endpoint.
+    #[error("one-way requests are not accepted")]
+    OneWayRequestNotAccepted,
+
+    /// A request sealed one-way carries state, which could never be
+    /// returned.
+    #[error("one-way requests cannot carry state")]
+    OneWayRequestWithState,
+
+    /// None of the recipient slots of a message opens with our key, reported
+    /// only when parsing with decryption diagnostics.
+    #[error("not a recipient of the message: {0}")]
+    NotARecipient(Box<DecryptionDiagnostics>),
+
+    /// A message expected to be signed but not encrypted is encrypted.
+    #[error("envelope is encrypted and must be parsed with a recipient key")]
+    UnexpectedEncryption,
+
     /// Peer continuation must be encrypted.
     #[error("peer continuation must be encrypted")]
     PeerContinuationNotEncrypted,
 
+    /// A continuation was parsed with a recipient key but is not encrypted.
+    #[error("continuation must be encrypted")]
+    ContinuationNotEncrypted,
+
+    /// A continuation's state key was derived in an epoch whose salt has
+    /// been discarded, or was never held.
+    #[error("unknown state epoch {0}")]
+    UnknownStateEpoch(u32),
+
+    /// A continuation's state is encrypted with a derived key, but no state
+    /// epochs were configured to derive it.
+    #[error(
+        "continuation state is epoch-encrypted but no state epochs are configured"
+    )]
+    StateEpochsRequired,
+
+    /// Exported state epochs hold no epoch, or hold one twice.
+    #[error("invalid state epochs")]
+    InvalidStateEpochs,
+
     /// Requests must contain a peer continuation.
     #[error("requests must contain a peer continuation")]
     MissingPeerContinuation,
 
+    /// A continuation filter refused the state of an outgoing continuation.
+    #[error("continuation rejected by filter: {0}")]
+    ContinuationRejectedByFilter(String),
+
+    /// A message was sealed with a session we do not hold.
+    #[error("unknown session {0}")]
+    UnknownSession(ARID),
+
+    /// A message was sealed with a session that has expired.
+    #[error("session {0} expired")]
+    SessionExpired(ARID),
+
+    /// A session has sealed as many messages as it is allowed to, and must
+    /// be rekeyed to seal more.
+    #[error("session {id} reached its limit of {limit} messages")]
+    SessionLimitReached { id: ARID, limit: u64 },
+
+    /// The authentication code of a message sealed with a session does not
+    /// verify.
+    #[error("session message authentication failed")]
+    SessionAuthenticationFailed,
+
+    /// A message sealed with a session does not follow the last one we
+    /// received, so it is replayed or reordered.
+    #[error("session message {counter} does not follow message {last}")]
+    SessionReplay { counter: u64, last: u64 },
+
+    /// A message proposing a session is sealed to no recipients, or was
+    /// received unencrypted, so the session key is in the clear.
+    #[error("session proposals must be encrypted")]
+    SessionProposalNotEncrypted,
+
+    /// A message sealed with a password could not be decrypted with the
+    /// password given.
+    #[error("password does not decrypt the message")]
+    PasswordDecryptionFailed,
+
+    /// Too few SSKR shares were given to reassemble a sealed message.
+    #[error(
+        "insufficient shares: {groups} of {threshold} required groups complete"
+    )]
+    InsufficientShares { groups: usize, threshold: usize },
+
+    /// SSKR shares given for reassembly come from different splits.
+    #[error("shares come from different splits")]
+    MismatchedShares,
+
+    /// An SSKR share is malformed, or the shares do not recover the key that
+    /// encrypts the message.
+    #[error("corrupted share")]
+    CorruptedShare,
+
+    /// A handshake message does not answer the challenge we issued.
+    #[error("handshake challenge not answered")]
+    ChallengeMismatch,
+
+    /// A handshake peer's key uses an encapsulation scheme we do not accept.
+    #[error("unsupported encapsulation scheme {0:?}")]
+    UnsupportedScheme(EncapsulationScheme),
+
+    /// A handshake was completed by a party whose introduction we did not
+    /// acknowledge.
+    #[error("no pending introduction from {0}")]
+    NoPendingIntroduction(XID),
+
+    /// A message was sealed to our public keys while a session with its
+    /// sender was established, without proposing a new session.
+    #[error("message bypasses the established session")]
+    SessionDowngrade,
+
+    /// A message is written in a newer version of the protocol than we
+    /// support.
+    #[error("unsupported GSTP version {found} (supported up to {supported})")]
+    UnsupportedGstpVersion { found: u32, supported: u32 },
+
+    /// A message lacks the provenance the parse options require.
+    #[error("message does not record its provenance")]
+    MissingProvenance,
+
+    /// A frame did not start with the GSTP magic prefix.
+    #[error("frame does not start with the GSTP magic prefix")]
+    FrameMagicMismatch,
+
+    /// A frame declared a layout version this crate does not understand.
+    #[error("unsupported frame version {0}")]
+    FrameVersionUnsupported(u8),
+
+    /// A frame declared a payload larger than the configured limit.
+    #[error("frame of {size} bytes exceeds the limit of {limit} bytes")]
+    FrameTooLarge { size: usize, limit: usize },
+
+    /// A field of a message exceeds the configured limit.
+    #[error("{field} of size {size} exceeds the limit of {limit}")]
+    FieldTooLarge {
+        field: &'static str,
+        size: usize,
+        limit: usize,
+    },
+
+    /// A component of a message, such as a parameter value, exceeds the
+    /// configured limit.
+    #[error("{component} of {size} bytes exceeds the limit of {limit} bytes")]
+    ComponentTooLarge {
+        component: String,
+        size: usize,
+        limit: usize,
+    },
+
+    /// A sealed response would exceed the recipient's maximum message size.
+    #[error(
+        "response of {size} bytes exceeds the recipient's limit of {limit} bytes"
+    )]
+    ResponseTooLarge { size: usize, limit: usize },
+
+    /// A chunk of a result split across several responses is malformed or
+    /// does not belong with the others.
+    #[error("invalid result chunk")]
+    InvalidResultChunk,
+
+    /// A transport chunk is malformed or does not belong with the others.
+    #[error("invalid chunk")]
+    InvalidChunk,
+
+    /// Two transport chunks with the same index carry different content.
+    #[error("chunk {index} was received twice with different content")]
+    ConflictingChunk { index: usize },
+
+    /// A message cannot be reassembled before all of its transport chunks
+    /// are received.
+    #[error("chunk {index} of {count} is missing")]
+    MissingChunk { index: usize, count: usize },
+
+    /// A message reassembled from transport chunks does not match the digest
+    /// the chunks carry.
+    #[error("reassembled message does not match its digest")]
+    ChunkDigestMismatch,
+
+    /// A chunk size budget is too small to carry any of the message.
+    #[error("chunk budget of {budget} bytes is below the minimum of {minimum}")]
+    ChunkBudgetTooSmall { budget: usize, minimum: usize },
+
+    /// A request ID or continuation was seen before within the freshness
+    /// window.
+    #[error("replay detected")]
+    ReplayDetected,
+
+    /// A replay store's log contains a malformed record.
+    #[error("invalid replay log record")]
+    InvalidReplayLog,
+
+    /// A delivery record does not describe the message it was checked
+    /// against.
+    #[error("delivery record does not match the message")]
+    DeliveryRecordMismatch,
+
+    /// An application assertion uses a predicate reserved by GSTP.
+    #[error("predicate {predicate} is reserved; reserved predicates are {}", reserved.join(", "))]
+    ReservedPredicate {
+        predicate: String,
+        reserved: Vec<String>,
+    },
+
+    /// A WebSocket frame does not start with a known message kind.
+    #[error("unknown frame kind {0}")]
+    UnknownFrameKind(u8),
+
+    /// An event topic cannot be used as an MQTT topic name, as it is empty
+    /// or contains a wildcard or null character.
+    #[error("invalid MQTT topic {0:?}")]
+    InvalidTopic(String),
+
+    /// An event arrived on an MQTT topic other than the one its content
+    /// names.
+    #[error("event received on topic {topic:?} belongs on {expected:?}")]
+    TopicMismatch { topic: String, expected: String },
+
+    /// The stream ended partway through a frame.
+    #[error("stream ended partway through a frame")]
+    FrameTruncated,
+
+    /// The stream ended cleanly before the next frame started.
+    #[error("end of stream")]
+    EndOfStream,
+
+    /// A response answers a request other than the one expected, or, unless
+    /// early failures are accepted, answers no request at all.
+    #[error("expected a response to {expected}, found {found:?}")]
+    ResponseIdMismatch { expected: ARID, found: Option<ARID> },
+
+    /// A response is not bound to the request envelope we sent.
+    #[error("response is not bound to the request sent")]
+    ResponseBindingMismatch,
+
+    /// A message does not commit to the digest of the message before it in
+    /// a transcript. `link` is the index of the message that broke the chain.
+    #[error("transcript broken at message {link}")]
+    TranscriptBroken { link: usize },
+
+    /// A response did not match any pending request.
+    #[error("no pending request matches response ID {0:?}")]
+    UnknownPendingRequest(Option<ARID>),
+
+    /// A response arrived after the deadline of its pending request.
+    #[error("pending request {0} expired")]
+    PendingRequestExpired(ARID),
+
+    /// The state echoed back by a peer differs from the state we issued.
+    #[error("echoed continuation state does not match the issued state")]
+    EchoedStateMismatch,
+
+    /// A message was signed by someone other than the expected sender.
+    #[error(
+        "message from unexpected sender: expected {expected}, found {found}"
+    )]
+    UnexpectedSender { expected: XID, found: XID },
+
+    /// A message was signed by a sender the parse options' sender policy
+    /// refuses.
+    #[error("sender {0} is not authorized")]
+    SenderNotAuthorized(XID),
+
+    /// A sender's verification key differs from the one recorded when it was
+    /// first seen.
+    #[error("verification key of sender {xid} has changed")]
+    SenderKeyChanged { xid: XID },
+
+    /// A message repeats an assertion it may carry only once.
+    #[error("ambiguous message: duplicated '{predicate}' assertion")]
+    AmbiguousAssertion { predicate: KnownValue },
+
+    /// None of the recipient hints on a message name a key in the directory.
+    #[error("no registered key matches any recipient slot")]
+    NoRegisteredRecipientKey,
+
+    /// None of the recipient slots of a sealed message opens with the given
+    /// keys.
+    #[error("no recipient slot opens with the given keys")]
+    NoRecipientSlot,
+
+    /// A delegation keyring has no key with the given fingerprint.
+    #[error("unknown delegation key: {0}")]
+    UnknownDelegationKey(Reference),
+
+    /// An export registry has no request with the given handle.
+    #[error("unknown export handle: {0}")]
+    UnknownExportHandle(String),
+
+    /// A response parsed as an early failure answers a particular request.
+    #[error("response is not an early failure")]
+    NotAnEarlyFailure,
+
+    /// An early failure carries a result, a continuation, or other content
+    /// it must not.
+    #[error("early failure carries content beyond its error")]
+    InvalidEarlyFailure,
+
+    /// An outbox has no entry with the given ID.
+    #[error("no outbox entry with ID {0}")]
+    UnknownOutboxEntry(ARID),
+
+    /// A stored outbox entry could not be decoded.
+    #[error("invalid outbox entry")]
+    InvalidOutboxEntry,
+
+    /// A string could not be decoded as an envelope UR.
+    #[error("invalid UR: {0}")]
+    InvalidUR(#[source] dcbor::Error),
+
+    /// Encoded text contains a character its encoding does not allow there.
+    #[error("invalid {encoding} character at position {position}")]
+    InvalidEncodedCharacter { encoding: Encoding, position: usize },
+
+    /// Encoded text has a length no valid encoding can have.
+    #[error("invalid {encoding} length {length}")]
+    InvalidEncodedLength { encoding: Encoding, length: usize },
+
+    /// Bytes follow the end of an encoded envelope.
+    #[error("{count} unexpected bytes follow the envelope")]
+    TrailingBytes { count: usize },
+
+    /// Input is not in any recognized encoding of an envelope.
+    #[error("unrecognized envelope encoding")]
+    UnrecognizedEncoding,
+
+    /// An HTTP message does not carry a sealed message. Holds the content
+    /// type it declared, if any.
+    #[error("unexpected content type {0:?}")]
+    UnexpectedContentType(Option<String>),
+
+    /// An HTTP peer answered with a status other than success. `envelope` is
+    /// the envelope its body carried, if any, such as a sealed early failure.
+    #[error("HTTP status {status}")]
+    HttpStatus {
+        status: u16,
+        envelope: Option<Envelope>,
+    },
+
+    /// A multipart UR part belongs to a different message than the parts
+    /// received before it.
+    #[error("part belongs to a different message")]
+    ForeignPart,
+
+    /// An envelope carries a different kind of message than expected.
+    #[error("expected a {expected} but found a {found}")]
+    MessageKindMismatch {
+        expected: MessageKind,
+        found: MessageKind,
+    },
+
+    /// An envelope does not carry a GSTP message.
+    #[error("envelope is not a GSTP message")]
+    UnknownMessageKind,
+
+    /// A failure response carries state, which was not allowed when it was
+    /// sealed.
+    #[error("state is not allowed on a failure response")]
+    StateNotAllowedOnFailure,
+
+    /// The service has been closed and accepts no more requests.
+    #[error("service is closed")]
+    ServiceClosed,
+
+    /// The sender's XID document could not be encoded into the message.
+    #[error("sender document could not be encoded: {0}")]
+    SenderDocumentEncoding(#[source] bc_xid::Error),
+
     /// Error from bc-envelope operations.
     #[error(transparent)]
     Envelope(#[from] bc_envelope::Error),
 
+    /// Error from bc-components operations.
+    #[error(transparent)]
+    Components(#[from] bc_components::Error),
+
     /// Error from bc-xid operations.
     #[error(transparent)]
     XID(#[from] bc_xid::Error),
+
+    /// Error from dcbor operations.
+    #[error(transparent)]
+    Cbor(#[from] dcbor::Error),
+
+    /// Error from I/O operations.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Error from an HTTP exchange, such as a failure to connect.
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,392 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+use bc_components::{ARID, Digest, PrivateKeys, XID, XIDProvider};
+use bc_envelope::prelude::*;
+
+use crate::{
+    MessageKind, ParseOptions, Result, SealedEvent, SealedEventBehavior,
+    consts, message_envelope::check_kind,
+};
+
+/// Content that can be carried by a [`SealedEvent`] and delivered by an
+/// [`EventBus`].
+pub trait EventContent:
+    EnvelopeEncodable
+    + TryFrom<Envelope>
+    + std::fmt::Debug
+    + Clone
+    + PartialEq
+    + 'static
+{
+}
+
+impl<T> EventContent for T where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq
+        + 'static
+{
+}
+
+/// Returns the topic of an event's content: the object of its `isA`
+/// assertion, if it has one.
+///
+/// A string object is taken as is, and a known value by its name.
+pub fn event_topic(content: &Envelope) -> Option<String> {
+    let object = content.object_for_predicate(known_values::IS_A).ok()?;
+    if let Ok(topic) = object.extract_subject::<String>() {
+        return Some(topic);
+    }
+    object
+        .extract_subject::<KnownValue>()
+        .ok()
+        .map(|value| value.name())
+}
+
+/// Selects the events a handler is interested in by their topic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TopicFilter {
+    /// Every event, including events without a topic.
+    Any,
+    /// Events with exactly the given topic.
+    Exact(String),
+    /// Events whose topic starts with the given prefix.
+    Prefix(String),
+}
+
+impl TopicFilter {
+    pub fn exact(topic: impl Into<String>) -> Self {
+        TopicFilter::Exact(topic.into())
+    }
+
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        TopicFilter::Prefix(prefix.into())
+    }
+
+    pub fn matches(&self, topic: Option<&str>) -> bool {
+        match (self, topic) {
+            (TopicFilter::Any, _) => true,
+            (TopicFilter::Exact(expected), Some(topic)) => expected == topic,
+            (TopicFilter::Prefix(prefix), Some(topic)) => {
+                topic.starts_with(prefix.as_str())
+            }
+            (_, None) => false,
+        }
+    }
+}
+
+/// The result returned by an event handler.
+pub type HandlerResult =
+    std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+type Handler =
+    Box<dyn Fn(&SealedEvent<Envelope>) -> HandlerResult + Send + 'static>;
+
+/// An error returned by, or raised while invoking, a single handler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandlerError {
+    /// The index of the handler, in the order handlers were registered, or
+    /// `None` for the default handler.
+    pub handler: Option<usize>,
+    pub message: String,
+}
+
+/// Where an event falls in the chain of events from its sender, as given by
+/// the digest it commits to with [`SealedEvent::with_previous_digest`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SequenceStatus {
+    /// The event follows the last event dispatched from its sender, or
+    /// commits to no previous event.
+    InOrder,
+    /// The event commits to an event that was never dispatched, so events
+    /// from its sender were missed.
+    Gap,
+    /// The event commits to an event from its sender dispatched before the
+    /// last one, so it arrived out of order.
+    Reordered,
+}
+
+/// What happened when an event was dispatched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DispatchReport {
+    pub event_id: ARID,
+    pub topic: Option<String>,
+    /// Whether the event had already been dispatched, in which case no
+    /// handler was invoked.
+    pub duplicate: bool,
+    /// Where the event falls in the chain of events from its sender, if
+    /// sequence tracking is enabled and the event is not a duplicate.
+    pub sequence: Option<SequenceStatus>,
+    /// The number of handlers invoked, including the default handler.
+    pub invoked: usize,
+    pub errors: Vec<HandlerError>,
+}
+
+/// A map that remembers at most a given number of entries, forgetting the
+/// least recently inserted first.
+#[derive(Debug)]
+struct Window<K, V> {
+    next: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> Default for Window<K, V> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, V> Window<K, V> {
+    fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    fn contains(&self, key: &K) -> bool { self.entries.contains_key(key) }
+
+    /// Inserts or refreshes an entry, then forgets the oldest entries beyond
+    /// `capacity`.
+    fn insert(&mut self, key: K, value: V, capacity: usize) {
+        let stamp = self.next;
+        self.next += 1;
+        if let Some((_, old)) = self.entries.insert(key.clone(), (value, stamp))
+        {
+            self.order.remove(&old);
+        }
+        self.order.insert(stamp, key);
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+/// Parses incoming events and dispatches them to handlers registered by
+/// topic.
+///
+/// Every handler whose [`TopicFilter`] matches an event is invoked with the
+/// event's content converted to the handler's content type, and an error
+/// from one handler does not prevent the others from running. A handler
+/// that panics is reported as an error in the same way, unless the crate is
+/// built to abort on panic. Events that no handler matches fall through to
+/// the default handler, if one is set.
+///
+/// Deduplication and sequence tracking remember a bounded window of recent
+/// events and senders, [`consts::DEFAULT_EVENT_WINDOW`] unless set with
+/// [`Self::with_window`].
+pub struct EventBus {
+    handlers: Vec<(TopicFilter, Handler)>,
+    default_handler: Option<Handler>,
+    deduplicate: bool,
+    track_sequence: bool,
+    window: usize,
+    seen: Window<(XID, ARID), ()>,
+    dispatched: Window<(XID, Digest), ()>,
+    heads: Window<XID, Digest>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            handlers: Vec::new(),
+            default_handler: None,
+            deduplicate: false,
+            track_sequence: false,
+            window: consts::DEFAULT_EVENT_WINDOW,
+            seen: Window::default(),
+            dispatched: Window::default(),
+            heads: Window::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field(
+                "filters",
+                &self
+                    .handlers
+                    .iter()
+                    .map(|(filter, _)| filter)
+                    .collect::<Vec<_>>(),
+            )
+            .field("default_handler", &self.default_handler.is_some())
+            .field("deduplicate", &self.deduplicate)
+            .field("track_sequence", &self.track_sequence)
+            .field("window", &self.window)
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self { Self::default() }
+
+    /// Dispatches each event ID from a given sender at most once, ignoring
+    /// replays. The same ID from another sender is a different event. Only
+    /// the IDs of the most recent events are remembered, so a replay older
+    /// than the window is dispatched again.
+    pub fn with_deduplication(mut self) -> Self {
+        self.deduplicate = true;
+        self
+    }
+
+    /// Reports where each event falls in the chain of events from its
+    /// sender, as a [`SequenceStatus`]. Events are dispatched whatever their
+    /// status.
+    pub fn with_sequence_tracking(mut self) -> Self {
+        self.track_sequence = true;
+        self
+    }
+
+    /// Sets how many events, and how many senders, deduplication and
+    /// sequence tracking remember.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Registers a handler for events matching `filter`, returning its
+    /// index.
+    ///
+    /// Events whose content cannot be converted to `T` are reported as an
+    /// error for this handler, as are panics.
+    pub fn register<T: EventContent>(
+        &mut self,
+        filter: TopicFilter,
+        handler: impl Fn(SealedEvent<T>) -> HandlerResult + Send + 'static,
+    ) -> usize {
+        self.handlers.push((
+            filter,
+            Box::new(move |event| handler(event.try_map_content()?)),
+        ));
+        self.handlers.len() - 1
+    }
+
+    /// Sets the handler invoked for events that no registered handler
+    /// matches.
+    pub fn set_default_handler(
+        &mut self,
+        handler: impl Fn(SealedEvent<Envelope>) -> HandlerResult + Send + 'static,
+    ) {
+        self.default_handler =
+            Some(Box::new(move |event| handler(event.clone())));
+    }
+
+    /// Parses a sealed event and invokes the handlers that match it.
+    ///
+    /// An error is returned only if the event cannot be parsed; errors from
+    /// handlers are collected in the returned report.
+    pub fn dispatch(
+        &mut self,
+        envelope: &Envelope,
+        recipient: &PrivateKeys,
+        options: &ParseOptions,
+    ) -> Result<DispatchReport> {
+        check_kind(envelope, MessageKind::Event)?;
+        let event = SealedEvent::<Envelope>::try_from_envelope_opt(
+            envelope, options, recipient,
+        )?;
+        let topic = event_topic(event.content());
+        let mut report = DispatchReport {
+            event_id: event.id(),
+            topic: topic.clone(),
+            duplicate: false,
+            sequence: None,
+            invoked: 0,
+            errors: Vec::new(),
+        };
+        if self.deduplicate {
+            let key = (event.sender().xid(), event.id());
+            if self.seen.contains(&key) {
+                report.duplicate = true;
+                return Ok(report);
+            }
+            self.seen.insert(key, (), self.window);
+        }
+        if self.track_sequence {
+            report.sequence = Some(self.track(&event, envelope));
+        }
+
+        for (index, (filter, handler)) in self.handlers.iter().enumerate() {
+            if filter.matches(topic.as_deref()) {
+                report.invoked += 1;
+                if let Err(message) = invoke(handler, &event) {
+                    report.errors.push(HandlerError {
+                        handler: Some(index),
+                        message,
+                    });
+                }
+            }
+        }
+        if report.invoked == 0
+            && let Some(handler) = &self.default_handler
+        {
+            report.invoked += 1;
+            if let Err(message) = invoke(handler, &event) {
+                report.errors.push(HandlerError {
+                    handler: None,
+                    message,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Places an event in the chain of events from its sender, recording it
+    /// as the head of the chain unless it arrived out of order.
+    fn track(
+        &mut self,
+        event: &SealedEvent<Envelope>,
+        envelope: &Envelope,
+    ) -> SequenceStatus {
+        let sender = event.sender().xid();
+        let status = match event.previous_digest() {
+            None => SequenceStatus::InOrder,
+            Some(previous) if self.heads.get(&sender) == Some(&previous) => {
+                SequenceStatus::InOrder
+            }
+            Some(previous) if self.dispatched.contains(&(sender, previous)) => {
+                SequenceStatus::Reordered
+            }
+            Some(_) => SequenceStatus::Gap,
+        };
+        let digest = envelope.digest();
+        self.dispatched.insert((sender, digest), (), self.window);
+        if status != SequenceStatus::Reordered {
+            self.heads.insert(sender, digest, self.window);
+        }
+        status
+    }
+}
+
+/// Invokes a handler, turning an error or a panic into its message.
+fn invoke(
+    handler: &Handler,
+    event: &SealedEvent<Envelope>,
+) -> std::result::Result<(), String> {
+    match catch_unwind(AssertUnwindSafe(|| handler(event))) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(format!("handler panicked: {message}"))
+        }
+    }
+}
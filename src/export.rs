@@ -0,0 +1,282 @@
+//! Plain, serializable views of verified messages, for handing their
+//! contents to code outside Rust.
+//!
+//! An exported request carries a handle. The [`ExportRegistry`] that exported
+//! it keeps the request itself, so that once the foreign code has done its
+//! work a [`SealedResponse`] answering the request, returning the peer's
+//! continuation, can be composed from the handle alone.
+
+use std::collections::HashMap;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use dcbor::Simple;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, Result, SealedEvent, SealedEventBehavior, SealedRequest,
+    SealedRequestBehavior, SealedResponse, SealedResponseBehavior,
+};
+
+/// A value carried by a message, converted to a JSON-compatible form where
+/// possible.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum ExportedValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+    /// Any other value, including arrays, maps, tagged values, and envelopes
+    /// with assertions, as CBOR diagnostic notation and the encoded CBOR.
+    Cbor {
+        diagnostic: String,
+        data: Vec<u8>,
+    },
+}
+
+impl From<&Envelope> for ExportedValue {
+    fn from(envelope: &Envelope) -> Self {
+        let Some(cbor) = envelope.as_leaf() else {
+            return ExportedValue::Cbor {
+                diagnostic: envelope.to_cbor().diagnostic_flat(),
+                data: envelope.to_cbor_data(),
+            };
+        };
+        let fallback = || ExportedValue::Cbor {
+            diagnostic: cbor.diagnostic_flat(),
+            data: cbor.to_cbor_data(),
+        };
+        match cbor.as_case() {
+            CBORCase::Unsigned(value) => i64::try_from(*value)
+                .map_or_else(|_| fallback(), ExportedValue::Integer),
+            CBORCase::Negative(value) => i64::try_from(*value).map_or_else(
+                |_| fallback(),
+                |value| ExportedValue::Integer(-1 - value),
+            ),
+            CBORCase::ByteString(bytes) => {
+                ExportedValue::Bytes(bytes.data().to_vec())
+            }
+            CBORCase::Text(text) => ExportedValue::Text(text.clone()),
+            CBORCase::Simple(Simple::Null) => ExportedValue::Null,
+            CBORCase::Simple(Simple::True) => ExportedValue::Bool(true),
+            CBORCase::Simple(Simple::False) => ExportedValue::Bool(false),
+            CBORCase::Simple(Simple::Float(value)) => {
+                ExportedValue::Float(*value)
+            }
+            _ => fallback(),
+        }
+    }
+}
+
+/// A named parameter of an exported request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedParameter {
+    pub name: String,
+    pub value: ExportedValue,
+}
+
+/// A verified request, exported by an [`ExportRegistry`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedRequestExport {
+    /// Identifies the request to the registry that exported it.
+    pub handle: String,
+    pub function: String,
+    /// The ID of the request, in hex.
+    pub id: String,
+    pub parameters: Vec<ExportedParameter>,
+    pub note: String,
+    /// The date of the request, in ISO 8601 format.
+    pub date: Option<String>,
+    /// The sender's XID, as a UR.
+    pub sender: String,
+    pub has_state: bool,
+    /// The state returned to us by the sender, as encoded CBOR.
+    pub state: Option<Vec<u8>>,
+}
+
+/// A verified response.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedResponseExport {
+    /// The ID of the request answered, in hex, if it is known.
+    pub id: Option<String>,
+    pub is_ok: bool,
+    pub result: Option<ExportedValue>,
+    pub error: Option<ExportedValue>,
+    /// The sender's XID, as a UR.
+    pub sender: String,
+    pub has_state: bool,
+    /// The state returned to us by the sender, as encoded CBOR.
+    pub state: Option<Vec<u8>>,
+}
+
+/// A verified event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedEventExport {
+    /// The ID of the event, in hex.
+    pub id: String,
+    pub content: ExportedValue,
+    pub note: String,
+    /// The date of the event, in ISO 8601 format.
+    pub date: Option<String>,
+    /// The sender's XID, as a UR.
+    pub sender: String,
+    pub has_state: bool,
+    /// The state returned to us by the sender, as encoded CBOR.
+    pub state: Option<Vec<u8>>,
+}
+
+fn sender_ur(sender: &XIDDocument) -> String { sender.xid().ur_string() }
+
+fn parameter_name(parameter: &Parameter) -> String {
+    let name = parameter.name();
+    match parameter {
+        Parameter::Named(_) => name.trim_matches('"').to_string(),
+        Parameter::Known(..) => name,
+    }
+}
+
+fn export_parameters(
+    request: &SealedRequest,
+) -> Result<Vec<ExportedParameter>> {
+    request
+        .request()
+        .body()
+        .expression_envelope()
+        .assertions()
+        .iter()
+        .map(|assertion| {
+            let parameter =
+                Parameter::try_from(assertion.try_predicate()?.try_leaf()?)?;
+            Ok(ExportedParameter {
+                name: parameter_name(&parameter),
+                value: ExportedValue::from(&assertion.try_object()?),
+            })
+        })
+        .collect()
+}
+
+impl From<&SealedResponse> for VerifiedResponseExport {
+    fn from(response: &SealedResponse) -> Self {
+        Self {
+            id: response.id().map(|id| id.hex()),
+            is_ok: response.is_ok(),
+            result: response.result().ok().map(ExportedValue::from),
+            error: response.error().ok().map(ExportedValue::from),
+            sender: sender_ur(response.sender()),
+            has_state: response.state().is_some(),
+            state: response.state().map(|state| state.to_cbor_data()),
+        }
+    }
+}
+
+impl<T> From<&SealedEvent<T>> for VerifiedEventExport
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    fn from(event: &SealedEvent<T>) -> Self {
+        Self {
+            id: event.id().hex(),
+            content: ExportedValue::from(&event.content().to_envelope()),
+            note: event.note().to_string(),
+            date: event.date().map(|date| date.to_string()),
+            sender: sender_ur(event.sender()),
+            has_state: event.state().is_some(),
+            state: event.state().map(|state| state.to_cbor_data()),
+        }
+    }
+}
+
+/// Exports verified requests and keeps them, by handle, until they are
+/// answered.
+#[derive(Debug, Default)]
+pub struct ExportRegistry {
+    requests: HashMap<String, SealedRequest>,
+}
+
+impl ExportRegistry {
+    pub fn new() -> Self { Self::default() }
+
+    /// Exports a verified request, keeping it until it is answered.
+    pub fn export_request(
+        &mut self,
+        request: SealedRequest,
+    ) -> Result<VerifiedRequestExport> {
+        let handle = ARID::new().hex();
+        let export = VerifiedRequestExport {
+            handle: handle.clone(),
+            function: request
+                .function()
+                .named_name()
+                .unwrap_or_else(|| request.function().name()),
+            id: request.id().hex(),
+            parameters: export_parameters(&request)?,
+            note: request.note().to_string(),
+            date: request.date().map(|date| date.to_string()),
+            sender: sender_ur(request.sender()),
+            has_state: request.state().is_some(),
+            state: request.state().map(|state| state.to_cbor_data()),
+        };
+        self.requests.insert(handle, request);
+        Ok(export)
+    }
+
+    /// The request exported with the given handle, if it has not yet been
+    /// answered.
+    pub fn request(&self, handle: &str) -> Option<&SealedRequest> {
+        self.requests.get(handle)
+    }
+
+    /// Starts a successful response, from `sender`, to the request exported
+    /// with the given handle, returning the continuation the requester sent,
+    /// bound to the request envelope, and encrypted to its ephemeral key, if
+    /// any.
+    ///
+    /// The request is forgotten.
+    pub fn respond_success(
+        &mut self,
+        handle: &str,
+        sender: &XIDDocument,
+    ) -> Result<SealedResponse> {
+        let request = self.take(handle)?;
+        Ok(SealedResponse::new_success(request.id(), sender)
+            .with_optional_peer_continuation(
+                request.peer_continuation().cloned(),
+            )
+            .with_optional_in_response_to(request.envelope_digest())
+            .with_optional_reply_key(request.ephemeral_key().cloned()))
+    }
+
+    /// Starts a failure response, from `sender`, to the request exported with
+    /// the given handle, returning the continuation the requester sent,
+    /// bound to the request envelope, and encrypted to its ephemeral key, if
+    /// any.
+    ///
+    /// The request is forgotten.
+    pub fn respond_failure(
+        &mut self,
+        handle: &str,
+        sender: &XIDDocument,
+    ) -> Result<SealedResponse> {
+        let request = self.take(handle)?;
+        Ok(SealedResponse::new_failure(request.id(), sender)
+            .with_optional_peer_continuation(
+                request.peer_continuation().cloned(),
+            )
+            .with_optional_in_response_to(request.envelope_digest())
+            .with_optional_reply_key(request.ephemeral_key().cloned()))
+    }
+
+    fn take(&mut self, handle: &str) -> Result<SealedRequest> {
+        self.requests
+            .remove(handle)
+            .ok_or_else(|| Error::UnknownExportHandle(handle.to_string()))
+    }
+}
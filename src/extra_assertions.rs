@@ -0,0 +1,89 @@
+use bc_components::DigestProvider;
+use bc_envelope::prelude::*;
+
+use crate::{
+    Error, Result, continuation_storage, gstp_version, provenance,
+    request_profile, sealed_request, sealed_response, session, state_lifetime,
+    transcript,
+};
+
+/// The predicates GSTP itself places on the signed layer of a message, which
+/// application assertions may not use.
+pub fn reserved_predicates() -> Vec<Envelope> {
+    let mut reserved: Vec<Envelope> = [
+        known_values::BODY,
+        known_values::CONTENT,
+        known_values::DATE,
+        known_values::ERROR,
+        known_values::NOTE,
+        known_values::RESULT,
+        known_values::SENDER,
+        known_values::SENDER_CONTINUATION,
+        known_values::RECIPIENT_CONTINUATION,
+        known_values::SIGNED,
+    ]
+    .into_iter()
+    .map(Envelope::new)
+    .collect();
+    reserved.extend(
+        [
+            gstp_version::GSTP_VERSION,
+            provenance::PROVENANCE,
+            sealed_request::EPHEMERAL_KEY,
+            sealed_response::IN_RESPONSE_TO,
+            continuation_storage::RECIPIENT_CONTINUATION_REF,
+            request_profile::ONE_WAY,
+            session::SESSION_PROPOSAL,
+            session::SESSION_ACK,
+            state_lifetime::PROPOSED_STATE_LIFETIME,
+            state_lifetime::GRANTED_STATE_LIFETIME,
+            transcript::TRANSCRIPT,
+        ]
+        .map(Envelope::new),
+    );
+    reserved
+}
+
+fn is_reserved(predicate: &Envelope, reserved: &[Envelope]) -> bool {
+    reserved
+        .iter()
+        .any(|reserved| reserved.digest() == predicate.digest())
+}
+
+/// Adds application assertions to a message before it is signed, failing
+/// with [`Error::ReservedPredicate`] if any uses a reserved predicate.
+pub(crate) fn add_to(
+    message: Envelope,
+    assertions: &[Envelope],
+) -> Result<Envelope> {
+    let reserved = reserved_predicates();
+    let mut message = message;
+    for assertion in assertions {
+        let predicate = assertion.try_predicate()?;
+        if is_reserved(&predicate, &reserved) {
+            return Err(Error::ReservedPredicate {
+                predicate: predicate.format_flat(),
+                reserved: reserved
+                    .iter()
+                    .map(|reserved| reserved.format_flat())
+                    .collect(),
+            });
+        }
+        message = message.add_assertion_envelope(assertion)?;
+    }
+    Ok(message)
+}
+
+/// Returns the assertions of a parsed message that GSTP does not reserve.
+pub(crate) fn from_message(message: &Envelope) -> Vec<Envelope> {
+    let reserved = reserved_predicates();
+    message
+        .assertions()
+        .into_iter()
+        .filter(|assertion| {
+            assertion
+                .try_predicate()
+                .is_ok_and(|predicate| !is_reserved(&predicate, &reserved))
+        })
+        .collect()
+}
@@ -0,0 +1,193 @@
+//! Framing of envelopes on raw byte streams such as TCP connections.
+//!
+//! Each frame is the [`MAGIC`] prefix, a single [`FRAME_VERSION`] byte, the
+//! length of the payload as a big-endian `u32`, and finally the payload, which
+//! is the CBOR encoding of the envelope. The magic prefix lets a receiver
+//! cheaply tell GSTP traffic apart from anything else on the same stream.
+
+use std::io::{ErrorKind, Read, Write};
+
+use bc_envelope::prelude::*;
+
+use crate::{Error, ParseLimits, Result};
+
+/// The media type of a single CBOR-encoded GSTP envelope.
+pub const MEDIA_TYPE: &str = "application/gordian-sealed-transaction+cbor";
+
+/// The bytes every frame starts with.
+pub const MAGIC: [u8; 4] = *b"GSTP";
+
+/// The version of the frame layout written by this crate.
+pub const FRAME_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4;
+
+fn encode_header(payload_len: usize) -> Result<[u8; HEADER_LEN]> {
+    let len = u32::try_from(payload_len).map_err(|_| Error::FrameTooLarge {
+        size: payload_len,
+        limit: u32::MAX as usize,
+    })?;
+    let mut header = [0u8; HEADER_LEN];
+    header[..MAGIC.len()].copy_from_slice(&MAGIC);
+    header[MAGIC.len()] = FRAME_VERSION;
+    header[MAGIC.len() + 1..].copy_from_slice(&len.to_be_bytes());
+    Ok(header)
+}
+
+/// Validates the magic prefix and version of a frame header.
+fn check_header(header: &[u8; HEADER_LEN]) -> Result<()> {
+    if header[..MAGIC.len()] != MAGIC {
+        return Err(Error::FrameMagicMismatch);
+    }
+    let version = header[MAGIC.len()];
+    if version != FRAME_VERSION {
+        return Err(Error::FrameVersionUnsupported(version));
+    }
+    Ok(())
+}
+
+/// Checks the big-endian `u32` length ending `header` against `limits`,
+/// returning it.
+fn declared_length(header: &[u8], limits: &ParseLimits) -> Result<usize> {
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&header[header.len() - 4..]);
+    let size = u32::from_be_bytes(len) as usize;
+    if size > limits.max_message_size() {
+        return Err(Error::FrameTooLarge {
+            size,
+            limit: limits.max_message_size(),
+        });
+    }
+    Ok(size)
+}
+
+/// Writes `envelope` to `w` as a single frame.
+pub fn write_frame(mut w: impl Write, envelope: &Envelope) -> Result<()> {
+    let payload = envelope.to_cbor_data();
+    w.write_all(&encode_header(payload.len())?)?;
+    w.write_all(&payload)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Reads a header of `N` bytes ending in a big-endian `u32` payload length,
+/// validates the rest of it with `check_header`, and returns the length once
+/// it is checked against `limits`.
+///
+/// Returns [`Error::EndOfStream`] if the stream ends cleanly before the
+/// header starts, and [`Error::FrameTruncated`] if it ends partway through.
+pub(crate) fn read_length_prefix<const N: usize>(
+    r: &mut impl Read,
+    limits: &ParseLimits,
+    check_header: impl FnOnce(&[u8; N]) -> Result<()>,
+) -> Result<usize> {
+    let mut header = [0u8; N];
+    let mut filled = 0;
+    while filled < N {
+        match r.read(&mut header[filled..]) {
+            Ok(0) if filled == 0 => return Err(Error::EndOfStream),
+            Ok(0) => return Err(Error::FrameTruncated),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    check_header(&header)?;
+    declared_length(&header, limits)
+}
+
+/// Reads a payload of `size` bytes and decodes it as an envelope.
+pub(crate) fn read_payload(r: &mut impl Read, size: usize) -> Result<Envelope> {
+    let mut payload = vec![0u8; size];
+    r.read_exact(&mut payload).map_err(|e| {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            Error::FrameTruncated
+        } else {
+            e.into()
+        }
+    })?;
+    Ok(Envelope::try_from_cbor_data(payload)?)
+}
+
+/// Reads a single frame from `r`.
+///
+/// Returns [`Error::EndOfStream`] if the stream ends cleanly before the next
+/// frame starts, and [`Error::FrameTruncated`] if it ends partway through a
+/// frame. The declared payload length is checked against `limits` before any
+/// buffer for it is allocated.
+pub fn read_frame(mut r: impl Read, limits: &ParseLimits) -> Result<Envelope> {
+    let size = read_length_prefix(&mut r, limits, check_header)?;
+    read_payload(&mut r, size)
+}
+
+/// Reads a single frame from `r` and converts it to a typed envelope such as
+/// [`SealedRequestEnvelope`](crate::SealedRequestEnvelope), rejecting frames
+/// that carry a different kind of message.
+pub fn read_frame_as<T>(r: impl Read, limits: &ParseLimits) -> Result<T>
+where
+    T: TryFrom<Envelope, Error = Error>,
+{
+    read_frame(r, limits)?.try_into()
+}
+
+/// Asynchronous counterparts of [`write_frame`] and [`read_frame`].
+#[cfg(feature = "async")]
+pub mod r#async {
+    use bc_envelope::prelude::*;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    use super::{HEADER_LEN, check_header, declared_length, encode_header};
+    use crate::{Error, ParseLimits, Result};
+
+    /// Writes `envelope` to `w` as a single frame.
+    pub async fn write_frame(
+        mut w: impl AsyncWrite + Unpin,
+        envelope: &Envelope,
+    ) -> Result<()> {
+        let payload = envelope.to_cbor_data();
+        w.write_all(&encode_header(payload.len())?).await?;
+        w.write_all(&payload).await?;
+        w.flush().await?;
+        Ok(())
+    }
+
+    /// Reads a single frame from `r`, with the same semantics as
+    /// [`read_frame`](super::read_frame).
+    pub async fn read_frame(
+        mut r: impl AsyncRead + Unpin,
+        limits: &ParseLimits,
+    ) -> Result<Envelope> {
+        let mut header = [0u8; HEADER_LEN];
+        let mut filled = 0;
+        while filled < HEADER_LEN {
+            match r.read(&mut header[filled..]).await? {
+                0 if filled == 0 => return Err(Error::EndOfStream),
+                0 => return Err(Error::FrameTruncated),
+                n => filled += n,
+            }
+        }
+        check_header(&header)?;
+        let size = declared_length(&header, limits)?;
+        let mut payload = vec![0u8; size];
+        r.read_exact(&mut payload).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                Error::FrameTruncated
+            } else {
+                e.into()
+            }
+        })?;
+        Ok(Envelope::try_from_cbor_data(payload)?)
+    }
+
+    /// Reads a single frame from `r` and converts it to a typed envelope,
+    /// with the same semantics as [`read_frame_as`](super::read_frame_as).
+    pub async fn read_frame_as<T>(
+        r: impl AsyncRead + Unpin,
+        limits: &ParseLimits,
+    ) -> Result<T>
+    where
+        T: TryFrom<Envelope, Error = Error>,
+    {
+        read_frame(r, limits).await?.try_into()
+    }
+}
@@ -0,0 +1,38 @@
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result, SealOptions, consts};
+
+pub(crate) const GSTP_VERSION: &str = "gstpVersion";
+
+/// The version of messages that predate the version assertion.
+const UNMARKED_VERSION: u32 = 1;
+
+/// The newest protocol version this crate can parse.
+pub(crate) fn max_supported_version() -> u32 {
+    consts::SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(consts::PROTOCOL_VERSION)
+}
+
+/// Adds the protocol version the message is written in before it is signed.
+pub(crate) fn add_version(
+    message: Envelope,
+    options: &SealOptions,
+) -> Envelope {
+    message.add_assertion(GSTP_VERSION, options.gstp_version())
+}
+
+/// Reads the protocol version of a message, failing with
+/// [`Error::UnsupportedGstpVersion`] if it is newer than we support.
+pub(crate) fn parse_version(message: &Envelope) -> Result<u32> {
+    let found = message
+        .extract_optional_object_for_predicate(GSTP_VERSION)?
+        .unwrap_or(UNMARKED_VERSION);
+    let supported = max_supported_version();
+    if found > supported {
+        return Err(Error::UnsupportedGstpVersion { found, supported });
+    }
+    Ok(found)
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use bc_components::{ARID, EncapsulationScheme, XID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, Result, SealedRequest, SealedRequestBehavior, SealedResponse,
+    SealedResponseBehavior, gstp_version,
+};
+
+const INTRODUCE: &str = "introduce";
+const INTRODUCE_COMPLETE: &str = "introduceComplete";
+const CHALLENGE: &str = "challenge";
+const ANSWER: &str = "answer";
+const VERSION: &str = "version";
+
+/// What one party learned about the other by completing a handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerInfo {
+    document: XIDDocument,
+    gstp_version: u32,
+    encapsulation_scheme: EncapsulationScheme,
+}
+
+impl PeerInfo {
+    /// The peer's XID document, whose keys the peer proved it holds.
+    pub fn document(&self) -> &XIDDocument { &self.document }
+
+    pub fn xid(&self) -> XID { self.document.xid() }
+
+    /// The newest protocol version both parties support.
+    pub fn gstp_version(&self) -> u32 { self.gstp_version }
+
+    /// The scheme of the key messages to the peer are encapsulated to.
+    pub fn encapsulation_scheme(&self) -> EncapsulationScheme {
+        self.encapsulation_scheme
+    }
+}
+
+/// The party that starts a handshake, introducing itself to a peer it may
+/// not yet know.
+///
+/// The handshake takes three messages, each signed by its sender:
+///
+/// 1. [`Self::introduce`]: a request carrying our XID document and a fresh
+///    challenge.
+/// 2. [`HandshakeResponder::acknowledge`]: a response answering our challenge,
+///    carrying the responder's document and its own challenge.
+/// 3. [`Self::complete`]: a request answering the responder's challenge.
+///
+/// Since each challenge is answered inside a signed message, each party
+/// proves it holds the keys of its document, and neither answer can be
+/// replayed from an earlier handshake. The introduction is typically sealed
+/// without recipients, as the responder's document is not yet known, and
+/// the later messages to the peer's document.
+#[derive(Clone, Debug)]
+pub struct HandshakeInitiator {
+    own: XIDDocument,
+    id: ARID,
+    challenge: ARID,
+    accepted_schemes: Option<Vec<EncapsulationScheme>>,
+}
+
+impl HandshakeInitiator {
+    pub fn new(own: &XIDDocument) -> Self {
+        Self {
+            own: own.clone(),
+            id: ARID::new(),
+            challenge: ARID::new(),
+            accepted_schemes: None,
+        }
+    }
+
+    /// Restricts the encapsulation schemes the responder's key may use,
+    /// failing with [`Error::UnsupportedScheme`] otherwise. Any scheme is
+    /// accepted by default.
+    pub fn with_accepted_schemes(
+        mut self,
+        schemes: impl IntoIterator<Item = EncapsulationScheme>,
+    ) -> Self {
+        self.accepted_schemes = Some(schemes.into_iter().collect());
+        self
+    }
+
+    /// The ID of the introduction, which the acknowledgment answers.
+    pub fn id(&self) -> ARID { self.id }
+
+    pub fn challenge(&self) -> ARID { self.challenge }
+
+    /// The introduction request, carrying our document and challenge.
+    pub fn introduce(&self) -> SealedRequest {
+        SealedRequest::new(INTRODUCE, self.id, &self.own)
+            .with_parameter(CHALLENGE, self.challenge)
+            .with_parameter(VERSION, gstp_version::max_supported_version())
+    }
+
+    /// Checks the responder's acknowledgment of our introduction, returning
+    /// what we learned about the responder and the request that completes
+    /// the handshake.
+    ///
+    /// Fails with [`Error::ChallengeMismatch`] if the acknowledgment does not
+    /// answer our challenge.
+    pub fn complete(
+        &self,
+        ack: &SealedResponse,
+    ) -> Result<(PeerInfo, SealedRequest)> {
+        if ack.id() != Some(self.id) {
+            return Err(Error::ResponseIdMismatch {
+                expected: self.id,
+                found: ack.id(),
+            });
+        }
+        let result = ack.result()?;
+        let answer: ARID = result.extract_object_for_predicate(ANSWER)?;
+        if answer != self.challenge {
+            return Err(Error::ChallengeMismatch);
+        }
+        let challenge: ARID = result.extract_object_for_predicate(CHALLENGE)?;
+        let gstp_version: u32 = result.extract_object_for_predicate(VERSION)?;
+        let supported = gstp_version::max_supported_version();
+        if gstp_version > supported {
+            return Err(Error::UnsupportedGstpVersion {
+                found: gstp_version,
+                supported,
+            });
+        }
+        let peer = PeerInfo {
+            document: ack.sender().clone(),
+            gstp_version,
+            encapsulation_scheme: accepted_scheme(
+                ack.sender(),
+                self.accepted_schemes.as_deref(),
+            )?,
+        };
+        let complete =
+            SealedRequest::new(INTRODUCE_COMPLETE, ARID::new(), &self.own)
+                .with_parameter(ANSWER, challenge);
+        Ok((peer, complete))
+    }
+}
+
+/// The party that answers a handshake started by a [`HandshakeInitiator`],
+/// keeping the challenge it issued to each initiator until the handshake
+/// completes.
+#[derive(Clone, Debug)]
+pub struct HandshakeResponder {
+    own: XIDDocument,
+    accepted_schemes: Option<Vec<EncapsulationScheme>>,
+    pending: HashMap<XID, (ARID, PeerInfo)>,
+}
+
+impl HandshakeResponder {
+    pub fn new(own: &XIDDocument) -> Self {
+        Self {
+            own: own.clone(),
+            accepted_schemes: None,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Restricts the encapsulation schemes an initiator's key may use,
+    /// failing with [`Error::UnsupportedScheme`] otherwise. Any scheme is
+    /// accepted by default.
+    pub fn with_accepted_schemes(
+        mut self,
+        schemes: impl IntoIterator<Item = EncapsulationScheme>,
+    ) -> Self {
+        self.accepted_schemes = Some(schemes.into_iter().collect());
+        self
+    }
+
+    /// The number of handshakes acknowledged but not yet completed.
+    pub fn pending(&self) -> usize { self.pending.len() }
+
+    /// Checks an introduction, returning the acknowledgment that answers its
+    /// challenge with one of our own.
+    ///
+    /// A later introduction from the same initiator replaces the earlier.
+    pub fn acknowledge(
+        &mut self,
+        introduce: &SealedRequest,
+    ) -> Result<SealedResponse> {
+        check_function(introduce, INTRODUCE)?;
+        let answer: ARID = introduce.extract_object_for_parameter(CHALLENGE)?;
+        let version: u32 = introduce.extract_object_for_parameter(VERSION)?;
+        let peer = PeerInfo {
+            document: introduce.sender().clone(),
+            gstp_version: version.min(gstp_version::max_supported_version()),
+            encapsulation_scheme: accepted_scheme(
+                introduce.sender(),
+                self.accepted_schemes.as_deref(),
+            )?,
+        };
+        let challenge = ARID::new();
+        let result = Envelope::unit()
+            .add_assertion(ANSWER, answer)
+            .add_assertion(CHALLENGE, challenge)
+            .add_assertion(VERSION, peer.gstp_version);
+        self.pending.insert(peer.xid(), (challenge, peer));
+        Ok(SealedResponse::new_success(introduce.id(), &self.own)
+            .with_result(result)
+            .with_optional_peer_continuation(
+                introduce.peer_continuation().cloned(),
+            ))
+    }
+
+    /// Checks the request completing a handshake, returning what we learned
+    /// about the initiator.
+    ///
+    /// Fails with [`Error::NoPendingIntroduction`] if we acknowledged no
+    /// introduction from its sender, and with [`Error::ChallengeMismatch`] if
+    /// it does not answer our challenge.
+    pub fn complete(&mut self, complete: &SealedRequest) -> Result<PeerInfo> {
+        check_function(complete, INTRODUCE_COMPLETE)?;
+        let xid = complete.sender().xid();
+        let (challenge, _) = self
+            .pending
+            .get(&xid)
+            .ok_or(Error::NoPendingIntroduction(xid))?;
+        let answer: ARID = complete.extract_object_for_parameter(ANSWER)?;
+        if answer != *challenge {
+            return Err(Error::ChallengeMismatch);
+        }
+        let (_, peer) = self.pending.remove(&xid).unwrap();
+        Ok(peer)
+    }
+}
+
+fn check_function(request: &SealedRequest, expected: &str) -> Result<()> {
+    let expected = Function::from(expected);
+    if *request.function() != expected {
+        return Err(Error::UnexpectedFunction {
+            expected,
+            found: request.function().clone(),
+        });
+    }
+    Ok(())
+}
+
+/// The scheme of `peer`'s encryption key, if `accepted` allows it.
+fn accepted_scheme(
+    peer: &XIDDocument,
+    accepted: Option<&[EncapsulationScheme]>,
+) -> Result<EncapsulationScheme> {
+    let scheme = peer
+        .encryption_key()
+        .ok_or(Error::RecipientMissingEncryptionKey(peer.xid()))?
+        .encapsulation_scheme();
+    if accepted.is_some_and(|accepted| !accepted.contains(&scheme)) {
+        return Err(Error::UnsupportedScheme(scheme));
+    }
+    Ok(scheme)
+}
@@ -0,0 +1,112 @@
+//! Carrying sealed messages in HTTP bodies, and a client built on `reqwest`.
+//!
+//! A sealed message travels as the raw CBOR encoding of its envelope with
+//! the [`MEDIA_TYPE`] content type. [`to_body`] and [`from_body`] convert
+//! between the two for use with any HTTP library, while
+//! [`send_sealed_request`], with the `http` feature, does the whole exchange
+//! of a request for its response with a [`reqwest::Client`].
+//!
+//! Failures of the exchange itself are kept apart from failures to parse
+//! what came back: the connection failing is [`Error::Http`], the peer
+//! answering with a status other than success is [`Error::HttpStatus`], and
+//! a body of another content type is [`Error::UnexpectedContentType`].
+
+use bc_envelope::prelude::*;
+#[cfg(feature = "http")]
+pub use client::send_sealed_request;
+
+pub use crate::framing::MEDIA_TYPE;
+use crate::{Error, Result, codec};
+
+/// Checks that `content_type`, the value of a `Content-Type` header, is
+/// [`MEDIA_TYPE`], ignoring case and any parameters.
+///
+/// Fails with [`Error::UnexpectedContentType`] otherwise, including when
+/// there is no header at all.
+pub fn check_content_type(content_type: Option<&str>) -> Result<()> {
+    let matches = content_type.is_some_and(|value| {
+        let essence = value.split(';').next().unwrap_or_default().trim();
+        essence.eq_ignore_ascii_case(MEDIA_TYPE)
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedContentType(
+            content_type.map(str::to_owned),
+        ))
+    }
+}
+
+/// The body of an HTTP message carrying `envelope`, to be sent with the
+/// [`MEDIA_TYPE`] content type.
+pub fn to_body(envelope: &Envelope) -> Vec<u8> { codec::to_cbor(envelope) }
+
+/// Decodes the envelope carried in the body of an HTTP message whose
+/// `Content-Type` header is `content_type`.
+///
+/// Fails as [`check_content_type`] does for a content type other than
+/// [`MEDIA_TYPE`], and as [`codec::from_cbor`] does for a body that is not
+/// the encoding of a single envelope.
+pub fn from_body(content_type: Option<&str>, body: &[u8]) -> Result<Envelope> {
+    check_content_type(content_type)?;
+    codec::from_cbor(body)
+}
+
+#[cfg(feature = "http")]
+mod client {
+    use bc_components::PrivateKeys;
+    use bc_envelope::prelude::*;
+    use bc_xid::XIDDocument;
+    use reqwest::header::CONTENT_TYPE;
+
+    use super::{MEDIA_TYPE, from_body, to_body};
+    use crate::{Error, ParseOptions, Result, SealedRequest, SealedResponse};
+
+    /// Seals `request` from `sender` to `recipient`, posts it to `url`, and
+    /// parses the response.
+    ///
+    /// The response is parsed with `options`, additionally requiring it to
+    /// answer `request` and to be sent by `recipient`. A status other than
+    /// success fails with [`Error::HttpStatus`], carrying the body's envelope
+    /// if it has one, such as a sealed early failure that can be parsed
+    /// with [`SealedResponse::try_parse_early_failure`].
+    pub async fn send_sealed_request(
+        client: &reqwest::Client,
+        url: impl reqwest::IntoUrl,
+        request: &SealedRequest,
+        sender: &PrivateKeys,
+        recipient: &XIDDocument,
+        options: &ParseOptions,
+    ) -> Result<SealedResponse> {
+        let sealed =
+            request.to_envelope(None, Some(sender), Some(recipient))?;
+        let http_response = client
+            .post(url)
+            .header(CONTENT_TYPE, MEDIA_TYPE)
+            .body(to_body(&sealed))
+            .send()
+            .await?;
+        let status = http_response.status();
+        let content_type = http_response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = http_response.bytes().await?;
+        if !status.is_success() {
+            return Err(Error::HttpStatus {
+                status: status.as_u16(),
+                envelope: from_body(content_type.as_deref(), &body).ok(),
+            });
+        }
+        let envelope = from_body(content_type.as_deref(), &body)?;
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &envelope,
+            &options
+                .clone()
+                .with_expected_id(request.id())
+                .with_expected_sender(recipient),
+            sender,
+        )
+    }
+}
@@ -0,0 +1,146 @@
+//! Information that can be read from a sealed message without decrypting it.
+
+use bc_components::{
+    ARID, EncapsulationScheme, PrivateKeys, Reference, ReferenceProvider,
+};
+use bc_envelope::prelude::*;
+
+use crate::Result;
+
+/// The predicate of the cleartext hints naming each recipient's encryption
+/// key, added when sealing with
+/// [`SealOptions::with_recipient_hints`](crate::SealOptions::with_recipient_hints).
+pub const RECIPIENT_KEY: &str = "recipientKey";
+
+/// The predicate of the cleartext hint giving the expiry of a continuation,
+/// added to the continuations a sender issues when sealing with
+/// [`SealOptions::with_continuation_expiry_hints`](crate::SealOptions::with_continuation_expiry_hints).
+pub const CONTINUATION_EXPIRY_HINT: &str = "expiryHint";
+
+/// The predicate of the cleartext hint giving the time after which a sealed
+/// message is no longer worth delivering, added when sealing with
+/// [`SealOptions::with_transport_expiry_hints`](crate::SealOptions::with_transport_expiry_hints).
+pub const TRANSPORT_EXPIRY_HINT: &str = "transportExpiry";
+
+/// The predicate of the cleartext assertion naming the session a message is
+/// sealed with, added when sealing with
+/// [`SealOptions::with_session`](crate::SealOptions::with_session).
+pub const SESSION_ID: &str = "session";
+
+/// Returns the fingerprints of the encryption keys a sealed message claims to
+/// be encrypted to.
+///
+/// The fingerprints are only present if the sender chose to add them, and are
+/// not authenticated, so they can only be used to pick which key to try.
+pub fn recipient_key_fingerprints(
+    envelope: &Envelope,
+) -> Result<Vec<Reference>> {
+    envelope
+        .objects_for_predicate(RECIPIENT_KEY)
+        .into_iter()
+        .map(|object| Ok(object.extract_subject()?))
+        .collect()
+}
+
+/// Returns the encapsulation scheme of each recipient slot of a sealed
+/// message, one per recipient it is encrypted to.
+///
+/// Slots are kept in digest order, so they cannot be matched with the
+/// recipient hints.
+pub fn recipient_schemes(
+    envelope: &Envelope,
+) -> Result<Vec<EncapsulationScheme>> {
+    Ok(envelope
+        .recipients()?
+        .iter()
+        .map(|slot| slot.encapsulation_scheme())
+        .collect())
+}
+
+/// What a sealed message that could not be decrypted reveals about who it
+/// was encrypted to, reported when parsing with
+/// [`ParseOptions::with_decryption_diagnostics`](crate::ParseOptions::with_decryption_diagnostics).
+///
+/// Nothing here is secret: it is either in the clear on the message or
+/// derived from the public half of the key that was tried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecryptionDiagnostics {
+    /// The encapsulation scheme of each recipient slot.
+    pub slot_schemes: Vec<EncapsulationScheme>,
+    /// The fingerprints of the recipients' keys, if the sender added
+    /// recipient hints.
+    pub recipient_hints: Vec<Reference>,
+    /// The fingerprint of the encryption key that was tried.
+    pub attempted_key: Reference,
+    /// The encapsulation scheme of the encryption key that was tried.
+    pub attempted_scheme: EncapsulationScheme,
+}
+
+impl DecryptionDiagnostics {
+    pub(crate) fn new(
+        envelope: &Envelope,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let attempted = recipient.public_keys()?;
+        let attempted = attempted.enapsulation_public_key();
+        Ok(Self {
+            slot_schemes: recipient_schemes(envelope)?,
+            recipient_hints: recipient_key_fingerprints(envelope)?,
+            attempted_key: attempted.reference(),
+            attempted_scheme: attempted.encapsulation_scheme(),
+        })
+    }
+}
+
+impl std::fmt::Display for DecryptionDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tried {:?} key {}; {} recipient slot(s): {:?}",
+            self.attempted_scheme,
+            self.attempted_key.ref_hex_short(),
+            self.slot_schemes.len(),
+            self.slot_schemes,
+        )?;
+        if !self.recipient_hints.is_empty() {
+            let hints: Vec<String> = self
+                .recipient_hints
+                .iter()
+                .map(|hint| hint.ref_hex_short())
+                .collect();
+            write!(f, "; encrypted to keys {}", hints.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the expiry a continuation issued by a peer claims for itself, if
+/// the peer added one.
+///
+/// The continuation itself is opaque to its holder, so the hint cannot be
+/// checked against it. It is authenticated only by the signature of the
+/// message that carried it.
+pub fn continuation_expiry_hint(
+    continuation: &Envelope,
+) -> Result<Option<Date>> {
+    Ok(continuation
+        .extract_optional_object_for_predicate(CONTINUATION_EXPIRY_HINT)?)
+}
+
+/// Returns the ID of the session a sealed message is encrypted with, if it is
+/// sealed with a session key rather than to its recipients' public keys.
+pub fn session_id(envelope: &Envelope) -> Result<Option<ARID>> {
+    Ok(envelope.extract_optional_object_for_predicate(SESSION_ID)?)
+}
+
+/// Returns the time after which a sealed message claims it is no longer worth
+/// delivering, if the sender added a hint.
+///
+/// The hint is not authenticated, so it is only fit for deciding what to
+/// keep in a queue.
+pub fn transport_expiry_hint(envelope: &Envelope) -> Result<Option<Date>> {
+    Ok(
+        envelope
+            .extract_optional_object_for_predicate(TRANSPORT_EXPIRY_HINT)?,
+    )
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use bc_components::{Decrypter, PrivateKeys, Reference, ReferenceProvider};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result, inspect};
+
+/// A set of private keys, indexed by the fingerprint of their encryption key,
+/// for servers that receive messages on behalf of many identities.
+pub trait KeyDirectory {
+    fn lookup(&self, fingerprint: &Reference) -> Option<&PrivateKeys>;
+}
+
+/// A [`KeyDirectory`] held in memory.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryKeyDirectory {
+    keys: HashMap<Reference, PrivateKeys>,
+}
+
+impl MemoryKeyDirectory {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds `private_keys` to the directory, returning the fingerprint of
+    /// their encryption key.
+    pub fn insert(&mut self, private_keys: PrivateKeys) -> Result<Reference> {
+        let fingerprint = private_keys
+            .encapsulation_private_key()
+            .public_key()?
+            .reference();
+        self.keys.insert(fingerprint, private_keys);
+        Ok(fingerprint)
+    }
+}
+
+impl KeyDirectory for MemoryKeyDirectory {
+    fn lookup(&self, fingerprint: &Reference) -> Option<&PrivateKeys> {
+        self.keys.get(fingerprint)
+    }
+}
+
+/// Picks the private keys for a sealed message from `directory`, using the
+/// recipient hints carried by the message.
+pub(crate) fn select_recipient<'a>(
+    envelope: &Envelope,
+    directory: &'a dyn KeyDirectory,
+) -> Result<(Reference, &'a PrivateKeys)> {
+    inspect::recipient_key_fingerprints(envelope)?
+        .into_iter()
+        .find_map(|fingerprint| {
+            directory
+                .lookup(&fingerprint)
+                .map(|private_keys| (fingerprint, private_keys))
+        })
+        .ok_or(Error::NoRegisteredRecipientKey)
+}
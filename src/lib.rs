@@ -1,4 +1,4 @@
-#![doc(html_root_url = "https://docs.rs/gstp/0.13.0")]
+#![doc(html_root_url = "https://docs.rs/gstp/0.14.0")]
 #![warn(rust_2018_idioms)]
 
 //! # Introduction
@@ -20,7 +20,7 @@
 //!
 //! ```toml
 //! [dependencies]
-//! gstp = "0.13.0"
+//! gstp = "0.14.0"
 //! ```
 //!
 //! # Examples
@@ -30,17 +30,181 @@
 
 mod error;
 pub use error::{Error, Result};
+mod register;
+pub use register::{is_registered, register};
 mod continuation;
 pub use continuation::Continuation;
+mod typed_continuation;
+pub use typed_continuation::TypedContinuation;
+mod parse_limits;
+pub use parse_limits::{FieldLimits, ParseLimits};
+mod message_kind;
+pub use message_kind::MessageKind;
+mod continuation_storage;
+pub use continuation_storage::{
+    ContinuationStorage, MemoryContinuationStorage, PeerContinuationRef,
+    export_continuation, import_continuation,
+};
+mod continuation_metrics;
+pub use continuation_metrics::{
+    ContinuationMetrics, FunctionStats, MemoryContinuationMetrics,
+    RejectionReason,
+};
+mod continuation_policy;
+pub use continuation_policy::ContinuationPolicy;
+mod continuation_stack;
+pub use continuation_stack::ContinuationStack;
+mod continuation_filter;
+pub use continuation_filter::{
+    AssertionStripFilter, ContinuationContext, ContinuationFilter,
+};
+mod delegation;
+pub use delegation::{DelegationKey, DelegationKeyring};
+mod delivery;
+pub use delivery::{
+    DeliveryAttempt, record_delivery_attempt, verify_delivery_attempt,
+};
+mod extra_assertions;
+pub use extra_assertions::reserved_predicates;
+mod duplicate_assertions;
+pub use duplicate_assertions::{DuplicateAssertionPolicy, ParseWarning};
+mod early_failure;
+pub use early_failure::EarlyFailure;
+mod event_bus;
+pub use event_bus::{
+    DispatchReport, EventBus, EventContent, HandlerError, HandlerResult,
+    SequenceStatus, TopicFilter, event_topic,
+};
+mod key_directory;
+pub use key_directory::{KeyDirectory, MemoryKeyDirectory};
+mod load_shedding;
+pub use load_shedding::{
+    LoadShedDecision, LoadShedPolicy, LoadShedStats, OVERLOADED, SHUTTING_DOWN,
+    overloaded_response, overloaded_retry_after, shutting_down_continuation,
+    shutting_down_response, shutting_down_retry_after,
+};
+mod message_envelope;
+pub use message_envelope::{
+    SealedEventEnvelope, SealedRequestEnvelope, SealedResponseEnvelope,
+    observable_kind,
+};
+mod parse_options;
+pub use parse_options::ParseOptions;
+mod parse_policy;
+pub use parse_policy::ParsePolicy;
+mod sender_policy;
+pub use sender_policy::SenderPolicy;
+mod transcript;
+pub use transcript::Transcript;
+mod tofu;
+pub use tofu::{MemoryTofuStore, TofuStore};
+mod partial_parse;
+pub use partial_parse::{ParseStage, PartialParse};
+mod gstp_version;
+mod provenance;
+pub use provenance::Provenance;
+mod receipt;
+pub use receipt::{
+    ContinuationReceipt, SealedArtifacts, SealingReport, validate_echoed_state,
+};
+mod seal_options;
+pub use seal_options::{
+    ChunkingFallback, CompressionPolicy, ContinuationSealer, DatePrecision,
+    SealOptions, SenderDisclosure, ValidityOverflow,
+};
+mod sealed_parameter;
+pub use sealed_parameter::open_sealed_parameter;
 mod sealed_request;
+mod sealing;
+mod session;
 pub use sealed_request::{SealedRequest, SealedRequestBehavior};
+pub use session::{Session, SessionKeys};
+mod handshake;
+pub use handshake::{HandshakeInitiator, HandshakeResponder, PeerInfo};
 mod sealed_response;
 pub use sealed_response::{SealedResponse, SealedResponseBehavior};
 mod sealed_event;
 pub use sealed_event::{SealedEvent, SealedEventBehavior};
+mod anonymous_event;
+pub use anonymous_event::AnonymousEvent;
+mod recovery;
+pub use recovery::{
+    CONTINUATION_EXPIRED, RecoveryAdvisor, RecoveryHint,
+    continuation_expired_response, recovered_continuation,
+};
+mod state_lifetime;
+pub use state_lifetime::{REFRESH_STATE, StateLease, refresh_response};
+mod state_epochs;
+pub use state_epochs::StateEpochs;
+mod state_store;
+pub use state_store::{ContinuationMode, MemoryStateStore, StateStore};
+mod strictness;
+pub use strictness::{Strictness, set_strictness, strictness};
+mod replay;
+pub use replay::{
+    ContinuationReplayGuard, FileLogBackend, FsyncPolicy, KeyValueBackend,
+    MemoryBackend, MemoryReplayGuard, ReplayStore,
+};
+mod request_profile;
+pub use request_profile::RequestProfile;
+mod result_transform;
+pub use result_transform::{AssertionElideTransform, ResultTransform};
+mod result_chunks;
+pub use result_chunks::ResultAssembler;
+mod quick;
+pub use quick::{Identity, QUICK_REQUEST_VALIDITY, open_request, seal_request};
+mod pending;
+pub use pending::{
+    FilePendingStore, MemoryPendingStore, PendingRecord, PendingRequests,
+    PendingStore,
+};
+
+mod outbox;
+pub use outbox::{
+    DeadLetterReason, FileOutboxStore, MemoryOutboxStore, Outbox, OutboxEntry,
+    OutboxMetadata, OutboxStore,
+};
 
 pub mod prelude;
 
+pub mod consts;
+
+pub mod deps;
+
+pub mod conformance;
+
+pub mod codec;
+
+pub mod framing;
+
+pub mod qr;
+
+pub mod transport;
+
+pub mod inspect;
+
+pub mod lint;
+
+pub mod maintenance;
+
+#[cfg(feature = "serde")]
+pub mod export;
+
+#[cfg(any(feature = "http", feature = "axum"))]
+pub mod http;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
+#[cfg(feature = "service-adapter")]
+pub mod service;
+
+#[cfg(feature = "sskr")]
+pub mod sharding;
+
+#[cfg(feature = "taint-checks")]
+pub mod taint;
+
 #[cfg(test)]
 mod tests {
     #[test]
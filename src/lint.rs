@@ -0,0 +1,402 @@
+//! Checks that messages are composed following best practice, before they
+//! are sealed.
+//!
+//! The lints find nothing that would stop a message from being sealed or
+//! parsed, only what is likely to be a mistake, so they are meant to be run
+//! in integration tests or debug builds, for example by asserting that a
+//! lint finds nothing.
+
+use std::{collections::HashSet, time::Duration};
+
+use bc_envelope::prelude::*;
+
+use crate::{
+    FieldLimits, SealedEvent, SealedEventBehavior, SealedRequest,
+    SealedRequestBehavior, SealedResponse, SealedResponseBehavior,
+};
+
+/// How serious a [`LintFinding`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintSeverity {
+    Warning,
+    Error,
+}
+
+/// A best practice checked by the lints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// A request is not dated.
+    RequestMissingDate,
+    /// An event is not dated.
+    EventMissingDate,
+    /// A continuation will be issued without an expiry.
+    ContinuationMissingExpiry,
+    /// A continuation will be valid for longer than the configured maximum.
+    ContinuationExpiryTooLong,
+    /// A failure response gives no error, leaving the peer to guess what
+    /// went wrong.
+    FailureWithoutError,
+    /// An early failure carries state or a continuation, which sealing
+    /// rejects.
+    EarlyFailureCarriesContinuation,
+    /// A note matches one of the configured secret patterns.
+    NoteContainsSecret,
+    /// A note is longer than the field limits allow.
+    NoteTooLong,
+    /// State is larger than the field limits allow.
+    StateTooLarge,
+    /// A parameter value is larger than the field limits allow.
+    ParameterTooLarge,
+    /// A result is larger than the field limits allow.
+    ResultTooLarge,
+}
+
+impl LintRule {
+    /// Every rule, in the order they are documented.
+    pub const ALL: [LintRule; 11] = [
+        LintRule::RequestMissingDate,
+        LintRule::EventMissingDate,
+        LintRule::ContinuationMissingExpiry,
+        LintRule::ContinuationExpiryTooLong,
+        LintRule::FailureWithoutError,
+        LintRule::EarlyFailureCarriesContinuation,
+        LintRule::NoteContainsSecret,
+        LintRule::NoteTooLong,
+        LintRule::StateTooLarge,
+        LintRule::ParameterTooLarge,
+        LintRule::ResultTooLarge,
+    ];
+
+    /// A stable identifier for the rule, suitable for configuration files.
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::RequestMissingDate => "request-missing-date",
+            LintRule::EventMissingDate => "event-missing-date",
+            LintRule::ContinuationMissingExpiry => {
+                "continuation-missing-expiry"
+            }
+            LintRule::ContinuationExpiryTooLong => {
+                "continuation-expiry-too-long"
+            }
+            LintRule::FailureWithoutError => "failure-without-error",
+            LintRule::EarlyFailureCarriesContinuation => {
+                "early-failure-carries-continuation"
+            }
+            LintRule::NoteContainsSecret => "note-contains-secret",
+            LintRule::NoteTooLong => "note-too-long",
+            LintRule::StateTooLarge => "state-too-large",
+            LintRule::ParameterTooLarge => "parameter-too-large",
+            LintRule::ResultTooLarge => "result-too-large",
+        }
+    }
+
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            LintRule::EarlyFailureCarriesContinuation
+            | LintRule::NoteContainsSecret => LintSeverity::Error,
+            _ => LintSeverity::Warning,
+        }
+    }
+}
+
+/// A departure from best practice found by a lint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    pub severity: LintSeverity,
+    pub rule: LintRule,
+    pub message: String,
+    /// The part of the message the finding is about, such as `note` or
+    /// `parameter "amount"`.
+    pub location: String,
+}
+
+impl std::fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} [{}] {}: {}",
+            self.severity,
+            self.rule.id(),
+            self.location,
+            self.message
+        )
+    }
+}
+
+/// Which lints run, and their thresholds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintConfig {
+    disabled: HashSet<LintRule>,
+    field_limits: FieldLimits,
+    max_continuation_validity: Duration,
+    secret_patterns: Vec<String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            disabled: HashSet::new(),
+            field_limits: FieldLimits::default(),
+            max_continuation_validity: Duration::from_secs(24 * 60 * 60),
+            secret_patterns: [
+                "password",
+                "passwd",
+                "secret",
+                "api_key",
+                "apikey",
+                "private key",
+                "ur:crypto-prvkeys",
+                "ur:seed",
+                "bearer ",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn new() -> Self { Self::default() }
+
+    /// Enables or disables `rule`. All rules are enabled by default.
+    pub fn with_rule(mut self, rule: LintRule, enabled: bool) -> Self {
+        if enabled {
+            self.disabled.remove(&rule);
+        } else {
+            self.disabled.insert(rule);
+        }
+        self
+    }
+
+    /// Sets the limits that notes, state, parameters, and results are held
+    /// to.
+    pub fn with_field_limits(mut self, field_limits: FieldLimits) -> Self {
+        self.field_limits = field_limits;
+        self
+    }
+
+    /// Sets the longest a continuation may be valid for. Defaults to a day.
+    pub fn with_max_continuation_validity(
+        mut self,
+        validity: Duration,
+    ) -> Self {
+        self.max_continuation_validity = validity;
+        self
+    }
+
+    /// Adds a pattern that must not appear in notes, matched without regard
+    /// to case.
+    pub fn with_secret_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.secret_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    pub fn field_limits(&self) -> &FieldLimits { &self.field_limits }
+
+    pub fn max_continuation_validity(&self) -> Duration {
+        self.max_continuation_validity
+    }
+
+    pub fn secret_patterns(&self) -> &[String] { &self.secret_patterns }
+}
+
+/// Collects the findings of enabled rules.
+struct Linter<'a> {
+    config: &'a LintConfig,
+    findings: Vec<LintFinding>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(config: &'a LintConfig) -> Self {
+        Self { config, findings: Vec::new() }
+    }
+
+    fn report(
+        &mut self,
+        rule: LintRule,
+        location: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        if self.config.is_enabled(rule) {
+            self.findings.push(LintFinding {
+                severity: rule.severity(),
+                rule,
+                message: message.into(),
+                location: location.into(),
+            });
+        }
+    }
+
+    fn note(&mut self, note: &str) {
+        let lowercase = note.to_lowercase();
+        if let Some(pattern) = self
+            .config
+            .secret_patterns
+            .iter()
+            .find(|pattern| lowercase.contains(&pattern.to_lowercase()))
+        {
+            self.report(
+                LintRule::NoteContainsSecret,
+                "note",
+                format!("note matches the secret pattern {:?}", pattern),
+            );
+        }
+        let limit = self.config.field_limits.max_note_length();
+        if note.len() > limit {
+            self.report(
+                LintRule::NoteTooLong,
+                "note",
+                format!("note of {} bytes exceeds {} bytes", note.len(), limit),
+            );
+        }
+    }
+
+    fn size(
+        &mut self,
+        rule: LintRule,
+        location: impl Into<String>,
+        envelope: &Envelope,
+        limit: usize,
+    ) {
+        let size = envelope.to_cbor_data().len();
+        if size > limit {
+            self.report(
+                rule,
+                location,
+                format!("{} bytes exceeds {} bytes", size, limit),
+            );
+        }
+    }
+
+    fn state(&mut self, state: Option<&Envelope>) {
+        if let Some(state) = state {
+            let limit = self.config.field_limits.max_state_size();
+            self.size(LintRule::StateTooLarge, "state", state, limit);
+        }
+    }
+}
+
+/// Lints a request before it is sealed.
+pub fn lint_request(
+    request: &SealedRequest,
+    config: &LintConfig,
+) -> Vec<LintFinding> {
+    let mut linter = Linter::new(config);
+    if request.date().is_none() {
+        linter.report(
+            LintRule::RequestMissingDate,
+            "date",
+            "request is not dated",
+        );
+    }
+    linter.note(request.note());
+    linter.state(request.state());
+    let limit = config.field_limits.max_parameter_value_size();
+    for assertion in request.body().expression_envelope().assertions() {
+        let (Ok(predicate), Ok(object)) =
+            (assertion.try_predicate(), assertion.try_object())
+        else {
+            continue;
+        };
+        let location = predicate
+            .try_leaf()
+            .ok()
+            .and_then(|leaf| Parameter::try_from(leaf).ok())
+            .map_or_else(
+                || "parameter".to_string(),
+                |parameter| format!("parameter {}", parameter.name()),
+            );
+        linter.size(LintRule::ParameterTooLarge, location, &object, limit);
+    }
+    linter.findings
+}
+
+/// Lints a response before it is sealed.
+pub fn lint_response(
+    response: &SealedResponse,
+    config: &LintConfig,
+) -> Vec<LintFinding> {
+    let mut linter = Linter::new(config);
+    if response.is_early_failure()
+        && (response.state().is_some()
+            || response.peer_continuation().is_some())
+    {
+        linter.report(
+            LintRule::EarlyFailureCarriesContinuation,
+            "state",
+            "early failure carries state or a continuation",
+        );
+    }
+    if let Ok(error) = response.error()
+        && error.is_identical_to(&Envelope::unknown())
+    {
+        linter.report(
+            LintRule::FailureWithoutError,
+            "error",
+            "failure response gives no error",
+        );
+    }
+    linter.state(response.state());
+    if let Ok(result) = response.result() {
+        let limit = config.field_limits.max_result_size();
+        linter.size(LintRule::ResultTooLarge, "result", result, limit);
+    }
+    linter.findings
+}
+
+/// Lints an event before it is sealed.
+pub fn lint_event<T>(
+    event: &SealedEvent<T>,
+    config: &LintConfig,
+) -> Vec<LintFinding>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    let mut linter = Linter::new(config);
+    if event.date().is_none() {
+        linter.report(LintRule::EventMissingDate, "date", "event is not dated");
+    }
+    linter.note(event.note());
+    linter.state(event.state());
+    linter.findings
+}
+
+/// Lints the `valid_until` a message is about to be sealed with, which
+/// becomes the expiry of the continuation issued with it.
+pub fn lint_valid_until(
+    valid_until: Option<Date>,
+    now: Date,
+    config: &LintConfig,
+) -> Vec<LintFinding> {
+    let mut linter = Linter::new(config);
+    match valid_until {
+        None => linter.report(
+            LintRule::ContinuationMissingExpiry,
+            "validUntil",
+            "continuation has no expiry",
+        ),
+        Some(valid_until)
+            if valid_until > now + config.max_continuation_validity =>
+        {
+            linter.report(
+                LintRule::ContinuationExpiryTooLong,
+                "validUntil",
+                format!(
+                    "continuation is valid for longer than {:?}",
+                    config.max_continuation_validity
+                ),
+            )
+        }
+        Some(_) => {}
+    }
+    linter.findings
+}
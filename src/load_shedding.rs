@@ -0,0 +1,233 @@
+//! Shedding load without abandoning work in progress.
+//!
+//! Under pressure, a server turns away requests that would start new work
+//! before requests that return a continuation, which are part of a workflow
+//! already under way. A [`LoadShedPolicy`] is evaluated once a request has
+//! been parsed and before it is handled, and a request it sheds is answered
+//! with [`overloaded_response`]. A server that is shutting down answers with
+//! [`shutting_down_response`] instead.
+
+use std::{sync::Mutex, time::Duration};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, Result, SealOptions, SealedRequest, SealedRequestBehavior,
+    SealedResponse, sealing,
+};
+
+/// The error a failure response carries when the server shed the request.
+pub const OVERLOADED: &str = "overloaded";
+
+/// The error a failure response carries when the server is shutting down.
+pub const SHUTTING_DOWN: &str = "shuttingDown";
+
+const RETRY_AFTER: &str = "retryAfter";
+const CONTINUATION: &str = "continuation";
+
+/// Whether a request is handled or shed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadShedDecision {
+    Admit,
+    /// The request should be answered with [`overloaded_response`], asking
+    /// the client to retry after the given delay.
+    Shed {
+        retry_after: Duration,
+    },
+}
+
+/// The thresholds of a [`LoadShedPolicy`] and the decisions it has made.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadShedStats {
+    pub fresh_threshold: f64,
+    pub continuing_threshold: f64,
+    /// The pressure when the last decision was made.
+    pub last_pressure: f64,
+    pub admitted_fresh: u64,
+    pub admitted_continuing: u64,
+    pub shed_fresh: u64,
+    pub shed_continuing: u64,
+}
+
+type Pressure = Box<dyn Fn() -> f64 + Send + Sync>;
+
+/// Decides which requests to shed from the current pressure on the server,
+/// a value from 0.0 (idle) to 1.0 (saturated).
+///
+/// Requests that return no continuation start new work and are shed at or
+/// above the fresh threshold. Requests that return a continuation are shed
+/// only at or above the higher continuing threshold.
+pub struct LoadShedPolicy {
+    pressure: Pressure,
+    retry_after: Duration,
+    stats: Mutex<LoadShedStats>,
+}
+
+impl std::fmt::Debug for LoadShedPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadShedPolicy")
+            .field("retry_after", &self.retry_after)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoadShedPolicy {
+    /// Creates a policy reading the pressure from `pressure`, shedding fresh
+    /// requests from 0.8 and continuing ones from 0.95, and asking clients
+    /// to retry after five seconds.
+    pub fn new(pressure: impl Fn() -> f64 + Send + Sync + 'static) -> Self {
+        Self {
+            pressure: Box::new(pressure),
+            retry_after: Duration::from_secs(5),
+            stats: Mutex::new(LoadShedStats {
+                fresh_threshold: 0.8,
+                continuing_threshold: 0.95,
+                ..LoadShedStats::default()
+            }),
+        }
+    }
+
+    /// Sets the pressures from which fresh and continuing requests are shed.
+    /// The continuing threshold is raised to the fresh one if it is lower.
+    pub fn with_thresholds(self, fresh: f64, continuing: f64) -> Self {
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.fresh_threshold = fresh;
+            stats.continuing_threshold = continuing.max(fresh);
+        }
+        self
+    }
+
+    /// Sets how long a shed client is asked to wait before retrying.
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Decides whether to handle `request`, which continues a workflow if it
+    /// returned a continuation to us.
+    pub fn evaluate(&self, request: &SealedRequest) -> LoadShedDecision {
+        self.decide(request.state().is_some())
+    }
+
+    /// Decides whether to handle a request, given whether it continues a
+    /// workflow.
+    pub fn decide(&self, continuing: bool) -> LoadShedDecision {
+        let pressure = (self.pressure)();
+        let mut stats = self.stats.lock().unwrap();
+        stats.last_pressure = pressure;
+        let threshold = if continuing {
+            stats.continuing_threshold
+        } else {
+            stats.fresh_threshold
+        };
+        let shed = pressure >= threshold;
+        let counter = match (continuing, shed) {
+            (false, false) => &mut stats.admitted_fresh,
+            (true, false) => &mut stats.admitted_continuing,
+            (false, true) => &mut stats.shed_fresh,
+            (true, true) => &mut stats.shed_continuing,
+        };
+        *counter += 1;
+        if shed {
+            LoadShedDecision::Shed { retry_after: self.retry_after }
+        } else {
+            LoadShedDecision::Admit
+        }
+    }
+
+    /// Returns the thresholds and the decisions made so far.
+    pub fn stats(&self) -> LoadShedStats { self.stats.lock().unwrap().clone() }
+}
+
+fn retry_response(
+    reason: &str,
+    request_id: ARID,
+    sender: &XIDDocument,
+    retry_after: Duration,
+) -> SealedResponse {
+    SealedResponse::new_failure(request_id, sender).with_error(
+        Envelope::new(reason).add_assertion(RETRY_AFTER, retry_after.as_secs()),
+    )
+}
+
+fn retry_after(response: &SealedResponse, reason: &str) -> Option<Duration> {
+    let error = response.error().ok()?;
+    if error.extract_subject::<String>().ok().as_deref() != Some(reason) {
+        return None;
+    }
+    error
+        .extract_object_for_predicate::<u64>(RETRY_AFTER)
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Composes the failure response to a request that was shed, asking the
+/// client to retry after `retry_after`, in whole seconds.
+pub fn overloaded_response(
+    request_id: ARID,
+    sender: &XIDDocument,
+    retry_after: Duration,
+) -> SealedResponse {
+    retry_response(OVERLOADED, request_id, sender, retry_after)
+}
+
+/// Returns how long the server asked us to wait before retrying, if
+/// `response` is a failure because the request was shed.
+pub fn overloaded_retry_after(response: &SealedResponse) -> Option<Duration> {
+    retry_after(response, OVERLOADED)
+}
+
+/// Composes the failure response to `request`, received while shutting down,
+/// asking the client to retry after `retry_after`, in whole seconds.
+///
+/// If the request returned state to us, the error carries it in a fresh
+/// continuation, encrypted like any continuation we issue, so that the client
+/// can resume the workflow once the server is back.
+pub fn shutting_down_response(
+    request: &SealedRequest,
+    sender: &XIDDocument,
+    retry_after: Duration,
+    options: &SealOptions,
+) -> Result<SealedResponse> {
+    let response =
+        retry_response(SHUTTING_DOWN, request.id(), sender, retry_after);
+    let Some(state) = request.state() else {
+        return Ok(response);
+    };
+    let continuation = sealing::issue_continuation(
+        &Continuation::new(state.clone())
+            .with_issued_at(options.sealing_date()),
+        sender,
+        request.id(),
+        Some(request.function()),
+        options,
+    )?;
+    let error = response.error()?.add_assertion(CONTINUATION, continuation);
+    Ok(response.with_error(error))
+}
+
+/// Returns how long the server asked us to wait before retrying, if
+/// `response` is a failure because the server was shutting down.
+pub fn shutting_down_retry_after(
+    response: &SealedResponse,
+) -> Option<Duration> {
+    retry_after(response, SHUTTING_DOWN)
+}
+
+/// Returns the continuation to return with the retried request, if
+/// `response` is a failure because the server was shutting down and the
+/// request had returned state to it.
+pub fn shutting_down_continuation(
+    response: &SealedResponse,
+) -> Option<Envelope> {
+    retry_after(response, SHUTTING_DOWN)?;
+    response
+        .error()
+        .ok()?
+        .object_for_predicate(CONTINUATION)
+        .ok()
+}
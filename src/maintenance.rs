@@ -0,0 +1,155 @@
+//! Housekeeping for queues of sealed messages, done without any private keys.
+
+use std::time::Duration;
+
+use bc_envelope::prelude::*;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::inspect;
+
+/// What [`sweep`] does with a message that carries no transport expiry hint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnhintedPolicy {
+    /// The message is reported as unknown and kept.
+    #[default]
+    Keep,
+
+    /// The message is reported as expired once it has been queued for the
+    /// given duration, and as unknown until then.
+    ExpireAfter(Duration),
+}
+
+/// Decides which queued messages [`sweep`] reports as expired.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SweepPolicy {
+    max_age: Option<Duration>,
+    unhinted: UnhintedPolicy,
+}
+
+impl SweepPolicy {
+    pub fn new() -> Self { Self::default() }
+
+    /// Expires every message queued for longer than `max_age`, whatever its
+    /// hint says.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets what happens to messages that carry no transport expiry hint.
+    pub fn with_unhinted(mut self, unhinted: UnhintedPolicy) -> Self {
+        self.unhinted = unhinted;
+        self
+    }
+
+    pub fn max_age(&self) -> Option<Duration> { self.max_age }
+
+    pub fn unhinted(&self) -> UnhintedPolicy { self.unhinted }
+}
+
+/// The IDs of swept messages, partitioned by their fate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SweepReport<Id> {
+    /// Messages that are still worth delivering.
+    pub deliverable: Vec<Id>,
+    /// Messages that should be discarded.
+    pub expired: Vec<Id>,
+    /// Messages without a usable transport expiry hint that the policy keeps.
+    pub unknown: Vec<Id>,
+}
+
+impl<Id> Default for SweepReport<Id> {
+    fn default() -> Self {
+        Self {
+            deliverable: Vec::new(),
+            expired: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+}
+
+/// Partitions queued messages into those that are deliverable, expired, or
+/// of unknown lifetime, from their cleartext transport expiry hints, see
+/// [`inspect::transport_expiry_hint`], and the time each was queued.
+///
+/// Each message is given with the caller's ID for it and the time it was
+/// added to the queue. A hint that cannot be read is treated as absent.
+pub fn sweep<Id>(
+    messages: impl IntoIterator<Item = (Id, Envelope, Date)>,
+    now: Date,
+    policy: &SweepPolicy,
+) -> SweepReport<Id> {
+    let mut report = SweepReport::default();
+    for (id, envelope, queued_at) in messages {
+        report.add(id, classify(&envelope, queued_at, now, policy));
+    }
+    report
+}
+
+/// Like [`sweep`], but reads the hints of the messages in parallel on the
+/// rayon thread pool. The IDs in each list keep the order the messages were
+/// given in.
+#[cfg(feature = "rayon")]
+pub fn par_sweep<Id>(
+    messages: impl IntoParallelIterator<Item = (Id, Envelope, Date)>,
+    now: Date,
+    policy: &SweepPolicy,
+) -> SweepReport<Id>
+where
+    Id: Send,
+{
+    let fates: Vec<(Id, Fate)> = messages
+        .into_par_iter()
+        .map(|(id, envelope, queued_at)| {
+            (id, classify(&envelope, queued_at, now, policy))
+        })
+        .collect();
+    let mut report = SweepReport::default();
+    for (id, fate) in fates {
+        report.add(id, fate);
+    }
+    report
+}
+
+/// Which list of a [`SweepReport`] a message belongs in.
+enum Fate {
+    Deliverable,
+    Expired,
+    Unknown,
+}
+
+impl<Id> SweepReport<Id> {
+    fn add(&mut self, id: Id, fate: Fate) {
+        match fate {
+            Fate::Deliverable => self.deliverable.push(id),
+            Fate::Expired => self.expired.push(id),
+            Fate::Unknown => self.unknown.push(id),
+        }
+    }
+}
+
+fn classify(
+    envelope: &Envelope,
+    queued_at: Date,
+    now: Date,
+    policy: &SweepPolicy,
+) -> Fate {
+    let too_old = |max_age: Duration| queued_at + max_age <= now;
+    let hint = inspect::transport_expiry_hint(envelope).ok().flatten();
+    let expired = policy.max_age.is_some_and(too_old)
+        || match hint {
+            Some(hint) => hint <= now,
+            None => match policy.unhinted {
+                UnhintedPolicy::Keep => false,
+                UnhintedPolicy::ExpireAfter(max_age) => too_old(max_age),
+            },
+        };
+    if expired {
+        Fate::Expired
+    } else if hint.is_some() {
+        Fate::Deliverable
+    } else {
+        Fate::Unknown
+    }
+}
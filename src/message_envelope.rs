@@ -0,0 +1,120 @@
+use bc_components::tags::{TAG_EVENT, TAG_REQUEST, TAG_RESPONSE};
+use bc_envelope::prelude::*;
+
+use crate::{Error, MessageKind, Result};
+
+/// Returns the kind of message carried by `envelope`, if it can be observed
+/// without decrypting it.
+///
+/// The kind of a message that is encrypted to its recipients is hidden, so
+/// `Ok(None)` is returned for it. Signed and unsigned messages are inspected,
+/// and an error is returned if they are not a GSTP message at all.
+pub fn observable_kind(envelope: &Envelope) -> Result<Option<MessageKind>> {
+    if envelope.subject().is_encrypted() {
+        return Ok(None);
+    }
+    let message = if envelope.subject().is_wrapped() {
+        envelope.try_unwrap()?
+    } else {
+        envelope.clone()
+    };
+    let kind = message
+        .subject()
+        .as_leaf()
+        .and_then(|leaf| {
+            leaf.as_tagged_value()
+                .and_then(|(tag, _)| match tag.value() {
+                    TAG_REQUEST => Some(MessageKind::Request),
+                    TAG_RESPONSE => Some(MessageKind::Response),
+                    TAG_EVENT => Some(MessageKind::Event),
+                    _ => None,
+                })
+        })
+        .ok_or(Error::UnknownMessageKind)?;
+    Ok(Some(kind))
+}
+
+/// Fails with [`Error::MessageKindMismatch`] if `envelope` observably carries
+/// a kind of message other than `expected`.
+pub(crate) fn check_kind(
+    envelope: &Envelope,
+    expected: MessageKind,
+) -> Result<()> {
+    match observable_kind(envelope)? {
+        Some(found) if found != expected => {
+            Err(Error::MessageKindMismatch { expected, found })
+        }
+        _ => Ok(()),
+    }
+}
+
+macro_rules! message_envelope {
+    ($(#[$meta:meta])* $name:ident, $kind:expr) => {
+        $(#[$meta])*
+        ///
+        /// Converting an [`Envelope`] with [`TryFrom`] checks the kind of the
+        /// message wherever it is observable; see [`observable_kind`]. The
+        /// kind of an encrypted message is checked when it is parsed.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct $name(Envelope);
+
+        impl $name {
+            /// The kind of message this envelope carries.
+            pub const KIND: MessageKind = $kind;
+
+            pub(crate) fn new_unchecked(envelope: Envelope) -> Self {
+                Self(envelope)
+            }
+
+            pub fn envelope(&self) -> &Envelope { &self.0 }
+
+            pub fn ur_string(&self) -> String { self.0.ur_string() }
+
+            /// Decodes an envelope UR, checking the kind of the message it
+            /// carries.
+            ///
+            /// Fails with [`Error::InvalidUR`] if the string is not an
+            /// envelope UR.
+            pub fn from_ur_string(ur_string: impl Into<String>) -> Result<Self> {
+                Envelope::from_ur_string(ur_string)
+                    .map_err(Error::InvalidUR)?
+                    .try_into()
+            }
+        }
+
+        impl TryFrom<Envelope> for $name {
+            type Error = Error;
+
+            fn try_from(envelope: Envelope) -> Result<Self> {
+                check_kind(&envelope, Self::KIND)?;
+                Ok(Self(envelope))
+            }
+        }
+
+        impl From<$name> for Envelope {
+            fn from(envelope: $name) -> Self { envelope.0 }
+        }
+
+        impl AsRef<Envelope> for $name {
+            fn as_ref(&self) -> &Envelope { &self.0 }
+        }
+    };
+}
+
+message_envelope!(
+    /// An envelope known to carry a sealed request.
+    SealedRequestEnvelope,
+    MessageKind::Request
+);
+
+message_envelope!(
+    /// An envelope known to carry a sealed response.
+    SealedResponseEnvelope,
+    MessageKind::Response
+);
+
+message_envelope!(
+    /// An envelope known to carry a sealed event.
+    SealedEventEnvelope,
+    MessageKind::Event
+);
@@ -0,0 +1,17 @@
+/// The kind of a GSTP message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Request,
+    Response,
+    Event,
+}
+
+impl std::fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageKind::Request => write!(f, "request"),
+            MessageKind::Response => write!(f, "response"),
+            MessageKind::Event => write!(f, "event"),
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bc_components::{ARID, XID};
+use bc_envelope::{Signer, prelude::*};
+
+use crate::{
+    DeliveryAttempt, Error, MessageKind, Result, SealedArtifacts,
+    record_delivery_attempt,
+};
+
+const KIND: &str = "kind";
+const MESSAGE: &str = "message";
+const CONTINUATION_EXPIRY: &str = "continuationExpiry";
+const DEADLINE: &str = "deadline";
+const RECIPIENT: &str = "recipient";
+const ATTEMPTS: &str = "attempts";
+const NEXT_ATTEMPT: &str = "nextAttempt";
+const DEAD_LETTER: &str = "deadLetter";
+
+/// When a sealed message stops being worth transmitting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxMetadata {
+    /// The time after which the continuation sealed into the message is no
+    /// longer valid. This is the `valid_until` the message was sealed with.
+    pub continuation_expiry: Option<Date>,
+    /// The time after which the recipient no longer wants the message.
+    pub deadline: Option<Date>,
+    /// The XID of the recipient.
+    pub recipient: XID,
+}
+
+impl OutboxMetadata {
+    pub fn new(recipient: XID) -> Self {
+        Self {
+            continuation_expiry: None,
+            deadline: None,
+            recipient,
+        }
+    }
+
+    /// The metadata of a message sealed to `recipient`, whose continuation
+    /// expiry is taken from the receipt of the continuation sealed into it.
+    pub fn from_artifacts(recipient: XID, artifacts: &SealedArtifacts) -> Self {
+        Self::new(recipient).with_continuation_expiry(
+            artifacts
+                .continuation_receipt
+                .as_ref()
+                .and_then(|receipt| receipt.valid_until),
+        )
+    }
+
+    pub fn with_continuation_expiry(mut self, expiry: Option<Date>) -> Self {
+        self.continuation_expiry = expiry;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Option<Date>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+}
+
+/// Why an outbox entry was given up on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The continuation sealed into the message expired.
+    ContinuationExpired,
+    /// The recipient's deadline passed.
+    DeadlinePassed,
+}
+
+impl DeadLetterReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeadLetterReason::ContinuationExpired => "continuationExpired",
+            DeadLetterReason::DeadlinePassed => "deadlinePassed",
+        }
+    }
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "continuationExpired" => Ok(DeadLetterReason::ContinuationExpired),
+            "deadlinePassed" => Ok(DeadLetterReason::DeadlinePassed),
+            _ => Err(Error::InvalidOutboxEntry),
+        }
+    }
+}
+
+/// A sealed message waiting to be transmitted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxEntry {
+    /// The ID of the entry within the outbox.
+    pub id: ARID,
+    /// The kind of the sealed message.
+    pub kind: MessageKind,
+    /// The sealed message, ready to transmit.
+    pub envelope: Envelope,
+    pub metadata: OutboxMetadata,
+    /// The number of failed transmission attempts so far.
+    pub attempts: u32,
+    /// The earliest time the next transmission should be attempted.
+    pub next_attempt: Date,
+    /// Why the entry was dead-lettered, if it was.
+    pub dead_letter: Option<DeadLetterReason>,
+}
+
+impl OutboxEntry {
+    fn to_envelope(&self) -> Envelope {
+        Envelope::new(self.id)
+            .add_assertion(KIND, self.kind.to_string())
+            .add_assertion(MESSAGE, self.envelope.clone())
+            .add_optional_assertion(
+                CONTINUATION_EXPIRY,
+                self.metadata.continuation_expiry,
+            )
+            .add_optional_assertion(DEADLINE, self.metadata.deadline)
+            .add_assertion(RECIPIENT, self.metadata.recipient)
+            .add_assertion(ATTEMPTS, self.attempts)
+            .add_assertion(NEXT_ATTEMPT, self.next_attempt)
+            .add_optional_assertion(
+                DEAD_LETTER,
+                self.dead_letter.map(|reason| reason.as_str()),
+            )
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<Self> {
+        let kind = match envelope
+            .extract_object_for_predicate::<String>(KIND)?
+            .as_str()
+        {
+            "request" => MessageKind::Request,
+            "response" => MessageKind::Response,
+            "event" => MessageKind::Event,
+            _ => return Err(Error::InvalidOutboxEntry),
+        };
+        let dead_letter = envelope
+            .extract_optional_object_for_predicate::<String>(DEAD_LETTER)?
+            .map(|reason| DeadLetterReason::from_str(&reason))
+            .transpose()?;
+        Ok(Self {
+            id: envelope.extract_subject()?,
+            kind,
+            envelope: envelope.object_for_predicate(MESSAGE)?,
+            metadata: OutboxMetadata {
+                continuation_expiry: envelope
+                    .extract_optional_object_for_predicate(
+                        CONTINUATION_EXPIRY,
+                    )?,
+                deadline: envelope
+                    .extract_optional_object_for_predicate(DEADLINE)?,
+                recipient: envelope.extract_object_for_predicate(RECIPIENT)?,
+            },
+            attempts: envelope.extract_object_for_predicate(ATTEMPTS)?,
+            next_attempt: envelope
+                .extract_object_for_predicate(NEXT_ATTEMPT)?,
+            dead_letter,
+        })
+    }
+
+    fn expiry_reason(&self, now: Date) -> Option<DeadLetterReason> {
+        if self
+            .metadata
+            .continuation_expiry
+            .is_some_and(|expiry| expiry <= now)
+        {
+            Some(DeadLetterReason::ContinuationExpired)
+        } else if self
+            .metadata
+            .deadline
+            .is_some_and(|deadline| deadline <= now)
+        {
+            Some(DeadLetterReason::DeadlinePassed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Storage for outbox entries, including dead-lettered ones.
+pub trait OutboxStore {
+    /// Stores an entry, replacing any previous entry with the same ID.
+    fn put(&mut self, entry: OutboxEntry) -> Result<()>;
+
+    /// Removes and returns an entry, if there is one.
+    fn take(&mut self, id: ARID) -> Result<Option<OutboxEntry>>;
+
+    /// Applies `update` to an entry in place, returning `false` if there is
+    /// no entry with that ID. The entry is left as it was if the update
+    /// cannot be stored.
+    fn update(
+        &mut self,
+        id: ARID,
+        update: &mut dyn FnMut(&mut OutboxEntry),
+    ) -> Result<bool>;
+
+    /// Returns all entries.
+    fn entries(&self) -> Result<Vec<OutboxEntry>>;
+}
+
+/// An [`OutboxStore`] that lives only as long as the process.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryOutboxStore {
+    entries: HashMap<ARID, OutboxEntry>,
+}
+
+impl MemoryOutboxStore {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl OutboxStore for MemoryOutboxStore {
+    fn put(&mut self, entry: OutboxEntry) -> Result<()> {
+        self.entries.insert(entry.id, entry);
+        Ok(())
+    }
+
+    fn take(&mut self, id: ARID) -> Result<Option<OutboxEntry>> {
+        Ok(self.entries.remove(&id))
+    }
+
+    fn update(
+        &mut self,
+        id: ARID,
+        update: &mut dyn FnMut(&mut OutboxEntry),
+    ) -> Result<bool> {
+        let Some(entry) = self.entries.get_mut(&id) else {
+            return Ok(false);
+        };
+        update(entry);
+        Ok(true)
+    }
+
+    fn entries(&self) -> Result<Vec<OutboxEntry>> {
+        Ok(self.entries.values().cloned().collect())
+    }
+}
+
+/// An [`OutboxStore`] persisted to a file as a CBOR array of envelopes.
+///
+/// The whole file is rewritten after every change, via a temporary file that
+/// is renamed into place.
+#[derive(Debug)]
+pub struct FileOutboxStore {
+    path: PathBuf,
+    entries: MemoryOutboxStore,
+}
+
+impl FileOutboxStore {
+    /// Opens the store at `path`, creating an empty one if the file does not
+    /// exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = MemoryOutboxStore::new();
+        if path.exists() {
+            let cbor = CBOR::try_from_data(fs::read(&path)?)?;
+            for item in cbor.try_into_array()? {
+                let envelope = Envelope::try_from(item)?;
+                entries.put(OutboxEntry::try_from_envelope(&envelope)?)?;
+            }
+        }
+        Ok(Self { path, entries })
+    }
+
+    fn save(&self) -> Result<()> {
+        let items: Vec<CBOR> = self
+            .entries
+            .entries
+            .values()
+            .map(|entry| entry.to_envelope().to_cbor())
+            .collect();
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, CBOR::from(items).to_cbor_data())?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl OutboxStore for FileOutboxStore {
+    fn put(&mut self, entry: OutboxEntry) -> Result<()> {
+        self.entries.put(entry)?;
+        self.save()
+    }
+
+    fn take(&mut self, id: ARID) -> Result<Option<OutboxEntry>> {
+        let entry = self.entries.take(id)?;
+        if entry.is_some() {
+            self.save()?;
+        }
+        Ok(entry)
+    }
+
+    fn update(
+        &mut self,
+        id: ARID,
+        update: &mut dyn FnMut(&mut OutboxEntry),
+    ) -> Result<bool> {
+        let Some(previous) = self.entries.entries.get(&id).cloned() else {
+            return Ok(false);
+        };
+        self.entries.update(id, update)?;
+        if let Err(error) = self.save() {
+            self.entries.put(previous)?;
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    fn entries(&self) -> Result<Vec<OutboxEntry>> { self.entries.entries() }
+}
+
+/// Queues sealed messages for transmission, retrying failures with
+/// exponential backoff.
+///
+/// Entries whose continuation has expired or whose deadline has passed are
+/// dead-lettered rather than transmitted, since a late retry is at best
+/// wasted and at worst harmful.
+#[derive(Debug)]
+pub struct Outbox<S: OutboxStore> {
+    store: S,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<S: OutboxStore> Outbox<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+        }
+    }
+
+    /// Sets the delay after the first failure, which doubles after each
+    /// further failure up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn store(&self) -> &S { &self.store }
+
+    pub fn into_store(self) -> S { self.store }
+
+    /// The delay before retrying after `attempts` failed attempts.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        backoff(self.initial_backoff, self.max_backoff, attempts)
+    }
+
+    /// Queues a sealed message for transmission as soon as possible, returning
+    /// the ID of its entry.
+    pub fn enqueue(
+        &mut self,
+        kind: MessageKind,
+        envelope: Envelope,
+        metadata: OutboxMetadata,
+        now: Date,
+    ) -> Result<ARID> {
+        let id = ARID::new();
+        self.store.put(OutboxEntry {
+            id,
+            kind,
+            envelope,
+            metadata,
+            attempts: 0,
+            next_attempt: now,
+            dead_letter: None,
+        })?;
+        Ok(id)
+    }
+
+    /// Returns the entry most overdue for transmission, if any is due.
+    ///
+    /// Entries that have expired by `now` are dead-lettered first.
+    pub fn next_due(&mut self, now: Date) -> Result<Option<OutboxEntry>> {
+        let mut due: Option<OutboxEntry> = None;
+        for mut entry in self.store.entries()? {
+            if entry.dead_letter.is_some() {
+                continue;
+            }
+            if let Some(reason) = entry.expiry_reason(now) {
+                entry.dead_letter = Some(reason);
+                self.store.put(entry)?;
+                continue;
+            }
+            if entry.next_attempt <= now
+                && due
+                    .as_ref()
+                    .is_none_or(|due| entry.next_attempt < due.next_attempt)
+            {
+                due = Some(entry);
+            }
+        }
+        Ok(due)
+    }
+
+    /// Removes an entry that was transmitted successfully.
+    pub fn mark_sent(&mut self, id: ARID) -> Result<()> {
+        self.store.take(id)?.ok_or(Error::UnknownOutboxEntry(id))?;
+        Ok(())
+    }
+
+    /// Removes an entry that was transmitted successfully over `transport`,
+    /// returning a record of the attempt signed by `sender`.
+    ///
+    /// See [`record_delivery_attempt`].
+    pub fn mark_sent_with_record(
+        &mut self,
+        id: ARID,
+        transport: impl Into<String>,
+        acknowledgment: Option<ByteString>,
+        sender: &dyn Signer,
+        now: Date,
+    ) -> Result<Envelope> {
+        let entry =
+            self.store.take(id)?.ok_or(Error::UnknownOutboxEntry(id))?;
+        let mut attempt = DeliveryAttempt::new(
+            &entry.envelope,
+            entry.metadata.recipient,
+            transport,
+            now,
+        );
+        attempt.acknowledgment = acknowledgment;
+        Ok(record_delivery_attempt(&attempt, sender))
+    }
+
+    /// Records a failed transmission, scheduling the next attempt.
+    pub fn mark_failed(&mut self, id: ARID, now: Date) -> Result<()> {
+        let (initial, max) = (self.initial_backoff, self.max_backoff);
+        let found = self.store.update(id, &mut |entry| {
+            entry.attempts += 1;
+            entry.next_attempt = now + backoff(initial, max, entry.attempts);
+        })?;
+        if !found {
+            return Err(Error::UnknownOutboxEntry(id));
+        }
+        Ok(())
+    }
+
+    /// Returns the entries that were given up on.
+    pub fn dead_letters(&self) -> Result<Vec<OutboxEntry>> {
+        Ok(self
+            .store
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.dead_letter.is_some())
+            .collect())
+    }
+}
+
+/// The delay before retrying after `attempts` failed attempts, doubling from
+/// `initial` up to `max`.
+fn backoff(initial: Duration, max: Duration, attempts: u32) -> Duration {
+    let factor = 1u32 << attempts.saturating_sub(1).min(31);
+    initial.saturating_mul(factor).min(max)
+}
@@ -0,0 +1,151 @@
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result, consts};
+
+/// Resource limits applied to incoming messages before they are parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLimits {
+    max_message_size: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self { consts::DEFAULT_PARSE_LIMITS }
+}
+
+impl ParseLimits {
+    pub fn new() -> Self { Self::default() }
+
+    pub(crate) const fn from_parts(max_message_size: usize) -> Self {
+        Self { max_message_size }
+    }
+
+    /// Sets the maximum size in bytes of an encoded message.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    pub fn max_message_size(&self) -> usize { self.max_message_size }
+}
+
+/// Limits on the fields of a decrypted message, checked before its sender is
+/// verified, and on the size of its components, checked as they are parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldLimits {
+    max_note_length: usize,
+    max_assertions: usize,
+    max_parameter_value_size: usize,
+    max_result_size: usize,
+    max_state_size: usize,
+}
+
+impl Default for FieldLimits {
+    fn default() -> Self { consts::DEFAULT_FIELD_LIMITS }
+}
+
+impl FieldLimits {
+    pub fn new() -> Self { Self::default() }
+
+    pub(crate) const fn from_parts(
+        max_note_length: usize,
+        max_assertions: usize,
+        max_parameter_value_size: usize,
+        max_result_size: usize,
+        max_state_size: usize,
+    ) -> Self {
+        Self {
+            max_note_length,
+            max_assertions,
+            max_parameter_value_size,
+            max_result_size,
+            max_state_size,
+        }
+    }
+
+    /// Sets the maximum length in bytes of a message's note.
+    pub fn with_max_note_length(mut self, max_note_length: usize) -> Self {
+        self.max_note_length = max_note_length;
+        self
+    }
+
+    /// Sets the maximum number of assertions on the outermost layer of a
+    /// message.
+    pub fn with_max_assertions(mut self, max_assertions: usize) -> Self {
+        self.max_assertions = max_assertions;
+        self
+    }
+
+    /// Sets the maximum encoded size in bytes of the value of any one
+    /// parameter of a request.
+    pub fn with_max_parameter_value_size(
+        mut self,
+        max_parameter_value_size: usize,
+    ) -> Self {
+        self.max_parameter_value_size = max_parameter_value_size;
+        self
+    }
+
+    /// Sets the maximum encoded size in bytes of the result of a response.
+    pub fn with_max_result_size(mut self, max_result_size: usize) -> Self {
+        self.max_result_size = max_result_size;
+        self
+    }
+
+    /// Sets the maximum encoded size in bytes of the state carried by a
+    /// continuation.
+    pub fn with_max_state_size(mut self, max_state_size: usize) -> Self {
+        self.max_state_size = max_state_size;
+        self
+    }
+
+    pub fn max_note_length(&self) -> usize { self.max_note_length }
+
+    pub fn max_assertions(&self) -> usize { self.max_assertions }
+
+    pub fn max_parameter_value_size(&self) -> usize {
+        self.max_parameter_value_size
+    }
+
+    pub fn max_result_size(&self) -> usize { self.max_result_size }
+
+    pub fn max_state_size(&self) -> usize { self.max_state_size }
+
+    /// Checks the value of each parameter of `body` against the limit.
+    pub(crate) fn check_parameters(&self, body: &Expression) -> Result<()> {
+        for assertion in body.expression_envelope().assertions() {
+            let object = assertion.try_object()?;
+            let parameter =
+                Parameter::try_from(assertion.try_predicate()?.try_leaf()?)?;
+            check_component(
+                || format!("parameter {}", parameter.name()),
+                &object,
+                self.max_parameter_value_size,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_result(&self, result: &Envelope) -> Result<()> {
+        check_component(|| "result".into(), result, self.max_result_size)
+    }
+
+    pub(crate) fn check_state(&self, state: &Envelope) -> Result<()> {
+        check_component(|| "state".into(), state, self.max_state_size)
+    }
+}
+
+fn check_component(
+    component: impl FnOnce() -> String,
+    envelope: &Envelope,
+    limit: usize,
+) -> Result<()> {
+    let size = envelope.to_cbor_data().len();
+    if size > limit {
+        return Err(Error::ComponentTooLarge {
+            component: component(),
+            size,
+            limit,
+        });
+    }
+    Ok(())
+}
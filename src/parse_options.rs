@@ -0,0 +1,879 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use bc_components::{
+    ARID, Decrypter, EncapsulationPrivateKey, PrivateKeys, SigningPublicKey,
+    SymmetricKey, XID, XIDProvider,
+};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, ContinuationMetrics, ContinuationReplayGuard,
+    ContinuationStorage, DuplicateAssertionPolicy, Error, FieldLimits,
+    ParsePolicy, PeerContinuationRef, Provenance, RejectionReason,
+    RequestProfile, Result, SealingReport, SenderPolicy, SessionKeys,
+    StateEpochs, StateStore, TofuStore, consts, continuation_storage,
+    inspect::{self, DecryptionDiagnostics},
+    provenance, sealing,
+    session::SessionAssertions,
+    state_epochs, state_store,
+};
+
+/// Options controlling how a sealed message is parsed and what it must
+/// satisfy to be accepted.
+///
+/// The parsing functions of each message type have `_opt` forms taking these
+/// options, of which the positional forms are thin wrappers. Options are
+/// cheap to clone, sharing stores, guards, and metrics through `Arc`, so a
+/// server can build them once and reuse them for each connection.
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    expected_id: Option<ARID>,
+    expected_function: Option<Function>,
+    now: Option<Date>,
+    expected_sender: Option<XID>,
+    request_digest: Option<Digest>,
+    accepted_delegates: HashSet<XID>,
+    sender_policy: SenderPolicy,
+    tofu_store: Option<Arc<dyn TofuStore>>,
+    continuation_keys: Vec<EncapsulationPrivateKey>,
+    ephemeral_key: Option<EncapsulationPrivateKey>,
+    continuation_symmetric_key: Option<SymmetricKey>,
+    previous_keys: Vec<PrivateKeys>,
+    state_epochs: Option<Arc<StateEpochs>>,
+    duplicate_assertions: DuplicateAssertionPolicy,
+    max_peer_continuation_lifetime: Option<Duration>,
+    max_continuation_age: Option<Duration>,
+    max_continuation_validity: Option<Duration>,
+    clock_skew_tolerance: Duration,
+    parse_policy: ParsePolicy,
+    field_limits: FieldLimits,
+    session: Option<SessionKeys>,
+    require_provenance: bool,
+    continuation_storage: Option<Arc<dyn ContinuationStorage>>,
+    accept_anonymous_events: bool,
+    accept_early_failures: bool,
+    request_profile: RequestProfile,
+    continuation_metrics: Option<Arc<dyn ContinuationMetrics>>,
+    continuation_replay_guard: Option<Arc<dyn ContinuationReplayGuard>>,
+    state_store: Option<Arc<dyn StateStore>>,
+    decryption_diagnostics: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            expected_id: None,
+            expected_function: None,
+            now: None,
+            expected_sender: None,
+            request_digest: None,
+            accepted_delegates: HashSet::new(),
+            sender_policy: SenderPolicy::default(),
+            tofu_store: None,
+            continuation_keys: Vec::new(),
+            ephemeral_key: None,
+            continuation_symmetric_key: None,
+            previous_keys: Vec::new(),
+            state_epochs: None,
+            duplicate_assertions: DuplicateAssertionPolicy::default(),
+            max_peer_continuation_lifetime: consts::DEFAULT_CONTINUATION_POLICY
+                .max_peer_lifetime,
+            max_continuation_age: None,
+            max_continuation_validity: None,
+            clock_skew_tolerance: Duration::ZERO,
+            parse_policy: ParsePolicy::default(),
+            field_limits: consts::DEFAULT_FIELD_LIMITS,
+            session: None,
+            require_provenance: false,
+            continuation_storage: None,
+            accept_anonymous_events: false,
+            accept_early_failures: false,
+            request_profile: RequestProfile::default(),
+            decryption_diagnostics: false,
+            continuation_metrics: None,
+            continuation_replay_guard: None,
+            state_store: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets the ID that the continuation returned to us must be valid for.
+    pub fn with_expected_id(self, expected_id: ARID) -> Self {
+        self.with_optional_expected_id(Some(expected_id))
+    }
+
+    pub fn with_optional_expected_id(
+        mut self,
+        expected_id: Option<ARID>,
+    ) -> Self {
+        self.expected_id = expected_id;
+        self
+    }
+
+    /// Sets the function that the continuation returned to us must be bound
+    /// to, if it is bound to one, typically that of the request a response
+    /// answers.
+    ///
+    /// A request must call this function, failing with
+    /// [`Error::UnexpectedFunction`] otherwise, and its continuation is
+    /// checked against it.
+    pub fn with_expected_function(
+        mut self,
+        function: impl Into<Function>,
+    ) -> Self {
+        self.expected_function = Some(function.into());
+        self
+    }
+
+    /// Sets the time against which continuation expiry is checked.
+    pub fn with_now(self, now: Date) -> Self {
+        self.with_optional_now(Some(now))
+    }
+
+    pub fn with_optional_now(mut self, now: Option<Date>) -> Self {
+        self.now = now;
+        self
+    }
+
+    /// Requires the message to be sent by `sender`, given by its XID or XID
+    /// document, typically the peer a request was encrypted to. A message
+    /// signed by anyone else fails with [`Error::UnexpectedSender`], and one
+    /// whose document merely claims the XID with another key fails with
+    /// [`Error::SenderKeyNotBound`].
+    ///
+    /// Without this, any validly signed message is accepted regardless of
+    /// who signed it.
+    pub fn with_expected_sender(mut self, sender: &impl XIDProvider) -> Self {
+        self.expected_sender = Some(sender.xid());
+        self
+    }
+
+    /// Requires a response to be bound to the request envelope with digest
+    /// `digest`, the one we sent, failing with
+    /// [`Error::ResponseBindingMismatch`] otherwise. This proves the peer saw
+    /// exactly the request we sent, not one substituted on the way.
+    pub fn with_request_digest(mut self, digest: Digest) -> Self {
+        self.request_digest = Some(digest);
+        self
+    }
+
+    /// Also accepts messages from the delegates listed in `principal`'s XID
+    /// document, for deployments where a fleet of servers answers on behalf
+    /// of a single published identity.
+    pub fn with_delegates_of(mut self, principal: &XIDDocument) -> Self {
+        self.accepted_delegates.extend(
+            principal.delegates().iter().map(|delegate| delegate.xid()),
+        );
+        self
+    }
+
+    /// Sets the policy deciding which authenticated senders are accepted.
+    /// The default accepts anyone.
+    pub fn with_sender_policy(mut self, policy: SenderPolicy) -> Self {
+        self.sender_policy = policy;
+        self
+    }
+
+    /// Trusts each sender's verification key on first use, as recorded by
+    /// `store`, rejecting messages from the same XID signed with another key
+    /// with [`Error::SenderKeyChanged`].
+    ///
+    /// What is recorded on first use is the inception key the message was
+    /// verified with, which the sender's XID is derived from, so a first
+    /// contact cannot pin someone else's XID to a key of its own. A key
+    /// other than the inception key is trusted only once blessed with
+    /// [`TofuStore::bless_key_change`].
+    pub fn with_tofu_store(mut self, store: Arc<dyn TofuStore>) -> Self {
+        self.tofu_store = Some(store);
+        self
+    }
+
+    /// Sets keys we held before a rotation, tried in order after the
+    /// recipient's own keys to decrypt both the message and the continuation
+    /// it returns to us, so that both still parse when sealed to an old key.
+    ///
+    /// If no key decrypts the message, parsing fails with
+    /// [`Error::NoKeyDecrypts`].
+    pub fn with_previous_keys(
+        mut self,
+        keys: impl IntoIterator<Item = PrivateKeys>,
+    ) -> Self {
+        self.previous_keys = keys.into_iter().collect();
+        self
+    }
+
+    /// Sets additional keys to try, before the recipient's own keys, when
+    /// decrypting continuations returned to us, typically the valid keys of
+    /// a [`DelegationKeyring`](crate::DelegationKeyring).
+    pub fn with_continuation_keys(
+        mut self,
+        keys: impl IntoIterator<Item = EncapsulationPrivateKey>,
+    ) -> Self {
+        self.continuation_keys = keys.into_iter().collect();
+        self
+    }
+
+    /// Sets the ephemeral key a request was sealed with, from
+    /// [`SealedArtifacts::ephemeral_key`](crate::SealedArtifacts::ephemeral_key),
+    /// tried before the recipient's own keys to decrypt both the response and
+    /// the continuation it returns to us.
+    pub fn with_ephemeral_key(mut self, key: EncapsulationPrivateKey) -> Self {
+        self.ephemeral_key = Some(key);
+        self
+    }
+
+    /// Sets the key to try first when decrypting continuations returned to
+    /// us, as issued with
+    /// [`ContinuationSealer::Symmetric`](crate::ContinuationSealer::Symmetric).
+    /// A continuation it does not decrypt is decrypted with the other keys.
+    pub fn with_continuation_symmetric_key(
+        mut self,
+        key: SymmetricKey,
+    ) -> Self {
+        self.continuation_symmetric_key = Some(key);
+        self
+    }
+
+    /// Decrypts continuations returned to us whose state was encrypted with
+    /// a key derived from `epochs`, as issued with
+    /// [`SealOptions::with_state_epochs`](crate::SealOptions::with_state_epochs).
+    ///
+    /// Without epochs, such a continuation is rejected with
+    /// [`Error::StateEpochsRequired`].
+    pub fn with_state_epochs(mut self, epochs: Arc<StateEpochs>) -> Self {
+        self.state_epochs = Some(epochs);
+        self
+    }
+
+    /// Sets how a message that repeats a singular GSTP assertion is handled.
+    /// By default it is rejected.
+    pub fn with_duplicate_assertions(
+        mut self,
+        policy: DuplicateAssertionPolicy,
+    ) -> Self {
+        self.duplicate_assertions = policy;
+        self
+    }
+
+    /// Caps how long a continuation issued by the peer is retained, counted
+    /// from the time of parsing.
+    ///
+    /// Peer continuations are opaque, so the cap is applied to the expiry
+    /// hint the peer added in the clear, if any, and otherwise imposed
+    /// outright. The resulting date is reported by
+    /// [`SealedResponse::peer_continuation_retain_until`](crate::SealedResponse::peer_continuation_retain_until),
+    /// after which the continuation should be discarded.
+    pub fn with_max_peer_continuation_lifetime(
+        mut self,
+        lifetime: Duration,
+    ) -> Self {
+        self.max_peer_continuation_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Rejects a continuation returned to us that was issued more than `age`
+    /// ago, however far its validity has been extended. A continuation that
+    /// does not record when it was issued is rejected too.
+    pub fn with_max_continuation_age(mut self, age: Duration) -> Self {
+        self.max_continuation_age = Some(age);
+        self
+    }
+
+    /// Rejects a continuation returned to us that claims to be valid for
+    /// more than `max` from now, or does not expire, with
+    /// [`Error::ContinuationValidityTooLong`].
+    pub fn with_max_continuation_validity(mut self, max: Duration) -> Self {
+        self.max_continuation_validity = Some(max);
+        self
+    }
+
+    /// Accepts a continuation returned to us if it is valid at some time
+    /// within `tolerance` of now, for peers whose clocks drift from ours. A
+    /// continuation that does not expire is unaffected. The default is no
+    /// tolerance.
+    pub fn with_clock_skew_tolerance(mut self, tolerance: Duration) -> Self {
+        self.clock_skew_tolerance = tolerance;
+        self
+    }
+
+    /// Sets how strictly the validity of continuations returned to us is
+    /// checked. The default is [`ParsePolicy::Lenient`].
+    pub fn with_parse_policy(mut self, policy: ParsePolicy) -> Self {
+        self.parse_policy = policy;
+        self
+    }
+
+    /// Sets the limits on the fields of a message.
+    pub fn with_field_limits(mut self, field_limits: FieldLimits) -> Self {
+        self.field_limits = field_limits;
+        self
+    }
+
+    /// Sets the session established with the peer.
+    ///
+    /// Messages sealed with the session are decrypted with its key. Until it
+    /// expires, a message sealed to our public keys instead is rejected
+    /// unless it proposes a new session.
+    pub fn with_session(mut self, session: SessionKeys) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Requires messages to record their provenance.
+    pub fn with_required_provenance(mut self, required: bool) -> Self {
+        self.require_provenance = required;
+        self
+    }
+
+    /// Accepts continuations returned by reference, resolving them through
+    /// `storage`.
+    ///
+    /// Without storage, a request that returns its continuation by reference
+    /// is rejected with [`Error::ContinuationRefUnsupported`].
+    pub fn with_continuation_storage(
+        mut self,
+        storage: Arc<dyn ContinuationStorage>,
+    ) -> Self {
+        self.continuation_storage = Some(storage);
+        self
+    }
+
+    /// Accepts [`AnonymousEvent`](crate::AnonymousEvent)s, whose senders are
+    /// neither identified nor authenticated. They are rejected by default.
+    pub fn with_anonymous_events(mut self, accept: bool) -> Self {
+        self.accept_anonymous_events = accept;
+        self
+    }
+
+    /// Accepts early failures, which answer no request and so carry no ID,
+    /// when an ID is expected. They are otherwise rejected with
+    /// [`Error::ResponseIdMismatch`].
+    pub fn with_early_failures(mut self, accept: bool) -> Self {
+        self.accept_early_failures = accept;
+        self
+    }
+
+    /// Sets whether requests are expected to be interactive or one-way.
+    /// A one-way endpoint accepts requests without a continuation, and an
+    /// interactive one rejects requests marked one-way.
+    pub fn with_request_profile(mut self, profile: RequestProfile) -> Self {
+        self.request_profile = profile;
+        self
+    }
+
+    /// Sets whether a message none of whose recipient slots opens with our
+    /// key fails with [`Error::NotARecipient`], describing the slots and the
+    /// key that was tried, rather than a terse decryption error.
+    pub fn with_decryption_diagnostics(mut self, diagnostics: bool) -> Self {
+        self.decryption_diagnostics = diagnostics;
+        self
+    }
+
+    /// Sets a recorder that counts every continuation returned to us,
+    /// accepted or rejected.
+    pub fn with_continuation_metrics(
+        mut self,
+        metrics: Arc<dyn ContinuationMetrics>,
+    ) -> Self {
+        self.continuation_metrics = Some(metrics);
+        self
+    }
+
+    /// Accepts each continuation returned to us that carries a nonce only
+    /// once, as recorded by `guard`, rejecting it afterwards with
+    /// [`Error::ContinuationReplayed`]. Continuations without a nonce are
+    /// accepted as before.
+    pub fn with_continuation_replay_guard(
+        mut self,
+        guard: Arc<dyn ContinuationReplayGuard>,
+    ) -> Self {
+        self.continuation_replay_guard = Some(guard);
+        self
+    }
+
+    /// Resolves the state of continuations returned to us that refer to it,
+    /// as issued with
+    /// [`ContinuationMode::Reference`](crate::ContinuationMode::Reference),
+    /// through `store`.
+    ///
+    /// A continuation whose state is not in the store, or returned without
+    /// a store, is rejected with [`Error::ContinuationStateUnavailable`].
+    pub fn with_state_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.state_store = Some(store);
+        self
+    }
+
+    pub fn expected_id(&self) -> Option<ARID> { self.expected_id }
+
+    pub fn expected_function(&self) -> Option<&Function> {
+        self.expected_function.as_ref()
+    }
+
+    pub fn now(&self) -> Option<Date> { self.now }
+
+    pub fn expected_sender(&self) -> Option<XID> { self.expected_sender }
+
+    pub fn request_digest(&self) -> Option<Digest> { self.request_digest }
+
+    pub fn sender_policy(&self) -> &SenderPolicy { &self.sender_policy }
+
+    pub fn duplicate_assertions(&self) -> DuplicateAssertionPolicy {
+        self.duplicate_assertions
+    }
+
+    pub fn max_peer_continuation_lifetime(&self) -> Option<Duration> {
+        self.max_peer_continuation_lifetime
+    }
+
+    pub fn max_continuation_age(&self) -> Option<Duration> {
+        self.max_continuation_age
+    }
+
+    pub fn max_continuation_validity(&self) -> Option<Duration> {
+        self.max_continuation_validity
+    }
+
+    pub fn clock_skew_tolerance(&self) -> Duration { self.clock_skew_tolerance }
+
+    pub fn parse_policy(&self) -> ParsePolicy { self.parse_policy }
+
+    pub fn field_limits(&self) -> &FieldLimits { &self.field_limits }
+
+    pub fn session(&self) -> Option<&SessionKeys> { self.session.as_ref() }
+
+    pub fn required_provenance(&self) -> bool { self.require_provenance }
+
+    pub fn accepts_early_failures(&self) -> bool { self.accept_early_failures }
+
+    pub fn accepts_anonymous_events(&self) -> bool {
+        self.accept_anonymous_events
+    }
+
+    pub fn request_profile(&self) -> RequestProfile { self.request_profile }
+
+    pub fn continuation_metrics(&self) -> Option<&dyn ContinuationMetrics> {
+        self.continuation_metrics.as_deref()
+    }
+
+    pub fn continuation_replay_guard(
+        &self,
+    ) -> Option<&dyn ContinuationReplayGuard> {
+        self.continuation_replay_guard.as_deref()
+    }
+
+    pub fn previous_keys(&self) -> &[PrivateKeys] { &self.previous_keys }
+
+    pub fn ephemeral_key(&self) -> Option<&EncapsulationPrivateKey> {
+        self.ephemeral_key.as_ref()
+    }
+
+    pub fn state_store(&self) -> Option<&dyn StateStore> {
+        self.state_store.as_deref()
+    }
+
+    pub fn continuation_storage(&self) -> Option<&dyn ContinuationStorage> {
+        self.continuation_storage.as_deref()
+    }
+
+    /// Returns the time against which continuation validity is checked: the
+    /// time set, or under [`ParsePolicy::Strict`] the current time if none
+    /// is.
+    pub(crate) fn validation_now(&self) -> Option<Date> {
+        match self.parse_policy {
+            ParsePolicy::Lenient => self.now,
+            ParsePolicy::Strict => Some(self.now.unwrap_or_else(Date::now)),
+        }
+    }
+
+    /// Reads the provenance of a message, checking that it has one if
+    /// required.
+    pub(crate) fn parse_provenance(
+        &self,
+        message: &Envelope,
+    ) -> Result<Option<Provenance>> {
+        let provenance = provenance::parse_provenance(message)?;
+        if self.require_provenance && provenance.is_none() {
+            return Err(Error::MissingProvenance);
+        }
+        Ok(provenance)
+    }
+
+    /// Decrypts a message sealed to its recipients' public keys, describing
+    /// its recipient slots if none opens and diagnostics are enabled.
+    ///
+    /// The ephemeral key, `recipient`, and the previous keys are tried in
+    /// turn, moving on to the next only while no recipient slot opens. Once a
+    /// slot opens, any later failure, such as a payload that inflates past
+    /// the limit, is returned as it is.
+    pub(crate) fn decrypt_to_recipient(
+        &self,
+        encrypted_envelope: &Envelope,
+        recipient: &PrivateKeys,
+    ) -> Result<(Envelope, SealingReport)> {
+        let keys: Vec<&dyn Decrypter> = self
+            .ephemeral_key
+            .iter()
+            .map(|key| key as &dyn Decrypter)
+            .chain([recipient as &dyn Decrypter])
+            .chain(self.previous_keys.iter().map(|keys| keys as &dyn Decrypter))
+            .collect();
+        for key in &keys {
+            match sealing::decrypt_to_recipient(encrypted_envelope, *key) {
+                Err(Error::Envelope(bc_envelope::Error::UnknownRecipient)) => {}
+                result => return result,
+            }
+        }
+        if self.decryption_diagnostics {
+            Err(Error::NotARecipient(Box::new(DecryptionDiagnostics::new(
+                encrypted_envelope,
+                recipient,
+            )?)))
+        } else if keys.len() > 1 {
+            Err(Error::NoKeyDecrypts {
+                attempted: keys.len(),
+            })
+        } else {
+            Err(bc_envelope::Error::UnknownRecipient.into())
+        }
+    }
+
+    /// Decrypts a sealed message, with the session key if it names our
+    /// session, and otherwise with `recipient`. Returns the signed envelope,
+    /// whether it was sealed with the session, and how it was sealed.
+    pub(crate) fn decrypt(
+        &self,
+        encrypted_envelope: &Envelope,
+        recipient: &PrivateKeys,
+    ) -> Result<(Envelope, bool, SealingReport)> {
+        let Some(id) = inspect::session_id(encrypted_envelope)? else {
+            let (signed, report) =
+                self.decrypt_to_recipient(encrypted_envelope, recipient)?;
+            return Ok((signed, false, report));
+        };
+        let session = self
+            .session
+            .as_ref()
+            .filter(|session| session.id() == id)
+            .ok_or(Error::UnknownSession(id))?;
+        if session.is_expired(self.now.unwrap_or_else(Date::now)) {
+            return Err(Error::SessionExpired(id));
+        }
+        let (signed, report) =
+            sealing::decrypt_with_session(encrypted_envelope, session)?;
+        Ok((signed, true, report))
+    }
+
+    /// Rejects a message that was not sealed with the established session,
+    /// unless the session has expired or the message proposes a new one.
+    pub(crate) fn check_session_downgrade(
+        &self,
+        sealed_with_session: bool,
+        assertions: &SessionAssertions,
+    ) -> Result<()> {
+        let Some(session) = &self.session else {
+            return Ok(());
+        };
+        if sealed_with_session
+            || assertions.proposal.is_some()
+            || session.is_expired(self.now.unwrap_or_else(Date::now))
+        {
+            Ok(())
+        } else {
+            Err(Error::SessionDowngrade)
+        }
+    }
+
+    /// Checks the fields of a decrypted message against the field limits.
+    pub(crate) fn check_fields(&self, message: &Envelope) -> Result<()> {
+        let limits = &self.field_limits;
+        let assertions = message.assertions().len();
+        if assertions > limits.max_assertions() {
+            return Err(Error::FieldTooLarge {
+                field: "assertions",
+                size: assertions,
+                limit: limits.max_assertions(),
+            });
+        }
+        if let Some(note) =
+            message.optional_object_for_predicate(known_values::NOTE)?
+            && let note = note.extract_subject::<String>()?
+            && note.len() > limits.max_note_length()
+        {
+            return Err(Error::FieldTooLarge {
+                field: "note",
+                size: note.len(),
+                limit: limits.max_note_length(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the date until which a continuation issued by the peer should
+    /// be retained: its expiry hint, clamped to the maximum lifetime if one
+    /// is set.
+    pub(crate) fn peer_continuation_retention(
+        &self,
+        peer_continuation: &Envelope,
+    ) -> Result<Option<Date>> {
+        let hint = inspect::continuation_expiry_hint(peer_continuation)?;
+        let Some(lifetime) = self.max_peer_continuation_lifetime else {
+            return Ok(hint);
+        };
+        let cap = self.now.unwrap_or_else(Date::now) + lifetime;
+        Ok(Some(hint.map_or(cap, |hint| hint.min(cap))))
+    }
+
+    /// Returns the continuation a message returns to us, either in full or,
+    /// if it is returned by reference, as loaded from the continuation
+    /// storage. A message returning both is refused.
+    pub(crate) fn recipient_continuation(
+        &self,
+        message: &Envelope,
+    ) -> Result<Option<Envelope>> {
+        let continuation = message.optional_object_for_predicate(
+            known_values::RECIPIENT_CONTINUATION,
+        )?;
+        let reference = message.optional_object_for_predicate(
+            continuation_storage::RECIPIENT_CONTINUATION_REF,
+        )?;
+        let reference = match (continuation, reference) {
+            (Some(_), Some(_)) => {
+                return Err(Error::ConflictingRecipientContinuations);
+            }
+            (continuation, None) => return Ok(continuation),
+            (None, Some(reference)) => reference,
+        };
+        let storage = self
+            .continuation_storage
+            .as_deref()
+            .ok_or(Error::ContinuationRefUnsupported)?;
+        PeerContinuationRef::try_from(reference)?
+            .resolve(storage)
+            .map(Some)
+    }
+
+    /// Decrypts and validates a continuation returned to `recipient` with a
+    /// message to `function` from the verified `sender`, counting it in the
+    /// continuation metrics under that function.
+    pub(crate) fn parse_continuation(
+        &self,
+        encrypted_continuation: &Envelope,
+        recipient: &PrivateKeys,
+        function: Option<&Function>,
+        sender: &XIDDocument,
+    ) -> Result<Continuation> {
+        let result = self.check_continuation(
+            encrypted_continuation,
+            recipient,
+            function,
+            sender,
+        );
+        if let Some(metrics) = &self.continuation_metrics {
+            match &result {
+                Ok(continuation) => metrics.record_returned(
+                    function,
+                    continuation.age(self.now.unwrap_or_else(Date::now)),
+                ),
+                Err(error) => metrics.record_rejected(
+                    function,
+                    RejectionReason::from_error(error),
+                ),
+            }
+        }
+        result
+    }
+
+    fn check_continuation(
+        &self,
+        encrypted_continuation: &Envelope,
+        recipient: &PrivateKeys,
+        function: Option<&Function>,
+        sender: &XIDDocument,
+    ) -> Result<Continuation> {
+        let continuation =
+            if state_epochs::is_epoch_encrypted(encrypted_continuation) {
+                let epochs = self
+                    .state_epochs
+                    .as_deref()
+                    .ok_or(Error::StateEpochsRequired)?;
+                Continuation::try_from_envelope_opt(
+                    &epochs.decrypt(encrypted_continuation)?,
+                    self,
+                    &[],
+                )?
+            } else if let Some(decrypted) = self
+                .continuation_symmetric_key
+                .as_ref()
+                .and_then(|key| encrypted_continuation.decrypt(key).ok())
+            {
+                Continuation::try_from_envelope_opt(&decrypted, self, &[])?
+            } else {
+                let mut keys: Vec<&dyn Decrypter> = self
+                    .ephemeral_key
+                    .iter()
+                    .chain(&self.continuation_keys)
+                    .map(|key| key as &dyn Decrypter)
+                    .collect();
+                keys.push(recipient);
+                keys.extend(
+                    self.previous_keys
+                        .iter()
+                        .map(|keys| keys as &dyn Decrypter),
+                );
+                Continuation::try_from_envelope_opt(
+                    encrypted_continuation,
+                    self,
+                    &keys,
+                )?
+            };
+        if let Some(max_age) = self.max_continuation_age {
+            let age = continuation
+                .age(self.now.unwrap_or_else(Date::now))
+                .ok_or(Error::ContinuationAgeUnknown)?;
+            if age > max_age {
+                return Err(Error::ContinuationTooOld { age, max_age });
+            }
+        }
+        if let Some(max) = self.max_continuation_validity {
+            let cap = self.now.unwrap_or_else(Date::now) + max;
+            if continuation
+                .valid_until()
+                .is_none_or(|valid_until| valid_until > cap)
+            {
+                return Err(Error::ContinuationValidityTooLong { max });
+            }
+        }
+        let function = function.or(self.expected_function.as_ref());
+        if !continuation.is_valid_function(function) {
+            let expected = continuation.valid_function().unwrap().clone();
+            return Err(match function {
+                Some(found) => Error::ContinuationFunctionMismatch {
+                    expected,
+                    found: found.clone(),
+                },
+                None => Error::ContinuationFunctionMissing(expected),
+            });
+        }
+        if continuation.valid_sender().is_some() {
+            // The continuation is bound to an XID, which the sender's
+            // document only claims until its key is shown to be bound to it.
+            self.sender_verification_key(sender)?;
+            let found = sender.xid();
+            if !continuation.is_valid_sender(found) {
+                return Err(Error::ContinuationSenderMismatch {
+                    expected: continuation.valid_sender().unwrap(),
+                    found,
+                });
+            }
+        }
+        let continuation = match state_store::resolve_state(
+            self.state_store.as_deref(),
+            continuation.state(),
+        )? {
+            Some(state) => continuation.with_state(state),
+            None => continuation,
+        };
+        self.field_limits.check_state(continuation.state())?;
+        Ok(continuation)
+    }
+
+    /// Records the nonce of a continuation returned to us with the replay
+    /// guard, if there is one, failing if it was recorded before.
+    ///
+    /// Called once every other check on the message has passed, so that a
+    /// message that is refused does not use up its continuation.
+    pub(crate) fn record_continuation_nonce(
+        &self,
+        nonce: Option<ARID>,
+        valid_until: Option<Date>,
+    ) -> Result<()> {
+        if let (Some(guard), Some(nonce)) =
+            (&self.continuation_replay_guard, nonce)
+        {
+            guard.check_and_record(
+                nonce,
+                valid_until,
+                self.now,
+                self.clock_skew_tolerance,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a response answers the expected request, if there is one.
+    pub(crate) fn check_response_id(&self, found: Option<ARID>) -> Result<()> {
+        let Some(expected) = self.expected_id else {
+            return Ok(());
+        };
+        match found {
+            Some(found) if found == expected => Ok(()),
+            None if self.accept_early_failures => Ok(()),
+            _ => Err(Error::ResponseIdMismatch { expected, found }),
+        }
+    }
+
+    /// Checks that a response is bound to the request we sent, if we expect
+    /// it to be.
+    pub(crate) fn check_request_digest(
+        &self,
+        found: Option<Digest>,
+    ) -> Result<()> {
+        match self.request_digest {
+            Some(expected) if found != Some(expected) => {
+                Err(Error::ResponseBindingMismatch)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that a request calls the expected function, if there is one.
+    pub(crate) fn check_function(&self, found: &Function) -> Result<()> {
+        match &self.expected_function {
+            Some(expected) if expected != found => {
+                Err(Error::UnexpectedFunction {
+                    expected: expected.clone(),
+                    found: found.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the key the signature of a message from `sender` must verify
+    /// against, as for [`sealing::sender_verification_key`], consulting the
+    /// trust-on-first-use store for a sender that has rotated its key.
+    pub(crate) fn sender_verification_key<'a>(
+        &self,
+        sender: &'a XIDDocument,
+    ) -> Result<&'a SigningPublicKey> {
+        sealing::sender_verification_key(sender, self.tofu_store.as_deref())
+    }
+
+    /// Checks that `sender` is the expected sender, or one of its accepted
+    /// delegates, that the sender policy accepts it, and that its key is the
+    /// one first seen for it.
+    ///
+    /// Its XID is compared only once it is known to be bound to the key the
+    /// message was verified with, since the XID alone is just a claim.
+    pub(crate) fn check_sender(&self, sender: &XIDDocument) -> Result<()> {
+        let key = self.sender_verification_key(sender)?;
+        let found = sender.xid();
+        if let Some(expected) = self.expected_sender
+            && found != expected
+            && !self.accepted_delegates.contains(&found)
+        {
+            return Err(Error::UnexpectedSender { expected, found });
+        }
+        if !self.sender_policy.permits(sender) {
+            return Err(Error::SenderNotAuthorized(found));
+        }
+        if let Some(store) = &self.tofu_store {
+            store.check_and_record(found, key)?;
+        }
+        Ok(())
+    }
+}
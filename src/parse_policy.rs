@@ -0,0 +1,14 @@
+/// How strictly the validity of continuations returned to us is checked
+/// when parsing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParsePolicy {
+    /// Expiry is only checked against a time given to the parser, and a
+    /// continuation need not expire.
+    #[default]
+    Lenient,
+
+    /// Expiry is checked against the current time unless another is given,
+    /// and a continuation that does not expire is rejected with
+    /// [`Error::ContinuationWithoutExpiry`](crate::Error::ContinuationWithoutExpiry).
+    Strict,
+}
@@ -0,0 +1,65 @@
+use bc_components::{ARID, Digest, XID};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::Error;
+
+/// A stage of parsing a sealed message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseStage {
+    /// Decrypting the message to the recipient.
+    #[default]
+    Decryption,
+    /// Reading the sender's XID document from the signed message.
+    Sender,
+    /// Verifying the sender's signature.
+    Signature,
+    /// Checking the continuations carried by the message.
+    Continuation,
+    /// Decoding the message itself.
+    Request,
+}
+
+/// What could be extracted from a sealed message before parsing it failed.
+///
+/// Fields prefixed with `claimed_` are read from the message before, or
+/// without, its signature being verified. They are whatever the sender chose
+/// to put there and must never be used for anything but observability.
+#[derive(Debug, Default)]
+pub struct PartialParse {
+    /// The size in bytes of the envelope as received.
+    pub envelope_size: usize,
+    /// The size in bytes of the signed envelope, if it could be decrypted.
+    pub decrypted_size: Option<usize>,
+    /// The ID of the request, as claimed by the unverified message.
+    pub claimed_id: Option<ARID>,
+    /// The function of the request, as claimed by the unverified message.
+    pub claimed_function: Option<Function>,
+    /// The XID of the sender, as claimed by the unverified message.
+    pub claimed_sender: Option<XID>,
+    /// Whether the sender's signature was verified.
+    pub signature_verified: bool,
+    /// The digest of the continuation the message returned to us, once the
+    /// sender's signature was verified. Continuations are checked after it
+    /// is recorded, so it is set even if the continuation had expired.
+    pub returned_continuation: Option<Digest>,
+    /// The error that stopped parsing, if any.
+    pub error: Option<Error>,
+    /// The sender's document, once its signature was verified.
+    pub(crate) verified_sender: Option<XIDDocument>,
+    pub(crate) stage: ParseStage,
+}
+
+impl PartialParse {
+    pub(crate) fn new(envelope: &Envelope) -> Self {
+        Self {
+            envelope_size: envelope.to_cbor_data().len(),
+            ..Self::default()
+        }
+    }
+
+    /// The stage at which parsing failed, or `None` if it succeeded.
+    pub fn failed_stage(&self) -> Option<ParseStage> {
+        self.error.as_ref().map(|_| self.stage)
+    }
+}
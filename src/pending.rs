@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bc_components::{ARID, PrivateKeys, XID, XIDProvider};
+use bc_envelope::{Signer, prelude::*};
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, ContinuationReceipt, Error, Result, SealOptions,
+    SealedRequest, SealedRequestBehavior, SealedResponse,
+    SealedResponseBehavior, sealing,
+};
+
+const FUNCTION: &str = "function";
+const DEADLINE: &str = "deadline";
+const PEER: &str = "peer";
+const CONTINUATION: &str = "continuation";
+const DELEGATE: &str = "delegate";
+
+/// A request that has been sent and is awaiting its response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingRecord {
+    /// The time after which a response is no longer accepted.
+    pub deadline: Option<Date>,
+    /// The function of the request.
+    pub function: Function,
+    /// The XID of the peer the request was sent to.
+    pub peer: XID,
+    /// The XIDs of the delegates whose responses are accepted in place of
+    /// the peer's.
+    pub delegates: Vec<XID>,
+    /// The continuation we issued with the request, exactly as it was sent.
+    /// It is encrypted to ourselves.
+    pub continuation: Envelope,
+}
+
+impl PendingRecord {
+    fn to_envelope(&self, id: ARID) -> Envelope {
+        let envelope = Envelope::new(id)
+            .add_assertion(FUNCTION, self.function.clone())
+            .add_optional_assertion(DEADLINE, self.deadline)
+            .add_assertion(PEER, self.peer)
+            .add_assertion(CONTINUATION, self.continuation.clone());
+        self.delegates.iter().fold(envelope, |envelope, delegate| {
+            envelope.add_assertion(DELEGATE, *delegate)
+        })
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<(ARID, Self)> {
+        let id = envelope.extract_subject()?;
+        let record = Self {
+            deadline: envelope
+                .extract_optional_object_for_predicate(DEADLINE)?,
+            function: envelope.extract_object_for_predicate(FUNCTION)?,
+            peer: envelope.extract_object_for_predicate(PEER)?,
+            continuation: envelope.object_for_predicate(CONTINUATION)?,
+            delegates: envelope
+                .objects_for_predicate(DELEGATE)
+                .iter()
+                .map(|delegate| delegate.extract_subject())
+                .collect::<std::result::Result<_, _>>()?,
+        };
+        Ok((id, record))
+    }
+
+    fn is_expired(&self, now: Date) -> bool {
+        self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+}
+
+/// Storage for requests awaiting their responses.
+pub trait PendingStore {
+    /// Records a pending request, replacing any previous record with the same
+    /// ID.
+    fn put(&mut self, id: ARID, record: PendingRecord) -> Result<()>;
+
+    /// Returns the record for a request, if there is one, leaving it in the
+    /// store.
+    fn get(&self, id: ARID) -> Result<Option<PendingRecord>>;
+
+    /// Removes and returns the record for a request, if there is one.
+    fn take(&mut self, id: ARID) -> Result<Option<PendingRecord>>;
+
+    /// Removes all records whose deadline is at or before `now`, returning
+    /// their IDs.
+    fn expire(&mut self, now: Date) -> Result<Vec<ARID>>;
+}
+
+/// A [`PendingStore`] that lives only as long as the process.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryPendingStore {
+    records: HashMap<ARID, PendingRecord>,
+}
+
+impl MemoryPendingStore {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl PendingStore for MemoryPendingStore {
+    fn put(&mut self, id: ARID, record: PendingRecord) -> Result<()> {
+        self.records.insert(id, record);
+        Ok(())
+    }
+
+    fn get(&self, id: ARID) -> Result<Option<PendingRecord>> {
+        Ok(self.records.get(&id).cloned())
+    }
+
+    fn take(&mut self, id: ARID) -> Result<Option<PendingRecord>> {
+        Ok(self.records.remove(&id))
+    }
+
+    fn expire(&mut self, now: Date) -> Result<Vec<ARID>> {
+        let expired: Vec<ARID> = self
+            .records
+            .iter()
+            .filter(|(_, record)| record.is_expired(now))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired {
+            self.records.remove(id);
+        }
+        Ok(expired)
+    }
+}
+
+/// A [`PendingStore`] persisted to a file as a CBOR array of envelopes.
+///
+/// The whole file is rewritten after every change, via a temporary file that
+/// is renamed into place, so a crash never leaves a partially written store.
+#[derive(Debug)]
+pub struct FilePendingStore {
+    path: PathBuf,
+    records: MemoryPendingStore,
+}
+
+impl FilePendingStore {
+    /// Opens the store at `path`, creating an empty one if the file does not
+    /// exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut records = MemoryPendingStore::new();
+        if path.exists() {
+            let cbor = CBOR::try_from_data(fs::read(&path)?)?;
+            for item in cbor.try_into_array()? {
+                let envelope = Envelope::try_from(item)?;
+                let (id, record) = PendingRecord::try_from_envelope(&envelope)?;
+                records.put(id, record)?;
+            }
+        }
+        Ok(Self { path, records })
+    }
+
+    fn save(&self) -> Result<()> {
+        let items: Vec<CBOR> = self
+            .records
+            .records
+            .iter()
+            .map(|(id, record)| record.to_envelope(*id).to_cbor())
+            .collect();
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, CBOR::from(items).to_cbor_data())?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl PendingStore for FilePendingStore {
+    fn put(&mut self, id: ARID, record: PendingRecord) -> Result<()> {
+        self.records.put(id, record)?;
+        self.save()
+    }
+
+    fn get(&self, id: ARID) -> Result<Option<PendingRecord>> {
+        self.records.get(id)
+    }
+
+    fn take(&mut self, id: ARID) -> Result<Option<PendingRecord>> {
+        let record = self.records.take(id)?;
+        if record.is_some() {
+            self.save()?;
+        }
+        Ok(record)
+    }
+
+    fn expire(&mut self, now: Date) -> Result<Vec<ARID>> {
+        let expired = self.records.expire(now)?;
+        if !expired.is_empty() {
+            self.save()?;
+        }
+        Ok(expired)
+    }
+}
+
+/// Correlates responses with the requests they answer.
+///
+/// Because the tracker keeps nothing but what is in its [`PendingStore`], a
+/// client that uses a persistent store can be restarted between sending a
+/// request and receiving its response.
+#[derive(Debug)]
+pub struct PendingRequests<S: PendingStore> {
+    store: S,
+    delegates: HashMap<XID, Vec<XID>>,
+}
+
+impl<S: PendingStore> PendingRequests<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            delegates: HashMap::new(),
+        }
+    }
+
+    /// Also accepts responses to requests sealed to `principal` from the
+    /// delegates listed in its XID document, for peers that are a fleet of
+    /// servers answering on behalf of a single published identity.
+    ///
+    /// The delegates are recorded with each request sealed to `principal`,
+    /// so they cannot answer requests sent to anyone else.
+    pub fn with_delegates_of(mut self, principal: &XIDDocument) -> Self {
+        self.delegates.insert(
+            principal.xid(),
+            principal
+                .delegates()
+                .iter()
+                .map(|delegate| delegate.xid())
+                .collect(),
+        );
+        self
+    }
+
+    pub fn store(&self) -> &S { &self.store }
+
+    pub fn into_store(self) -> S { self.store }
+
+    /// Seals `request` to `peer` and records it as pending.
+    ///
+    /// The continuation issued with the request is valid until `deadline`,
+    /// after which a response is no longer accepted.
+    pub fn seal(
+        &mut self,
+        request: &SealedRequest,
+        deadline: Option<Date>,
+        sender: &dyn Signer,
+        peer: &XIDDocument,
+    ) -> Result<Envelope> {
+        let options = SealOptions::default();
+        let deadline = deadline.map(|date| options.normalize_date(date));
+        let artifacts =
+            request.seal_detailed(deadline, Some(sender), &[peer], &options)?;
+        let record = PendingRecord {
+            deadline,
+            function: request.request().body().function().clone(),
+            peer: peer.xid(),
+            delegates: self
+                .delegates
+                .get(&peer.xid())
+                .cloned()
+                .unwrap_or_default(),
+            continuation: artifacts
+                .own_continuation
+                .expect("requests always carry a continuation"),
+        };
+        self.store.put(request.id(), record)?;
+        Ok(artifacts.envelope)
+    }
+
+    /// Matches a parsed response to its pending request, removing the request
+    /// from the store once the response is accepted.
+    ///
+    /// The response must come from the peer the request was sealed to, whose
+    /// document must carry the inception key its XID is derived from. The
+    /// stored continuation is decrypted and revalidated against the
+    /// response's ID and `now`, and the state echoed back in the response
+    /// must be the state we originally issued. A response failing any of
+    /// these checks leaves the request pending, so that a forged or stray
+    /// response cannot cancel it.
+    pub fn match_response(
+        &mut self,
+        response: &SealedResponse,
+        now: Date,
+        recipient: &PrivateKeys,
+    ) -> Result<PendingRecord> {
+        let id = response.id().ok_or(Error::UnknownPendingRequest(None))?;
+        let record = self
+            .store
+            .get(id)?
+            .ok_or(Error::UnknownPendingRequest(Some(id)))?;
+        if record.is_expired(now) {
+            return Err(Error::PendingRequestExpired(id));
+        }
+        sealing::sender_verification_key(response.sender(), None)?;
+        let sender = response.sender().xid();
+        if sender != record.peer && !record.delegates.contains(&sender) {
+            return Err(Error::UnexpectedSender {
+                expected: record.peer,
+                found: sender,
+            });
+        }
+        let continuation = Continuation::try_from_envelope(
+            &record.continuation,
+            Some(id),
+            Some(now),
+            Some(recipient),
+        )?;
+        ContinuationReceipt::new(&continuation)
+            .validate_state(response.state())?;
+        self.store.take(id)?;
+        Ok(record)
+    }
+
+    /// Removes all pending requests whose deadline has passed.
+    pub fn expire(&mut self, now: Date) -> Result<Vec<ARID>> {
+        self.store.expire(now)
+    }
+}
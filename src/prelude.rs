@@ -1,5 +1,9 @@
 pub use crate::{
-    Continuation, Error, Result, SealedEvent, SealedEventBehavior,
-    SealedRequest, SealedRequestBehavior, SealedResponse,
-    SealedResponseBehavior,
+    AssertionStripFilter, CompressionPolicy, Continuation, ContinuationContext,
+    ContinuationFilter, ContinuationReceipt, DatePrecision, Error, FieldLimits,
+    MessageKind, ParseLimits, ParseOptions, ParsePolicy, ParseStage,
+    PartialParse, Result, SealOptions, SealedArtifacts, SealedEvent,
+    SealedEventBehavior, SealedEventEnvelope, SealedRequest,
+    SealedRequestBehavior, SealedRequestEnvelope, SealedResponse,
+    SealedResponseBehavior, SealedResponseEnvelope,
 };
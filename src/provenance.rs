@@ -0,0 +1,75 @@
+use bc_envelope::prelude::*;
+
+use crate::{Result, SealOptions, consts};
+
+pub(crate) const PROVENANCE: &str = "provenance";
+const PROTOCOL_VERSION: &str = "protocolVersion";
+const BUILD: &str = "build";
+
+/// Records which implementation sealed a message, added inside the signature
+/// when sealing with
+/// [`SealOptions::with_provenance`](crate::SealOptions::with_provenance).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    crate_version: String,
+    protocol_version: u32,
+    build: Option<String>,
+}
+
+impl Provenance {
+    /// The provenance of messages sealed by this crate, with an optional
+    /// application build identifier.
+    pub fn current(build: Option<String>) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: consts::PROTOCOL_VERSION,
+            build,
+        }
+    }
+
+    /// The version of the `gstp` crate that sealed the message.
+    pub fn crate_version(&self) -> &str { &self.crate_version }
+
+    pub fn protocol_version(&self) -> u32 { self.protocol_version }
+
+    /// The build identifier supplied by the sending application, if any.
+    pub fn build(&self) -> Option<&str> { self.build.as_deref() }
+
+    fn to_envelope(&self) -> Envelope {
+        Envelope::new(self.crate_version.as_str())
+            .add_assertion(PROTOCOL_VERSION, self.protocol_version)
+            .add_optional_assertion(BUILD, self.build.clone())
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<Self> {
+        Ok(Self {
+            crate_version: envelope.extract_subject()?,
+            protocol_version: envelope
+                .extract_object_for_predicate(PROTOCOL_VERSION)?,
+            build: envelope.extract_optional_object_for_predicate(BUILD)?,
+        })
+    }
+}
+
+/// Adds our provenance to a message before it is signed, if `options` ask
+/// for it.
+pub(crate) fn add_provenance(
+    message: Envelope,
+    options: &SealOptions,
+) -> Envelope {
+    if !options.provenance() {
+        return message;
+    }
+    let provenance = Provenance::current(options.build_id().map(String::from));
+    message.add_assertion(PROVENANCE, provenance.to_envelope())
+}
+
+/// Reads the provenance of a message, if it has one.
+pub(crate) fn parse_provenance(
+    message: &Envelope,
+) -> Result<Option<Provenance>> {
+    message
+        .optional_object_for_predicate(PROVENANCE)?
+        .map(|provenance| Provenance::try_from_envelope(&provenance))
+        .transpose()
+}
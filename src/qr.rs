@@ -0,0 +1,150 @@
+//! Multipart UR encoding of sealed messages for animated QR codes.
+//!
+//! A message too large for a single QR code is split into fountain-coded
+//! `ur:envelope` parts by [`Encoder`], to be displayed in a loop. An
+//! [`Accumulator`] on the scanning side takes parts in whatever order they
+//! are read, including repeats, until it can reassemble the envelope.
+
+use std::collections::HashSet;
+
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+const UR_TYPE: &str = "envelope";
+
+fn invalid_part(error: impl std::fmt::Display) -> Error {
+    Error::InvalidUR(dcbor::Error::msg(error.to_string()))
+}
+
+/// Emits the multipart UR strings of an envelope.
+///
+/// The first [`Self::parts_count`] parts each carry one fragment of the
+/// message. The parts after them mix fragments together, so the sequence
+/// never ends and a receiver that missed a frame can catch up from any later
+/// one.
+pub struct Encoder {
+    encoder: ur::Encoder<'static>,
+}
+
+impl Encoder {
+    /// Creates an encoder for `envelope` whose parts carry at most
+    /// `max_fragment_len` bytes of the message each.
+    pub fn new(envelope: &Envelope, max_fragment_len: usize) -> Result<Self> {
+        let data = envelope.untagged_cbor().to_cbor_data();
+        let encoder = ur::Encoder::new(&data, max_fragment_len, UR_TYPE)
+            .map_err(invalid_part)?;
+        Ok(Self { encoder })
+    }
+
+    /// The number of fragments the message is split into, and so the fewest
+    /// parts a receiver needs.
+    pub fn parts_count(&self) -> usize { self.encoder.fragment_count() }
+
+    /// Returns the next part in the sequence.
+    pub fn next_part(&mut self) -> Result<String> {
+        self.encoder.next_part().map_err(invalid_part)
+    }
+}
+
+impl Iterator for Encoder {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> { self.next_part().ok() }
+}
+
+/// Reassembles an envelope from the multipart UR strings of an [`Encoder`].
+///
+/// Parts may arrive in any order and more than once. A part that is not a
+/// multipart `ur:envelope` fails with [`Error::InvalidUR`], and one that
+/// belongs to a different message than the parts before it fails with
+/// [`Error::ForeignPart`]; neither disturbs the parts already received.
+#[derive(Default)]
+pub struct Accumulator {
+    decoder: ur::Decoder,
+    parts_count: Option<usize>,
+    received: HashSet<usize>,
+}
+
+impl Accumulator {
+    pub fn new() -> Self { Self::default() }
+
+    /// Takes a scanned part, returning `true` once the envelope is complete.
+    ///
+    /// Scanners that read QR codes in alphanumeric mode return the part in
+    /// upper case, which is accepted.
+    pub fn receive(&mut self, part: &str) -> Result<bool> {
+        let part = part.trim().to_ascii_lowercase();
+        let (sequence, count) = parse_sequence(&part)?;
+        if self.is_complete() {
+            return Ok(true);
+        }
+        self.decoder.receive(&part).map_err(|error| match error {
+            ur::ur::Error::Fountain(ur::fountain::Error::InconsistentPart) => {
+                Error::ForeignPart
+            }
+            error => invalid_part(error),
+        })?;
+        self.parts_count = Some(count);
+        self.received.insert(sequence);
+        Ok(self.is_complete())
+    }
+
+    /// Whether enough parts have been received to reassemble the envelope.
+    pub fn is_complete(&self) -> bool { self.decoder.complete() }
+
+    /// An estimate of how much of the envelope has been received, from 0 to
+    /// 100.
+    ///
+    /// Only reaches 100 once the envelope is complete. Until then it counts
+    /// the distinct parts received against the number of fragments, which
+    /// the fountain code usually needs a few more than.
+    pub fn percent_complete(&self) -> u8 {
+        if self.is_complete() {
+            return 100;
+        }
+        let Some(count) = self.parts_count else {
+            return 0;
+        };
+        let received = self.received.len().min(count);
+        (received * 100 / count).min(99) as u8
+    }
+
+    /// The reassembled envelope, or `None` if it is not yet complete.
+    pub fn envelope(&self) -> Result<Option<Envelope>> {
+        let Some(data) = self.decoder.message().map_err(invalid_part)? else {
+            return Ok(None);
+        };
+        let cbor = CBOR::try_from_data(data).map_err(Error::InvalidUR)?;
+        Ok(Some(
+            Envelope::from_untagged_cbor(cbor).map_err(Error::InvalidUR)?,
+        ))
+    }
+}
+
+/// Checks that `part` is a multipart envelope UR, returning its sequence
+/// number and the number of fragments in its message.
+fn parse_sequence(part: &str) -> Result<(usize, usize)> {
+    let Some(rest) = part.strip_prefix("ur:") else {
+        return Err(invalid_part("not a UR"));
+    };
+    let mut components = rest.split('/');
+    let ur_type = components.next().unwrap_or_default();
+    if ur_type != UR_TYPE {
+        return Err(invalid_part(format!(
+            "expected UR type {UR_TYPE}, found {ur_type}"
+        )));
+    }
+    let (Some(sequence), Some(_), None) =
+        (components.next(), components.next(), components.next())
+    else {
+        return Err(invalid_part("not a multipart UR"));
+    };
+    sequence
+        .split_once('-')
+        .and_then(|(sequence, count)| {
+            Some((sequence.parse().ok()?, count.parse().ok()?))
+        })
+        .filter(|&(sequence, count)| sequence > 0 && count > 0)
+        .ok_or_else(|| invalid_part("invalid part indices"))
+}
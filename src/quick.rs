@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use bc_components::{ARID, PrivateKeys, keypair};
+use bc_envelope::prelude::*;
+use bc_xid::{XIDDocument, XIDGenesisMarkOptions, XIDInceptionKeyOptions};
+
+use crate::{Result, SealedRequest};
+
+/// How long the continuation of a request sealed by [`seal_request`] is
+/// valid.
+pub const QUICK_REQUEST_VALIDITY: Duration = Duration::from_secs(60);
+
+/// A party's XID document together with the private keys that sign and
+/// decrypt its messages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Identity {
+    document: XIDDocument,
+    private_keys: PrivateKeys,
+}
+
+impl Identity {
+    pub fn new(document: XIDDocument, private_keys: PrivateKeys) -> Self {
+        Self { document, private_keys }
+    }
+
+    /// Generates a new identity with a fresh key pair.
+    pub fn generate() -> Self {
+        let (private_keys, public_keys) = keypair();
+        let document = XIDDocument::new(
+            XIDInceptionKeyOptions::PublicAndPrivateKeys(
+                public_keys,
+                private_keys.clone(),
+            ),
+            XIDGenesisMarkOptions::None,
+        );
+        Self::new(document, private_keys)
+    }
+
+    pub fn document(&self) -> &XIDDocument { &self.document }
+
+    pub fn private_keys(&self) -> &PrivateKeys { &self.private_keys }
+}
+
+impl AsRef<XIDDocument> for Identity {
+    fn as_ref(&self) -> &XIDDocument { &self.document }
+}
+
+/// Seals a request for `function` from `client` to `server`, dated now and
+/// valid for [`QUICK_REQUEST_VALIDITY`], returning its ID and the sealed
+/// envelope.
+///
+/// ```
+/// use bc_envelope::prelude::*;
+/// use gstp::Identity;
+///
+/// let client = Identity::generate();
+/// let server = Identity::generate();
+///
+/// let (id, sealed) = gstp::seal_request(
+///     "greet",
+///     &[("name", Envelope::new("Alice"))],
+///     &client,
+///     server.document(),
+/// )?;
+///
+/// let request = gstp::open_request(&sealed, &server)?;
+/// assert_eq!(request.id(), id);
+/// assert_eq!(
+///     request.extract_object_for_parameter::<String>("name")?,
+///     "Alice"
+/// );
+/// # Ok::<(), gstp::Error>(())
+/// ```
+pub fn seal_request(
+    function: impl Into<Function>,
+    params: &[(&str, Envelope)],
+    client: &Identity,
+    server: &XIDDocument,
+) -> Result<(ARID, Envelope)> {
+    let id = ARID::new();
+    let now = Date::now();
+    let request = params.iter().fold(
+        SealedRequest::new(function, id, client.document()).with_date(now),
+        |request, (name, value)| request.with_parameter(*name, value.clone()),
+    );
+    let envelope = request.to_envelope(
+        Some(now + QUICK_REQUEST_VALIDITY),
+        Some(client.private_keys()),
+        Some(server),
+    )?;
+    Ok((id, envelope))
+}
+
+/// Opens a request sealed to `server`, checking that it is signed by its
+/// sender and that any continuation it carries has not expired.
+pub fn open_request(
+    envelope: &Envelope,
+    server: &Identity,
+) -> Result<SealedRequest> {
+    SealedRequest::try_from_envelope(
+        envelope,
+        None,
+        Some(Date::now()),
+        server.private_keys(),
+    )
+}
@@ -0,0 +1,110 @@
+use bc_components::{ARID, Digest, DigestProvider, EncapsulationPrivateKey};
+use bc_envelope::prelude::*;
+
+use crate::{
+    Continuation, Error, Result, SealedResponse, SealedResponseBehavior,
+};
+
+const STATE_DIGEST: &str = "stateDigest";
+const VALID_ID: &str = "validId";
+const VALID_UNTIL: &str = "validUntil";
+const ISSUED_AT: &str = "issuedAt";
+
+/// A record of a continuation we issued, small enough to persist, that lets us
+/// recognize the state when a peer echoes it back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContinuationReceipt {
+    /// The digest of the state, or `None` if the continuation carried no
+    /// state.
+    pub state_digest: Option<Digest>,
+    /// The ID the continuation is valid for.
+    pub valid_id: Option<ARID>,
+    /// The time until which the continuation is valid.
+    pub valid_until: Option<Date>,
+    /// The time the continuation was issued.
+    pub issued_at: Option<Date>,
+}
+
+impl ContinuationReceipt {
+    pub(crate) fn new(continuation: &Continuation) -> Self {
+        let state = continuation.state();
+        Self {
+            state_digest: (!state.is_null()).then(|| state.digest()),
+            valid_id: continuation.id(),
+            valid_until: continuation.valid_until(),
+            issued_at: continuation.issued_at(),
+        }
+    }
+
+    /// Checks that `echoed` is the state this receipt was issued for.
+    pub fn validate_state(&self, echoed: Option<&Envelope>) -> Result<()> {
+        let echoed_digest = echoed
+            .filter(|state| !state.is_null())
+            .map(|state| state.digest());
+        if echoed_digest != self.state_digest {
+            return Err(Error::EchoedStateMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl From<ContinuationReceipt> for Envelope {
+    fn from(receipt: ContinuationReceipt) -> Self {
+        Envelope::new(known_values::UNIT)
+            .add_optional_assertion(STATE_DIGEST, receipt.state_digest)
+            .add_optional_assertion(VALID_ID, receipt.valid_id)
+            .add_optional_assertion(VALID_UNTIL, receipt.valid_until)
+            .add_optional_assertion(ISSUED_AT, receipt.issued_at)
+    }
+}
+
+impl TryFrom<Envelope> for ContinuationReceipt {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        Ok(Self {
+            state_digest: envelope
+                .extract_optional_object_for_predicate(STATE_DIGEST)?,
+            valid_id: envelope
+                .extract_optional_object_for_predicate(VALID_ID)?,
+            valid_until: envelope
+                .extract_optional_object_for_predicate(VALID_UNTIL)?,
+            issued_at: envelope
+                .extract_optional_object_for_predicate(ISSUED_AT)?,
+        })
+    }
+}
+
+/// The components produced by sealing a message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SealedArtifacts {
+    /// The sealed message, ready to transmit.
+    pub envelope: Envelope,
+    /// The continuation we issued with the message, encrypted to ourselves,
+    /// exactly as it was sent. Requests always carry one unless sealed
+    /// one-way.
+    pub own_continuation: Option<Envelope>,
+    /// A receipt for the continuation we issued with the message.
+    pub continuation_receipt: Option<ContinuationReceipt>,
+    /// The private half of the ephemeral key a request was sealed with under
+    /// [`SealOptions::with_ephemeral_keys`](crate::SealOptions::with_ephemeral_keys),
+    /// needed to parse the response.
+    pub ephemeral_key: Option<EncapsulationPrivateKey>,
+}
+
+/// How a parsed message was sealed, as found while opening it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SealingReport {
+    /// Whether the signed payload was compressed inside the encryption, as
+    /// under [`CompressionPolicy::Payload`](crate::CompressionPolicy::Payload).
+    pub compressed: bool,
+}
+
+/// Checks that the state a response echoes back is the state we issued with
+/// the request described by `receipt`.
+pub fn validate_echoed_state(
+    receipt: &ContinuationReceipt,
+    response: &SealedResponse,
+) -> Result<()> {
+    receipt.validate_state(response.state())
+}
@@ -0,0 +1,171 @@
+//! Recovering a workflow whose continuation expired.
+//!
+//! Rather than only failing a request whose continuation has expired, a
+//! server can answer with a [`RecoveryHint`]: the function to call to
+//! re-establish state, and a grace token, a short-lived continuation that
+//! refers to the expired one by digest without revealing its state. The
+//! client's [`RecoveryAdvisor`] recognizes such failures and composes the
+//! recovery request, which returns the grace token as its continuation.
+
+use std::time::Duration;
+
+use bc_components::{ARID, Digest};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, Error, Result, SealOptions, SealedRequest,
+    SealedRequestBehavior, SealedResponse, sealing,
+};
+
+/// The error a failure response carries when the continuation returned to
+/// the server had expired and the workflow can be recovered.
+pub const CONTINUATION_EXPIRED: &str = "continuationExpired";
+
+const RECOVERY_FUNCTION: &str = "recoveryFunction";
+const GRACE_TOKEN: &str = "graceToken";
+const GRACE_UNTIL: &str = "graceUntil";
+const EXPIRED_CONTINUATION: &str = "expiredContinuation";
+
+/// How to recover from an expired continuation, as carried by the error of a
+/// failure response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoveryHint {
+    /// The function to call to re-establish state.
+    pub function: Function,
+    /// The continuation to return with the recovery request, encrypted to
+    /// the server.
+    pub grace_token: Envelope,
+    /// When the grace token expires.
+    pub grace_until: Date,
+}
+
+impl From<RecoveryHint> for Envelope {
+    fn from(hint: RecoveryHint) -> Self {
+        Envelope::new(CONTINUATION_EXPIRED)
+            .add_assertion(RECOVERY_FUNCTION, hint.function)
+            .add_assertion(GRACE_TOKEN, hint.grace_token)
+            .add_assertion(GRACE_UNTIL, hint.grace_until)
+    }
+}
+
+impl TryFrom<Envelope> for RecoveryHint {
+    type Error = Error;
+
+    fn try_from(envelope: Envelope) -> Result<Self> {
+        if envelope.extract_subject::<String>().ok().as_deref()
+            != Some(CONTINUATION_EXPIRED)
+        {
+            return Err(Error::InvalidRecoveryHint);
+        }
+        Ok(Self {
+            function: envelope
+                .object_for_predicate(RECOVERY_FUNCTION)?
+                .try_into()?,
+            grace_token: envelope.object_for_predicate(GRACE_TOKEN)?,
+            grace_until: envelope.extract_object_for_predicate(GRACE_UNTIL)?,
+        })
+    }
+}
+
+/// Composes the failure response to a request whose continuation, with the
+/// given digest, had expired, offering recovery through `function` for
+/// `grace` from now.
+///
+/// The digest of the expired continuation is reported by
+/// [`PartialParse::returned_continuation`](crate::PartialParse::returned_continuation)
+/// when parsing the request fails with [`Error::ContinuationExpired`]. The
+/// grace token is encrypted like any continuation the server issues.
+pub fn continuation_expired_response(
+    request_id: ARID,
+    sender: &XIDDocument,
+    expired_continuation: Digest,
+    function: impl Into<Function>,
+    grace: Duration,
+    options: &SealOptions,
+) -> Result<SealedResponse> {
+    let now = options.sealing_date();
+    let grace_until = options.normalize_date(now + grace);
+    let state = Envelope::new(GRACE_TOKEN)
+        .add_assertion(EXPIRED_CONTINUATION, expired_continuation);
+    let function = function.into();
+    let grace_token = sealing::issue_continuation(
+        &Continuation::new(state)
+            .with_valid_until(grace_until)
+            .with_issued_at(now),
+        sender,
+        request_id,
+        Some(&function),
+        options,
+    )?;
+    let hint = RecoveryHint { function, grace_token, grace_until };
+    Ok(SealedResponse::new_failure(request_id, sender).with_error(hint))
+}
+
+/// Returns the digest of the expired continuation a grace token refers to,
+/// if `state`, as returned to the server with a recovery request, is that of
+/// a grace token.
+pub fn recovered_continuation(state: &Envelope) -> Option<Digest> {
+    if state.extract_subject::<String>().ok().as_deref() != Some(GRACE_TOKEN) {
+        return None;
+    }
+    state
+        .extract_object_for_predicate(EXPIRED_CONTINUATION)
+        .ok()
+}
+
+/// Recognizes failures that offer recovery from an expired continuation,
+/// and composes the requests that recover from them.
+///
+/// Only recoveries through functions the client has allowed are attempted
+/// automatically.
+#[derive(Clone, Debug, Default)]
+pub struct RecoveryAdvisor {
+    auto_recoverable: Vec<Function>,
+}
+
+impl RecoveryAdvisor {
+    pub fn new() -> Self { Self::default() }
+
+    /// Allows automatic recovery through `function`.
+    pub fn with_auto_recovery(mut self, function: impl Into<Function>) -> Self {
+        self.auto_recoverable.push(function.into());
+        self
+    }
+
+    /// Returns the recovery hint carried by `response`, if it is a failure
+    /// that offers recovery.
+    pub fn recovery_hint(
+        &self,
+        response: &SealedResponse,
+    ) -> Option<RecoveryHint> {
+        let error = response.error().ok()?;
+        RecoveryHint::try_from(error.clone()).ok()
+    }
+
+    /// Whether recovery through `hint` may be attempted automatically at
+    /// `now`: its function is allowed and its grace token is unexpired.
+    pub fn can_auto_recover(&self, hint: &RecoveryHint, now: Date) -> bool {
+        now < hint.grace_until && self.auto_recoverable.contains(&hint.function)
+    }
+
+    /// Composes the request that recovers through `hint`, returning its
+    /// grace token. Parameters needed to re-establish state can be added
+    /// before it is sealed.
+    ///
+    /// Fails with [`Error::RecoveryNotPossible`] unless
+    /// [`Self::can_auto_recover`].
+    pub fn compose_recovery_request(
+        &self,
+        hint: &RecoveryHint,
+        id: ARID,
+        sender: &XIDDocument,
+        now: Date,
+    ) -> Result<SealedRequest> {
+        if !self.can_auto_recover(hint, now) {
+            return Err(Error::RecoveryNotPossible);
+        }
+        Ok(SealedRequest::new(hint.function.clone(), id, sender)
+            .with_peer_continuation(hint.grace_token.clone()))
+    }
+}
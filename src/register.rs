@@ -0,0 +1,19 @@
+use std::sync::Once;
+
+static REGISTER: Once = Once::new();
+
+/// Registers the CBOR tags and known values used by GSTP messages with the
+/// global format context, so that they are named when messages are
+/// formatted.
+///
+/// Registration happens once per process; later calls return immediately.
+/// Sealing and parsing a message register automatically, so calling this is
+/// only needed to format envelopes before any message has been handled.
+pub fn register() { REGISTER.call_once(bc_envelope::register_tags); }
+
+/// Returns whether [`register`] has run in this process, either explicitly
+/// or when a message was sealed or parsed.
+///
+/// Registration done directly through `bc_envelope::register_tags` is not
+/// detected.
+pub fn is_registered() -> bool { REGISTER.is_completed() }
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use bc_components::{ARID, DigestProvider};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// Storage for the keys a [`ReplayStore`] has seen, so that users can keep
+/// them in the database of their choice.
+///
+/// Keys and values are opaque bytes.
+pub trait KeyValueBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()>;
+
+    fn remove(&mut self, key: &[u8]) -> Result<()>;
+
+    /// Returns every stored entry, in no particular order.
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Reclaims the space held by removed entries, if the backend needs to.
+    fn compact(&mut self) -> Result<()> { Ok(()) }
+}
+
+/// A [`KeyValueBackend`] that lives only as long as the process.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self { Self::default() }
+}
+
+impl KeyValueBackend for MemoryBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<()> {
+        self.entries.remove(key);
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        Ok(self
+            .entries
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
+
+/// When a [`FileLogBackend`] flushes its writes to stable storage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Writes are left to the operating system to flush. A crash of the
+    /// machine, though not of the process, may lose the most recent entries.
+    #[default]
+    Never,
+
+    /// Every write is flushed before it is acknowledged.
+    EveryWrite,
+}
+
+/// A [`KeyValueBackend`] persisted to an append-only log file.
+///
+/// Each change is appended as a length-prefixed CBOR record. When the log is
+/// opened, a final record cut short by a crash is discarded and truncated
+/// away, so only the write in progress is lost. [`KeyValueBackend::compact`]
+/// rewrites the log with only the live entries, via a temporary file that is
+/// renamed into place.
+#[derive(Debug)]
+pub struct FileLogBackend {
+    path: PathBuf,
+    file: File,
+    fsync: FsyncPolicy,
+    entries: MemoryBackend,
+}
+
+impl FileLogBackend {
+    /// Opens the log at `path`, creating an empty one if the file does not
+    /// exist.
+    pub fn open(path: impl AsRef<Path>, fsync: FsyncPolicy) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = MemoryBackend::new();
+        if path.exists() {
+            let data = fs::read(&path)?;
+            let valid_len = replay_log(&data, &mut entries)?;
+            if valid_len < data.len() {
+                OpenOptions::new()
+                    .write(true)
+                    .open(&path)?
+                    .set_len(valid_len as u64)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            fsync,
+            entries,
+        })
+    }
+
+    fn append(&mut self, record: CBOR) -> Result<()> {
+        self.file.write_all(&encode_record(&record))?;
+        if self.fsync == FsyncPolicy::EveryWrite {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// Frames a record with its length as a big-endian `u32`.
+fn encode_record(record: &CBOR) -> Vec<u8> {
+    let data = record.to_cbor_data();
+    let mut framed = (data.len() as u32).to_be_bytes().to_vec();
+    framed.extend(data);
+    framed
+}
+
+/// Applies the records in `data` to `entries`, returning the length of the
+/// intact prefix of the log.
+///
+/// A put is recorded as `[key, value]` and a removal as `[key]`. An
+/// incomplete final record is ignored, but a malformed complete one is an
+/// error.
+fn replay_log(data: &[u8], entries: &mut MemoryBackend) -> Result<usize> {
+    let mut offset = 0;
+    while let Some(header) = data.get(offset..offset + 4) {
+        let len = u32::from_be_bytes(header.try_into().unwrap()) as usize;
+        let Some(record) = data.get(offset + 4..offset + 4 + len) else {
+            break;
+        };
+        let items = CBOR::try_from_data(record)?.try_into_array()?;
+        match items.as_slice() {
+            [key, value] => entries.put(
+                &key.clone().try_into_byte_string()?,
+                &value.clone().try_into_byte_string()?,
+            )?,
+            [key] => entries.remove(&key.clone().try_into_byte_string()?)?,
+            _ => return Err(Error::InvalidReplayLog),
+        }
+        offset += 4 + len;
+    }
+    Ok(offset)
+}
+
+impl KeyValueBackend for FileLogBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.entries.get(key)
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.append(CBOR::from(vec![
+            CBOR::to_byte_string(key),
+            CBOR::to_byte_string(value),
+        ]))?;
+        self.entries.put(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Result<()> {
+        if self.entries.get(key)?.is_none() {
+            return Ok(());
+        }
+        self.append(CBOR::from(vec![CBOR::to_byte_string(key)]))?;
+        self.entries.remove(key)
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.entries.entries()
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        let mut data = Vec::new();
+        for (key, value) in self.entries.entries()? {
+            data.extend(encode_record(&CBOR::from(vec![
+                CBOR::to_byte_string(key),
+                CBOR::to_byte_string(value),
+            ])));
+        }
+        let temp_path = self.path.with_extension("tmp");
+        let mut temp = File::create(&temp_path)?;
+        temp.write_all(&data)?;
+        if self.fsync == FsyncPolicy::EveryWrite {
+            temp.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Rejects request IDs and continuations that have been seen before within
+/// a freshness window.
+///
+/// Each key is remembered until the window has passed since it was first
+/// seen, so messages older than the window must be rejected by other means,
+/// such as the expiry of their continuations. With a persistent backend,
+/// replays are still caught after a restart.
+#[derive(Debug)]
+pub struct ReplayStore<B: KeyValueBackend> {
+    backend: B,
+    window: Duration,
+    compact_every: Option<usize>,
+    inserts_since_compaction: usize,
+}
+
+impl<B: KeyValueBackend> ReplayStore<B> {
+    pub fn new(backend: B, window: Duration) -> Self {
+        Self {
+            backend,
+            window,
+            compact_every: None,
+            inserts_since_compaction: 0,
+        }
+    }
+
+    /// Compacts the store after every `inserts` newly seen keys.
+    pub fn with_compact_every(mut self, inserts: usize) -> Self {
+        self.compact_every = Some(inserts);
+        self
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+
+    pub fn into_backend(self) -> B { self.backend }
+
+    pub fn window(&self) -> Duration { self.window }
+
+    /// Records the ID of a request received at `now`, failing with
+    /// [`Error::ReplayDetected`] if it was already seen within the window.
+    pub fn check_request_id(&mut self, id: ARID, now: Date) -> Result<()> {
+        self.check(id.data(), now)
+    }
+
+    /// Records a continuation returned to us at `now`, failing with
+    /// [`Error::ReplayDetected`] if it was already returned within the
+    /// window.
+    pub fn check_continuation(
+        &mut self,
+        continuation: &Envelope,
+        now: Date,
+    ) -> Result<()> {
+        self.check(continuation.digest().data(), now)
+    }
+
+    fn check(&mut self, key: &[u8], now: Date) -> Result<()> {
+        if let Some(expires) = self.backend.get(key)? {
+            let expires = Date::try_from(CBOR::try_from_data(expires)?)?;
+            if expires > now {
+                return Err(Error::ReplayDetected);
+            }
+        }
+        let expires = now + self.window;
+        self.backend.put(key, &expires.to_cbor_data())?;
+        self.inserts_since_compaction += 1;
+        if self
+            .compact_every
+            .is_some_and(|every| self.inserts_since_compaction >= every)
+        {
+            self.compact(now)?;
+        }
+        Ok(())
+    }
+
+    /// Forgets every key whose window has passed at `now` and reclaims the
+    /// space, returning the number of keys forgotten.
+    pub fn compact(&mut self, now: Date) -> Result<usize> {
+        let mut removed = 0;
+        for (key, expires) in self.backend.entries()? {
+            let expires = Date::try_from(CBOR::try_from_data(expires)?)?;
+            if expires <= now {
+                self.backend.remove(&key)?;
+                removed += 1;
+            }
+        }
+        self.backend.compact()?;
+        self.inserts_since_compaction = 0;
+        Ok(removed)
+    }
+}
+
+/// Records the nonces of continuations returned to us, so that each is
+/// accepted only once, when consulted through
+/// [`ParseOptions::with_continuation_replay_guard`](crate::ParseOptions::with_continuation_replay_guard).
+///
+/// A nonce is recorded only once every other check on the message carrying
+/// it has passed, so a message that is refused does not use it up.
+pub trait ContinuationReplayGuard: std::fmt::Debug + Send + Sync {
+    /// Records `nonce`, failing with [`Error::ContinuationReplayed`] if it
+    /// was recorded before.
+    ///
+    /// The continuation carrying it is valid until `valid_until`, and is
+    /// still accepted for `tolerance` afterwards, so the nonce must be
+    /// remembered until then. `now` is the time of the parse, if the parse
+    /// options give one.
+    fn check_and_record(
+        &self,
+        nonce: ARID,
+        valid_until: Option<Date>,
+        now: Option<Date>,
+        tolerance: Duration,
+    ) -> Result<()>;
+}
+
+/// A [`ContinuationReplayGuard`] that remembers nonces in memory until the
+/// continuations carrying them can no longer be accepted.
+///
+/// When the parse options give the current time, nonces whose continuations
+/// have expired, allowing for the clock-skew tolerance, are pruned on each
+/// check. Without a clock nothing is pruned. The nonces of continuations
+/// that never expire are remembered for as long as the guard lives.
+#[derive(Debug, Default)]
+pub struct MemoryReplayGuard {
+    nonces: Mutex<HashMap<ARID, Option<Date>>>,
+}
+
+impl MemoryReplayGuard {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns the number of nonces remembered.
+    pub fn len(&self) -> usize { self.nonces.lock().unwrap().len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Forgets every nonce that need not be remembered at `now`, returning
+    /// the number forgotten.
+    pub fn prune(&self, now: Date) -> usize {
+        let mut nonces = self.nonces.lock().unwrap();
+        let before = nonces.len();
+        nonces.retain(|_, retain_until| {
+            retain_until.is_none_or(|retain_until| retain_until > now)
+        });
+        before - nonces.len()
+    }
+}
+
+impl ContinuationReplayGuard for MemoryReplayGuard {
+    fn check_and_record(
+        &self,
+        nonce: ARID,
+        valid_until: Option<Date>,
+        now: Option<Date>,
+        tolerance: Duration,
+    ) -> Result<()> {
+        if let Some(now) = now {
+            self.prune(now);
+        }
+        let mut nonces = self.nonces.lock().unwrap();
+        if nonces.contains_key(&nonce) {
+            return Err(Error::ContinuationReplayed);
+        }
+        nonces.insert(
+            nonce,
+            valid_until.map(|valid_until| valid_until + tolerance),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+/// The predicate marking a request sealed under [`RequestProfile::OneWay`].
+pub(crate) const ONE_WAY: &str = "oneWay";
+
+/// Whether requests expect to be answered, as agreed by every party of a
+/// deployment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RequestProfile {
+    /// Requests carry the sender's continuation, which the response must
+    /// return, and a request without one is rejected.
+    #[default]
+    Interactive,
+
+    /// Requests are one-way notifications that are never answered. They are
+    /// sealed without a continuation and marked as one-way, so that an
+    /// interactive endpoint rejects them with
+    /// [`Error::OneWayRequestNotAccepted`](crate::Error::OneWayRequestNotAccepted)
+    /// rather than as malformed.
+    OneWay,
+}
@@ -0,0 +1,129 @@
+use bc_components::{ARID, Digest, DigestProvider};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result, SealedResponse};
+
+pub(crate) const CHUNK_INDEX: &str = "chunkIndex";
+pub(crate) const CHUNK_COUNT: &str = "chunkCount";
+const RESULT_DIGEST: &str = "resultDigest";
+
+/// One part of a result split across several responses by
+/// [`SealedResponse::seal_chunked`]. Every chunk carries the manifest of the
+/// whole: the number of chunks and the digest of the reassembled result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ResultChunk {
+    pub index: usize,
+    pub count: usize,
+    pub digest: Digest,
+    pub data: ByteString,
+}
+
+impl ResultChunk {
+    /// Splits the encoded `result` into chunks of at most `chunk_size` bytes.
+    pub fn split(result: &Envelope, chunk_size: usize) -> Vec<Self> {
+        let digest = result.digest();
+        let data = result.to_cbor_data();
+        let count = data.len().div_ceil(chunk_size);
+        data.chunks(chunk_size)
+            .enumerate()
+            .map(|(index, part)| Self {
+                index,
+                count,
+                digest,
+                data: part.into(),
+            })
+            .collect()
+    }
+
+    pub fn to_envelope(&self) -> Envelope {
+        Envelope::new(self.data.clone())
+            .add_assertion(CHUNK_INDEX, self.index)
+            .add_assertion(CHUNK_COUNT, self.count)
+            .add_assertion(RESULT_DIGEST, self.digest)
+    }
+
+    /// Returns the chunk carried by `result`, or `None` if it is a result
+    /// sent whole.
+    pub fn try_from_result(result: &Envelope) -> Result<Option<Self>> {
+        let Some(count) =
+            result.extract_optional_object_for_predicate(CHUNK_COUNT)?
+        else {
+            return Ok(None);
+        };
+        let chunk = Self {
+            index: result.extract_object_for_predicate(CHUNK_INDEX)?,
+            count,
+            digest: result.extract_object_for_predicate(RESULT_DIGEST)?,
+            data: result.extract_subject()?,
+        };
+        if chunk.index >= chunk.count {
+            return Err(Error::InvalidResultChunk);
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// Collects the results of parsed responses, reassembling those that were
+/// split across several responses by [`SealedResponse::seal_chunked`].
+///
+/// The chunks of a result may arrive in any order, and a result sent whole
+/// is returned as soon as it is added, so a client can handle both forms
+/// alike.
+#[derive(Clone, Debug, Default)]
+pub struct ResultAssembler {
+    id: Option<ARID>,
+    digest: Option<Digest>,
+    chunks: Vec<Option<ByteString>>,
+}
+
+impl ResultAssembler {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds a successful response, returning its result once it is
+    /// complete.
+    ///
+    /// Fails with [`Error::InvalidResultChunk`] if a chunk does not belong to
+    /// the result being assembled, or if the reassembled result does not
+    /// match the digest in its manifest.
+    pub fn add(
+        &mut self,
+        response: &SealedResponse,
+    ) -> Result<Option<Envelope>> {
+        let result = response.result()?;
+        let Some(chunk) = ResultChunk::try_from_result(result)? else {
+            return Ok(Some(result.clone()));
+        };
+        if self.chunks.is_empty() {
+            self.id = response.id();
+            self.digest = Some(chunk.digest);
+            self.chunks = vec![None; chunk.count];
+        } else if self.id != response.id()
+            || self.digest != Some(chunk.digest)
+            || self.chunks.len() != chunk.count
+        {
+            return Err(Error::InvalidResultChunk);
+        }
+        self.chunks[chunk.index] = Some(chunk.data);
+        if self.chunks.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+
+        let data: Vec<u8> = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .flatten()
+            .flat_map(Vec::from)
+            .collect();
+        let digest = self.digest.take();
+        self.id = None;
+        let result = Envelope::try_from_cbor_data(data)
+            .map_err(|_| Error::InvalidResultChunk)?;
+        if Some(result.digest()) != digest {
+            return Err(Error::InvalidResultChunk);
+        }
+        Ok(Some(result))
+    }
+
+    /// Returns `true` if some chunks of a result have been added but not
+    /// all of them.
+    pub fn is_pending(&self) -> bool { !self.chunks.is_empty() }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+use bc_envelope::prelude::*;
+
+use crate::Result;
+
+/// Maps the result of a response to the view a particular recipient should
+/// see, as given to
+/// [`SealedResponse::to_envelopes_per_recipient`](crate::SealedResponse::to_envelopes_per_recipient).
+///
+/// A transform may return the result unchanged, a redacted version of it,
+/// or an error to abort sealing.
+pub trait ResultTransform: std::fmt::Debug + Send + Sync {
+    fn transform(&self, result: Envelope) -> Result<Envelope>;
+}
+
+/// A transform that elides every assertion on the result whose predicate is
+/// one of a given set.
+///
+/// Eliding keeps the digest of the result, so a recipient can still show
+/// that its view is part of the full result.
+#[derive(Clone, Debug)]
+pub struct AssertionElideTransform {
+    predicates: Vec<Envelope>,
+}
+
+impl AssertionElideTransform {
+    pub fn new<P>(predicates: impl IntoIterator<Item = P>) -> Self
+    where
+        P: EnvelopeEncodable,
+    {
+        Self {
+            predicates: predicates
+                .into_iter()
+                .map(|predicate| predicate.into_envelope())
+                .collect(),
+        }
+    }
+}
+
+impl ResultTransform for AssertionElideTransform {
+    fn transform(&self, result: Envelope) -> Result<Envelope> {
+        let target: HashSet<Digest> = result
+            .assertions()
+            .into_iter()
+            .filter(|assertion| {
+                assertion.as_predicate().is_some_and(|predicate| {
+                    self.predicates
+                        .iter()
+                        .any(|elided| elided.digest() == predicate.digest())
+                })
+            })
+            .map(|assertion| assertion.digest())
+            .collect();
+        Ok(result.elide_removing_set(&target))
+    }
+}
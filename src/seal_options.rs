@@ -0,0 +1,651 @@
+use std::{sync::Arc, time::Duration};
+
+use bc_components::{
+    EncapsulationPublicKey, KeyDerivationMethod, SymmetricKey,
+};
+use bc_envelope::prelude::*;
+
+use crate::{
+    ContinuationFilter, ContinuationMetrics, ContinuationMode, Error,
+    FieldLimits, RequestProfile, Result, SessionKeys, StateEpochs, consts,
+    continuation,
+};
+
+/// How the signed payload of a sealed message is compressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionPolicy {
+    /// The signed payload is encrypted as-is.
+    #[default]
+    None,
+
+    /// The signed payload is compressed before it is encrypted to the
+    /// recipients. The compressed form is only kept if it is actually smaller
+    /// than the original, so incompressible payloads are stored unchanged.
+    ///
+    /// Compression never changes digests, so the signature remains verifiable
+    /// after the recipient decompresses the payload. Messages that are not
+    /// encrypted to any recipient are never compressed.
+    Payload,
+}
+
+/// How the continuations we issue are encrypted to ourselves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContinuationSealer {
+    /// Encapsulated to a public key, as continuations are by default to the
+    /// sender's own encryption key.
+    PublicKey(EncapsulationPublicKey),
+
+    /// Encrypted with a long-lived secret that only we hold, which is faster
+    /// and smaller than encapsulation. Continuations returned to us must then
+    /// be parsed with the same key, through
+    /// [`ParseOptions::with_continuation_symmetric_key`](crate::ParseOptions::with_continuation_symmetric_key).
+    Symmetric(SymmetricKey),
+}
+
+/// The precision to which the dates in a sealed message are truncated before
+/// it is signed.
+///
+/// A date is encoded as a whole number of seconds if it has no fractional
+/// part, and as a float otherwise, so the same instant written with different
+/// precisions has different digests. Truncating every date to a precision
+/// agreed with the peer keeps digests stable across implementations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatePrecision {
+    /// Dates are truncated to whole seconds.
+    Seconds,
+
+    /// Dates are truncated to whole milliseconds.
+    Milliseconds,
+}
+
+impl DatePrecision {
+    /// Truncates `date` to this precision.
+    pub fn normalize(self, date: Date) -> Date {
+        let nanos = date.datetime().timestamp_subsec_nanos();
+        let kept = match self {
+            DatePrecision::Seconds => 0,
+            DatePrecision::Milliseconds => nanos - nanos % 1_000_000,
+        };
+        date - Duration::from_nanos((nanos - kept).into())
+    }
+}
+
+impl Default for DatePrecision {
+    fn default() -> Self { consts::DEFAULT_DATE_PRECISION }
+}
+
+/// How much of the sender's XID document is disclosed in a sealed message.
+///
+/// Undisclosed parts of the document are elided rather than removed, so its
+/// digest, and the signature over the message, are unchanged, and the sender
+/// can later prove what an elided part contained.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SenderDisclosure {
+    /// The whole document is disclosed.
+    #[default]
+    Full,
+
+    /// Only the key the message is verified with is disclosed, without its
+    /// endpoints or permissions.
+    VerificationOnly,
+
+    /// The verification key, and the assertions of the document with the
+    /// given predicates, are disclosed.
+    Custom(Vec<KnownValue>),
+}
+
+/// What [`SealedResponse::seal_chunked`](crate::SealedResponse::seal_chunked)
+/// does with a response too large for the recipient's maximum message size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChunkingFallback {
+    /// Sealing fails with
+    /// [`Error::ResponseTooLarge`](crate::Error::ResponseTooLarge).
+    #[default]
+    Fail,
+
+    /// The result is split across several responses, each small enough for
+    /// the recipient, which reassembles it with a
+    /// [`ResultAssembler`](crate::ResultAssembler).
+    Chunk,
+}
+
+/// What sealing does with a continuation that would be valid for longer than
+/// the maximum set with
+/// [`SealOptions::with_max_continuation_validity`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidityOverflow {
+    /// Sealing fails with
+    /// [`Error::ContinuationValidityTooLong`](crate::Error::ContinuationValidityTooLong).
+    #[default]
+    Reject,
+
+    /// The continuation is sealed to expire at the maximum instead.
+    Clamp,
+}
+
+/// Options controlling how a sealed message is turned into an envelope.
+#[derive(Clone, Debug)]
+pub struct SealOptions {
+    compression: CompressionPolicy,
+    continuation_filter: Option<Arc<dyn ContinuationFilter>>,
+    continuation_metrics: Option<Arc<dyn ContinuationMetrics>>,
+    recipient_hints: bool,
+    transport_expiry_hints: bool,
+    continuation_sealer: Option<ContinuationSealer>,
+    state_epochs: Option<Arc<StateEpochs>>,
+    continuation_mode: ContinuationMode,
+    state_compression: bool,
+    continuation_expiry_hints: bool,
+    continuation_nonces: bool,
+    function_binding: bool,
+    sender_binding: bool,
+    date_precision: DatePrecision,
+    session: Option<SessionKeys>,
+    sender_disclosure: SenderDisclosure,
+    gstp_version: u32,
+    ephemeral_keys: bool,
+    password_derivation: KeyDerivationMethod,
+    provenance: bool,
+    build_id: Option<String>,
+    component_limits: Option<FieldLimits>,
+    max_state_size: Option<usize>,
+    max_continuation_validity: Option<Duration>,
+    validity_overflow: ValidityOverflow,
+    now: Option<Date>,
+    valid_from: Option<Date>,
+    recipient_max_size: Option<usize>,
+    chunking_fallback: ChunkingFallback,
+    request_profile: RequestProfile,
+    state_on_failure: bool,
+    allow_unprotected: bool,
+}
+
+impl Default for SealOptions {
+    fn default() -> Self {
+        Self {
+            compression: CompressionPolicy::default(),
+            continuation_filter: None,
+            continuation_metrics: None,
+            recipient_hints: false,
+            transport_expiry_hints: false,
+            continuation_sealer: None,
+            state_epochs: None,
+            continuation_mode: ContinuationMode::Inline,
+            state_compression: false,
+            continuation_expiry_hints: consts::DEFAULT_CONTINUATION_POLICY
+                .expiry_hints,
+            continuation_nonces: true,
+            function_binding: false,
+            sender_binding: false,
+            date_precision: DatePrecision::default(),
+            session: None,
+            sender_disclosure: SenderDisclosure::default(),
+            gstp_version: consts::PROTOCOL_VERSION,
+            ephemeral_keys: false,
+            password_derivation: KeyDerivationMethod::default(),
+            provenance: false,
+            build_id: None,
+            component_limits: None,
+            max_state_size: None,
+            max_continuation_validity: None,
+            validity_overflow: ValidityOverflow::default(),
+            now: None,
+            valid_from: None,
+            recipient_max_size: None,
+            chunking_fallback: ChunkingFallback::default(),
+            request_profile: RequestProfile::default(),
+            state_on_failure: false,
+            allow_unprotected: false,
+        }
+    }
+}
+
+impl SealOptions {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn with_compression(mut self, compression: CompressionPolicy) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets a filter that sees, and may redact or reject, the state of every
+    /// continuation before it is encrypted.
+    pub fn with_continuation_filter(
+        mut self,
+        filter: Arc<dyn ContinuationFilter>,
+    ) -> Self {
+        self.continuation_filter = Some(filter);
+        self
+    }
+
+    /// Sets a recorder that counts every continuation issued.
+    pub fn with_continuation_metrics(
+        mut self,
+        metrics: Arc<dyn ContinuationMetrics>,
+    ) -> Self {
+        self.continuation_metrics = Some(metrics);
+        self
+    }
+
+    /// Sets whether the fingerprint of each recipient's encryption key is
+    /// added to the sealed message in the clear, so that a server holding
+    /// many identities can pick the right key without trial decryption.
+    ///
+    /// The hints reveal to any observer which keys a message is encrypted to.
+    pub fn with_recipient_hints(mut self, recipient_hints: bool) -> Self {
+        self.recipient_hints = recipient_hints;
+        self
+    }
+
+    /// Sets whether the `valid_until` a message is sealed with is added to it
+    /// in the clear, so that queues holding the message for an offline
+    /// recipient can discard it once it has expired, without decrypting it.
+    pub fn with_transport_expiry_hints(
+        mut self,
+        transport_expiry_hints: bool,
+    ) -> Self {
+        self.transport_expiry_hints = transport_expiry_hints;
+        self
+    }
+
+    /// Sets whether the continuations issued with a request or response carry
+    /// a random nonce, which a
+    /// [`ContinuationReplayGuard`](crate::ContinuationReplayGuard) uses to
+    /// accept each of them only once. On by default.
+    pub fn with_continuation_nonces(mut self, nonces: bool) -> Self {
+        self.continuation_nonces = nonces;
+        self
+    }
+
+    /// Sets whether the continuation issued with a request is bound to the
+    /// function of the request, so that the peer can only return it in the
+    /// response to that request. Off by default.
+    pub fn with_function_binding(mut self, binding: bool) -> Self {
+        self.function_binding = binding;
+        self
+    }
+
+    /// Sets whether the continuation issued with a request or response is
+    /// bound to the recipient it is sealed to, so that it is rejected if
+    /// returned in a message signed by anyone else. Sealing to other than
+    /// exactly one recipient then fails with
+    /// [`Error::AmbiguousSenderBinding`](crate::Error::AmbiguousSenderBinding).
+    /// Off by default.
+    pub fn with_sender_binding(mut self, binding: bool) -> Self {
+        self.sender_binding = binding;
+        self
+    }
+
+    /// Sets the key that continuations we issue are encrypted to, in place of
+    /// the sender's identity key, typically the current key of a
+    /// [`DelegationKeyring`](crate::DelegationKeyring).
+    pub fn with_continuation_key(self, key: EncapsulationPublicKey) -> Self {
+        self.with_continuation_sealer(ContinuationSealer::PublicKey(key))
+    }
+
+    /// Sets how continuations we issue are encrypted, in place of
+    /// encapsulating them to the sender's identity key.
+    pub fn with_continuation_sealer(
+        mut self,
+        sealer: ContinuationSealer,
+    ) -> Self {
+        self.continuation_sealer = Some(sealer);
+        self
+    }
+
+    /// Encrypts the state of continuations we issue with keys derived from
+    /// `epochs`, rather than to a public key, so that discarding an epoch
+    /// makes its continuations undecryptable. Continuations returned to us
+    /// must then be parsed with the same epochs, through
+    /// [`ParseOptions::with_state_epochs`](crate::ParseOptions::with_state_epochs).
+    pub fn with_state_epochs(mut self, epochs: Arc<StateEpochs>) -> Self {
+        self.state_epochs = Some(epochs);
+        self
+    }
+
+    /// Sets where the state of continuations we issue is kept. With
+    /// [`ContinuationMode::Reference`], continuations returned to us must be
+    /// parsed with the same store, through
+    /// [`ParseOptions::with_state_store`](crate::ParseOptions::with_state_store).
+    pub fn with_continuation_mode(mut self, mode: ContinuationMode) -> Self {
+        self.continuation_mode = mode;
+        self
+    }
+
+    /// Sets whether the state of continuations we issue is compressed before
+    /// it is encrypted. The compressed form is only kept if it is actually
+    /// smaller, and continuations returned to us are decompressed
+    /// transparently. Off by default.
+    pub fn with_state_compression(mut self, compress: bool) -> Self {
+        self.state_compression = compress;
+        self
+    }
+
+    /// Sets whether the expiry of each continuation we issue is added to it
+    /// in the clear, so that the peer holding the continuation knows how long
+    /// it is worth keeping.
+    pub fn with_continuation_expiry_hints(mut self, hints: bool) -> Self {
+        self.continuation_expiry_hints = hints;
+        self
+    }
+
+    /// Sets the precision to which the message's date and the expiry of the
+    /// continuations we issue are truncated.
+    pub fn with_date_precision(mut self, precision: DatePrecision) -> Self {
+        self.date_precision = precision;
+        self
+    }
+
+    /// Seals the message with the key of an established session instead of
+    /// encrypting it to each recipient's public key.
+    ///
+    /// Once the session has expired, messages are sealed to the recipients'
+    /// public keys again.
+    pub fn with_session(mut self, session: SessionKeys) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Sets how much of the sender's XID document is disclosed.
+    pub fn with_sender_disclosure(
+        mut self,
+        disclosure: SenderDisclosure,
+    ) -> Self {
+        self.sender_disclosure = disclosure;
+        self
+    }
+
+    /// Sets the protocol version the message declares, so that a peer known
+    /// to support only an older version can be written to in it.
+    pub fn with_gstp_version(mut self, version: u32) -> Self {
+        self.gstp_version = version;
+        self
+    }
+
+    /// Sets whether each request generates a fresh encapsulation keypair,
+    /// encrypts the continuation it issues to the ephemeral public key in
+    /// place of the sender's identity key, and carries the public key so
+    /// that the response can be encrypted to it. The private key is returned
+    /// in [`SealedArtifacts::ephemeral_key`](crate::SealedArtifacts::ephemeral_key)
+    /// and must be given to
+    /// [`ParseOptions::with_ephemeral_key`](crate::ParseOptions::with_ephemeral_key)
+    /// to parse the response.
+    ///
+    /// Once the private key is discarded, a compromise of either party's
+    /// identity keys no longer exposes the response or the returned
+    /// continuation. The request is still signed by the sender's identity.
+    pub fn with_ephemeral_keys(mut self, ephemeral: bool) -> Self {
+        self.ephemeral_keys = ephemeral;
+        self
+    }
+
+    /// Sets how the key of a message sealed with a password is derived from
+    /// it, by default with Argon2id.
+    pub fn with_password_derivation(
+        mut self,
+        method: KeyDerivationMethod,
+    ) -> Self {
+        self.password_derivation = method;
+        self
+    }
+
+    /// Sets whether the message records, inside its signature, the versions
+    /// of the crate and protocol that sealed it.
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Sets a build identifier of the sending application, recorded with the
+    /// provenance.
+    pub fn with_build_id(mut self, build_id: impl Into<String>) -> Self {
+        self.build_id = Some(build_id.into());
+        self
+    }
+
+    /// Sets limits on the size of parameter values, results, and continuation
+    /// state, checked before sealing so that a message the peer would reject
+    /// is never sent.
+    pub fn with_component_limits(mut self, limits: FieldLimits) -> Self {
+        self.component_limits = Some(limits);
+        self
+    }
+
+    /// Sets the maximum size in bytes of the CBOR-encoded state of the
+    /// continuation issued with a message. Sealing a message with larger
+    /// state fails with
+    /// [`Error::ContinuationStateTooLarge`](crate::Error::ContinuationStateTooLarge),
+    /// so that state which would outgrow the transport is caught where it is
+    /// added rather than on every round trip.
+    pub fn with_max_state_size(mut self, limit: usize) -> Self {
+        self.max_state_size = Some(limit);
+        self
+    }
+
+    /// Caps how long after the time of sealing the continuations we issue are
+    /// valid, whatever `valid_until` they are sealed with. A continuation
+    /// that would be valid for longer, or would not expire, is handled as
+    /// the validity overflow policy says.
+    pub fn with_max_continuation_validity(mut self, max: Duration) -> Self {
+        self.max_continuation_validity = Some(max);
+        self
+    }
+
+    /// Sets what happens to a continuation that would be valid for longer
+    /// than the maximum. By default sealing fails.
+    pub fn with_validity_overflow(
+        mut self,
+        overflow: ValidityOverflow,
+    ) -> Self {
+        self.validity_overflow = overflow;
+        self
+    }
+
+    /// Sets the time used as the time of sealing, for example to record when
+    /// a continuation was issued. Defaults to the current time.
+    pub fn with_now(mut self, now: Date) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Sets the time before which the continuations issued with a request or
+    /// response are not valid, to be sealed alongside their `valid_until`.
+    pub fn with_valid_from(mut self, valid_from: Date) -> Self {
+        self.valid_from = Some(valid_from);
+        self
+    }
+
+    /// Sets the maximum size in bytes of a sealed message that the recipient
+    /// accepts. A response expected to exceed it fails to seal with
+    /// [`Error::ResponseTooLarge`](crate::Error::ResponseTooLarge), or is split
+    /// by [`SealedResponse::seal_chunked`](crate::SealedResponse::seal_chunked)
+    /// according to the chunking fallback.
+    pub fn with_recipient_max_size(mut self, max_size: usize) -> Self {
+        self.recipient_max_size = Some(max_size);
+        self
+    }
+
+    /// Sets what [`SealedResponse::seal_chunked`](crate::SealedResponse::seal_chunked)
+    /// does with a response too large for the recipient.
+    pub fn with_chunking_fallback(
+        mut self,
+        fallback: ChunkingFallback,
+    ) -> Self {
+        self.chunking_fallback = fallback;
+        self
+    }
+
+    /// Sets whether requests are sealed as interactive or one-way. One-way
+    /// requests carry no continuation.
+    pub fn with_request_profile(mut self, profile: RequestProfile) -> Self {
+        self.request_profile = profile;
+        self
+    }
+
+    /// Sets whether a failure response may carry state, for a server that
+    /// wants to hand the client a continuation to retry with. Otherwise
+    /// sealing a failure with state fails with
+    /// [`Error::StateNotAllowedOnFailure`](crate::Error::StateNotAllowedOnFailure).
+    pub fn with_state_on_failure(mut self, allowed: bool) -> Self {
+        self.state_on_failure = allowed;
+        self
+    }
+
+    pub fn compression(&self) -> CompressionPolicy { self.compression }
+
+    pub fn continuation_filter(&self) -> Option<&dyn ContinuationFilter> {
+        self.continuation_filter.as_deref()
+    }
+
+    pub fn continuation_metrics(&self) -> Option<&dyn ContinuationMetrics> {
+        self.continuation_metrics.as_deref()
+    }
+
+    pub fn recipient_hints(&self) -> bool { self.recipient_hints }
+
+    pub fn transport_expiry_hints(&self) -> bool { self.transport_expiry_hints }
+
+    pub fn continuation_key(&self) -> Option<&EncapsulationPublicKey> {
+        match &self.continuation_sealer {
+            Some(ContinuationSealer::PublicKey(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    pub fn continuation_sealer(&self) -> Option<&ContinuationSealer> {
+        self.continuation_sealer.as_ref()
+    }
+
+    pub fn state_epochs(&self) -> Option<&StateEpochs> {
+        self.state_epochs.as_deref()
+    }
+
+    pub fn continuation_mode(&self) -> &ContinuationMode {
+        &self.continuation_mode
+    }
+
+    pub fn state_compression(&self) -> bool { self.state_compression }
+
+    pub fn continuation_nonces(&self) -> bool { self.continuation_nonces }
+
+    pub fn function_binding(&self) -> bool { self.function_binding }
+
+    pub fn sender_binding(&self) -> bool { self.sender_binding }
+
+    pub fn continuation_expiry_hints(&self) -> bool {
+        self.continuation_expiry_hints
+    }
+
+    pub fn date_precision(&self) -> DatePrecision { self.date_precision }
+
+    pub fn session(&self) -> Option<&SessionKeys> { self.session.as_ref() }
+
+    pub fn sender_disclosure(&self) -> &SenderDisclosure {
+        &self.sender_disclosure
+    }
+
+    pub fn gstp_version(&self) -> u32 { self.gstp_version }
+
+    pub fn ephemeral_keys(&self) -> bool { self.ephemeral_keys }
+
+    pub fn password_derivation(&self) -> KeyDerivationMethod {
+        self.password_derivation
+    }
+
+    pub fn provenance(&self) -> bool { self.provenance }
+
+    pub fn build_id(&self) -> Option<&str> { self.build_id.as_deref() }
+
+    pub fn component_limits(&self) -> Option<&FieldLimits> {
+        self.component_limits.as_ref()
+    }
+
+    pub fn max_state_size(&self) -> Option<usize> { self.max_state_size }
+
+    pub fn max_continuation_validity(&self) -> Option<Duration> {
+        self.max_continuation_validity
+    }
+
+    pub fn validity_overflow(&self) -> ValidityOverflow {
+        self.validity_overflow
+    }
+
+    pub fn now(&self) -> Option<Date> { self.now }
+
+    pub fn valid_from(&self) -> Option<Date> { self.valid_from }
+
+    pub fn recipient_max_size(&self) -> Option<usize> {
+        self.recipient_max_size
+    }
+
+    pub fn chunking_fallback(&self) -> ChunkingFallback {
+        self.chunking_fallback
+    }
+
+    pub fn request_profile(&self) -> RequestProfile { self.request_profile }
+
+    pub fn state_on_failure(&self) -> bool { self.state_on_failure }
+
+    /// Allows sealing without a signer or recipients under
+    /// [`Strictness::Production`](crate::Strictness::Production).
+    pub(crate) fn allowing_unprotected(&self) -> Self {
+        Self { allow_unprotected: true, ..self.clone() }
+    }
+
+    pub(crate) fn allows_unprotected(&self) -> bool { self.allow_unprotected }
+
+    /// Returns the time of sealing, truncated to the configured precision.
+    pub(crate) fn sealing_date(&self) -> Date {
+        self.normalize_date(self.now.unwrap_or_else(Date::now))
+    }
+
+    /// Truncates `date` to the configured precision.
+    pub(crate) fn normalize_date(&self, date: Date) -> Date {
+        self.date_precision.normalize(date)
+    }
+
+    /// Returns the expiry of a continuation sealed with `valid_until`, held
+    /// to the maximum continuation validity if one is set.
+    pub(crate) fn continuation_valid_until(
+        &self,
+        valid_until: Option<Date>,
+    ) -> Result<Option<Date>> {
+        let Some(max) = self.max_continuation_validity else {
+            return Ok(valid_until);
+        };
+        let cap = self.normalize_date(self.sealing_date() + max);
+        match valid_until {
+            Some(valid_until) if valid_until <= cap => Ok(Some(valid_until)),
+            _ => match self.validity_overflow {
+                ValidityOverflow::Reject => {
+                    Err(Error::ContinuationValidityTooLong { max })
+                }
+                ValidityOverflow::Clamp => Ok(Some(cap)),
+            },
+        }
+    }
+
+    pub(crate) fn check_parameters(&self, body: &Expression) -> Result<()> {
+        match &self.component_limits {
+            Some(limits) => limits.check_parameters(body),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_result(&self, result: &Envelope) -> Result<()> {
+        match &self.component_limits {
+            Some(limits) => limits.check_result(result),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_state(&self, state: &Envelope) -> Result<()> {
+        if let Some(limit) = self.max_state_size {
+            continuation::check_state_size(state, limit)?;
+        }
+        match &self.component_limits {
+            Some(limits) => limits.check_state(state),
+            None => Ok(()),
+        }
+    }
+}
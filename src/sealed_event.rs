@@ -1,11 +1,15 @@
-use bc_components::{ARID, Encrypter, PrivateKeys};
+use bc_components::{ARID, PrivateKeys, Reference};
 use bc_envelope::{Signer, prelude::*};
-use bc_xid::{
-    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, ContinuationContext, Error, KeyDirectory, MessageKind,
+    ParseOptions, ParseWarning, Provenance, Result, SealOptions,
+    SealedEventEnvelope, SealingReport, duplicate_assertions, extra_assertions,
+    gstp_version, key_directory, message_envelope, provenance, sealing,
+    transcript,
 };
 
-use crate::{Continuation, Error, Result};
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct SealedEvent<T>
 where
@@ -23,6 +27,21 @@ where
     // This is a continuation we previously received from the peer and want to
     // send back to them.
     peer_continuation: Option<Envelope>,
+    // Anything questionable about the message that was accepted anyway when
+    // it was parsed.
+    warnings: Vec<ParseWarning>,
+    // When parsed, the protocol version the message is written in.
+    gstp_version: Option<u32>,
+    // When parsed, the provenance recorded by the sender.
+    provenance: Option<Provenance>,
+    // Application assertions added to the signed message.
+    extra_assertions: Vec<Envelope>,
+    // The envelope digest of the message before this one in a transcript.
+    previous_digest: Option<Digest>,
+    // When parsed, the digest of the envelope as it was received.
+    envelope_digest: Option<Digest>,
+    // When parsed, how the message was sealed, if it was decrypted.
+    sealing_report: Option<SealingReport>,
 }
 
 impl<T> std::fmt::Display for SealedEvent<T>
@@ -70,8 +89,39 @@ where
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            previous_digest: None,
+            envelope_digest: None,
+            sealing_report: None,
         }
     }
+
+    /// Converts the content of the event to another type.
+    pub(crate) fn try_map_content<U>(&self) -> Result<SealedEvent<U>>
+    where
+        U: EnvelopeEncodable
+            + TryFrom<Envelope>
+            + std::fmt::Debug
+            + Clone
+            + PartialEq,
+    {
+        Ok(SealedEvent {
+            event: Event::<U>::try_from(Envelope::from(self.event.clone()))?,
+            sender: self.sender.clone(),
+            state: self.state.clone(),
+            peer_continuation: self.peer_continuation.clone(),
+            warnings: self.warnings.clone(),
+            gstp_version: self.gstp_version,
+            provenance: self.provenance.clone(),
+            extra_assertions: self.extra_assertions.clone(),
+            previous_digest: self.previous_digest,
+            envelope_digest: self.envelope_digest,
+            sealing_report: self.sealing_report,
+        })
+    }
 }
 
 impl<T> EventBehavior<T> for SealedEvent<T>
@@ -88,6 +138,13 @@ where
             sender: self.sender,
             state: self.state,
             peer_continuation: self.peer_continuation,
+            warnings: self.warnings,
+            gstp_version: self.gstp_version,
+            provenance: self.provenance,
+            extra_assertions: self.extra_assertions,
+            previous_digest: self.previous_digest,
+            envelope_digest: self.envelope_digest,
+            sealing_report: self.sealing_report,
         }
     }
 
@@ -97,6 +154,13 @@ where
             sender: self.sender,
             state: self.state,
             peer_continuation: self.peer_continuation,
+            warnings: self.warnings,
+            gstp_version: self.gstp_version,
+            provenance: self.provenance,
+            extra_assertions: self.extra_assertions,
+            previous_digest: self.previous_digest,
+            envelope_digest: self.envelope_digest,
+            sealing_report: self.sealing_report,
         }
     }
 
@@ -244,38 +308,97 @@ where
         sender: Option<&dyn Signer>,
         recipients: &[&XIDDocument],
     ) -> Result<Envelope> {
-        let sender_encryption_key = self
-            .sender
-            .encryption_key()
-            .ok_or(Error::SenderMissingEncryptionKey)?;
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &SealOptions::default(),
+        )
+    }
+
+    /// Creates an envelope that can be decrypted by zero or more recipients,
+    /// sealed according to `options`.
+    pub fn to_envelope_with_options(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        sealing::check_protection(
+            sender.is_some(),
+            !recipients.is_empty(),
+            options,
+        )?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let mut result = self.compose(valid_until, options)?;
+        if let Some(sender_private_key) = sender {
+            result = result.sign(sender_private_key);
+        }
+        sealing::encrypt_to_recipients(result, recipients, valid_until, options)
+    }
+
+    /// Composes the message, issuing its continuation, before it is signed
+    /// and encrypted.
+    fn compose(
+        &self,
+        valid_until: Option<Date>,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        // The sender must be able to receive continuations, even if this
+        // event issues none.
+        sealing::check_continuation_key(options, &self.sender)?;
         let sender_continuation: Option<Envelope> =
             if let Some(state) = &self.state {
-                Some(
-                    Continuation::new(state.clone())
-                        .with_optional_valid_until(valid_until)
-                        .to_envelope(Some(sender_encryption_key)),
-                )
+                let state = sealing::filter_state(
+                    options,
+                    &ContinuationContext {
+                        kind: MessageKind::Event,
+                        function: None,
+                    },
+                    state.clone(),
+                )?;
+                options.check_state(&state)?;
+                Some(sealing::issue_continuation(
+                    &Continuation::new(state)
+                        .with_optional_valid_until(
+                            options.continuation_valid_until(valid_until)?,
+                        )
+                        .with_issued_at(options.sealing_date()),
+                    &self.sender,
+                    self.id(),
+                    None,
+                    options,
+                )?)
             } else {
-                valid_until.map(|valid_until| {
-                    Continuation::new(Envelope::null())
-                        .with_valid_until(valid_until)
-                        .to_envelope(Some(sender_encryption_key))
-                })
+                valid_until
+                    .map(|valid_until| {
+                        sealing::issue_continuation(
+                            &Continuation::new(Envelope::null())
+                                .with_optional_valid_until(
+                                    options.continuation_valid_until(Some(
+                                        valid_until,
+                                    ))?,
+                                )
+                                .with_issued_at(options.sealing_date()),
+                            &self.sender,
+                            self.id(),
+                            None,
+                            options,
+                        )
+                    })
+                    .transpose()?
             };
 
-        let mut result = self
-            .event
-            .clone()
+        let mut event = self.event.clone();
+        if let Some(date) = event.date() {
+            event = event.with_date(options.normalize_date(date));
+        }
+        let mut result = event
             .into_envelope()
             .add_assertion(
                 known_values::SENDER,
-                self.sender
-                    .to_envelope(
-                        XIDPrivateKeyOptions::default(),
-                        XIDGeneratorOptions::default(),
-                        XIDSigningOptions::default(),
-                    )
-                    .unwrap(),
+                sealing::sender_envelope(&self.sender, options)?,
             )
             .add_optional_assertion(
                 known_values::SENDER_CONTINUATION,
@@ -286,26 +409,199 @@ where
                 self.peer_continuation.clone(),
             );
 
+        result = result.add_optional_assertion(
+            transcript::TRANSCRIPT,
+            self.previous_digest,
+        );
+        result = gstp_version::add_version(result, options);
+        result = provenance::add_provenance(result, options);
+        extra_assertions::add_to(result, &self.extra_assertions)
+    }
+
+    /// Seals this event like [`Self::to_envelope_with_options`], encrypted
+    /// with a key derived from `password` rather than to recipients, for
+    /// transfer to a party whose keys we do not know, such as by QR code
+    /// between air-gapped devices.
+    pub fn to_envelope_with_password(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        password: impl AsRef<[u8]>,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        sealing::check_protection(sender.is_some(), true, options)?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let mut message = self.compose(valid_until, options)?;
         if let Some(sender_private_key) = sender {
-            result = result.sign(sender_private_key);
+            message = message.sign(sender_private_key);
         }
+        sealing::lock_with_password(
+            message,
+            password.as_ref(),
+            valid_until,
+            options,
+        )
+    }
 
-        if !recipients.is_empty() {
-            let recipient_keys = recipients
-                .iter()
-                .map(|recipient| {
-                    recipient
-                        .encryption_key()
-                        .ok_or(Error::RecipientMissingEncryptionKey)
-                        .map(|key| key as &dyn Encrypter)
-                })
-                .collect::<Result<Vec<&dyn Encrypter>>>()?;
-            result = result
-                .wrap()
-                .encrypt_subject_to_recipients(&recipient_keys)?;
-        }
+    /// Seals this event like [`Self::to_envelope_with_options`], even if it
+    /// is unsigned or unencrypted under
+    /// [`Strictness::Production`](crate::Strictness::Production).
+    pub fn to_envelope_unprotected_i_know_what_i_am_doing(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &options.allowing_unprotected(),
+        )
+    }
+
+    /// Seals this event like [`Self::to_envelope_with_options`], returning
+    /// an envelope typed by its message kind.
+    pub fn seal(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedEventEnvelope> {
+        self.to_envelope_with_options(valid_until, sender, recipients, options)
+            .map(SealedEventEnvelope::new_unchecked)
+    }
+
+    /// Seals this event like [`Self::to_envelope`], encoded as a
+    /// `ur:envelope` string for transport by QR code or NFC.
+    pub fn to_ur_string(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipient: Option<&XIDDocument>,
+    ) -> Result<String> {
+        Ok(self
+            .to_envelope(valid_until, sender, recipient)?
+            .ur_string())
+    }
+
+    /// Anything questionable about the message that was accepted anyway when
+    /// it was parsed, such as duplicate assertions discarded under
+    /// [`DuplicateAssertionPolicy::TakeFirst`](crate::DuplicateAssertionPolicy::TakeFirst).
+    pub fn warnings(&self) -> &[ParseWarning] { &self.warnings }
+
+    /// The protocol version the message is written in, if it was parsed.
+    /// Messages that predate the version assertion are version 1.
+    pub fn gstp_version(&self) -> Option<u32> { self.gstp_version }
+
+    /// The provenance recorded by the sender, if the message was parsed and
+    /// the sender sealed it with
+    /// [`SealOptions::with_provenance`](crate::SealOptions::with_provenance).
+    pub fn provenance(&self) -> Option<&Provenance> { self.provenance.as_ref() }
+
+    /// Adds an assertion to the signed message, on the same layer as its
+    /// note and date, for metadata such as a tenant or trace ID. May be
+    /// called repeatedly.
+    ///
+    /// Sealing fails with [`Error::ReservedPredicate`] if `predicate` is one
+    /// of the [`reserved_predicates`](crate::reserved_predicates).
+    pub fn with_extra_assertion(
+        mut self,
+        predicate: impl EnvelopeEncodable,
+        object: impl EnvelopeEncodable,
+    ) -> Self {
+        self.extra_assertions
+            .push(Envelope::new_assertion(predicate, object));
+        self
+    }
 
-        Ok(result)
+    /// The assertions added with [`Self::with_extra_assertion`]. When the
+    /// message was parsed, every assertion on its signed layer whose
+    /// predicate is not reserved.
+    pub fn extra_assertions(&self) -> &[Envelope] { &self.extra_assertions }
+
+    /// Commits this event to `digest`, the envelope digest of the message
+    /// before it in a [`Transcript`](crate::Transcript).
+    pub fn with_previous_digest(mut self, digest: Digest) -> Self {
+        self.previous_digest = Some(digest);
+        self
+    }
+
+    pub fn with_optional_previous_digest(
+        mut self,
+        digest: Option<Digest>,
+    ) -> Self {
+        self.previous_digest = digest;
+        self
+    }
+
+    /// The envelope digest of the message before this one in a transcript,
+    /// if the event commits to one.
+    pub fn previous_digest(&self) -> Option<Digest> { self.previous_digest }
+
+    /// The digest of the envelope this event was parsed from.
+    pub fn envelope_digest(&self) -> Option<Digest> { self.envelope_digest }
+
+    /// How the event was sealed, if it was parsed from an encrypted
+    /// envelope.
+    pub fn sealing_report(&self) -> Option<SealingReport> {
+        self.sealing_report
+    }
+
+    /// Parses an event from an envelope typed by its message kind, like
+    /// [`Self::try_from_envelope`].
+    pub fn try_from_sealed(
+        envelope: &SealedEventEnvelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        Self::try_from_envelope(
+            envelope.envelope(),
+            expected_id,
+            now,
+            recipient,
+        )
+    }
+
+    /// Parses an event from a `ur:envelope` string, such as one produced by
+    /// [`Self::to_ur_string`], like [`Self::try_from_envelope_opt`].
+    ///
+    /// Fails with [`Error::InvalidUR`] if the string is not an envelope UR,
+    /// and with [`Error::MessageKindMismatch`] if it carries another kind of
+    /// message.
+    pub fn try_from_ur_string(
+        ur_string: impl Into<String>,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let envelope = SealedEventEnvelope::from_ur_string(ur_string)?;
+        Self::try_from_envelope_opt(envelope.envelope(), options, recipient)
+    }
+
+    /// Parses an event like [`Self::try_from_envelope`], picking the
+    /// recipient's private keys from `directory` using the recipient hints
+    /// carried by the envelope.
+    ///
+    /// Returns the fingerprint of the encryption key that matched alongside
+    /// the event.
+    pub fn try_from_envelope_with_directory(
+        encrypted_envelope: &Envelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+        directory: &dyn KeyDirectory,
+    ) -> Result<(Self, Reference)> {
+        let (fingerprint, private_keys) =
+            key_directory::select_recipient(encrypted_envelope, directory)?;
+        let event = Self::try_from_envelope(
+            encrypted_envelope,
+            expected_id,
+            now,
+            private_keys,
+        )?;
+        Ok((event, fingerprint))
     }
 
     pub fn try_from_envelope(
@@ -314,16 +610,127 @@ where
         now: Option<Date>,
         recipient_private_key: &PrivateKeys,
     ) -> Result<Self> {
-        let signed_envelope =
-            encrypted_envelope.decrypt_to_recipient(recipient_private_key)?;
-        let sender: XIDDocument = signed_envelope
-            .try_unwrap()?
+        Self::try_from_envelope_opt(
+            encrypted_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(expected_id)
+                .with_optional_now(now),
+            recipient_private_key,
+        )
+    }
+
+    /// Parses an event like [`Self::try_from_envelope`], configured by
+    /// `options`.
+    pub fn try_from_envelope_opt(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let (signed_envelope, report) = options
+            .decrypt_to_recipient(encrypted_envelope, recipient_private_key)?;
+        let event = Self::try_from_signed(
+            &signed_envelope,
+            options,
+            Some(recipient_private_key),
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..event
+        })
+    }
+
+    /// Parses an event sealed with [`Self::to_envelope_with_password`],
+    /// checking it against `options` and opening any continuation returned
+    /// to us with `recipient_private_key`.
+    ///
+    /// Fails with [`Error::PasswordDecryptionFailed`] if `password` is not
+    /// the one the event was sealed with.
+    pub fn try_from_envelope_with_password(
+        encrypted_envelope: &Envelope,
+        password: impl AsRef<[u8]>,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let (signed_envelope, report) = sealing::unlock_with_password(
+            encrypted_envelope,
+            password.as_ref(),
+        )?;
+        let event = Self::try_from_signed(
+            &signed_envelope,
+            options,
+            Some(recipient_private_key),
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..event
+        })
+    }
+
+    /// Parses an event that was signed but not encrypted, the form in which
+    /// events are broadcast, without needing any keys.
+    ///
+    /// The sender's signature is verified as for an encrypted event. A
+    /// continuation returned to us cannot be opened without our keys, so
+    /// the state is always `None`, but the sender's continuation is kept to
+    /// be returned later. Fails with [`Error::UnexpectedEncryption`] if the
+    /// envelope is encrypted.
+    pub fn try_from_signed_envelope(
+        signed_envelope: &Envelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+    ) -> Result<Self> {
+        Self::try_from_signed_envelope_opt(
+            signed_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(expected_id)
+                .with_optional_now(now),
+            None,
+        )
+    }
+
+    /// Parses an event like [`Self::try_from_signed_envelope`], configured
+    /// by `options`, opening any continuation returned to us with
+    /// `recipient_private_key`.
+    pub fn try_from_signed_envelope_opt(
+        signed_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient_private_key: Option<&PrivateKeys>,
+    ) -> Result<Self> {
+        if signed_envelope.subject().is_encrypted() {
+            return Err(Error::UnexpectedEncryption);
+        }
+        Self::try_from_signed(signed_envelope, options, recipient_private_key)
+    }
+
+    fn try_from_signed(
+        signed_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient_private_key: Option<&PrivateKeys>,
+    ) -> Result<Self> {
+        let mut warnings = Vec::new();
+        let event_envelope = duplicate_assertions::check_singular_assertions(
+            signed_envelope.try_unwrap()?,
+            options.duplicate_assertions(),
+            &mut warnings,
+        )?;
+        message_envelope::check_kind(&event_envelope, MessageKind::Event)?;
+        options.check_fields(&event_envelope)?;
+        let sender: XIDDocument = event_envelope
             .object_for_predicate(known_values::SENDER)?
             .try_into()?;
-        let sender_verification_key = sender
-            .verification_key()
-            .ok_or(Error::SenderMissingVerificationKey)?;
-        let event_envelope = signed_envelope.verify(sender_verification_key)?;
+        let sender_verification_key =
+            options.sender_verification_key(&sender)?;
+        // The signature covers the message as it was sent, so once it is
+        // verified the checked message can be used in its place.
+        signed_envelope.verify(sender_verification_key)?;
+        options.check_sender(&sender)?;
+        let gstp_version = gstp_version::parse_version(&event_envelope)?;
+        let previous_digest = event_envelope
+            .extract_optional_object_for_predicate(transcript::TRANSCRIPT)?;
+        let provenance = options.parse_provenance(&event_envelope)?;
+        let extra_assertions = extra_assertions::from_message(&event_envelope);
         let peer_continuation = event_envelope
             .optional_object_for_predicate(known_values::SENDER_CONTINUATION)?;
         if let Some(some_peer_continuation) = peer_continuation.clone()
@@ -336,18 +743,70 @@ where
                 known_values::RECIPIENT_CONTINUATION,
             )?;
         let state: Option<Envelope>;
-        if let Some(encrypted_continuation) = encrypted_continuation {
-            let continuation = Continuation::try_from_envelope(
+        let continuation_valid_until: Option<Date>;
+        let continuation_nonce: Option<ARID>;
+        if let (Some(encrypted_continuation), Some(recipient_private_key)) =
+            (encrypted_continuation, recipient_private_key)
+        {
+            let continuation = options.parse_continuation(
                 &encrypted_continuation,
-                expected_id,
-                now,
-                Some(recipient_private_key),
+                recipient_private_key,
+                None,
+                &sender,
             )?;
+            #[cfg(feature = "taint-checks")]
+            crate::taint::mark_tainted(continuation.state());
             state = Some(continuation.state().clone());
+            continuation_valid_until = continuation.valid_until();
+            continuation_nonce = continuation.nonce();
         } else {
             state = None;
+            continuation_valid_until = None;
+            continuation_nonce = None;
         }
         let event = Event::<T>::try_from(event_envelope)?;
-        Ok(Self { event, sender, state, peer_continuation })
+        options.record_continuation_nonce(
+            continuation_nonce,
+            continuation_valid_until,
+        )?;
+        Ok(Self {
+            event,
+            sender,
+            state,
+            peer_continuation,
+            warnings,
+            gstp_version: Some(gstp_version),
+            provenance,
+            extra_assertions,
+            previous_digest,
+            envelope_digest: Some(signed_envelope.digest()),
+            sealing_report: None,
+        })
+    }
+}
+
+impl SealedEvent<Envelope> {
+    /// Delivers an event already sealed to some recipients to
+    /// `new_recipients` too, without signing it again.
+    ///
+    /// The content key is recovered from the recipient slot that `holder`
+    /// can open, so anyone who can read the event, whether an original
+    /// recipient or a sender who sealed it to itself, can share it further.
+    /// Encrypting to several recipients never prevented that, since a
+    /// recipient could always forward the plaintext, but it does mean the
+    /// list of slots is not a statement by the sender of who the event is
+    /// for.
+    ///
+    /// The signed payload is untouched, so the signature and the digest of
+    /// the payload are preserved, and the slots of the original recipients
+    /// are unaffected. The digest of the envelope as a whole changes with
+    /// the added slots. An event sealed with a session key has no slots and
+    /// cannot be shared this way.
+    pub fn add_recipients(
+        sealed: &Envelope,
+        holder: &PrivateKeys,
+        new_recipients: &[&XIDDocument],
+    ) -> Result<Envelope> {
+        sealing::add_recipients(sealed, holder, new_recipients)
     }
 }
@@ -0,0 +1,30 @@
+use bc_components::{PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{Error, Result};
+
+/// Encrypts a parameter value to `third_party`, wrapping it first so that
+/// its assertions are encrypted too.
+pub(crate) fn seal_parameter(
+    value: Envelope,
+    third_party: &XIDDocument,
+) -> Result<Envelope> {
+    let key = third_party.encryption_key().ok_or_else(|| {
+        Error::RecipientMissingEncryptionKey(third_party.xid())
+    })?;
+    Ok(value.encrypt_to_recipient(key))
+}
+
+/// Decrypts a parameter value sealed to us with
+/// [`SealedRequest::with_parameter_sealed_to`](crate::SealedRequest::with_parameter_sealed_to),
+/// as forwarded by the primary recipient of the request.
+pub fn open_sealed_parameter<T>(
+    sealed: &Envelope,
+    recipient: &PrivateKeys,
+) -> Result<T>
+where
+    T: TryFrom<CBOR, Error = dcbor::Error> + 'static,
+{
+    Ok(sealed.decrypt_to_recipient(recipient)?.extract_subject()?)
+}
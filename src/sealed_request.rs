@@ -1,10 +1,25 @@
-use bc_components::{ARID, Encrypter, PrivateKeys};
+use std::time::Duration;
+
+use bc_components::{
+    ARID, DigestProvider, EncapsulationPublicKey, PrivateKeys, Reference,
+    XIDProvider,
+};
 use bc_envelope::{Signer, prelude::*};
-use bc_xid::{
-    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+use bc_xid::XIDDocument;
+
+use crate::{
+    Continuation, ContinuationContext, ContinuationReceipt, Error,
+    KeyDirectory, MessageKind, ParseOptions, ParseStage, ParseWarning,
+    PartialParse, PeerContinuationRef, Provenance, RequestProfile, Result,
+    SealOptions, SealedArtifacts, SealedRequestEnvelope, SealingReport,
+    SessionKeys, continuation_stack, continuation_storage,
+    duplicate_assertions, extra_assertions, gstp_version, key_directory,
+    message_envelope, provenance, request_profile, sealed_parameter, sealing,
+    session::{self, SessionAssertions},
+    state_lifetime, transcript, typed_continuation,
 };
 
-use crate::{Continuation, Error, Result};
+pub(crate) const EPHEMERAL_KEY: &str = "ephemeralKey";
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SealedRequest {
@@ -16,6 +31,33 @@ pub struct SealedRequest {
     // This is a continuation we previously received from the peer and want to
     // send back to them.
     peer_continuation: Option<Envelope>,
+    // A reference to a continuation the peer kept, sent back in place of
+    // the continuation itself.
+    peer_continuation_ref: Option<PeerContinuationRef>,
+    // Anything questionable about the message that was accepted anyway when
+    // it was parsed.
+    warnings: Vec<ParseWarning>,
+    // When parsed, the protocol version the message is written in.
+    gstp_version: Option<u32>,
+    // When parsed, the provenance recorded by the sender.
+    provenance: Option<Provenance>,
+    // Application assertions added to the signed message.
+    extra_assertions: Vec<Envelope>,
+    // The session proposed or acknowledged by the message.
+    session: SessionAssertions,
+    // The lifetime the sender proposes for the state we keep for it.
+    proposed_state_lifetime: Option<Duration>,
+    // When parsed, the expiry and ID of the continuation returned to us.
+    continuation_valid_until: Option<Date>,
+    continuation_id: Option<ARID>,
+    // When parsed, the digest of the envelope as it was received.
+    envelope_digest: Option<Digest>,
+    // When parsed, how the message was sealed, if it was decrypted.
+    sealing_report: Option<SealingReport>,
+    // The envelope digest of the message before this one in a transcript.
+    previous_digest: Option<Digest>,
+    // When parsed, the ephemeral key the response should be encrypted to.
+    ephemeral_key: Option<EncapsulationPublicKey>,
 }
 
 impl std::fmt::Display for SealedRequest {
@@ -49,6 +91,19 @@ impl SealedRequest {
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            peer_continuation_ref: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            proposed_state_lifetime: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            envelope_digest: None,
+            sealing_report: None,
+            previous_digest: None,
+            ephemeral_key: None,
         }
     }
 
@@ -62,6 +117,64 @@ impl SealedRequest {
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            peer_continuation_ref: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            proposed_state_lifetime: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            envelope_digest: None,
+            sealing_report: None,
+            previous_digest: None,
+            ephemeral_key: None,
+        }
+    }
+}
+
+#[cfg(feature = "test-utils")]
+impl SealedRequest {
+    /// Creates a request as it would be after being parsed: from `sender`,
+    /// carrying the given parameters, the state returned to us, and the
+    /// continuation the sender wants back, without any cryptography.
+    ///
+    /// For testing handlers only; nothing about the request is verified.
+    pub fn synthetic<P, V>(
+        function: impl Into<Function>,
+        id: ARID,
+        sender: impl AsRef<XIDDocument>,
+        parameters: impl IntoIterator<Item = (P, V)>,
+        state: Option<Envelope>,
+        peer_continuation: Option<Envelope>,
+    ) -> Self
+    where
+        P: Into<Parameter>,
+        V: EnvelopeEncodable,
+    {
+        let mut request = Request::new(function, id);
+        for (parameter, value) in parameters {
+            request = request.with_parameter(parameter, value);
+        }
+        Self {
+            request,
+            sender: sender.as_ref().clone(),
+            state,
+            peer_continuation,
+            peer_continuation_ref: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            proposed_state_lifetime: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            envelope_digest: None,
+            sealing_report: None,
+            previous_digest: None,
+            ephemeral_key: None,
         }
     }
 }
@@ -72,6 +185,9 @@ impl ExpressionBehavior for SealedRequest {
         parameter: impl Into<Parameter>,
         value: impl EnvelopeEncodable,
     ) -> Self {
+        let value = value.into_envelope();
+        #[cfg(feature = "taint-checks")]
+        crate::taint::check_untainted(&value, "with_parameter");
         self.request = self.request.with_parameter(parameter, value);
         self
     }
@@ -81,6 +197,11 @@ impl ExpressionBehavior for SealedRequest {
         parameter: impl Into<Parameter>,
         value: Option<impl EnvelopeEncodable>,
     ) -> Self {
+        let value = value.map(|value| value.into_envelope());
+        #[cfg(feature = "taint-checks")]
+        if let Some(value) = &value {
+            crate::taint::check_untainted(value, "with_optional_parameter");
+        }
         self.request = self.request.with_optional_parameter(parameter, value);
         self
     }
@@ -215,6 +336,7 @@ impl SealedRequestBehavior for SealedRequest {
 
     fn with_peer_continuation(mut self, peer_continuation: Envelope) -> Self {
         self.peer_continuation = Some(peer_continuation);
+        self.peer_continuation_ref = None;
         self
     }
 
@@ -223,6 +345,7 @@ impl SealedRequestBehavior for SealedRequest {
         peer_continuation: Option<Envelope>,
     ) -> Self {
         self.peer_continuation = peer_continuation;
+        self.peer_continuation_ref = None;
         self
     }
 
@@ -264,68 +387,539 @@ impl SealedRequest {
     }
 
     /// Creates an envelope that can be decrypted by zero or more recipients.
+    ///
+    /// Each recipient can parse the request with its own private keys. With
+    /// no recipients the request is signed but not encrypted. Fails with
+    /// [`Error::RecipientMissingEncryptionKey`] naming the first recipient
+    /// that has no encryption key.
     pub fn to_envelope_for_recipients(
         &self,
         valid_until: Option<Date>,
         sender: Option<&dyn Signer>,
         recipients: &[&XIDDocument],
     ) -> Result<Envelope> {
-        // Even if no state is provided, requests always include a continuation
-        // that at least specifies the required valid response ID.
-        let state = self.state.clone().unwrap_or(Envelope::null());
-        let continuation = Continuation::new(state)
-            .with_valid_id(self.id())
-            .with_optional_valid_until(valid_until);
-        let sender_encryption_key = self
-            .sender
-            .encryption_key()
-            .ok_or(Error::SenderMissingEncryptionKey)?;
-        let sender_continuation =
-            continuation.to_envelope(Some(sender_encryption_key));
-
-        let mut result = self
-            .request
-            .clone()
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &SealOptions::default(),
+        )
+    }
+
+    /// Creates an envelope that can be decrypted by zero or more recipients,
+    /// sealed according to `options`.
+    pub fn to_envelope_with_options(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.seal_detailed(valid_until, sender, recipients, options)
+            .map(|artifacts| artifacts.envelope)
+    }
+
+    /// Seals this request like [`Self::to_envelope_with_options`], also
+    /// returning the continuation issued with it and a receipt that can be
+    /// persisted to recognize the state when it is echoed back.
+    pub fn seal_detailed(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedArtifacts> {
+        sealing::check_protection(
+            sender.is_some(),
+            !recipients.is_empty(),
+            options,
+        )?;
+        self.session.check_encrypted(!recipients.is_empty())?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let mut artifacts = self.compose(valid_until, recipients, options)?;
+        if let Some(sender_private_key) = sender {
+            artifacts.envelope = artifacts.envelope.sign(sender_private_key);
+        }
+        artifacts.envelope = sealing::encrypt_to_recipients(
+            artifacts.envelope,
+            recipients,
+            valid_until,
+            options,
+        )?;
+        Ok(artifacts)
+    }
+
+    /// Composes the message, issuing its continuation, before it is signed
+    /// and encrypted.
+    pub(crate) fn compose(
+        &self,
+        valid_until: Option<Date>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedArtifacts> {
+        options.check_parameters(self.body())?;
+        let one_way = options.request_profile() == RequestProfile::OneWay;
+        let ephemeral = (options.ephemeral_keys() && !one_way)
+            .then(|| sealing::ephemeral_keypair(&self.sender));
+        let (sender_continuation, continuation_receipt) = if one_way {
+            if self.state.is_some() {
+                return Err(Error::OneWayRequestWithState);
+            }
+            (None, None)
+        } else {
+            // Even if no state is provided, interactive requests always
+            // include a continuation that at least specifies the required
+            // valid response ID.
+            let state = match self.state.clone() {
+                Some(state) => sealing::filter_state(
+                    options,
+                    &ContinuationContext {
+                        kind: MessageKind::Request,
+                        function: Some(self.function()),
+                    },
+                    state,
+                )?,
+                None => Envelope::null(),
+            };
+            options.check_state(&state)?;
+            let continuation = Continuation::new(state)
+                .with_issued_at(options.sealing_date())
+                .with_valid_id(self.id())
+                .with_optional_valid_sender(sealing::bound_sender(
+                    options, recipients,
+                )?)
+                .with_optional_valid_function(
+                    options.function_binding().then(|| self.function().clone()),
+                )
+                .with_optional_nonce(
+                    options.continuation_nonces().then(ARID::new),
+                )
+                .with_optional_valid_from(
+                    options
+                        .valid_from()
+                        .map(|date| options.normalize_date(date)),
+                )
+                .with_optional_valid_until(
+                    options.continuation_valid_until(valid_until)?,
+                );
+            // An ephemeral key takes the place of the sender's own as the
+            // key the continuation is encrypted to.
+            let ephemeral_options;
+            let continuation_options = match &ephemeral {
+                Some((_, public_key)) => {
+                    ephemeral_options = options
+                        .clone()
+                        .with_continuation_key(public_key.clone());
+                    &ephemeral_options
+                }
+                None => options,
+            };
+            let sender_continuation = sealing::issue_continuation(
+                &continuation,
+                &self.sender,
+                self.id(),
+                Some(self.function()),
+                continuation_options,
+            )?;
+            (
+                Some(sender_continuation),
+                Some(ContinuationReceipt::new(&continuation)),
+            )
+        };
+
+        let mut request = self.request.clone();
+        if let Some(date) = request.date() {
+            request = request.with_date(options.normalize_date(date));
+        }
+        let mut result = request
             .into_envelope()
             .add_assertion(
                 known_values::SENDER,
-                self.sender
-                    .to_envelope(
-                        XIDPrivateKeyOptions::default(),
-                        XIDGeneratorOptions::default(),
-                        XIDSigningOptions::default(),
-                    )
-                    .unwrap(),
+                sealing::sender_envelope(&self.sender, options)?,
             )
-            .add_assertion(
+            .add_optional_assertion(
                 known_values::SENDER_CONTINUATION,
-                sender_continuation,
+                sender_continuation.clone(),
+            )
+            .add_optional_assertion(
+                request_profile::ONE_WAY,
+                one_way.then_some(true),
             )
             .add_optional_assertion(
                 known_values::RECIPIENT_CONTINUATION,
                 self.peer_continuation.clone(),
+            )
+            .add_optional_assertion(
+                continuation_storage::RECIPIENT_CONTINUATION_REF,
+                self.peer_continuation_ref.clone().map(Envelope::from),
             );
+        result = self.session.add_to(result);
+        result = result.add_optional_assertion(
+            EPHEMERAL_KEY,
+            ephemeral
+                .as_ref()
+                .map(|(_, public_key)| CBOR::from(public_key.clone())),
+        );
+        result = result.add_optional_assertion(
+            state_lifetime::PROPOSED_STATE_LIFETIME,
+            self.proposed_state_lifetime
+                .map(|lifetime| lifetime.as_secs()),
+        );
+
+        result = result.add_optional_assertion(
+            transcript::TRANSCRIPT,
+            self.previous_digest,
+        );
+        result = gstp_version::add_version(result, options);
+        result = provenance::add_provenance(result, options);
+        result = extra_assertions::add_to(result, &self.extra_assertions)?;
+
+        Ok(SealedArtifacts {
+            envelope: result,
+            own_continuation: sender_continuation,
+            continuation_receipt,
+            ephemeral_key: ephemeral.map(|(private_key, _)| private_key),
+        })
+    }
 
+    /// Seals this request like [`Self::to_envelope_with_options`], encrypted
+    /// with a key derived from `password` rather than to recipients, for
+    /// transfer to a party whose keys we do not know, such as by QR code
+    /// between air-gapped devices.
+    pub fn to_envelope_with_password(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        password: impl AsRef<[u8]>,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        sealing::check_protection(sender.is_some(), true, options)?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let mut message = self.compose(valid_until, &[], options)?.envelope;
         if let Some(sender_private_key) = sender {
-            result = result.sign(sender_private_key);
+            message = message.sign(sender_private_key);
         }
+        sealing::lock_with_password(
+            message,
+            password.as_ref(),
+            valid_until,
+            options,
+        )
+    }
+
+    /// Seals this request like [`Self::to_envelope_with_options`], even if
+    /// it is unsigned or unencrypted under
+    /// [`Strictness::Production`](crate::Strictness::Production).
+    pub fn to_envelope_unprotected_i_know_what_i_am_doing(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &options.allowing_unprotected(),
+        )
+    }
+
+    /// Seals this request like [`Self::to_envelope_with_options`], returning
+    /// an envelope typed by its message kind.
+    pub fn seal(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedRequestEnvelope> {
+        self.to_envelope_with_options(valid_until, sender, recipients, options)
+            .map(SealedRequestEnvelope::new_unchecked)
+    }
+
+    /// Seals this request like [`Self::to_envelope`], encoded as a
+    /// `ur:envelope` string for transport by QR code or NFC.
+    pub fn to_ur_string(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipient: Option<&XIDDocument>,
+    ) -> Result<String> {
+        Ok(self
+            .to_envelope(valid_until, sender, recipient)?
+            .ur_string())
+    }
+
+    /// Anything questionable about the message that was accepted anyway when
+    /// it was parsed, such as duplicate assertions discarded under
+    /// [`DuplicateAssertionPolicy::TakeFirst`](crate::DuplicateAssertionPolicy::TakeFirst).
+    pub fn warnings(&self) -> &[ParseWarning] { &self.warnings }
+
+    /// The protocol version the message is written in, if it was parsed.
+    /// Messages that predate the version assertion are version 1.
+    pub fn gstp_version(&self) -> Option<u32> { self.gstp_version }
+
+    /// The provenance recorded by the sender, if the message was parsed and
+    /// the sender sealed it with
+    /// [`SealOptions::with_provenance`](crate::SealOptions::with_provenance).
+    pub fn provenance(&self) -> Option<&Provenance> { self.provenance.as_ref() }
+
+    /// Adds an assertion to the signed message, on the same layer as its
+    /// note and date, for metadata such as a tenant or trace ID. May be
+    /// called repeatedly.
+    ///
+    /// Sealing fails with [`Error::ReservedPredicate`] if `predicate` is one
+    /// of the [`reserved_predicates`](crate::reserved_predicates).
+    pub fn with_extra_assertion(
+        mut self,
+        predicate: impl EnvelopeEncodable,
+        object: impl EnvelopeEncodable,
+    ) -> Self {
+        self.extra_assertions
+            .push(Envelope::new_assertion(predicate, object));
+        self
+    }
+
+    /// The assertions added with [`Self::with_extra_assertion`]. When the
+    /// message was parsed, every assertion on its signed layer whose
+    /// predicate is not reserved.
+    pub fn extra_assertions(&self) -> &[Envelope] { &self.extra_assertions }
+
+    /// Commits this request to `digest`, the envelope digest of the message
+    /// before it in a [`Transcript`](crate::Transcript).
+    pub fn with_previous_digest(mut self, digest: Digest) -> Self {
+        self.previous_digest = Some(digest);
+        self
+    }
+
+    pub fn with_optional_previous_digest(
+        mut self,
+        digest: Option<Digest>,
+    ) -> Self {
+        self.previous_digest = digest;
+        self
+    }
+
+    /// The envelope digest of the message before this one in a transcript,
+    /// if the request commits to one.
+    pub fn previous_digest(&self) -> Option<Digest> { self.previous_digest }
+
+    /// The ephemeral key the request was sealed with under
+    /// [`SealOptions::with_ephemeral_keys`], which the response should be
+    /// encrypted to with
+    /// [`SealedResponse::with_reply_key`](crate::SealedResponse::with_reply_key).
+    pub fn ephemeral_key(&self) -> Option<&EncapsulationPublicKey> {
+        self.ephemeral_key.as_ref()
+    }
+
+    /// Returns a continuation the peer kept in its
+    /// [`ContinuationStorage`](crate::ContinuationStorage) by reference,
+    /// in place of the continuation itself, which is cleared.
+    ///
+    /// The peer must parse the request with
+    /// [`ParseOptions::with_continuation_storage`].
+    pub fn with_peer_continuation_ref(
+        mut self,
+        reference: PeerContinuationRef,
+    ) -> Self {
+        self.peer_continuation = None;
+        self.peer_continuation_ref = Some(reference);
+        self
+    }
+
+    pub fn peer_continuation_ref(&self) -> Option<&PeerContinuationRef> {
+        self.peer_continuation_ref.as_ref()
+    }
+
+    /// Adds a parameter whose value is encrypted to `third_party`, such as a
+    /// downstream processor, so that the recipient of the request sees only
+    /// ciphertext that it can forward.
+    ///
+    /// The value is encrypted before the request is signed, so the signature
+    /// covers the sealed form and it cannot be substituted. Fails with
+    /// [`Error::RecipientMissingEncryptionKey`] if `third_party` has no
+    /// encryption key.
+    pub fn with_parameter_sealed_to(
+        self,
+        parameter: impl Into<Parameter>,
+        value: impl EnvelopeEncodable,
+        third_party: &XIDDocument,
+    ) -> Result<Self> {
+        let value = value.into_envelope();
+        #[cfg(feature = "taint-checks")]
+        crate::taint::check_untainted(&value, "with_parameter_sealed_to");
+        let sealed = sealed_parameter::seal_parameter(value, third_party)?;
+        Ok(self.with_parameter(parameter, sealed))
+    }
 
-        if !recipients.is_empty() {
-            let recipient_keys = recipients
-                .iter()
-                .map(|recipient| {
-                    recipient
-                        .encryption_key()
-                        .ok_or(Error::RecipientMissingEncryptionKey)
-                        .map(|key| key as &dyn Encrypter)
-                })
-                .collect::<Result<Vec<&dyn Encrypter>>>()?;
-            result = result
-                .wrap()
-                .encrypt_subject_to_recipients(&recipient_keys)?;
+    /// Returns the sealed value of `parameter`, opaque to us, for forwarding
+    /// to the third party it was sealed to.
+    ///
+    /// Fails with [`Error::ParameterNotSealed`] if the parameter is present
+    /// but not sealed.
+    pub fn sealed_parameter(
+        &self,
+        parameter: impl Into<Parameter>,
+    ) -> Result<Envelope> {
+        let parameter = parameter.into();
+        let value = self.object_for_parameter(parameter.clone())?;
+        if !value.subject().is_encrypted() {
+            return Err(Error::ParameterNotSealed(parameter.name()));
         }
+        Ok(value)
+    }
+
+    /// Decrypts the value of `parameter`, sealed to the holder of
+    /// `recipient`.
+    pub fn extract_sealed_parameter<T>(
+        &self,
+        parameter: impl Into<Parameter>,
+        recipient: &PrivateKeys,
+    ) -> Result<T>
+    where
+        T: TryFrom<CBOR, Error = dcbor::Error> + 'static,
+    {
+        sealed_parameter::open_sealed_parameter(
+            &self.sealed_parameter(parameter)?,
+            recipient,
+        )
+    }
+
+    /// Proposes a session for the rest of the conversation with the
+    /// recipient. The proposal travels inside the encrypted message, so
+    /// sealing without a recipient fails with
+    /// [`Error::SessionProposalNotEncrypted`].
+    pub fn with_session_proposal(mut self, session: SessionKeys) -> Self {
+        self.session.proposal = Some(session);
+        self
+    }
+
+    /// Acknowledges the session with the given ID, proposed by the
+    /// recipient.
+    pub fn with_session_ack(mut self, id: ARID) -> Self {
+        self.session.ack = Some(id);
+        self
+    }
+
+    /// The session proposed by the sender, if any.
+    pub fn session_proposal(&self) -> Option<&SessionKeys> {
+        self.session.proposal.as_ref()
+    }
+
+    /// The ID of the session the sender acknowledged, if any.
+    pub fn session_ack(&self) -> Option<ARID> { self.session.ack }
+
+    /// Proposes how long the state the recipient keeps for us should live,
+    /// in whole seconds. The recipient reports the lifetime it granted with
+    /// its response.
+    pub fn with_proposed_state_lifetime(mut self, lifetime: Duration) -> Self {
+        self.proposed_state_lifetime = Some(lifetime);
+        self
+    }
+
+    /// The lifetime the sender proposed for the state we keep for it, if
+    /// any.
+    pub fn proposed_state_lifetime(&self) -> Option<Duration> {
+        self.proposed_state_lifetime
+    }
 
-        Ok(result)
+    /// The date until which the continuation the request returned to us is
+    /// valid, if the request was parsed and the continuation expires.
+    pub fn continuation_valid_until(&self) -> Option<Date> {
+        self.continuation_valid_until
+    }
+
+    /// The message ID the continuation the request returned to us is valid
+    /// for, if the request was parsed and the continuation is bound to one.
+    pub fn continuation_id(&self) -> Option<ARID> { self.continuation_id }
+
+    /// The digest of the envelope this request was parsed from, which a
+    /// response can carry with [`SealedResponse::with_in_response_to`] to
+    /// prove which request it answers.
+    pub fn envelope_digest(&self) -> Option<Digest> { self.envelope_digest }
+
+    /// How the request was sealed, if it was parsed from an encrypted
+    /// envelope.
+    pub fn sealing_report(&self) -> Option<SealingReport> {
+        self.sealing_report
+    }
+
+    /// Pushes `state` onto the state of the request, as the innermost layer
+    /// of a [`ContinuationStack`](crate::ContinuationStack). State that is
+    /// not already a stack becomes its outermost layer.
+    pub fn with_pushed_state(
+        mut self,
+        state: impl EnvelopeEncodable,
+    ) -> Result<Self> {
+        self.state =
+            Some(continuation_stack::push_state(self.state.as_ref(), state)?);
+        Ok(self)
+    }
+
+    /// Pops the innermost layer off the state of the request, returning it
+    /// with the state that remains, or `None` if the request has no state.
+    pub fn popped_state(&self) -> Result<Option<(Envelope, Option<Envelope>)>> {
+        continuation_stack::pop_state(self.state.as_ref())
+    }
+
+    /// Decodes the state of the request as a `T`, failing with
+    /// [`Error::ContinuationStateDecoding`] if it is not one.
+    pub fn state_as<T: TryFrom<Envelope>>(&self) -> Result<Option<T>> {
+        self.state
+            .as_ref()
+            .map(typed_continuation::decode_state)
+            .transpose()
+    }
+
+    /// Parses a request from an envelope typed by its message kind, like
+    /// [`Self::try_from_envelope`].
+    pub fn try_from_sealed(
+        envelope: &SealedRequestEnvelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        Self::try_from_envelope(
+            envelope.envelope(),
+            expected_id,
+            now,
+            recipient,
+        )
+    }
+
+    /// Parses a request from a `ur:envelope` string, such as one produced by
+    /// [`Self::to_ur_string`], like [`Self::try_from_envelope_opt`].
+    ///
+    /// Fails with [`Error::InvalidUR`] if the string is not an envelope UR,
+    /// and with [`Error::MessageKindMismatch`] if it carries another kind of
+    /// message.
+    pub fn try_from_ur_string(
+        ur_string: impl Into<String>,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let envelope = SealedRequestEnvelope::from_ur_string(ur_string)?;
+        Self::try_from_envelope_opt(envelope.envelope(), options, recipient)
+    }
+
+    /// Parses a request like [`Self::try_from_envelope`], picking the
+    /// recipient's private keys from `directory` using the recipient hints
+    /// carried by the envelope.
+    ///
+    /// Returns the fingerprint of the encryption key that matched alongside
+    /// the request.
+    pub fn try_from_envelope_with_directory(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        directory: &dyn KeyDirectory,
+    ) -> Result<(Self, Reference)> {
+        let (fingerprint, private_keys) =
+            key_directory::select_recipient(encrypted_envelope, directory)?;
+        let request =
+            Self::try_from_envelope(encrypted_envelope, id, now, private_keys)?;
+        Ok((request, fingerprint))
     }
 
     pub fn try_from_envelope(
@@ -334,43 +928,320 @@ impl SealedRequest {
         now: Option<Date>,
         recipient: &PrivateKeys,
     ) -> Result<Self> {
-        let signed_envelope =
-            encrypted_envelope.decrypt_to_recipient(recipient)?;
-        let sender: XIDDocument = signed_envelope
-            .try_unwrap()?
+        Self::try_from_envelope_opt(
+            encrypted_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(id)
+                .with_optional_now(now),
+            recipient,
+        )
+    }
+
+    /// Parses a request like [`Self::try_from_envelope`], configured by
+    /// `options`.
+    pub fn try_from_envelope_opt(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let mut partial = PartialParse::new(encrypted_envelope);
+        Self::try_from_envelope_recording(
+            encrypted_envelope,
+            options,
+            recipient,
+            &mut partial,
+        )
+    }
+
+    /// Parses a request like [`Self::try_from_envelope`], but instead of
+    /// failing outright also returns whatever metadata could be extracted
+    /// along the way.
+    ///
+    /// Nothing in the returned [`PartialParse`] is trustworthy unless the
+    /// request itself was returned.
+    pub fn try_from_envelope_lenient(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        recipient: &PrivateKeys,
+    ) -> (Option<Self>, PartialParse) {
+        let mut partial = PartialParse::new(encrypted_envelope);
+        match Self::try_from_envelope_recording(
+            encrypted_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(id)
+                .with_optional_now(now),
+            recipient,
+            &mut partial,
+        ) {
+            Ok(request) => (Some(request), partial),
+            Err(error) => {
+                partial.error = Some(error);
+                (None, partial)
+            }
+        }
+    }
+
+    /// Parses a request that was signed but not encrypted, as sealed with no
+    /// recipients, for transports that provide confidentiality themselves.
+    ///
+    /// The sender's signature and continuation are checked as for an
+    /// encrypted request. Without our private keys a continuation returned
+    /// to us cannot be opened, so the state is always `None`; use
+    /// [`Self::try_from_signed_envelope_opt`] to open it. Fails with
+    /// [`Error::UnexpectedEncryption`] if the envelope is encrypted, and with
+    /// [`Error::SessionProposalNotEncrypted`] if the request proposes a
+    /// session.
+    pub fn try_from_signed_envelope(
+        signed_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+    ) -> Result<Self> {
+        Self::try_from_signed_envelope_opt(
+            signed_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(id)
+                .with_optional_now(now),
+            None,
+        )
+    }
+
+    /// Parses a request like [`Self::try_from_signed_envelope`], configured
+    /// by `options`, opening any continuation returned to us with
+    /// `recipient`.
+    pub fn try_from_signed_envelope_opt(
+        signed_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient: Option<&PrivateKeys>,
+    ) -> Result<Self> {
+        if signed_envelope.subject().is_encrypted() {
+            return Err(Error::UnexpectedEncryption);
+        }
+        session::check_not_proposed_in_clear(signed_envelope)?;
+        let mut partial = PartialParse::new(signed_envelope);
+        Self::try_from_signed_recording(
+            signed_envelope,
+            false,
+            false,
+            options,
+            recipient,
+            &mut partial,
+        )
+    }
+
+    pub(crate) fn try_from_envelope_recording(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+        partial: &mut PartialParse,
+    ) -> Result<Self> {
+        partial.stage = ParseStage::Decryption;
+        let (signed_envelope, sealed_with_session, report) =
+            options.decrypt(encrypted_envelope, recipient)?;
+        partial.decrypted_size = Some(signed_envelope.to_cbor_data().len());
+        let request = Self::try_from_signed_recording(
+            &signed_envelope,
+            sealed_with_session,
+            false,
+            options,
+            Some(recipient),
+            partial,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..request
+        })
+    }
+
+    /// Parses a request sealed with [`Self::to_envelope_with_password`],
+    /// checking it against `options` and opening any continuation returned
+    /// to us with `recipient`.
+    ///
+    /// Fails with [`Error::PasswordDecryptionFailed`] if `password` is not
+    /// the one the request was sealed with.
+    pub fn try_from_envelope_with_password(
+        encrypted_envelope: &Envelope,
+        password: impl AsRef<[u8]>,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let (signed_envelope, report) = sealing::unlock_with_password(
+            encrypted_envelope,
+            password.as_ref(),
+        )?;
+        let mut partial = PartialParse::new(encrypted_envelope);
+        let request = Self::try_from_signed_recording(
+            &signed_envelope,
+            false,
+            false,
+            options,
+            Some(recipient),
+            &mut partial,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..request
+        })
+    }
+
+    /// Parses a request sealed by a [`Session`](crate::Session), given the
+    /// envelope it decrypted and authenticated in place of a signature.
+    pub(crate) fn try_from_session_envelope(
+        encrypted_envelope: &Envelope,
+        authenticated_envelope: &Envelope,
+        report: SealingReport,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let mut partial = PartialParse::new(encrypted_envelope);
+        let request = Self::try_from_signed_recording(
+            authenticated_envelope,
+            true,
+            true,
+            options,
+            Some(recipient),
+            &mut partial,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..request
+        })
+    }
+
+    fn try_from_signed_recording(
+        signed_envelope: &Envelope,
+        sealed_with_session: bool,
+        session_authenticated: bool,
+        options: &ParseOptions,
+        recipient: Option<&PrivateKeys>,
+        partial: &mut PartialParse,
+    ) -> Result<Self> {
+        partial.stage = ParseStage::Sender;
+        let mut warnings = Vec::new();
+        let message = duplicate_assertions::check_singular_assertions(
+            signed_envelope.try_unwrap()?,
+            options.duplicate_assertions(),
+            &mut warnings,
+        )?;
+        message_envelope::check_kind(&message, MessageKind::Request)?;
+        options.check_fields(&message)?;
+        if let Ok(request) = Request::try_from(message.clone()) {
+            partial.claimed_id = Some(request.id());
+            partial.claimed_function = Some(request.function().clone());
+        }
+        let sender: XIDDocument = message
             .object_for_predicate(known_values::SENDER)?
             .try_into()?;
-        let sender_verification_key = sender
-            .verification_key()
-            .ok_or(Error::SenderMissingVerificationKey)?;
-        let request_envelope =
+        partial.claimed_sender = Some(sender.xid());
+        let sender_verification_key =
+            options.sender_verification_key(&sender)?;
+
+        partial.stage = ParseStage::Signature;
+        // The signature covers the message as it was sent, so once it is
+        // verified the checked message can be used in its place. A session
+        // has already checked its authentication code in its place.
+        if !session_authenticated {
             signed_envelope.verify(sender_verification_key)?;
-        let peer_continuation = request_envelope
+            partial.signature_verified = true;
+            partial.verified_sender = Some(sender.clone());
+        }
+        options.check_sender(&sender)?;
+        if let Some(function) = &partial.claimed_function {
+            options.check_function(function)?;
+        }
+        let gstp_version = gstp_version::parse_version(&message)?;
+        let previous_digest = message
+            .extract_optional_object_for_predicate(transcript::TRANSCRIPT)?;
+        let ephemeral_key =
+            message.extract_optional_object_for_predicate(EPHEMERAL_KEY)?;
+        let provenance = options.parse_provenance(&message)?;
+        let extra_assertions = extra_assertions::from_message(&message);
+        let session = SessionAssertions::try_from_message(&message)?;
+        options.check_session_downgrade(sealed_with_session, &session)?;
+
+        partial.stage = ParseStage::Continuation;
+        let interactive =
+            options.request_profile() == RequestProfile::Interactive;
+        if interactive
+            && message
+                .optional_object_for_predicate(request_profile::ONE_WAY)?
+                .is_some()
+        {
+            return Err(Error::OneWayRequestNotAccepted);
+        }
+        let peer_continuation = message
             .optional_object_for_predicate(known_values::SENDER_CONTINUATION)?;
         if let Some(some_peer_continuation) = peer_continuation.clone() {
             if !some_peer_continuation.subject().is_encrypted() {
                 return Err(Error::PeerContinuationNotEncrypted);
             }
-        } else {
+        } else if interactive {
             return Err(Error::MissingPeerContinuation);
         }
-        let encrypted_continuation = request_envelope
-            .optional_object_for_predicate(
-                known_values::RECIPIENT_CONTINUATION,
-            )?;
+        let encrypted_continuation =
+            options.recipient_continuation(&message)?;
+        partial.returned_continuation = encrypted_continuation
+            .as_ref()
+            .map(|continuation| continuation.digest());
         let state: Option<Envelope>;
-        if let Some(encrypted_continuation) = encrypted_continuation {
-            let continuation = Continuation::try_from_envelope(
+        let continuation_valid_until: Option<Date>;
+        let continuation_id: Option<ARID>;
+        let continuation_nonce: Option<ARID>;
+        if let (Some(encrypted_continuation), Some(recipient)) =
+            (encrypted_continuation, recipient)
+        {
+            let continuation = options.parse_continuation(
                 &encrypted_continuation,
-                id,
-                now,
-                Some(recipient),
+                recipient,
+                partial.claimed_function.as_ref(),
+                &sender,
             )?;
+            #[cfg(feature = "taint-checks")]
+            crate::taint::mark_tainted(continuation.state());
             state = Some(continuation.state().clone());
+            continuation_valid_until = continuation.valid_until();
+            continuation_id = continuation.id();
+            continuation_nonce = continuation.nonce();
         } else {
             state = None;
+            continuation_valid_until = None;
+            continuation_id = None;
+            continuation_nonce = None;
         }
-        let request = Request::try_from(request_envelope)?;
-        Ok(Self { request, sender, state, peer_continuation })
+
+        let proposed_state_lifetime = state_lifetime::lifetime_from_message(
+            &message,
+            state_lifetime::PROPOSED_STATE_LIFETIME,
+        )?;
+
+        partial.stage = ParseStage::Request;
+        let request = Request::try_from(message)?;
+        options.field_limits().check_parameters(request.body())?;
+        options.record_continuation_nonce(
+            continuation_nonce,
+            continuation_valid_until,
+        )?;
+        Ok(Self {
+            request,
+            sender,
+            state,
+            peer_continuation,
+            peer_continuation_ref: None,
+            warnings,
+            gstp_version: Some(gstp_version),
+            provenance,
+            extra_assertions,
+            session,
+            proposed_state_lifetime,
+            continuation_valid_until,
+            continuation_id,
+            envelope_digest: Some(signed_envelope.digest()),
+            sealing_report: None,
+            previous_digest,
+            ephemeral_key,
+        })
     }
 }
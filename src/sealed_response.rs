@@ -1,10 +1,28 @@
-use bc_components::{ARID, Encrypter, PrivateKeys};
+use std::time::Duration;
+
+use bc_components::{
+    ARID, EncapsulationPublicKey, PrivateKeys, Reference, XID, XIDProvider,
+};
 use bc_envelope::{Signer, prelude::*};
-use bc_xid::{
-    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+use bc_xid::XIDDocument;
+
+use crate::{
+    ChunkingFallback, Continuation, ContinuationContext, ContinuationPolicy,
+    ContinuationReceipt, EarlyFailure, Error, KeyDirectory, MessageKind,
+    ParseOptions, ParseWarning, Provenance, Result, ResultTransform,
+    SealOptions, SealedArtifacts, SealedRequest, SealedResponseEnvelope,
+    SealingReport, SessionKeys, Strictness, continuation_stack,
+    duplicate_assertions, extra_assertions, gstp_version, key_directory,
+    message_envelope, provenance, result_chunks::ResultChunk, sealing,
+    session::SessionAssertions, state_lifetime, strictness, transcript,
+    typed_continuation,
 };
 
-use crate::{Continuation, Error, Result};
+pub(crate) const IN_RESPONSE_TO: &str = "inResponseTo";
+
+/// The bytes by which the headers of a chunk's data and indexes may exceed
+/// those of an empty chunk.
+const CHUNK_HEADERS: usize = 32;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SealedResponse {
@@ -16,6 +34,38 @@ pub struct SealedResponse {
     // This is a continuation we previously received from the peer and want to
     // send back to them.
     peer_continuation: Option<Envelope>,
+    // Anything questionable about the message that was accepted anyway when
+    // it was parsed.
+    warnings: Vec<ParseWarning>,
+    // When parsed, the protocol version the message is written in.
+    gstp_version: Option<u32>,
+    // When parsed, the provenance recorded by the sender.
+    provenance: Option<Provenance>,
+    // Application assertions added to the signed message.
+    extra_assertions: Vec<Envelope>,
+    // The session proposed or acknowledged by the message.
+    session: SessionAssertions,
+    // When parsed, the date until which the peer's continuation should be
+    // kept.
+    peer_continuation_retain_until: Option<Date>,
+    // The lifetime we granted the state the peer keeps for us.
+    granted_state_lifetime: Option<Duration>,
+    // The function the peer must return our state with.
+    state_function: Option<Function>,
+    // When parsed, the expiry and ID of the continuation returned to us.
+    continuation_valid_until: Option<Date>,
+    continuation_id: Option<ARID>,
+    // The digest of the request envelope this response answers.
+    in_response_to: Option<Digest>,
+    // The envelope digest of the message before this one in a transcript.
+    previous_digest: Option<Digest>,
+    // When parsed, the digest of the envelope as it was received.
+    envelope_digest: Option<Digest>,
+    // When parsed, how the message was sealed, if it was decrypted.
+    sealing_report: Option<SealingReport>,
+    // The ephemeral key of the request, encrypted to in place of the
+    // recipients' own keys.
+    reply_key: Option<EncapsulationPublicKey>,
 }
 
 impl std::fmt::Display for SealedResponse {
@@ -45,6 +95,21 @@ impl SealedResponse {
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            peer_continuation_retain_until: None,
+            granted_state_lifetime: None,
+            state_function: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            in_response_to: None,
+            previous_digest: None,
+            envelope_digest: None,
+            sealing_report: None,
+            reply_key: None,
         }
     }
 
@@ -58,18 +123,66 @@ impl SealedResponse {
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            peer_continuation_retain_until: None,
+            granted_state_lifetime: None,
+            state_function: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            in_response_to: None,
+            previous_digest: None,
+            envelope_digest: None,
+            sealing_report: None,
+            reply_key: None,
         }
     }
 
     /// An early failure takes place before the message has been decrypted,
     /// and therefore the ID and sender public key are not known.
+    ///
+    /// An early failure never carries state or a continuation; sealing one
+    /// that does fails with [`Error::InvalidEarlyFailure`].
     pub fn new_early_failure(sender: impl AsRef<XIDDocument>) -> Self {
         Self {
             response: Response::new_early_failure(),
             sender: sender.as_ref().clone(),
             state: None,
             peer_continuation: None,
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: Vec::new(),
+            session: SessionAssertions::default(),
+            peer_continuation_retain_until: None,
+            granted_state_lifetime: None,
+            state_function: None,
+            continuation_valid_until: None,
+            continuation_id: None,
+            in_response_to: None,
+            previous_digest: None,
+            envelope_digest: None,
+            sealing_report: None,
+            reply_key: None,
+        }
+    }
+
+    /// Adds state like [`SealedResponseBehavior::with_state`], failing with
+    /// [`Error::StateNotAllowedOnFailure`] right away if this is a failure.
+    pub fn try_with_state(self, state: impl EnvelopeEncodable) -> Result<Self> {
+        if !self.response.is_ok() {
+            return Err(Error::StateNotAllowedOnFailure);
         }
+        Ok(self.with_state(state))
+    }
+
+    /// Returns `true` if this is a failure that does not answer any
+    /// particular request.
+    pub fn is_early_failure(&self) -> bool {
+        self.response.is_err() && self.response.id().is_none()
     }
 }
 
@@ -78,7 +191,12 @@ pub trait SealedResponseBehavior: ResponseBehavior {
     // Composition
     //
 
-    /// Adds state to the request that the peer may return at some future time.
+    /// Adds state to the response that the peer may return at some future
+    /// time.
+    ///
+    /// Sealing a failure response with state fails with
+    /// [`Error::StateNotAllowedOnFailure`] unless allowed by
+    /// [`SealOptions::with_state_on_failure`].
     fn with_state(self, state: impl EnvelopeEncodable) -> Self;
 
     fn with_optional_state(self, state: Option<impl EnvelopeEncodable>)
@@ -86,11 +204,31 @@ pub trait SealedResponseBehavior: ResponseBehavior {
 
     /// Adds a continuation we previously received from the recipient and want
     /// to send back to them.
-    fn with_peer_continuation(
+    fn with_peer_continuation(self, peer_continuation: Envelope) -> Self;
+
+    /// Adds a continuation we previously received from the recipient and want
+    /// to send back to them.
+    fn with_optional_peer_continuation(
         self,
-        peer_continuation: Option<&Envelope>,
+        peer_continuation: Option<Envelope>,
     ) -> Self;
 
+    /// Adds a continuation we previously received from the recipient and want
+    /// to send back to them.
+    #[deprecated(
+        since = "0.14.0",
+        note = "use `with_optional_peer_continuation(peer_continuation.cloned())`"
+    )]
+    fn with_optional_peer_continuation_ref(
+        self,
+        peer_continuation: Option<&Envelope>,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        self.with_optional_peer_continuation(peer_continuation.cloned())
+    }
+
     //
     // Parsing
     //
@@ -107,13 +245,8 @@ impl SealedResponseBehavior for SealedResponse {
     // Composition
     //
 
-    /// Adds state to the request that the peer may return at some future time.
     fn with_state(mut self, state: impl EnvelopeEncodable) -> Self {
-        if self.response.is_ok() {
-            self.state = Some(state.into_envelope());
-        } else {
-            panic!("Cannot set state on a failed response");
-        }
+        self.state = Some(state.into_envelope());
         self
     }
 
@@ -129,13 +262,16 @@ impl SealedResponseBehavior for SealedResponse {
         }
     }
 
-    /// Adds a continuation we previously received from the recipient and want
-    /// to send back to them.
-    fn with_peer_continuation(
+    fn with_peer_continuation(mut self, peer_continuation: Envelope) -> Self {
+        self.peer_continuation = Some(peer_continuation);
+        self
+    }
+
+    fn with_optional_peer_continuation(
         mut self,
-        peer_continuation: Option<&Envelope>,
+        peer_continuation: Option<Envelope>,
     ) -> Self {
-        self.peer_continuation = peer_continuation.cloned();
+        self.peer_continuation = peer_continuation;
         self
     }
 
@@ -154,6 +290,9 @@ impl SealedResponseBehavior for SealedResponse {
 
 impl ResponseBehavior for SealedResponse {
     fn with_result(mut self, result: impl EnvelopeEncodable) -> Self {
+        let result = result.into_envelope();
+        #[cfg(feature = "taint-checks")]
+        crate::taint::check_untainted(&result, "with_result");
         self.response = self.response.with_result(result);
         self
     }
@@ -164,6 +303,11 @@ impl ResponseBehavior for SealedResponse {
         mut self,
         result: Option<impl EnvelopeEncodable>,
     ) -> Self {
+        let result = result.map(|result| result.into_envelope());
+        #[cfg(feature = "taint-checks")]
+        if let Some(result) = &result {
+            crate::taint::check_untainted(result, "with_optional_result");
+        }
         self.response = self.response.with_optional_result(result);
         self
     }
@@ -240,18 +384,232 @@ impl SealedResponse {
         sender: Option<&dyn Signer>,
         recipients: &[&XIDDocument],
     ) -> Result<Envelope> {
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &SealOptions::default(),
+        )
+    }
+
+    /// Creates an envelope that can be decrypted by zero or more recipients,
+    /// sealed according to `options`.
+    pub fn to_envelope_with_options(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.seal_detailed(valid_until, sender, recipients, options)
+            .map(|artifacts| artifacts.envelope)
+    }
+
+    /// Seals this response like [`Self::to_envelope_with_options`], also
+    /// returning the continuation issued with it, if any, and a receipt that
+    /// can be persisted to recognize the state when it is echoed back.
+    pub fn seal_detailed(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedArtifacts> {
+        sealing::check_protection(
+            sender.is_some(),
+            !recipients.is_empty(),
+            options,
+        )?;
+        self.session.check_encrypted(
+            !recipients.is_empty() || self.reply_key.is_some(),
+        )?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let (mut result, sender_continuation, continuation_receipt) =
+            self.compose(valid_until, recipients, options)?;
+
+        // Estimate the size before spending anything on signing and
+        // encryption, then confirm it once the message is sealed.
+        let limit = options.recipient_max_size();
+        if let Some(limit) = limit {
+            let size = sealing::estimate_sealed_size(&result, recipients.len());
+            if size > limit {
+                return Err(Error::ResponseTooLarge { size, limit });
+            }
+        }
+
+        if let Some(sender_private_key) = sender {
+            result = result.sign(sender_private_key);
+        }
+
+        let envelope = match &self.reply_key {
+            Some(key) => {
+                sealing::encrypt_to_keys(result, &[key], valid_until, options)?
+            }
+            None => sealing::encrypt_to_recipients(
+                result,
+                recipients,
+                valid_until,
+                options,
+            )?,
+        };
+        if let Some(limit) = limit {
+            let size = envelope.to_cbor_data().len();
+            if size > limit {
+                return Err(Error::ResponseTooLarge { size, limit });
+            }
+        }
+        Ok(SealedArtifacts {
+            envelope,
+            own_continuation: sender_continuation,
+            continuation_receipt,
+            ephemeral_key: None,
+        })
+    }
+
+    /// Seals a separate copy of this response to each recipient, passing the
+    /// result through that recipient's transform, if any, so that each sees
+    /// only its intended view.
+    ///
+    /// The message is composed once, so every copy carries the same
+    /// continuation, but each copy is signed separately after its result is
+    /// transformed. A transform that changes the result, rather than only
+    /// eliding parts of it, therefore changes the digest of that copy, and
+    /// the signatures of different copies do not verify one another.
+    /// Transforms are not applied to a failure, which every recipient sees
+    /// unchanged.
+    ///
+    /// Returns the XID of each recipient with the envelope sealed to it.
+    pub fn to_envelopes_per_recipient(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[(&XIDDocument, Option<&dyn ResultTransform>)],
+        options: &SealOptions,
+    ) -> Result<Vec<(XID, Envelope)>> {
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let (message, _, _) = self.compose(
+            valid_until,
+            &Vec::from_iter(recipients.iter().map(|(recipient, _)| *recipient)),
+            options,
+        )?;
+        let result = self.response.result().ok();
+
+        recipients
+            .iter()
+            .map(|(recipient, transform)| {
+                sealing::check_protection(sender.is_some(), true, options)?;
+                let mut copy = message.clone();
+                if let (Some(transform), Some(result)) = (transform, result) {
+                    let transformed = transform.transform(result.clone())?;
+                    copy = copy.replace_assertion(
+                        Envelope::new_assertion(
+                            known_values::RESULT,
+                            result.clone(),
+                        ),
+                        Envelope::new_assertion(
+                            known_values::RESULT,
+                            transformed,
+                        ),
+                    )?;
+                }
+                if let Some(sender_private_key) = sender {
+                    copy = copy.sign(sender_private_key);
+                }
+                let envelope = sealing::encrypt_to_recipients(
+                    copy,
+                    &[recipient],
+                    valid_until,
+                    options,
+                )?;
+                if let Some(limit) = options.recipient_max_size() {
+                    let size = envelope.to_cbor_data().len();
+                    if size > limit {
+                        return Err(Error::ResponseTooLarge { size, limit });
+                    }
+                }
+                Ok((recipient.xid(), envelope))
+            })
+            .collect()
+    }
+
+    /// Builds the unsigned message for `recipients`, issuing the continuation
+    /// for our state.
+    pub(crate) fn compose(
+        &self,
+        valid_until: Option<Date>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<(Envelope, Option<Envelope>, Option<ContinuationReceipt>)> {
+        if self.is_early_failure()
+            && (self.state.is_some() || self.peer_continuation.is_some())
+        {
+            return Err(Error::InvalidEarlyFailure);
+        }
+        if self.is_early_failure()
+            && strictness() == Strictness::Production
+            && self.response.error()? == &Envelope::unknown()
+        {
+            return Err(Error::EarlyFailureWithoutReason);
+        }
+        if !self.response.is_ok()
+            && self.state.is_some()
+            && !options.state_on_failure()
+        {
+            return Err(Error::StateNotAllowedOnFailure);
+        }
+        if let Ok(result) = self.response.result() {
+            options.check_result(result)?;
+        }
         let sender_continuation: Option<Envelope>;
+        let continuation_receipt: Option<ContinuationReceipt>;
         if let Some(state) = &self.state {
-            let continuation =
-                Continuation::new(state).with_optional_valid_until(valid_until);
-            let sender_encryption_key = self
-                .sender
-                .encryption_key()
-                .ok_or(Error::SenderMissingEncryptionKey)?;
-            sender_continuation =
-                Some(continuation.to_envelope(Some(sender_encryption_key)));
+            let state = sealing::filter_state(
+                options,
+                &ContinuationContext {
+                    kind: MessageKind::Response,
+                    function: None,
+                },
+                state.clone(),
+            )?;
+            options.check_state(&state)?;
+            let granted_until = self
+                .granted_state_lifetime
+                .map(|lifetime| options.sealing_date() + lifetime);
+            let valid_until = match (valid_until, granted_until) {
+                (Some(valid_until), Some(granted_until)) => {
+                    Some(valid_until.min(granted_until))
+                }
+                (valid_until, granted_until) => valid_until.or(granted_until),
+            };
+            let valid_until = options.continuation_valid_until(valid_until)?;
+            let continuation = Continuation::new(state)
+                .with_optional_valid_function(self.state_function.clone())
+                .with_optional_valid_sender(sealing::bound_sender(
+                    options, recipients,
+                )?)
+                .with_optional_nonce(
+                    options.continuation_nonces().then(ARID::new),
+                )
+                .with_optional_valid_from(
+                    options
+                        .valid_from()
+                        .map(|date| options.normalize_date(date)),
+                )
+                .with_optional_valid_until(valid_until)
+                .with_issued_at(options.sealing_date());
+            sender_continuation = Some(sealing::issue_continuation(
+                &continuation,
+                &self.sender,
+                // Early failures cannot carry state.
+                self.id().ok_or(Error::InvalidEarlyFailure)?,
+                None,
+                options,
+            )?);
+            continuation_receipt =
+                Some(ContinuationReceipt::new(&continuation));
         } else {
             sender_continuation = None;
+            continuation_receipt = None;
         }
 
         let mut result = self
@@ -260,43 +618,465 @@ impl SealedResponse {
             .into_envelope()
             .add_assertion(
                 known_values::SENDER,
-                self.sender
-                    .to_envelope(
-                        XIDPrivateKeyOptions::default(),
-                        XIDGeneratorOptions::default(),
-                        XIDSigningOptions::default(),
-                    )
-                    .unwrap(),
+                sealing::sender_envelope(&self.sender, options)?,
             )
             .add_optional_assertion(
                 known_values::SENDER_CONTINUATION,
-                sender_continuation,
+                sender_continuation.clone(),
             )
             .add_optional_assertion(
                 known_values::RECIPIENT_CONTINUATION,
                 self.peer_continuation.clone(),
             );
+        result = self.session.add_to(result);
+        result = result.add_optional_assertion(
+            state_lifetime::GRANTED_STATE_LIFETIME,
+            self.granted_state_lifetime
+                .map(|lifetime| lifetime.as_secs()),
+        );
+        result =
+            result.add_optional_assertion(IN_RESPONSE_TO, self.in_response_to);
+
+        result = result.add_optional_assertion(
+            transcript::TRANSCRIPT,
+            self.previous_digest,
+        );
+        result = gstp_version::add_version(result, options);
+        result = provenance::add_provenance(result, options);
+        result = extra_assertions::add_to(result, &self.extra_assertions)?;
 
+        Ok((result, sender_continuation, continuation_receipt))
+    }
+
+    /// Seals this response like [`Self::to_envelope_with_options`], encrypted
+    /// with a key derived from `password` rather than to recipients, for
+    /// transfer to a party whose keys we do not know, such as by QR code
+    /// between air-gapped devices.
+    pub fn to_envelope_with_password(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        password: impl AsRef<[u8]>,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        sealing::check_protection(sender.is_some(), true, options)?;
+        let valid_until = valid_until.map(|date| options.normalize_date(date));
+        let (mut message, _, _) = self.compose(valid_until, &[], options)?;
         if let Some(sender_private_key) = sender {
-            result = result.sign(sender_private_key);
+            message = message.sign(sender_private_key);
         }
+        sealing::lock_with_password(
+            message,
+            password.as_ref(),
+            valid_until,
+            options,
+        )
+    }
 
-        if !recipients.is_empty() {
-            let recipient_keys = recipients
-                .iter()
-                .map(|recipient| {
-                    recipient
-                        .encryption_key()
-                        .ok_or(Error::RecipientMissingEncryptionKey)
-                        .map(|key| key as &dyn Encrypter)
-                })
-                .collect::<Result<Vec<&dyn Encrypter>>>()?;
-            result = result
-                .wrap()
-                .encrypt_subject_to_recipients(&recipient_keys)?;
+    /// Seals this response like [`Self::to_envelope_with_options`], even if
+    /// it is unsigned or unencrypted under [`Strictness::Production`].
+    pub fn to_envelope_unprotected_i_know_what_i_am_doing(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            &options.allowing_unprotected(),
+        )
+    }
+
+    /// Seals this response like [`Self::to_envelope_with_options`], returning
+    /// an envelope typed by its message kind.
+    pub fn seal(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<SealedResponseEnvelope> {
+        self.to_envelope_with_options(valid_until, sender, recipients, options)
+            .map(SealedResponseEnvelope::new_unchecked)
+    }
+
+    /// Seals this response like [`Self::to_envelope`], encoded as a
+    /// `ur:envelope` string for transport by QR code or NFC.
+    pub fn to_ur_string(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipient: Option<&XIDDocument>,
+    ) -> Result<String> {
+        Ok(self
+            .to_envelope(valid_until, sender, recipient)?
+            .ur_string())
+    }
+
+    /// Seals this response for a recipient whose maximum message size is set
+    /// with [`SealOptions::with_recipient_max_size`], splitting its result
+    /// across several responses if it would not fit and the options allow
+    /// [`ChunkingFallback::Chunk`].
+    ///
+    /// Only the first of several responses carries the state and the peer's
+    /// continuation. The recipient reassembles the result with a
+    /// [`ResultAssembler`](crate::ResultAssembler).
+    pub fn seal_chunked(
+        &self,
+        valid_until: Option<Date>,
+        sender: Option<&dyn Signer>,
+        recipients: &[&XIDDocument],
+        options: &SealOptions,
+    ) -> Result<Vec<Envelope>> {
+        let sealed = self.to_envelope_with_options(
+            valid_until,
+            sender,
+            recipients,
+            options,
+        );
+        let (Err(Error::ResponseTooLarge { limit, .. }), Ok(result)) =
+            (&sealed, self.response.result())
+        else {
+            return sealed.map(|envelope| vec![envelope]);
+        };
+        if options.chunking_fallback() != ChunkingFallback::Chunk {
+            return sealed.map(|envelope| vec![envelope]);
+        }
+        let limit = *limit;
+
+        // Size the chunks from a response carrying an empty one, allowing
+        // for the headers of the chunk's data and indexes.
+        let empty = ResultChunk {
+            index: 0,
+            count: 1,
+            digest: result.digest(),
+            data: ByteString::from(Vec::new()),
+        };
+        let (skeleton, _, _) = self.chunk_response(&empty, true).compose(
+            valid_until,
+            recipients,
+            options,
+        )?;
+        let size = sealing::estimate_sealed_size(&skeleton, recipients.len())
+            + CHUNK_HEADERS;
+        let chunk_size = limit.saturating_sub(size);
+        if chunk_size == 0 {
+            return Err(Error::ResponseTooLarge { size, limit });
         }
 
-        Ok(result)
+        ResultChunk::split(result, chunk_size)
+            .iter()
+            .map(|chunk| {
+                self.chunk_response(chunk, chunk.index == 0)
+                    .to_envelope_with_options(
+                        valid_until,
+                        sender,
+                        recipients,
+                        options,
+                    )
+            })
+            .collect()
+    }
+
+    /// Returns a copy of this response carrying `chunk` as its result, and
+    /// carrying the state and continuations only if it is the `first`.
+    fn chunk_response(&self, chunk: &ResultChunk, first: bool) -> Self {
+        let id = self.response.id().expect("successful response has an ID");
+        Self {
+            response: Response::new_success(id)
+                .with_result(chunk.to_envelope()),
+            sender: self.sender.clone(),
+            state: self.state.clone().filter(|_| first),
+            peer_continuation: self.peer_continuation.clone().filter(|_| first),
+            warnings: Vec::new(),
+            gstp_version: None,
+            provenance: None,
+            extra_assertions: self.extra_assertions.clone(),
+            session: if first {
+                self.session.clone()
+            } else {
+                SessionAssertions::default()
+            },
+            peer_continuation_retain_until: None,
+            granted_state_lifetime: self
+                .granted_state_lifetime
+                .filter(|_| first),
+            state_function: self.state_function.clone(),
+            continuation_valid_until: None,
+            continuation_id: None,
+            in_response_to: self.in_response_to,
+            previous_digest: self.previous_digest,
+            envelope_digest: None,
+            sealing_report: None,
+            reply_key: self.reply_key.clone(),
+        }
+    }
+
+    /// Anything questionable about the message that was accepted anyway when
+    /// it was parsed, such as duplicate assertions discarded under
+    /// [`DuplicateAssertionPolicy::TakeFirst`](crate::DuplicateAssertionPolicy::TakeFirst).
+    pub fn warnings(&self) -> &[ParseWarning] { &self.warnings }
+
+    /// The protocol version the message is written in, if it was parsed.
+    /// Messages that predate the version assertion are version 1.
+    pub fn gstp_version(&self) -> Option<u32> { self.gstp_version }
+
+    /// The provenance recorded by the sender, if the message was parsed and
+    /// the sender sealed it with
+    /// [`SealOptions::with_provenance`](crate::SealOptions::with_provenance).
+    pub fn provenance(&self) -> Option<&Provenance> { self.provenance.as_ref() }
+
+    /// Adds an assertion to the signed message, on the same layer as its
+    /// note and date, for metadata such as a tenant or trace ID. May be
+    /// called repeatedly.
+    ///
+    /// Sealing fails with [`Error::ReservedPredicate`] if `predicate` is one
+    /// of the [`reserved_predicates`](crate::reserved_predicates).
+    pub fn with_extra_assertion(
+        mut self,
+        predicate: impl EnvelopeEncodable,
+        object: impl EnvelopeEncodable,
+    ) -> Self {
+        self.extra_assertions
+            .push(Envelope::new_assertion(predicate, object));
+        self
+    }
+
+    /// The assertions added with [`Self::with_extra_assertion`]. When the
+    /// message was parsed, every assertion on its signed layer whose
+    /// predicate is not reserved.
+    pub fn extra_assertions(&self) -> &[Envelope] { &self.extra_assertions }
+
+    /// Commits this response to `digest`, the envelope digest of the message
+    /// before it in a [`Transcript`](crate::Transcript).
+    pub fn with_previous_digest(mut self, digest: Digest) -> Self {
+        self.previous_digest = Some(digest);
+        self
+    }
+
+    pub fn with_optional_previous_digest(
+        mut self,
+        digest: Option<Digest>,
+    ) -> Self {
+        self.previous_digest = digest;
+        self
+    }
+
+    /// The envelope digest of the message before this one in a transcript,
+    /// if the response commits to one.
+    pub fn previous_digest(&self) -> Option<Digest> { self.previous_digest }
+
+    /// The digest of the envelope this response was parsed from.
+    pub fn envelope_digest(&self) -> Option<Digest> { self.envelope_digest }
+
+    /// How the response was sealed, if it was parsed from an encrypted
+    /// envelope.
+    pub fn sealing_report(&self) -> Option<SealingReport> {
+        self.sealing_report
+    }
+
+    /// Encrypts this response to `key`, the ephemeral key of the request it
+    /// answers, in place of the recipients' own keys.
+    pub fn with_reply_key(self, key: EncapsulationPublicKey) -> Self {
+        self.with_optional_reply_key(Some(key))
+    }
+
+    pub fn with_optional_reply_key(
+        mut self,
+        key: Option<EncapsulationPublicKey>,
+    ) -> Self {
+        self.reply_key = key;
+        self
+    }
+
+    pub fn reply_key(&self) -> Option<&EncapsulationPublicKey> {
+        self.reply_key.as_ref()
+    }
+
+    /// Proposes a session for the rest of the conversation with the
+    /// recipient. The proposal travels inside the encrypted message, so
+    /// sealing without a recipient fails with
+    /// [`Error::SessionProposalNotEncrypted`].
+    pub fn with_session_proposal(mut self, session: SessionKeys) -> Self {
+        self.session.proposal = Some(session);
+        self
+    }
+
+    /// Acknowledges the session with the given ID, proposed by the
+    /// recipient.
+    pub fn with_session_ack(mut self, id: ARID) -> Self {
+        self.session.ack = Some(id);
+        self
+    }
+
+    /// The session proposed by the sender, if any.
+    pub fn session_proposal(&self) -> Option<&SessionKeys> {
+        self.session.proposal.as_ref()
+    }
+
+    /// The ID of the session the sender acknowledged, if any.
+    pub fn session_ack(&self) -> Option<ARID> { self.session.ack }
+
+    /// The date until which the continuation issued by the peer should be
+    /// kept, if the response was parsed and the peer issued one whose expiry
+    /// is known or capped; see
+    /// [`ParseOptions::with_max_peer_continuation_lifetime`].
+    pub fn peer_continuation_retain_until(&self) -> Option<Date> {
+        self.peer_continuation_retain_until
+    }
+
+    /// The date until which the continuation the response returned to us is
+    /// valid, if the response was parsed and the continuation expires.
+    pub fn continuation_valid_until(&self) -> Option<Date> {
+        self.continuation_valid_until
+    }
+
+    /// The message ID the continuation the response returned to us is valid
+    /// for, if the response was parsed and the continuation is bound to one.
+    pub fn continuation_id(&self) -> Option<ARID> { self.continuation_id }
+
+    /// Pushes `state` onto the state of the response, as the innermost layer
+    /// of a [`ContinuationStack`](crate::ContinuationStack). State that is
+    /// not already a stack becomes its outermost layer.
+    pub fn with_pushed_state(
+        mut self,
+        state: impl EnvelopeEncodable,
+    ) -> Result<Self> {
+        self.state =
+            Some(continuation_stack::push_state(self.state.as_ref(), state)?);
+        Ok(self)
+    }
+
+    /// Pops the innermost layer off the state of the response, returning it
+    /// with the state that remains, or `None` if the response has no state.
+    pub fn popped_state(&self) -> Result<Option<(Envelope, Option<Envelope>)>> {
+        continuation_stack::pop_state(self.state.as_ref())
+    }
+
+    /// Decodes the state of the response as a `T`, failing with
+    /// [`Error::ContinuationStateDecoding`] if it is not one.
+    pub fn state_as<T: TryFrom<Envelope>>(&self) -> Result<Option<T>> {
+        self.state
+            .as_ref()
+            .map(typed_continuation::decode_state)
+            .transpose()
+    }
+
+    /// Records that the state of this response lives for `lifetime`, in
+    /// whole seconds. The continuation expires then, or earlier if the
+    /// response is sealed with an earlier `valid_until`.
+    pub fn with_granted_state_lifetime(mut self, lifetime: Duration) -> Self {
+        self.granted_state_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Grants the state of this response the lifetime `request` proposed,
+    /// as clamped by `policy`; see
+    /// [`ContinuationPolicy::grant_state_lifetime`].
+    pub fn with_negotiated_state_lifetime(
+        mut self,
+        request: &SealedRequest,
+        policy: &ContinuationPolicy,
+    ) -> Self {
+        self.granted_state_lifetime =
+            policy.grant_state_lifetime(request.proposed_state_lifetime());
+        self
+    }
+
+    /// Binds the state of this response to `function`, so that the peer can
+    /// only return it with a request to that function.
+    pub fn with_state_function(
+        mut self,
+        function: impl Into<Function>,
+    ) -> Self {
+        self.state_function = Some(function.into());
+        self
+    }
+
+    /// Binds this response to the request envelope with digest `digest`, as
+    /// given by [`SealedRequest::envelope_digest`], so that the requester
+    /// can check that we saw exactly the envelope it sent.
+    pub fn with_in_response_to(mut self, digest: Digest) -> Self {
+        self.in_response_to = Some(digest);
+        self
+    }
+
+    pub fn with_optional_in_response_to(
+        mut self,
+        digest: Option<Digest>,
+    ) -> Self {
+        self.in_response_to = digest;
+        self
+    }
+
+    /// The digest of the request envelope this response answers, if it is
+    /// bound to one.
+    pub fn in_response_to(&self) -> Option<Digest> { self.in_response_to }
+
+    /// The lifetime the sender granted the state it keeps for us, if any.
+    pub fn granted_state_lifetime(&self) -> Option<Duration> {
+        self.granted_state_lifetime
+    }
+
+    /// Parses a response from an envelope typed by its message kind, like
+    /// [`Self::try_from_encrypted_envelope`].
+    pub fn try_from_sealed(
+        envelope: &SealedResponseEnvelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        Self::try_from_encrypted_envelope(
+            envelope.envelope(),
+            expected_id,
+            now,
+            recipient,
+        )
+    }
+
+    /// Parses a response from a `ur:envelope` string, such as one produced by
+    /// [`Self::to_ur_string`], like [`Self::try_from_encrypted_envelope_opt`].
+    ///
+    /// Fails with [`Error::InvalidUR`] if the string is not an envelope UR,
+    /// and with [`Error::MessageKindMismatch`] if it carries another kind of
+    /// message.
+    pub fn try_from_ur_string(
+        ur_string: impl Into<String>,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<Self> {
+        let envelope = SealedResponseEnvelope::from_ur_string(ur_string)?;
+        Self::try_from_encrypted_envelope_opt(
+            envelope.envelope(),
+            options,
+            recipient,
+        )
+    }
+
+    /// Parses a response like [`Self::try_from_encrypted_envelope`], picking
+    /// the recipient's private keys from `directory` using the recipient
+    /// hints carried by the envelope.
+    ///
+    /// Returns the fingerprint of the encryption key that matched alongside
+    /// the response.
+    pub fn try_from_encrypted_envelope_with_directory(
+        encrypted_envelope: &Envelope,
+        expected_id: Option<ARID>,
+        now: Option<Date>,
+        directory: &dyn KeyDirectory,
+    ) -> Result<(Self, Reference)> {
+        let (fingerprint, private_keys) =
+            key_directory::select_recipient(encrypted_envelope, directory)?;
+        let response = Self::try_from_encrypted_envelope(
+            encrypted_envelope,
+            expected_id,
+            now,
+            private_keys,
+        )?;
+        Ok((response, fingerprint))
     }
 
     pub fn try_from_encrypted_envelope(
@@ -305,17 +1085,184 @@ impl SealedResponse {
         now: Option<Date>,
         recipient_private_key: &PrivateKeys,
     ) -> Result<Self> {
-        let signed_envelope =
-            encrypted_envelope.decrypt_to_recipient(recipient_private_key)?;
-        let sender: XIDDocument = signed_envelope
-            .try_unwrap()?
+        Self::try_from_encrypted_envelope_opt(
+            encrypted_envelope,
+            &ParseOptions::new()
+                .with_optional_expected_id(expected_id)
+                .with_optional_now(now),
+            recipient_private_key,
+        )
+    }
+
+    /// Parses an early failure with minimal trust, to learn why a peer could
+    /// not process a request even when the peer's identity is unknown.
+    ///
+    /// The sender is neither pinned nor required to have signed the
+    /// response; see [`EarlyFailure`] for what is reported instead. Anything
+    /// but a bare early failure, including a response that answers a request,
+    /// carries a result, or carries a continuation, is refused.
+    pub fn try_parse_early_failure(
+        encrypted_envelope: &Envelope,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<EarlyFailure> {
+        let (signed_envelope, _) = sealing::decrypt_to_recipient(
+            encrypted_envelope,
+            recipient_private_key,
+        )?;
+        let message = signed_envelope.try_unwrap()?;
+        let response = Response::try_from(message.clone())?;
+        if response.is_ok() || response.id().is_some() {
+            return Err(Error::NotAnEarlyFailure);
+        }
+        gstp_version::parse_version(&message)?;
+        let version_predicate = Envelope::new(gstp_version::GSTP_VERSION);
+        for assertion in message.assertions() {
+            let predicate = assertion.try_predicate()?;
+            let permitted = predicate.digest() == version_predicate.digest()
+                || predicate.as_known_value().is_some_and(|value| {
+                    *value == known_values::ERROR
+                        || *value == known_values::SENDER
+                });
+            if !permitted {
+                return Err(Error::InvalidEarlyFailure);
+            }
+        }
+        let claimed_sender: Option<XIDDocument> = message
+            .optional_object_for_predicate(known_values::SENDER)?
+            .map(XIDDocument::try_from)
+            .transpose()?;
+        let signature_verified = claimed_sender
+            .as_ref()
+            .and_then(|sender| sender.inception_signing_key())
+            .is_some_and(|key| signed_envelope.verify(key).is_ok());
+        Ok(EarlyFailure {
+            error: response.error()?.clone(),
+            claimed_sender: claimed_sender.map(|sender| sender.xid()),
+            signature_verified,
+        })
+    }
+
+    /// Parses a response, checking it against `options`.
+    pub fn try_from_encrypted_envelope_opt(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let (signed_envelope, sealed_with_session, report) =
+            options.decrypt(encrypted_envelope, recipient_private_key)?;
+        let response = Self::try_from_signed(
+            &signed_envelope,
+            sealed_with_session,
+            false,
+            options,
+            recipient_private_key,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..response
+        })
+    }
+
+    /// Parses a response sealed with [`Self::to_envelope_with_password`],
+    /// checking it against `options` and opening any continuation returned
+    /// to us with `recipient_private_key`.
+    ///
+    /// Fails with [`Error::PasswordDecryptionFailed`] if `password` is not
+    /// the one the response was sealed with.
+    pub fn try_from_envelope_with_password(
+        encrypted_envelope: &Envelope,
+        password: impl AsRef<[u8]>,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let (signed_envelope, report) = sealing::unlock_with_password(
+            encrypted_envelope,
+            password.as_ref(),
+        )?;
+        let response = Self::try_from_signed(
+            &signed_envelope,
+            false,
+            false,
+            options,
+            recipient_private_key,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..response
+        })
+    }
+
+    /// Parses a response sealed by a [`Session`](crate::Session), given the
+    /// envelope it decrypted and authenticated in place of a signature.
+    pub(crate) fn try_from_session_envelope(
+        encrypted_envelope: &Envelope,
+        authenticated_envelope: &Envelope,
+        report: SealingReport,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let response = Self::try_from_signed(
+            authenticated_envelope,
+            true,
+            true,
+            options,
+            recipient_private_key,
+        )?;
+        Ok(Self {
+            envelope_digest: Some(encrypted_envelope.digest()),
+            sealing_report: Some(report),
+            ..response
+        })
+    }
+
+    fn try_from_signed(
+        signed_envelope: &Envelope,
+        sealed_with_session: bool,
+        session_authenticated: bool,
+        options: &ParseOptions,
+        recipient_private_key: &PrivateKeys,
+    ) -> Result<Self> {
+        let mut warnings = Vec::new();
+        let response_envelope =
+            duplicate_assertions::check_singular_assertions(
+                signed_envelope.try_unwrap()?,
+                options.duplicate_assertions(),
+                &mut warnings,
+            )?;
+        message_envelope::check_kind(
+            &response_envelope,
+            MessageKind::Response,
+        )?;
+        options.check_fields(&response_envelope)?;
+        let sender: XIDDocument = response_envelope
             .object_for_predicate(known_values::SENDER)?
             .try_into()?;
-        let sender_verification_key = sender
-            .verification_key()
-            .ok_or(Error::SenderMissingVerificationKey)?;
-        let response_envelope =
+        let sender_verification_key =
+            options.sender_verification_key(&sender)?;
+        // The signature covers the message as it was sent, so once it is
+        // verified the checked message can be used in its place. A session
+        // has already checked its authentication code in its place.
+        if !session_authenticated {
             signed_envelope.verify(sender_verification_key)?;
+        }
+        options.check_sender(&sender)?;
+        let response = Response::try_from(response_envelope.clone())?;
+        options.check_response_id(response.id())?;
+        let in_response_to = response_envelope
+            .extract_optional_object_for_predicate(IN_RESPONSE_TO)?;
+        if response.id().is_some() {
+            options.check_request_digest(in_response_to)?;
+        }
+        let gstp_version = gstp_version::parse_version(&response_envelope)?;
+        let previous_digest = response_envelope
+            .extract_optional_object_for_predicate(transcript::TRANSCRIPT)?;
+        let provenance = options.parse_provenance(&response_envelope)?;
+        let extra_assertions =
+            extra_assertions::from_message(&response_envelope);
+        let session = SessionAssertions::try_from_message(&response_envelope)?;
+        options.check_session_downgrade(sealed_with_session, &session)?;
         let peer_continuation = response_envelope
             .optional_object_for_predicate(known_values::SENDER_CONTINUATION)?;
         if let Some(some_peer_continuation) = peer_continuation.clone()
@@ -327,23 +1274,76 @@ impl SealedResponse {
             .optional_object_for_predicate(
                 known_values::RECIPIENT_CONTINUATION,
             )?;
+        let carries_continuation =
+            peer_continuation.is_some() || encrypted_continuation.is_some();
+        let peer_continuation_retain_until = peer_continuation
+            .as_ref()
+            .map(|continuation| {
+                options.peer_continuation_retention(continuation)
+            })
+            .transpose()?
+            .flatten();
         let state: Option<Envelope>;
+        let continuation_valid_until: Option<Date>;
+        let continuation_id: Option<ARID>;
+        let continuation_nonce: Option<ARID>;
         if let Some(encrypted_continuation) = encrypted_continuation {
-            let continuation = Continuation::try_from_envelope(
+            let continuation = options.parse_continuation(
                 &encrypted_continuation,
-                expected_id,
-                now,
-                Some(recipient_private_key),
+                recipient_private_key,
+                None,
+                &sender,
             )?;
+            #[cfg(feature = "taint-checks")]
+            crate::taint::mark_tainted(continuation.state());
             if continuation.state().is_null() {
                 state = None;
             } else {
                 state = Some(continuation.state().clone());
             }
+            continuation_valid_until = continuation.valid_until();
+            continuation_id = continuation.id();
+            continuation_nonce = continuation.nonce();
         } else {
             state = None;
+            continuation_valid_until = None;
+            continuation_id = None;
+            continuation_nonce = None;
+        }
+        let granted_state_lifetime = state_lifetime::lifetime_from_message(
+            &response_envelope,
+            state_lifetime::GRANTED_STATE_LIFETIME,
+        )?;
+        if response.id().is_none() && carries_continuation {
+            return Err(Error::InvalidEarlyFailure);
+        }
+        if let Ok(result) = response.result() {
+            options.field_limits().check_result(result)?;
         }
-        let response = Response::try_from(response_envelope)?;
-        Ok(Self { response, sender, state, peer_continuation })
+        options.record_continuation_nonce(
+            continuation_nonce,
+            continuation_valid_until,
+        )?;
+        Ok(Self {
+            response,
+            sender,
+            state,
+            peer_continuation,
+            warnings,
+            gstp_version: Some(gstp_version),
+            provenance,
+            extra_assertions,
+            session,
+            peer_continuation_retain_until,
+            granted_state_lifetime,
+            state_function: None,
+            continuation_valid_until,
+            continuation_id,
+            in_response_to,
+            previous_digest,
+            envelope_digest: None,
+            sealing_report: None,
+            reply_key: None,
+        })
     }
 }
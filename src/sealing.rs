@@ -0,0 +1,572 @@
+use std::{collections::HashSet, time::Duration};
+
+use bc_components::{
+    ARID, Decrypter, EncapsulationPrivateKey, EncapsulationPublicKey,
+    EncapsulationScheme, Encrypter, PrivateKeys, PublicKeys, ReferenceProvider,
+    SigningPublicKey, SymmetricKey, XID, XIDProvider,
+};
+use bc_crypto::hash::crc32;
+use bc_envelope::prelude::*;
+use bc_xid::{
+    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+};
+
+use crate::{
+    CompressionPolicy, Continuation, ContinuationContext, ContinuationMode,
+    ContinuationSealer, Error, Result, SealOptions, SealingReport,
+    SenderDisclosure, SessionKeys, Strictness, TofuStore, consts, inspect,
+    state_store, strictness,
+};
+
+/// Under [`Strictness::Production`], refuses to seal a message that would
+/// be unsigned or unencrypted, unless `options` allow it.
+pub(crate) fn check_protection(
+    signed: bool,
+    encrypted: bool,
+    options: &SealOptions,
+) -> Result<()> {
+    if strictness() == Strictness::Production
+        && !options.allows_unprotected()
+        && (!signed || !encrypted)
+    {
+        return Err(Error::UnprotectedSealForbidden);
+    }
+    Ok(())
+}
+
+/// Passes the state of an outgoing continuation through the continuation
+/// filter, if one is configured.
+pub(crate) fn filter_state(
+    options: &SealOptions,
+    context: &ContinuationContext<'_>,
+    state: Envelope,
+) -> Result<Envelope> {
+    let Some(filter) = options.continuation_filter() else {
+        return Ok(state);
+    };
+    filter.filter(context, state).map_err(|error| match error {
+        Error::ContinuationRejectedByFilter(_) => error,
+        error => Error::ContinuationRejectedByFilter(error.to_string()),
+    })
+}
+
+/// Returns the key that continuations we issue are encapsulated to: the
+/// configured continuation key if there is one, otherwise the sender's own
+/// encryption key.
+fn continuation_key<'a>(
+    options: &'a SealOptions,
+    sender: &'a XIDDocument,
+) -> Result<&'a dyn Encrypter> {
+    match options.continuation_key() {
+        Some(key) => Ok(key),
+        None => sender
+            .encryption_key()
+            .map(|key| key as &dyn Encrypter)
+            .ok_or(Error::SenderMissingEncryptionKey),
+    }
+}
+
+/// Generates an ephemeral encapsulation keypair for a request from `sender`,
+/// in the scheme of the sender's own encryption key if it has one.
+pub(crate) fn ephemeral_keypair(
+    sender: &XIDDocument,
+) -> (EncapsulationPrivateKey, EncapsulationPublicKey) {
+    sender
+        .encryption_key()
+        .map_or_else(EncapsulationScheme::default, |key| {
+            key.encapsulation_scheme()
+        })
+        .keypair()
+}
+
+/// Checks that `sender` has a key for continuations to be encapsulated to,
+/// unless `options` seal them with a symmetric key.
+pub(crate) fn check_continuation_key(
+    options: &SealOptions,
+    sender: &XIDDocument,
+) -> Result<()> {
+    if let Some(ContinuationSealer::Symmetric(_)) =
+        options.continuation_sealer()
+    {
+        return Ok(());
+    }
+    continuation_key(options, sender).map(|_| ())
+}
+
+/// Returns the peer that continuations we issue are bound to, if `options`
+/// ask for it: the one recipient of the message.
+pub(crate) fn bound_sender(
+    options: &SealOptions,
+    recipients: &[&XIDDocument],
+) -> Result<Option<XID>> {
+    if !options.sender_binding() {
+        return Ok(None);
+    }
+    match recipients {
+        [recipient] => Ok(Some(recipient.xid())),
+        _ => Err(Error::AmbiguousSenderBinding {
+            recipients: recipients.len(),
+        }),
+    }
+}
+
+/// Returns the sender's XID document as embedded in a message, eliding what
+/// `options` do not disclose.
+pub(crate) fn sender_envelope(
+    sender: &XIDDocument,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    let document = sender
+        .to_envelope(
+            XIDPrivateKeyOptions::default(),
+            XIDGeneratorOptions::default(),
+            XIDSigningOptions::default(),
+        )
+        .map_err(Error::SenderDocumentEncoding)?;
+    let disclosed: &[KnownValue] = match options.sender_disclosure() {
+        SenderDisclosure::Full => return Ok(document),
+        SenderDisclosure::VerificationOnly => &[],
+        SenderDisclosure::Custom(predicates) => predicates,
+    };
+    let verification_key = sender.verification_key();
+    let mut elided = HashSet::new();
+    for assertion in document.assertions() {
+        let predicate = assertion
+            .try_predicate()
+            .ok()
+            .and_then(|predicate| predicate.try_known_value().ok().cloned());
+        if predicate == Some(known_values::KEY)
+            && is_key_for(&assertion, verification_key)
+        {
+            if disclosed.is_empty() {
+                elided.extend(
+                    assertion
+                        .try_object()
+                        .map(|key| key.assertions())
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|assertion| assertion.digest()),
+                );
+            }
+        } else if !predicate
+            .is_some_and(|predicate| disclosed.contains(&predicate))
+        {
+            elided.insert(assertion.digest());
+        }
+    }
+    Ok(document.elide_removing_set(&elided))
+}
+
+/// Returns `true` if a `key` assertion of a XID document carries
+/// `verification_key`.
+fn is_key_for(
+    assertion: &Envelope,
+    verification_key: Option<&SigningPublicKey>,
+) -> bool {
+    assertion
+        .try_object()
+        .ok()
+        .and_then(|key| key.subject().try_leaf().ok())
+        .and_then(|leaf| PublicKeys::try_from(leaf).ok())
+        .is_some_and(|keys| Some(keys.signing_public_key()) == verification_key)
+}
+
+/// Encrypts a continuation that `sender` issues as `options` ask, adding its
+/// expiry hint in the clear if they ask for one, and counts it in the
+/// continuation metrics under `function`. If `options` keep state by
+/// reference, the state is put in their store first, and if they compress
+/// state, it is compressed.
+pub(crate) fn issue_continuation(
+    continuation: &Continuation,
+    sender: &XIDDocument,
+    id: ARID,
+    function: Option<&Function>,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    if let Some(metrics) = options.continuation_metrics() {
+        let issued_at = continuation
+            .issued_at()
+            .unwrap_or_else(|| options.sealing_date());
+        let lifetime = continuation.valid_until().map(|valid_until| {
+            Duration::from_secs_f64(
+                (valid_until.timestamp() - issued_at.timestamp()).max(0.0),
+            )
+        });
+        metrics.record_issued(function, lifetime);
+    }
+    let mut continuation = continuation
+        .clone()
+        .with_state_compression(options.state_compression());
+    if let ContinuationMode::Reference(store) = options.continuation_mode() {
+        let state = state_store::store_state(
+            store.as_ref(),
+            continuation.state().clone(),
+        );
+        continuation = continuation.with_state(state);
+    }
+    let envelope = match (options.state_epochs(), options.continuation_sealer())
+    {
+        (Some(epochs), _) => epochs.encrypt(continuation.to_envelope(None), id),
+        (None, Some(ContinuationSealer::Symmetric(key))) => {
+            continuation.to_envelope_with_symmetric_key(key)
+        }
+        (None, _) => {
+            continuation.to_envelope(Some(continuation_key(options, sender)?))
+        }
+    };
+    Ok(match continuation.valid_until() {
+        Some(valid_until) if options.continuation_expiry_hints() => envelope
+            .add_assertion(inspect::CONTINUATION_EXPIRY_HINT, valid_until),
+        _ => envelope,
+    })
+}
+
+/// Encrypts a signed message envelope to zero or more recipients.
+///
+/// With no recipients the signed envelope is returned unchanged. Otherwise the
+/// signed envelope is wrapped, optionally compressed, and its subject
+/// encrypted with the session key if an unexpired session is configured, or
+/// else to every recipient. `valid_until` is added in the clear if `options`
+/// ask for transport expiry hints.
+pub(crate) fn encrypt_to_recipients(
+    signed: Envelope,
+    recipients: &[&XIDDocument],
+    valid_until: Option<Date>,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    crate::register();
+    if recipients.is_empty() {
+        return Ok(signed);
+    }
+
+    let recipient_keys = recipients
+        .iter()
+        .map(|recipient| {
+            recipient
+                .encryption_key()
+                .ok_or_else(|| {
+                    Error::RecipientMissingEncryptionKey(recipient.xid())
+                })
+                .map(|key| key as &dyn Encrypter)
+        })
+        .collect::<Result<Vec<&dyn Encrypter>>>()?;
+    encrypt_to_keys(signed, &recipient_keys, valid_until, options)
+}
+
+/// Encrypts a signed message envelope like [`encrypt_to_recipients`], to
+/// recipient keys rather than documents.
+pub(crate) fn encrypt_to_keys(
+    signed: Envelope,
+    recipient_keys: &[&dyn Encrypter],
+    valid_until: Option<Date>,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    crate::register();
+    if recipient_keys.is_empty() {
+        return Ok(signed);
+    }
+
+    if let Some(session) = options.session()
+        && !session.is_expired(options.now().unwrap_or_else(Date::now))
+    {
+        return encrypt_with_session(signed, session, valid_until, options);
+    }
+
+    let mut encrypted = wrap_payload(signed, options)?
+        .encrypt_subject_to_recipients(recipient_keys)?;
+    if options.recipient_hints() {
+        for key in recipient_keys {
+            encrypted = encrypted.add_assertion(
+                inspect::RECIPIENT_KEY,
+                key.encapsulation_public_key().reference(),
+            );
+        }
+    }
+    Ok(add_transport_expiry_hint(encrypted, valid_until, options))
+}
+
+/// Encrypts a signed message envelope like [`encrypt_to_recipients`], with
+/// a key derived from `password` rather than to recipients.
+pub(crate) fn lock_with_password(
+    signed: Envelope,
+    password: &[u8],
+    valid_until: Option<Date>,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    crate::register();
+    Ok(add_transport_expiry_hint(
+        wrap_payload(signed, options)?
+            .lock_subject(options.password_derivation(), password)?,
+        valid_until,
+        options,
+    ))
+}
+
+/// Decrypts a message sealed with a password, returning the signed envelope
+/// inside and how it was sealed, or [`Error::PasswordDecryptionFailed`] if
+/// `password` is not the one it was sealed with.
+pub(crate) fn unlock_with_password(
+    encrypted_envelope: &Envelope,
+    password: &[u8],
+) -> Result<(Envelope, SealingReport)> {
+    crate::register();
+    let payload = encrypted_envelope.unlock_subject(password).map_err(
+        |error| match error {
+            bc_envelope::Error::UnknownSecret => {
+                Error::PasswordDecryptionFailed
+            }
+            error => error.into(),
+        },
+    )?;
+    unwrap_payload(payload)
+}
+
+/// Encrypts a signed message envelope like [`encrypt_to_recipients`], with
+/// the key of `session` rather than to recipients.
+pub(crate) fn encrypt_with_session(
+    signed: Envelope,
+    session: &SessionKeys,
+    valid_until: Option<Date>,
+    options: &SealOptions,
+) -> Result<Envelope> {
+    crate::register();
+    Ok(add_transport_expiry_hint(
+        wrap_payload(signed, options)?
+            .encrypt_subject(session.key())?
+            .add_assertion(inspect::SESSION_ID, session.id()),
+        valid_until,
+        options,
+    ))
+}
+
+/// Wraps a signed message envelope, compressing it if `options` ask for
+/// payload compression and it gets smaller.
+fn wrap_payload(signed: Envelope, options: &SealOptions) -> Result<Envelope> {
+    let payload = signed.wrap();
+    if options.compression() == CompressionPolicy::Payload {
+        let compressed = payload.compress()?;
+        if compressed.to_cbor_data().len() < payload.to_cbor_data().len() {
+            return Ok(compressed);
+        }
+    }
+    Ok(payload)
+}
+
+/// Recovers the content key of a message encrypted to recipients from the
+/// slot `holder` can open, and adds a slot for each of `new_recipients`,
+/// leaving the encrypted payload untouched.
+///
+/// Recipient hints are added for the new recipients if the message carries
+/// any.
+pub(crate) fn add_recipients(
+    sealed: &Envelope,
+    holder: &PrivateKeys,
+    new_recipients: &[&XIDDocument],
+) -> Result<Envelope> {
+    crate::register();
+    let content_key = sealed
+        .recipients()?
+        .iter()
+        .find_map(|slot| slot.decrypt(holder).ok())
+        .ok_or(Error::NoRecipientSlot)?;
+    let content_key = SymmetricKey::from_tagged_cbor_data(content_key)?;
+    // A slot could have been added with another key by anyone holding the
+    // recipient's public key, so check it opens the payload.
+    sealed.decrypt_subject(&content_key)?;
+
+    let hinted = !inspect::recipient_key_fingerprints(sealed)?.is_empty();
+    let mut result = sealed.clone();
+    for recipient in new_recipients {
+        let key = recipient.encryption_key().ok_or_else(|| {
+            Error::RecipientMissingEncryptionKey(recipient.xid())
+        })?;
+        result = result.add_recipient(key, &content_key);
+        if hinted {
+            result = result.add_assertion(
+                inspect::RECIPIENT_KEY,
+                key.encapsulation_public_key().reference(),
+            );
+        }
+    }
+    Ok(result)
+}
+
+fn add_transport_expiry_hint(
+    encrypted: Envelope,
+    valid_until: Option<Date>,
+    options: &SealOptions,
+) -> Envelope {
+    match valid_until {
+        Some(valid_until) if options.transport_expiry_hints() => {
+            encrypted.add_assertion(inspect::TRANSPORT_EXPIRY_HINT, valid_until)
+        }
+        _ => encrypted,
+    }
+}
+
+/// The bytes a signature adds to a message, allowing for the larger
+/// classical signature schemes.
+const SIGNATURE_OVERHEAD: usize = 160;
+
+/// The bytes that wrapping and encrypting a message adds once.
+const ENCRYPTION_OVERHEAD: usize = 128;
+
+/// The bytes each recipient adds to an encrypted message, allowing for
+/// X25519 key encapsulation.
+const RECIPIENT_OVERHEAD: usize = 192;
+
+/// Estimates the size of `message` once it is signed and encrypted to
+/// `recipients` recipients, without doing either.
+pub(crate) fn estimate_sealed_size(
+    message: &Envelope,
+    recipients: usize,
+) -> usize {
+    message.to_cbor_data().len()
+        + SIGNATURE_OVERHEAD
+        + ENCRYPTION_OVERHEAD
+        + recipients * RECIPIENT_OVERHEAD
+}
+
+/// Decrypts a sealed message envelope, returning the signed envelope inside
+/// and how it was sealed.
+///
+/// Payloads compressed by [`encrypt_to_recipients`] are decompressed
+/// transparently.
+pub(crate) fn decrypt_to_recipient(
+    encrypted_envelope: &Envelope,
+    recipient: &dyn Decrypter,
+) -> Result<(Envelope, SealingReport)> {
+    crate::register();
+    let payload = encrypted_envelope.decrypt_subject_to_recipient(recipient)?;
+    unwrap_payload(payload)
+}
+
+/// Returns the key a signature by `sender` must verify against for the XID
+/// it claims to be authenticated: its inception signing key, from which the
+/// XID is derived, or failing that the key recorded for the XID in `tofu`,
+/// such as one blessed after a rotation.
+///
+/// Any other key in the document proves nothing, since anyone can write a
+/// document naming someone else's XID alongside a key of their own. Fails
+/// with [`Error::SenderMissingVerificationKey`] if the document has no keys,
+/// with [`Error::SenderKeyChanged`] if none of them is the key recorded in
+/// `tofu`, and with [`Error::SenderKeyNotBound`] otherwise.
+pub(crate) fn sender_verification_key<'a>(
+    sender: &'a XIDDocument,
+    tofu: Option<&dyn TofuStore>,
+) -> Result<&'a SigningPublicKey> {
+    if let Some(key) = sender.inception_signing_key() {
+        return Ok(key);
+    }
+    if sender.keys().is_empty() {
+        return Err(Error::SenderMissingVerificationKey);
+    }
+    let xid = sender.xid();
+    if let Some(store) = tofu
+        && let Some(recorded) = store.lookup(xid)?
+    {
+        return sender
+            .keys()
+            .iter()
+            .map(|key| key.public_keys().signing_public_key())
+            .find(|key| **key == recorded)
+            .ok_or(Error::SenderKeyChanged { xid });
+    }
+    Err(Error::SenderKeyNotBound(xid))
+}
+
+/// Reads the sender a request claims, without verifying it, so that an
+/// early failure can be encrypted to it.
+#[cfg(feature = "axum")]
+pub(crate) fn claimed_sender(
+    envelope: &Envelope,
+    private_keys: &PrivateKeys,
+) -> Option<XIDDocument> {
+    let message = decrypt_to_recipient(envelope, private_keys)
+        .ok()?
+        .0
+        .try_unwrap()
+        .ok()?;
+    let sender = message.object_for_predicate(known_values::SENDER).ok()?;
+    XIDDocument::try_from(sender).ok()
+}
+
+/// Decrypts a message sealed with a session key, returning the signed
+/// envelope inside and how it was sealed.
+pub(crate) fn decrypt_with_session(
+    encrypted_envelope: &Envelope,
+    session: &SessionKeys,
+) -> Result<(Envelope, SealingReport)> {
+    crate::register();
+    let payload = encrypted_envelope
+        .subject()
+        .decrypt_subject(session.key())?;
+    unwrap_payload(payload)
+}
+
+/// Unwraps the signed envelope from a decrypted payload, decompressing it if
+/// it was compressed.
+///
+/// The sender's key and signature are both inside the compressed payload, so
+/// it must be decompressed before either can be checked; it is the cap of
+/// [`consts::MAX_DECOMPRESSED_MESSAGE_SIZE`] that bounds what an
+/// unauthenticated payload can make us allocate.
+fn unwrap_payload(payload: Envelope) -> Result<(Envelope, SealingReport)> {
+    let subject = payload.subject();
+    let compressed = subject.is_compressed();
+    let signed = if compressed {
+        decompress(&subject, consts::MAX_DECOMPRESSED_MESSAGE_SIZE)?
+            .try_unwrap()?
+    } else {
+        payload.try_unwrap()?
+    };
+    Ok((signed, SealingReport { compressed }))
+}
+
+/// Decompresses a compressed envelope, producing at most `limit` bytes.
+///
+/// The size the envelope declares is checked against `limit` before
+/// anything is decompressed, failing with [`Error::DecompressedTooLarge`],
+/// and the data is then inflated into at most that many bytes, so data
+/// expanding beyond its declared size fails as corrupt rather than
+/// exhausting memory.
+pub(crate) fn decompress(
+    envelope: &Envelope,
+    limit: usize,
+) -> Result<Envelope> {
+    let EnvelopeCase::Compressed(compressed) = envelope.case() else {
+        return Err(bc_envelope::Error::NotCompressed.into());
+    };
+    let digest = compressed
+        .digest_opt()
+        .ok_or(bc_envelope::Error::MissingDigest)?;
+    // A checksum, the decompressed size, and the data, as validated when the
+    // envelope was decoded.
+    let fields = compressed.untagged_cbor().try_into_array()?;
+    let checksum: u32 = fields[0].clone().try_into()?;
+    let size: usize = fields[1].clone().try_into()?;
+    if size > limit {
+        return Err(Error::DecompressedTooLarge { size, limit });
+    }
+    let data = fields[2].clone().try_into_byte_string()?;
+    // Data that would not shrink is stored as is.
+    let data = if data.len() >= size {
+        data
+    } else {
+        miniz_oxide::inflate::decompress_to_vec_with_limit(&data, size)
+            .map_err(|_| {
+                bc_components::Error::compression("corrupt compressed data")
+            })?
+    };
+    if data.len() != size || crc32(&data) != checksum {
+        return Err(bc_components::Error::compression(
+            "compressed data checksum mismatch",
+        )
+        .into());
+    }
+    let decompressed = Envelope::from_tagged_cbor_data(data)?;
+    if decompressed.digest() != digest {
+        return Err(bc_envelope::Error::InvalidDigest.into());
+    }
+    Ok(decompressed)
+}
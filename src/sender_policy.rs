@@ -0,0 +1,66 @@
+use std::{collections::HashSet, sync::Arc};
+
+use bc_components::{XID, XIDProvider};
+use bc_xid::XIDDocument;
+
+type SenderPredicate = Arc<dyn Fn(&XIDDocument) -> bool + Send + Sync>;
+
+/// Decides whose messages are accepted, by their authenticated sender.
+///
+/// The policy is consulted once the sender's signature has been verified
+/// with the key its XID is bound to, so that the XID it matches on is
+/// authenticated, and before any continuation is decrypted. A sender it
+/// refuses fails parsing with
+/// [`Error::SenderNotAuthorized`](crate::Error::SenderNotAuthorized).
+#[derive(Clone, Default)]
+pub enum SenderPolicy {
+    /// Accepts every sender.
+    #[default]
+    AcceptAll,
+    /// Accepts only the listed senders.
+    Allow(HashSet<XID>),
+    /// Accepts every sender but those listed.
+    Deny(HashSet<XID>),
+    /// Accepts the senders for which the predicate returns `true`.
+    Callback(SenderPredicate),
+}
+
+impl std::fmt::Debug for SenderPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AcceptAll => f.write_str("AcceptAll"),
+            Self::Allow(xids) => f.debug_tuple("Allow").field(xids).finish(),
+            Self::Deny(xids) => f.debug_tuple("Deny").field(xids).finish(),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+impl SenderPolicy {
+    pub fn allow(senders: impl IntoIterator<Item = XID>) -> Self {
+        Self::Allow(senders.into_iter().collect())
+    }
+
+    pub fn deny(senders: impl IntoIterator<Item = XID>) -> Self {
+        Self::Deny(senders.into_iter().collect())
+    }
+
+    pub fn callback(
+        predicate: impl Fn(&XIDDocument) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self::Callback(Arc::new(predicate))
+    }
+
+    /// Whether the policy accepts messages from `sender`.
+    ///
+    /// The XID of `sender` is taken as given, so a document not yet
+    /// authenticated by parsing a message it signed must not be passed here.
+    pub fn permits(&self, sender: &XIDDocument) -> bool {
+        match self {
+            Self::AcceptAll => true,
+            Self::Allow(xids) => xids.contains(&sender.xid()),
+            Self::Deny(xids) => !xids.contains(&sender.xid()),
+            Self::Callback(predicate) => predicate(sender),
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! An adapter that mounts a GSTP request handler in an in-process service
+//! stack.
+//!
+//! [`Service`] is a crate-local equivalent of `tower::Service`: a caller
+//! waits for [`Service::poll_ready`] before each [`Service::call`], which is
+//! how [`GstpService`] applies backpressure once its concurrency limit is
+//! reached.
+//!
+//! A service is torn down in three steps: [`GstpService::begin_shutdown`]
+//! stops handing requests to the handler, [`GstpService::drain`] waits for
+//! the requests already handed to it, and [`GstpService::close`] releases the
+//! private keys.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bc_components::{PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    Error, LoadShedDecision, LoadShedPolicy, ParseOptions, PartialParse,
+    Result, SealOptions, SealedRequest, SealedRequestBehavior, SealedResponse,
+    SealedResponseBehavior, Strictness, overloaded_response,
+    shutting_down_response, strictness, transport::GstpTransport,
+};
+
+/// The reason given for refusing a request that could not be parsed, where
+/// the parse error is not disclosed.
+const REFUSED: &str = "request refused";
+
+/// A future that can be sent between threads.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// An asynchronous function from a request to a response.
+pub trait Service<Request> {
+    type Response;
+    type Error;
+    type Future: Future<
+        Output = std::result::Result<Self::Response, Self::Error>,
+    >;
+
+    /// Returns `Ready` once the service can accept a call.
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::result::Result<(), Self::Error>>;
+
+    /// Processes a request. Must only be called after `poll_ready` returned
+    /// `Ready`.
+    fn call(&mut self, request: Request) -> Self::Future;
+}
+
+/// The stage of a [`GstpService`]'s lifecycle, shared by all its clones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceStatus {
+    Running,
+    /// Requests are answered with a
+    /// [`shutting_down_response`](crate::shutting_down_response) rather
+    /// than handled, and requests already handed to the handler complete.
+    ShuttingDown,
+    /// The private keys have been released, and every call fails with
+    /// [`Error::ServiceClosed`].
+    Closed,
+}
+
+#[derive(Debug)]
+struct Lifecycle {
+    status: ServiceStatus,
+    retry_after: Duration,
+    private_keys: Option<PrivateKeys>,
+}
+
+type Handler = Arc<
+    dyn Fn(SealedRequest) -> BoxFuture<Result<SealedResponse>> + Send + Sync,
+>;
+
+/// A [`Service`] from incoming to outgoing envelopes that parses each
+/// request, dispatches it to a handler, and seals the handler's response to
+/// the sender of the request.
+///
+/// Errors are answered rather than returned: a request that cannot be parsed
+/// is answered with an early failure, and a handler error with a failure
+/// response to the request. An early failure is encrypted only to a sender
+/// whose signature was verified. Under [`Strictness::Production`] it gives a
+/// fixed reason rather than the parse error, and a request whose sender was
+/// not verified is dropped, failing with the parse error. Otherwise the
+/// service fails only if a response cannot be sealed.
+///
+/// Clones share the concurrency limit and the lifecycle, and each reserves
+/// its own slot when it is polled ready.
+pub struct GstpService {
+    identity: XIDDocument,
+    lifecycle: Arc<Mutex<Lifecycle>>,
+    parse_options: ParseOptions,
+    seal_options: SealOptions,
+    handler: Handler,
+    load_shedding: Option<Arc<LoadShedPolicy>>,
+    concurrency_limit: usize,
+    semaphore: Arc<Semaphore>,
+    permit: Option<OwnedSemaphorePermit>,
+    acquiring: Option<BoxFuture<OwnedSemaphorePermit>>,
+}
+
+impl Clone for GstpService {
+    fn clone(&self) -> Self {
+        Self {
+            identity: self.identity.clone(),
+            lifecycle: self.lifecycle.clone(),
+            parse_options: self.parse_options.clone(),
+            seal_options: self.seal_options.clone(),
+            handler: self.handler.clone(),
+            load_shedding: self.load_shedding.clone(),
+            concurrency_limit: self.concurrency_limit,
+            semaphore: self.semaphore.clone(),
+            permit: None,
+            acquiring: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for GstpService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GstpService")
+            .field("identity", &self.identity.xid())
+            .field("status", &self.status())
+            .field("available", &self.semaphore.available_permits())
+            .finish_non_exhaustive()
+    }
+}
+
+impl GstpService {
+    /// Creates a service answering as `identity`, which handles at most
+    /// `concurrency_limit` requests at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `concurrency_limit` is zero, since such a service would
+    /// never become ready, or does not fit in a `u32`, since the service
+    /// could not then be drained.
+    pub fn new<F, Fut>(
+        identity: &XIDDocument,
+        private_keys: &PrivateKeys,
+        concurrency_limit: usize,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(SealedRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<SealedResponse>> + Send + 'static,
+    {
+        assert!(
+            u32::try_from(concurrency_limit).is_ok_and(|limit| limit > 0),
+            "the concurrency limit must be from 1 to u32::MAX"
+        );
+        Self {
+            identity: identity.clone(),
+            lifecycle: Arc::new(Mutex::new(Lifecycle {
+                status: ServiceStatus::Running,
+                retry_after: Duration::ZERO,
+                private_keys: Some(private_keys.clone()),
+            })),
+            parse_options: ParseOptions::default(),
+            seal_options: SealOptions::default(),
+            handler: Arc::new(move |request| Box::pin(handler(request))),
+            load_shedding: None,
+            concurrency_limit,
+            semaphore: Arc::new(Semaphore::new(concurrency_limit)),
+            permit: None,
+            acquiring: None,
+        }
+    }
+
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    pub fn with_seal_options(mut self, options: SealOptions) -> Self {
+        self.seal_options = options;
+        self
+    }
+
+    /// Sheds requests according to `policy` before they reach the handler,
+    /// answering them with an
+    /// [`overloaded_response`](crate::overloaded_response).
+    pub fn with_load_shedding(mut self, policy: Arc<LoadShedPolicy>) -> Self {
+        self.load_shedding = Some(policy);
+        self
+    }
+
+    /// Waits until the service can accept a call.
+    pub async fn ready(&mut self) -> Result<()> {
+        std::future::poll_fn(|cx| self.poll_ready(cx)).await
+    }
+
+    pub fn status(&self) -> ServiceStatus {
+        self.lifecycle.lock().unwrap().status
+    }
+
+    /// Stops handing requests to the handler. Until the service is closed,
+    /// each request is answered with a
+    /// [`shutting_down_response`](crate::shutting_down_response) asking the
+    /// client to retry after `grace`, which hands back the state the request
+    /// returned to us in a fresh continuation so that the client does not
+    /// lose it.
+    pub fn begin_shutdown(&self, grace: Duration) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.status == ServiceStatus::Running {
+            lifecycle.status = ServiceStatus::ShuttingDown;
+            lifecycle.retry_after = grace;
+        }
+    }
+
+    /// Returns a future that resolves once no call is in flight.
+    ///
+    /// A clone that has been polled ready holds a slot until it is called
+    /// or dropped, so the future does not resolve while one is held.
+    pub fn drain(&self) -> impl Future<Output = ()> + Send + 'static {
+        let semaphore = self.semaphore.clone();
+        // The limit was checked to fit when the service was created.
+        let limit = u32::try_from(self.concurrency_limit)
+            .expect("concurrency limit fits in a u32");
+        async move {
+            // The semaphore is never closed.
+            drop(semaphore.acquire_many_owned(limit).await.unwrap());
+        }
+    }
+
+    /// Closes the service, dropping its handles to the private keys.
+    /// Calls in flight keep their own handles until they complete, so drain
+    /// the service first.
+    pub fn close(&self) {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        lifecycle.status = ServiceStatus::Closed;
+        lifecycle.private_keys = None;
+    }
+
+    async fn process(
+        self,
+        private_keys: PrivateKeys,
+        shutting_down: Option<Duration>,
+        envelope: Envelope,
+    ) -> Result<Envelope> {
+        let mut partial = PartialParse::new(&envelope);
+        let request = match SealedRequest::try_from_envelope_recording(
+            &envelope,
+            &self.parse_options,
+            &private_keys,
+            &mut partial,
+        ) {
+            Ok(request) => request,
+            Err(error) => {
+                return self.refuse(&private_keys, partial, error);
+            }
+        };
+        let id = request.id();
+        let sender = request.sender().clone();
+        let peer_continuation = request.peer_continuation().cloned();
+        // Every response is bound to the request envelope it answers, and
+        // encrypted to its ephemeral key if it carries one.
+        let in_response_to = request.envelope_digest();
+        let reply_key = request.ephemeral_key().cloned();
+        if let Some(retry_after) = shutting_down {
+            return shutting_down_response(
+                &request,
+                &self.identity,
+                retry_after,
+                &self.seal_options,
+            )?
+            .with_optional_peer_continuation(peer_continuation.clone())
+            .with_optional_in_response_to(in_response_to)
+            .with_optional_reply_key(reply_key)
+            .to_envelope_with_options(
+                None,
+                Some(&private_keys),
+                &[&sender],
+                &self.seal_options,
+            );
+        }
+        let decision = self
+            .load_shedding
+            .as_ref()
+            .map_or(LoadShedDecision::Admit, |policy| {
+                policy.evaluate(&request)
+            });
+        let response = if let LoadShedDecision::Shed { retry_after } = decision
+        {
+            Ok(overloaded_response(id, &self.identity, retry_after)
+                .with_optional_peer_continuation(peer_continuation.clone()))
+        } else {
+            (self.handler)(request).await
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(error) => SealedResponse::new_failure(id, &self.identity)
+                .with_error(error.to_string())
+                .with_optional_peer_continuation(peer_continuation.clone()),
+        };
+        let in_response_to = response.in_response_to().or(in_response_to);
+        let reply_key = response.reply_key().cloned().or(reply_key);
+        response
+            .with_optional_in_response_to(in_response_to)
+            .with_optional_reply_key(reply_key)
+            .to_envelope_with_options(
+                None,
+                Some(&private_keys),
+                &[&sender],
+                &self.seal_options,
+            )
+    }
+
+    /// Answers a request that could not be parsed with an early failure, or
+    /// drops it by failing with `error`, as described on [`GstpService`].
+    ///
+    /// Whatever the request claims before its signature is verified, its
+    /// sender included, is chosen by whoever sent it, so nothing is
+    /// encrypted to that sender and the failure does not echo the error.
+    fn refuse(
+        &self,
+        private_keys: &PrivateKeys,
+        partial: PartialParse,
+        error: Error,
+    ) -> Result<Envelope> {
+        let production = strictness() == Strictness::Production;
+        let failure = SealedResponse::new_early_failure(&self.identity);
+        match partial.verified_sender {
+            Some(sender) => failure
+                .with_error(if production {
+                    REFUSED.to_string()
+                } else {
+                    error.to_string()
+                })
+                .to_envelope_with_options(
+                    None,
+                    Some(private_keys),
+                    &[&sender],
+                    &self.seal_options,
+                ),
+            None if production => Err(error),
+            None => failure
+                .with_error(REFUSED)
+                .to_envelope_unprotected_i_know_what_i_am_doing(
+                    None,
+                    Some(private_keys),
+                    &[],
+                    &self.seal_options,
+                ),
+        }
+    }
+}
+
+impl Service<Envelope> for GstpService {
+    type Response = Envelope;
+    type Error = crate::Error;
+    type Future = BoxFuture<Result<Envelope>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.status() == ServiceStatus::Closed {
+            return Poll::Ready(Err(Error::ServiceClosed));
+        }
+        if self.permit.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        let acquiring = self.acquiring.get_or_insert_with(|| {
+            let semaphore = self.semaphore.clone();
+            Box::pin(async move {
+                // The semaphore is never closed.
+                semaphore.acquire_owned().await.unwrap()
+            })
+        });
+        match acquiring.as_mut().poll(cx) {
+            Poll::Ready(permit) => {
+                self.acquiring = None;
+                self.permit = Some(permit);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn call(&mut self, envelope: Envelope) -> Self::Future {
+        let permit = self
+            .permit
+            .take()
+            .expect("poll_ready must return Ready before call");
+        let (private_keys, shutting_down) = {
+            let lifecycle = self.lifecycle.lock().unwrap();
+            let shutting_down = (lifecycle.status
+                == ServiceStatus::ShuttingDown)
+                .then_some(lifecycle.retry_after);
+            (lifecycle.private_keys.clone(), shutting_down)
+        };
+        let Some(private_keys) = private_keys else {
+            return Box::pin(async { Err(Error::ServiceClosed) });
+        };
+        let future =
+            self.clone().process(private_keys, shutting_down, envelope);
+        Box::pin(async move {
+            let result = future.await;
+            drop(permit);
+            result
+        })
+    }
+}
+
+/// Exchanges requests with the service in process, as a loopback peer.
+impl GstpTransport for GstpService {
+    async fn exchange(&mut self, request: Envelope) -> Result<Envelope> {
+        self.ready().await?;
+        self.call(request).await
+    }
+}
@@ -0,0 +1,366 @@
+use bc_components::{ARID, DigestProvider, PrivateKeys, SymmetricKey};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, ParseOptions, Result, SealOptions, SealedRequest, SealedResponse,
+    SealingReport, inspect, sealing,
+};
+
+pub(crate) const SESSION_PROPOSAL: &str = "sessionProposal";
+pub(crate) const SESSION_ACK: &str = "sessionAck";
+const KEY: &str = "key";
+const EXPIRES: &str = "expires";
+const COUNTER: &str = "sessionCounter";
+const MAC: &str = "sessionMac";
+const MAC_KEY_SALT: &[u8] = b"gstp-session-mac";
+
+/// A symmetric key shared by two peers, letting them seal the messages of a
+/// conversation without a key encapsulation per message.
+///
+/// One peer proposes the session inside an encrypted message, with
+/// [`SealedRequest::with_session_proposal`](crate::SealedRequest::with_session_proposal),
+/// and the other acknowledges it in its answer, with
+/// [`SealedResponse::with_session_ack`](crate::SealedResponse::with_session_ack).
+/// Once acknowledged, both sides seal with
+/// [`SealOptions::with_session`](crate::SealOptions::with_session) and parse
+/// with [`ParseOptions::with_session`](crate::ParseOptions::with_session)
+/// until the session expires.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionKeys {
+    id: ARID,
+    key: SymmetricKey,
+    expires: Date,
+}
+
+impl SessionKeys {
+    /// Generates a new session that expires at `expires`.
+    pub fn new(expires: Date) -> Self {
+        Self {
+            id: ARID::new(),
+            key: SymmetricKey::new(),
+            expires,
+        }
+    }
+
+    /// Identifies the session, in the clear, on every message sealed with it.
+    pub fn id(&self) -> ARID { self.id }
+
+    pub fn key(&self) -> &SymmetricKey { &self.key }
+
+    pub fn expires(&self) -> Date { self.expires }
+
+    pub fn is_expired(&self, now: Date) -> bool { self.expires <= now }
+
+    /// Returns `true` if `ack`, the session acknowledged by a peer, is this
+    /// session.
+    pub fn is_acknowledged_by(&self, ack: Option<ARID>) -> bool {
+        ack == Some(self.id)
+    }
+
+    fn to_envelope(&self) -> Envelope {
+        Envelope::new(self.id)
+            .add_assertion(KEY, self.key.to_cbor())
+            .add_assertion(EXPIRES, self.expires)
+    }
+
+    fn try_from_envelope(envelope: &Envelope) -> Result<Self> {
+        Ok(Self {
+            id: envelope.extract_subject()?,
+            key: SymmetricKey::from_tagged_cbor(
+                envelope.object_for_predicate(KEY)?.try_leaf()?,
+            )?,
+            expires: envelope.extract_object_for_predicate(EXPIRES)?,
+        })
+    }
+}
+
+/// The session proposal and acknowledgment carried inside a message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SessionAssertions {
+    pub proposal: Option<SessionKeys>,
+    pub ack: Option<ARID>,
+}
+
+impl SessionAssertions {
+    pub fn add_to(&self, message: Envelope) -> Envelope {
+        message
+            .add_optional_assertion(
+                SESSION_PROPOSAL,
+                self.proposal.as_ref().map(SessionKeys::to_envelope),
+            )
+            .add_optional_assertion(SESSION_ACK, self.ack)
+    }
+
+    /// Fails with [`Error::SessionProposalNotEncrypted`] if a message carrying
+    /// a proposal would be sent without being encrypted.
+    pub fn check_encrypted(&self, encrypted: bool) -> Result<()> {
+        if self.proposal.is_some() && !encrypted {
+            return Err(Error::SessionProposalNotEncrypted);
+        }
+        Ok(())
+    }
+
+    pub fn try_from_message(message: &Envelope) -> Result<Self> {
+        Ok(Self {
+            proposal: message
+                .optional_object_for_predicate(SESSION_PROPOSAL)?
+                .map(|proposal| SessionKeys::try_from_envelope(&proposal))
+                .transpose()?,
+            ack: message.extract_optional_object_for_predicate(SESSION_ACK)?,
+        })
+    }
+}
+
+/// Fails with [`Error::SessionProposalNotEncrypted`] if a message received
+/// without encryption proposes a session, whose key has then crossed the wire
+/// in the clear.
+pub(crate) fn check_not_proposed_in_clear(
+    signed_envelope: &Envelope,
+) -> Result<()> {
+    let proposed = signed_envelope.try_unwrap().is_ok_and(|message| {
+        !message
+            .assertions_with_predicate(SESSION_PROPOSAL)
+            .is_empty()
+    });
+    if proposed {
+        return Err(Error::SessionProposalNotEncrypted);
+    }
+    Ok(())
+}
+
+/// A conversation with one peer over an established [`SessionKeys`], in which
+/// messages are authenticated by a code computed with the session key rather
+/// than signed, and encrypted with the session key rather than to the peer's
+/// public keys.
+///
+/// The session is established by an exchange of signed and encrypted
+/// messages, which proposes and acknowledges the keys. Messages sealed with
+/// the session are numbered, and each must follow the last one received, so
+/// a replayed or reordered message is rejected with [`Error::SessionReplay`].
+/// Continuations are issued and returned as in any other message.
+///
+/// A session refuses to seal once its keys expire, or once it has sealed the
+/// number of messages set by [`Self::with_message_limit`], until it is
+/// rekeyed by proposing new keys in a message sealed with it and calling
+/// [`Self::rekey`] once they are acknowledged.
+#[derive(Clone, Debug)]
+pub struct Session {
+    keys: SessionKeys,
+    mac_key: SymmetricKey,
+    peer: XIDDocument,
+    message_limit: Option<u64>,
+    sent: u64,
+    received: u64,
+}
+
+impl Session {
+    /// Creates a session with `peer` over the acknowledged `keys`.
+    pub fn new(keys: SessionKeys, peer: &XIDDocument) -> Self {
+        Self {
+            mac_key: derive_mac_key(&keys),
+            keys,
+            peer: peer.clone(),
+            message_limit: None,
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    /// Sets the number of messages the session seals before it must be
+    /// rekeyed.
+    pub fn with_message_limit(mut self, limit: u64) -> Self {
+        self.message_limit = Some(limit);
+        self
+    }
+
+    pub fn keys(&self) -> &SessionKeys { &self.keys }
+
+    pub fn peer(&self) -> &XIDDocument { &self.peer }
+
+    pub fn message_limit(&self) -> Option<u64> { self.message_limit }
+
+    /// The number of messages sealed since the session was last keyed.
+    pub fn sent(&self) -> u64 { self.sent }
+
+    /// The number of the last message received since the session was last
+    /// keyed.
+    pub fn received(&self) -> u64 { self.received }
+
+    /// Replaces the keys of the session with `keys`, proposed and
+    /// acknowledged in messages sealed with the current keys, and restarts
+    /// the message count.
+    pub fn rekey(&mut self, keys: SessionKeys) {
+        *self = Self {
+            message_limit: self.message_limit,
+            ..Self::new(keys, &self.peer)
+        };
+    }
+
+    /// Seals `request` with the session.
+    pub fn seal_request(
+        &mut self,
+        request: &SealedRequest,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.check_can_seal(options)?;
+        let message = request.compose(None, &[&self.peer], options)?.envelope;
+        self.seal(message, options)
+    }
+
+    /// Seals `response` with the session.
+    pub fn seal_response(
+        &mut self,
+        response: &SealedResponse,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        self.check_can_seal(options)?;
+        let (message, _, _) = response.compose(None, &[&self.peer], options)?;
+        self.seal(message, options)
+    }
+
+    /// Parses a request from the peer sealed with the session, checking it
+    /// against `options`.
+    pub fn open_request(
+        &mut self,
+        envelope: &Envelope,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<SealedRequest> {
+        let (authenticated, counter, report) = self.open(envelope, options)?;
+        let request = SealedRequest::try_from_session_envelope(
+            envelope,
+            &authenticated,
+            report,
+            &self.peer_options(options),
+            recipient,
+        )?;
+        self.received = counter;
+        Ok(request)
+    }
+
+    /// Parses a response from the peer sealed with the session, checking it
+    /// against `options`.
+    pub fn open_response(
+        &mut self,
+        envelope: &Envelope,
+        options: &ParseOptions,
+        recipient: &PrivateKeys,
+    ) -> Result<SealedResponse> {
+        let (authenticated, counter, report) = self.open(envelope, options)?;
+        let response = SealedResponse::try_from_session_envelope(
+            envelope,
+            &authenticated,
+            report,
+            &self.peer_options(options),
+            recipient,
+        )?;
+        self.received = counter;
+        Ok(response)
+    }
+
+    fn check_can_seal(&self, options: &SealOptions) -> Result<()> {
+        if self
+            .keys
+            .is_expired(options.now().unwrap_or_else(Date::now))
+        {
+            return Err(Error::SessionExpired(self.keys.id()));
+        }
+        match self.message_limit {
+            Some(limit) if self.sent >= limit => {
+                Err(Error::SessionLimitReached {
+                    id: self.keys.id(),
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Numbers and authenticates `message`, in the place a signature would
+    /// take, and encrypts it with the session key.
+    fn seal(
+        &mut self,
+        message: Envelope,
+        options: &SealOptions,
+    ) -> Result<Envelope> {
+        let counter = self.sent + 1;
+        let numbered = message.wrap().add_assertion(COUNTER, counter);
+        let mac = self.mac(&numbered);
+        let authenticated =
+            numbered.add_assertion(MAC, CBOR::to_byte_string(mac));
+        let sealed = sealing::encrypt_with_session(
+            authenticated,
+            &self.keys,
+            None,
+            options,
+        )?;
+        self.sent = counter;
+        Ok(sealed)
+    }
+
+    /// Decrypts `envelope` and checks its authentication code and number,
+    /// returning the authenticated envelope, its number, and how it was
+    /// sealed.
+    fn open(
+        &self,
+        envelope: &Envelope,
+        options: &ParseOptions,
+    ) -> Result<(Envelope, u64, SealingReport)> {
+        let id = self.keys.id();
+        if inspect::session_id(envelope)? != Some(id) {
+            return Err(Error::UnknownSession(id));
+        }
+        if self
+            .keys
+            .is_expired(options.now().unwrap_or_else(Date::now))
+        {
+            return Err(Error::SessionExpired(id));
+        }
+        let (authenticated, report) =
+            sealing::decrypt_with_session(envelope, &self.keys)?;
+        if authenticated.assertions().len() != 2 {
+            return Err(Error::SessionAuthenticationFailed);
+        }
+        let counter: u64 =
+            authenticated.extract_object_for_predicate(COUNTER)?;
+        let mac: ByteString =
+            authenticated.extract_object_for_predicate(MAC)?;
+        let numbered = authenticated.subject().add_assertion(COUNTER, counter);
+        if !constant_time_eq(&self.mac(&numbered), mac.data()) {
+            return Err(Error::SessionAuthenticationFailed);
+        }
+        if counter <= self.received {
+            return Err(Error::SessionReplay {
+                counter,
+                last: self.received,
+            });
+        }
+        Ok((authenticated, counter, report))
+    }
+
+    /// Messages sealed with the session must come from the peer, so that our
+    /// own messages cannot be reflected back to us.
+    fn peer_options(&self, options: &ParseOptions) -> ParseOptions {
+        options.clone().with_expected_sender(&self.peer)
+    }
+
+    fn mac(&self, numbered: &Envelope) -> Vec<u8> {
+        bc_crypto::hmac_sha256(self.mac_key.data(), numbered.digest().data())
+            .to_vec()
+    }
+}
+
+fn derive_mac_key(keys: &SessionKeys) -> SymmetricKey {
+    let key = bc_crypto::hkdf_hmac_sha256(
+        keys.key().data(),
+        MAC_KEY_SALT,
+        SymmetricKey::SYMMETRIC_KEY_SIZE,
+    );
+    SymmetricKey::from_data_ref(key).unwrap()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
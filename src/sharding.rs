@@ -0,0 +1,122 @@
+//! Splitting sealed messages into SSKR shares for distribution over several
+//! channels or custodians.
+//!
+//! [`split`] wraps any envelope produced by the seal paths, encrypts it with
+//! a fresh content key, and splits that key according to an [`SSKRSpec`].
+//! Each share is a copy of the encrypted envelope carrying one `sskrShare`
+//! assertion. [`join`] reverses the process and returns the original sealed
+//! envelope, ready to be passed to the matching `try_from_envelope`.
+
+use std::collections::BTreeMap;
+
+use bc_components::{
+    DigestProvider, SSKRShare, SSKRSpec, SymmetricKey, sskr_combine,
+};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// Splits a sealed envelope into SSKR share envelopes.
+///
+/// The outer vector holds one entry per group in `spec`, each containing that
+/// group's member shares.
+pub fn split(sealed: &Envelope, spec: &SSKRSpec) -> Result<Vec<Vec<Envelope>>> {
+    let content_key = SymmetricKey::new();
+    let encrypted = sealed.wrap().encrypt_subject(&content_key)?;
+    Ok(encrypted.sskr_split(spec, &content_key)?)
+}
+
+/// Reassembles a sealed envelope from a set of SSKR share envelopes.
+///
+/// Shares may be given in any order, and repeated copies of the same share
+/// are ignored. Fails with [`Error::MismatchedShares`] if the shares come
+/// from different splits, [`Error::InsufficientShares`] if they do not meet
+/// the split's thresholds, and [`Error::CorruptedShare`] if a share cannot be
+/// decoded or the recovered key does not decrypt the message.
+pub fn join(shares: &[Envelope]) -> Result<Envelope> {
+    let Some(first) = shares.first() else {
+        return Err(Error::InsufficientShares {
+            groups: 0,
+            threshold: 1,
+        });
+    };
+    let subject = first.subject().digest();
+
+    let mut members: BTreeMap<(usize, usize), SSKRShare> = BTreeMap::new();
+    let mut layout: Option<(u16, usize, usize)> = None;
+    for envelope in shares {
+        let share = extract_share(envelope)?;
+        let this = (
+            share.identifier(),
+            share.group_threshold(),
+            share.group_count(),
+        );
+        match layout {
+            None => layout = Some(this),
+            Some(expected) if expected.0 != this.0 => {
+                return Err(Error::MismatchedShares);
+            }
+            Some(expected) if expected != this => {
+                return Err(Error::CorruptedShare);
+            }
+            Some(_) => {}
+        }
+        if envelope.subject().digest() != subject {
+            return Err(Error::MismatchedShares);
+        }
+        let key = (share.group_index(), share.member_index());
+        match members.get(&key) {
+            Some(existing) if existing.as_bytes() != share.as_bytes() => {
+                return Err(Error::CorruptedShare);
+            }
+            Some(_) => {}
+            None => {
+                members.insert(key, share);
+            }
+        }
+    }
+    let (_, threshold, _) = layout.unwrap();
+
+    let mut group_members: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    for share in members.values() {
+        let entry = group_members
+            .entry(share.group_index())
+            .or_insert((share.member_threshold(), 0));
+        if entry.0 != share.member_threshold() {
+            return Err(Error::CorruptedShare);
+        }
+        entry.1 += 1;
+    }
+    let groups = group_members
+        .values()
+        .filter(|(member_threshold, count)| count >= member_threshold)
+        .count();
+    if groups < threshold {
+        return Err(Error::InsufficientShares { groups, threshold });
+    }
+
+    let shares: Vec<SSKRShare> = members.into_values().collect();
+    let secret = sskr_combine(&shares).map_err(|_| Error::CorruptedShare)?;
+    let content_key = SymmetricKey::from_data_ref(&secret)
+        .map_err(|_| Error::CorruptedShare)?;
+    let decrypted = first
+        .decrypt_subject(&content_key)
+        .map_err(|_| Error::CorruptedShare)?;
+    decrypted
+        .subject()
+        .try_unwrap()
+        .map_err(|_| Error::CorruptedShare)
+}
+
+fn extract_share(envelope: &Envelope) -> Result<SSKRShare> {
+    let assertions =
+        envelope.assertions_with_predicate(known_values::SSKR_SHARE);
+    let [assertion] = assertions.as_slice() else {
+        return Err(Error::CorruptedShare);
+    };
+    assertion
+        .as_object()
+        .and_then(|object| object.extract_subject::<SSKRShare>().ok())
+        .filter(|share| share.as_bytes().len() > 5)
+        .ok_or(Error::CorruptedShare)
+}
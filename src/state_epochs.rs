@@ -0,0 +1,243 @@
+//! Continuation state encrypted with keys derived per message and per epoch.
+//!
+//! By default a continuation is encrypted to the issuer's long-term
+//! encryption key, so anyone who obtains that key can read the state of every
+//! continuation ever captured. With [`StateEpochs`], the state is instead
+//! encrypted with a symmetric key derived from the identity key, the ID of
+//! the message the continuation was issued with, and the salt of the current
+//! epoch. The salts never leave the issuer, and once the salt of an epoch is
+//! discarded the continuations issued in it can no longer be decrypted.
+//!
+//! To survive a restart, the salts are exported with
+//! [`StateEpochs::to_encrypted_envelope`], encrypted with a key derived from
+//! the identity key, and restored with
+//! [`StateEpochs::try_from_encrypted_envelope`].
+
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use bc_components::{ARID, PrivateKeys, SymmetricKey};
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+/// The epoch a continuation's state key was derived in, in the clear.
+pub(crate) const STATE_EPOCH: &str = "stateEpoch";
+/// The message ID a continuation's state key was derived from, in the clear.
+pub(crate) const STATE_KEY_ID: &str = "stateKeyID";
+
+const EPOCH: &str = "epoch";
+const SALT: &str = "salt";
+
+const SALT_SIZE: usize = 32;
+
+/// The HKDF salt of the key the salts are exported with. It is shorter than
+/// the salt of any state key, an epoch's salt followed by a message ID, so
+/// the two keys never coincide.
+const EXPORT_SALT: &[u8] = b"gstp state epochs";
+
+type Epochs = Vec<(u32, Vec<u8>)>;
+
+/// The epoch salts from which continuation state keys are derived.
+///
+/// Share one instance, through
+/// [`SealOptions::with_state_epochs`](crate::SealOptions::with_state_epochs)
+/// and
+/// [`ParseOptions::with_state_epochs`](crate::ParseOptions::with_state_epochs),
+/// between the code that seals messages and the code that parses the
+/// continuations returned with them. Continuations are always issued in the
+/// newest epoch; [`Self::rotate`] starts a new one and [`Self::discard`] or
+/// [`Self::retain_latest`] drop old ones.
+pub struct StateEpochs {
+    key_material: Vec<u8>,
+    /// Oldest first; the last is the current epoch, so it is never empty.
+    epochs: RwLock<Epochs>,
+}
+
+impl std::fmt::Debug for StateEpochs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateEpochs")
+            .field("epochs", &self.epochs())
+            .finish_non_exhaustive()
+    }
+}
+
+impl StateEpochs {
+    /// Creates a manager deriving keys from `identity`, starting in epoch 0.
+    pub fn new(identity: &PrivateKeys) -> Self {
+        Self {
+            key_material: identity.to_cbor_data(),
+            epochs: RwLock::new(vec![(0, bc_rand::random_data(SALT_SIZE))]),
+        }
+    }
+
+    /// Restores the salts exported with [`Self::to_encrypted_envelope`],
+    /// deriving keys from `identity`, which must be the identity they were
+    /// exported with.
+    ///
+    /// Fails with [`Error::InvalidStateEpochs`] if the export holds no
+    /// epochs or holds one twice.
+    pub fn try_from_encrypted_envelope(
+        envelope: &Envelope,
+        identity: &PrivateKeys,
+    ) -> Result<Self> {
+        let key_material = identity.to_cbor_data();
+        let decrypted = envelope.decrypt(&export_key(&key_material))?;
+        let mut epochs = decrypted
+            .objects_for_predicate(EPOCH)
+            .iter()
+            .map(|epoch| {
+                let salt: ByteString =
+                    epoch.extract_object_for_predicate(SALT)?;
+                Ok((epoch.extract_subject()?, salt.into()))
+            })
+            .collect::<Result<Epochs>>()?;
+        epochs.sort_by_key(|(epoch, _)| *epoch);
+        if epochs.is_empty()
+            || epochs.windows(2).any(|pair| pair[0].0 == pair[1].0)
+        {
+            return Err(Error::InvalidStateEpochs);
+        }
+        Ok(Self {
+            key_material,
+            epochs: RwLock::new(epochs),
+        })
+    }
+
+    /// Exports the salts of the epochs still held, encrypted with a key
+    /// derived from the identity, so that they can be stored alongside it
+    /// and restored with [`Self::try_from_encrypted_envelope`].
+    pub fn to_encrypted_envelope(&self) -> Envelope {
+        self.read()
+            .iter()
+            .fold(
+                Envelope::new(known_values::UNIT),
+                |envelope, (epoch, salt)| {
+                    envelope.add_assertion(
+                        EPOCH,
+                        Envelope::new(*epoch).add_assertion(
+                            SALT,
+                            ByteString::from(salt.clone()),
+                        ),
+                    )
+                },
+            )
+            .encrypt(&export_key(&self.key_material))
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Epochs> {
+        // Each update is a single push, retain, or drain on the list, so a
+        // panic while the lock was held cannot have left it inconsistent.
+        self.epochs.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Epochs> {
+        self.epochs.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the epoch new continuations are issued in.
+    pub fn current_epoch(&self) -> u32 { current(&self.read()).0 }
+
+    /// Returns the epochs whose continuations can still be decrypted,
+    /// oldest first.
+    pub fn epochs(&self) -> Vec<u32> {
+        self.read().iter().map(|(epoch, _)| *epoch).collect()
+    }
+
+    /// Starts a new epoch with a fresh salt and returns it. Earlier epochs
+    /// remain decryptable until discarded.
+    pub fn rotate(&self) -> u32 {
+        let mut epochs = self.write();
+        let epoch = current(&epochs).0 + 1;
+        epochs.push((epoch, bc_rand::random_data(SALT_SIZE)));
+        epoch
+    }
+
+    /// Discards the salt of `epoch`, returning `false` if it is unknown or
+    /// is the current epoch, which cannot be discarded.
+    pub fn discard(&self, epoch: u32) -> bool {
+        let mut epochs = self.write();
+        let current = current(&epochs).0;
+        let count = epochs.len();
+        epochs.retain(|(e, _)| *e != epoch || *e == current);
+        epochs.len() < count
+    }
+
+    /// Discards all but the `count` newest epochs, always keeping the
+    /// current one.
+    pub fn retain_latest(&self, count: usize) {
+        let mut epochs = self.write();
+        let excess = epochs.len().saturating_sub(count.max(1));
+        epochs.drain(..excess);
+    }
+
+    fn derive(&self, salt: &[u8], id: ARID) -> SymmetricKey {
+        let salt = [salt, id.data()].concat();
+        let key = bc_crypto::hkdf_hmac_sha256(
+            &self.key_material,
+            salt,
+            SymmetricKey::SYMMETRIC_KEY_SIZE,
+        );
+        SymmetricKey::from_data_ref(key)
+            .expect("HKDF returns a key of the requested size")
+    }
+
+    /// Returns the current epoch and the key of a continuation issued in it
+    /// with the message `id`.
+    pub(crate) fn current_key(&self, id: ARID) -> (u32, SymmetricKey) {
+        let epochs = self.read();
+        let (epoch, salt) = current(&epochs);
+        (*epoch, self.derive(salt, id))
+    }
+
+    /// Returns the key of a continuation issued in `epoch` with the message
+    /// `id`.
+    pub(crate) fn key(&self, epoch: u32, id: ARID) -> Result<SymmetricKey> {
+        let epochs = self.read();
+        let (_, salt) = epochs
+            .iter()
+            .find(|(e, _)| *e == epoch)
+            .ok_or(Error::UnknownStateEpoch(epoch))?;
+        Ok(self.derive(salt, id))
+    }
+
+    /// Encrypts `continuation`, the unencrypted envelope of a continuation
+    /// issued with the message `id`, in the current epoch.
+    pub(crate) fn encrypt(&self, continuation: Envelope, id: ARID) -> Envelope {
+        let (epoch, key) = self.current_key(id);
+        continuation
+            .encrypt(&key)
+            .add_assertion(STATE_EPOCH, epoch)
+            .add_assertion(STATE_KEY_ID, id)
+    }
+
+    /// Decrypts a continuation encrypted with [`Self::encrypt`].
+    pub(crate) fn decrypt(&self, continuation: &Envelope) -> Result<Envelope> {
+        let epoch: u32 =
+            continuation.extract_object_for_predicate(STATE_EPOCH)?;
+        let id: ARID =
+            continuation.extract_object_for_predicate(STATE_KEY_ID)?;
+        Ok(continuation.decrypt(&self.key(epoch, id)?)?)
+    }
+}
+
+/// Returns the current epoch and its salt.
+fn current(epochs: &Epochs) -> &(u32, Vec<u8>) {
+    epochs.last().expect("the current epoch is never discarded")
+}
+
+/// Returns the key the salts are exported with.
+fn export_key(key_material: &[u8]) -> SymmetricKey {
+    let key = bc_crypto::hkdf_hmac_sha256(
+        key_material,
+        EXPORT_SALT,
+        SymmetricKey::SYMMETRIC_KEY_SIZE,
+    );
+    SymmetricKey::from_data_ref(key)
+        .expect("HKDF returns a key of the requested size")
+}
+
+/// Returns `true` if `continuation` was encrypted with a state key rather
+/// than to a recipient.
+pub(crate) fn is_epoch_encrypted(continuation: &Envelope) -> bool {
+    continuation.assertion_with_predicate(STATE_EPOCH).is_ok()
+}
@@ -0,0 +1,123 @@
+//! Negotiating how long the state a server keeps for a client lives.
+//!
+//! A client proposes a lifetime with
+//! [`SealedRequest::with_proposed_state_lifetime`]. The server clamps it
+//! according to its [`ContinuationPolicy`], records the lifetime it granted
+//! with [`SealedResponse::with_negotiated_state_lifetime`], and issues its
+//! continuation to expire accordingly. The client tracks the grant as a
+//! [`StateLease`], and rolls the state over through the well-known
+//! [`REFRESH_STATE`] function before it lapses. If it misses the refresh, the
+//! server finds the continuation expired and can offer
+//! [recovery](crate::continuation_expired_response).
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    ContinuationPolicy, Result, SealedRequest, SealedRequestBehavior,
+    SealedResponse, SealedResponseBehavior,
+};
+
+pub(crate) const PROPOSED_STATE_LIFETIME: &str = "proposedStateLifetime";
+pub(crate) const GRANTED_STATE_LIFETIME: &str = "grantedStateLifetime";
+
+/// The function a client calls to have the server reissue the state it
+/// holds, with a new lifetime, before the current one lapses.
+pub const REFRESH_STATE: &str = "refreshState";
+
+/// Reads a lifetime, in whole seconds, from a message.
+pub(crate) fn lifetime_from_message(
+    message: &Envelope,
+    predicate: &str,
+) -> Result<Option<Duration>> {
+    Ok(message
+        .extract_optional_object_for_predicate::<u64>(predicate)?
+        .map(Duration::from_secs))
+}
+
+impl ContinuationPolicy {
+    /// Returns the lifetime granted for state whose holder proposed
+    /// `proposed`: the proposal, capped at the maximum state lifetime, if
+    /// any.
+    pub fn grant_state_lifetime(
+        &self,
+        proposed: Option<Duration>,
+    ) -> Option<Duration> {
+        match (proposed, self.max_state_lifetime) {
+            (Some(proposed), Some(max)) => Some(proposed.min(max)),
+            (proposed, max) => proposed.or(max),
+        }
+    }
+}
+
+/// The state a server holds for us, as the continuation it issued and the
+/// lifetime it granted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateLease {
+    continuation: Envelope,
+    granted: Duration,
+    expires: Date,
+}
+
+impl StateLease {
+    /// Returns the lease carried by `response`, received at `received`, if
+    /// it carries a continuation and a granted lifetime.
+    pub fn from_response(
+        response: &SealedResponse,
+        received: Date,
+    ) -> Option<Self> {
+        let granted = response.granted_state_lifetime()?;
+        Some(Self {
+            continuation: response.peer_continuation()?.clone(),
+            granted,
+            expires: received + granted,
+        })
+    }
+
+    /// The continuation to return to the server.
+    pub fn continuation(&self) -> &Envelope { &self.continuation }
+
+    pub fn granted(&self) -> Duration { self.granted }
+
+    /// When the state lapses, as measured by our clock.
+    pub fn expires(&self) -> Date { self.expires }
+
+    /// When to refresh the state to keep `lead` ahead of its expiry.
+    pub fn refresh_at(&self, lead: Duration) -> Date {
+        self.expires - lead.min(self.granted)
+    }
+
+    /// Whether the state should be refreshed at `now`, keeping `lead` ahead
+    /// of its expiry.
+    pub fn needs_refresh(&self, now: Date, lead: Duration) -> bool {
+        now >= self.refresh_at(lead)
+    }
+
+    /// Composes the request that refreshes the state, proposing the lifetime
+    /// granted last time.
+    pub fn refresh_request(
+        &self,
+        id: ARID,
+        sender: &XIDDocument,
+    ) -> SealedRequest {
+        SealedRequest::new(REFRESH_STATE, id, sender)
+            .with_peer_continuation(self.continuation.clone())
+            .with_proposed_state_lifetime(self.granted)
+    }
+}
+
+/// Composes the response to a [`REFRESH_STATE`] request, reissuing the state
+/// it returned with a lifetime negotiated under `policy`.
+pub fn refresh_response(
+    request: &SealedRequest,
+    sender: &XIDDocument,
+    policy: &ContinuationPolicy,
+) -> SealedResponse {
+    SealedResponse::new_success(request.id(), sender)
+        .with_optional_state(request.state().cloned())
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .with_negotiated_state_lifetime(request, policy)
+}
@@ -0,0 +1,106 @@
+//! Keeping the state of continuations server-side.
+//!
+//! With [`ContinuationMode::Reference`], the state of each continuation we
+//! issue is put in a [`StateStore`] and the continuation carries only a
+//! reference to it. A continuation returned to us is resolved through the
+//! store given to
+//! [`ParseOptions::with_state_store`](crate::ParseOptions::with_state_store),
+//! so that handlers see the state as if it had been carried inline.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+
+use crate::{Error, Result};
+
+const STATE_REF: &str = "stateRef";
+
+/// Holds the state of continuations we issued by reference.
+pub trait StateStore: std::fmt::Debug + Send + Sync {
+    /// Stores `state`, returning the handle it can be retrieved by.
+    fn put(&self, state: Envelope) -> ARID;
+
+    /// Returns the state stored under `id`, or `None` if it is unknown or
+    /// has been evicted.
+    fn get(&self, id: ARID) -> Option<Envelope>;
+}
+
+/// A [`StateStore`] held in memory, which keeps every state for as long as
+/// it lives.
+#[derive(Debug, Default)]
+pub struct MemoryStateStore {
+    states: Mutex<HashMap<ARID, Envelope>>,
+}
+
+impl MemoryStateStore {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns the number of states stored.
+    pub fn len(&self) -> usize { self.states.lock().unwrap().len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Forgets the state stored under `id`, returning it if it was known.
+    pub fn remove(&self, id: ARID) -> Option<Envelope> {
+        self.states.lock().unwrap().remove(&id)
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn put(&self, state: Envelope) -> ARID {
+        let id = ARID::new();
+        self.states.lock().unwrap().insert(id, state);
+        id
+    }
+
+    fn get(&self, id: ARID) -> Option<Envelope> {
+        self.states.lock().unwrap().get(&id).cloned()
+    }
+}
+
+/// Where the state of the continuations we issue is kept.
+#[derive(Clone, Debug, Default)]
+pub enum ContinuationMode {
+    /// The state is carried in the continuation.
+    #[default]
+    Inline,
+
+    /// The state is put in the store, and the continuation carries a
+    /// reference to it in its place.
+    Reference(Arc<dyn StateStore>),
+}
+
+/// Puts `state` in `store`, returning the reference that replaces it in the
+/// continuation.
+pub(crate) fn store_state(store: &dyn StateStore, state: Envelope) -> Envelope {
+    Envelope::unit().add_assertion(STATE_REF, store.put(state))
+}
+
+/// Returns the handle `state` refers to, if it is a reference rather than
+/// state carried inline.
+fn state_ref(state: &Envelope) -> Result<Option<ARID>> {
+    if state.subject().as_known_value() != Some(&known_values::UNIT) {
+        return Ok(None);
+    }
+    Ok(state.extract_optional_object_for_predicate(STATE_REF)?)
+}
+
+/// Resolves `state` through `store` if it is a reference, failing with
+/// [`Error::ContinuationStateUnavailable`] if there is no store or the store
+/// no longer holds it. Returns `None` if the state is carried inline.
+pub(crate) fn resolve_state(
+    store: Option<&dyn StateStore>,
+    state: &Envelope,
+) -> Result<Option<Envelope>> {
+    let Some(id) = state_ref(state)? else {
+        return Ok(None);
+    };
+    store
+        .and_then(|store| store.get(id))
+        .map(Some)
+        .ok_or(Error::ContinuationStateUnavailable(id))
+}
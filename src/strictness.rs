@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How strictly messages are checked as they are sealed, set once for the
+/// whole process with [`set_strictness`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Any combination of signer and recipients may be sealed, including
+    /// none at all.
+    #[default]
+    Permissive,
+    /// Messages must be both signed and encrypted, and early failures must
+    /// give a reason. Unprotected messages may still be sealed through
+    /// `to_envelope_unprotected_i_know_what_i_am_doing`.
+    Production,
+}
+
+static STRICTNESS: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the strictness of sealing for the whole process, typically once at
+/// startup.
+pub fn set_strictness(strictness: Strictness) {
+    let value = match strictness {
+        Strictness::Permissive => 0,
+        Strictness::Production => 1,
+    };
+    STRICTNESS.store(value, Ordering::Relaxed);
+}
+
+/// Returns the strictness of sealing set with [`set_strictness`].
+pub fn strictness() -> Strictness {
+    match STRICTNESS.load(Ordering::Relaxed) {
+        0 => Strictness::Permissive,
+        _ => Strictness::Production,
+    }
+}
@@ -0,0 +1,90 @@
+//! Opt-in tracking of continuation state obtained from parsed messages.
+//!
+//! When the `taint-checks` feature is enabled, every state envelope recovered
+//! from a decrypted continuation is recorded as tainted. The composition
+//! methods that place values in the readable part of a message
+//! (`with_result`, `with_parameter`, and friends) then assert in debug builds
+//! that no tainted envelope appears anywhere inside the value. State is
+//! private to its issuer and may only be passed along via `with_state`.
+//!
+//! Envelopes are matched by identity, so the state itself and its clones
+//! are tainted, while an equal envelope built independently is not. Only
+//! the [`TAINT_CAPACITY`] most recently recovered states are tracked; each
+//! is held until it is forgotten, so that its identity cannot be reused.
+
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, OnceLock},
+};
+
+use bc_envelope::prelude::*;
+
+/// The number of recovered states tracked at a time.
+pub const TAINT_CAPACITY: usize = 1024;
+
+fn tainted_states() -> &'static Mutex<VecDeque<Envelope>> {
+    static TAINTED: OnceLock<Mutex<VecDeque<Envelope>>> = OnceLock::new();
+    TAINTED.get_or_init(|| Mutex::new(VecDeque::new()))
+}
+
+/// Records a state envelope recovered from a parsed continuation.
+///
+/// The null envelope is never tainted, as it is the placeholder used when a
+/// continuation carries no state.
+pub(crate) fn mark_tainted(state: &Envelope) {
+    if state.is_null() {
+        return;
+    }
+    let mut tainted = tainted_states().lock().unwrap();
+    if tainted.len() == TAINT_CAPACITY {
+        tainted.pop_front();
+    }
+    tainted.push_back(state.clone());
+}
+
+/// Returns `true` if `envelope` is, or contains, a state envelope recovered
+/// from a parsed continuation, or a clone of one.
+pub fn is_tainted(envelope: &Envelope) -> bool {
+    let tainted = tainted_states().lock().unwrap();
+    !tainted.is_empty() && contains_any(envelope, &tainted)
+}
+
+/// Returns `true` if `envelope` or any envelope inside it shares its
+/// allocation with one of `states`.
+fn contains_any(envelope: &Envelope, states: &VecDeque<Envelope>) -> bool {
+    if states
+        .iter()
+        .any(|state| std::ptr::eq(state.case(), envelope.case()))
+    {
+        return true;
+    }
+    match envelope.case() {
+        EnvelopeCase::Node {
+            subject,
+            assertions,
+            ..
+        } => {
+            contains_any(subject, states)
+                || assertions
+                    .iter()
+                    .any(|assertion| contains_any(assertion, states))
+        }
+        EnvelopeCase::Wrapped { envelope, .. } => {
+            contains_any(envelope, states)
+        }
+        EnvelopeCase::Assertion(assertion) => {
+            contains_any(&assertion.predicate(), states)
+                || contains_any(&assertion.object(), states)
+        }
+        _ => false,
+    }
+}
+
+/// Asserts in debug builds that `envelope` carries no tainted state.
+pub(crate) fn check_untainted(envelope: &Envelope, context: &str) {
+    debug_assert!(
+        !is_tainted(envelope),
+        "{context}: continuation state from a parsed message must only be \
+         returned via with_state"
+    );
+}
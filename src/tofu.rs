@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use bc_components::{SigningPublicKey, XID, XIDProvider};
+use bc_xid::XIDDocument;
+
+use crate::{Error, Result};
+
+/// The verification keys first seen for each sender, for trust on first
+/// use.
+///
+/// Once a sender's key is recorded, messages from the same XID signed with
+/// any other key are rejected with [`Error::SenderKeyChanged`] until the
+/// change is blessed with [`TofuStore::bless_key_change`]. Only keys that
+/// authenticate the XID are recorded on first use: the inception key it is
+/// derived from, or a key already blessed for it.
+pub trait TofuStore: std::fmt::Debug + Send + Sync {
+    /// Returns the verification key recorded for `xid`, if any.
+    fn lookup(&self, xid: XID) -> Result<Option<SigningPublicKey>>;
+
+    /// Records `key` as the verification key of `xid`, replacing any
+    /// recorded before.
+    fn record(&self, xid: XID, key: SigningPublicKey) -> Result<()>;
+
+    /// Accepts the current verification key of `sender` in place of the
+    /// one recorded, after a rotation confirmed out of band.
+    fn bless_key_change(&self, sender: &XIDDocument) -> Result<()> {
+        let key = sender
+            .verification_key()
+            .ok_or(Error::SenderMissingVerificationKey)?;
+        self.record(sender.xid(), key.clone())
+    }
+
+    /// Records the key of a sender seen for the first time, or checks it
+    /// against the one recorded.
+    fn check_and_record(&self, xid: XID, key: &SigningPublicKey) -> Result<()> {
+        match self.lookup(xid)? {
+            None => self.record(xid, key.clone()),
+            Some(recorded) if recorded == *key => Ok(()),
+            Some(_) => Err(Error::SenderKeyChanged { xid }),
+        }
+    }
+}
+
+/// A [`TofuStore`] that lives only as long as the process.
+#[derive(Debug, Default)]
+pub struct MemoryTofuStore {
+    keys: Mutex<HashMap<XID, SigningPublicKey>>,
+}
+
+impl MemoryTofuStore {
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns the number of senders recorded.
+    pub fn len(&self) -> usize { self.keys.lock().unwrap().len() }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+}
+
+impl TofuStore for MemoryTofuStore {
+    fn lookup(&self, xid: XID) -> Result<Option<SigningPublicKey>> {
+        Ok(self.keys.lock().unwrap().get(&xid).cloned())
+    }
+
+    fn record(&self, xid: XID, key: SigningPublicKey) -> Result<()> {
+        self.keys.lock().unwrap().insert(xid, key);
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use bc_components::Digest;
+
+use crate::{Error, Result};
+
+/// The predicate under which a message commits to the digest of the message
+/// before it in a conversation.
+pub(crate) const TRANSCRIPT: &str = "transcript";
+
+/// A hash chain over the messages of a conversation, each committing to the
+/// digest of the one before it, as TLS transcripts do.
+///
+/// Each message is sealed with the digest of the last recorded, as given by
+/// [`Self::head`], and recorded once sent or parsed. The first message
+/// commits to no previous digest.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transcript {
+    digests: Vec<Digest>,
+}
+
+impl Transcript {
+    pub fn new() -> Self { Self::default() }
+
+    /// The digest the next message must commit to, or `None` before the
+    /// first message.
+    pub fn head(&self) -> Option<Digest> { self.digests.last().copied() }
+
+    /// Records the message with envelope digest `digest`, which committed to
+    /// `previous`, failing with [`Error::TranscriptBroken`] if `previous` is
+    /// not the digest of the last message recorded.
+    pub fn record(
+        &mut self,
+        previous: Option<Digest>,
+        digest: Digest,
+    ) -> Result<()> {
+        if previous != self.head() {
+            return Err(Error::TranscriptBroken {
+                link: self.digests.len(),
+            });
+        }
+        self.digests.push(digest);
+        Ok(())
+    }
+
+    /// Checks that `links`, each the previous digest a message committed to
+    /// and the digest of its envelope, form an unbroken chain, returning it.
+    pub fn verify(
+        links: impl IntoIterator<Item = (Option<Digest>, Digest)>,
+    ) -> Result<Self> {
+        let mut transcript = Self::new();
+        for (previous, digest) in links {
+            transcript.record(previous, digest)?;
+        }
+        Ok(transcript)
+    }
+
+    /// The digests of the messages recorded, in order.
+    pub fn digests(&self) -> &[Digest] { &self.digests }
+
+    pub fn len(&self) -> usize { self.digests.len() }
+
+    pub fn is_empty(&self) -> bool { self.digests.is_empty() }
+}
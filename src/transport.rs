@@ -0,0 +1,27 @@
+//! Helpers for carrying sealed envelopes over specific transports.
+
+use std::future::Future;
+
+use bc_envelope::prelude::*;
+
+use crate::Result;
+
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+
+pub mod nfc;
+
+pub mod stream;
+
+pub mod ws;
+
+/// Carries a sealed request to a peer and returns its sealed response, such
+/// as an HTTP client or an in-process
+/// [`GstpService`](crate::service::GstpService).
+pub trait GstpTransport: Send {
+    /// Sends `request` and waits for the response.
+    fn exchange(
+        &mut self,
+        request: Envelope,
+    ) -> impl Future<Output = Result<Envelope>> + Send;
+}
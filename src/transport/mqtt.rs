@@ -0,0 +1,208 @@
+//! Publishing sealed events on MQTT topics and decoding them on receipt.
+//!
+//! An event is published as the CBOR encoding of its sealed envelope on a
+//! topic made of a prefix shared by the fleet followed by the event's topic,
+//! as given by [`event_topic`]: an event whose content has an `isA` of
+//! `"door/open"`, published under `"site/1"`, goes to `"site/1/door/open"`,
+//! and an event without a topic goes to the prefix itself.
+//!
+//! Events sealed without recipients are broadcast: they are signed but not
+//! encrypted, so an [`EventDecoder`] without keys can still verify them.
+//! A decoder with keys parses both broadcast events and events encrypted to
+//! it.
+//!
+//! The helpers work with any client through the [`MqttClient`] trait, whose
+//! implementations are expected to report their own failures as
+//! [`Error::Io`].
+
+use std::future::Future;
+
+use bc_components::PrivateKeys;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, EventContent, ParseOptions, Result, SealOptions, SealedEvent, codec,
+    event_topic,
+};
+
+/// The largest payload accepted unless configured otherwise.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 128 * 1024;
+
+/// A connection to an MQTT broker, such as an adapter over a `rumqttc` or
+/// `paho-mqtt` client, which chooses the quality of service.
+pub trait MqttClient: Send {
+    /// Publishes `payload` on `topic`.
+    fn publish(
+        &mut self,
+        topic: &str,
+        payload: Vec<u8>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Subscribes to the topics matching `filter`.
+    fn subscribe(
+        &mut self,
+        filter: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Returns the topic `event` is published on under `topic_prefix`.
+///
+/// Fails with [`Error::InvalidTopic`] if the topic would be empty or would
+/// contain a wildcard or null character.
+pub fn topic_for_event<T>(
+    topic_prefix: &str,
+    event: &SealedEvent<T>,
+) -> Result<String>
+where
+    T: EventContent,
+{
+    let prefix = topic_prefix.trim_end_matches('/');
+    let topic = match event_topic(&event.content().to_envelope()) {
+        Some(topic) if prefix.is_empty() => topic,
+        Some(topic) => format!("{prefix}/{topic}"),
+        None => prefix.to_string(),
+    };
+    if topic.is_empty() || topic.contains(['+', '#', '\0']) {
+        return Err(Error::InvalidTopic(topic));
+    }
+    Ok(topic)
+}
+
+/// Seals `event` from `sender` to `recipients` and publishes it on its topic
+/// under `topic_prefix`, returning the topic.
+///
+/// With no recipients the event is broadcast, signed but not encrypted.
+/// Fails with [`Error::FrameTooLarge`] without publishing anything if the
+/// payload would exceed `max_payload_size`.
+pub async fn publish_event<T>(
+    client: &mut impl MqttClient,
+    topic_prefix: &str,
+    event: &SealedEvent<T>,
+    valid_until: Option<Date>,
+    sender: &PrivateKeys,
+    recipients: &[&XIDDocument],
+    max_payload_size: usize,
+) -> Result<String>
+where
+    T: EventContent,
+{
+    let topic = topic_for_event(topic_prefix, event)?;
+    let envelope = event.to_envelope_with_options(
+        valid_until,
+        Some(sender),
+        recipients,
+        &SealOptions::default(),
+    )?;
+    let payload = codec::to_cbor(&envelope);
+    if payload.len() > max_payload_size {
+        return Err(Error::FrameTooLarge {
+            size: payload.len(),
+            limit: max_payload_size,
+        });
+    }
+    client.publish(&topic, payload).await?;
+    Ok(topic)
+}
+
+/// Decodes the payloads of MQTT messages carrying events published under a
+/// topic prefix.
+///
+/// A decoder without keys verifies broadcast events only; one given keys
+/// with [`Self::with_private_keys`] also decrypts events sealed to it.
+#[derive(Clone, Debug)]
+pub struct EventDecoder {
+    topic_prefix: String,
+    private_keys: Option<PrivateKeys>,
+    parse_options: ParseOptions,
+    max_payload_size: usize,
+}
+
+impl EventDecoder {
+    pub fn new(topic_prefix: impl Into<String>) -> Self {
+        Self {
+            topic_prefix: topic_prefix.into(),
+            private_keys: None,
+            parse_options: ParseOptions::default(),
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+
+    pub fn with_private_keys(mut self, private_keys: &PrivateKeys) -> Self {
+        self.private_keys = Some(private_keys.clone());
+        self
+    }
+
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    pub fn with_max_payload_size(mut self, max_payload_size: usize) -> Self {
+        self.max_payload_size = max_payload_size;
+        self
+    }
+
+    /// The filter matching every topic events are published on under the
+    /// decoder's prefix.
+    pub fn topic_filter(&self) -> String {
+        let prefix = self.topic_prefix.trim_end_matches('/');
+        if prefix.is_empty() {
+            "#".to_string()
+        } else {
+            format!("{prefix}/#")
+        }
+    }
+
+    /// Subscribes `client` to every topic events are published on under the
+    /// decoder's prefix.
+    pub async fn subscribe(&self, client: &mut impl MqttClient) -> Result<()> {
+        client.subscribe(&self.topic_filter()).await
+    }
+
+    /// Parses the payload of a message received on `topic`.
+    ///
+    /// Fails with [`Error::FrameTooLarge`] before decoding a payload over the
+    /// configured size, with [`Error::UnexpectedEncryption`] for an encrypted
+    /// event if the decoder has no keys, and with [`Error::TopicMismatch`] if
+    /// the event's content names a topic other than `topic`, so that an
+    /// event cannot be replayed to subscribers of another topic.
+    pub fn decode<T>(
+        &self,
+        topic: &str,
+        payload: &[u8],
+    ) -> Result<SealedEvent<T>>
+    where
+        T: EventContent,
+    {
+        if payload.len() > self.max_payload_size {
+            return Err(Error::FrameTooLarge {
+                size: payload.len(),
+                limit: self.max_payload_size,
+            });
+        }
+        let envelope = codec::from_cbor(payload)?;
+        let event = match &self.private_keys {
+            Some(private_keys) if envelope.subject().is_encrypted() => {
+                SealedEvent::try_from_envelope_opt(
+                    &envelope,
+                    &self.parse_options,
+                    private_keys,
+                )?
+            }
+            private_keys => SealedEvent::try_from_signed_envelope_opt(
+                &envelope,
+                &self.parse_options,
+                private_keys.as_ref(),
+            )?,
+        };
+        let expected = topic_for_event(&self.topic_prefix, &event)?;
+        if topic != expected {
+            return Err(Error::TopicMismatch {
+                topic: topic.to_string(),
+                expected,
+            });
+        }
+        Ok(event)
+    }
+}
@@ -0,0 +1,163 @@
+//! Chunking of envelopes to fit the capacity of NFC tags and NDEF records.
+//!
+//! [`split`] encodes an envelope and divides it into chunks of at most a
+//! given number of bytes. Each chunk is itself a small envelope whose subject
+//! is a slice of the message, asserting its index, the number of chunks, and
+//! the digest of the whole encoded message, so a [`Reassembler`] can collect
+//! them in any order and check the result before it is parsed. The digest
+//! covers the encoding rather than being the envelope's own digest, which
+//! does not change if the ciphertext of an encrypted subject is damaged.
+
+use bc_components::Digest;
+use bc_envelope::prelude::*;
+
+use crate::{
+    Error, Result,
+    result_chunks::{CHUNK_COUNT, CHUNK_INDEX},
+};
+
+const MESSAGE_DIGEST: &str = "messageDigest";
+
+/// The most a byte string header can grow from that of an empty one.
+const LENGTH_HEADER_GROWTH: usize = 8;
+
+fn chunk_envelope(
+    index: usize,
+    count: usize,
+    digest: Digest,
+    data: &[u8],
+) -> Envelope {
+    Envelope::new(ByteString::from(data))
+        .add_assertion(CHUNK_INDEX, index)
+        .add_assertion(CHUNK_COUNT, count)
+        .add_assertion(MESSAGE_DIGEST, digest)
+}
+
+/// Splits `envelope` into encoded chunks of at most `budget` bytes each.
+///
+/// Fails with [`Error::ChunkBudgetTooSmall`] if `budget` leaves no room for
+/// any of the message beside the chunk's own assertions.
+pub fn split(envelope: &Envelope, budget: usize) -> Result<Vec<Vec<u8>>> {
+    let data = envelope.to_cbor_data();
+    let digest = Digest::from_image(&data);
+    // No chunk index or count can exceed the length of the message.
+    let minimum = chunk_envelope(data.len(), data.len(), digest, &[])
+        .to_cbor_data()
+        .len()
+        + LENGTH_HEADER_GROWTH
+        + 1;
+    if budget < minimum {
+        return Err(Error::ChunkBudgetTooSmall { budget, minimum });
+    }
+    let chunk_size = budget - minimum + 1;
+    let count = data.len().div_ceil(chunk_size);
+    Ok(data
+        .chunks(chunk_size)
+        .enumerate()
+        .map(|(index, part)| {
+            chunk_envelope(index, count, digest, part).to_cbor_data()
+        })
+        .collect())
+}
+
+/// Collects the chunks produced by [`split`] and reassembles the envelope.
+///
+/// Chunks may be added in any order, and a chunk added again with the same
+/// content is ignored.
+#[derive(Clone, Debug, Default)]
+pub struct Reassembler {
+    digest: Option<Digest>,
+    chunks: Vec<Option<ByteString>>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds an encoded chunk.
+    ///
+    /// Fails with [`Error::InvalidChunk`] if the chunk is malformed or
+    /// belongs to a different message than the chunks before it, and with
+    /// [`Error::ConflictingChunk`] if a chunk with the same index but
+    /// different content was already added.
+    pub fn add(&mut self, chunk: impl AsRef<[u8]>) -> Result<()> {
+        let chunk = Envelope::try_from_cbor_data(chunk.as_ref().to_vec())
+            .map_err(|_| Error::InvalidChunk)?;
+        let (index, count, digest, data) = parse_chunk(&chunk)?;
+        if self.chunks.is_empty() {
+            self.digest = Some(digest);
+            self.chunks = vec![None; count];
+        } else if self.digest != Some(digest) || self.chunks.len() != count {
+            return Err(Error::InvalidChunk);
+        }
+        match &self.chunks[index] {
+            Some(existing) if existing != &data => {
+                Err(Error::ConflictingChunk { index })
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.chunks[index] = Some(data);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether every chunk of the message has been added.
+    pub fn is_complete(&self) -> bool {
+        !self.chunks.is_empty() && self.chunks.iter().all(Option::is_some)
+    }
+
+    /// The indexes of the chunks not yet added, which are unknown until the
+    /// first chunk is.
+    pub fn missing(&self) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| chunk.is_none())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Reassembles the envelope, ready to be parsed.
+    ///
+    /// Fails with [`Error::MissingChunk`] naming the first chunk not yet
+    /// added, and with [`Error::ChunkDigestMismatch`] if the reassembled
+    /// envelope does not match the digest its chunks carry.
+    pub fn finish(self) -> Result<Envelope> {
+        let count = self.chunks.len();
+        if count == 0 {
+            return Err(Error::MissingChunk { index: 0, count });
+        }
+        if let Some(index) = self.missing().first() {
+            return Err(Error::MissingChunk {
+                index: *index,
+                count,
+            });
+        }
+        let data: Vec<u8> = self
+            .chunks
+            .into_iter()
+            .flatten()
+            .flat_map(Vec::from)
+            .collect();
+        if Some(Digest::from_image(&data)) != self.digest {
+            return Err(Error::ChunkDigestMismatch);
+        }
+        Ok(Envelope::try_from_cbor_data(data)?)
+    }
+}
+
+fn parse_chunk(chunk: &Envelope) -> Result<(usize, usize, Digest, ByteString)> {
+    let parse = || -> Result<_> {
+        Ok((
+            chunk.extract_object_for_predicate::<usize>(CHUNK_INDEX)?,
+            chunk.extract_object_for_predicate::<usize>(CHUNK_COUNT)?,
+            chunk.extract_object_for_predicate::<Digest>(MESSAGE_DIGEST)?,
+            chunk.extract_subject::<ByteString>()?,
+        ))
+    };
+    let parsed = parse().map_err(|_| Error::InvalidChunk)?;
+    if parsed.0 >= parsed.1 || chunk.assertions().len() != 3 {
+        return Err(Error::InvalidChunk);
+    }
+    Ok(parsed)
+}
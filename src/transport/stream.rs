@@ -0,0 +1,43 @@
+//! Length-prefixed envelopes on raw byte streams such as TCP connections.
+//!
+//! Each envelope is written as the length of its CBOR encoding, a big-endian
+//! `u32`, followed by the encoding itself. Unlike the frames of
+//! [`framing`](crate::framing) there is no magic prefix or version byte, so
+//! this suits peers that already agree the stream carries nothing but GSTP
+//! messages.
+
+use std::io::{Read, Write};
+
+use bc_envelope::prelude::*;
+
+use crate::{Error, ParseLimits, Result, framing};
+
+const PREFIX_LEN: usize = 4;
+
+/// Writes `envelope` to `w`, prefixed with its length.
+pub fn write_envelope(w: &mut impl Write, envelope: &Envelope) -> Result<()> {
+    let data = envelope.to_cbor_data();
+    let len = u32::try_from(data.len()).map_err(|_| Error::FrameTooLarge {
+        size: data.len(),
+        limit: u32::MAX as usize,
+    })?;
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(&data)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Reads a length-prefixed envelope from `r`.
+///
+/// Returns [`Error::EndOfStream`] if the stream ends cleanly before the next
+/// envelope starts, and [`Error::FrameTruncated`] if it ends partway through
+/// one. A declared length over the maximum message size of `limits` fails
+/// with [`Error::FrameTooLarge`] before any buffer for it is allocated.
+pub fn read_envelope(
+    r: &mut impl Read,
+    limits: &ParseLimits,
+) -> Result<Envelope> {
+    let size =
+        framing::read_length_prefix::<PREFIX_LEN>(r, limits, |_| Ok(()))?;
+    framing::read_payload(r, size)
+}
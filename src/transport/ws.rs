@@ -0,0 +1,258 @@
+//! Framing of sealed messages on WebSocket connections.
+//!
+//! Requests, responses, and events share one connection, each in its own
+//! binary WebSocket message: a byte giving the [`MessageKind`], since the
+//! kind of an encrypted message is hidden, followed by the CBOR encoding of
+//! the sealed envelope. [`encode_frame`] and [`decode_frame`] convert
+//! between the two.
+//!
+//! [`split`] wraps the two halves of a connection, as [`WsSink`] and
+//! [`WsSource`], in a [`GstpSender`] that seals outgoing messages to the
+//! peer and a [`GstpReceiver`] that parses incoming ones into
+//! [`GstpFrame`]s, each of which can be moved to its own task. Responses
+//! are correlated with their requests by [`SealedResponse::id`], for
+//! example with [`PendingRequests`](crate::PendingRequests).
+//!
+//! [`SealedResponse::id`]: crate::SealedResponseBehavior::id
+
+use std::future::Future;
+
+use bc_components::PrivateKeys;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+
+use crate::{
+    Error, EventContent, MessageKind, ParseOptions, Result, SealOptions,
+    SealedEvent, SealedRequest, SealedResponse, codec,
+};
+
+fn kind_byte(kind: MessageKind) -> u8 {
+    match kind {
+        MessageKind::Request => 1,
+        MessageKind::Response => 2,
+        MessageKind::Event => 3,
+    }
+}
+
+/// Encodes `envelope`, carrying a message of the given kind, as the payload
+/// of a binary WebSocket message.
+pub fn encode_frame(kind: MessageKind, envelope: &Envelope) -> Vec<u8> {
+    let mut frame = vec![kind_byte(kind)];
+    frame.extend(codec::to_cbor(envelope));
+    frame
+}
+
+/// Decodes the payload of a binary WebSocket message into the kind of
+/// message it carries and its envelope.
+///
+/// Fails with [`Error::FrameTruncated`] if the payload is empty, and with
+/// [`Error::UnknownFrameKind`] if it does not start with a known kind.
+pub fn decode_frame(frame: &[u8]) -> Result<(MessageKind, Envelope)> {
+    let (&kind, data) = frame.split_first().ok_or(Error::FrameTruncated)?;
+    let kind = match kind {
+        1 => MessageKind::Request,
+        2 => MessageKind::Response,
+        3 => MessageKind::Event,
+        _ => return Err(Error::UnknownFrameKind(kind)),
+    };
+    Ok((kind, codec::from_cbor(data)?))
+}
+
+/// The sending half of a WebSocket connection, such as an adapter over the
+/// sink of a `tungstenite` stream.
+pub trait WsSink: Send {
+    /// Sends a binary message.
+    fn send_binary(
+        &mut self,
+        data: Vec<u8>,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// The receiving half of a WebSocket connection.
+pub trait WsSource: Send {
+    /// Receives the next binary message, skipping messages of other types,
+    /// or fails with [`Error::EndOfStream`] once the connection is closed.
+    fn recv_binary(&mut self) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+/// A message received on a WebSocket connection, parsed according to its
+/// kind.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GstpFrame<T>
+where
+    T: EventContent,
+{
+    Request(SealedRequest),
+    Response(SealedResponse),
+    Event(SealedEvent<T>),
+}
+
+impl<T> GstpFrame<T>
+where
+    T: EventContent,
+{
+    /// Parses the payload of a binary WebSocket message, checking it against
+    /// `options`.
+    pub fn parse(
+        frame: &[u8],
+        options: &ParseOptions,
+        private_keys: &PrivateKeys,
+    ) -> Result<Self> {
+        let (kind, envelope) = decode_frame(frame)?;
+        Ok(match kind {
+            MessageKind::Request => {
+                Self::Request(SealedRequest::try_from_envelope_opt(
+                    &envelope,
+                    options,
+                    private_keys,
+                )?)
+            }
+            MessageKind::Response => {
+                Self::Response(SealedResponse::try_from_encrypted_envelope_opt(
+                    &envelope,
+                    options,
+                    private_keys,
+                )?)
+            }
+            MessageKind::Event => {
+                Self::Event(SealedEvent::try_from_envelope_opt(
+                    &envelope,
+                    options,
+                    private_keys,
+                )?)
+            }
+        })
+    }
+
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Self::Request(_) => MessageKind::Request,
+            Self::Response(_) => MessageKind::Response,
+            Self::Event(_) => MessageKind::Event,
+        }
+    }
+}
+
+/// Wraps the halves of a WebSocket connection to `peer`, signing outgoing
+/// messages and decrypting incoming ones with `private_keys`.
+///
+/// The receiver accepts only messages sent by `peer`.
+pub fn split<S, R>(
+    sink: S,
+    source: R,
+    private_keys: &PrivateKeys,
+    peer: &XIDDocument,
+) -> (GstpSender<S>, GstpReceiver<R>)
+where
+    S: WsSink,
+    R: WsSource,
+{
+    let sender = GstpSender {
+        sink,
+        private_keys: private_keys.clone(),
+        peer: peer.clone(),
+        seal_options: SealOptions::default(),
+    };
+    let receiver = GstpReceiver {
+        source,
+        private_keys: private_keys.clone(),
+        parse_options: ParseOptions::new().with_expected_sender(peer),
+    };
+    (sender, receiver)
+}
+
+/// Seals messages to the peer of a WebSocket connection and sends them.
+#[derive(Debug)]
+pub struct GstpSender<S: WsSink> {
+    sink: S,
+    private_keys: PrivateKeys,
+    peer: XIDDocument,
+    seal_options: SealOptions,
+}
+
+impl<S: WsSink> GstpSender<S> {
+    pub fn with_seal_options(mut self, options: SealOptions) -> Self {
+        self.seal_options = options;
+        self
+    }
+
+    pub fn peer(&self) -> &XIDDocument { &self.peer }
+
+    pub async fn send_request(
+        &mut self,
+        request: &SealedRequest,
+    ) -> Result<()> {
+        let envelope = request.to_envelope_with_options(
+            None,
+            Some(&self.private_keys),
+            &[&self.peer],
+            &self.seal_options,
+        )?;
+        self.send(MessageKind::Request, &envelope).await
+    }
+
+    pub async fn send_response(
+        &mut self,
+        response: &SealedResponse,
+    ) -> Result<()> {
+        let envelope = response.to_envelope_with_options(
+            None,
+            Some(&self.private_keys),
+            &[&self.peer],
+            &self.seal_options,
+        )?;
+        self.send(MessageKind::Response, &envelope).await
+    }
+
+    pub async fn send_event<T>(&mut self, event: &SealedEvent<T>) -> Result<()>
+    where
+        T: EventContent,
+    {
+        let envelope = event.to_envelope_with_options(
+            None,
+            Some(&self.private_keys),
+            &[&self.peer],
+            &self.seal_options,
+        )?;
+        self.send(MessageKind::Event, &envelope).await
+    }
+
+    async fn send(
+        &mut self,
+        kind: MessageKind,
+        envelope: &Envelope,
+    ) -> Result<()> {
+        self.sink.send_binary(encode_frame(kind, envelope)).await
+    }
+}
+
+/// Receives messages from the peer of a WebSocket connection and parses
+/// them.
+#[derive(Debug)]
+pub struct GstpReceiver<R: WsSource> {
+    source: R,
+    private_keys: PrivateKeys,
+    parse_options: ParseOptions,
+}
+
+impl<R: WsSource> GstpReceiver<R> {
+    /// Replaces the options incoming messages are checked against, including
+    /// the requirement that they are sent by the peer.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self
+    }
+
+    /// Receives and parses the next message.
+    ///
+    /// Fails with [`Error::EndOfStream`] once the connection is closed, and
+    /// as [`GstpFrame::parse`] does for a message that cannot be parsed,
+    /// after which the receiver can still be used.
+    pub async fn recv<T>(&mut self) -> Result<GstpFrame<T>>
+    where
+        T: EventContent,
+    {
+        let frame = self.source.recv_binary().await?;
+        GstpFrame::parse(&frame, &self.parse_options, &self.private_keys)
+    }
+}
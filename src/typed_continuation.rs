@@ -0,0 +1,128 @@
+use bc_components::{ARID, Decrypter, Encrypter, PrivateKeys};
+use bc_envelope::prelude::*;
+
+use crate::{Continuation, Error, ParseOptions, Result};
+
+/// A [`Continuation`] whose state is decoded as a `T`, for applications whose
+/// state is always of one type.
+///
+/// Everything but the state is that of the underlying continuation, which
+/// is encoded exactly as an untyped one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedContinuation<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    continuation: Continuation,
+    state: T,
+}
+
+impl<T> TypedContinuation<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    pub fn new(state: T) -> Self {
+        Self { continuation: Continuation::new(state.clone()), state }
+    }
+
+    /// Decodes the state of `continuation`, failing with
+    /// [`Error::ContinuationStateDecoding`] if it is not a `T`.
+    pub fn from_continuation(continuation: Continuation) -> Result<Self> {
+        let state = decode_state(continuation.state())?;
+        Ok(Self { continuation, state })
+    }
+
+    /// Applies `f` to the underlying continuation, for example to set its
+    /// validity, keeping the state.
+    pub fn map_continuation(
+        self,
+        f: impl FnOnce(Continuation) -> Continuation,
+    ) -> Self {
+        let continuation = f(self.continuation);
+        Self { continuation, state: self.state }
+    }
+
+    pub fn state(&self) -> &T { &self.state }
+
+    pub fn continuation(&self) -> &Continuation { &self.continuation }
+
+    pub fn into_continuation(self) -> Continuation { self.continuation }
+
+    pub fn to_envelope(&self, recipient: Option<&dyn Encrypter>) -> Envelope {
+        self.continuation.to_envelope(recipient)
+    }
+
+    /// Parses a continuation like [`Continuation::try_from_envelope`],
+    /// decoding its state as a `T`.
+    pub fn try_from_envelope(
+        encrypted_envelope: &Envelope,
+        id: Option<ARID>,
+        now: Option<Date>,
+        recipient: Option<&PrivateKeys>,
+    ) -> Result<Self> {
+        Self::from_continuation(Continuation::try_from_envelope(
+            encrypted_envelope,
+            id,
+            now,
+            recipient,
+        )?)
+    }
+
+    /// Parses a continuation like [`Continuation::try_from_envelope_opt`],
+    /// decoding its state as a `T`.
+    pub fn try_from_envelope_opt(
+        encrypted_envelope: &Envelope,
+        options: &ParseOptions,
+        keys: &[&dyn Decrypter],
+    ) -> Result<Self> {
+        Self::from_continuation(Continuation::try_from_envelope_opt(
+            encrypted_envelope,
+            options,
+            keys,
+        )?)
+    }
+}
+
+impl<T> TryFrom<Continuation> for TypedContinuation<T>
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    type Error = Error;
+
+    fn try_from(continuation: Continuation) -> Result<Self> {
+        Self::from_continuation(continuation)
+    }
+}
+
+impl<T> From<TypedContinuation<T>> for Continuation
+where
+    T: EnvelopeEncodable
+        + TryFrom<Envelope>
+        + std::fmt::Debug
+        + Clone
+        + PartialEq,
+{
+    fn from(typed: TypedContinuation<T>) -> Self { typed.continuation }
+}
+
+/// Decodes `state` as a `T`, failing with
+/// [`Error::ContinuationStateDecoding`] if it is not one.
+pub(crate) fn decode_state<T: TryFrom<Envelope>>(
+    state: &Envelope,
+) -> Result<T> {
+    T::try_from(state.clone()).map_err(|_| {
+        Error::ContinuationStateDecoding(std::any::type_name::<T>())
+    })
+}
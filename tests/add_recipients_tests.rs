@@ -0,0 +1,105 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{inspect, prelude::*};
+
+use crate::common::new_party;
+
+fn open(
+    envelope: &Envelope,
+    private_keys: &PrivateKeys,
+) -> SealedEvent<String> {
+    SealedEvent::try_from_envelope(envelope, None, None, private_keys).unwrap()
+}
+
+#[test]
+fn test_add_recipients_to_event() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (alice, alice_private_keys) = new_party(&mut rng);
+    let (bob, bob_private_keys) = new_party(&mut rng);
+    let (carol, carol_private_keys) = new_party(&mut rng);
+    let (dave, dave_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let original = SealedEvent::<String>::new(
+        "Meeting moved to 3pm".to_string(),
+        id,
+        &sender,
+    )
+    .to_envelope_with_options(
+        None,
+        Some(&sender_private_keys),
+        &[&alice, &bob],
+        &SealOptions::default().with_recipient_hints(true),
+    )
+    .unwrap();
+    assert!(
+        SealedEvent::<String>::try_from_envelope(
+            &original,
+            None,
+            None,
+            &carol_private_keys,
+        )
+        .is_err()
+    );
+
+    // Bob shares the event with Carol, and Carol in turn with Dave, while
+    // the sender is offline.
+    let shared =
+        SealedEvent::add_recipients(&original, &bob_private_keys, &[&carol])
+            .unwrap();
+    let shared =
+        SealedEvent::add_recipients(&shared, &carol_private_keys, &[&dave])
+            .unwrap();
+    assert_eq!(shared.recipients().unwrap().len(), 4);
+    assert_eq!(
+        inspect::recipient_key_fingerprints(&shared).unwrap().len(),
+        4
+    );
+
+    // The encrypted payload, and so the signature inside it, is untouched.
+    assert_eq!(shared.subject().digest(), original.subject().digest());
+    let signed = shared.decrypt_to_recipient(&dave_private_keys).unwrap();
+    signed.verify(sender.verification_key().unwrap()).unwrap();
+
+    for private_keys in [
+        &alice_private_keys,
+        &bob_private_keys,
+        &carol_private_keys,
+        &dave_private_keys,
+    ] {
+        let event = open(&shared, private_keys);
+        assert_eq!(event.id(), id);
+        assert_eq!(event.content(), "Meeting moved to 3pm");
+    }
+    // The original envelope is unchanged.
+    open(&original, &alice_private_keys);
+}
+
+#[test]
+fn test_add_recipients_requires_a_slot() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (alice, _) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    let sealed =
+        SealedEvent::<String>::new("hello".to_string(), ARID::new(), &sender)
+            .to_envelope(None, Some(&sender_private_keys), Some(&alice))
+            .unwrap();
+
+    // The sender did not seal the event to itself, so holds no slot either.
+    for private_keys in [&sender_private_keys, &mallory_private_keys] {
+        assert!(matches!(
+            SealedEvent::add_recipients(&sealed, private_keys, &[&mallory]),
+            Err(Error::NoRecipientSlot)
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{AnonymousEvent, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_anonymous_event_accepted_when_opted_in() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let date = Date::from_string("2024-07-01T12:00:00Z").unwrap();
+    let envelope = AnonymousEvent::<String>::new("Crash report.", id)
+        .with_note("Telemetry")
+        .with_date(date)
+        .to_envelope_anonymous(&[&server])
+        .unwrap();
+
+    let parsed = AnonymousEvent::<String>::try_from_anonymous_envelope(
+        &envelope,
+        &server_private_keys,
+        &ParseOptions::new().with_anonymous_events(true),
+    )
+    .unwrap();
+    assert_eq!(parsed.content(), "Crash report.");
+    assert_eq!(parsed.id(), id);
+    assert_eq!(parsed.note(), "Telemetry");
+    assert_eq!(parsed.date(), Some(date));
+    assert!(parsed.warnings().is_empty());
+}
+
+#[test]
+fn test_anonymous_event_carries_no_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let envelope = AnonymousEvent::<String>::new("Crash report.", ARID::new())
+        .to_envelope_anonymous(&[&server])
+        .unwrap();
+    let message = envelope
+        .decrypt_subject_to_recipient(&server_private_keys)
+        .unwrap()
+        .try_unwrap()
+        .unwrap();
+    assert!(
+        message
+            .optional_assertion_with_predicate(known_values::SENDER)
+            .unwrap()
+            .is_none()
+    );
+    assert!(!message.subject().is_wrapped());
+
+    // Nor can it be mistaken for an authenticated event.
+    assert!(
+        SealedEvent::<String>::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_anonymous_event_rejected_by_default() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let envelope = AnonymousEvent::<String>::new("Crash report.", ARID::new())
+        .to_envelope_anonymous(&[&server])
+        .unwrap();
+    let error = AnonymousEvent::<String>::try_from_anonymous_envelope(
+        &envelope,
+        &server_private_keys,
+        &ParseOptions::new(),
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::AnonymousEventsNotAccepted));
+}
+
+#[test]
+fn test_anonymous_event_requires_recipient() {
+    bc_envelope::register_tags();
+
+    let error = AnonymousEvent::<String>::new("Crash report.", ARID::new())
+        .to_envelope_anonymous(&[])
+        .unwrap_err();
+    assert!(matches!(error, Error::AnonymousEventWithoutRecipients));
+}
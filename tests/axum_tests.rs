@@ -0,0 +1,195 @@
+#![cfg(feature = "axum")]
+
+mod common;
+
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    http::{Request, StatusCode, header::CONTENT_TYPE},
+    response::Response,
+    routing::post,
+};
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    axum::{Gstp, GstpResponse, GstpServerState},
+    http::{self, MEDIA_TYPE},
+    prelude::*,
+};
+use tower::ServiceExt;
+
+use crate::common::new_party;
+
+async fn double(request: Gstp<SealedRequest>) -> GstpResponse {
+    let amount: u32 = request.extract_object_for_parameter("amount").unwrap();
+    let response =
+        SealedResponse::new_success(request.id(), request.identity())
+            .with_result(amount * 2);
+    request.respond(response)
+}
+
+fn router(state: GstpServerState) -> Router {
+    Router::new()
+        .route("/gstp", post(double))
+        .layer(Extension(state))
+}
+
+async fn post_body(
+    router: Router,
+    content_type: &str,
+    body: Vec<u8>,
+) -> (StatusCode, Option<String>, Vec<u8>) {
+    let request = Request::post("/gstp")
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap();
+    let response: Response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, content_type, body.to_vec())
+}
+
+#[tokio::test]
+async fn test_sealed_exchange() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let state = GstpServerState::new(&server, &server_private_keys);
+
+    let id = ARID::new();
+    let sealed = SealedRequest::new("double", id, &client)
+        .with_parameter("amount", 21)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let (status, content_type, body) =
+        post_body(router(state), MEDIA_TYPE, http::to_body(&sealed)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let envelope = http::from_body(content_type.as_deref(), &body).unwrap();
+    let options = ParseOptions::new()
+        .with_expected_id(id)
+        .with_expected_sender(&server)
+        .with_request_digest(sealed.digest());
+    let response = SealedResponse::try_from_encrypted_envelope_opt(
+        &envelope,
+        &options,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.extract_result::<u32>().unwrap(), 42);
+}
+
+#[tokio::test]
+async fn test_verification_failure() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, impostor_private_keys) = new_party(&mut rng);
+    let state = GstpServerState::new(&server, &server_private_keys);
+
+    // Signed with keys other than those of the sender it claims.
+    let sealed = SealedRequest::new("double", ARID::new(), &client)
+        .with_parameter("amount", 21)
+        .to_envelope(None, Some(&impostor_private_keys), Some(&server))
+        .unwrap();
+    let (status, content_type, body) =
+        post_body(router(state), MEDIA_TYPE, http::to_body(&sealed)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The early failure is sealed to the claimed sender, not in plaintext.
+    let envelope = http::from_body(content_type.as_deref(), &body).unwrap();
+    let failure = SealedResponse::try_parse_early_failure(
+        &envelope,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(failure.signature_verified);
+    assert_eq!(failure.claimed_sender, Some(server.xid()));
+}
+
+#[tokio::test]
+async fn test_unauthorized_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (expected, _) = new_party(&mut rng);
+    let state = GstpServerState::new(&server, &server_private_keys)
+        .with_parse_options(
+            ParseOptions::new().with_expected_sender(&expected),
+        );
+
+    let sealed = SealedRequest::new("double", ARID::new(), &client)
+        .with_parameter("amount", 21)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let (status, content_type, body) =
+        post_body(router(state), MEDIA_TYPE, http::to_body(&sealed)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let envelope = http::from_body(content_type.as_deref(), &body).unwrap();
+    SealedResponse::try_parse_early_failure(&envelope, &client_private_keys)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_unreadable_requests() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let state = GstpServerState::new(&server, &server_private_keys);
+    let sealed = SealedRequest::new("double", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+
+    // A body of another content type is refused without being parsed, and a
+    // body that is not an envelope cannot be parsed. Neither names a sender
+    // to encrypt the early failure to, so it is only signed.
+    for (content_type, body, expected_status) in [
+        (
+            "application/cbor",
+            http::to_body(&sealed),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ),
+        (
+            MEDIA_TYPE,
+            b"not an envelope".to_vec(),
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        let (status, response_type, body) =
+            post_body(router(state.clone()), content_type, body).await;
+        assert_eq!(status, expected_status);
+        let envelope =
+            http::from_body(response_type.as_deref(), &body).unwrap();
+        let error = envelope
+            .try_unwrap()
+            .unwrap()
+            .object_for_predicate(known_values::ERROR)
+            .unwrap();
+        assert!(!error.extract_subject::<String>().unwrap().is_empty());
+        assert!(
+            envelope
+                .verify(&server_private_keys.public_keys().unwrap())
+                .is_ok()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_missing_state() {
+    let router = Router::new().route("/gstp", post(double));
+    let (status, _, _) = post_body(router, MEDIA_TYPE, Vec::new()).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_broadcast_event() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (subscriber, subscriber_private_keys) = new_party(&mut rng);
+    let now = Date::from_string("2024-07-01T12:00:00Z").unwrap();
+
+    let id = ARID::new();
+    let event = SealedEvent::<String>::new("Price update", id, &sender)
+        .with_state("sequence 7");
+    let valid_until = Some(now + Duration::from_secs(60));
+
+    // A listener with no keys of its own consumes the broadcast form.
+    let broadcast = event
+        .to_envelope(valid_until, Some(&sender_private_keys), None)
+        .unwrap();
+    let received =
+        SealedEvent::<String>::try_from_signed_envelope(&broadcast, None, None)
+            .unwrap();
+    assert_eq!(received.id(), id);
+    assert_eq!(received.content(), "Price update");
+    assert_eq!(received.sender().xid(), sender.xid());
+    assert!(received.state().is_none());
+    // The sender's continuation is kept, still encrypted, to echo back.
+    let continuation = received.peer_continuation().unwrap();
+    assert!(continuation.subject().is_encrypted());
+
+    // The same event sealed to a subscriber parses to the same thing.
+    let sealed = event
+        .to_envelope(valid_until, Some(&sender_private_keys), Some(&subscriber))
+        .unwrap();
+    let delivered = SealedEvent::<String>::try_from_envelope(
+        &sealed,
+        None,
+        None,
+        &subscriber_private_keys,
+    )
+    .unwrap();
+    assert_eq!(delivered.id(), received.id());
+    assert_eq!(delivered.content(), received.content());
+    assert_eq!(delivered.sender().xid(), received.sender().xid());
+    assert!(delivered.peer_continuation().is_some());
+
+    // An encrypted event is reported as such.
+    assert!(matches!(
+        SealedEvent::<String>::try_from_signed_envelope(&sealed, None, None),
+        Err(Error::UnexpectedEncryption)
+    ));
+}
+
+#[test]
+fn test_broadcast_event_must_be_signed() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, _) = new_party(&mut rng);
+    let (_, impostor_private_keys) = new_party(&mut rng);
+
+    let forged =
+        SealedEvent::<String>::new("Price update", ARID::new(), &sender)
+            .to_envelope(None, Some(&impostor_private_keys), None)
+            .unwrap();
+    assert!(
+        SealedEvent::<String>::try_from_signed_envelope(&forged, None, None)
+            .is_err()
+    );
+    let unsigned =
+        SealedEvent::<String>::new("Price update", ARID::new(), &sender)
+            .to_envelope(None, None, None)
+            .unwrap();
+    assert!(
+        SealedEvent::<String>::try_from_signed_envelope(&unsigned, None, None)
+            .is_err()
+    );
+}
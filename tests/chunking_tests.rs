@@ -0,0 +1,167 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{ChunkingFallback, ResultAssembler, prelude::*};
+
+use crate::common::new_party;
+
+const MAX_SIZE: usize = 4096;
+
+fn parse_all(
+    envelopes: &[Envelope],
+    client_private_keys: &PrivateKeys,
+) -> Vec<SealedResponse> {
+    envelopes
+        .iter()
+        .map(|envelope| {
+            SealedResponse::try_from_encrypted_envelope(
+                envelope,
+                None,
+                None,
+                client_private_keys,
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_result_under_limit_is_sealed_inline() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let envelopes = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("A small result.")
+        .seal_chunked(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new()
+                .with_recipient_max_size(MAX_SIZE)
+                .with_chunking_fallback(ChunkingFallback::Chunk),
+        )
+        .unwrap();
+    assert_eq!(envelopes.len(), 1);
+    assert!(envelopes[0].to_cbor_data().len() <= MAX_SIZE);
+
+    let responses = parse_all(&envelopes, &client_private_keys);
+    let mut assembler = ResultAssembler::new();
+    let result = assembler.add(&responses[0]).unwrap().unwrap();
+    assert_eq!(
+        result.extract_subject::<String>().unwrap(),
+        "A small result."
+    );
+    assert!(!assembler.is_pending());
+}
+
+#[test]
+fn test_result_over_limit_is_chunked() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let large: String = (0..20_000)
+        .map(|i| (b'a' + (i % 26) as u8) as char)
+        .collect();
+    let id = ARID::new();
+    let envelopes = SealedResponse::new_success(id, &server)
+        .with_result(large.clone())
+        .with_state("Server state.")
+        .seal_chunked(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new()
+                .with_recipient_max_size(MAX_SIZE)
+                .with_chunking_fallback(ChunkingFallback::Chunk),
+        )
+        .unwrap();
+    assert!(envelopes.len() > 1);
+    for envelope in &envelopes {
+        assert!(envelope.to_cbor_data().len() <= MAX_SIZE);
+    }
+
+    let responses = parse_all(&envelopes, &client_private_keys);
+    // Only the first response carries the server's continuation.
+    assert!(responses[0].peer_continuation().is_some());
+    assert!(
+        responses[1..]
+            .iter()
+            .all(|r| r.peer_continuation().is_none())
+    );
+
+    // Chunks may arrive in any order.
+    let mut assembler = ResultAssembler::new();
+    let (first, rest) = responses.split_first().unwrap();
+    for response in rest.iter().rev() {
+        assert!(assembler.add(response).unwrap().is_none());
+        assert!(assembler.is_pending());
+    }
+    let result = assembler.add(first).unwrap().unwrap();
+    assert_eq!(result.extract_subject::<String>().unwrap(), large);
+    assert!(!assembler.is_pending());
+
+    // A chunk of another result is rejected.
+    let other = SealedResponse::new_success(ARID::new(), &server)
+        .with_result(large)
+        .seal_chunked(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new()
+                .with_recipient_max_size(MAX_SIZE)
+                .with_chunking_fallback(ChunkingFallback::Chunk),
+        )
+        .unwrap();
+    let other = parse_all(&other, &client_private_keys);
+    let mut assembler = ResultAssembler::new();
+    assembler.add(&responses[0]).unwrap();
+    assert!(matches!(
+        assembler.add(&other[1]),
+        Err(Error::InvalidResultChunk)
+    ));
+}
+
+#[test]
+fn test_result_over_limit_without_chunking_fails() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("x".repeat(20_000));
+    let options = SealOptions::new().with_recipient_max_size(MAX_SIZE);
+    assert!(matches!(
+        response.seal_chunked(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options,
+        ),
+        Err(Error::ResponseTooLarge {
+            limit: MAX_SIZE,
+            ..
+        })
+    ));
+    assert!(matches!(
+        response.to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options.with_chunking_fallback(ChunkingFallback::Chunk),
+        ),
+        Err(Error::ResponseTooLarge {
+            limit: MAX_SIZE,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+#[test]
+fn test_is_valid_date_with_tolerance() {
+    let valid_until = date("2024-07-01T12:00:00Z");
+    let now = Some(date("2024-07-01T12:00:20Z"));
+    let continuation =
+        Continuation::new("Session state.").with_valid_until(valid_until);
+    assert!(!continuation.is_valid_date(now, Duration::ZERO));
+    assert!(!continuation.is_valid_date(now, Duration::from_secs(10)));
+    assert!(continuation.is_valid_date(now, Duration::from_secs(30)));
+
+    // A continuation that does not expire is valid with or without one.
+    let unbounded = Continuation::new("Session state.");
+    assert!(unbounded.is_valid_date(now, Duration::ZERO));
+    assert!(unbounded.is_valid_date(now, Duration::from_secs(30)));
+
+    // The tolerance also applies to a continuation not yet valid.
+    let cooling_down = Continuation::new("Session state.")
+        .with_valid_from(date("2024-07-01T12:00:40Z"));
+    assert!(!cooling_down.is_valid_date(now, Duration::from_secs(10)));
+    assert!(cooling_down.is_valid_date(now, Duration::from_secs(30)));
+}
+
+#[test]
+fn test_parse_with_clock_skew_tolerance() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let continuation = Continuation::new("Server state.")
+        .with_valid_until(date("2024-07-01T12:00:00Z"))
+        .to_envelope(Some(&server_private_keys.public_keys().unwrap()));
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_peer_continuation(continuation)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+
+    // The request arrives 20 seconds after the continuation expired.
+    let options = ParseOptions::new().with_now(date("2024-07-01T12:00:20Z"));
+    let parse = |options: &ParseOptions| {
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            options,
+            &server_private_keys,
+        )
+    };
+
+    assert!(matches!(parse(&options), Err(Error::ContinuationExpired)));
+    assert!(matches!(
+        parse(
+            &options
+                .clone()
+                .with_clock_skew_tolerance(Duration::from_secs(10))
+        ),
+        Err(Error::ContinuationExpired)
+    ));
+    let parsed = parse(
+        &options
+            .clone()
+            .with_clock_skew_tolerance(Duration::from_secs(30)),
+    )
+    .unwrap();
+    assert_eq!(parsed.state().unwrap(), &Envelope::new("Server state."));
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    codec::{self, Encoding},
+    prelude::*,
+};
+
+use crate::common::new_party;
+
+fn sealed_request() -> (Envelope, ARID, PrivateKeys) {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+    let envelope = SealedRequest::new("test", id, &client)
+        .with_parameter("param1", 42)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    (envelope, id, server_private_keys)
+}
+
+#[test]
+fn test_round_trips() {
+    let (sealed, id, server_private_keys) = sealed_request();
+
+    let cbor = codec::to_cbor(&sealed);
+    assert!(codec::from_cbor(&cbor).unwrap().is_identical_to(&sealed));
+
+    let hex = codec::to_hex(&sealed);
+    assert!(hex.starts_with("d8c8"));
+    assert!(codec::from_hex(&hex).unwrap().is_identical_to(&sealed));
+
+    let base64url = codec::to_base64url(&sealed);
+    assert!(!base64url.contains(['+', '/', '=']));
+    let envelope = codec::from_base64url(&base64url).unwrap();
+    assert!(envelope.is_identical_to(&sealed));
+
+    let request = SealedRequest::try_from_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.id(), id);
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<i32>("param1")
+            .unwrap(),
+        42
+    );
+}
+
+#[test]
+fn test_decode_any() {
+    let (sealed, _, _) = sealed_request();
+
+    let cbor = codec::to_cbor(&sealed);
+    let hex = format!("  {}\n", codec::to_hex(&sealed));
+    let base64url = format!("{}\r\n", codec::to_base64url(&sealed));
+    for (input, encoding) in [
+        (cbor.as_slice(), Encoding::Cbor),
+        (hex.as_bytes(), Encoding::Hex),
+        (base64url.as_bytes(), Encoding::Base64Url),
+    ] {
+        assert_eq!(codec::detect(input), Some(encoding));
+        assert!(codec::decode_any(input).unwrap().is_identical_to(&sealed));
+    }
+
+    for input in [&b""[..], b" \n"] {
+        assert_eq!(codec::detect(input), None);
+        assert!(matches!(
+            codec::decode_any(input),
+            Err(Error::UnrecognizedEncoding)
+        ));
+    }
+}
+
+#[test]
+fn test_trailing_bytes() {
+    let (sealed, _, _) = sealed_request();
+
+    let mut cbor = codec::to_cbor(&sealed);
+    cbor.extend_from_slice(&[0x00, 0x01]);
+    assert!(matches!(
+        codec::from_cbor(&cbor),
+        Err(Error::TrailingBytes { count: 2 })
+    ));
+
+    let hex = format!("{}00", codec::to_hex(&sealed));
+    assert!(matches!(
+        codec::from_hex(&hex),
+        Err(Error::TrailingBytes { count: 1 })
+    ));
+
+    let base64url = codec::to_base64url(&sealed);
+    let len = base64url.len();
+    assert!(matches!(
+        codec::decode_any(format!("{base64url}AAAA").as_bytes()),
+        Err(Error::TrailingBytes { count: 3 })
+    ));
+
+    // Strict decoding does not trim whitespace.
+    assert!(matches!(
+        codec::from_base64url(&format!("{base64url}\n")),
+        Err(Error::InvalidEncodedCharacter {
+            encoding: Encoding::Base64Url,
+            position,
+        }) if position == len
+    ));
+}
+
+#[test]
+fn test_invalid_hex() {
+    let (sealed, _, _) = sealed_request();
+    let hex = codec::to_hex(&sealed);
+
+    for (invalid, position) in [
+        (hex.to_uppercase(), 0),
+        (format!("d8c8 {}", &hex[4..]), 4),
+        (format!("{}g0", &hex[..10]), 10),
+    ] {
+        assert!(matches!(
+            codec::from_hex(&invalid),
+            Err(Error::InvalidEncodedCharacter {
+                encoding: Encoding::Hex,
+                position: p,
+            }) if p == position
+        ));
+    }
+
+    assert!(matches!(
+        codec::from_hex(&hex[..hex.len() - 1]),
+        Err(Error::InvalidEncodedLength {
+            encoding: Encoding::Hex,
+            ..
+        })
+    ));
+
+    // Well-formed hex that is not an envelope.
+    assert!(matches!(codec::from_hex("00"), Err(Error::Cbor(_))));
+}
+
+#[test]
+fn test_invalid_base64url() {
+    let (sealed, _, _) = sealed_request();
+    let base64url = codec::to_base64url(&sealed);
+    let standard = base64url.replace('-', "+").replace('_', "/");
+    assert_ne!(standard, base64url);
+
+    for invalid in [
+        standard,
+        format!("{base64url}=="),
+        format!("{}!{}", &base64url[..8], &base64url[9..]),
+    ] {
+        assert!(matches!(
+            codec::from_base64url(&invalid),
+            Err(Error::InvalidEncodedCharacter {
+                encoding: Encoding::Base64Url,
+                ..
+            })
+        ));
+    }
+
+    // A final character with unused bits set is not canonical.
+    assert!(matches!(
+        codec::from_base64url("AB"),
+        Err(Error::InvalidEncodedCharacter {
+            encoding: Encoding::Base64Url,
+            position: 1,
+        })
+    ));
+
+    assert!(matches!(
+        codec::from_base64url(&base64url[..base64url.len() / 4 * 4 + 1]),
+        Err(Error::InvalidEncodedLength {
+            encoding: Encoding::Base64Url,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,35 @@
+//! Fixtures shared by the integration tests.
+
+// Each test binary uses only some of the fixtures.
+#![allow(dead_code)]
+
+use bc_components::{
+    EncapsulationScheme, PrivateKeys, SignatureScheme, keypair_opt_using,
+};
+use bc_rand::RandomNumberGenerator;
+use bc_xid::{XIDDocument, XIDGenesisMarkOptions, XIDInceptionKeyOptions};
+
+/// A new party whose document holds the public and private halves of fresh
+/// keys, returned with its private keys.
+pub fn new_party(
+    rng: &mut impl RandomNumberGenerator,
+) -> (XIDDocument, PrivateKeys) {
+    new_party_signing_with(SignatureScheme::default(), rng)
+}
+
+/// A new party like [`new_party`] whose keys sign with `scheme`.
+pub fn new_party_signing_with(
+    scheme: SignatureScheme,
+    rng: &mut impl RandomNumberGenerator,
+) -> (XIDDocument, PrivateKeys) {
+    let (private_keys, public_keys) =
+        keypair_opt_using(scheme, EncapsulationScheme::default(), rng).unwrap();
+    let document = XIDDocument::new(
+        XIDInceptionKeyOptions::PublicAndPrivateKeys(
+            public_keys,
+            private_keys.clone(),
+        ),
+        XIDGenesisMarkOptions::None,
+    );
+    (document, private_keys)
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_oversized_parameter() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let limits = FieldLimits::new().with_max_parameter_value_size(64);
+    let request = SealedRequest::new("upload", ARID::new(), &client)
+        .with_parameter("name", "small")
+        .with_parameter("blob", "x".repeat(100));
+
+    // The sender catches the oversized parameter before sealing...
+    match request.to_envelope_with_options(
+        None,
+        Some(&client_private_keys),
+        &[&server],
+        &SealOptions::new().with_component_limits(limits.clone()),
+    ) {
+        Err(Error::ComponentTooLarge {
+            component,
+            limit: 64,
+            ..
+        }) => {
+            assert_eq!(component, "parameter \"blob\"")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // ...and the receiver rejects it if it is sealed anyway.
+    let envelope = request
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    SealedRequest::try_from_envelope_opt(
+        &envelope,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    match SealedRequest::try_from_envelope_opt(
+        &envelope,
+        &ParseOptions::new().with_field_limits(limits),
+        &server_private_keys,
+    ) {
+        Err(Error::ComponentTooLarge {
+            component,
+            size,
+            limit: 64,
+        }) => {
+            assert_eq!(component, "parameter \"blob\"");
+            assert!(size > 100);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_oversized_result_and_state() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let limits = FieldLimits::new()
+        .with_max_result_size(64)
+        .with_max_state_size(64);
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("y".repeat(100))
+        .with_state("z".repeat(100));
+
+    match response.to_envelope_with_options(
+        None,
+        Some(&server_private_keys),
+        &[&client],
+        &SealOptions::new().with_component_limits(limits.clone()),
+    ) {
+        Err(Error::ComponentTooLarge { component, .. }) => {
+            assert_eq!(component, "result")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let envelope = response
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    match SealedResponse::try_from_encrypted_envelope_opt(
+        &envelope,
+        &ParseOptions::new().with_field_limits(limits.clone()),
+        &client_private_keys,
+    ) {
+        Err(Error::ComponentTooLarge { component, .. }) => {
+            assert_eq!(component, "result")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // State is checked when the continuation returns to its issuer.
+    let (sealed_request, state_artifacts) = {
+        let request = SealedRequest::new("test", ARID::new(), &client)
+            .with_state("z".repeat(100));
+        let artifacts = request
+            .seal_detailed(
+                None,
+                Some(&client_private_keys),
+                &[&server],
+                &SealOptions::new(),
+            )
+            .unwrap();
+        (request, artifacts)
+    };
+    let received = SealedRequest::try_from_envelope_opt(
+        &state_artifacts.envelope,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    let reply = SealedResponse::new_success(sealed_request.id(), &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(received.peer_continuation().cloned())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    match SealedResponse::try_from_encrypted_envelope_opt(
+        &reply,
+        &ParseOptions::new().with_field_limits(limits),
+        &client_private_keys,
+    ) {
+        Err(Error::ComponentTooLarge { component, .. }) => {
+            assert_eq!(component, "state")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use bc_components::{ARID, Compressed};
+use bc_envelope::prelude::*;
+use bc_rand::{make_fake_random_number_generator, rng_random_data};
+use gstp::{consts, prelude::*};
+
+use crate::common::new_party;
+
+fn seal_response(
+    result: impl EnvelopeEncodable,
+    compression: CompressionPolicy,
+) -> (usize, SealedResponse) {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let response =
+        SealedResponse::new_success(ARID::new(), &server).with_result(result);
+    let options = SealOptions::new().with_compression(compression);
+    let envelope = response
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options,
+        )
+        .unwrap();
+    let size = envelope.to_cbor_data().len();
+
+    let parsed = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    (size, parsed)
+}
+
+#[test]
+fn test_compressible_payload() {
+    let text = "All work and no play makes Jack a dull boy. ".repeat(100);
+
+    let (plain_size, plain) =
+        seal_response(text.clone(), CompressionPolicy::None);
+    let (compressed_size, compressed) =
+        seal_response(text.clone(), CompressionPolicy::Payload);
+
+    assert!(compressed_size * 2 < plain_size);
+    assert_eq!(plain.extract_result::<String>().unwrap(), text);
+    assert_eq!(compressed.extract_result::<String>().unwrap(), text);
+    assert!(!plain.sealing_report().unwrap().compressed);
+    assert!(compressed.sealing_report().unwrap().compressed);
+}
+
+#[test]
+fn test_incompressible_payload_is_stored() {
+    let mut rng = make_fake_random_number_generator();
+    let data = ByteString::from(rng_random_data(&mut rng, 4096));
+
+    let (plain_size, _) = seal_response(data.clone(), CompressionPolicy::None);
+    let (compressed_size, compressed) =
+        seal_response(data.clone(), CompressionPolicy::Payload);
+
+    assert_eq!(compressed_size, plain_size);
+    assert_eq!(compressed.extract_result::<ByteString>().unwrap(), data);
+    assert!(!compressed.sealing_report().unwrap().compressed);
+}
+
+/// Seals a compressed payload declaring `size` bytes, inflating from
+/// `decompressed`, to a fresh party, and returns what parsing it with
+/// `options` gives.
+fn parse_compressed_payload(
+    decompressed: &[u8],
+    size: usize,
+    options: &ParseOptions,
+) -> Result<SealedResponse> {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (_, client_private_keys) = new_party(&mut rng);
+    let client_public_keys = client_private_keys.public_keys().unwrap();
+
+    let data = miniz_oxide::deflate::compress_to_vec(decompressed, 6);
+    let compressed = Compressed::new(
+        bc_crypto::hash::crc32(decompressed),
+        size,
+        data,
+        Some(Envelope::new("anything").digest()),
+    )
+    .unwrap();
+    let envelope = Envelope::try_from(compressed)
+        .unwrap()
+        .encrypt_subject_to_recipient(&client_public_keys)
+        .unwrap();
+    SealedResponse::try_from_encrypted_envelope_opt(
+        &envelope,
+        options,
+        &client_private_keys,
+    )
+}
+
+#[test]
+fn test_decompression_is_bounded() {
+    let bomb = vec![0u8; consts::MAX_DECOMPRESSED_MESSAGE_SIZE + 1];
+
+    // A payload declaring more than the limit is refused before inflating.
+    let result =
+        parse_compressed_payload(&bomb, bomb.len(), &ParseOptions::new());
+    assert!(matches!(
+        result,
+        Err(Error::DecompressedTooLarge { size, limit })
+            if size == bomb.len()
+                && limit == consts::MAX_DECOMPRESSED_MESSAGE_SIZE
+    ));
+
+    // Keys kept from before a rotation are tried only while no recipient
+    // slot opens, so they do not mask the error.
+    let (_, previous_keys) =
+        new_party(&mut make_fake_random_number_generator());
+    let result = parse_compressed_payload(
+        &bomb,
+        bomb.len(),
+        &ParseOptions::new().with_previous_keys([previous_keys]),
+    );
+    assert!(matches!(result, Err(Error::DecompressedTooLarge { .. })));
+
+    // One that understates its size stops inflating at the size declared.
+    let result =
+        parse_compressed_payload(&bomb, 1024 * 1024, &ParseOptions::new());
+    assert!(matches!(result, Err(Error::Components(_))));
+}
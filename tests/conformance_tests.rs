@@ -0,0 +1,179 @@
+#![cfg(feature = "service-adapter")]
+
+mod common;
+
+use bc_components::PrivateKeys;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{
+    FieldLimits,
+    conformance::{Check, ConformanceSuite, Outcome},
+    prelude::*,
+    service::GstpService,
+};
+
+use crate::common::new_party;
+
+/// A loopback server answering `ping`, and `describe` if `describe` is set,
+/// and refusing any other function.
+fn loopback(
+    server: &XIDDocument,
+    server_private_keys: &PrivateKeys,
+    describe: Option<&'static str>,
+) -> GstpService {
+    let identity = server.clone();
+    GstpService::new(server, server_private_keys, 1, move |request| {
+        let server = identity.clone();
+        async move {
+            let id = request.id();
+            let function = request.function().clone();
+            Ok(if function == Function::from("ping") {
+                SealedResponse::new_success(id, &server).with_result("pong")
+            } else if function == Function::from("describe")
+                && let Some(capabilities) = describe
+            {
+                SealedResponse::new_success(id, &server)
+                    .with_result(capabilities)
+            } else {
+                SealedResponse::new_failure(id, &server)
+                    .with_error("unknown function")
+            })
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_conforming_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // A peer checks continuation expiry against the current time only under
+    // the strict policy.
+    let mut transport =
+        loopback(&server, &server_private_keys, Some("ping, describe"))
+            .with_parse_options(
+                ParseOptions::new().with_parse_policy(ParsePolicy::Strict),
+            );
+    let report = ConformanceSuite::new(&client, &client_private_keys, &server)
+        .with_checks(vec![
+            Check::Ping,
+            Check::OversizedMessage(512 * 1024),
+            Check::ExpiredContinuation,
+            Check::UnsupportedVersion,
+            Check::Describe,
+        ])
+        .run(&mut transport)
+        .await;
+
+    assert!(report.passed());
+    for result in &report.results {
+        assert_eq!(result.outcome, Outcome::Pass, "{}", result.check);
+    }
+}
+
+#[tokio::test]
+async fn test_nonconforming_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The peer accepts parameters of any size and, under the lenient policy,
+    // continuations whatever their expiry.
+    let mut transport = loopback(&server, &server_private_keys, Some("ping"))
+        .with_parse_options(ParseOptions::new().with_field_limits(
+            FieldLimits::new().with_max_parameter_value_size(usize::MAX),
+        ));
+    let report = ConformanceSuite::new(&client, &client_private_keys, &server)
+        .with_checks(vec![
+            Check::Ping,
+            Check::OversizedMessage(512 * 1024),
+            Check::ExpiredContinuation,
+            Check::UnsupportedVersion,
+        ])
+        .run(&mut transport)
+        .await;
+
+    assert!(!report.passed());
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].check, Check::OversizedMessage(512 * 1024));
+    assert_eq!(failures[1].check, Check::ExpiredContinuation);
+    assert!(
+        failures
+            .iter()
+            .all(|failure| failure.response_summary.is_some())
+    );
+}
+
+#[tokio::test]
+async fn test_refusal_for_another_reason() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // A peer that refuses everything does not pass the oversized check by
+    // accident.
+    let identity = server.clone();
+    let mut transport =
+        GstpService::new(&server, &server_private_keys, 1, move |request| {
+            let server = identity.clone();
+            async move {
+                Ok(SealedResponse::new_failure(request.id(), &server)
+                    .with_error("down for maintenance"))
+            }
+        })
+        .with_parse_options(ParseOptions::new().with_field_limits(
+            FieldLimits::new().with_max_parameter_value_size(usize::MAX),
+        ));
+    let report = ConformanceSuite::new(&client, &client_private_keys, &server)
+        .with_checks(vec![Check::OversizedMessage(512 * 1024)])
+        .run(&mut transport)
+        .await;
+    assert!(matches!(
+        &report.results[0].outcome,
+        Outcome::Fail(reason) if reason.contains("another reason")
+    ));
+}
+
+#[tokio::test]
+async fn test_describe_is_skipped_if_not_implemented() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let mut transport = loopback(&server, &server_private_keys, None);
+    let report = ConformanceSuite::new(&client, &client_private_keys, &server)
+        .with_checks(vec![Check::Describe])
+        .run(&mut transport)
+        .await;
+    assert!(report.passed());
+    assert!(matches!(report.results[0].outcome, Outcome::Skipped(_)));
+}
+
+#[tokio::test]
+async fn test_impostor_peer_fails_ping() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (impostor, impostor_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The transport leads to an impostor rather than the server.
+    let mut transport = loopback(&impostor, &impostor_private_keys, None);
+    let report = ConformanceSuite::new(&client, &client_private_keys, &server)
+        .with_checks(vec![Check::Ping])
+        .run(&mut transport)
+        .await;
+    assert!(!report.passed());
+}
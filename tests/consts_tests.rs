@@ -0,0 +1,87 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{consts, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_defaults_match_consts() {
+    assert_eq!(ParseLimits::default(), consts::DEFAULT_PARSE_LIMITS);
+    assert_eq!(FieldLimits::default(), consts::DEFAULT_FIELD_LIMITS);
+
+    let parse_options = ParseOptions::default();
+    assert_eq!(parse_options.field_limits(), &consts::DEFAULT_FIELD_LIMITS);
+    assert_eq!(
+        parse_options.max_peer_continuation_lifetime(),
+        consts::DEFAULT_CONTINUATION_POLICY.max_peer_lifetime
+    );
+    assert_eq!(
+        SealOptions::default().continuation_expiry_hints(),
+        consts::DEFAULT_CONTINUATION_POLICY.expiry_hints
+    );
+    assert_eq!(
+        SealOptions::default().date_precision(),
+        consts::DEFAULT_DATE_PRECISION
+    );
+
+    assert!(
+        consts::SUPPORTED_PROTOCOL_VERSIONS.contains(&consts::PROTOCOL_VERSION)
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_describe_defaults() {
+    let json = serde_json::to_value(consts::describe_defaults()).unwrap();
+    assert_eq!(json["protocol_version"], consts::PROTOCOL_VERSION);
+    assert_eq!(
+        json["max_message_size"],
+        consts::DEFAULT_PARSE_LIMITS.max_message_size()
+    );
+    assert_eq!(json["duplicate_assertions"], "Reject");
+    assert_eq!(
+        json["max_peer_continuation_lifetime"],
+        serde_json::Value::Null
+    );
+}
+
+#[test]
+fn test_field_limits() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_note("A note of some length.")
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parse = |limits: FieldLimits| {
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            &ParseOptions::new().with_field_limits(limits),
+            &server_private_keys,
+        )
+    };
+
+    parse(FieldLimits::default()).unwrap();
+    assert!(matches!(
+        parse(FieldLimits::new().with_max_note_length(8)),
+        Err(Error::FieldTooLarge {
+            field: "note",
+            size: 22,
+            limit: 8
+        })
+    ));
+    assert!(matches!(
+        parse(FieldLimits::new().with_max_assertions(1)),
+        Err(Error::FieldTooLarge {
+            field: "assertions",
+            ..
+        })
+    ));
+}
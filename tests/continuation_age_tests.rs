@@ -0,0 +1,123 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+#[test]
+fn test_issued_at_recorded_when_sealing() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let sealed_at = date("2024-07-01T12:00:00Z");
+    let artifacts = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("Client state.")
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::new().with_now(sealed_at),
+        )
+        .unwrap();
+    let receipt = artifacts.continuation_receipt.unwrap();
+    assert_eq!(receipt.issued_at, Some(sealed_at));
+    let round_tripped =
+        ContinuationReceipt::try_from(Envelope::from(receipt.clone())).unwrap();
+    assert_eq!(round_tripped, receipt);
+
+    let continuation = Continuation::try_from_envelope(
+        &artifacts.own_continuation.unwrap(),
+        None,
+        None,
+        Some(&client_private_keys),
+    )
+    .unwrap();
+    assert_eq!(continuation.issued_at(), Some(sealed_at));
+    assert_eq!(
+        continuation.age(date("2024-07-01T12:05:00Z")),
+        Some(Duration::from_secs(300))
+    );
+    // A clock running behind the issuer's sees an age of zero.
+    assert_eq!(
+        continuation.age(date("2024-07-01T11:00:00Z")),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(Continuation::new("No date.").age(sealed_at), None);
+}
+
+#[test]
+fn test_max_continuation_age_ignores_sliding_validity() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server keeps extending the validity of a session continuation but
+    // carries its original issue time forward.
+    let issued_at = date("2024-07-01T12:00:00Z");
+    let continuation = |valid_until: Date| {
+        Continuation::new("Session state.")
+            .with_issued_at(issued_at)
+            .with_valid_until(valid_until)
+            .to_envelope(Some(&server_private_keys.public_keys().unwrap()))
+    };
+    let parse =
+        |peer_continuation: &Envelope, now: Date, max_age: Option<Duration>| {
+            let request = SealedRequest::new("test", ARID::new(), &client)
+                .with_peer_continuation(peer_continuation.clone())
+                .to_envelope(None, Some(&client_private_keys), Some(&server))
+                .unwrap();
+            let mut options = ParseOptions::new().with_now(now);
+            if let Some(max_age) = max_age {
+                options = options.with_max_continuation_age(max_age);
+            }
+            SealedRequest::try_from_envelope_opt(
+                &request,
+                &options,
+                &server_private_keys,
+            )
+        };
+
+    let extended = continuation(date("2024-07-02T12:00:00Z"));
+    let max_age = Some(Duration::from_secs(3600));
+
+    // Without a maximum age, extended validity keeps the state alive.
+    parse(&extended, date("2024-07-01T18:00:00Z"), None).unwrap();
+
+    // At exactly the maximum age the continuation is still accepted...
+    parse(&extended, date("2024-07-01T13:00:00Z"), max_age).unwrap();
+
+    // ...but one second later it is rejected, although still valid.
+    assert!(matches!(
+        parse(&extended, date("2024-07-01T13:00:01Z"), max_age),
+        Err(Error::ContinuationTooOld { age, .. })
+            if age == Duration::from_secs(3601)
+    ));
+
+    // Expiry is still enforced within the maximum age.
+    let expiring = continuation(date("2024-07-01T12:30:00Z"));
+    assert!(matches!(
+        parse(&expiring, date("2024-07-01T12:45:00Z"), max_age),
+        Err(Error::ContinuationExpired)
+    ));
+
+    // A continuation without an issue time cannot satisfy a maximum age.
+    let undated = Continuation::new("Session state.")
+        .to_envelope(Some(&server_private_keys.public_keys().unwrap()));
+    parse(&undated, issued_at, None).unwrap();
+    assert!(matches!(
+        parse(&undated, issued_at, max_age),
+        Err(Error::ContinuationAgeUnknown)
+    ));
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+#[test]
+fn test_expired_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let envelope = Continuation::new("cursor")
+        .with_valid_until(date("2024-01-01"))
+        .to_envelope(Some(server.encryption_key().unwrap()));
+    let error = Continuation::try_from_envelope(
+        &envelope,
+        None,
+        Some(date("2024-01-02")),
+        Some(&server_private_keys),
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::ContinuationExpired));
+}
+
+#[test]
+fn test_continuation_for_another_request() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let envelope = Continuation::new("cursor")
+        .with_valid_id(ARID::new())
+        .to_envelope(Some(server.encryption_key().unwrap()));
+    let error = Continuation::try_from_envelope(
+        &envelope,
+        Some(ARID::new()),
+        None,
+        Some(&server_private_keys),
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::ContinuationIdInvalid));
+}
+
+#[test]
+fn test_unencrypted_continuation_with_recipient() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (_, server_private_keys) = new_party(&mut rng);
+
+    let envelope = Continuation::new("cursor").to_envelope(None);
+    let error = Continuation::try_from_envelope(
+        &envelope,
+        None,
+        None,
+        Some(&server_private_keys),
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::ContinuationNotEncrypted));
+
+    // Without a recipient, the unencrypted continuation is accepted.
+    let continuation =
+        Continuation::try_from_envelope(&envelope, None, None, None).unwrap();
+    assert_eq!(
+        continuation.state().extract_subject::<String>().unwrap(),
+        "cursor"
+    );
+}
+
+#[test]
+fn test_continuation_not_yet_valid() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let envelope = Continuation::new("cursor")
+        .with_valid_from(date("2024-01-02"))
+        .with_valid_until(date("2024-01-03"))
+        .to_envelope(Some(server.encryption_key().unwrap()));
+    let parse = |now: &str| {
+        Continuation::try_from_envelope(
+            &envelope,
+            None,
+            Some(date(now)),
+            Some(&server_private_keys),
+        )
+    };
+    assert!(matches!(
+        parse("2024-01-01").unwrap_err(),
+        Error::ContinuationNotYetValid
+    ));
+    let continuation = parse("2024-01-02").unwrap();
+    assert_eq!(continuation.valid_from(), Some(date("2024-01-02")));
+    assert!(matches!(
+        parse("2024-01-03").unwrap_err(),
+        Error::ContinuationExpired
+    ));
+}
+
+#[test]
+fn test_response_continuation_cool_down() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server hands back state the client may only return after a
+    // minute.
+    let now = date("2024-01-01T12:00:00Z");
+    let cool_down = date("2024-01-01T12:01:00Z");
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state("retry")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new().with_now(now).with_valid_from(cool_down),
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+
+    let request = SealedRequest::new("retry", ARID::new(), &client)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parse = |now: &str| {
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            Some(date(now)),
+            &server_private_keys,
+        )
+    };
+    assert!(matches!(
+        parse("2024-01-01T12:00:30Z").unwrap_err(),
+        Error::ContinuationNotYetValid
+    ));
+    let request = parse("2024-01-01T12:01:00Z").unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "retry"
+    );
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+use indoc::indoc;
+
+use crate::common::new_party;
+
+#[test]
+fn test_strip_filter_redacts_state() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let state = Expression::new("nextPage")
+        .with_parameter("fromRecord", 200)
+        .into_envelope()
+        .add_assertion(known_values::NOTE, "Customer asked about refunds.");
+    let options = SealOptions::new().with_continuation_filter(Arc::new(
+        AssertionStripFilter::new([known_values::NOTE]),
+    ));
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .with_state(state)
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options,
+        )
+        .unwrap();
+
+    // The client echoes the server's continuation back in its next request.
+    let parsed_response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    let request = SealedRequest::new("nextPage", ARID::new(), &client)
+        .with_optional_peer_continuation(
+            parsed_response.peer_continuation().cloned(),
+        )
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed_request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+
+    #[rustfmt::skip]
+    assert_eq!(parsed_request.state().unwrap().format(), (indoc! {r#"
+        «"nextPage"» [
+            ❰"fromRecord"❱: 200
+        ]
+    "#}).trim());
+}
+
+#[derive(Debug)]
+struct RejectAllFilter;
+
+impl ContinuationFilter for RejectAllFilter {
+    fn filter(
+        &self,
+        context: &ContinuationContext<'_>,
+        _state: Envelope,
+    ) -> gstp::Result<Envelope> {
+        assert_eq!(context.kind, MessageKind::Request);
+        assert_eq!(context.function, Some(&Function::from("search")));
+        Err(Error::ContinuationRejectedByFilter("not reviewed".into()))
+    }
+}
+
+#[test]
+fn test_rejecting_filter_aborts_sealing() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let options =
+        SealOptions::new().with_continuation_filter(Arc::new(RejectAllFilter));
+    let result = SealedRequest::new("search", ARID::new(), &client)
+        .with_state("Private cursor.")
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &options,
+        );
+    assert!(matches!(
+        result,
+        Err(Error::ContinuationRejectedByFilter(reason)) if reason == "not reviewed"
+    ));
+}
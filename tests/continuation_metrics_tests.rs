@@ -0,0 +1,119 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{MemoryContinuationMetrics, RejectionReason, prelude::*};
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+#[test]
+fn test_continuation_metrics() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let client_metrics = Arc::new(MemoryContinuationMetrics::new());
+    let server_metrics = Arc::new(MemoryContinuationMetrics::new());
+
+    let start = date("2024-07-01T12:00:00Z");
+    let client_seal = SealOptions::new()
+        .with_now(start)
+        .with_continuation_metrics(client_metrics.clone());
+    let server_seal = SealOptions::new()
+        .with_now(start)
+        .with_continuation_metrics(server_metrics.clone());
+
+    // The client asks for a listing; the server answers with a continuation
+    // valid for a minute.
+    let request = SealedRequest::new("listRecords", ARID::new(), &client)
+        .to_envelope_with_options(
+            Some(start + Duration::from_secs(30)),
+            Some(&client_private_keys),
+            &[&server],
+            &client_seal,
+        )
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        Some(start),
+        &server_private_keys,
+    )
+    .unwrap();
+    let response = SealedResponse::new_success(request.id(), &server)
+        .with_result("Records 0-9")
+        .with_state(10)
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope_with_options(
+            Some(start + Duration::from_secs(60)),
+            Some(&server_private_keys),
+            &[&client],
+            &server_seal,
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new()
+            .with_expected_id(request.id())
+            .with_now(start + Duration::from_secs(5))
+            .with_continuation_metrics(client_metrics.clone()),
+        &client_private_keys,
+    )
+    .unwrap();
+
+    // The client returns the server's continuation twice: in time, and
+    // again after it has expired.
+    let server_parse = |now: Date| {
+        ParseOptions::new()
+            .with_now(now)
+            .with_continuation_metrics(server_metrics.clone())
+    };
+    for (now, accepted) in [
+        (start + Duration::from_secs(10), true),
+        (start + Duration::from_secs(120), false),
+    ] {
+        let next = SealedRequest::new("nextPage", ARID::new(), &client)
+            .with_peer_continuation(
+                response.peer_continuation().unwrap().clone(),
+            )
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        let result = SealedRequest::try_from_envelope_opt(
+            &next,
+            &server_parse(now),
+            &server_private_keys,
+        );
+        assert_eq!(result.is_ok(), accepted);
+    }
+
+    let client_stats = client_metrics.snapshot();
+    assert_eq!(client_stats.len(), 2);
+    // The continuation returned in the response is counted without a
+    // function.
+    assert_eq!(client_stats[0].function, None);
+    assert_eq!(client_stats[0].returned, 1);
+    assert_eq!(client_stats[0].total_age, Duration::from_secs(5));
+    assert_eq!(
+        client_stats[1].function,
+        Some(Function::from("listRecords"))
+    );
+    assert_eq!(client_stats[1].issued, 1);
+    assert_eq!(client_stats[1].total_lifetime, Duration::from_secs(30));
+
+    let server_stats = server_metrics.snapshot();
+    assert_eq!(server_stats.len(), 2);
+    assert_eq!(server_stats[0].function, None);
+    assert_eq!(server_stats[0].issued, 1);
+    assert_eq!(server_stats[0].total_lifetime, Duration::from_secs(60));
+    assert_eq!(server_stats[1].function, Some(Function::from("nextPage")));
+    assert_eq!(server_stats[1].returned, 1);
+    assert_eq!(server_stats[1].total_age, Duration::from_secs(10));
+    assert_eq!(server_stats[1].total_rejected(), 1);
+    assert_eq!(server_stats[1].rejected[&RejectionReason::Expired], 1);
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{MemoryContinuationStorage, PeerContinuationRef, prelude::*};
+
+use crate::common::new_party;
+
+/// Has the server issue a continuation in a response to the client,
+/// returning the continuation as the client received it.
+fn issue_continuation(
+    server: &XIDDocument,
+    server_private_keys: &PrivateKeys,
+    client: &XIDDocument,
+) -> Envelope {
+    SealedResponse::new_success(ARID::new(), server)
+        .with_result("OK")
+        .with_state("Server state.")
+        .seal_detailed(
+            None,
+            Some(server_private_keys),
+            &[client],
+            &SealOptions::default(),
+        )
+        .unwrap()
+        .own_continuation
+        .unwrap()
+}
+
+fn referring_request(
+    client: &XIDDocument,
+    client_private_keys: &PrivateKeys,
+    server: &XIDDocument,
+    reference: PeerContinuationRef,
+) -> Envelope {
+    SealedRequest::new("next", ARID::new(), client)
+        .with_peer_continuation_ref(reference)
+        .to_envelope(None, Some(client_private_keys), Some(server))
+        .unwrap()
+}
+
+#[test]
+fn test_continuation_ref_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let storage = Arc::new(MemoryContinuationStorage::new());
+    let handle = ARID::new();
+    storage.insert(handle, continuation.clone());
+
+    let reference =
+        PeerContinuationRef::for_continuation(handle, &continuation);
+    let request = referring_request(
+        &client,
+        &client_private_keys,
+        &server,
+        reference.clone(),
+    );
+
+    let parsed = SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new().with_continuation_storage(storage),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.state().unwrap().format_flat(), "\"Server state.\"");
+    assert_eq!(
+        PeerContinuationRef::try_from(Envelope::from(reference.clone()))
+            .unwrap(),
+        reference
+    );
+}
+
+#[test]
+fn test_continuation_ref_is_smaller() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let by_value = SealedRequest::new("next", ARID::new(), &client)
+        .with_peer_continuation(continuation.clone())
+        .to_envelope(None, Some(&client_private_keys), None)
+        .unwrap();
+    let by_reference = SealedRequest::new("next", ARID::new(), &client)
+        .with_peer_continuation_ref(PeerContinuationRef::for_continuation(
+            ARID::new(),
+            &continuation,
+        ))
+        .to_envelope(None, Some(&client_private_keys), None)
+        .unwrap();
+    assert!(by_reference.to_cbor_data().len() < by_value.to_cbor_data().len());
+}
+
+#[test]
+fn test_continuation_ref_unknown_handle() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let request = referring_request(
+        &client,
+        &client_private_keys,
+        &server,
+        PeerContinuationRef::for_continuation(ARID::new(), &continuation),
+    );
+
+    let error = SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new().with_continuation_storage(Arc::new(
+            MemoryContinuationStorage::new(),
+        )),
+        &server_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::UnknownContinuationHandle));
+
+    // Without storage, references are not accepted at all.
+    let error = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::ContinuationRefUnsupported));
+}
+
+#[test]
+fn test_continuation_ref_stale_digest() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let old_continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let new_continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let storage = Arc::new(MemoryContinuationStorage::new());
+    let handle = ARID::new();
+    // The server has since replaced the continuation under the handle.
+    storage.insert(handle, new_continuation);
+
+    let request = referring_request(
+        &client,
+        &client_private_keys,
+        &server,
+        PeerContinuationRef::for_continuation(handle, &old_continuation),
+    );
+    let error = SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new().with_continuation_storage(storage),
+        &server_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::ContinuationDigestMismatch));
+}
+
+#[test]
+fn test_continuation_and_reference_conflict() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let continuation =
+        issue_continuation(&server, &server_private_keys, &client);
+    let storage = Arc::new(MemoryContinuationStorage::new());
+    let handle = ARID::new();
+    storage.insert(handle, continuation.clone());
+    let reference =
+        PeerContinuationRef::for_continuation(handle, &continuation);
+
+    // Setting the continuation, even as an option, replaces a reference.
+    let request = SealedRequest::new("next", ARID::new(), &client)
+        .with_peer_continuation_ref(reference.clone())
+        .with_optional_peer_continuation(Some(continuation.clone()));
+    assert!(request.peer_continuation_ref().is_none());
+
+    // A message returning both is refused rather than one being chosen.
+    let signed = SealedRequest::new("next", ARID::new(), &client)
+        .with_peer_continuation_ref(reference)
+        .to_envelope(None, Some(&client_private_keys), None)
+        .unwrap();
+    let both = signed
+        .try_unwrap()
+        .unwrap()
+        .add_assertion(known_values::RECIPIENT_CONTINUATION, continuation)
+        .sign(&client_private_keys);
+    assert!(matches!(
+        SealedRequest::try_from_signed_envelope_opt(
+            &both,
+            &ParseOptions::new().with_continuation_storage(storage),
+            Some(&server_private_keys),
+        ),
+        Err(Error::ConflictingRecipientContinuations)
+    ));
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{inspect, prelude::*};
+
+use crate::common::new_party;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Has the server answer with a continuation valid until `valid_until`, and
+/// has the client parse the response, capping retention at a week.
+fn client_retention(
+    valid_until: Option<Date>,
+    expiry_hints: bool,
+    now: Date,
+) -> (SealedResponse, Option<Date>) {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .with_state("Server state.")
+        .to_envelope_with_options(
+            valid_until,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new().with_continuation_expiry_hints(expiry_hints),
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new()
+            .with_now(now)
+            .with_max_peer_continuation_lifetime(7 * DAY),
+        &client_private_keys,
+    )
+    .unwrap();
+    let retain_until = response.peer_continuation_retain_until();
+    (response, retain_until)
+}
+
+#[test]
+fn test_hint_within_cap() {
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let valid_until = now + DAY;
+    let (response, retain_until) =
+        client_retention(Some(valid_until), true, now);
+    assert_eq!(
+        inspect::continuation_expiry_hint(
+            response.peer_continuation().unwrap()
+        )
+        .unwrap(),
+        Some(valid_until)
+    );
+    assert_eq!(retain_until, Some(valid_until));
+}
+
+#[test]
+fn test_hint_beyond_cap_is_clamped() {
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let (_, retain_until) = client_retention(Some(now + 3650 * DAY), true, now);
+    assert_eq!(retain_until, Some(now + 7 * DAY));
+}
+
+#[test]
+fn test_no_hint_imposes_cap() {
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let (response, retain_until) =
+        client_retention(Some(now + DAY), false, now);
+    assert_eq!(
+        inspect::continuation_expiry_hint(
+            response.peer_continuation().unwrap()
+        )
+        .unwrap(),
+        None
+    );
+    assert_eq!(retain_until, Some(now + 7 * DAY));
+}
+
+#[test]
+fn test_hint_does_not_affect_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server's continuation, hint and all, still decrypts when the client
+    // sends it back.
+    let now = Date::from_ymd(2026, 1, 1);
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .with_state("Server state.")
+        .to_envelope_with_options(
+            Some(now + DAY),
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new().with_continuation_expiry_hints(true),
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        Some(now),
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.peer_continuation_retain_until(), Some(now + DAY));
+
+    let request = SealedRequest::new("next", ARID::new(), &client)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        Some(now),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "Server state."
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{ContinuationStack, prelude::*};
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+struct Parties {
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+}
+
+impl Parties {
+    fn new() -> Self {
+        let mut rng = make_fake_random_number_generator();
+        let (server, server_private_keys) = new_party(&mut rng);
+        let (client, client_private_keys) = new_party(&mut rng);
+        Self {
+            client,
+            client_private_keys,
+            server,
+            server_private_keys,
+        }
+    }
+
+    /// Sends `request` with its state sealed until `valid_until`, has the
+    /// server return the continuation, and parses the response at `now`.
+    fn round_trip(
+        &self,
+        request: SealedRequest,
+        valid_until: Date,
+        now: Date,
+    ) -> gstp::Result<SealedResponse> {
+        let continuation = request
+            .seal_detailed(
+                Some(valid_until),
+                Some(&self.client_private_keys),
+                &[&self.server],
+                &SealOptions::new(),
+            )?
+            .own_continuation
+            .unwrap();
+        let response = SealedResponse::new_success(request.id(), &self.server)
+            .with_peer_continuation(continuation)
+            .to_envelope(
+                None,
+                Some(&self.server_private_keys),
+                Some(&self.client),
+            )?;
+        SealedResponse::try_from_encrypted_envelope(
+            &response,
+            Some(request.id()),
+            Some(now),
+            &self.client_private_keys,
+        )
+    }
+}
+
+#[test]
+fn test_stack_encoding() {
+    let stack = ContinuationStack::new()
+        .with_pushed("outer")
+        .with_pushed("middle")
+        .with_pushed("inner");
+    assert_eq!(stack.len(), 3);
+    assert_eq!(stack.top(), Some(&Envelope::new("inner")));
+    assert_eq!(
+        ContinuationStack::from_state(&stack.to_state()).unwrap(),
+        stack
+    );
+
+    // State that is not a stack is a stack of one layer.
+    let plain = Envelope::new("plain");
+    let stack = ContinuationStack::from_state(&plain).unwrap();
+    assert_eq!(stack.layers(), &[plain]);
+}
+
+#[test]
+fn test_three_deep_push_pop_round_trip() {
+    bc_envelope::register_tags();
+
+    let parties = Parties::new();
+    let valid_until = date("2030-01-01");
+    let now = date("2029-01-01");
+    let layers = ["checkout", "payment", "confirmation"];
+
+    // Each step of the workflow nests its state within that of the last.
+    let mut state: Option<Envelope> = None;
+    for layer in layers {
+        let request = SealedRequest::new("step", ARID::new(), &parties.client)
+            .with_optional_state(state)
+            .with_pushed_state(layer)
+            .unwrap();
+        let response = parties.round_trip(request, valid_until, now).unwrap();
+        state = response.state().cloned();
+    }
+    let stack = ContinuationStack::from_state(state.as_ref().unwrap()).unwrap();
+    assert_eq!(stack.len(), 3);
+
+    // Each step pops its state on completion, returning to the one before.
+    for layer in layers.iter().rev() {
+        let request = SealedRequest::new("step", ARID::new(), &parties.client)
+            .with_optional_state(state);
+        let response = parties.round_trip(request, valid_until, now).unwrap();
+        let (top, rest) = response.popped_state().unwrap().unwrap();
+        assert_eq!(top, Envelope::new(*layer));
+        state = rest;
+    }
+    assert!(state.is_none());
+}
+
+#[test]
+fn test_stack_expires_with_continuation() {
+    bc_envelope::register_tags();
+
+    let parties = Parties::new();
+    let request = SealedRequest::new("step", ARID::new(), &parties.client)
+        .with_pushed_state("outer")
+        .unwrap()
+        .with_pushed_state("middle")
+        .unwrap()
+        .with_pushed_state("inner")
+        .unwrap();
+
+    // Every layer shares the expiry of the continuation that carries them.
+    assert!(matches!(
+        parties.round_trip(request, date("2030-01-01"), date("2030-06-01"),),
+        Err(Error::ContinuationExpired)
+    ));
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use bc_components::SymmetricKey;
+use bc_envelope::prelude::*;
+use gstp::{export_continuation, import_continuation, prelude::*};
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+fn peer_continuation() -> Envelope {
+    Continuation::new("Server state.")
+        .to_envelope(None)
+        .encrypt_subject(&SymmetricKey::new())
+        .unwrap()
+}
+
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[test]
+fn test_continuation_storage_round_trip() {
+    bc_envelope::register_tags();
+
+    let key = SymmetricKey::new();
+    let continuation = peer_continuation();
+    let exported_at = date("2024-07-01T12:00:00Z");
+    let data = export_continuation(&continuation, &key, exported_at);
+
+    let imported =
+        import_continuation(&data, &key, date("2024-07-01T18:00:00Z"), MAX_AGE)
+            .unwrap();
+    assert!(imported.is_identical_to(&continuation));
+}
+
+#[test]
+fn test_continuation_storage_detects_tampering() {
+    bc_envelope::register_tags();
+
+    let key = SymmetricKey::new();
+    let now = date("2024-07-01T12:00:00Z");
+    let data = export_continuation(&peer_continuation(), &key, now);
+
+    for i in 0..data.len() {
+        let mut flipped = data.clone();
+        flipped[i] ^= 0x01;
+        assert!(
+            matches!(
+                import_continuation(&flipped, &key, now, MAX_AGE),
+                Err(Error::StoredContinuationTampered)
+            ),
+            "bit flip at byte {} was not detected",
+            i
+        );
+    }
+
+    assert!(matches!(
+        import_continuation(&data[..data.len() - 1], &key, now, MAX_AGE),
+        Err(Error::StoredContinuationTampered)
+    ));
+    assert!(matches!(
+        import_continuation(&data, &SymmetricKey::new(), now, MAX_AGE),
+        Err(Error::StoredContinuationTampered)
+    ));
+}
+
+#[test]
+fn test_continuation_storage_rejects_stale_import() {
+    bc_envelope::register_tags();
+
+    let key = SymmetricKey::new();
+    let data = export_continuation(
+        &peer_continuation(),
+        &key,
+        date("2024-07-01T12:00:00Z"),
+    );
+
+    import_continuation(&data, &key, date("2024-07-02T12:00:00Z"), MAX_AGE)
+        .unwrap();
+    assert!(matches!(
+        import_continuation(&data, &key, date("2024-07-02T12:00:01Z"), MAX_AGE),
+        Err(Error::StoredContinuationStale { age, max_age: MAX_AGE })
+            if age == Duration::from_secs(24 * 60 * 60 + 1)
+    ));
+}
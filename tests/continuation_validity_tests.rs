@@ -0,0 +1,96 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+#[test]
+fn test_remaining_validity() {
+    let valid_until = date("2024-07-01T12:00:00Z");
+    let continuation =
+        Continuation::new("Session state.").with_valid_until(valid_until);
+    assert_eq!(
+        continuation.remaining_validity(date("2024-07-01T11:59:00Z")),
+        Some(Duration::from_secs(60))
+    );
+    // An expired continuation has no validity remaining.
+    assert_eq!(
+        continuation.remaining_validity(date("2024-07-01T12:01:00Z")),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(
+        Continuation::new("No expiry.").remaining_validity(valid_until),
+        None
+    );
+}
+
+#[test]
+fn test_parsed_messages_expose_continuation_validity() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let now = date("2024-07-01T12:00:00Z");
+    let valid_until = date("2024-07-01T13:00:00Z");
+    let options = ParseOptions::new().with_now(now);
+
+    // The client's continuation comes back to it in the server's response.
+    let request_id = ARID::new();
+    let client_continuation = Continuation::new("Client state.")
+        .with_valid_id(request_id)
+        .with_valid_until(valid_until)
+        .to_envelope(Some(&client_private_keys.public_keys().unwrap()));
+    let response = SealedResponse::new_success(request_id, &server)
+        .with_peer_continuation(client_continuation)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parsed_response = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &options,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        parsed_response.continuation_valid_until(),
+        Some(valid_until)
+    );
+    assert_eq!(parsed_response.continuation_id(), Some(request_id));
+
+    // The server's continuation comes back to it in the client's request.
+    let server_continuation = Continuation::new("Server state.")
+        .with_valid_until(valid_until)
+        .to_envelope(Some(&server_private_keys.public_keys().unwrap()));
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_peer_continuation(server_continuation)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed_request = SealedRequest::try_from_envelope_opt(
+        &request,
+        &options,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed_request.continuation_valid_until(), Some(valid_until));
+    assert_eq!(parsed_request.continuation_id(), None);
+
+    // Without a returned continuation there is nothing to report.
+    let bare = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed_bare = SealedRequest::try_from_envelope_opt(
+        &bare,
+        &options,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed_bare.continuation_valid_until(), None);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{inspect, prelude::*};
+
+use crate::common::new_party;
+
+/// The same instant, parsed from a string and computed by adding a duration
+/// carrying sub-second precision.
+fn dates() -> (Date, Date) {
+    let parsed = Date::try_from("2026-01-01T12:00:00Z").unwrap();
+    let computed = Date::from_ymd(2026, 1, 1)
+        + Duration::from_millis(12 * 3600 * 1000 + 250);
+    (parsed, computed)
+}
+
+#[test]
+fn test_normalize() {
+    let (parsed, computed) = dates();
+    assert_ne!(parsed, computed);
+    assert_eq!(DatePrecision::default(), DatePrecision::Seconds);
+    assert_eq!(DatePrecision::Seconds.normalize(computed), parsed);
+    assert_eq!(
+        DatePrecision::Milliseconds
+            .normalize(computed + Duration::from_nanos(7)),
+        computed
+    );
+}
+
+#[test]
+fn test_dates_have_stable_digests() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+    let options = SealOptions::new().with_continuation_expiry_hints(true);
+
+    let seal = |date: Date| {
+        let artifacts = SealedRequest::new("test", id, &client)
+            .with_date(date)
+            .seal_detailed(
+                Some(date),
+                Some(&client_private_keys),
+                &[&server],
+                &options,
+            )
+            .unwrap();
+        let request = SealedRequest::try_from_envelope(
+            &artifacts.envelope,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .unwrap();
+        let expiry = artifacts.own_continuation.unwrap();
+        (request, expiry)
+    };
+
+    let (parsed, computed) = dates();
+    let (from_parsed, parsed_expiry) = seal(parsed);
+    let (from_computed, computed_expiry) = seal(computed);
+
+    assert_eq!(from_computed.date(), Some(parsed));
+    assert_eq!(
+        from_parsed.request().clone().into_envelope().digest(),
+        from_computed.request().clone().into_envelope().digest()
+    );
+    assert_eq!(
+        inspect::continuation_expiry_hint(&computed_expiry).unwrap(),
+        Some(parsed)
+    );
+    assert_eq!(
+        inspect::continuation_expiry_hint(&parsed_expiry).unwrap(),
+        inspect::continuation_expiry_hint(&computed_expiry).unwrap()
+    );
+}
+
+#[test]
+fn test_event_dates_have_stable_digests() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+
+    let seal = |date: Date| {
+        let sealed = SealedEvent::<String>::new("Tick.", id, &sender)
+            .with_date(date)
+            .to_envelope(None, Some(&sender_private_keys), Some(&recipient))
+            .unwrap();
+        SealedEvent::<String>::try_from_envelope(
+            &sealed,
+            None,
+            None,
+            &recipient_private_keys,
+        )
+        .unwrap()
+    };
+
+    let (parsed, computed) = dates();
+    let from_computed = seal(computed);
+    assert_eq!(from_computed.date(), Some(parsed));
+    assert_eq!(
+        seal(parsed).event().clone().into_envelope().digest(),
+        from_computed.event().clone().into_envelope().digest()
+    );
+}
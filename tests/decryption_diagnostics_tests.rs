@@ -0,0 +1,116 @@
+mod common;
+
+use bc_components::{ARID, EncapsulationScheme, ReferenceProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{inspect, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_response_to_rotated_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (old_client, _) = new_party(&mut rng);
+    let (new_client, new_client_private_keys) = new_party(&mut rng);
+    let old_key = old_client.encryption_key().unwrap().reference();
+    let new_key = new_client.encryption_key().unwrap().reference();
+
+    // The server still has the client's document from before it rotated.
+    let id = ARID::new();
+    let response = SealedResponse::new_success(id, &server)
+        .with_result("ok")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&old_client],
+            &SealOptions::new().with_recipient_hints(true),
+        )
+        .unwrap();
+    assert_eq!(
+        inspect::recipient_schemes(&response).unwrap(),
+        vec![EncapsulationScheme::X25519]
+    );
+
+    // By default the error stays terse.
+    let error = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new().with_expected_id(id),
+        &new_client_private_keys,
+    )
+    .unwrap_err();
+    assert!(!matches!(error, Error::NotARecipient(_)));
+    assert!(!error.to_string().contains(&old_key.ref_hex_short()));
+
+    let error = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new()
+            .with_expected_id(id)
+            .with_decryption_diagnostics(true),
+        &new_client_private_keys,
+    )
+    .unwrap_err();
+    let Error::NotARecipient(diagnostics) = &error else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(diagnostics.slot_schemes, vec![EncapsulationScheme::X25519]);
+    assert_eq!(diagnostics.recipient_hints, vec![old_key]);
+    assert_eq!(diagnostics.attempted_key, new_key);
+    assert_eq!(diagnostics.attempted_scheme, EncapsulationScheme::X25519);
+    let message = error.to_string();
+    assert!(message.contains(&old_key.ref_hex_short()));
+    assert!(message.contains(&new_key.ref_hex_short()));
+}
+
+#[test]
+fn test_request_diagnostics_without_hints() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, _) = new_party(&mut rng);
+    let (standby, _) = new_party(&mut rng);
+    let (_, other_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope_for_recipients(
+            None,
+            Some(&client_private_keys),
+            &[&server, &standby],
+        )
+        .unwrap();
+    let error = SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new().with_decryption_diagnostics(true),
+        &other_private_keys,
+    )
+    .unwrap_err();
+    let Error::NotARecipient(diagnostics) = error else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(diagnostics.slot_schemes.len(), 2);
+    assert!(diagnostics.recipient_hints.is_empty());
+}
+
+#[test]
+fn test_event_diagnostics() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (listener, _) = new_party(&mut rng);
+    let (_, other_private_keys) = new_party(&mut rng);
+
+    let event = SealedEvent::<String>::new("ping", ARID::new(), &sender)
+        .to_envelope(None, Some(&sender_private_keys), Some(&listener))
+        .unwrap();
+    let error = SealedEvent::<String>::try_from_envelope_opt(
+        &event,
+        &ParseOptions::new().with_decryption_diagnostics(true),
+        &other_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::NotARecipient(_)));
+}
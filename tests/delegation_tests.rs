@@ -0,0 +1,281 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{
+    ARID, Decrypter, EncapsulationScheme, PrivateKeys, SignatureScheme,
+    keypair_opt,
+};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{DelegationKeyring, prelude::*};
+
+use crate::common::new_party;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Has the server answer a request with `state`, encrypted to the keyring's
+/// current key, and has the client send the resulting continuation back in a
+/// new request.
+fn round_trip(
+    keyring: &DelegationKeyring,
+    state: &str,
+    server: &XIDDocument,
+    server_private_keys: &PrivateKeys,
+    client: &XIDDocument,
+    client_private_keys: &PrivateKeys,
+) -> Envelope {
+    let options = SealOptions::new()
+        .with_continuation_key(keyring.current().unwrap().public_key().clone());
+    let response = SealedResponse::new_success(ARID::new(), server)
+        .with_result("ok")
+        .with_state(state)
+        .to_envelope_with_options(
+            None,
+            Some(server_private_keys),
+            &[client],
+            &options,
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        client_private_keys,
+    )
+    .unwrap();
+    SealedRequest::new("next", ARID::new(), client)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(client_private_keys), Some(server))
+        .unwrap()
+}
+
+#[test]
+fn test_old_continuations_decrypt_until_key_expires() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let start = Date::from_ymd(2026, 1, 1);
+    let mut keyring = DelegationKeyring::new();
+    let first_key = keyring.rotate(start, 2 * DAY);
+    let before_rotation = round_trip(
+        &keyring,
+        "Before rotation.",
+        &server,
+        &server_private_keys,
+        &client,
+        &client_private_keys,
+    );
+
+    let rotated = start + DAY;
+    let second_key = keyring.rotate(rotated, 2 * DAY);
+    assert_ne!(first_key, second_key);
+    assert_eq!(keyring.current().unwrap().id(), second_key);
+    let after_rotation = round_trip(
+        &keyring,
+        "After rotation.",
+        &server,
+        &server_private_keys,
+        &client,
+        &client_private_keys,
+    );
+
+    let parse = |envelope: &Envelope, keyring: &DelegationKeyring, now| {
+        SealedRequest::try_from_envelope_opt(
+            envelope,
+            &ParseOptions::new()
+                .with_now(now)
+                .with_continuation_keys(keyring.decryption_keys(now)),
+            &server_private_keys,
+        )
+    };
+
+    // Both keys are valid, so both continuations decrypt.
+    let now = rotated + Duration::from_secs(60);
+    assert_eq!(
+        parse(&before_rotation, &keyring, now)
+            .unwrap()
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "Before rotation."
+    );
+    assert_eq!(
+        parse(&after_rotation, &keyring, now)
+            .unwrap()
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "After rotation."
+    );
+
+    // Once the first key expires, only the newer continuation decrypts.
+    let now = start + 2 * DAY;
+    assert_eq!(keyring.expire(now), vec![first_key]);
+    assert!(parse(&before_rotation, &keyring, now).is_err());
+    assert!(parse(&after_rotation, &keyring, now).is_ok());
+}
+
+#[test]
+fn test_worker_decrypts_with_distributed_keyring() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let mut keyring = DelegationKeyring::new();
+    keyring.rotate(now, DAY);
+    let request = round_trip(
+        &keyring,
+        "Handled by a worker.",
+        &server,
+        &server_private_keys,
+        &client,
+        &client_private_keys,
+    );
+
+    // The front end unwraps the request, but leaves the continuation for a
+    // worker.
+    let continuation = continuation_from(&request, &server_private_keys);
+
+    // The keyring is signed by the server and encrypted to the worker for
+    // distribution, and nobody else can read it.
+    let (worker, worker_private_keys) = new_party(&mut rng);
+    let server_key = server.verification_key().unwrap();
+    let distributed = keyring
+        .to_encrypted_envelope(&server_private_keys, &worker)
+        .unwrap()
+        .to_cbor_data();
+    let distributed = Envelope::try_from_cbor_data(distributed).unwrap();
+    assert!(
+        DelegationKeyring::try_from_encrypted_envelope(
+            &distributed,
+            &client_private_keys,
+            server_key,
+        )
+        .is_err()
+    );
+
+    // A keyring anyone else sends the worker is refused.
+    let planted = DelegationKeyring::new()
+        .to_encrypted_envelope(&client_private_keys, &worker)
+        .unwrap();
+    assert!(
+        DelegationKeyring::try_from_encrypted_envelope(
+            &planted,
+            &worker_private_keys,
+            server_key,
+        )
+        .is_err()
+    );
+
+    let worker_keyring = DelegationKeyring::try_from_encrypted_envelope(
+        &distributed,
+        &worker_private_keys,
+        server_key,
+    )
+    .unwrap();
+    assert_eq!(worker_keyring, keyring);
+
+    let keys = worker_keyring.decryption_keys(now);
+    let keys: Vec<&dyn Decrypter> =
+        keys.iter().map(|key| key as &dyn Decrypter).collect();
+    let continuation = Continuation::try_from_envelope_with_keys(
+        &continuation,
+        None,
+        Some(now),
+        &keys,
+    )
+    .unwrap();
+    assert_eq!(
+        continuation.state().extract_subject::<String>().unwrap(),
+        "Handled by a worker."
+    );
+
+    // The server's identity key is of no use to the worker.
+    assert!(
+        Continuation::try_from_envelope(
+            &continuation_from(&request, &server_private_keys),
+            None,
+            Some(now),
+            Some(&server_private_keys),
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_keys_follow_identity_scheme() {
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let (server_private_keys, _) =
+        keypair_opt(SignatureScheme::MLDSA65, EncapsulationScheme::MLKEM768);
+    let mut keyring = DelegationKeyring::for_identity(&server_private_keys);
+    assert_eq!(keyring.scheme(), EncapsulationScheme::MLKEM768);
+    keyring.rotate(now, DAY);
+    assert_eq!(
+        keyring
+            .current()
+            .unwrap()
+            .public_key()
+            .encapsulation_scheme(),
+        EncapsulationScheme::MLKEM768
+    );
+
+    // A deserialized keyring keeps generating keys with the same scheme.
+    let mut restored =
+        DelegationKeyring::try_from(Envelope::from(keyring.clone())).unwrap();
+    assert_eq!(restored, keyring);
+    restored.rotate(now + DAY, DAY);
+    assert_eq!(
+        restored
+            .current()
+            .unwrap()
+            .private_key()
+            .encapsulation_scheme(),
+        EncapsulationScheme::MLKEM768
+    );
+
+    assert_eq!(
+        DelegationKeyring::new().scheme(),
+        EncapsulationScheme::X25519
+    );
+}
+
+/// Extracts the still-encrypted continuation returned in a sealed request.
+fn continuation_from(request: &Envelope, recipient: &PrivateKeys) -> Envelope {
+    request
+        .decrypt_subject_to_recipient(recipient)
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .object_for_predicate(known_values::RECIPIENT_CONTINUATION)
+        .unwrap()
+}
+
+#[test]
+fn test_activate_unknown_key() {
+    let mut keyring = DelegationKeyring::new();
+    let id = keyring.generate(Date::now(), DAY);
+    assert!(keyring.current().is_none());
+    keyring.activate(id).unwrap();
+    assert_eq!(keyring.current().unwrap().id(), id);
+
+    let mut other = DelegationKeyring::new();
+    let unknown = other.generate(Date::now(), DAY);
+    assert!(matches!(
+        keyring.activate(unknown),
+        Err(Error::UnknownDelegationKey(found)) if found == unknown
+    ));
+}
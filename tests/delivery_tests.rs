@@ -0,0 +1,99 @@
+mod common;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    DeliveryAttempt, MemoryOutboxStore, MessageKind, Outbox, OutboxMetadata,
+    prelude::*, record_delivery_attempt, verify_delivery_attempt,
+};
+
+use crate::common::new_party;
+
+#[test]
+fn test_delivery_attempt_record() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, _) = new_party(&mut rng);
+    let (other, _) = new_party(&mut rng);
+    let now = Date::from_string("2024-07-01T12:00:00Z").unwrap();
+
+    let message = SealedEvent::<String>::new("Notice.", ARID::new(), &sender)
+        .to_envelope(None, Some(&sender_private_keys), Some(&recipient))
+        .unwrap();
+    let attempt = DeliveryAttempt::new(
+        &message,
+        recipient.xid(),
+        "https://example.com/inbox",
+        now,
+    )
+    .with_acknowledgment(b"202 Accepted".to_vec());
+    let record = record_delivery_attempt(&attempt, &sender_private_keys);
+
+    let verified =
+        verify_delivery_attempt(&record, sender.verification_key().unwrap())
+            .unwrap();
+    assert_eq!(verified, attempt);
+    verified.check_message(&message).unwrap();
+
+    // The record only holds for the message it was made for...
+    let other_message =
+        SealedEvent::<String>::new("Notice.", ARID::new(), &sender)
+            .to_envelope(None, Some(&sender_private_keys), Some(&recipient))
+            .unwrap();
+    assert!(matches!(
+        verified.check_message(&other_message),
+        Err(Error::DeliveryRecordMismatch)
+    ));
+
+    // ...and only from the sender that signed it.
+    assert!(
+        verify_delivery_attempt(&record, other.verification_key().unwrap())
+            .is_err()
+    );
+}
+
+#[test]
+fn test_outbox_records_delivery() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, _) = new_party(&mut rng);
+    let now = Date::from_string("2024-07-01T12:00:00Z").unwrap();
+
+    let message = SealedEvent::<String>::new("Notice.", ARID::new(), &sender)
+        .to_envelope(None, Some(&sender_private_keys), Some(&recipient))
+        .unwrap();
+    let mut outbox = Outbox::new(MemoryOutboxStore::new());
+    let id = outbox
+        .enqueue(
+            MessageKind::Event,
+            message.clone(),
+            OutboxMetadata::new(recipient.xid()),
+            now,
+        )
+        .unwrap();
+
+    let record = outbox
+        .mark_sent_with_record(
+            id,
+            "queue:notices",
+            None,
+            &sender_private_keys,
+            now,
+        )
+        .unwrap();
+    assert!(outbox.next_due(now).unwrap().is_none());
+
+    let attempt =
+        verify_delivery_attempt(&record, sender.verification_key().unwrap())
+            .unwrap();
+    attempt.check_message(&message).unwrap();
+    assert_eq!(attempt.recipient, recipient.xid());
+    assert_eq!(attempt.transport, "queue:notices");
+    assert_eq!(attempt.timestamp, now);
+    assert_eq!(attempt.acknowledgment, None);
+}
@@ -0,0 +1,265 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::{
+    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+};
+use gstp::{DuplicateAssertionPolicy, ParseWarning, prelude::*};
+
+use crate::common::new_party;
+
+fn document_envelope(document: &XIDDocument) -> Envelope {
+    document
+        .to_envelope(
+            XIDPrivateKeyOptions::default(),
+            XIDGeneratorOptions::default(),
+            XIDSigningOptions::default(),
+        )
+        .unwrap()
+}
+
+/// Decrypts and unwraps a sealed message, down to the unsigned message.
+fn open(sealed: &Envelope, recipient: &PrivateKeys) -> Envelope {
+    sealed
+        .decrypt_subject_to_recipient(recipient)
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+}
+
+/// Signs a message and encrypts it to `recipient`, as a buggy peer would
+/// after adding assertions to it.
+fn reseal(
+    message: Envelope,
+    signer: &PrivateKeys,
+    recipient: &XIDDocument,
+) -> Envelope {
+    message
+        .sign(signer)
+        .wrap()
+        .encrypt_subject_to_recipient(recipient.encryption_key().unwrap())
+        .unwrap()
+}
+
+struct Parties {
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+    other: XIDDocument,
+    other_private_keys: PrivateKeys,
+}
+
+fn parties() -> Parties {
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (other, other_private_keys) = new_party(&mut rng);
+    Parties {
+        client,
+        client_private_keys,
+        server,
+        server_private_keys,
+        other,
+        other_private_keys,
+    }
+}
+
+/// A request from the client to the server, opened by the server.
+fn request_message(parties: &Parties) -> Envelope {
+    let sealed = SealedRequest::new("test", ARID::new(), &parties.client)
+        .with_note("First note.")
+        .with_date(Date::from_ymd(2026, 1, 1))
+        .to_envelope(
+            None,
+            Some(&parties.client_private_keys),
+            Some(&parties.server),
+        )
+        .unwrap();
+    open(&sealed, &parties.server_private_keys)
+}
+
+fn parse_request(
+    parties: &Parties,
+    sealed: &Envelope,
+    policy: DuplicateAssertionPolicy,
+) -> Result<SealedRequest> {
+    SealedRequest::try_from_envelope_opt(
+        sealed,
+        &ParseOptions::new().with_duplicate_assertions(policy),
+        &parties.server_private_keys,
+    )
+}
+
+fn assert_strict_and_lenient(
+    parties: &Parties,
+    sealed: &Envelope,
+    predicate: KnownValue,
+) -> SealedRequest {
+    match parse_request(parties, sealed, DuplicateAssertionPolicy::Reject) {
+        Err(Error::AmbiguousAssertion { predicate: found }) => {
+            assert_eq!(found, predicate)
+        }
+        other => panic!("expected an ambiguous assertion, got {:?}", other),
+    }
+    let request =
+        parse_request(parties, sealed, DuplicateAssertionPolicy::TakeFirst)
+            .unwrap();
+    assert_eq!(
+        request.warnings(),
+        &[ParseWarning::DuplicateAssertion { predicate }]
+    );
+    request
+}
+
+#[test]
+fn test_duplicate_note_and_date() {
+    bc_envelope::register_tags();
+    let parties = parties();
+
+    let message = request_message(&parties)
+        .add_assertion(known_values::NOTE, "Second note.");
+    let sealed = reseal(message, &parties.client_private_keys, &parties.server);
+    let request =
+        assert_strict_and_lenient(&parties, &sealed, known_values::NOTE);
+    assert!(["First note.", "Second note."].contains(&request.note()));
+
+    let message = request_message(&parties)
+        .add_assertion(known_values::DATE, Date::from_ymd(2026, 1, 2));
+    let sealed = reseal(message, &parties.client_private_keys, &parties.server);
+    assert_strict_and_lenient(&parties, &sealed, known_values::DATE);
+}
+
+#[test]
+fn test_duplicate_sender() {
+    bc_envelope::register_tags();
+    let parties = parties();
+
+    let message = request_message(&parties)
+        .add_assertion(known_values::SENDER, document_envelope(&parties.other));
+
+    // Whichever sender is kept must have signed the message for it to be
+    // accepted.
+    let first_sender: XIDDocument = message
+        .assertions_with_predicate(known_values::SENDER)[0]
+        .try_object()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let signer = if first_sender.xid() == parties.client.xid() {
+        &parties.client_private_keys
+    } else {
+        &parties.other_private_keys
+    };
+    let sealed = reseal(message, signer, &parties.server);
+    let request =
+        assert_strict_and_lenient(&parties, &sealed, known_values::SENDER);
+    assert_eq!(request.sender().xid(), first_sender.xid());
+}
+
+#[test]
+fn test_duplicate_continuations() {
+    bc_envelope::register_tags();
+    let parties = parties();
+
+    let extra_sender_continuation = Continuation::new("Extra.")
+        .to_envelope(Some(parties.client.encryption_key().unwrap()));
+    let message = request_message(&parties).add_assertion(
+        known_values::SENDER_CONTINUATION,
+        extra_sender_continuation,
+    );
+    let sealed = reseal(message, &parties.client_private_keys, &parties.server);
+    assert_strict_and_lenient(
+        &parties,
+        &sealed,
+        known_values::SENDER_CONTINUATION,
+    );
+
+    let recipient_continuation = |state: &str| {
+        Continuation::new(state)
+            .to_envelope(Some(parties.server.encryption_key().unwrap()))
+    };
+    let message = request_message(&parties)
+        .add_assertion(
+            known_values::RECIPIENT_CONTINUATION,
+            recipient_continuation("First state."),
+        )
+        .add_assertion(
+            known_values::RECIPIENT_CONTINUATION,
+            recipient_continuation("Second state."),
+        );
+    let sealed = reseal(message, &parties.client_private_keys, &parties.server);
+    let request = assert_strict_and_lenient(
+        &parties,
+        &sealed,
+        known_values::RECIPIENT_CONTINUATION,
+    );
+    let state: String = request.state().unwrap().extract_subject().unwrap();
+    assert!(["First state.", "Second state."].contains(&state.as_str()));
+}
+
+#[test]
+fn test_duplicates_rejected_in_responses_and_events() {
+    bc_envelope::register_tags();
+    let parties = parties();
+
+    let sealed = SealedResponse::new_success(ARID::new(), &parties.server)
+        .with_result("ok")
+        .to_envelope(
+            None,
+            Some(&parties.server_private_keys),
+            Some(&parties.client),
+        )
+        .unwrap();
+    let message = open(&sealed, &parties.client_private_keys)
+        .add_assertion(known_values::SENDER, document_envelope(&parties.other));
+    let sealed = reseal(message, &parties.server_private_keys, &parties.client);
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope(
+            &sealed,
+            None,
+            None,
+            &parties.client_private_keys,
+        ),
+        Err(Error::AmbiguousAssertion { predicate })
+            if predicate == known_values::SENDER
+    ));
+
+    let sealed = SealedEvent::<String>::new(
+        "Something happened.",
+        ARID::new(),
+        &parties.server,
+    )
+    .with_note("First note.")
+    .to_envelope(
+        None,
+        Some(&parties.server_private_keys),
+        Some(&parties.client),
+    )
+    .unwrap();
+    let message = open(&sealed, &parties.client_private_keys)
+        .add_assertion(known_values::NOTE, "Second note.");
+    let sealed = reseal(message, &parties.server_private_keys, &parties.client);
+    assert!(matches!(
+        SealedEvent::<String>::try_from_envelope(
+            &sealed,
+            None,
+            None,
+            &parties.client_private_keys,
+        ),
+        Err(Error::AmbiguousAssertion { predicate })
+            if predicate == known_values::NOTE
+    ));
+    let event = SealedEvent::<String>::try_from_envelope_opt(
+        &sealed,
+        &ParseOptions::new()
+            .with_duplicate_assertions(DuplicateAssertionPolicy::TakeFirst),
+        &parties.client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.warnings().len(), 1);
+}
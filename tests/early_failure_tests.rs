@@ -0,0 +1,161 @@
+mod common;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_early_failure_from_unknown_server() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (expected_server, _) = new_party(&mut rng);
+    let (unknown_server, unknown_server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let response = SealedResponse::new_early_failure(&unknown_server)
+        .with_error("Could not decrypt request.")
+        .to_envelope(None, Some(&unknown_server_private_keys), Some(&client))
+        .unwrap();
+
+    // A client that pins its server rejects the response outright...
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &response,
+            &ParseOptions::new().with_expected_sender(&expected_server),
+            &client_private_keys,
+        ),
+        Err(Error::UnexpectedSender { .. })
+    ));
+
+    // ...but can still learn why its request failed.
+    let failure = SealedResponse::try_parse_early_failure(
+        &response,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        failure.error.extract_subject::<String>().unwrap(),
+        "Could not decrypt request."
+    );
+    assert_eq!(failure.claimed_sender, Some(unknown_server.xid()));
+    assert!(failure.signature_verified);
+}
+
+#[test]
+fn test_full_response_is_not_an_early_failure() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    for response in [
+        SealedResponse::new_success(ARID::new(), &server).with_result("ok"),
+        SealedResponse::new_failure(ARID::new(), &server).with_error("no"),
+    ] {
+        let response = response
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap();
+        assert!(matches!(
+            SealedResponse::try_parse_early_failure(
+                &response,
+                &client_private_keys
+            ),
+            Err(Error::NotAnEarlyFailure)
+        ));
+    }
+}
+
+#[test]
+fn test_spoofed_early_failure_smuggling_content() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let response = SealedResponse::new_early_failure(&server)
+        .with_error("Nothing to see here.")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let message = response
+        .decrypt_subject_to_recipient(&client_private_keys)
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .try_unwrap()
+        .unwrap();
+    let reseal = |message: Envelope| {
+        message
+            .sign(&server_private_keys)
+            .wrap()
+            .encrypt_subject_to_recipient(client.encryption_key().unwrap())
+            .unwrap()
+    };
+
+    let smuggled = reseal(message.add_assertion("payload", "Transfer $100."));
+    assert!(matches!(
+        SealedResponse::try_parse_early_failure(
+            &smuggled,
+            &client_private_keys
+        ),
+        Err(Error::InvalidEarlyFailure)
+    ));
+
+    let smuggled =
+        reseal(message.add_assertion(known_values::RESULT, "Transfer $100."));
+    assert!(
+        SealedResponse::try_parse_early_failure(
+            &smuggled,
+            &client_private_keys
+        )
+        .is_err()
+    );
+
+    let continuation = Continuation::new("Smuggled state.")
+        .to_envelope(Some(client.encryption_key().unwrap()));
+    let smuggled = reseal(
+        message
+            .add_assertion(known_values::RECIPIENT_CONTINUATION, continuation),
+    );
+    assert!(matches!(
+        SealedResponse::try_parse_early_failure(
+            &smuggled,
+            &client_private_keys
+        ),
+        Err(Error::InvalidEarlyFailure)
+    ));
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope(
+            &smuggled,
+            None,
+            None,
+            &client_private_keys
+        ),
+        Err(Error::InvalidEarlyFailure)
+    ));
+}
+
+#[test]
+fn test_early_failure_cannot_carry_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let continuation = Continuation::new("Client state.")
+        .to_envelope(Some(client.encryption_key().unwrap()));
+    let response = SealedResponse::new_early_failure(&server)
+        .with_error("Could not decrypt request.")
+        .with_peer_continuation(continuation);
+    assert!(response.is_early_failure());
+    assert!(matches!(
+        response.to_envelope(None, Some(&server_private_keys), Some(&client)),
+        Err(Error::InvalidEarlyFailure)
+    ));
+}
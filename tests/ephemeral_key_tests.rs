@@ -0,0 +1,108 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{Continuation, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_ephemeral_keys() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let artifacts = SealedRequest::new("test", id, &client)
+        .with_state("Client state.")
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::new().with_ephemeral_keys(true),
+        )
+        .unwrap();
+    let ephemeral_key = artifacts.ephemeral_key.unwrap();
+
+    // The continuation is sealed to the ephemeral key, not the client's own.
+    let own_continuation = artifacts.own_continuation.unwrap();
+    assert!(
+        Continuation::try_from_envelope(
+            &own_continuation,
+            None,
+            None,
+            Some(&client_private_keys),
+        )
+        .is_err()
+    );
+
+    let request = SealedRequest::try_from_envelope(
+        &artifacts.envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request.ephemeral_key(),
+        Some(&ephemeral_key.public_key().unwrap())
+    );
+
+    let response = SealedResponse::new_success(id, &server)
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .with_optional_reply_key(request.ephemeral_key().cloned())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+
+    // The client's identity keys alone open neither the response...
+    assert!(
+        SealedResponse::try_from_encrypted_envelope(
+            &response,
+            Some(id),
+            None,
+            &client_private_keys,
+        )
+        .is_err()
+    );
+
+    // ...but with the ephemeral key the response and its state are recovered.
+    let parsed = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new()
+            .with_expected_id(id)
+            .with_ephemeral_key(ephemeral_key),
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.state().unwrap(), &Envelope::new("Client state."));
+}
+
+#[test]
+fn test_ephemeral_keys_are_opt_in() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let artifacts = SealedRequest::new("test", ARID::new(), &client)
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::new(),
+        )
+        .unwrap();
+    assert!(artifacts.ephemeral_key.is_none());
+    let request = SealedRequest::try_from_envelope(
+        &artifacts.envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert!(request.ephemeral_key().is_none());
+}
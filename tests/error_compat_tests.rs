@@ -0,0 +1,60 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// A consumer written against boxed, type-erased errors, as code using
+/// `anyhow` is. `Error` must convert with `?`, which also makes it convertible
+/// into `anyhow::Error`.
+fn legacy_parse(
+    response: &Envelope,
+    id: ARID,
+    recipient: &PrivateKeys,
+) -> std::result::Result<String, BoxError> {
+    let response = SealedResponse::try_from_encrypted_envelope(
+        response,
+        Some(id),
+        None,
+        recipient,
+    )?;
+    Ok(response.result()?.extract_subject()?)
+}
+
+#[test]
+fn test_error_is_type_erasable() {
+    fn assert_error<E: std::error::Error + Send + Sync + 'static>() {}
+    assert_error::<Error>();
+}
+
+#[test]
+fn test_legacy_consumer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, other_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+
+    let response = SealedResponse::new_success(id, &server)
+        .with_result("ok")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    assert_eq!(
+        legacy_parse(&response, id, &client_private_keys).unwrap(),
+        "ok"
+    );
+
+    // The typed error survives type erasure and can be recovered.
+    let error = legacy_parse(&response, id, &other_private_keys).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<Error>(),
+        Some(Error::Envelope(_))
+    ));
+}
@@ -0,0 +1,343 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{EventBus, SequenceStatus, TopicFilter, prelude::*};
+
+use crate::common::new_party;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Temperature(i64);
+
+impl From<Temperature> for Envelope {
+    fn from(value: Temperature) -> Self {
+        Envelope::new(value.0).add_assertion(known_values::IS_A, "temperature")
+    }
+}
+
+impl TryFrom<Envelope> for Temperature {
+    type Error = bc_envelope::Error;
+
+    fn try_from(envelope: Envelope) -> std::result::Result<Self, Self::Error> {
+        Ok(Temperature(envelope.extract_subject()?))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Alert(String);
+
+impl From<Alert> for Envelope {
+    fn from(value: Alert) -> Self {
+        Envelope::new(value.0).add_assertion(known_values::IS_A, "alert")
+    }
+}
+
+impl TryFrom<Envelope> for Alert {
+    type Error = bc_envelope::Error;
+
+    fn try_from(envelope: Envelope) -> std::result::Result<Self, Self::Error> {
+        Ok(Alert(envelope.extract_subject()?))
+    }
+}
+
+fn seal(
+    content: impl Into<Envelope>,
+    sender: &XIDDocument,
+    sender_private_keys: &PrivateKeys,
+    recipient: &XIDDocument,
+) -> Envelope {
+    SealedEvent::<Envelope>::new(content.into(), ARID::new(), sender)
+        .to_envelope(None, Some(sender_private_keys), Some(recipient))
+        .unwrap()
+}
+
+#[test]
+fn test_dispatch_to_typed_and_default_handlers() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+
+    let log = Arc::new(Mutex::new(Vec::<String>::new()));
+    let mut bus = EventBus::new();
+
+    let temperatures = log.clone();
+    bus.register(
+        TopicFilter::exact("temperature"),
+        move |event: SealedEvent<Temperature>| {
+            temperatures
+                .lock()
+                .unwrap()
+                .push(format!("temperature {}", event.content().0));
+            Ok(())
+        },
+    );
+    let failing = bus.register(
+        TopicFilter::exact("temperature"),
+        |_: SealedEvent<Temperature>| Err("sensor offline".into()),
+    );
+    let alerts = log.clone();
+    bus.register(
+        TopicFilter::prefix("al"),
+        move |event: SealedEvent<Alert>| {
+            alerts
+                .lock()
+                .unwrap()
+                .push(format!("alert {}", event.content().0));
+            Ok(())
+        },
+    );
+    let unknown = log.clone();
+    bus.set_default_handler(move |event| {
+        unknown
+            .lock()
+            .unwrap()
+            .push(format!("unknown {}", event.content().format_flat()));
+        Ok(())
+    });
+
+    let options = ParseOptions::new().with_expected_sender(&sender);
+    let report = bus
+        .dispatch(
+            &seal(Temperature(21), &sender, &sender_private_keys, &recipient),
+            &recipient_private_keys,
+            &options,
+        )
+        .unwrap();
+    assert_eq!(report.topic.as_deref(), Some("temperature"));
+    assert_eq!(report.invoked, 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].handler, Some(failing));
+    assert_eq!(report.errors[0].message, "sensor offline");
+
+    let report = bus
+        .dispatch(
+            &seal(
+                Alert("Door open".into()),
+                &sender,
+                &sender_private_keys,
+                &recipient,
+            ),
+            &recipient_private_keys,
+            &options,
+        )
+        .unwrap();
+    assert_eq!(report.invoked, 1);
+    assert!(report.errors.is_empty());
+
+    let report = bus
+        .dispatch(
+            &seal(
+                Envelope::new("Hello"),
+                &sender,
+                &sender_private_keys,
+                &recipient,
+            ),
+            &recipient_private_keys,
+            &options,
+        )
+        .unwrap();
+    assert_eq!(report.topic, None);
+    assert_eq!(report.invoked, 1);
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "temperature 21".to_string(),
+            "alert Door open".to_string(),
+            r#"unknown "Hello""#.to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_deduplication() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+
+    let count = Arc::new(Mutex::new(0));
+    let mut bus = EventBus::new().with_deduplication();
+    let counter = count.clone();
+    bus.register(TopicFilter::Any, move |_: SealedEvent<Envelope>| {
+        *counter.lock().unwrap() += 1;
+        Ok(())
+    });
+
+    let envelope =
+        seal(Temperature(5), &sender, &sender_private_keys, &recipient);
+    let first = bus
+        .dispatch(&envelope, &recipient_private_keys, &ParseOptions::new())
+        .unwrap();
+    let second = bus
+        .dispatch(&envelope, &recipient_private_keys, &ParseOptions::new())
+        .unwrap();
+    assert!(!first.duplicate);
+    assert!(second.duplicate);
+    assert_eq!(second.invoked, 0);
+    assert_eq!(*count.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_deduplication_is_per_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+    let (other, other_private_keys) = new_party(&mut rng);
+
+    // Both senders happen to choose the same event ID.
+    let id = ARID::new();
+    let seal_as = |sender: &XIDDocument, private_keys: &PrivateKeys| {
+        SealedEvent::<Envelope>::new(Envelope::from(Temperature(5)), id, sender)
+            .to_envelope(None, Some(private_keys), Some(&recipient))
+            .unwrap()
+    };
+    let from_sender = seal_as(&sender, &sender_private_keys);
+    let from_other = seal_as(&other, &other_private_keys);
+
+    let mut bus = EventBus::new().with_deduplication();
+    let mut dispatch = |envelope: &Envelope| {
+        bus.dispatch(envelope, &recipient_private_keys, &ParseOptions::new())
+            .unwrap()
+            .duplicate
+    };
+    assert!(!dispatch(&from_sender));
+    assert!(!dispatch(&from_other));
+    assert!(dispatch(&from_sender));
+    assert!(dispatch(&from_other));
+}
+
+#[test]
+fn test_deduplication_window() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+
+    let mut bus = EventBus::new().with_deduplication().with_window(2);
+    let envelopes: Vec<Envelope> = (0..3)
+        .map(|n| {
+            seal(Temperature(n), &sender, &sender_private_keys, &recipient)
+        })
+        .collect();
+    let mut dispatch = |envelope: &Envelope| {
+        bus.dispatch(envelope, &recipient_private_keys, &ParseOptions::new())
+            .unwrap()
+            .duplicate
+    };
+    for envelope in &envelopes {
+        assert!(!dispatch(envelope));
+    }
+
+    // Only the last two IDs are remembered.
+    assert!(dispatch(&envelopes[2]));
+    assert!(!dispatch(&envelopes[0]));
+}
+
+#[test]
+fn test_sequence_tracking() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+
+    // The sender chains four events, each committing to the one before.
+    let mut previous = None;
+    let chain: Vec<Envelope> = (0..4)
+        .map(|n| {
+            let envelope = SealedEvent::<Envelope>::new(
+                Envelope::from(Temperature(n)),
+                ARID::new(),
+                &sender,
+            )
+            .with_optional_previous_digest(previous)
+            .to_envelope(None, Some(&sender_private_keys), Some(&recipient))
+            .unwrap();
+            previous = Some(envelope.digest());
+            envelope
+        })
+        .collect();
+
+    let mut bus = EventBus::new().with_sequence_tracking();
+    let mut dispatch = |envelope: &Envelope| {
+        bus.dispatch(envelope, &recipient_private_keys, &ParseOptions::new())
+            .unwrap()
+            .sequence
+            .unwrap()
+    };
+    assert_eq!(dispatch(&chain[0]), SequenceStatus::InOrder);
+    assert_eq!(dispatch(&chain[2]), SequenceStatus::Gap);
+    assert_eq!(dispatch(&chain[1]), SequenceStatus::Reordered);
+    assert_eq!(dispatch(&chain[3]), SequenceStatus::InOrder);
+
+    // Without tracking, nothing is reported.
+    let report = EventBus::new()
+        .dispatch(&chain[0], &recipient_private_keys, &ParseOptions::new())
+        .unwrap();
+    assert_eq!(report.sequence, None);
+}
+
+#[test]
+fn test_panicking_handler_is_isolated() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+
+    let count = Arc::new(Mutex::new(0));
+    let mut bus = EventBus::new();
+    bus.register(TopicFilter::Any, |_: SealedEvent<Envelope>| {
+        panic!("sensor offline")
+    });
+    let counter = count.clone();
+    bus.register(TopicFilter::Any, move |_: SealedEvent<Envelope>| {
+        *counter.lock().unwrap() += 1;
+        Ok(())
+    });
+
+    let report = bus
+        .dispatch(
+            &seal(Temperature(5), &sender, &sender_private_keys, &recipient),
+            &recipient_private_keys,
+            &ParseOptions::new(),
+        )
+        .unwrap();
+    assert_eq!(report.invoked, 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].handler, Some(0));
+    assert_eq!(report.errors[0].message, "handler panicked: sensor offline");
+    assert_eq!(*count.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_unexpected_sender_is_not_dispatched() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (recipient, recipient_private_keys) = new_party(&mut rng);
+    let (other, _) = new_party(&mut rng);
+
+    let mut bus = EventBus::new();
+    bus.register(TopicFilter::Any, |_: SealedEvent<Envelope>| {
+        panic!("handler must not be invoked")
+    });
+    let result = bus.dispatch(
+        &seal(Temperature(5), &sender, &sender_private_keys, &recipient),
+        &recipient_private_keys,
+        &ParseOptions::new().with_expected_sender(&other),
+    );
+    assert!(matches!(result, Err(Error::UnexpectedSender { .. })));
+}
@@ -0,0 +1,283 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::{Delegate, XIDDocument};
+use gstp::{MemoryPendingStore, PendingRequests, prelude::*};
+
+use crate::common::new_party;
+
+fn respond(
+    id: ARID,
+    sender: &XIDDocument,
+    sender_private_keys: &PrivateKeys,
+    recipient: &XIDDocument,
+) -> Envelope {
+    SealedResponse::new_success(id, sender)
+        .with_result("ok")
+        .to_envelope(None, Some(sender_private_keys), Some(recipient))
+        .unwrap()
+}
+
+#[test]
+fn test_expected_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let options = ParseOptions::new().with_expected_sender(&server);
+
+    let response = respond(id, &server, &server_private_keys, &client);
+    SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &options,
+        &client_private_keys,
+    )
+    .unwrap();
+
+    // Mallory's response is validly signed, but by the wrong party.
+    let response = respond(id, &mallory, &mallory_private_keys, &client);
+    SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    let result = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &options,
+        &client_private_keys,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::UnexpectedSender { expected, found })
+            if expected == server.xid() && found == mallory.xid()
+    ));
+}
+
+/// A document claiming `victim`'s XID, carrying `impostor`'s key.
+fn forge(victim: &XIDDocument, impostor: &XIDDocument) -> XIDDocument {
+    let mut forged = XIDDocument::from_xid(victim.xid());
+    forged
+        .add_key(impostor.inception_key().unwrap().clone())
+        .unwrap();
+    forged
+}
+
+#[test]
+fn test_forged_sender_document() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let forged = forge(&server, &mallory);
+    assert_eq!(forged.xid(), server.xid());
+
+    // Mallory's signature verifies under the key in the forged document, but
+    // that key is not the one the server's XID is derived from.
+    let options = ParseOptions::new().with_expected_sender(&server);
+    let response =
+        respond(ARID::new(), &forged, &mallory_private_keys, &client);
+    for options in [&ParseOptions::new(), &options] {
+        assert!(matches!(
+            SealedResponse::try_from_encrypted_envelope_opt(
+                &response,
+                options,
+                &client_private_keys,
+            ),
+            Err(Error::SenderKeyNotBound(xid)) if xid == server.xid()
+        ));
+    }
+
+    let forged = forge(&client, &mallory);
+    let request = SealedRequest::new("test", ARID::new(), &forged)
+        .to_envelope(None, Some(&mallory_private_keys), Some(&server))
+        .unwrap();
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            &ParseOptions::new().with_expected_sender(&client),
+            &server_private_keys,
+        ),
+        Err(Error::SenderKeyNotBound(xid)) if xid == client.xid()
+    ));
+}
+
+#[test]
+fn test_delegated_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (mut server, _) = new_party(&mut rng);
+    let (worker, worker_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    server.add_delegate(Delegate::new(&worker)).unwrap();
+
+    let response = respond(ARID::new(), &worker, &worker_private_keys, &client);
+
+    let strict = ParseOptions::new().with_expected_sender(&server);
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &response,
+            &strict,
+            &client_private_keys,
+        ),
+        Err(Error::UnexpectedSender { .. })
+    ));
+
+    let delegated = strict.with_delegates_of(&server);
+    SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &delegated,
+        &client_private_keys,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_pending_request_expects_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+
+    let mut pending = PendingRequests::new(MemoryPendingStore::new());
+    let request = SealedRequest::new("test", ARID::new(), &client);
+    pending
+        .seal(&request, None, &client_private_keys, &server)
+        .unwrap();
+
+    // Mallory races a response with the right ID.
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &respond(request.id(), &mallory, &mallory_private_keys, &client),
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(matches!(
+        pending.match_response(&response, now, &client_private_keys),
+        Err(Error::UnexpectedSender { expected, found })
+            if expected == server.xid() && found == mallory.xid()
+    ));
+
+    // A response claiming the server's XID without its key is refused too.
+    let request = SealedRequest::new("test", ARID::new(), &client);
+    pending
+        .seal(&request, None, &client_private_keys, &server)
+        .unwrap();
+    let response =
+        SealedResponse::new_success(request.id(), forge(&server, &mallory));
+    assert!(matches!(
+        pending.match_response(&response, now, &client_private_keys),
+        Err(Error::SenderKeyNotBound(xid)) if xid == server.xid()
+    ));
+}
+
+#[test]
+fn test_pending_delegates_are_per_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (mut server, _) = new_party(&mut rng);
+    let (other, _) = new_party(&mut rng);
+    let (worker, worker_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    server.add_delegate(Delegate::new(&worker)).unwrap();
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+
+    let mut pending = PendingRequests::new(MemoryPendingStore::new())
+        .with_delegates_of(&server);
+    let to_server = SealedRequest::new("test", ARID::new(), &client);
+    pending
+        .seal(&to_server, None, &client_private_keys, &server)
+        .unwrap();
+    let to_other = SealedRequest::new("test", ARID::new(), &client);
+    pending
+        .seal(&to_other, None, &client_private_keys, &other)
+        .unwrap();
+    let answer = |id| {
+        SealedResponse::try_from_encrypted_envelope(
+            &respond(id, &worker, &worker_private_keys, &client),
+            None,
+            None,
+            &client_private_keys,
+        )
+        .unwrap()
+    };
+
+    // The server's delegate cannot answer a request sent to another peer.
+    assert!(matches!(
+        pending.match_response(&answer(to_other.id()), now, &client_private_keys),
+        Err(Error::UnexpectedSender { expected, found })
+            if expected == other.xid() && found == worker.xid()
+    ));
+    let record = pending
+        .match_response(&answer(to_server.id()), now, &client_private_keys)
+        .unwrap();
+    assert_eq!(record.delegates, vec![worker.xid()]);
+}
+
+#[test]
+fn test_expected_sender_of_requests_and_events() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    // A server that knows its client pins it by XID alone.
+    let options = ParseOptions::new().with_expected_sender(&client.xid());
+    let request = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedRequest::new("test", ARID::new(), sender)
+            .to_envelope(None, Some(keys), Some(&server))
+            .unwrap()
+    };
+    SealedRequest::try_from_envelope_opt(
+        &request(&client, &client_private_keys),
+        &options,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &request(&mallory, &mallory_private_keys),
+            &options,
+            &server_private_keys,
+        ),
+        Err(Error::UnexpectedSender { expected, found })
+            if expected == client.xid() && found == mallory.xid()
+    ));
+
+    let event = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), sender)
+            .to_envelope(None, Some(keys), Some(&server))
+            .unwrap()
+    };
+    SealedEvent::<String>::try_from_envelope_opt(
+        &event(&client, &client_private_keys),
+        &options,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert!(matches!(
+        SealedEvent::<String>::try_from_envelope_opt(
+            &event(&mallory, &mallory_private_keys),
+            &options,
+            &server_private_keys,
+        ),
+        Err(Error::UnexpectedSender { .. })
+    ));
+}
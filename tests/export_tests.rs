@@ -0,0 +1,131 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    export::{
+        ExportRegistry, ExportedValue, VerifiedEventExport,
+        VerifiedRequestExport, VerifiedResponseExport,
+    },
+    prelude::*,
+};
+
+use crate::common::new_party;
+
+#[test]
+fn test_export_request_and_respond_by_handle() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("transfer", ARID::new(), &client)
+        .with_parameter("amount", 42)
+        .with_parameter("memo", "Rent")
+        .with_parameter("raw", ByteString::from(vec![1, 2, 3]))
+        .with_parameter("list", vec![1, 2])
+        .with_note("Monthly.")
+        .with_state("Client state.");
+    let sealed = request
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed = SealedRequest::try_from_envelope(
+        &sealed,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+
+    let mut registry = ExportRegistry::new();
+    let export = registry.export_request(parsed).unwrap();
+    let json = serde_json::to_string(&export).unwrap();
+
+    // The foreign side reads the export and hands back only the handle.
+    let imported: VerifiedRequestExport = serde_json::from_str(&json).unwrap();
+    assert_eq!(imported, export);
+    assert_eq!(imported.function, "transfer");
+    assert_eq!(imported.id, request.id().hex());
+    assert_eq!(imported.note, "Monthly.");
+    assert_eq!(imported.sender, client.xid().ur_string());
+    assert!(!imported.has_state);
+    let value = |name: &str| {
+        imported
+            .parameters
+            .iter()
+            .find(|parameter| parameter.name == name)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_eq!(value("amount"), ExportedValue::Integer(42));
+    assert_eq!(value("memo"), ExportedValue::Text("Rent".into()));
+    assert_eq!(value("raw"), ExportedValue::Bytes(vec![1, 2, 3]));
+    assert!(matches!(
+        value("list"),
+        ExportedValue::Cbor { diagnostic, .. } if diagnostic == "[1, 2]"
+    ));
+
+    let response = registry
+        .respond_success(&imported.handle, &server)
+        .unwrap()
+        .with_result("done")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(request.id()),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        response
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "Client state."
+    );
+
+    let export = VerifiedResponseExport::from(&response);
+    assert!(export.is_ok);
+    assert_eq!(export.result, Some(ExportedValue::Text("done".into())));
+    assert!(export.has_state);
+    assert_eq!(
+        export.state,
+        Some(Envelope::new("Client state.").to_cbor_data())
+    );
+
+    // The handle is forgotten once answered.
+    assert!(matches!(
+        registry.respond_success(&imported.handle, &server),
+        Err(Error::UnknownExportHandle(_))
+    ));
+}
+
+#[test]
+fn test_export_event() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, _) = new_party(&mut rng);
+
+    let event = SealedEvent::<String>::new("Door open", ARID::new(), &sender)
+        .with_date(Date::from_ymd(2026, 1, 1));
+    let export = VerifiedEventExport::from(&event);
+    let json = serde_json::to_value(&export).unwrap();
+    assert_eq!(
+        json["content"],
+        serde_json::json!({ "type": "text", "value": "Door open" })
+    );
+    assert_eq!(json["date"], "2026-01-01");
+    assert_eq!(
+        serde_json::from_value::<VerifiedEventExport>(json).unwrap(),
+        export
+    );
+}
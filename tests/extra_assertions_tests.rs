@@ -0,0 +1,168 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn object_for(
+    assertions: &[Envelope],
+    predicate: impl EnvelopeEncodable,
+) -> String {
+    let predicate = predicate.into_envelope();
+    assertions
+        .iter()
+        .find(|assertion| {
+            assertion
+                .try_predicate()
+                .unwrap()
+                .is_identical_to(&predicate)
+        })
+        .unwrap()
+        .try_object()
+        .unwrap()
+        .extract_subject()
+        .unwrap()
+}
+
+#[test]
+fn test_extra_assertions_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_note("A note.")
+        .with_extra_assertion("tenant", "acme")
+        .with_extra_assertion(known_values::ID, "trace-42");
+    let sealed = request
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed = SealedRequest::try_from_envelope(
+        &sealed,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.extra_assertions().len(), 2);
+    assert_eq!(object_for(parsed.extra_assertions(), "tenant"), "acme");
+    assert_eq!(
+        object_for(parsed.extra_assertions(), known_values::ID),
+        "trace-42"
+    );
+    assert_eq!(parsed.note(), "A note.");
+
+    let response = SealedResponse::new_success(parsed.id(), &server)
+        .with_result("ok")
+        .with_extra_assertion("tenant", "acme")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parsed = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(object_for(parsed.extra_assertions(), "tenant"), "acme");
+
+    let event = SealedEvent::<String>::new("Notice.", ARID::new(), &server)
+        .with_extra_assertion(known_values::ID, "trace-43")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parsed = SealedEvent::<String>::try_from_envelope(
+        &event,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        object_for(parsed.extra_assertions(), known_values::ID),
+        "trace-43"
+    );
+}
+
+#[test]
+fn test_extra_assertions_are_signed() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let sealed = SealedRequest::new("test", ARID::new(), &client)
+        .with_extra_assertion("tenant", "acme")
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let signed = sealed
+        .decrypt_subject_to_recipient(&server_private_keys)
+        .unwrap()
+        .try_unwrap()
+        .unwrap();
+    let message = signed.try_unwrap().unwrap();
+    let tampered = message
+        .remove_assertion(Envelope::new_assertion("tenant", "acme"))
+        .add_assertion("tenant", "evil")
+        .wrap();
+    let signatures = signed.assertions();
+    let tampered = signatures
+        .iter()
+        .fold(tampered, |envelope, signature| {
+            envelope.add_assertion_envelope(signature).unwrap()
+        })
+        .wrap()
+        .encrypt_subject_to_recipient(server.encryption_key().unwrap())
+        .unwrap();
+    assert!(
+        SealedRequest::try_from_envelope(
+            &tampered,
+            None,
+            None,
+            &server_private_keys
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_reserved_predicates_are_rejected() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let reserved = gstp::reserved_predicates();
+    assert!(
+        reserved
+            .iter()
+            .any(|p| p.is_identical_to(&Envelope::new(known_values::NOTE)))
+    );
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_extra_assertion(known_values::NOTE, "Not a note.");
+    match request.to_envelope(None, Some(&client_private_keys), Some(&server)) {
+        Err(Error::ReservedPredicate {
+            predicate,
+            reserved,
+        }) => {
+            assert_eq!(predicate, "'note'");
+            assert!(reserved.contains(&"'senderContinuation'".to_string()));
+            assert!(reserved.contains(&"\"provenance\"".to_string()));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_extra_assertion("sessionAck", "Not an acknowledgment.");
+    assert!(matches!(
+        response.to_envelope(None, Some(&server_private_keys), Some(&client)),
+        Err(Error::ReservedPredicate { .. })
+    ));
+}
@@ -0,0 +1,120 @@
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use gstp::{
+    framing::{MAGIC, read_frame, write_frame},
+    prelude::*,
+};
+
+fn envelopes() -> Vec<Envelope> {
+    bc_envelope::register_tags();
+
+    (0..3)
+        .map(|i| {
+            Request::new("test", ARID::new())
+                .with_parameter("index", i)
+                .into_envelope()
+        })
+        .collect()
+}
+
+#[test]
+fn test_back_to_back_frames() {
+    let envelopes = envelopes();
+    let mut stream = Vec::new();
+    for envelope in &envelopes {
+        write_frame(&mut stream, envelope).unwrap();
+    }
+    assert_eq!(&stream[..MAGIC.len()], &MAGIC);
+
+    let limits = ParseLimits::default();
+    let mut reader = stream.as_slice();
+    for envelope in &envelopes {
+        assert_eq!(&read_frame(&mut reader, &limits).unwrap(), envelope);
+    }
+    assert!(matches!(
+        read_frame(&mut reader, &limits),
+        Err(Error::EndOfStream)
+    ));
+}
+
+#[test]
+fn test_truncated_final_frame() {
+    let envelopes = envelopes();
+    let mut stream = Vec::new();
+    for envelope in &envelopes {
+        write_frame(&mut stream, envelope).unwrap();
+    }
+    stream.truncate(stream.len() - 5);
+
+    let limits = ParseLimits::default();
+    let mut reader = stream.as_slice();
+    read_frame(&mut reader, &limits).unwrap();
+    read_frame(&mut reader, &limits).unwrap();
+    assert!(matches!(
+        read_frame(&mut reader, &limits),
+        Err(Error::FrameTruncated)
+    ));
+
+    // A stream that ends inside the header is also truncated.
+    let mut reader = &stream[..3];
+    assert!(matches!(
+        read_frame(&mut reader, &limits),
+        Err(Error::FrameTruncated)
+    ));
+}
+
+#[test]
+fn test_oversized_declared_length() {
+    // The header declares a 4 GiB payload that is never sent.
+    let mut stream = MAGIC.to_vec();
+    stream.push(1);
+    stream.extend_from_slice(&u32::MAX.to_be_bytes());
+
+    let limits = ParseLimits::new().with_max_message_size(1024);
+    assert!(matches!(
+        read_frame(stream.as_slice(), &limits),
+        Err(Error::FrameTooLarge { size, limit: 1024 }) if size == u32::MAX as usize
+    ));
+}
+
+#[test]
+fn test_wrong_magic_and_version() {
+    let limits = ParseLimits::default();
+
+    let stream = b"HTTP/1.1 200 OK\r\n".to_vec();
+    assert!(matches!(
+        read_frame(stream.as_slice(), &limits),
+        Err(Error::FrameMagicMismatch)
+    ));
+
+    let mut stream = Vec::new();
+    write_frame(&mut stream, &envelopes()[0]).unwrap();
+    stream[MAGIC.len()] = 99;
+    assert!(matches!(
+        read_frame(stream.as_slice(), &limits),
+        Err(Error::FrameVersionUnsupported(99))
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_frames() {
+    use gstp::framing::r#async;
+
+    let envelopes = envelopes();
+    let mut stream = Vec::new();
+    for envelope in &envelopes {
+        r#async::write_frame(&mut stream, envelope).await.unwrap();
+    }
+
+    let limits = ParseLimits::default();
+    let mut reader = stream.as_slice();
+    for envelope in &envelopes {
+        let frame = r#async::read_frame(&mut reader, &limits).await.unwrap();
+        assert_eq!(&frame, envelope);
+    }
+    assert!(matches!(
+        r#async::read_frame(&mut reader, &limits).await,
+        Err(Error::EndOfStream)
+    ));
+}
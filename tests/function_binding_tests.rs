@@ -0,0 +1,168 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_server_state_bound_to_function() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server issues a cursor that may only be returned to `nextPage`.
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state("cursor")
+        .with_state_function("nextPage")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    let continuation = response.peer_continuation().unwrap().clone();
+
+    let send = |function: &str| {
+        let request = SealedRequest::new(function, ARID::new(), &client)
+            .with_peer_continuation(continuation.clone())
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            None,
+            &server_private_keys,
+        )
+    };
+    let request = send("nextPage").unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "cursor"
+    );
+    let error = send("deleteAll").unwrap_err();
+    assert!(matches!(
+        &error,
+        Error::ContinuationFunctionMismatch { expected, found }
+            if *expected == Function::from("nextPage")
+                && *found == Function::from("deleteAll")
+    ));
+}
+
+#[test]
+fn test_client_state_bound_to_request_function() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The client binds its state to the function it calls.
+    let id = ARID::new();
+    let envelope = SealedRequest::new("fetch", id, &client)
+        .with_state("pending fetch")
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::new().with_function_binding(true),
+        )
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+
+    let parse = |options: ParseOptions| {
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &envelope,
+            &options.with_expected_id(id),
+            &client_private_keys,
+        )
+    };
+    let response =
+        parse(ParseOptions::new().with_expected_function("fetch")).unwrap();
+    assert_eq!(
+        response
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "pending fetch"
+    );
+    assert!(matches!(
+        parse(ParseOptions::new().with_expected_function("store")),
+        Err(Error::ContinuationFunctionMismatch { .. })
+    ));
+
+    // A response names no function, so without an expected function the
+    // bound state is refused rather than accepted unchecked.
+    assert!(matches!(
+        parse(ParseOptions::new()),
+        Err(Error::ContinuationFunctionMissing(expected))
+            if expected == Function::from("fetch")
+    ));
+}
+
+#[test]
+fn test_request_for_unexpected_function() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let seal = |function: Function| {
+        SealedRequest::new(function, ARID::new(), &client)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap()
+    };
+    let parse = |envelope: &Envelope, expected: Function| {
+        SealedRequest::try_from_envelope_opt(
+            envelope,
+            &ParseOptions::new().with_expected_function(expected),
+            &server_private_keys,
+        )
+    };
+
+    // An endpoint expecting "other" rejects a request to "test".
+    let envelope = seal("test".into());
+    assert!(parse(&envelope, "test".into()).is_ok());
+    assert!(matches!(
+        parse(&envelope, "other".into()),
+        Err(Error::UnexpectedFunction { expected, found })
+            if expected == Function::from("other")
+                && found == Function::from("test")
+    ));
+
+    // Known-value functions are compared by value.
+    let envelope = seal(Function::new_known(100, Some("test".into())));
+    assert!(parse(&envelope, Function::from(100)).is_ok());
+    assert!(matches!(
+        parse(&envelope, Function::from(101)),
+        Err(Error::UnexpectedFunction { .. })
+    ));
+    assert!(matches!(
+        parse(&envelope, "test".into()),
+        Err(Error::UnexpectedFunction { .. })
+    ));
+}
@@ -0,0 +1,292 @@
+//! Golden transcript of a request, response, and event round trip.
+//!
+//! Each message is signed with Ed25519 keys from fixed seeds, which sign
+//! deterministically, and compared byte-for-byte with the fixtures in
+//! `tests/golden`. Continuations are encrypted with a random nonce, so they
+//! are elided before comparison; eliding keeps their digests, so the
+//! signature still verifies and any change to what they contain is still
+//! caught. For the same reason they are sealed without their random nonces
+//! and with a fixed symmetric key.
+//!
+//! The encrypted layer seals a random content key, so its bytes are never
+//! the same twice. Its structure is compared with a `.encrypted.txt`
+//! fixture instead, and the encrypted subject is checked to carry the digest
+//! of the signed message.
+//!
+//! After an intentional change to the message format, regenerate the
+//! fixtures with:
+//!
+//! ```sh
+//! GSTP_REGENERATE_GOLDEN=1 cargo test --test golden_tests
+//! ```
+//!
+//! and add an entry to the version history in `README.md` that mentions the
+//! new `MARKER`, which the test checks for.
+
+mod common;
+
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use bc_components::{ARID, Digest, PrivateKeys, SignatureScheme, SymmetricKey};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{ContinuationSealer, prelude::*};
+use hex_literal::hex;
+
+use crate::common::new_party_signing_with;
+
+const REGENERATE: &str = "GSTP_REGENERATE_GOLDEN";
+
+fn now() -> Date { Date::from_string("2024-07-01T12:00:00Z").unwrap() }
+
+fn continuation_key() -> SymmetricKey {
+    SymmetricKey::from_data(hex!(
+        "3f1d2c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f000"
+    ))
+}
+
+fn options() -> SealOptions {
+    SealOptions::default()
+        .with_now(now())
+        .with_continuation_nonces(false)
+        .with_continuation_sealer(ContinuationSealer::Symmetric(
+            continuation_key(),
+        ))
+}
+
+fn request_id() -> ARID {
+    ARID::from_data(hex!(
+        "c66be27dbad7cd095ca77647406d07976dc0f35f0d4d654bb0e96dd227a1e9fc"
+    ))
+}
+
+fn event_id() -> ARID {
+    ARID::from_data(hex!(
+        "9b2b0a3e3f2ab5b3d6d9c3e1f0a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2"
+    ))
+}
+
+/// One message of the transcript in both of its sealed layers.
+struct Sealed {
+    name: &'static str,
+    sender: XIDDocument,
+    signed: Envelope,
+    encrypted: Envelope,
+}
+
+/// Elides the encrypted continuations of a signed message, dropping their
+/// randomly sealed ciphertext so that only what sealing derives
+/// deterministically is left.
+fn canonical(signed: &Envelope) -> Envelope {
+    let message = signed.try_unwrap().unwrap();
+    let encrypted: HashSet<Digest> = message
+        .assertions()
+        .into_iter()
+        .filter_map(|assertion| assertion.try_object().ok())
+        .map(|object| object.subject())
+        .filter(|subject| subject.is_encrypted())
+        .map(|subject| subject.digest())
+        .collect();
+    signed.elide_removing_set(&encrypted)
+}
+
+/// Seals a message both signed only and signed then encrypted.
+fn seal(
+    name: &'static str,
+    sender: (&XIDDocument, &PrivateKeys),
+    recipient: &XIDDocument,
+    to_envelope: impl Fn(&PrivateKeys, &[&XIDDocument]) -> Envelope,
+) -> Sealed {
+    Sealed {
+        name,
+        sender: sender.0.clone(),
+        signed: to_envelope(sender.1, &[]),
+        encrypted: to_envelope(sender.1, &[recipient]),
+    }
+}
+
+/// Seals the transcript, returning each message by name.
+fn transcript() -> Vec<Sealed> {
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_keys) =
+        new_party_signing_with(SignatureScheme::Ed25519, &mut rng);
+    let (server, server_keys) =
+        new_party_signing_with(SignatureScheme::Ed25519, &mut rng);
+    let valid_until = now() + std::time::Duration::from_secs(60);
+
+    let request = seal(
+        "request",
+        (&client, &client_keys),
+        &server,
+        |keys, recipients| {
+            SealedRequest::new("getRecords", request_id(), &client)
+                .with_parameter("fromRecord", 100)
+                .with_parameter("toRecord", 199)
+                .with_note("Golden request")
+                .with_date(now())
+                .with_state("Client state.")
+                .to_envelope_with_options(
+                    Some(valid_until),
+                    Some(keys),
+                    recipients,
+                    &options(),
+                )
+                .unwrap()
+        },
+    );
+    let client_continuation = request
+        .signed
+        .try_unwrap()
+        .unwrap()
+        .object_for_predicate(known_values::SENDER_CONTINUATION)
+        .ok();
+
+    let response = seal(
+        "response",
+        (&server, &server_keys),
+        &client,
+        |keys, recipients| {
+            SealedResponse::new_success(request_id(), &server)
+                .with_result("Records retrieved: 100-199")
+                .with_state("Server state.")
+                .with_optional_peer_continuation(client_continuation.clone())
+                .to_envelope_with_options(
+                    Some(valid_until),
+                    Some(keys),
+                    recipients,
+                    &options(),
+                )
+                .unwrap()
+        },
+    );
+
+    let event = seal(
+        "event",
+        (&server, &server_keys),
+        &client,
+        |keys, recipients| {
+            SealedEvent::<String>::new(
+                "Records changed.".to_string(),
+                event_id(),
+                &server,
+            )
+            .with_note("Golden event")
+            .with_date(now())
+            .to_envelope_with_options(
+                Some(valid_until),
+                Some(keys),
+                recipients,
+                &options(),
+            )
+            .unwrap()
+        },
+    );
+
+    vec![request, response, event]
+}
+
+/// The fixture files of the transcript, by name.
+fn fixtures(transcript: &[Sealed]) -> Vec<(String, String)> {
+    transcript
+        .iter()
+        .flat_map(|sealed| {
+            [
+                (
+                    format!("{}.hex", sealed.name),
+                    to_hex(&canonical(&sealed.signed).to_cbor_data()),
+                ),
+                (
+                    format!("{}.encrypted.txt", sealed.name),
+                    sealed.encrypted.format(),
+                ),
+            ]
+        })
+        .collect()
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Identifies a version of the transcript, so that a change to it can be
+/// tied to a changelog entry.
+fn marker(fixtures: &[(String, String)]) -> String {
+    let image: Vec<u8> = fixtures
+        .iter()
+        .flat_map(|(_, contents)| contents.as_bytes().to_vec())
+        .collect();
+    Digest::from_image(image).short_description()
+}
+
+#[test]
+fn test_transcript_is_deterministic() {
+    bc_envelope::register_tags();
+    assert_eq!(fixtures(&transcript()), fixtures(&transcript()));
+}
+
+#[test]
+fn test_golden_layers() {
+    bc_envelope::register_tags();
+    for sealed in transcript() {
+        let canonical = canonical(&sealed.signed);
+        assert_eq!(canonical.digest(), sealed.signed.digest());
+        canonical
+            .verify(sealed.sender.verification_key().unwrap())
+            .unwrap();
+        assert!(sealed.encrypted.subject().is_encrypted());
+        assert_eq!(
+            sealed.encrypted.subject().digest(),
+            sealed.signed.wrap().digest(),
+            "the encrypted {} does not seal its signed message",
+            sealed.name
+        );
+    }
+}
+
+#[test]
+fn test_golden_transcript() {
+    bc_envelope::register_tags();
+    let fixtures = fixtures(&transcript());
+    let marker = marker(&fixtures);
+    let dir = golden_dir();
+
+    if std::env::var_os(REGENERATE).is_some() {
+        fs::create_dir_all(&dir).unwrap();
+        for (file, contents) in &fixtures {
+            fs::write(dir.join(file), format!("{}\n", contents.trim_end()))
+                .unwrap();
+        }
+        fs::write(dir.join("MARKER"), format!("{marker}\n")).unwrap();
+    }
+
+    for (file, contents) in &fixtures {
+        let path = dir.join(file);
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!("missing {}; run with {REGENERATE}=1", path.display())
+        });
+        assert_eq!(
+            contents.trim_end(),
+            expected.trim_end(),
+            "{file} differs from its golden fixture; if the change is \
+             intentional, run with {REGENERATE}=1"
+        );
+    }
+
+    let committed = fs::read_to_string(dir.join("MARKER")).unwrap();
+    assert_eq!(committed.trim(), marker);
+
+    let readme = fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("README.md"),
+    )
+    .unwrap();
+    assert!(
+        readme.contains(&format!("golden transcript `{marker}`")),
+        "the version history in README.md must mention golden transcript \
+         `{marker}`"
+    );
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{consts, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_same_version() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.gstp_version(), Some(consts::PROTOCOL_VERSION));
+
+    let response = SealedResponse::new_success(request.id(), &server)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.gstp_version(), Some(consts::PROTOCOL_VERSION));
+
+    let event =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &server)
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap();
+    let event = SealedEvent::<String>::try_from_envelope(
+        &event,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.gstp_version(), Some(consts::PROTOCOL_VERSION));
+
+    // Messages built locally have no peer version.
+    assert_eq!(
+        SealedRequest::new("test", ARID::new(), &client).gstp_version(),
+        None
+    );
+}
+
+#[test]
+fn test_older_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // A peer predating the version assertion sends none.
+    let signed = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope_unprotected_i_know_what_i_am_doing(
+            None,
+            Some(&client_private_keys),
+            &[],
+            &SealOptions::new(),
+        )
+        .unwrap();
+    let message = signed.try_unwrap().unwrap();
+    let version = message.assertion_with_predicate("gstpVersion").unwrap();
+    let legacy = message.remove_assertion(version).sign(&client_private_keys);
+
+    let request =
+        SealedRequest::try_from_signed_envelope(&legacy, None, None).unwrap();
+    assert_eq!(request.gstp_version(), Some(1));
+}
+
+#[test]
+fn test_newer_peer() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let newer =
+        SealOptions::new().with_gstp_version(consts::PROTOCOL_VERSION + 1);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &newer,
+        )
+        .unwrap();
+    assert!(matches!(
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            None,
+            &server_private_keys,
+        ),
+        Err(Error::UnsupportedGstpVersion { found, supported })
+            if found == consts::PROTOCOL_VERSION + 1
+                && supported == consts::PROTOCOL_VERSION
+    ));
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &newer,
+        )
+        .unwrap();
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope(
+            &response,
+            None,
+            None,
+            &client_private_keys,
+        ),
+        Err(Error::UnsupportedGstpVersion { .. })
+    ));
+
+    let event =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &server)
+            .to_envelope_with_options(
+                None,
+                Some(&server_private_keys),
+                &[&client],
+                &newer,
+            )
+            .unwrap();
+    assert!(matches!(
+        SealedEvent::<String>::try_from_envelope(
+            &event,
+            None,
+            None,
+            &client_private_keys,
+        ),
+        Err(Error::UnsupportedGstpVersion { .. })
+    ));
+}
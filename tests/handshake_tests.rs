@@ -0,0 +1,127 @@
+mod common;
+
+use bc_components::XIDProvider;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{HandshakeInitiator, HandshakeResponder, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_handshake_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (alice, alice_private_keys) = new_party(&mut rng);
+    let (bob, bob_private_keys) = new_party(&mut rng);
+
+    let initiator = HandshakeInitiator::new(&alice);
+    let mut responder = HandshakeResponder::new(&bob);
+
+    // Alice does not know Bob's document yet, so her introduction is only
+    // signed.
+    let introduce = initiator
+        .introduce()
+        .to_envelope_unprotected_i_know_what_i_am_doing(
+            None,
+            Some(&alice_private_keys),
+            &[],
+            &SealOptions::new(),
+        )
+        .unwrap();
+    let introduce =
+        SealedRequest::try_from_signed_envelope(&introduce, None, None)
+            .unwrap();
+    let ack = responder
+        .acknowledge(&introduce)
+        .unwrap()
+        .to_envelope(None, Some(&bob_private_keys), Some(&alice))
+        .unwrap();
+    assert_eq!(responder.pending(), 1);
+
+    let ack = SealedResponse::try_from_encrypted_envelope(
+        &ack,
+        Some(initiator.id()),
+        None,
+        &alice_private_keys,
+    )
+    .unwrap();
+    let (bob_info, complete) = initiator.complete(&ack).unwrap();
+    assert_eq!(bob_info.xid(), bob.xid());
+    assert_eq!(bob_info.gstp_version(), gstp::consts::PROTOCOL_VERSION);
+
+    let complete = complete
+        .to_envelope(None, Some(&alice_private_keys), Some(&bob))
+        .unwrap();
+    let complete = SealedRequest::try_from_envelope(
+        &complete,
+        None,
+        None,
+        &bob_private_keys,
+    )
+    .unwrap();
+    let alice_info = responder.complete(&complete).unwrap();
+    assert_eq!(alice_info.xid(), alice.xid());
+    assert_eq!(
+        alice_info.encapsulation_scheme(),
+        bob_info.encapsulation_scheme()
+    );
+    assert_eq!(responder.pending(), 0);
+
+    // The handshake cannot be completed twice.
+    assert!(matches!(
+        responder.complete(&complete),
+        Err(Error::NoPendingIntroduction(xid)) if xid == alice.xid()
+    ));
+}
+
+#[test]
+fn test_handshake_challenge_mismatch() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (alice, _) = new_party(&mut rng);
+    let (bob, _) = new_party(&mut rng);
+
+    // An acknowledgment of one introduction does not complete another.
+    let first = HandshakeInitiator::new(&alice);
+    let second = HandshakeInitiator::new(&alice);
+    let mut responder = HandshakeResponder::new(&bob);
+    let ack = responder.acknowledge(&first.introduce()).unwrap();
+    let replayed = SealedResponse::new_success(second.id(), &bob)
+        .with_result(ack.result().unwrap().clone());
+    assert!(matches!(
+        second.complete(&replayed),
+        Err(Error::ChallengeMismatch)
+    ));
+
+    // Nor does a completion that answers a stale challenge.
+    let (_, complete) = first.complete(&ack).unwrap();
+    responder.acknowledge(&second.introduce()).unwrap();
+    assert!(matches!(
+        responder.complete(&complete),
+        Err(Error::ChallengeMismatch)
+    ));
+}
+
+#[test]
+fn test_handshake_unsupported_scheme() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (alice, _) = new_party(&mut rng);
+    let (bob, _) = new_party(&mut rng);
+
+    let initiator = HandshakeInitiator::new(&alice);
+    let scheme = alice.encryption_key().unwrap().encapsulation_scheme();
+    let mut responder = HandshakeResponder::new(&bob).with_accepted_schemes([]);
+    assert!(matches!(
+        responder.acknowledge(&initiator.introduce()),
+        Err(Error::UnsupportedScheme(found)) if found == scheme
+    ));
+    assert_eq!(responder.pending(), 0);
+
+    let mut responder =
+        HandshakeResponder::new(&bob).with_accepted_schemes([scheme]);
+    assert!(responder.acknowledge(&initiator.introduce()).is_ok());
+}
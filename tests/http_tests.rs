@@ -0,0 +1,271 @@
+#![cfg(feature = "http")]
+
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{
+    http::{self, MEDIA_TYPE},
+    prelude::*,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::common::new_party;
+
+struct Parties {
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+}
+
+fn parties() -> Parties {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    Parties {
+        server,
+        server_private_keys,
+        client,
+        client_private_keys,
+    }
+}
+
+/// Answers a single HTTP request on a local port with `respond`, which is
+/// given the request's content type and body, returning the URL to post to.
+async fn serve_once<F>(respond: F) -> String
+where
+    F: FnOnce(Option<String>, Vec<u8>) -> (u16, &'static str, Vec<u8>)
+        + Send
+        + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/gstp", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let header_end = loop {
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+            if let Some(i) = received.windows(4).position(|w| w == b"\r\n\r\n")
+            {
+                break i + 4;
+            }
+        };
+        let head = String::from_utf8(received[..header_end].to_vec()).unwrap();
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let length: usize = header("content-length").unwrap().parse().unwrap();
+        let mut body = received[header_end..].to_vec();
+        while body.len() < length {
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            body.extend_from_slice(&buf[..n]);
+        }
+
+        let (status, content_type, body) =
+            respond(header("content-type"), body);
+        let head = format!(
+            "HTTP/1.1 {status} Status\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        socket.write_all(head.as_bytes()).await.unwrap();
+        socket.write_all(&body).await.unwrap();
+        socket.shutdown().await.unwrap();
+    });
+    url
+}
+
+#[test]
+fn test_body_round_trip() {
+    let parties = parties();
+    let sealed = SealedRequest::new("test", ARID::new(), &parties.client)
+        .to_envelope(
+            None,
+            Some(&parties.client_private_keys),
+            Some(&parties.server),
+        )
+        .unwrap();
+    let body = http::to_body(&sealed);
+
+    for content_type in [
+        MEDIA_TYPE,
+        "Application/Gordian-Sealed-Transaction+CBOR",
+        "application/gordian-sealed-transaction+cbor; charset=binary",
+    ] {
+        let envelope = http::from_body(Some(content_type), &body).unwrap();
+        assert!(envelope.is_identical_to(&sealed));
+    }
+
+    for content_type in [None, Some("application/cbor"), Some("text/plain")] {
+        assert!(matches!(
+            http::from_body(content_type, &body),
+            Err(Error::UnexpectedContentType(found))
+                if found.as_deref() == content_type
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_send_sealed_request() {
+    let parties = parties();
+    let server = parties.server.clone();
+    let server_private_keys = parties.server_private_keys.clone();
+    let url = serve_once(move |content_type, body| {
+        let envelope = http::from_body(content_type.as_deref(), &body).unwrap();
+        let request = SealedRequest::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .unwrap();
+        let amount: u32 =
+            request.extract_object_for_parameter("amount").unwrap();
+        let response = SealedResponse::new_success(request.id(), &server)
+            .with_result(amount * 2)
+            .to_envelope(
+                None,
+                Some(&server_private_keys),
+                Some(request.sender()),
+            )
+            .unwrap();
+        (200, MEDIA_TYPE, http::to_body(&response))
+    })
+    .await;
+
+    let id = ARID::new();
+    let request = SealedRequest::new("double", id, &parties.client)
+        .with_parameter("amount", 21);
+    let response = http::send_sealed_request(
+        &reqwest::Client::new(),
+        url,
+        &request,
+        &parties.client_private_keys,
+        &parties.server,
+        &ParseOptions::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.id(), Some(id));
+    assert_eq!(response.extract_result::<u32>().unwrap(), 42);
+}
+
+#[tokio::test]
+async fn test_error_status() {
+    let parties = parties();
+    let request = SealedRequest::new("test", ARID::new(), &parties.client);
+    let client = reqwest::Client::new();
+    let options = ParseOptions::new();
+    let send = |url: String| {
+        http::send_sealed_request(
+            &client,
+            url,
+            &request,
+            &parties.client_private_keys,
+            &parties.server,
+            &options,
+        )
+    };
+
+    let url =
+        serve_once(|_, _| (503, "text/plain", b"Service Unavailable".to_vec()))
+            .await;
+    assert!(matches!(
+        send(url).await,
+        Err(Error::HttpStatus {
+            status: 503,
+            envelope: None
+        })
+    ));
+
+    // A sealed early failure survives an error status.
+    let server = parties.server.clone();
+    let server_private_keys = parties.server_private_keys.clone();
+    let client_document = parties.client.clone();
+    let url = serve_once(move |_, _| {
+        let failure = SealedResponse::new_early_failure(&server)
+            .with_error("cannot decrypt")
+            .to_envelope(
+                None,
+                Some(&server_private_keys),
+                Some(&client_document),
+            )
+            .unwrap();
+        (400, MEDIA_TYPE, http::to_body(&failure))
+    })
+    .await;
+    let Err(Error::HttpStatus {
+        status: 400,
+        envelope: Some(envelope),
+    }) = send(url).await
+    else {
+        panic!("expected an error status carrying an envelope");
+    };
+    let failure = SealedResponse::try_parse_early_failure(
+        &envelope,
+        &parties.client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        failure.error.extract_subject::<String>().unwrap(),
+        "cannot decrypt"
+    );
+}
+
+#[tokio::test]
+async fn test_unexpected_content_type() {
+    let parties = parties();
+    let request = SealedRequest::new("test", ARID::new(), &parties.client);
+    let url =
+        serve_once(|_, _| (200, "application/json", b"{}".to_vec())).await;
+    let result = http::send_sealed_request(
+        &reqwest::Client::new(),
+        url,
+        &request,
+        &parties.client_private_keys,
+        &parties.server,
+        &ParseOptions::new(),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(Error::UnexpectedContentType(Some(found)))
+            if found == "application/json"
+    ));
+}
+
+#[tokio::test]
+async fn test_connection_failure() {
+    let parties = parties();
+    let request = SealedRequest::new("test", ARID::new(), &parties.client);
+
+    // Nothing listens on a port once its listener is dropped.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/gstp", listener.local_addr().unwrap());
+    drop(listener);
+
+    let result = http::send_sealed_request(
+        &reqwest::Client::new(),
+        url,
+        &request,
+        &parties.client_private_keys,
+        &parties.server,
+        &ParseOptions::new(),
+    )
+    .await;
+    assert!(matches!(result, Err(Error::Http(_))));
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, ReferenceProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{MemoryKeyDirectory, inspect, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_select_tenant_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let tenants: Vec<(XIDDocument, PrivateKeys)> =
+        (0..3).map(|_| new_party(&mut rng)).collect();
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let mut directory = MemoryKeyDirectory::new();
+    let fingerprints: Vec<_> = tenants
+        .iter()
+        .map(|(_, private_keys)| {
+            directory.insert(private_keys.clone()).unwrap()
+        })
+        .collect();
+
+    let tenant = &tenants[1].0;
+    let request = SealedRequest::new("test", ARID::new(), &client);
+    let envelope = request
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[tenant],
+            &SealOptions::new().with_recipient_hints(true),
+        )
+        .unwrap();
+
+    assert_eq!(
+        inspect::recipient_key_fingerprints(&envelope).unwrap(),
+        vec![tenant.encryption_key().unwrap().reference()]
+    );
+
+    let (parsed, fingerprint) =
+        SealedRequest::try_from_envelope_with_directory(
+            &envelope, None, None, &directory,
+        )
+        .unwrap();
+    assert_eq!(fingerprint, fingerprints[1]);
+    assert_eq!(parsed.id(), request.id());
+}
+
+#[test]
+fn test_unregistered_tenant() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (registered, registered_private_keys) = new_party(&mut rng);
+    let (unregistered, _) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let mut directory = MemoryKeyDirectory::new();
+    directory.insert(registered_private_keys).unwrap();
+
+    let hinted = SealedResponse::new_success(ARID::new(), &client)
+        .with_result("ok")
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[&unregistered],
+            &SealOptions::new().with_recipient_hints(true),
+        )
+        .unwrap();
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope_with_directory(
+            &hinted, None, None, &directory,
+        ),
+        Err(Error::NoRegisteredRecipientKey)
+    ));
+
+    // Without hints even a registered recipient cannot be selected.
+    let unhinted = SealedResponse::new_success(ARID::new(), &client)
+        .with_result("ok")
+        .to_envelope(None, Some(&client_private_keys), Some(&registered))
+        .unwrap();
+    assert!(
+        inspect::recipient_key_fingerprints(&unhinted)
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        SealedResponse::try_from_encrypted_envelope_with_directory(
+            &unhinted, None, None, &directory,
+        ),
+        Err(Error::NoRegisteredRecipientKey)
+    ));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use bc_components::ARID;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_parse_after_key_rotation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, key1) = new_party(&mut rng);
+    let (rotated_server, key2) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server mints a continuation under key1.
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state("cursor")
+        .to_envelope(None, Some(&key1), Some(&client))
+        .unwrap();
+    let continuation = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap()
+    .peer_continuation()
+    .unwrap()
+    .clone();
+
+    // After rotating to key2, the client returns it to the new key, or to
+    // the old one if it has not yet seen the rotation.
+    let request_to = |recipient: &XIDDocument| {
+        SealedRequest::new("nextPage", ARID::new(), &client)
+            .with_peer_continuation(continuation.clone())
+            .to_envelope(None, Some(&client_private_keys), Some(recipient))
+            .unwrap()
+    };
+    let options = ParseOptions::new().with_previous_keys([key1.clone()]);
+    for recipient in [&rotated_server, &server] {
+        let request = SealedRequest::try_from_envelope_opt(
+            &request_to(recipient),
+            &options,
+            &key2,
+        )
+        .unwrap();
+        assert_eq!(
+            request
+                .state()
+                .unwrap()
+                .extract_subject::<String>()
+                .unwrap(),
+            "cursor"
+        );
+    }
+
+    // Without the old key, the continuation can no longer be read.
+    assert!(
+        SealedRequest::try_from_envelope_opt(
+            &request_to(&rotated_server),
+            &ParseOptions::new(),
+            &key2,
+        )
+        .is_err()
+    );
+
+    // When no key decrypts the message, the error says how many were tried.
+    let (_, unrelated) = new_party(&mut rng);
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &request_to(&server),
+            &ParseOptions::new().with_previous_keys([unrelated.clone()]),
+            &key2,
+        ),
+        Err(Error::NoKeyDecrypts { attempted: 2 })
+    ));
+    // With decryption diagnostics, the recipient slots are described instead.
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &request_to(&server),
+            &ParseOptions::new()
+                .with_previous_keys([unrelated])
+                .with_decryption_diagnostics(true),
+            &key2,
+        ),
+        Err(Error::NotARecipient(_))
+    ));
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use bc_components::{ARID, XID};
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{RequestProfile, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_request_from_keyless_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (_, signer_private_keys) = new_party(&mut rng);
+    let sender = XIDDocument::from(XID::from_data([1; 32]));
+
+    // The continuation cannot be encrypted to a sender without keys.
+    let error = SealedRequest::new("test", ARID::new(), &sender)
+        .to_envelope(None, Some(&signer_private_keys), Some(&server))
+        .unwrap_err();
+    assert!(matches!(error, Error::SenderMissingEncryptionKey));
+
+    // Without a continuation, the key-less document is embedded as it is.
+    SealedRequest::new("test", ARID::new(), &sender)
+        .to_envelope_with_options(
+            None,
+            Some(&signer_private_keys),
+            &[&server],
+            &SealOptions::default()
+                .with_request_profile(RequestProfile::OneWay),
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_event_from_keyless_sender() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (_, signer_private_keys) = new_party(&mut rng);
+    let sender = XIDDocument::from(XID::from_data([1; 32]));
+
+    let error =
+        SealedEvent::<String>::new("ping".to_string(), ARID::new(), &sender)
+            .to_envelope_for_recipients(
+                None,
+                Some(&signer_private_keys),
+                &[&server],
+            )
+            .unwrap_err();
+    assert!(matches!(error, Error::SenderMissingEncryptionKey));
+}
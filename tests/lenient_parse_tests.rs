@@ -0,0 +1,102 @@
+mod common;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_lenient_parse_of_valid_request() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let envelope = SealedRequest::new("transfer", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let (request, partial) = SealedRequest::try_from_envelope_lenient(
+        &envelope,
+        None,
+        None,
+        &server_private_keys,
+    );
+    assert!(request.is_some());
+    assert!(partial.signature_verified);
+    assert_eq!(partial.failed_stage(), None);
+}
+
+#[test]
+fn test_lenient_parse_of_forged_signature() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+    let (_, attacker_private_keys) = new_party(&mut rng);
+
+    // The attacker claims to be the client but can only sign with their own
+    // key.
+    let id = ARID::new();
+    let envelope = SealedRequest::new("transfer", id, &client)
+        .with_parameter("amount", 1000)
+        .to_envelope(None, Some(&attacker_private_keys), Some(&server))
+        .unwrap();
+
+    let (request, partial) = SealedRequest::try_from_envelope_lenient(
+        &envelope,
+        None,
+        None,
+        &server_private_keys,
+    );
+    assert!(request.is_none());
+    assert_eq!(partial.failed_stage(), Some(ParseStage::Signature));
+    assert!(!partial.signature_verified);
+    assert_eq!(partial.envelope_size, envelope.to_cbor_data().len());
+    assert!(partial.decrypted_size.is_some());
+    assert_eq!(partial.claimed_id, Some(id));
+    assert_eq!(partial.claimed_function, Some(Function::from("transfer")));
+    assert_eq!(partial.claimed_sender, Some(client.xid()));
+    assert!(matches!(partial.error, Some(Error::Envelope(_))));
+
+    // The strict parser reports the same failure.
+    assert!(
+        SealedRequest::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            &server_private_keys
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_lenient_parse_of_undecryptable_request() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, eavesdropper_private_keys) = new_party(&mut rng);
+
+    let envelope = SealedRequest::new("transfer", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let (request, partial) = SealedRequest::try_from_envelope_lenient(
+        &envelope,
+        None,
+        None,
+        &eavesdropper_private_keys,
+    );
+    assert!(request.is_none());
+    assert_eq!(partial.failed_stage(), Some(ParseStage::Decryption));
+    assert_eq!(partial.envelope_size, envelope.to_cbor_data().len());
+    assert_eq!(partial.decrypted_size, None);
+    assert_eq!(partial.claimed_id, None);
+    assert_eq!(partial.claimed_function, None);
+    assert_eq!(partial.claimed_sender, None);
+}
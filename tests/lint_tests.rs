@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::{
+    lint::{
+        LintConfig, LintRule, LintSeverity, lint_event, lint_request,
+        lint_response, lint_valid_until,
+    },
+    prelude::*,
+};
+
+fn party() -> XIDDocument {
+    XIDDocument::from(bc_components::XID::from_data([1; 32]))
+}
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+fn rules(findings: &[gstp::lint::LintFinding]) -> Vec<LintRule> {
+    findings.iter().map(|finding| finding.rule).collect()
+}
+
+#[test]
+fn test_clean_messages_have_no_findings() {
+    bc_envelope::register_tags();
+
+    let config = LintConfig::new();
+    let now = date("2024-07-01T12:00:00Z");
+    let request = SealedRequest::new("test", ARID::new(), party())
+        .with_parameter("amount", 100)
+        .with_note("Monthly transfer.")
+        .with_date(now);
+    assert!(lint_request(&request, &config).is_empty());
+
+    let response = SealedResponse::new_success(ARID::new(), party())
+        .with_result("ok")
+        .with_state("Next step.");
+    assert!(lint_response(&response, &config).is_empty());
+
+    let event =
+        SealedEvent::<String>::new("ping", ARID::new(), party()).with_date(now);
+    assert!(lint_event(&event, &config).is_empty());
+
+    assert!(
+        lint_valid_until(Some(now + Duration::from_secs(60)), now, &config)
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_missing_dates() {
+    bc_envelope::register_tags();
+
+    let config = LintConfig::new();
+    let request = SealedRequest::new("test", ARID::new(), party());
+    assert_eq!(
+        rules(&lint_request(&request, &config)),
+        [LintRule::RequestMissingDate]
+    );
+    let event = SealedEvent::<String>::new("ping", ARID::new(), party());
+    assert_eq!(
+        rules(&lint_event(&event, &config)),
+        [LintRule::EventMissingDate]
+    );
+}
+
+#[test]
+fn test_continuation_expiry() {
+    let config = LintConfig::new()
+        .with_max_continuation_validity(Duration::from_secs(3600));
+    let now = date("2024-07-01T12:00:00Z");
+    assert_eq!(
+        rules(&lint_valid_until(None, now, &config)),
+        [LintRule::ContinuationMissingExpiry]
+    );
+    assert_eq!(
+        rules(&lint_valid_until(
+            Some(now + Duration::from_secs(3601)),
+            now,
+            &config
+        )),
+        [LintRule::ContinuationExpiryTooLong]
+    );
+}
+
+#[test]
+fn test_failures() {
+    bc_envelope::register_tags();
+
+    let config = LintConfig::new();
+    let failure = SealedResponse::new_failure(ARID::new(), party());
+    assert_eq!(
+        rules(&lint_response(&failure, &config)),
+        [LintRule::FailureWithoutError]
+    );
+    let failure = failure.with_error("Insufficient funds.");
+    assert!(lint_response(&failure, &config).is_empty());
+
+    let early = SealedResponse::new_early_failure(party())
+        .with_error("Could not decrypt request.")
+        .with_peer_continuation(Envelope::new("Continuation."));
+    let findings = lint_response(&early, &config);
+    assert_eq!(
+        rules(&findings),
+        [LintRule::EarlyFailureCarriesContinuation]
+    );
+    assert_eq!(findings[0].severity, LintSeverity::Error);
+}
+
+#[test]
+fn test_notes() {
+    bc_envelope::register_tags();
+
+    let now = date("2024-07-01T12:00:00Z");
+    let request = SealedRequest::new("test", ARID::new(), party())
+        .with_date(now)
+        .with_note("The PASSWORD is hunter2, and this note is long.");
+    let config = LintConfig::new()
+        .with_field_limits(FieldLimits::new().with_max_note_length(16));
+    let findings = lint_request(&request, &config);
+    assert_eq!(
+        rules(&findings),
+        [LintRule::NoteContainsSecret, LintRule::NoteTooLong]
+    );
+    assert_eq!(findings[0].location, "note");
+    assert_eq!(findings[0].severity, LintSeverity::Error);
+
+    let request = SealedRequest::new("test", ARID::new(), party())
+        .with_date(now)
+        .with_note("Account 12-34.");
+    assert!(lint_request(&request, &LintConfig::new()).is_empty());
+    assert_eq!(
+        rules(&lint_request(
+            &request,
+            &LintConfig::new().with_secret_pattern("account")
+        )),
+        [LintRule::NoteContainsSecret]
+    );
+}
+
+#[test]
+fn test_sizes() {
+    bc_envelope::register_tags();
+
+    let config = LintConfig::new().with_field_limits(
+        FieldLimits::new()
+            .with_max_parameter_value_size(32)
+            .with_max_result_size(32)
+            .with_max_state_size(32),
+    );
+    let request = SealedRequest::new("test", ARID::new(), party())
+        .with_date(date("2024-07-01T12:00:00Z"))
+        .with_parameter("small", "x")
+        .with_parameter("large", "x".repeat(64))
+        .with_state("y".repeat(64));
+    let findings = lint_request(&request, &config);
+    assert_eq!(
+        rules(&findings),
+        [LintRule::StateTooLarge, LintRule::ParameterTooLarge]
+    );
+    assert_eq!(findings[1].location, "parameter \"large\"");
+
+    let response = SealedResponse::new_success(ARID::new(), party())
+        .with_result("z".repeat(64));
+    assert_eq!(
+        rules(&lint_response(&response, &config)),
+        [LintRule::ResultTooLarge]
+    );
+}
+
+#[test]
+fn test_disabled_rules() {
+    bc_envelope::register_tags();
+
+    let request =
+        SealedRequest::new("test", ARID::new(), party()).with_note("my secret");
+    let config = LintConfig::new()
+        .with_rule(LintRule::RequestMissingDate, false)
+        .with_rule(LintRule::NoteContainsSecret, false);
+    assert!(lint_request(&request, &config).is_empty());
+    assert_eq!(LintRule::ALL.len(), 11);
+    assert_eq!(LintRule::NoteTooLong.id(), "note-too-long");
+}
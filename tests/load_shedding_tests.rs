@@ -0,0 +1,103 @@
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use bc_components::{ARID, Encrypter};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    LoadShedDecision, LoadShedPolicy, overloaded_response,
+    overloaded_retry_after, prelude::*,
+};
+
+use crate::common::new_party;
+
+#[test]
+fn test_load_shedding() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let parse = |request: SealedRequest| {
+        let envelope = request
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        SealedRequest::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .unwrap()
+    };
+    let fresh = parse(SealedRequest::new("startExport", ARID::new(), &client));
+    let continuation = Continuation::new("page 2")
+        .to_envelope(Some(server.encryption_key().unwrap() as &dyn Encrypter));
+    let continuing = parse(
+        SealedRequest::new("nextPage", ARID::new(), &client)
+            .with_peer_continuation(continuation),
+    );
+
+    let pressure = Arc::new(AtomicU64::new(0));
+    let policy = {
+        let pressure = pressure.clone();
+        LoadShedPolicy::new(move || {
+            f64::from_bits(pressure.load(Ordering::Relaxed))
+        })
+        .with_thresholds(0.7, 0.9)
+        .with_retry_after(Duration::from_secs(30))
+    };
+    let shed = LoadShedDecision::Shed {
+        retry_after: Duration::from_secs(30),
+    };
+
+    for (level, fresh_decision, continuing_decision) in [
+        (0.5, LoadShedDecision::Admit, LoadShedDecision::Admit),
+        (0.8, shed, LoadShedDecision::Admit),
+        (0.95, shed, shed),
+    ] {
+        pressure.store(f64::to_bits(level), Ordering::Relaxed);
+        assert_eq!(policy.evaluate(&fresh), fresh_decision);
+        assert_eq!(policy.evaluate(&continuing), continuing_decision);
+    }
+
+    let stats = policy.stats();
+    assert_eq!(stats.fresh_threshold, 0.7);
+    assert_eq!(stats.continuing_threshold, 0.9);
+    assert_eq!(stats.last_pressure, 0.95);
+    assert_eq!(stats.admitted_fresh, 1);
+    assert_eq!(stats.shed_fresh, 2);
+    assert_eq!(stats.admitted_continuing, 2);
+    assert_eq!(stats.shed_continuing, 1);
+
+    // The client learns when to retry.
+    let id = fresh.id();
+    let response = overloaded_response(id, &server, Duration::from_secs(30))
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        overloaded_retry_after(&response),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        overloaded_retry_after(
+            &SealedResponse::new_success(id, &server).with_result("ok")
+        ),
+        None
+    );
+}
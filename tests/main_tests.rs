@@ -231,6 +231,7 @@ fn test_sealed_request() {
     assert_eq!(signed_client_request_envelope.format(), (indoc! {r#"
         {
             request(ARID(c66be27d)) [
+                "gstpVersion": 1
                 'body': «"test"» [
                     ❰"param1"❱: 42
                     ❰"param2"❱: "hello"
@@ -325,13 +326,13 @@ fn test_sealed_request() {
         .with_parameter("fromRecord", 200)
         .with_parameter("toRecord", 299);
     // The state we're sending back to the client is whatever they sent us.
-    let peer_continuation = parsed_client_request.peer_continuation();
+    let peer_continuation = parsed_client_request.peer_continuation().cloned();
 
     let server_response =
         SealedResponse::new_success(parsed_client_request.id(), server)
             .with_result("Records retrieved: 100-199")
             .with_state(state)
-            .with_peer_continuation(peer_continuation);
+            .with_optional_peer_continuation(peer_continuation);
 
     //
     // We examine the form of the response envelope after it is signed by the
@@ -352,6 +353,7 @@ fn test_sealed_request() {
     assert_eq!(signed_server_response_envelope.format(), (indoc! {r#"
         {
             response(ARID(c66be27d)) [
+                "gstpVersion": 1
                 'recipientContinuation': ENCRYPTED [
                     'hasRecipient': SealedMessage
                 ]
@@ -531,12 +533,13 @@ fn test_multi_recipient_request_and_response() {
     let server_state = Expression::new("nextPage")
         .with_parameter("fromRecord", 200)
         .with_parameter("toRecord", 299);
-    let peer_continuation = parsed_client_request_server.peer_continuation();
+    let peer_continuation =
+        parsed_client_request_server.peer_continuation().cloned();
     let server_response =
         SealedResponse::new_success(parsed_client_request_server.id(), &server)
             .with_result("Records retrieved: 100-199")
             .with_state(server_state)
-            .with_peer_continuation(peer_continuation);
+            .with_optional_peer_continuation(peer_continuation);
 
     let response_recipients = vec![&client, &auditor];
     let sealed_server_response_envelope = server_response
@@ -639,6 +642,7 @@ fn test_sealed_event() {
     assert_eq!(signed_event_envelope.format(), (indoc! {r#"
         {
             event(ARID(c66be27d)) [
+                "gstpVersion": 1
                 'content': "test"
                 'date': 2024-07-04T11:11:11Z
                 'note': "This is a test"
@@ -0,0 +1,91 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    inspect,
+    maintenance::{SweepPolicy, UnhintedPolicy, sweep},
+    prelude::*,
+};
+
+use crate::common::new_party;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Requests queued at various times before `now`, with and without hints.
+fn mixed_batch(now: Date) -> Vec<(&'static str, Envelope, Date)> {
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, _) = new_party(&mut rng);
+
+    let seal = |valid_until: Option<Date>, hints: bool| {
+        SealedRequest::new("test", ARID::new(), &client)
+            .to_envelope_with_options(
+                valid_until,
+                Some(&client_private_keys),
+                &[&server],
+                &SealOptions::new().with_transport_expiry_hints(hints),
+            )
+            .unwrap()
+    };
+
+    let live = seal(Some(now + HOUR), true);
+    assert_eq!(
+        inspect::transport_expiry_hint(&live).unwrap(),
+        Some(now + HOUR)
+    );
+    vec![
+        ("live", live, now - HOUR),
+        ("stale", seal(Some(now - HOUR), true), now - 2 * HOUR),
+        ("old", seal(Some(now + HOUR), true), now - 48 * HOUR),
+        ("unhinted", seal(Some(now + HOUR), false), now - HOUR),
+        ("unhinted-old", seal(None, true), now - 10 * HOUR),
+    ]
+}
+
+#[test]
+fn test_sweep_mixed_batch() {
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let batch = mixed_batch(now);
+
+    let report = sweep(batch.clone(), now, &SweepPolicy::new());
+    assert_eq!(report.deliverable, ["live", "old"]);
+    assert_eq!(report.expired, ["stale"]);
+    assert_eq!(report.unknown, ["unhinted", "unhinted-old"]);
+
+    let policy = SweepPolicy::new()
+        .with_max_age(24 * HOUR)
+        .with_unhinted(UnhintedPolicy::ExpireAfter(6 * HOUR));
+    let report = sweep(batch, now, &policy);
+    assert_eq!(report.deliverable, ["live"]);
+    assert_eq!(report.expired, ["stale", "old", "unhinted-old"]);
+    assert_eq!(report.unknown, ["unhinted"]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_sweep_matches_sweep() {
+    use gstp::maintenance::par_sweep;
+
+    bc_envelope::register_tags();
+
+    let now = Date::from_ymd(2026, 1, 1);
+    let batch = mixed_batch(now);
+    let policies = [
+        SweepPolicy::new(),
+        SweepPolicy::new()
+            .with_max_age(24 * HOUR)
+            .with_unhinted(UnhintedPolicy::ExpireAfter(6 * HOUR)),
+    ];
+    for policy in &policies {
+        assert_eq!(
+            par_sweep(batch.clone(), now, policy),
+            sweep(batch.clone(), now, policy)
+        );
+    }
+}
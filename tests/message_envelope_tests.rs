@@ -0,0 +1,90 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    framing::{read_frame_as, write_frame},
+    prelude::*,
+};
+
+use crate::common::new_party;
+
+#[test]
+fn test_typed_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_parameter("param", 42);
+    let sealed = request
+        .seal(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::default(),
+        )
+        .unwrap();
+
+    // The typed envelope survives UR encoding and framing.
+    let sealed =
+        SealedRequestEnvelope::from_ur_string(sealed.ur_string()).unwrap();
+    let mut stream = Vec::new();
+    write_frame(&mut stream, sealed.envelope()).unwrap();
+    let sealed: SealedRequestEnvelope =
+        read_frame_as(stream.as_slice(), &ParseLimits::default()).unwrap();
+
+    let parsed = SealedRequest::try_from_sealed(
+        &sealed,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        parsed.extract_object_for_parameter::<i32>("param").unwrap(),
+        42
+    );
+}
+
+#[test]
+fn test_mismatched_kind_rejected() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    // Without recipients the signed response is not encrypted, so its kind is
+    // observable.
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .to_envelope(None, Some(&server_private_keys), None)
+        .unwrap();
+
+    assert!(SealedResponseEnvelope::try_from(response.clone()).is_ok());
+    assert!(matches!(
+        SealedRequestEnvelope::try_from(response.clone()),
+        Err(Error::MessageKindMismatch {
+            expected: MessageKind::Request,
+            found: MessageKind::Response,
+        })
+    ));
+
+    let mut stream = Vec::new();
+    write_frame(&mut stream, &response).unwrap();
+    assert!(matches!(
+        read_frame_as::<SealedEventEnvelope>(
+            stream.as_slice(),
+            &ParseLimits::default()
+        ),
+        Err(Error::MessageKindMismatch { .. })
+    ));
+
+    assert!(matches!(
+        SealedEventEnvelope::try_from(Envelope::new("Hello.")),
+        Err(Error::UnknownMessageKind)
+    ));
+}
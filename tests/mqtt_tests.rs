@@ -0,0 +1,184 @@
+#![cfg(feature = "mqtt")]
+
+mod common;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{
+    prelude::*,
+    transport::mqtt::{
+        DEFAULT_MAX_PAYLOAD_SIZE, EventDecoder, MqttClient, publish_event,
+    },
+};
+
+use crate::common::new_party;
+
+/// A client that records what it is asked to do instead of talking to a
+/// broker.
+#[derive(Default)]
+struct MockClient {
+    published: Vec<(String, Vec<u8>)>,
+    subscriptions: Vec<String>,
+}
+
+impl MqttClient for MockClient {
+    async fn publish(&mut self, topic: &str, payload: Vec<u8>) -> Result<()> {
+        self.published.push((topic.to_string(), payload));
+        Ok(())
+    }
+
+    async fn subscribe(&mut self, filter: &str) -> Result<()> {
+        self.subscriptions.push(filter.to_string());
+        Ok(())
+    }
+}
+
+fn door_event(sender: &XIDDocument) -> SealedEvent<Envelope> {
+    let content =
+        Envelope::new("front").add_assertion(known_values::IS_A, "door/open");
+    SealedEvent::new(content, ARID::new(), sender)
+}
+
+#[tokio::test]
+async fn test_publish_and_decode() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (device, device_private_keys) = new_party(&mut rng);
+    let (hub, hub_private_keys) = new_party(&mut rng);
+    let mut client = MockClient::default();
+
+    let broadcast = door_event(&device);
+    let topic = publish_event(
+        &mut client,
+        "site/1/",
+        &broadcast,
+        None,
+        &device_private_keys,
+        &[],
+        DEFAULT_MAX_PAYLOAD_SIZE,
+    )
+    .await
+    .unwrap();
+    assert_eq!(topic, "site/1/door/open");
+
+    let sealed = door_event(&device);
+    publish_event(
+        &mut client,
+        "site/1",
+        &sealed,
+        None,
+        &device_private_keys,
+        &[&hub],
+        DEFAULT_MAX_PAYLOAD_SIZE,
+    )
+    .await
+    .unwrap();
+
+    let decoder = EventDecoder::new("site/1");
+    decoder.subscribe(&mut client).await.unwrap();
+    assert_eq!(client.subscriptions, ["site/1/#"]);
+
+    // A subscriber without keys verifies the broadcast event but cannot read
+    // the one sealed to the hub.
+    let (topic, payload) = &client.published[0];
+    let event = decoder.decode::<Envelope>(topic, payload).unwrap();
+    assert_eq!(event.id(), broadcast.id());
+    assert_eq!(event.sender().xid(), device.xid());
+    assert!(event.content().is_identical_to(broadcast.content()));
+    let (topic, payload) = &client.published[1];
+    assert_eq!(topic, "site/1/door/open");
+    assert!(matches!(
+        decoder.decode::<Envelope>(topic, payload),
+        Err(Error::UnexpectedEncryption)
+    ));
+
+    // The hub reads both.
+    let decoder = decoder.with_private_keys(&hub_private_keys);
+    let event = decoder.decode::<Envelope>(topic, payload).unwrap();
+    assert_eq!(event.id(), sealed.id());
+    let (topic, payload) = &client.published[0];
+    decoder.decode::<Envelope>(topic, payload).unwrap();
+}
+
+#[tokio::test]
+async fn test_rejected_messages() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (device, device_private_keys) = new_party(&mut rng);
+    let mut client = MockClient::default();
+
+    // Nothing is published if the payload is over the limit.
+    let result = publish_event(
+        &mut client,
+        "site/1",
+        &door_event(&device),
+        None,
+        &device_private_keys,
+        &[],
+        16,
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(Error::FrameTooLarge { limit: 16, .. })
+    ));
+    assert!(client.published.is_empty());
+
+    // Topics may not contain wildcards.
+    let wildcard = SealedEvent::<Envelope>::new(
+        Envelope::new("any").add_assertion(known_values::IS_A, "door/+"),
+        ARID::new(),
+        &device,
+    );
+    let result = publish_event(
+        &mut client,
+        "site/1",
+        &wildcard,
+        None,
+        &device_private_keys,
+        &[],
+        DEFAULT_MAX_PAYLOAD_SIZE,
+    )
+    .await;
+    assert!(
+        matches!(result, Err(Error::InvalidTopic(topic)) if topic == "site/1/door/+")
+    );
+
+    publish_event(
+        &mut client,
+        "site/1",
+        &door_event(&device),
+        None,
+        &device_private_keys,
+        &[],
+        DEFAULT_MAX_PAYLOAD_SIZE,
+    )
+    .await
+    .unwrap();
+    let (topic, payload) = &client.published[0];
+
+    // The decoder has its own limit.
+    let decoder = EventDecoder::new("site/1").with_max_payload_size(16);
+    assert!(matches!(
+        decoder.decode::<Envelope>(topic, payload),
+        Err(Error::FrameTooLarge { limit: 16, .. })
+    ));
+
+    // An event moved to another topic is rejected.
+    let decoder = EventDecoder::new("site/1");
+    let result = decoder.decode::<Envelope>("site/1/door/closed", payload);
+    assert!(matches!(
+        result,
+        Err(Error::TopicMismatch { topic, expected })
+            if topic == "site/1/door/closed" && expected == "site/1/door/open"
+    ));
+    let decoder = EventDecoder::new("site/2");
+    assert!(matches!(
+        decoder.decode::<Envelope>(topic, payload),
+        Err(Error::TopicMismatch { .. })
+    ));
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use bc_components::{ARID, XID};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_request_to_primary_and_standby() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (primary, primary_private_keys) = new_party(&mut rng);
+    let (standby, standby_private_keys) = new_party(&mut rng);
+    let (_, outsider_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let envelope = SealedRequest::new("transfer", id, &client)
+        .with_parameter("amount", 42)
+        .to_envelope_for_recipients(
+            None,
+            Some(&client_private_keys),
+            &[&primary, &standby],
+        )
+        .unwrap();
+
+    for private_keys in [&primary_private_keys, &standby_private_keys] {
+        let request = SealedRequest::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            private_keys,
+        )
+        .unwrap();
+        assert_eq!(request.id(), id);
+        assert_eq!(
+            request
+                .extract_object_for_parameter::<i32>("amount")
+                .unwrap(),
+            42
+        );
+    }
+    assert!(
+        SealedRequest::try_from_envelope(
+            &envelope,
+            None,
+            None,
+            &outsider_private_keys,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_request_without_recipients_is_signed_only() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let envelope = SealedRequest::new("transfer", ARID::new(), &client)
+        .to_envelope_for_recipients(None, Some(&client_private_keys), &[])
+        .unwrap();
+    assert!(!envelope.is_encrypted());
+    envelope.verify(client.verification_key().unwrap()).unwrap();
+}
+
+#[test]
+fn test_request_recipient_without_encryption_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (primary, _) = new_party(&mut rng);
+    let xid = XID::from_data([1; 32]);
+    let standby = XIDDocument::from(xid);
+
+    let error = SealedRequest::new("transfer", ARID::new(), &client)
+        .to_envelope_for_recipients(
+            None,
+            Some(&client_private_keys),
+            &[&primary, &standby],
+        )
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::RecipientMissingEncryptionKey(x) if x == xid)
+    );
+    assert!(error.to_string().contains(&xid.to_string()));
+}
+
+#[test]
+fn test_response_to_coordinator_and_auditor() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (coordinator, coordinator_private_keys) = new_party(&mut rng);
+    let (auditor, auditor_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let response =
+        SealedResponse::new_success(id, &server).with_result("Committed");
+    let envelope = response
+        .to_envelope_for_recipients(
+            None,
+            Some(&server_private_keys),
+            &[&coordinator, &auditor],
+        )
+        .unwrap();
+    for private_keys in [&coordinator_private_keys, &auditor_private_keys] {
+        let response = SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            Some(id),
+            None,
+            private_keys,
+        )
+        .unwrap();
+        assert_eq!(response.extract_result::<String>().unwrap(), "Committed");
+    }
+
+    // A single recipient gets the same layout either way, differing only in
+    // the signature.
+    let single = response
+        .to_envelope(None, Some(&server_private_keys), Some(&coordinator))
+        .unwrap();
+    let slice = response
+        .to_envelope_for_recipients(
+            None,
+            Some(&server_private_keys),
+            &[&coordinator],
+        )
+        .unwrap();
+    assert_eq!(single.format(), slice.format());
+    assert_eq!(
+        single
+            .decrypt_to_recipient(&coordinator_private_keys)
+            .unwrap()
+            .subject()
+            .digest(),
+        slice
+            .decrypt_to_recipient(&coordinator_private_keys)
+            .unwrap()
+            .subject()
+            .digest()
+    );
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{prelude::*, transport::nfc};
+
+use crate::common::new_party;
+
+fn sealed_event(content: &str) -> (Envelope, ARID, PrivateKeys) {
+    let mut rng = make_fake_random_number_generator();
+    let (reader, reader_private_keys) = new_party(&mut rng);
+    let (tag, tag_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+    let envelope = SealedEvent::<String>::new(content.repeat(100), id, &tag)
+        .to_envelope(None, Some(&tag_private_keys), Some(&reader))
+        .unwrap();
+    (envelope, id, reader_private_keys)
+}
+
+fn chunk_with_data(chunk: &[u8], data: &[u8]) -> Vec<u8> {
+    let chunk = Envelope::try_from_cbor_data(chunk.to_vec()).unwrap();
+    chunk
+        .replace_subject(Envelope::new(ByteString::from(data)))
+        .to_cbor_data()
+}
+
+#[test]
+fn test_split_and_reassemble() {
+    bc_envelope::register_tags();
+
+    let (sealed, id, reader_private_keys) = sealed_event("Tag event. ");
+    let chunks = nfc::split(&sealed, 512).unwrap();
+    assert!(chunks.len() > 2);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 512));
+
+    // Chunks arrive out of order, some of them twice.
+    let mut reassembler = nfc::Reassembler::new();
+    assert!(reassembler.missing().is_empty());
+    for chunk in chunks.iter().rev().chain(chunks.iter().step_by(2)) {
+        reassembler.add(chunk).unwrap();
+    }
+    assert!(reassembler.is_complete());
+    let envelope = reassembler.finish().unwrap();
+    assert!(envelope.is_identical_to(&sealed));
+
+    let event = SealedEvent::<String>::try_from_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &reader_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.content(), &"Tag event. ".repeat(100));
+
+    // A message within the budget is carried in a single chunk.
+    let chunks = nfc::split(&sealed, 8 * 1024).unwrap();
+    assert_eq!(chunks.len(), 1);
+    let mut reassembler = nfc::Reassembler::new();
+    reassembler.add(&chunks[0]).unwrap();
+    assert!(reassembler.finish().unwrap().is_identical_to(&sealed));
+}
+
+#[test]
+fn test_missing_chunk() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_event("Tag event. ");
+    let chunks = nfc::split(&sealed, 512).unwrap();
+    let count = chunks.len();
+
+    let mut reassembler = nfc::Reassembler::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        if index != 1 {
+            reassembler.add(chunk).unwrap();
+        }
+    }
+    assert!(!reassembler.is_complete());
+    assert_eq!(reassembler.missing(), vec![1]);
+    let error = reassembler.finish().unwrap_err();
+    assert!(
+        matches!(error, Error::MissingChunk { index: 1, count: c } if c == count)
+    );
+
+    let error = nfc::Reassembler::new().finish().unwrap_err();
+    assert!(matches!(error, Error::MissingChunk { .. }));
+}
+
+#[test]
+fn test_conflicting_chunk() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_event("Tag event. ");
+    let chunks = nfc::split(&sealed, 512).unwrap();
+
+    let mut reassembler = nfc::Reassembler::new();
+    reassembler.add(&chunks[0]).unwrap();
+    let conflicting = chunk_with_data(&chunks[0], b"Something else.");
+    let error = reassembler.add(conflicting).unwrap_err();
+    assert!(matches!(error, Error::ConflictingChunk { index: 0 }));
+}
+
+#[test]
+fn test_digest_mismatch() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_event("Tag event. ");
+    let mut chunks = nfc::split(&sealed, 512).unwrap();
+
+    // Alter the content of a chunk consistently on every copy received.
+    let chunk = Envelope::try_from_cbor_data(chunks[1].clone()).unwrap();
+    let mut data: Vec<u8> =
+        chunk.extract_subject::<ByteString>().unwrap().into();
+    data[10] ^= 0x01;
+    chunks[1] = chunk_with_data(&chunks[1], &data);
+
+    let mut reassembler = nfc::Reassembler::new();
+    for chunk in &chunks {
+        reassembler.add(chunk).unwrap();
+    }
+    let error = reassembler.finish().unwrap_err();
+    assert!(matches!(error, Error::ChunkDigestMismatch));
+}
+
+#[test]
+fn test_invalid_chunks() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_event("Tag event. ");
+    let (other, _, _) = sealed_event("Other event. ");
+    let chunks = nfc::split(&sealed, 512).unwrap();
+    let other_chunks = nfc::split(&other, 512).unwrap();
+
+    let mut reassembler = nfc::Reassembler::new();
+    reassembler.add(&chunks[0]).unwrap();
+    for invalid in [
+        other_chunks[1].clone(),
+        b"not a chunk".to_vec(),
+        Envelope::new("Not a chunk.").to_cbor_data(),
+    ] {
+        let error = reassembler.add(invalid).unwrap_err();
+        assert!(matches!(error, Error::InvalidChunk));
+    }
+
+    let error = nfc::split(&sealed, 64).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::ChunkBudgetTooSmall { budget: 64, .. }
+    ));
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    DeadLetterReason, FileOutboxStore, MemoryOutboxStore, Outbox,
+    OutboxMetadata, prelude::*,
+};
+
+use crate::common::new_party;
+
+fn now() -> Date { Date::try_from("2024-07-04T11:11:11Z").unwrap() }
+
+fn seconds(n: u64) -> Duration { Duration::from_secs(n) }
+
+/// Seals a response whose continuation is valid for `validity`, returning the
+/// envelope and matching outbox metadata.
+fn sealed_response(validity: Duration) -> (Envelope, OutboxMetadata) {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let valid_until = Some(now() + validity);
+    let artifacts = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .with_state("Next page.")
+        .seal_detailed(
+            valid_until,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    // The expiry comes from the continuation sealed into the response.
+    let metadata = OutboxMetadata::from_artifacts(client.xid(), &artifacts);
+    assert_eq!(metadata.continuation_expiry, valid_until);
+    (artifacts.envelope, metadata)
+}
+
+#[test]
+fn test_successful_retry() {
+    let (envelope, metadata) = sealed_response(seconds(600));
+    let mut outbox = Outbox::new(MemoryOutboxStore::new());
+    let id = outbox
+        .enqueue(MessageKind::Response, envelope.clone(), metadata, now())
+        .unwrap();
+
+    let entry = outbox.next_due(now()).unwrap().unwrap();
+    assert_eq!(entry.id, id);
+    assert_eq!(entry.envelope, envelope);
+
+    // The first transmission fails, so the entry backs off.
+    outbox.mark_failed(id, now()).unwrap();
+    assert!(outbox.next_due(now()).unwrap().is_none());
+
+    let retry_at = now() + outbox.backoff(1);
+    let entry = outbox.next_due(retry_at).unwrap().unwrap();
+    assert_eq!(entry.attempts, 1);
+    outbox.mark_sent(id).unwrap();
+    assert!(outbox.next_due(retry_at).unwrap().is_none());
+    assert!(outbox.dead_letters().unwrap().is_empty());
+}
+
+#[test]
+fn test_expired_entry_is_dead_lettered() {
+    let (envelope, metadata) = sealed_response(seconds(10));
+    let path = std::env::temp_dir()
+        .join(format!("gstp-outbox-{}.cbor", ARID::new().hex()));
+
+    let id = {
+        let mut outbox = Outbox::new(FileOutboxStore::open(&path).unwrap());
+        let id = outbox
+            .enqueue(MessageKind::Response, envelope, metadata, now())
+            .unwrap();
+        outbox.mark_failed(id, now()).unwrap();
+        id
+    };
+
+    // After a restart the continuation has expired, so the retry is never
+    // attempted.
+    let mut outbox = Outbox::new(FileOutboxStore::open(&path).unwrap());
+    assert!(outbox.next_due(now() + seconds(60)).unwrap().is_none());
+    let dead_letters = outbox.dead_letters().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].id, id);
+    assert_eq!(
+        dead_letters[0].dead_letter,
+        Some(DeadLetterReason::ContinuationExpired)
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_backoff_schedule() {
+    let outbox = Outbox::new(MemoryOutboxStore::new())
+        .with_backoff(seconds(2), seconds(30));
+    let schedule: Vec<u64> = (1..=6)
+        .map(|attempts| outbox.backoff(attempts).as_secs())
+        .collect();
+    assert_eq!(schedule, vec![2, 4, 8, 16, 30, 30]);
+    assert_eq!(outbox.backoff(u32::MAX), seconds(30));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+fn lenient() -> ParseOptions { ParseOptions::new() }
+
+fn strict() -> ParseOptions {
+    ParseOptions::new().with_parse_policy(ParsePolicy::Strict)
+}
+
+#[test]
+fn test_continuation_parse_policy() {
+    let expired = Continuation::new("State.")
+        .with_valid_until(date("2024-07-01T12:00:00Z"))
+        .to_envelope(None);
+    let unbounded = Continuation::new("State.").to_envelope(None);
+
+    // Without a time, a lenient parse checks no expiry...
+    Continuation::try_from_envelope_opt(&expired, &lenient(), &[]).unwrap();
+    Continuation::try_from_envelope_opt(&unbounded, &lenient(), &[]).unwrap();
+
+    // ...while a strict one checks it against the current time, and
+    // requires the continuation to expire.
+    assert!(matches!(
+        Continuation::try_from_envelope_opt(&expired, &strict(), &[]),
+        Err(Error::ContinuationExpired)
+    ));
+    assert!(matches!(
+        Continuation::try_from_envelope_opt(&unbounded, &strict(), &[]),
+        Err(Error::ContinuationWithoutExpiry)
+    ));
+
+    // A time given to a strict parse is used instead of the current time.
+    let before_expiry = strict().with_now(date("2024-07-01T11:00:00Z"));
+    Continuation::try_from_envelope_opt(&expired, &before_expiry, &[]).unwrap();
+}
+
+#[test]
+fn test_message_parse_policy() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let expired = |private_keys: &PrivateKeys| {
+        Continuation::new("State.")
+            .with_valid_until(date("2024-07-01T12:00:00Z"))
+            .to_envelope(Some(&private_keys.public_keys().unwrap()))
+    };
+    let unbounded = |private_keys: &PrivateKeys| {
+        Continuation::new("State.")
+            .to_envelope(Some(&private_keys.public_keys().unwrap()))
+    };
+
+    let request = |continuation: Envelope| {
+        SealedRequest::new("test", ARID::new(), &client)
+            .with_peer_continuation(continuation)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap()
+    };
+    let parse_request = |envelope: &Envelope, options: &ParseOptions| {
+        SealedRequest::try_from_envelope_opt(
+            envelope,
+            options,
+            &server_private_keys,
+        )
+        .map(|_| ())
+    };
+
+    let response = |continuation: Envelope| {
+        SealedResponse::new_success(ARID::new(), &server)
+            .with_peer_continuation(continuation)
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap()
+    };
+    let parse_response = |envelope: &Envelope, options: &ParseOptions| {
+        SealedResponse::try_from_encrypted_envelope_opt(
+            envelope,
+            options,
+            &client_private_keys,
+        )
+        .map(|_| ())
+    };
+
+    let event = |continuation: Envelope| {
+        SealedEvent::<String>::new("test", ARID::new(), &server)
+            .with_peer_continuation(continuation)
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap()
+    };
+    let parse_event = |envelope: &Envelope, options: &ParseOptions| {
+        SealedEvent::<String>::try_from_envelope_opt(
+            envelope,
+            options,
+            &client_private_keys,
+        )
+        .map(|_| ())
+    };
+
+    let cases = [
+        (
+            request(expired(&server_private_keys)),
+            request(unbounded(&server_private_keys)),
+            &parse_request as &dyn Fn(&Envelope, &ParseOptions) -> _,
+        ),
+        (
+            response(expired(&client_private_keys)),
+            response(unbounded(&client_private_keys)),
+            &parse_response,
+        ),
+        (
+            event(expired(&client_private_keys)),
+            event(unbounded(&client_private_keys)),
+            &parse_event,
+        ),
+    ];
+    for (expired, unbounded, parse) in cases {
+        parse(&expired, &lenient()).unwrap();
+        parse(&unbounded, &lenient()).unwrap();
+        assert!(matches!(
+            parse(&expired, &strict()),
+            Err(Error::ContinuationExpired)
+        ));
+        assert!(matches!(
+            parse(&unbounded, &strict()),
+            Err(Error::ContinuationWithoutExpiry)
+        ));
+    }
+}
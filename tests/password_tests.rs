@@ -0,0 +1,118 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+const PASSWORD: &str = "correct horse battery staple";
+
+#[test]
+fn test_password_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let options = SealOptions::new();
+
+    // The client knows only the passphrase, not the server's keys.
+    let id = ARID::new();
+    let request = SealedRequest::new("test", id, &client)
+        .with_state("Client state.")
+        .to_envelope_with_password(
+            None,
+            Some(&client_private_keys),
+            PASSWORD,
+            &options,
+        )
+        .unwrap();
+    assert!(request.is_locked_with_password());
+    let request = SealedRequest::try_from_envelope_with_password(
+        &request,
+        PASSWORD,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.id(), id);
+
+    // The continuation returns to the client as usual.
+    let response = SealedResponse::new_success(id, &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope_with_password(
+            None,
+            Some(&server_private_keys),
+            PASSWORD,
+            &options,
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_envelope_with_password(
+        &response,
+        PASSWORD,
+        &ParseOptions::new().with_expected_id(id),
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.extract_result::<String>().unwrap(), "ok");
+    assert_eq!(response.state().unwrap(), &Envelope::new("Client state."));
+
+    let event =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &client)
+            .to_envelope_with_password(
+                None,
+                Some(&client_private_keys),
+                PASSWORD,
+                &options,
+            )
+            .unwrap();
+    let event = SealedEvent::<String>::try_from_envelope_with_password(
+        &event,
+        PASSWORD,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.content(), "Event.");
+}
+
+#[test]
+fn test_wrong_password() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (_, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope_with_password(
+            None,
+            Some(&client_private_keys),
+            PASSWORD,
+            &SealOptions::new(),
+        )
+        .unwrap();
+    assert!(matches!(
+        SealedRequest::try_from_envelope_with_password(
+            &request,
+            "Tr0ub4dor&3",
+            &ParseOptions::new(),
+            &server_private_keys,
+        ),
+        Err(Error::PasswordDecryptionFailed)
+    ));
+
+    // Nor can the envelope be read with the keys of any party.
+    assert!(
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .is_err()
+    );
+}
@@ -0,0 +1,83 @@
+use bc_components::{ARID, XID};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+fn sender() -> XIDDocument { XIDDocument::from(XID::from_data([1; 32])) }
+
+fn continuation() -> Envelope { Envelope::new("Continuation.") }
+
+/// Asserts the continuation each way of setting it leaves on a message.
+fn assert_peer_continuation<M>(
+    message: M,
+    with: impl Fn(M, Envelope) -> M,
+    with_optional: impl Fn(M, Option<Envelope>) -> M,
+    peer_continuation: impl Fn(&M) -> Option<&Envelope>,
+) where
+    M: Clone,
+{
+    assert!(peer_continuation(&message).is_none());
+
+    let set = with(message.clone(), continuation());
+    assert!(
+        peer_continuation(&set)
+            .unwrap()
+            .is_identical_to(&continuation())
+    );
+
+    let set = with_optional(message, Some(continuation()));
+    assert!(
+        peer_continuation(&set)
+            .unwrap()
+            .is_identical_to(&continuation())
+    );
+
+    let cleared = with_optional(set, None);
+    assert!(peer_continuation(&cleared).is_none());
+}
+
+#[test]
+fn test_request_peer_continuation() {
+    assert_peer_continuation(
+        SealedRequest::new("test", ARID::new(), sender()),
+        SealedRequest::with_peer_continuation,
+        SealedRequest::with_optional_peer_continuation,
+        SealedRequest::peer_continuation,
+    );
+}
+
+#[test]
+fn test_response_peer_continuation() {
+    assert_peer_continuation(
+        SealedResponse::new_success(ARID::new(), sender()),
+        SealedResponse::with_peer_continuation,
+        SealedResponse::with_optional_peer_continuation,
+        SealedResponse::peer_continuation,
+    );
+}
+
+#[test]
+fn test_event_peer_continuation() {
+    assert_peer_continuation(
+        SealedEvent::<String>::new("test", ARID::new(), sender()),
+        SealedEvent::with_peer_continuation,
+        SealedEvent::with_optional_peer_continuation,
+        SealedEvent::peer_continuation,
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_response_peer_continuation_ref() {
+    let continuation = continuation();
+    let response = SealedResponse::new_success(ARID::new(), sender())
+        .with_optional_peer_continuation_ref(Some(&continuation));
+    assert!(
+        response
+            .peer_continuation()
+            .unwrap()
+            .is_identical_to(&continuation)
+    );
+    let response = response.with_optional_peer_continuation_ref(None);
+    assert!(response.peer_continuation().is_none());
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{FilePendingStore, MemoryPendingStore, PendingRequests, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_match_response_after_restart() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+    let path = std::env::temp_dir()
+        .join(format!("gstp-pending-{}.cbor", ARID::new().hex()));
+
+    //
+    // The client seals and sends a request, then its process dies.
+    //
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("The state of things.");
+    let sealed_request = {
+        let mut pending =
+            PendingRequests::new(FilePendingStore::open(&path).unwrap());
+        pending
+            .seal(
+                &request,
+                Some(now + Duration::from_secs(60)),
+                &client_private_keys,
+                &server,
+            )
+            .unwrap()
+    };
+
+    //
+    // The server answers.
+    //
+
+    let parsed_request = SealedRequest::try_from_envelope(
+        &sealed_request,
+        None,
+        Some(now),
+        &server_private_keys,
+    )
+    .unwrap();
+    let sealed_response =
+        SealedResponse::new_success(parsed_request.id(), &server)
+            .with_result("ok")
+            .with_optional_peer_continuation(
+                parsed_request.peer_continuation().cloned(),
+            )
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap();
+
+    //
+    // A new client process rehydrates its pending requests and matches the
+    // response.
+    //
+
+    let mut pending =
+        PendingRequests::new(FilePendingStore::open(&path).unwrap());
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &sealed_response,
+        Some(request.id()),
+        Some(now),
+        &client_private_keys,
+    )
+    .unwrap();
+    let record = pending
+        .match_response(&response, now, &client_private_keys)
+        .unwrap();
+    assert_eq!(record.function, Function::from("test"));
+    assert_eq!(record.peer, server.xid());
+
+    // The record was consumed, including on disk.
+    let mut pending =
+        PendingRequests::new(FilePendingStore::open(&path).unwrap());
+    assert!(matches!(
+        pending.match_response(&response, now, &client_private_keys),
+        Err(Error::UnknownPendingRequest(Some(id))) if id == request.id()
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_rejected_response_leaves_request_pending() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+
+    let mut pending = PendingRequests::new(MemoryPendingStore::new());
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("The state of things.");
+    let sealed_request = pending
+        .seal(
+            &request,
+            Some(now + Duration::from_secs(60)),
+            &client_private_keys,
+            &server,
+        )
+        .unwrap();
+    let parsed_request = SealedRequest::try_from_envelope(
+        &sealed_request,
+        None,
+        Some(now),
+        &server_private_keys,
+    )
+    .unwrap();
+    let respond = |sender: &XIDDocument, keys: &PrivateKeys| {
+        let envelope = SealedResponse::new_success(request.id(), sender)
+            .with_result("ok")
+            .with_optional_peer_continuation(
+                parsed_request.peer_continuation().cloned(),
+            )
+            .to_envelope(None, Some(keys), Some(&client))
+            .unwrap();
+        SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            Some(request.id()),
+            Some(now),
+            &client_private_keys,
+        )
+        .unwrap()
+    };
+
+    // Mallory's response with the right ID is refused without cancelling
+    // the request.
+    let forged = respond(&mallory, &mallory_private_keys);
+    assert!(matches!(
+        pending.match_response(&forged, now, &client_private_keys),
+        Err(Error::UnexpectedSender { .. })
+    ));
+
+    // The server's response still matches, and only then is the request
+    // removed.
+    let response = respond(&server, &server_private_keys);
+    let record = pending
+        .match_response(&response, now, &client_private_keys)
+        .unwrap();
+    assert_eq!(record.peer, server.xid());
+    assert!(matches!(
+        pending.match_response(&response, now, &client_private_keys),
+        Err(Error::UnknownPendingRequest(Some(id))) if id == request.id()
+    ));
+}
+
+#[test]
+fn test_expired_pending_requests() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+
+    let mut pending = PendingRequests::new(MemoryPendingStore::new());
+    let short = SealedRequest::new("short", ARID::new(), &client);
+    let long = SealedRequest::new("long", ARID::new(), &client);
+    pending
+        .seal(
+            &short,
+            Some(now + Duration::from_secs(10)),
+            &client_private_keys,
+            &server,
+        )
+        .unwrap();
+    pending
+        .seal(
+            &long,
+            Some(now + Duration::from_secs(60)),
+            &client_private_keys,
+            &server,
+        )
+        .unwrap();
+
+    let expired = pending.expire(now + Duration::from_secs(30)).unwrap();
+    assert_eq!(expired, vec![short.id()]);
+}
@@ -228,13 +228,13 @@ fn test_sealed_request() {
         .with_parameter("fromRecord", 200)
         .with_parameter("toRecord", 299);
     // The state we're sending back to the client is whatever they sent us.
-    let peer_continuation = parsed_client_request.peer_continuation();
+    let peer_continuation = parsed_client_request.peer_continuation().cloned();
 
     let server_response =
         SealedResponse::new_success(parsed_client_request.id(), server)
             .with_result("Records retrieved: 100-199")
             .with_state(state)
-            .with_peer_continuation(peer_continuation);
+            .with_optional_peer_continuation(peer_continuation);
 
     //
     // We examine the form of the response envelope after it is signed by the
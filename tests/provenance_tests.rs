@@ -0,0 +1,104 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{consts, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_provenance() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let seal = |options: &SealOptions| {
+        SealedRequest::new("test", ARID::new(), &client)
+            .to_envelope_with_options(
+                None,
+                Some(&client_private_keys),
+                &[&server],
+                options,
+            )
+            .unwrap()
+    };
+    let parse = |sealed: &Envelope, options: &ParseOptions| {
+        SealedRequest::try_from_envelope_opt(
+            sealed,
+            options,
+            &server_private_keys,
+        )
+    };
+    let strict = ParseOptions::new().with_required_provenance(true);
+
+    // Present.
+    let stamped = seal(
+        &SealOptions::new()
+            .with_provenance(true)
+            .with_build_id("app 1.2.3 (abc123)"),
+    );
+    let request = parse(&stamped, &strict).unwrap();
+    let provenance = request.provenance().unwrap();
+    assert_eq!(provenance.crate_version(), env!("CARGO_PKG_VERSION"));
+    assert_eq!(provenance.protocol_version(), consts::PROTOCOL_VERSION);
+    assert_eq!(provenance.build(), Some("app 1.2.3 (abc123)"));
+
+    // Absent.
+    let unstamped = seal(&SealOptions::new());
+    let request = parse(&unstamped, &ParseOptions::new()).unwrap();
+    assert_eq!(request.provenance(), None);
+
+    // Required.
+    assert!(matches!(
+        parse(&unstamped, &strict),
+        Err(Error::MissingProvenance)
+    ));
+}
+
+#[test]
+fn test_provenance_on_responses_and_events() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let options = SealOptions::new().with_provenance(true);
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options,
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.provenance().unwrap().build(), None);
+
+    let event = SealedEvent::<String>::new("Tick.", ARID::new(), &server)
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &options,
+        )
+        .unwrap();
+    let event = SealedEvent::<String>::try_from_envelope(
+        &event,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(event.provenance().is_some());
+}
@@ -0,0 +1,105 @@
+//! Snapshot of the public API.
+//!
+//! The public API, as rustdoc sees it with every feature enabled, is listed
+//! in `tests/golden/public_api.txt` so that a change to the surface of the
+//! crate shows up in review before it is released. The listing is generated
+//! from rustdoc's JSON output, which needs a nightly toolchain whose format
+//! matches the `public-api` dev-dependency. After an intentional change,
+//! regenerate the listing with:
+//!
+//! ```sh
+//! GSTP_REGENERATE_PUBLIC_API=1 cargo test --test public_api_tests
+//! ```
+
+use std::{fs, path::PathBuf};
+
+const REGENERATE: &str = "GSTP_REGENERATE_PUBLIC_API";
+
+fn manifest_dir() -> PathBuf { PathBuf::from(env!("CARGO_MANIFEST_DIR")) }
+
+/// Lists the public items of the crate, one per line, in sorted order.
+fn public_api() -> String {
+    // Documented in a target directory of its own, so as not to wait on
+    // the lock of the one this test was built in.
+    let json = rustdoc_json::Builder::default()
+        .toolchain("nightly")
+        .manifest_path(manifest_dir().join("Cargo.toml"))
+        .target_dir(env!("CARGO_TARGET_TMPDIR"))
+        .all_features(true)
+        .silent(true)
+        .build()
+        .unwrap();
+    // Blanket implementations such as `impl<T> From<T> for T` are the same
+    // for every type, so they are left out.
+    public_api::Builder::from_rustdoc_json(json)
+        .omit_blanket_impls(true)
+        .build()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_public_api_snapshot() {
+    let listing = public_api();
+    let path = manifest_dir().join("tests/golden/public_api.txt");
+    if std::env::var_os(REGENERATE).is_some() {
+        fs::write(&path, &listing).unwrap();
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("missing {}; run with {REGENERATE}=1", path.display())
+    });
+    if listing != expected {
+        let added: Vec<&str> = listing
+            .lines()
+            .filter(|line| !expected.lines().any(|e| e == *line))
+            .collect();
+        let removed: Vec<&str> = expected
+            .lines()
+            .filter(|line| !listing.lines().any(|l| l == *line))
+            .collect();
+        panic!(
+            "the public API differs from its snapshot; if the change is \
+             intentional, run with {REGENERATE}=1\nadded:\n  {}\nremoved:\n  \
+             {}",
+            added.join("\n  "),
+            removed.join("\n  ")
+        );
+    }
+}
+
+/// Every foreign type in the public API can be named through `gstp::deps`.
+#[test]
+fn test_deps_reexports() {
+    use gstp::deps::*;
+
+    fn named<T: ?Sized>() {}
+    named::<ARID>();
+    named::<Date>();
+    named::<Digest>();
+    named::<Envelope>();
+    named::<Expression>();
+    named::<Function>();
+    named::<KnownValue>();
+    named::<Parameter>();
+    named::<PrivateKeys>();
+    named::<Reference>();
+    named::<SymmetricKey>();
+    named::<XID>();
+    named::<XIDDocument>();
+    named::<CBOR>();
+    named::<EncapsulationPrivateKey>();
+    named::<EncapsulationPublicKey>();
+    named::<EncapsulationScheme>();
+    named::<dyn Decrypter>();
+    named::<dyn Encrypter>();
+    named::<dyn Signer>();
+    named::<dyn Verifier>();
+    named::<dyn EnvelopeEncodable>();
+
+    // The re-exported types are the ones the API is written against.
+    let document = XIDDocument::from(XID::from_data([1; 32]));
+    let request: gstp::SealedRequest =
+        gstp::SealedRequest::new("ping", ARID::new(), &document);
+    let _: &XIDDocument =
+        gstp::prelude::SealedRequestBehavior::sender(&request);
+}
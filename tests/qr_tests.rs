@@ -0,0 +1,139 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{prelude::*, qr};
+
+use crate::common::new_party;
+
+fn sealed_request(payload: &str) -> (Envelope, ARID, PrivateKeys) {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+    let envelope = SealedRequest::new("test", id, &client)
+        .with_parameter("payload", payload.repeat(200))
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    (envelope, id, server_private_keys)
+}
+
+#[test]
+fn test_shuffled_duplicated_parts() {
+    bc_envelope::register_tags();
+
+    let (sealed, id, server_private_keys) = sealed_request("Large payload. ");
+    let mut encoder = qr::Encoder::new(&sealed, 200).unwrap();
+    let count = encoder.parts_count();
+    assert!(count > 10);
+
+    // Scan the first few parts in a scrambled order, seeing most twice.
+    let parts: Vec<String> = encoder.by_ref().take(count + 7).collect();
+    let len = parts.len();
+    // A prime step that does not divide the length visits every part.
+    let step = [7, 11, 13]
+        .into_iter()
+        .find(|step| !len.is_multiple_of(*step))
+        .unwrap();
+    let mut stream = Vec::new();
+    for i in 0..len {
+        let part = &parts[(i * step) % len];
+        stream.push(part.clone());
+        if i % 3 != 0 {
+            stream.push(part.clone());
+        }
+    }
+
+    let mut accumulator = qr::Accumulator::new();
+    assert_eq!(accumulator.percent_complete(), 0);
+    assert!(accumulator.envelope().unwrap().is_none());
+    let mut last_percent = 0;
+    let mut complete = false;
+    for part in stream {
+        complete = accumulator.receive(&part).unwrap();
+        let percent = accumulator.percent_complete();
+        assert!(percent >= last_percent);
+        assert_eq!(percent == 100, complete);
+        last_percent = percent;
+    }
+    assert!(complete);
+
+    let envelope = accumulator.envelope().unwrap().unwrap();
+    assert!(envelope.is_identical_to(&sealed));
+    let request = SealedRequest::try_from_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<String>("payload")
+            .unwrap(),
+        "Large payload. ".repeat(200)
+    );
+}
+
+#[test]
+fn test_uppercase_parts() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request("Payload. ");
+    let encoder = qr::Encoder::new(&sealed, 500).unwrap();
+    let count = encoder.parts_count();
+
+    let mut accumulator = qr::Accumulator::new();
+    for part in encoder.take(count * 2) {
+        if accumulator.receive(&part.to_uppercase()).unwrap() {
+            break;
+        }
+    }
+    let envelope = accumulator.envelope().unwrap().unwrap();
+    assert!(envelope.is_identical_to(&sealed));
+}
+
+#[test]
+fn test_foreign_parts() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request("First message. ");
+    let (other, _, _) = sealed_request("Second message. ");
+    let mut encoder = qr::Encoder::new(&sealed, 200).unwrap();
+    let mut other_encoder = qr::Encoder::new(&other, 200).unwrap();
+
+    let mut accumulator = qr::Accumulator::new();
+    accumulator.receive(&encoder.next_part().unwrap()).unwrap();
+    let error = accumulator
+        .receive(&other_encoder.next_part().unwrap())
+        .unwrap_err();
+    assert!(matches!(error, Error::ForeignPart));
+
+    // The rejected part leaves the message being received undisturbed.
+    while !accumulator.receive(&encoder.next_part().unwrap()).unwrap() {}
+    let envelope = accumulator.envelope().unwrap().unwrap();
+    assert!(envelope.is_identical_to(&sealed));
+}
+
+#[test]
+fn test_invalid_parts() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request("Payload. ");
+    let part = qr::Encoder::new(&sealed, 200).unwrap().next_part().unwrap();
+
+    let mut accumulator = qr::Accumulator::new();
+    for invalid in [
+        String::new(),
+        "not a ur".to_string(),
+        sealed.ur_string(),
+        part.replacen("ur:envelope/", "ur:bytes/", 1),
+        part.replacen("ur:envelope/1-", "ur:envelope/x-", 1),
+        format!("{}zzzz", &part[..part.len() - 4]),
+    ] {
+        let error = accumulator.receive(&invalid).unwrap_err();
+        assert!(matches!(error, Error::InvalidUR(_)), "{invalid}: {error:?}");
+    }
+    assert_eq!(accumulator.percent_complete(), 0);
+}
@@ -0,0 +1,37 @@
+use bc_components::XIDProvider;
+use bc_envelope::prelude::*;
+use gstp::{Identity, open_request, prelude::*, seal_request};
+
+#[test]
+fn test_quick_exchange() {
+    let client = Identity::generate();
+    let server = Identity::generate();
+
+    let (id, sealed) = seal_request(
+        "add",
+        &[("lhs", Envelope::new(2)), ("rhs", Envelope::new(3))],
+        &client,
+        server.document(),
+    )
+    .unwrap();
+
+    let request = open_request(&sealed, &server).unwrap();
+    assert_eq!(request.id(), id);
+    assert_eq!(request.function(), &Function::from("add"));
+    assert_eq!(request.sender().xid(), client.document().xid());
+    let sum = request.extract_object_for_parameter::<i32>("lhs").unwrap()
+        + request.extract_object_for_parameter::<i32>("rhs").unwrap();
+    assert_eq!(sum, 5);
+    assert!(request.date().is_some());
+}
+
+#[test]
+fn test_quick_request_requires_recipient() {
+    let client = Identity::generate();
+    let server = Identity::generate();
+    let other = Identity::generate();
+
+    let (_, sealed) =
+        seal_request("ping", &[], &client, server.document()).unwrap();
+    assert!(open_request(&sealed, &other).is_err());
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{prelude::*, validate_echoed_state};
+
+use crate::common::new_party;
+
+#[test]
+fn test_persisted_receipt_validates_echoed_state() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("The state of things.");
+    let artifacts = request
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    assert!(artifacts.own_continuation.unwrap().subject().is_encrypted());
+
+    // Only the receipt survives the restart.
+    let persisted = artifacts
+        .continuation_receipt
+        .unwrap()
+        .into_envelope()
+        .to_cbor_data();
+    let receipt = ContinuationReceipt::try_from(
+        Envelope::try_from_cbor_data(persisted).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(receipt.valid_id, Some(request.id()));
+
+    let parsed_request = SealedRequest::try_from_envelope(
+        &artifacts.envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    let response = SealedResponse::new_success(parsed_request.id(), &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(
+            parsed_request.peer_continuation().cloned(),
+        )
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(request.id()),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    validate_echoed_state(&receipt, &response).unwrap();
+}
+
+#[test]
+fn test_substituted_state_detected() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let first = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("First state.")
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    let second_request = SealedRequest::new("test", ARID::new(), &client)
+        .with_state("Second state.");
+    let second = second_request
+        .seal_detailed(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &SealOptions::default(),
+        )
+        .unwrap();
+
+    // The server answers the second request, but echoes back the first
+    // request's continuation.
+    let response = SealedResponse::new_success(second_request.id(), &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(first.own_continuation.clone())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(matches!(
+        validate_echoed_state(&second.continuation_receipt.unwrap(), &response),
+        Err(Error::EchoedStateMismatch)
+    ));
+}
+
+#[test]
+fn test_response_artifacts() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let plain = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .seal_detailed(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    assert!(plain.own_continuation.is_none());
+    assert!(plain.continuation_receipt.is_none());
+
+    let state = Envelope::new("Next page.");
+    let stateful = SealedResponse::new_success(ARID::new(), &server)
+        .with_result("ok")
+        .with_state(state.clone())
+        .seal_detailed(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    let receipt = stateful.continuation_receipt.unwrap();
+    receipt.validate_state(Some(&state)).unwrap();
+    assert!(receipt.validate_state(None).is_err());
+}
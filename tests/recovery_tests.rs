@@ -0,0 +1,196 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, DigestProvider, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{
+    RecoveryAdvisor, continuation_expired_response, prelude::*,
+    recovered_continuation,
+};
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+const PAGE_SIZE: u64 = 10;
+
+/// Serves a page of records starting at `from`, issuing a continuation that
+/// remembers where the next page starts.
+fn serve_page(
+    request: &SealedRequest,
+    from: u64,
+    server: &XIDDocument,
+    server_private_keys: &PrivateKeys,
+    client: &XIDDocument,
+    now: Date,
+) -> Envelope {
+    let to = from + PAGE_SIZE - 1;
+    SealedResponse::new_success(request.id(), server)
+        .with_result(format!("Records {}-{}", from, to))
+        .with_state(to + 1)
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope_with_options(
+            Some(now + Duration::from_secs(60)),
+            Some(server_private_keys),
+            &[client],
+            &SealOptions::new().with_now(now),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_recover_expired_pagination() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let advisor = RecoveryAdvisor::new().with_auto_recovery("resumeListing");
+
+    // The client asks for the first page.
+    let start = date("2024-07-01T12:00:00Z");
+    let request = SealedRequest::new("listRecords", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        Some(start),
+        &server_private_keys,
+    )
+    .unwrap();
+    let response =
+        serve_page(&request, 0, &server, &server_private_keys, &client, start);
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(request.id()),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.extract_result::<String>().unwrap(), "Records 0-9");
+    let last_record = 9;
+
+    // The client comes back for the next page after the continuation has
+    // expired.
+    let later = start + Duration::from_secs(120);
+    let request_id = ARID::new();
+    let next = SealedRequest::new("listRecords", request_id, &client)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let (parsed, partial) = SealedRequest::try_from_envelope_lenient(
+        &next,
+        None,
+        Some(later),
+        &server_private_keys,
+    );
+    assert!(parsed.is_none());
+    assert!(matches!(partial.error, Some(Error::ContinuationExpired)));
+    let expired = partial.returned_continuation.unwrap();
+    assert_eq!(expired, response.peer_continuation().unwrap().digest());
+
+    // The server offers recovery instead of just failing.
+    let failure = continuation_expired_response(
+        partial.claimed_id.unwrap(),
+        &server,
+        expired,
+        "resumeListing",
+        Duration::from_secs(30),
+        &SealOptions::new().with_now(later),
+    )
+    .unwrap()
+    .to_envelope(None, Some(&server_private_keys), Some(&client))
+    .unwrap();
+    let failure = SealedResponse::try_from_encrypted_envelope(
+        &failure,
+        Some(request_id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+
+    // The client recognizes the failure and recovers, telling the server
+    // where it got to.
+    let hint = advisor.recovery_hint(&failure).unwrap();
+    assert!(advisor.can_auto_recover(&hint, later));
+    let recovery = advisor
+        .compose_recovery_request(&hint, ARID::new(), &client, later)
+        .unwrap()
+        .with_parameter("lastRecord", last_record)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+
+    let recovery = SealedRequest::try_from_envelope(
+        &recovery,
+        None,
+        Some(later),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(recovery.function(), &Function::from("resumeListing"));
+    assert_eq!(
+        recovered_continuation(recovery.state().unwrap()),
+        Some(expired)
+    );
+    let from: u64 = recovery
+        .extract_object_for_parameter::<u64>("lastRecord")
+        .unwrap()
+        + 1;
+    let resumed = serve_page(
+        &recovery,
+        from,
+        &server,
+        &server_private_keys,
+        &client,
+        later,
+    );
+    let resumed = SealedResponse::try_from_encrypted_envelope(
+        &resumed,
+        Some(recovery.id()),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(resumed.extract_result::<String>().unwrap(), "Records 10-19");
+}
+
+#[test]
+fn test_recovery_requires_allowed_function_and_grace() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let now = date("2024-07-01T12:00:00Z");
+    let failure = continuation_expired_response(
+        ARID::new(),
+        &server,
+        Envelope::new("Old continuation.").digest(),
+        "resumeListing",
+        Duration::from_secs(30),
+        &SealOptions::new().with_now(now),
+    )
+    .unwrap();
+
+    let advisor = RecoveryAdvisor::new();
+    let hint = advisor.recovery_hint(&failure).unwrap();
+    assert!(!advisor.can_auto_recover(&hint, now));
+    assert!(matches!(
+        advisor.compose_recovery_request(&hint, ARID::new(), &client, now),
+        Err(Error::RecoveryNotPossible)
+    ));
+
+    let advisor = advisor.with_auto_recovery("resumeListing");
+    assert!(advisor.can_auto_recover(&hint, now));
+    assert!(!advisor.can_auto_recover(&hint, now + Duration::from_secs(31)));
+
+    // Other failures carry no hint.
+    let other = SealedResponse::new_failure(ARID::new(), &server)
+        .with_error("Something else.");
+    assert!(advisor.recovery_hint(&other).is_none());
+}
@@ -0,0 +1,59 @@
+//! Runs in its own process, so nothing here may call
+//! `bc_envelope::register_tags`.
+
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_round_trip_without_registration() {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let request = SealedRequest::new("test", id, &client)
+        .with_parameter("param", 42)
+        .with_state("Client state.")
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    assert!(gstp::is_registered());
+
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    let response = SealedResponse::new_success(id, &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        response
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "Client state."
+    );
+
+    // Formatting picks up the registered names.
+    let formatted = request.request().clone().into_envelope().format();
+    assert!(formatted.contains("request(ARID("));
+    assert!(formatted.contains("'body'"));
+}
@@ -0,0 +1,309 @@
+mod common;
+
+use std::{fs, io::Write, sync::Arc, time::Duration};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    ContinuationReplayGuard, FileLogBackend, FsyncPolicy, KeyValueBackend,
+    MemoryBackend, MemoryReplayGuard, ReplayStore, prelude::*,
+};
+
+use crate::common::new_party;
+
+const WINDOW: Duration = Duration::from_secs(300);
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+fn temp_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("gstp-replay-{}.log", ARID::new().hex()))
+}
+
+#[test]
+fn test_replay_window() {
+    bc_envelope::register_tags();
+
+    let mut store = ReplayStore::new(MemoryBackend::new(), WINDOW);
+    let id = ARID::new();
+    let now = date("2024-07-01T12:00:00Z");
+
+    store.check_request_id(id, now).unwrap();
+    assert!(matches!(
+        store.check_request_id(id, now + Duration::from_secs(299)),
+        Err(Error::ReplayDetected)
+    ));
+    store.check_request_id(ARID::new(), now).unwrap();
+
+    // Once the window has passed the ID is accepted again.
+    store
+        .check_request_id(id, now + Duration::from_secs(300))
+        .unwrap();
+
+    let continuation = Envelope::new("Continuation.");
+    store.check_continuation(&continuation, now).unwrap();
+    assert!(matches!(
+        store.check_continuation(&continuation, now),
+        Err(Error::ReplayDetected)
+    ));
+}
+
+#[test]
+fn test_replay_caught_after_restart() {
+    bc_envelope::register_tags();
+
+    let path = temp_path();
+    let id = ARID::new();
+    let now = date("2024-07-01T12:00:00Z");
+
+    {
+        let backend =
+            FileLogBackend::open(&path, FsyncPolicy::EveryWrite).unwrap();
+        let mut store = ReplayStore::new(backend, WINDOW);
+        store.check_request_id(id, now).unwrap();
+    }
+
+    let backend = FileLogBackend::open(&path, FsyncPolicy::EveryWrite).unwrap();
+    let mut store = ReplayStore::new(backend, WINDOW);
+    assert!(matches!(
+        store.check_request_id(id, now + Duration::from_secs(60)),
+        Err(Error::ReplayDetected)
+    ));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_truncated_final_record_is_discarded() {
+    bc_envelope::register_tags();
+
+    let path = temp_path();
+    let id = ARID::new();
+    let now = date("2024-07-01T12:00:00Z");
+
+    {
+        let backend = FileLogBackend::open(&path, FsyncPolicy::Never).unwrap();
+        let mut store = ReplayStore::new(backend, WINDOW);
+        store.check_request_id(id, now).unwrap();
+    }
+    let intact_len = fs::metadata(&path).unwrap().len();
+
+    // A crash leaves part of a record at the end of the log.
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(&[0, 0, 0, 40, 0x82, 0x58])
+        .unwrap();
+
+    let backend = FileLogBackend::open(&path, FsyncPolicy::Never).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), intact_len);
+    let mut store = ReplayStore::new(backend, WINDOW);
+    assert!(matches!(
+        store.check_request_id(id, now),
+        Err(Error::ReplayDetected)
+    ));
+    store.check_request_id(ARID::new(), now).unwrap();
+    drop(store);
+
+    let backend = FileLogBackend::open(&path, FsyncPolicy::Never).unwrap();
+    assert_eq!(backend.entries().unwrap().len(), 2);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_compaction_drops_expired_entries() {
+    bc_envelope::register_tags();
+
+    let path = temp_path();
+    let now = date("2024-07-01T12:00:00Z");
+    let old_id = ARID::new();
+    let new_id = ARID::new();
+
+    let backend = FileLogBackend::open(&path, FsyncPolicy::Never).unwrap();
+    let mut store = ReplayStore::new(backend, WINDOW);
+    store.check_request_id(old_id, now).unwrap();
+    for _ in 0..10 {
+        let id = ARID::new();
+        store.check_request_id(id, now).unwrap();
+    }
+    let later = now + Duration::from_secs(400);
+    store.check_request_id(new_id, later).unwrap();
+    let before = fs::metadata(&path).unwrap().len();
+
+    assert_eq!(store.compact(later).unwrap(), 11);
+    assert!(fs::metadata(&path).unwrap().len() < before);
+    drop(store);
+
+    let backend = FileLogBackend::open(&path, FsyncPolicy::Never).unwrap();
+    assert_eq!(backend.entries().unwrap().len(), 1);
+    let mut store = ReplayStore::new(backend, WINDOW);
+    assert!(matches!(
+        store.check_request_id(new_id, later),
+        Err(Error::ReplayDetected)
+    ));
+    store.check_request_id(old_id, later).unwrap();
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_periodic_compaction() {
+    bc_envelope::register_tags();
+
+    let now = date("2024-07-01T12:00:00Z");
+    let mut store =
+        ReplayStore::new(MemoryBackend::new(), WINDOW).with_compact_every(3);
+    store.check_request_id(ARID::new(), now).unwrap();
+    store.check_request_id(ARID::new(), now).unwrap();
+    let later = now + WINDOW;
+    store.check_request_id(ARID::new(), later).unwrap();
+    assert_eq!(store.backend().entries().unwrap().len(), 1);
+}
+
+/// A request returning the continuation of a response the server sent for a
+/// side-effecting operation, with the server's private keys.
+fn request_returning_continuation(
+    card: &str,
+) -> (Envelope, bc_components::PrivateKeys) {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // The server hands the client state for a side-effecting operation.
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state("chargeCard")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    let request = SealedRequest::new("confirm", ARID::new(), &client)
+        .with_parameter("card", card)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    (request, server_private_keys)
+}
+
+#[test]
+fn test_continuation_accepted_once() {
+    bc_envelope::register_tags();
+
+    let (request, server_private_keys) = request_returning_continuation("1234");
+    let guard = Arc::new(MemoryReplayGuard::new());
+    let options =
+        ParseOptions::new().with_continuation_replay_guard(guard.clone());
+    let parse = || {
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            &options,
+            &server_private_keys,
+        )
+    };
+    let first = parse().unwrap();
+    assert!(first.state().is_some());
+    assert!(matches!(parse(), Err(Error::ContinuationReplayed)));
+    assert_eq!(guard.len(), 1);
+}
+
+#[test]
+fn test_refused_message_keeps_its_nonce() {
+    bc_envelope::register_tags();
+
+    let (request, server_private_keys) =
+        request_returning_continuation(&"4".repeat(64));
+    let guard = Arc::new(MemoryReplayGuard::new());
+
+    // A request refused after its continuation is checked, here for a
+    // parameter over the limit, does not use the continuation up.
+    let strict = ParseOptions::new()
+        .with_continuation_replay_guard(guard.clone())
+        .with_field_limits(
+            FieldLimits::new().with_max_parameter_value_size(16),
+        );
+    let refused = SealedRequest::try_from_envelope_opt(
+        &request,
+        &strict,
+        &server_private_keys,
+    );
+    assert!(refused.is_err());
+    assert!(!matches!(refused, Err(Error::ContinuationReplayed)));
+    assert!(guard.is_empty());
+
+    let options =
+        ParseOptions::new().with_continuation_replay_guard(guard.clone());
+    SealedRequest::try_from_envelope_opt(
+        &request,
+        &options,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(guard.len(), 1);
+}
+
+#[test]
+fn test_replay_guard_pruning() {
+    let guard = MemoryReplayGuard::new();
+    let now = date("2024-07-01T12:00:00Z");
+    let tolerance = Duration::from_secs(30);
+    let (expiring, lasting) = (ARID::new(), ARID::new());
+
+    let valid_until = now + Duration::from_secs(60);
+    guard
+        .check_and_record(expiring, Some(valid_until), Some(now), tolerance)
+        .unwrap();
+    guard
+        .check_and_record(lasting, None, Some(now), tolerance)
+        .unwrap();
+    assert_eq!(guard.prune(now), 0);
+
+    // The continuation is still accepted within the tolerance after it
+    // expires, so its nonce is kept until then.
+    assert_eq!(guard.prune(valid_until), 0);
+    assert!(matches!(
+        guard.check_and_record(
+            expiring,
+            Some(valid_until),
+            Some(valid_until),
+            tolerance
+        ),
+        Err(Error::ContinuationReplayed)
+    ));
+
+    // Once the tolerance has passed too the nonce is forgotten, but the
+    // nonce of a continuation that never expires is kept.
+    assert_eq!(guard.prune(valid_until + tolerance), 1);
+    assert!(matches!(
+        guard.check_and_record(lasting, None, Some(now), tolerance),
+        Err(Error::ContinuationReplayed)
+    ));
+    assert_eq!(guard.len(), 1);
+}
+
+#[test]
+fn test_replay_guard_without_clock_keeps_nonces() {
+    let guard = MemoryReplayGuard::new();
+    let long_ago = date("2000-01-01T00:00:00Z");
+    let nonce = ARID::new();
+
+    // Without the time of the parse the guard cannot tell that a nonce has
+    // expired, so it forgets none.
+    guard
+        .check_and_record(nonce, Some(long_ago), None, Duration::ZERO)
+        .unwrap();
+    guard
+        .check_and_record(ARID::new(), None, None, Duration::ZERO)
+        .unwrap();
+    assert!(matches!(
+        guard.check_and_record(nonce, Some(long_ago), None, Duration::ZERO),
+        Err(Error::ContinuationReplayed)
+    ));
+    assert_eq!(guard.len(), 2);
+}
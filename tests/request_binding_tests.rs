@@ -0,0 +1,72 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_response_bound_to_request() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let transfer = |amount: u32| {
+        SealedRequest::new("transfer", id, &client)
+            .with_parameter("amount", amount)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap()
+    };
+    let answer = |envelope: &Envelope| {
+        let request = SealedRequest::try_from_envelope(
+            envelope,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .unwrap();
+        assert_eq!(request.envelope_digest(), Some(envelope.digest()));
+        SealedResponse::new_success(request.id(), &server)
+            .with_optional_peer_continuation(
+                request.peer_continuation().cloned(),
+            )
+            .with_optional_in_response_to(request.envelope_digest())
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap()
+    };
+    let sent = transfer(10);
+    let options = ParseOptions::new()
+        .with_expected_id(id)
+        .with_request_digest(sent.digest());
+    let parse = |envelope: &Envelope| {
+        SealedResponse::try_from_encrypted_envelope_opt(
+            envelope,
+            &options,
+            &client_private_keys,
+        )
+    };
+
+    let response = parse(&answer(&sent)).unwrap();
+    assert_eq!(response.in_response_to(), Some(sent.digest()));
+
+    // A request swapped on the way for another with the same ID is detected.
+    let swapped = transfer(1000);
+    assert!(matches!(
+        parse(&answer(&swapped)),
+        Err(Error::ResponseBindingMismatch)
+    ));
+
+    // So is a response that is not bound at all.
+    let unbound = SealedResponse::new_success(id, &server)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    assert!(matches!(
+        parse(&unbound),
+        Err(Error::ResponseBindingMismatch)
+    ));
+}
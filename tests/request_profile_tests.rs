@@ -0,0 +1,94 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{RequestProfile, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_request_profiles() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (receiver, receiver_private_keys) = new_party(&mut rng);
+
+    let seal = |profile: RequestProfile| {
+        SealedRequest::new("notify", ARID::new(), &sender)
+            .with_parameter("level", 3)
+            .seal_detailed(
+                None,
+                Some(&sender_private_keys),
+                &[&receiver],
+                &SealOptions::new().with_request_profile(profile),
+            )
+            .unwrap()
+    };
+    let parse = |envelope: &Envelope, profile: RequestProfile| {
+        SealedRequest::try_from_envelope_opt(
+            envelope,
+            &ParseOptions::new().with_request_profile(profile),
+            &receiver_private_keys,
+        )
+    };
+
+    let interactive = seal(RequestProfile::Interactive);
+    assert!(interactive.own_continuation.is_some());
+    let one_way = seal(RequestProfile::OneWay);
+    assert!(one_way.own_continuation.is_none());
+    assert!(one_way.continuation_receipt.is_none());
+    assert!(
+        one_way.envelope.to_cbor_data().len()
+            < interactive.envelope.to_cbor_data().len()
+    );
+
+    // Interactive to interactive.
+    let request =
+        parse(&interactive.envelope, RequestProfile::Interactive).unwrap();
+    assert!(request.peer_continuation().is_some());
+
+    // Interactive to one-way: the continuation is simply not required.
+    let request = parse(&interactive.envelope, RequestProfile::OneWay).unwrap();
+    assert!(request.peer_continuation().is_some());
+
+    // One-way to one-way.
+    let request = parse(&one_way.envelope, RequestProfile::OneWay).unwrap();
+    assert!(request.peer_continuation().is_none());
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<i32>("level")
+            .unwrap(),
+        3
+    );
+    assert!(request.extra_assertions().is_empty());
+
+    // One-way to interactive fails loudly.
+    assert!(matches!(
+        parse(&one_way.envelope, RequestProfile::Interactive),
+        Err(Error::OneWayRequestNotAccepted)
+    ));
+}
+
+#[test]
+fn test_one_way_request_with_state() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (sender, sender_private_keys) = new_party(&mut rng);
+    let (receiver, _) = new_party(&mut rng);
+
+    assert!(matches!(
+        SealedRequest::new("notify", ARID::new(), &sender)
+            .with_state("cursor")
+            .to_envelope_with_options(
+                None,
+                Some(&sender_private_keys),
+                &[&receiver],
+                &SealOptions::new()
+                    .with_request_profile(RequestProfile::OneWay),
+            ),
+        Err(Error::OneWayRequestWithState)
+    ));
+}
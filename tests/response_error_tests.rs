@@ -0,0 +1,78 @@
+mod common;
+
+use bc_components::{ARID, XID};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_response_state_without_sender_encryption_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, _) = new_party(&mut rng);
+    let (_, server_private_keys) = new_party(&mut rng);
+    let server = XIDDocument::from(XID::from_data([1; 32]));
+
+    let error = SealedResponse::new_success(ARID::new(), &server)
+        .with_state("cursor")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap_err();
+    assert!(matches!(error, Error::SenderMissingEncryptionKey));
+}
+
+#[test]
+fn test_response_from_sender_without_verification_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, server_private_keys) = new_party(&mut rng);
+    let server = XIDDocument::from(XID::from_data([1; 32]));
+
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let error = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::SenderMissingVerificationKey));
+}
+
+#[test]
+fn test_response_with_plaintext_peer_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    // A server that sends its continuation in the clear, signed and
+    // encrypted as usual.
+    let id = ARID::new();
+    let message = SealedResponse::new_success(id, &server)
+        .to_envelope(None, Some(&server_private_keys), None)
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .add_assertion(known_values::SENDER_CONTINUATION, "cursor");
+    let envelope = message
+        .sign(&server_private_keys)
+        .encrypt_to_recipient(client.encryption_key().unwrap());
+    let error = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(error, Error::PeerContinuationNotEncrypted));
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_response_id_checked() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let other_id = ARID::new();
+    let respond = |id: ARID| {
+        SealedResponse::new_success(id, &server)
+            .with_result("ok")
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .unwrap()
+    };
+    let parse = |envelope: &Envelope| {
+        SealedResponse::try_from_encrypted_envelope(
+            envelope,
+            Some(id),
+            None,
+            &client_private_keys,
+        )
+    };
+
+    assert!(parse(&respond(id)).is_ok());
+
+    // A response to another request is rejected even though it carries no
+    // continuation to give it away.
+    assert!(matches!(
+        parse(&respond(other_id)),
+        Err(Error::ResponseIdMismatch { expected, found })
+            if expected == id && found == Some(other_id)
+    ));
+
+    // Without an expected ID, any response is accepted.
+    assert!(
+        SealedResponse::try_from_encrypted_envelope(
+            &respond(other_id),
+            None,
+            None,
+            &client_private_keys,
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_early_failure_without_id() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let failure = SealedResponse::new_early_failure(&server)
+        .with_error("Could not decrypt request.")
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parse = |options: ParseOptions| {
+        SealedResponse::try_from_encrypted_envelope_opt(
+            &failure,
+            &options.with_expected_id(id),
+            &client_private_keys,
+        )
+    };
+
+    // An early failure answers no request, so is rejected when an ID is
+    // expected unless the caller opts in.
+    assert!(matches!(
+        parse(ParseOptions::new()),
+        Err(Error::ResponseIdMismatch { expected, found: None })
+            if expected == id
+    ));
+    let response =
+        parse(ParseOptions::new().with_early_failures(true)).unwrap();
+    assert!(response.is_early_failure());
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_state_on_failure_is_rejected_by_default() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+    let id = ARID::new();
+
+    // State is set while the handler expects to succeed...
+    let success = SealedResponse::new_success(id, &server)
+        .try_with_state("cursor")
+        .unwrap();
+    success
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+
+    // ...and carried over when it fails instead.
+    let failure = SealedResponse::new_failure(id, &server)
+        .with_error("disk full")
+        .with_optional_state(success.state().cloned());
+    assert!(matches!(
+        failure.to_envelope(None, Some(&server_private_keys), Some(&client)),
+        Err(Error::StateNotAllowedOnFailure)
+    ));
+
+    // Setting state after the response is a failure fails right away.
+    assert!(matches!(
+        SealedResponse::new_failure(id, &server).try_with_state("cursor"),
+        Err(Error::StateNotAllowedOnFailure)
+    ));
+
+    // A failure without state seals as before.
+    SealedResponse::new_failure(id, &server)
+        .with_error("disk full")
+        .with_optional_state(None::<String>)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+}
+
+#[test]
+fn test_state_on_failure_when_allowed() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+
+    let envelope = SealedResponse::new_failure(id, &server)
+        .with_error("try again")
+        .with_state("cursor")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::default().with_state_on_failure(true),
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(!response.is_ok());
+
+    // The client can return the continuation to retry.
+    let retry = SealedRequest::new("retry", ARID::new(), &client)
+        .with_peer_continuation(response.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &retry,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "cursor"
+    );
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{AssertionElideTransform, ResultTransform, prelude::*};
+
+use crate::common::new_party;
+
+/// Replaces the result with a summary, changing its digest.
+#[derive(Debug)]
+struct Summarize;
+
+impl ResultTransform for Summarize {
+    fn transform(&self, result: Envelope) -> gstp::Result<Envelope> {
+        Ok(result.subject())
+    }
+}
+
+#[test]
+fn test_result_per_recipient() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (auditor, auditor_private_keys) = new_party(&mut rng);
+    let (customer, customer_private_keys) = new_party(&mut rng);
+    let (partner, partner_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let result = Envelope::new("Payment 1234")
+        .add_assertion("amount", 42)
+        .add_assertion("cardNumber", "4111-1111")
+        .add_assertion("riskScore", 17);
+    let elide = AssertionElideTransform::new(["cardNumber", "riskScore"]);
+    let envelopes = SealedResponse::new_success(id, &server)
+        .with_result(result.clone())
+        .with_state("cursor")
+        .to_envelopes_per_recipient(
+            None,
+            Some(&server_private_keys),
+            &[
+                (&auditor, None),
+                (&customer, Some(&elide)),
+                (&partner, Some(&Summarize)),
+            ],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(envelopes.len(), 3);
+    assert_eq!(envelopes[0].0, auditor.xid());
+    assert_eq!(envelopes[1].0, customer.xid());
+    assert_eq!(envelopes[2].0, partner.xid());
+
+    let parse = |envelope: &Envelope, private_keys: &PrivateKeys| {
+        SealedResponse::try_from_encrypted_envelope(
+            envelope,
+            Some(id),
+            None,
+            private_keys,
+        )
+        .unwrap()
+    };
+
+    // The auditor sees the full result.
+    let auditor_view = parse(&envelopes[0].1, &auditor_private_keys);
+    assert!(auditor_view.result().unwrap().is_identical_to(&result));
+
+    // The customer sees the amount only, but the elided result keeps the
+    // digest of the full result.
+    let customer_view = parse(&envelopes[1].1, &customer_private_keys);
+    let customer_result = customer_view.result().unwrap();
+    assert_eq!(customer_result.digest(), result.digest());
+    assert_eq!(
+        customer_result
+            .extract_object_for_predicate::<i32>("amount")
+            .unwrap(),
+        42
+    );
+    assert!(customer_result.object_for_predicate("cardNumber").is_err());
+    assert!(customer_result.object_for_predicate("riskScore").is_err());
+
+    // The partner sees a summary whose digest differs.
+    let partner_view = parse(&envelopes[2].1, &partner_private_keys);
+    let partner_result = partner_view.result().unwrap();
+    assert_ne!(partner_result.digest(), result.digest());
+    assert_eq!(
+        partner_result.extract_subject::<String>().unwrap(),
+        "Payment 1234"
+    );
+
+    // Each copy is sealed to its recipient only.
+    assert!(
+        SealedResponse::try_from_encrypted_envelope(
+            &envelopes[1].1,
+            Some(id),
+            None,
+            &auditor_private_keys,
+        )
+        .is_err()
+    );
+
+    // Every copy carries the same continuation.
+    let continuations: Vec<Envelope> = envelopes
+        .iter()
+        .map(|(_, envelope)| {
+            envelope
+                .decrypt_to_recipient(&auditor_private_keys)
+                .or_else(|_| {
+                    envelope.decrypt_to_recipient(&customer_private_keys)
+                })
+                .or_else(|_| {
+                    envelope.decrypt_to_recipient(&partner_private_keys)
+                })
+                .unwrap()
+                .try_unwrap()
+                .unwrap()
+                .object_for_predicate(known_values::SENDER_CONTINUATION)
+                .unwrap()
+        })
+        .collect();
+    assert!(continuations[0].is_identical_to(&continuations[1]));
+    assert!(continuations[0].is_identical_to(&continuations[2]));
+}
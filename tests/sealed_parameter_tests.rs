@@ -0,0 +1,142 @@
+mod common;
+
+use bc_components::{ARID, XID};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{open_sealed_parameter, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_parameter_sealed_to_third_party() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (merchant, merchant_private_keys) = new_party(&mut rng);
+    let (processor, processor_private_keys) = new_party(&mut rng);
+
+    let envelope = SealedRequest::new("pay", ARID::new(), &client)
+        .with_parameter("amount", 42)
+        .with_parameter_sealed_to("credential", "4111-1111", &processor)
+        .unwrap()
+        .to_envelope(None, Some(&client_private_keys), Some(&merchant))
+        .unwrap();
+
+    // The merchant handles the rest of the request, but sees only
+    // ciphertext for the credential.
+    let request = SealedRequest::try_from_envelope(
+        &envelope,
+        None,
+        None,
+        &merchant_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<i32>("amount")
+            .unwrap(),
+        42
+    );
+    assert!(
+        request
+            .extract_object_for_parameter::<String>("credential")
+            .is_err()
+    );
+    assert!(
+        request
+            .extract_sealed_parameter::<String>(
+                "credential",
+                &merchant_private_keys
+            )
+            .is_err()
+    );
+
+    // The processor opens what the merchant forwards.
+    let forwarded = request.sealed_parameter("credential").unwrap();
+    let credential: String =
+        open_sealed_parameter(&forwarded, &processor_private_keys).unwrap();
+    assert_eq!(credential, "4111-1111");
+    assert_eq!(
+        request
+            .extract_sealed_parameter::<String>(
+                "credential",
+                &processor_private_keys
+            )
+            .unwrap(),
+        "4111-1111"
+    );
+
+    assert!(matches!(
+        request.sealed_parameter("amount"),
+        Err(Error::ParameterNotSealed(_))
+    ));
+}
+
+#[test]
+fn test_sealed_parameter_covered_by_signature() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (processor, _) = new_party(&mut rng);
+
+    let request = SealedRequest::new("pay", ARID::new(), &client)
+        .with_parameter_sealed_to("credential", "4111-1111", &processor)
+        .unwrap();
+    let original = request.sealed_parameter("credential").unwrap();
+    let signed = request
+        .to_envelope(None, Some(&client_private_keys), None)
+        .unwrap();
+    let verification_key = client.verification_key().unwrap();
+    let message = signed.verify(verification_key).unwrap();
+
+    // Substituting another sealed value breaks the signature.
+    let substitute = SealedRequest::new("pay", ARID::new(), &client)
+        .with_parameter_sealed_to("credential", "5500-0000", &processor)
+        .unwrap()
+        .sealed_parameter("credential")
+        .unwrap();
+    let body = message.object_for_predicate(known_values::BODY).unwrap();
+    let parameter = Parameter::from("credential");
+    let tampered_body = body
+        .replace_assertion(
+            Envelope::new_assertion(parameter.clone(), original),
+            Envelope::new_assertion(parameter, substitute),
+        )
+        .unwrap();
+    let tampered = message
+        .replace_assertion(
+            Envelope::new_assertion(known_values::BODY, body),
+            Envelope::new_assertion(known_values::BODY, tampered_body),
+        )
+        .unwrap();
+    let tampered = signed.assertions().into_iter().fold(
+        tampered.wrap(),
+        |envelope, assertion| {
+            envelope.add_assertion_envelope(assertion).unwrap()
+        },
+    );
+    assert!(tampered.verify(verification_key).is_err());
+}
+
+#[test]
+fn test_parameter_sealed_to_party_without_key() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, _) = new_party(&mut rng);
+
+    let xid = XID::from_data([1; 32]);
+    let error = SealedRequest::new("pay", ARID::new(), &client)
+        .with_parameter_sealed_to(
+            "credential",
+            "4111-1111",
+            &XIDDocument::from(xid),
+        )
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::RecipientMissingEncryptionKey(x) if x == xid)
+    );
+}
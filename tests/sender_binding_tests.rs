@@ -0,0 +1,156 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn binding() -> SealOptions { SealOptions::new().with_sender_binding(true) }
+
+#[test]
+fn test_continuation_echoed_by_another_client() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (alice, alice_private_keys) = new_party(&mut rng);
+    let (bob, bob_private_keys) = new_party(&mut rng);
+
+    // The server hands Alice a continuation bound to her.
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state("Alice's cursor")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&alice],
+            &binding(),
+        )
+        .unwrap();
+    let continuation = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &alice_private_keys,
+    )
+    .unwrap()
+    .peer_continuation()
+    .unwrap()
+    .clone();
+
+    let echo = |client: &XIDDocument, private_keys: &PrivateKeys| {
+        let request = SealedRequest::new("nextPage", ARID::new(), client)
+            .with_peer_continuation(continuation.clone())
+            .to_envelope(None, Some(private_keys), Some(&server))
+            .unwrap();
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            None,
+            &server_private_keys,
+        )
+    };
+
+    let request = echo(&alice, &alice_private_keys).unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "Alice's cursor"
+    );
+
+    // Bob's request is validly signed, but the continuation is not his.
+    let error = echo(&bob, &bob_private_keys).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::ContinuationSenderMismatch { expected, found }
+            if expected == alice.xid() && found == bob.xid()
+    ));
+
+    // Nor can Bob pass off his key as Alice's.
+    let mut impostor = XIDDocument::from_xid(alice.xid());
+    impostor
+        .add_key(bob.inception_key().unwrap().clone())
+        .unwrap();
+    let error = echo(&impostor, &bob_private_keys).unwrap_err();
+    assert!(matches!(
+        error,
+        Error::SenderKeyNotBound(xid) if xid == alice.xid()
+    ));
+}
+
+#[test]
+fn test_request_continuation_bound_to_server() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (impostor, impostor_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let envelope = SealedRequest::new("fetch", id, &client)
+        .to_envelope_with_options(
+            None,
+            Some(&client_private_keys),
+            &[&server],
+            &binding(),
+        )
+        .unwrap();
+    let request = SealedRequest::try_from_envelope(
+        &envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+
+    // Only a response from the server the request was sealed to returns
+    // the client's state.
+    let answer = |responder: &XIDDocument, private_keys: &PrivateKeys| {
+        let envelope = SealedResponse::new_success(id, responder)
+            .with_optional_peer_continuation(
+                request.peer_continuation().cloned(),
+            )
+            .to_envelope(None, Some(private_keys), Some(&client))
+            .unwrap();
+        SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            Some(id),
+            None,
+            &client_private_keys,
+        )
+    };
+    assert!(answer(&server, &server_private_keys).is_ok());
+    assert!(matches!(
+        answer(&impostor, &impostor_private_keys),
+        Err(Error::ContinuationSenderMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_sender_binding_needs_one_recipient() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (alice, _) = new_party(&mut rng);
+    let (bob, _) = new_party(&mut rng);
+
+    let result = SealedResponse::new_success(ARID::new(), &server)
+        .with_state("cursor")
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&alice, &bob],
+            &binding(),
+        );
+    assert!(matches!(
+        result,
+        Err(Error::AmbiguousSenderBinding { recipients: 2 })
+    ));
+}
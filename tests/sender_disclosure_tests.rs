@@ -0,0 +1,164 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, URI, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::{RandomNumberGenerator, make_fake_random_number_generator};
+use bc_xid::{
+    XIDDocument, XIDGeneratorOptions, XIDPrivateKeyOptions, XIDSigningOptions,
+};
+use gstp::{SenderDisclosure, prelude::*};
+
+use crate::common::new_party;
+
+/// A client whose document lists a resolution method and a key endpoint it
+/// would rather not show every server.
+fn new_client(
+    rng: &mut impl RandomNumberGenerator,
+) -> (XIDDocument, PrivateKeys) {
+    let (mut document, private_keys) = new_party(rng);
+    document
+        .add_resolution_method(URI::new("https://resolver.example").unwrap());
+    let mut key = document.remove_inception_key().unwrap();
+    key.add_endpoint(URI::new("https://client.example/inbox").unwrap());
+    document.add_key(key).unwrap();
+    (document, private_keys)
+}
+
+fn document_envelope(document: &XIDDocument) -> Envelope {
+    document
+        .to_envelope(
+            XIDPrivateKeyOptions::default(),
+            XIDGeneratorOptions::default(),
+            XIDSigningOptions::default(),
+        )
+        .unwrap()
+}
+
+/// Seals a request from `client` under `disclosure`, returning the sealed
+/// envelope and the sender document as it was embedded.
+fn seal(
+    client: &XIDDocument,
+    client_private_keys: &PrivateKeys,
+    server: &XIDDocument,
+    server_private_keys: &PrivateKeys,
+    disclosure: SenderDisclosure,
+) -> (Envelope, Envelope) {
+    let sealed = SealedRequest::new("test", ARID::new(), client)
+        .to_envelope_with_options(
+            None,
+            Some(client_private_keys),
+            &[server],
+            &SealOptions::new().with_sender_disclosure(disclosure),
+        )
+        .unwrap();
+    let embedded = sealed
+        .decrypt_subject_to_recipient(server_private_keys)
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .try_unwrap()
+        .unwrap()
+        .object_for_predicate(known_values::SENDER)
+        .unwrap();
+    (sealed, embedded)
+}
+
+#[test]
+fn test_verification_only() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_client(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let (sealed, embedded) = seal(
+        &client,
+        &client_private_keys,
+        &server,
+        &server_private_keys,
+        SenderDisclosure::VerificationOnly,
+    );
+    let formatted = embedded.format();
+    assert!(!formatted.contains("resolver.example"));
+    assert!(!formatted.contains("client.example"));
+
+    // The request still verifies, and the server can still answer it.
+    let request = SealedRequest::try_from_envelope(
+        &sealed,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.sender().xid(), client.xid());
+    assert!(request.sender().resolution_methods().is_empty());
+    assert!(request.sender().encryption_key().is_some());
+    SealedResponse::new_success(request.id(), &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope(None, Some(&server_private_keys), Some(request.sender()))
+        .unwrap();
+}
+
+#[test]
+fn test_elided_endpoint_can_be_proven() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_client(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let (_, embedded) = seal(
+        &client,
+        &client_private_keys,
+        &server,
+        &server_private_keys,
+        SenderDisclosure::VerificationOnly,
+    );
+    let full = document_envelope(&client);
+    assert!(
+        embedded.is_identical_to(&full) || embedded.digest() == full.digest()
+    );
+
+    // Later, the client proves to the server that its key lists the
+    // endpoint, without revealing anything else.
+    let endpoint = full.assertions_with_predicate(known_values::KEY)[0]
+        .try_object()
+        .unwrap()
+        .assertions_with_predicate(known_values::ENDPOINT)[0]
+        .clone();
+    let proof = full.proof_contains_target(&endpoint).unwrap();
+    assert!(embedded.confirm_contains_target(&endpoint, &proof));
+}
+
+#[test]
+fn test_custom_disclosure() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_client(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+
+    let (sealed, embedded) = seal(
+        &client,
+        &client_private_keys,
+        &server,
+        &server_private_keys,
+        SenderDisclosure::Custom(vec![known_values::DEREFERENCE_VIA]),
+    );
+    let formatted = embedded.format();
+    assert!(formatted.contains("resolver.example"));
+    assert!(formatted.contains("client.example"));
+
+    let request = SealedRequest::try_from_envelope(
+        &sealed,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        request.sender().resolution_methods(),
+        client.resolution_methods()
+    );
+}
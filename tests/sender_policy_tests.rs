@@ -0,0 +1,133 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{SenderPolicy, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_sender_policies() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (alice, alice_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    let request = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedRequest::new("test", ARID::new(), sender)
+            .to_envelope(None, Some(keys), Some(&server))
+            .unwrap()
+    };
+    let from_alice = request(&alice, &alice_private_keys);
+    let from_mallory = request(&mallory, &mallory_private_keys);
+    let parse = |envelope: &Envelope, policy: SenderPolicy| {
+        SealedRequest::try_from_envelope_opt(
+            envelope,
+            &ParseOptions::new().with_sender_policy(policy),
+            &server_private_keys,
+        )
+    };
+
+    // By default anyone is accepted.
+    assert!(parse(&from_mallory, SenderPolicy::default()).is_ok());
+
+    let allow = SenderPolicy::allow([alice.xid()]);
+    assert!(parse(&from_alice, allow.clone()).is_ok());
+    assert!(matches!(
+        parse(&from_mallory, allow),
+        Err(Error::SenderNotAuthorized(xid)) if xid == mallory.xid()
+    ));
+
+    let deny = SenderPolicy::deny([mallory.xid()]);
+    assert!(parse(&from_alice, deny.clone()).is_ok());
+    assert!(matches!(
+        parse(&from_mallory, deny),
+        Err(Error::SenderNotAuthorized(xid)) if xid == mallory.xid()
+    ));
+
+    let alice_xid = alice.xid();
+    let callback =
+        SenderPolicy::callback(move |sender| sender.xid() == alice_xid);
+    assert!(parse(&from_alice, callback.clone()).is_ok());
+    assert!(matches!(
+        parse(&from_mallory, callback),
+        Err(Error::SenderNotAuthorized(_))
+    ));
+}
+
+#[test]
+fn test_policy_checked_before_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    // A continuation the server cannot open would fail decryption, but the
+    // revoked sender is refused before it is tried.
+    let unopenable = Continuation::new("Forged.")
+        .to_envelope(Some(&mallory_private_keys.public_keys().unwrap()));
+    let event =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &mallory)
+            .with_peer_continuation(unopenable)
+            .to_envelope(None, Some(&mallory_private_keys), Some(&server))
+            .unwrap();
+
+    assert!(
+        SealedEvent::<String>::try_from_envelope(
+            &event,
+            None,
+            None,
+            &server_private_keys,
+        )
+        .is_err()
+    );
+    assert!(matches!(
+        SealedEvent::<String>::try_from_envelope_opt(
+            &event,
+            &ParseOptions::new()
+                .with_sender_policy(SenderPolicy::deny([mallory.xid()])),
+            &server_private_keys,
+        ),
+        Err(Error::SenderNotAuthorized(xid)) if xid == mallory.xid()
+    ));
+}
+
+#[test]
+fn test_policy_applies_to_authenticated_xid() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (alice, _) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    // Mallory signs with her own key a document claiming Alice's XID.
+    let mut impostor = XIDDocument::from_xid(alice.xid());
+    impostor
+        .add_key(mallory.inception_key().unwrap().clone())
+        .unwrap();
+    let request = SealedRequest::new("test", ARID::new(), &impostor)
+        .to_envelope(None, Some(&mallory_private_keys), Some(&server))
+        .unwrap();
+
+    // Neither an allow-list naming Alice nor a deny-list naming Mallory is
+    // consulted for a claim the signature does not back.
+    for policy in [
+        SenderPolicy::allow([alice.xid()]),
+        SenderPolicy::deny([mallory.xid()]),
+    ] {
+        assert!(matches!(
+            SealedRequest::try_from_envelope_opt(
+                &request,
+                &ParseOptions::new().with_sender_policy(policy),
+                &server_private_keys,
+            ),
+            Err(Error::SenderKeyNotBound(xid)) if xid == alice.xid()
+        ));
+    }
+}
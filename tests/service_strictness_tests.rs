@@ -0,0 +1,62 @@
+#![cfg(feature = "service-adapter")]
+
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    Strictness,
+    prelude::*,
+    service::{GstpService, Service},
+    set_strictness,
+};
+
+use crate::common::new_party;
+
+// The strictness is global to the process, so the service is checked under
+// production strictness in a test binary of its own.
+#[tokio::test]
+async fn test_service_refuses_quietly_in_production() {
+    bc_envelope::register_tags();
+    set_strictness(Strictness::Production);
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, outsider_private_keys) = new_party(&mut rng);
+
+    let mut service =
+        GstpService::new(&server, &server_private_keys, 1, |_| async {
+            Err(Error::InvalidRecoveryHint)
+        })
+        .with_parse_options(ParseOptions::new().with_field_limits(
+            FieldLimits::new().with_max_parameter_value_size(16),
+        ));
+
+    // A verified sender is told only that its request was refused.
+    let oversized = SealedRequest::new("fail", ARID::new(), &client)
+        .with_parameter("card", "4".repeat(64))
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(oversized).await.unwrap();
+    assert!(envelope.subject().is_encrypted());
+    let failure = SealedResponse::try_parse_early_failure(
+        &envelope,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(failure.signature_verified);
+    assert_eq!(
+        failure.error.extract_subject::<String>().unwrap(),
+        "request refused"
+    );
+
+    // A request whose sender is not verified is dropped.
+    let forged = SealedRequest::new("fail", ARID::new(), &client)
+        .to_envelope(None, Some(&outsider_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    assert!(service.call(forged).await.is_err());
+}
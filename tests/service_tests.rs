@@ -0,0 +1,305 @@
+#![cfg(feature = "service-adapter")]
+
+mod common;
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    LoadShedPolicy, overloaded_retry_after,
+    prelude::*,
+    service::{GstpService, Service, ServiceStatus},
+    shutting_down_continuation, shutting_down_retry_after,
+};
+
+use crate::common::new_party;
+
+#[tokio::test]
+async fn test_service_concurrency_limit() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let service = {
+        let server = server.clone();
+        let in_flight = in_flight.clone();
+        let max_in_flight = max_in_flight.clone();
+        GstpService::new(
+            &server.clone(),
+            &server_private_keys,
+            2,
+            move |request| {
+                let server = server.clone();
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now, Ordering::SeqCst);
+                    for _ in 0..5 {
+                        tokio::task::yield_now().await;
+                    }
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let n: i32 = request.extract_object_for_parameter("n")?;
+                    Ok(SealedResponse::new_success(request.id(), &server)
+                        .with_result(n * 2))
+                }
+            },
+        )
+    };
+
+    let mut tasks = Vec::new();
+    for n in 0..6 {
+        let id = ARID::new();
+        let request = SealedRequest::new("double", id, &client)
+            .with_parameter("n", n)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        let digest = request.digest();
+        let mut service = service.clone();
+        tasks.push(tokio::spawn(async move {
+            service.ready().await.unwrap();
+            (id, digest, n, service.call(request).await.unwrap())
+        }));
+    }
+
+    for task in tasks {
+        let (id, digest, n, envelope) = task.await.unwrap();
+        // The service binds each response to the request it answers.
+        let response = SealedResponse::try_from_encrypted_envelope_opt(
+            &envelope,
+            &ParseOptions::new()
+                .with_expected_id(id)
+                .with_request_digest(digest),
+            &client_private_keys,
+        )
+        .unwrap();
+        assert_eq!(response.extract_result::<i32>().unwrap(), n * 2);
+    }
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    assert_eq!(in_flight.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_service_answers_errors() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (_, outsider_private_keys) = new_party(&mut rng);
+
+    let mut service =
+        GstpService::new(&server, &server_private_keys, 1, |_| async {
+            Err(Error::InvalidRecoveryHint)
+        })
+        .with_parse_options(ParseOptions::new().with_field_limits(
+            FieldLimits::new().with_max_parameter_value_size(16),
+        ));
+
+    // A handler error is answered with a failure to the request.
+    let id = ARID::new();
+    let request = SealedRequest::new("fail", id, &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(request).await.unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        response.extract_error::<String>().unwrap(),
+        Error::InvalidRecoveryHint.to_string()
+    );
+
+    // A request refused once its sender is verified is answered with an
+    // early failure giving the error, encrypted to the sender.
+    let oversized = SealedRequest::new("fail", ARID::new(), &client)
+        .with_parameter("card", "4".repeat(64))
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(oversized).await.unwrap();
+    let failure = SealedResponse::try_parse_early_failure(
+        &envelope,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(failure.signature_verified);
+    assert!(
+        failure
+            .error
+            .extract_subject::<String>()
+            .unwrap()
+            .contains("exceeds the limit")
+    );
+
+    // A request whose signature does not verify is answered in the clear,
+    // with a fixed reason, rather than encrypted to the sender it claims.
+    let forged = SealedRequest::new("fail", ARID::new(), &client)
+        .to_envelope(None, Some(&outsider_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(forged).await.unwrap();
+    assert!(!envelope.subject().is_encrypted());
+    envelope.verify(server.verification_key().unwrap()).unwrap();
+    let failure = Response::try_from(envelope.try_unwrap().unwrap()).unwrap();
+    assert_eq!(
+        failure.extract_error::<String>().unwrap(),
+        "request refused"
+    );
+
+    // A request that cannot be decrypted is answered with a signed early
+    // failure, as there is nobody to encrypt it to.
+    let (outsider, _) = new_party(&mut rng);
+    let misdirected = SealedRequest::new("fail", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&outsider))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(misdirected).await.unwrap();
+    assert!(!envelope.subject().is_encrypted());
+    envelope.verify(server.verification_key().unwrap()).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "concurrency limit")]
+fn test_service_rejects_zero_concurrency_limit() {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    GstpService::new(&server, &server_private_keys, 0, |_| async {
+        Err(Error::InvalidRecoveryHint)
+    });
+}
+
+#[tokio::test]
+async fn test_service_sheds_load() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let policy = Arc::new(LoadShedPolicy::new(|| 1.0));
+    let mut service = {
+        let server = server.clone();
+        let handled = handled.clone();
+        GstpService::new(
+            &server.clone(),
+            &server_private_keys,
+            1,
+            move |request| {
+                handled.fetch_add(1, Ordering::SeqCst);
+                let response =
+                    SealedResponse::new_success(request.id(), &server);
+                async move { Ok(response) }
+            },
+        )
+        .with_load_shedding(policy.clone())
+    };
+
+    let id = ARID::new();
+    let request = SealedRequest::new("startExport", id, &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(request).await.unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert!(overloaded_retry_after(&response).is_some());
+    assert_eq!(handled.load(Ordering::SeqCst), 0);
+    assert_eq!(policy.stats().shed_fresh, 1);
+}
+
+#[tokio::test]
+async fn test_service_shutdown() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let handled = Arc::new(AtomicUsize::new(0));
+    let mut service = {
+        let server = server.clone();
+        let handled = handled.clone();
+        GstpService::new(
+            &server.clone(),
+            &server_private_keys,
+            2,
+            move |request| {
+                handled.fetch_add(1, Ordering::SeqCst);
+                let response =
+                    SealedResponse::new_success(request.id(), &server);
+                async move { Ok(response) }
+            },
+        )
+    };
+    assert_eq!(service.status(), ServiceStatus::Running);
+
+    service.begin_shutdown(Duration::from_secs(30));
+    assert_eq!(service.status(), ServiceStatus::ShuttingDown);
+
+    // A request continuing a workflow is answered with a shutdown failure
+    // that hands its state back in a fresh continuation.
+    let continuation = Continuation::new("cursor")
+        .to_envelope(Some(&server_private_keys.public_keys().unwrap()));
+    let id = ARID::new();
+    let request = SealedRequest::new("next", id, &client)
+        .with_peer_continuation(continuation)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    service.ready().await.unwrap();
+    let envelope = service.call(request).await.unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        shutting_down_retry_after(&response),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(handled.load(Ordering::SeqCst), 0);
+    let reissued = Continuation::try_from_envelope(
+        &shutting_down_continuation(&response).unwrap(),
+        None,
+        None,
+        Some(&server_private_keys),
+    )
+    .unwrap();
+    assert_eq!(
+        reissued.state().extract_subject::<String>().unwrap(),
+        "cursor"
+    );
+
+    service.drain().await;
+    service.close();
+    assert_eq!(service.status(), ServiceStatus::Closed);
+
+    // Once closed, requests are rejected outright.
+    let mut clone = service.clone();
+    assert!(matches!(clone.ready().await, Err(Error::ServiceClosed)));
+}
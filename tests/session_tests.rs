@@ -0,0 +1,463 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{Session, SessionKeys, inspect, prelude::*};
+
+use crate::common::new_party;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+struct Parties {
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+}
+
+fn parties() -> Parties {
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+    Parties {
+        client,
+        client_private_keys,
+        server,
+        server_private_keys,
+    }
+}
+
+/// The client proposes a session in its first request and the server
+/// acknowledges it.
+fn establish(parties: &Parties, session: SessionKeys) -> SessionKeys {
+    let request = SealedRequest::new("hello", ARID::new(), &parties.client)
+        .with_session_proposal(session.clone())
+        .to_envelope(
+            None,
+            Some(&parties.client_private_keys),
+            Some(&parties.server),
+        )
+        .unwrap();
+    assert_eq!(inspect::session_id(&request).unwrap(), None);
+    let request = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        None,
+        &parties.server_private_keys,
+    )
+    .unwrap();
+    let proposal = request.session_proposal().unwrap().clone();
+    assert_eq!(proposal, session);
+
+    let response = SealedResponse::new_success(request.id(), &parties.server)
+        .with_result("hi")
+        .with_session_ack(proposal.id())
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope(
+            None,
+            Some(&parties.server_private_keys),
+            Some(&parties.client),
+        )
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        Some(request.id()),
+        None,
+        &parties.client_private_keys,
+    )
+    .unwrap();
+    assert!(session.is_acknowledged_by(response.session_ack()));
+    proposal
+}
+
+fn seal_request(
+    parties: &Parties,
+    request: SealedRequest,
+    options: &SealOptions,
+) -> Envelope {
+    request
+        .to_envelope_with_options(
+            None,
+            Some(&parties.client_private_keys),
+            &[&parties.server],
+            options,
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_session_establishment_and_use() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let session = establish(&parties, SessionKeys::new(Date::now() + HOUR));
+
+    let id = ARID::new();
+    let request = seal_request(
+        &parties,
+        SealedRequest::new("next", id, &parties.client),
+        &SealOptions::new().with_session(session.clone()),
+    );
+    assert_eq!(inspect::session_id(&request).unwrap(), Some(session.id()));
+
+    // A server without the session cannot read the message.
+    assert!(matches!(
+        SealedRequest::try_from_envelope(
+            &request,
+            None,
+            None,
+            &parties.server_private_keys,
+        ),
+        Err(Error::UnknownSession(found)) if found == session.id()
+    ));
+
+    let server_options = ParseOptions::new().with_session(session.clone());
+    let request = SealedRequest::try_from_envelope_opt(
+        &request,
+        &server_options,
+        &parties.server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.id(), id);
+
+    // The server answers within the session too.
+    let response = SealedResponse::new_success(id, &parties.server)
+        .with_result("ok")
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+        .to_envelope_with_options(
+            None,
+            Some(&parties.server_private_keys),
+            &[&parties.client],
+            &SealOptions::new().with_session(session.clone()),
+        )
+        .unwrap();
+    assert_eq!(inspect::session_id(&response).unwrap(), Some(session.id()));
+    let response = SealedResponse::try_from_encrypted_envelope_opt(
+        &response,
+        &ParseOptions::new()
+            .with_expected_id(id)
+            .with_session(session),
+        &parties.client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.extract_result::<String>().unwrap(), "ok");
+}
+
+#[test]
+fn test_session_expiry() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let now = Date::now();
+    let session = establish(&parties, SessionKeys::new(now + HOUR));
+
+    let request = seal_request(
+        &parties,
+        SealedRequest::new("next", ARID::new(), &parties.client),
+        &SealOptions::new().with_session(session.clone()),
+    );
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            &ParseOptions::new()
+                .with_session(session.clone())
+                .with_now(now + 2 * HOUR),
+            &parties.server_private_keys,
+        ),
+        Err(Error::SessionExpired(_))
+    ));
+
+    // Once expired, sealing falls back to the recipients' public keys, which
+    // the server accepts.
+    let expired = SessionKeys::new(Date::from_ymd(2020, 1, 1));
+    let request = seal_request(
+        &parties,
+        SealedRequest::new("next", ARID::new(), &parties.client),
+        &SealOptions::new().with_session(expired.clone()),
+    );
+    assert_eq!(inspect::session_id(&request).unwrap(), None);
+    SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new().with_session(expired),
+        &parties.server_private_keys,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_session_downgrade_rejected() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let session = establish(&parties, SessionKeys::new(Date::now() + HOUR));
+    let server_options = ParseOptions::new().with_session(session);
+
+    let downgraded = seal_request(
+        &parties,
+        SealedRequest::new("next", ARID::new(), &parties.client),
+        &SealOptions::new(),
+    );
+    assert!(matches!(
+        SealedRequest::try_from_envelope_opt(
+            &downgraded,
+            &server_options,
+            &parties.server_private_keys,
+        ),
+        Err(Error::SessionDowngrade)
+    ));
+
+    // Re-keying with a new proposal is allowed.
+    let rekey = SessionKeys::new(Date::now() + HOUR);
+    let rekeyed = seal_request(
+        &parties,
+        SealedRequest::new("next", ARID::new(), &parties.client)
+            .with_session_proposal(rekey.clone()),
+        &SealOptions::new(),
+    );
+    let request = SealedRequest::try_from_envelope_opt(
+        &rekeyed,
+        &server_options,
+        &parties.server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.session_proposal(), Some(&rekey));
+}
+
+#[test]
+fn test_session_proposal_must_be_encrypted() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let session = SessionKeys::new(Date::now() + HOUR);
+
+    // Sealing a proposal to nobody would send the session key in the clear.
+    let request = SealedRequest::new("hello", ARID::new(), &parties.client)
+        .with_session_proposal(session.clone());
+    assert!(matches!(
+        request.to_envelope(None, Some(&parties.client_private_keys), None),
+        Err(Error::SessionProposalNotEncrypted)
+    ));
+    let response = SealedResponse::new_success(ARID::new(), &parties.server)
+        .with_result("hi")
+        .with_session_proposal(session);
+    assert!(matches!(
+        response.to_envelope(None, Some(&parties.server_private_keys), None),
+        Err(Error::SessionProposalNotEncrypted)
+    ));
+
+    // A proposal that arrives unencrypted is refused.
+    let signed = SealedRequest::new("hello", ARID::new(), &parties.client)
+        .to_envelope(None, Some(&parties.client_private_keys), None)
+        .unwrap();
+    let forged = signed
+        .try_unwrap()
+        .unwrap()
+        .add_assertion("sessionProposal", "proposal")
+        .sign(&parties.client_private_keys);
+    assert!(matches!(
+        SealedRequest::try_from_signed_envelope(&forged, None, None),
+        Err(Error::SessionProposalNotEncrypted)
+    ));
+}
+
+#[test]
+fn test_session_ping_pong() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let keys = establish(&parties, SessionKeys::new(Date::now() + HOUR));
+    let mut client_session = Session::new(keys.clone(), &parties.server);
+    let mut server_session = Session::new(keys, &parties.client);
+
+    for round in 1..=5u32 {
+        let id = ARID::new();
+        let ping = client_session
+            .seal_request(
+                &SealedRequest::new("ping", id, &parties.client)
+                    .with_parameter("round", round)
+                    .with_state(round),
+                &SealOptions::new(),
+            )
+            .unwrap();
+        let request = server_session
+            .open_request(
+                &ping,
+                &ParseOptions::new(),
+                &parties.server_private_keys,
+            )
+            .unwrap();
+        assert_eq!(
+            request
+                .extract_object_for_parameter::<u32>("round")
+                .unwrap(),
+            round
+        );
+
+        let pong = server_session
+            .seal_response(
+                &SealedResponse::new_success(id, &parties.server)
+                    .with_result(round)
+                    .with_optional_peer_continuation(
+                        request.peer_continuation().cloned(),
+                    ),
+                &SealOptions::new(),
+            )
+            .unwrap();
+        let response = client_session
+            .open_response(
+                &pong,
+                &ParseOptions::new().with_expected_id(id),
+                &parties.client_private_keys,
+            )
+            .unwrap();
+        assert_eq!(response.extract_result::<u32>().unwrap(), round);
+        // Continuations work as in any other message.
+        assert_eq!(response.state().unwrap(), &Envelope::new(round));
+    }
+    assert_eq!(client_session.sent(), 5);
+    assert_eq!(client_session.received(), 5);
+    assert_eq!(server_session.sent(), 5);
+    assert_eq!(server_session.received(), 5);
+}
+
+#[test]
+fn test_session_replay_and_reflection() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let keys = establish(&parties, SessionKeys::new(Date::now() + HOUR));
+    let mut client_session = Session::new(keys.clone(), &parties.server);
+    let mut server_session = Session::new(keys, &parties.client);
+
+    let ping = client_session
+        .seal_request(
+            &SealedRequest::new("ping", ARID::new(), &parties.client),
+            &SealOptions::new(),
+        )
+        .unwrap();
+    server_session
+        .open_request(&ping, &ParseOptions::new(), &parties.server_private_keys)
+        .unwrap();
+
+    // The same message is not accepted twice.
+    assert!(matches!(
+        server_session.open_request(
+            &ping,
+            &ParseOptions::new(),
+            &parties.server_private_keys,
+        ),
+        Err(Error::SessionReplay {
+            counter: 1,
+            last: 1
+        })
+    ));
+
+    // Nor is our own message reflected back to us.
+    assert!(matches!(
+        client_session.open_request(
+            &ping,
+            &ParseOptions::new(),
+            &parties.client_private_keys,
+        ),
+        Err(Error::UnexpectedSender { .. })
+    ));
+    assert_eq!(client_session.received(), 0);
+
+    // Nor a message sealed with other keys.
+    let mut other_session =
+        Session::new(SessionKeys::new(Date::now() + HOUR), &parties.client);
+    assert!(matches!(
+        other_session.open_request(
+            &ping,
+            &ParseOptions::new(),
+            &parties.server_private_keys,
+        ),
+        Err(Error::UnknownSession(_))
+    ));
+}
+
+#[test]
+fn test_session_limits_and_rekeying() {
+    bc_envelope::register_tags();
+    let parties = parties();
+    let now = Date::now();
+    let keys = establish(&parties, SessionKeys::new(now + HOUR));
+    let mut client_session =
+        Session::new(keys.clone(), &parties.server).with_message_limit(2);
+    let mut server_session = Session::new(keys.clone(), &parties.client);
+    let options = SealOptions::new().with_now(now);
+
+    // A session refuses to seal once its keys expire.
+    assert!(matches!(
+        client_session.seal_request(
+            &SealedRequest::new("ping", ARID::new(), &parties.client),
+            &SealOptions::new().with_now(now + HOUR * 2),
+        ),
+        Err(Error::SessionExpired(id)) if id == keys.id()
+    ));
+
+    let first = client_session
+        .seal_request(
+            &SealedRequest::new("ping", ARID::new(), &parties.client),
+            &options,
+        )
+        .unwrap();
+    let new_keys = SessionKeys::new(now + HOUR);
+    let id = ARID::new();
+    let second = client_session
+        .seal_request(
+            &SealedRequest::new("rekey", id, &parties.client)
+                .with_session_proposal(new_keys.clone()),
+            &options,
+        )
+        .unwrap();
+
+    // The session refuses to seal past its limit until it is rekeyed.
+    assert!(matches!(
+        client_session.seal_request(
+            &SealedRequest::new("ping", ARID::new(), &parties.client),
+            &options,
+        ),
+        Err(Error::SessionLimitReached { limit: 2, .. })
+    ));
+
+    let parse_options = ParseOptions::new().with_now(now);
+    server_session
+        .open_request(&first, &parse_options, &parties.server_private_keys)
+        .unwrap();
+    let request = server_session
+        .open_request(&second, &parse_options, &parties.server_private_keys)
+        .unwrap();
+    let proposal = request.session_proposal().unwrap().clone();
+    let ack = server_session
+        .seal_response(
+            &SealedResponse::new_success(id, &parties.server)
+                .with_session_ack(proposal.id())
+                .with_optional_peer_continuation(
+                    request.peer_continuation().cloned(),
+                ),
+            &options,
+        )
+        .unwrap();
+    let response = client_session
+        .open_response(
+            &ack,
+            &parse_options.clone().with_expected_id(id),
+            &parties.client_private_keys,
+        )
+        .unwrap();
+    assert!(new_keys.is_acknowledged_by(response.session_ack()));
+    client_session.rekey(new_keys);
+    server_session.rekey(proposal);
+
+    let third = client_session
+        .seal_request(
+            &SealedRequest::new("ping", ARID::new(), &parties.client),
+            &options,
+        )
+        .unwrap();
+    assert_eq!(client_session.sent(), 1);
+    assert_eq!(client_session.message_limit(), Some(2));
+    server_session
+        .open_request(&third, &parse_options, &parties.server_private_keys)
+        .unwrap();
+    assert_eq!(server_session.received(), 1);
+}
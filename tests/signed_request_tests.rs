@@ -0,0 +1,100 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, Encrypter, XIDProvider};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_signed_request() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+    let now = Date::from_string("2024-07-01T12:00:00Z").unwrap();
+
+    // The server previously gave the client a continuation.
+    let server_continuation = Continuation::new("page 2")
+        .with_valid_until(now + Duration::from_secs(60))
+        .to_envelope(Some(server.encryption_key().unwrap() as &dyn Encrypter));
+
+    let id = ARID::new();
+    let envelope = SealedRequest::new("listRecords", id, &client)
+        .with_parameter("limit", 10)
+        .with_peer_continuation(server_continuation)
+        .to_envelope(None, Some(&client_private_keys), None)
+        .unwrap();
+    assert!(!envelope.subject().is_encrypted());
+
+    let request =
+        SealedRequest::try_from_signed_envelope(&envelope, None, Some(now))
+            .unwrap();
+    assert_eq!(request.id(), id);
+    assert_eq!(request.function(), &Function::from("listRecords"));
+    assert_eq!(request.sender().xid(), client.xid());
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<i32>("limit")
+            .unwrap(),
+        10
+    );
+    assert!(request.peer_continuation().is_some());
+    // Without our keys the continuation cannot be opened.
+    assert!(request.state().is_none());
+
+    let request = SealedRequest::try_from_signed_envelope_opt(
+        &envelope,
+        &ParseOptions::new().with_now(now),
+        Some(&server_private_keys),
+    )
+    .unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "page 2"
+    );
+}
+
+#[test]
+fn test_signed_request_rejections() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, _) = new_party(&mut rng);
+
+    // An encrypted request is reported as such.
+    let encrypted = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    assert!(matches!(
+        SealedRequest::try_from_signed_envelope(&encrypted, None, None),
+        Err(Error::UnexpectedEncryption)
+    ));
+
+    // An unsigned request does not verify.
+    let unsigned = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope(None, None, None)
+        .unwrap();
+    assert!(
+        SealedRequest::try_from_signed_envelope(&unsigned, None, None).is_err()
+    );
+
+    // A request must still carry the sender's continuation.
+    let bare = Request::new("test", ARID::new())
+        .into_envelope()
+        .add_assertion(known_values::SENDER, client.clone())
+        .sign(&client_private_keys);
+    assert!(matches!(
+        SealedRequest::try_from_signed_envelope(&bare, None, None),
+        Err(Error::MissingPeerContinuation)
+    ));
+}
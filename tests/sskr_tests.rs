@@ -0,0 +1,125 @@
+#![cfg(feature = "sskr")]
+
+mod common;
+
+use bc_components::{ARID, PrivateKeys, SSKRGroupSpec, SSKRShare, SSKRSpec};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{prelude::*, sharding};
+
+use crate::common::new_party;
+
+fn two_of_three() -> SSKRSpec {
+    SSKRSpec::new(1, vec![SSKRGroupSpec::new(2, 3).unwrap()]).unwrap()
+}
+
+fn sealed_request() -> (Envelope, ARID, PrivateKeys) {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let id = ARID::new();
+    let envelope = SealedRequest::new("test", id, &client)
+        .with_parameter("param1", 42)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    (envelope, id, server_private_keys)
+}
+
+#[test]
+fn test_split_and_join() {
+    bc_envelope::register_tags();
+
+    let (sealed, id, server_private_keys) = sealed_request();
+    let shares = sharding::split(&sealed, &two_of_three()).unwrap();
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].len(), 3);
+
+    // Any two of the three shares recover the message.
+    let joined =
+        sharding::join(&[shares[0][2].clone(), shares[0][0].clone()]).unwrap();
+    assert!(joined.is_identical_to(&sealed));
+    let request = SealedRequest::try_from_envelope(
+        &joined,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.id(), id);
+    assert_eq!(
+        request
+            .extract_object_for_parameter::<i32>("param1")
+            .unwrap(),
+        42
+    );
+}
+
+#[test]
+fn test_insufficient_shares() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request();
+    let shares = sharding::split(&sealed, &two_of_three()).unwrap();
+
+    // A repeated share does not count twice.
+    let error = sharding::join(&[shares[0][1].clone(), shares[0][1].clone()])
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::InsufficientShares {
+            groups: 0,
+            threshold: 1
+        }
+    ));
+    assert!(matches!(
+        sharding::join(&[]),
+        Err(Error::InsufficientShares { .. })
+    ));
+}
+
+#[test]
+fn test_mismatched_shares() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request();
+    let first = sharding::split(&sealed, &two_of_three()).unwrap();
+    let second = sharding::split(&sealed, &two_of_three()).unwrap();
+
+    let error = sharding::join(&[first[0][0].clone(), second[0][1].clone()])
+        .unwrap_err();
+    assert!(matches!(error, Error::MismatchedShares));
+}
+
+#[test]
+fn test_corrupted_share() {
+    bc_envelope::register_tags();
+
+    let (sealed, _, _) = sealed_request();
+    let shares = sharding::split(&sealed, &two_of_three()).unwrap();
+
+    // Flip a bit in the share value, leaving its metadata intact.
+    let original = &shares[0][1];
+    let share: SSKRShare = original
+        .object_for_predicate(known_values::SSKR_SHARE)
+        .unwrap()
+        .extract_subject()
+        .unwrap();
+    let mut data = share.as_bytes().to_vec();
+    data[8] ^= 0x01;
+    let corrupted = original
+        .remove_assertion(
+            original
+                .assertion_with_predicate(known_values::SSKR_SHARE)
+                .unwrap(),
+        )
+        .add_assertion(known_values::SSKR_SHARE, SSKRShare::from_data(data));
+
+    let error = sharding::join(&[shares[0][0].clone(), corrupted]).unwrap_err();
+    assert!(matches!(error, Error::CorruptedShare));
+
+    // A share envelope without a share assertion is also rejected.
+    let error =
+        sharding::join(&[shares[0][0].clone(), Envelope::new("Not a share.")])
+            .unwrap_err();
+    assert!(matches!(error, Error::CorruptedShare));
+}
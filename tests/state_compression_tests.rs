@@ -0,0 +1,131 @@
+mod common;
+
+use bc_components::{ARID, Compressed};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{consts, prelude::*};
+
+use crate::common::new_party;
+
+fn compressible_state() -> Envelope {
+    Expression::new("checkout")
+        .with_parameter("cart", "item ".repeat(200))
+        .into_envelope()
+}
+
+#[test]
+fn test_continuation_state_compression() {
+    let continuation = Continuation::new(compressible_state());
+    let plain = continuation.to_envelope(None);
+    let compressed = continuation
+        .clone()
+        .with_state_compression(true)
+        .to_envelope(None);
+    assert!(compressed.subject().is_compressed());
+    assert!(compressed.to_cbor_data().len() < plain.to_cbor_data().len() / 2);
+    assert_eq!(compressed.digest(), plain.digest());
+
+    // Without compression the output is unchanged.
+    assert_eq!(
+        continuation
+            .clone()
+            .with_state_compression(false)
+            .to_envelope(None)
+            .to_cbor_data(),
+        plain.to_cbor_data()
+    );
+
+    let parsed =
+        Continuation::try_from_envelope(&compressed, None, None, None).unwrap();
+    assert_eq!(parsed, continuation);
+
+    // State that does not shrink is left uncompressed.
+    let small = Continuation::new("Small.");
+    assert_eq!(
+        small.clone().with_state_compression(true).to_envelope(None),
+        small.to_envelope(None)
+    );
+    assert!(
+        !small
+            .with_state_compression(true)
+            .to_envelope(None)
+            .subject()
+            .is_compressed()
+    );
+}
+
+#[test]
+fn test_compressed_state_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let seal = |compress: bool| {
+        SealedRequest::new("test", ARID::new(), &client)
+            .with_state(compressible_state())
+            .seal_detailed(
+                None,
+                Some(&client_private_keys),
+                &[&server],
+                &SealOptions::new().with_state_compression(compress),
+            )
+            .unwrap()
+            .own_continuation
+            .unwrap()
+    };
+    let plain = seal(false);
+    let compressed = seal(true);
+    assert!(compressed.to_cbor_data().len() < plain.to_cbor_data().len());
+
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_peer_continuation(compressed)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parsed = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.state(), Some(&compressible_state()));
+}
+
+#[test]
+fn test_rejects_overlarge_decompressed_state() {
+    // A compressed state declaring an absurd size is refused before it is
+    // decompressed.
+    let wrapped = Envelope::new("State.").wrap();
+    let size = 100 * 1024 * 1024;
+    let bomb =
+        Compressed::new(0, size, vec![0; 16], Some(wrapped.digest())).unwrap();
+    let envelope = Envelope::try_from(bomb).unwrap();
+    assert!(matches!(
+        Continuation::try_from_envelope(&envelope, None, None, None),
+        Err(Error::ContinuationStateTooLarge { size: found, limit })
+            if found == size && limit == consts::MAX_DECOMPRESSED_STATE_SIZE
+    ));
+}
+
+#[test]
+fn test_rejects_state_understating_its_size() {
+    // State declaring a size within the limit but inflating beyond it stops
+    // at the size declared.
+    let state = vec![0u8; consts::MAX_DECOMPRESSED_STATE_SIZE + 1];
+    let data = miniz_oxide::deflate::compress_to_vec(&state, 6);
+    let wrapped = Envelope::new("State.").wrap();
+    let understated = Compressed::new(
+        bc_crypto::hash::crc32(&state),
+        1024 * 1024,
+        data,
+        Some(wrapped.digest()),
+    )
+    .unwrap();
+    let envelope = Envelope::try_from(understated).unwrap();
+    assert!(matches!(
+        Continuation::try_from_envelope(&envelope, None, None, None),
+        Err(Error::Components(_))
+    ));
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{StateEpochs, prelude::*};
+
+use crate::common::new_party;
+
+struct Conversation {
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+}
+
+impl Conversation {
+    /// Returns the continuation the server issues with its response.
+    fn issue(&self, state: &str, epochs: &Arc<StateEpochs>) -> Envelope {
+        let id = ARID::new();
+        let response = SealedResponse::new_success(id, &self.server)
+            .with_state(state)
+            .to_envelope_with_options(
+                None,
+                Some(&self.server_private_keys),
+                &[&self.client],
+                &SealOptions::default().with_state_epochs(epochs.clone()),
+            )
+            .unwrap();
+        SealedResponse::try_from_encrypted_envelope(
+            &response,
+            Some(id),
+            None,
+            &self.client_private_keys,
+        )
+        .unwrap()
+        .peer_continuation()
+        .unwrap()
+        .clone()
+    }
+
+    /// Returns the continuation to the server, which parses it.
+    fn return_to_server(
+        &self,
+        continuation: &Envelope,
+        options: &ParseOptions,
+    ) -> gstp::Result<SealedRequest> {
+        let request = SealedRequest::new("next", ARID::new(), &self.client)
+            .with_peer_continuation(continuation.clone())
+            .to_envelope(
+                None,
+                Some(&self.client_private_keys),
+                Some(&self.server),
+            )
+            .unwrap();
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            options,
+            &self.server_private_keys,
+        )
+    }
+}
+
+fn conversation() -> Conversation {
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    Conversation {
+        server,
+        server_private_keys,
+        client,
+        client_private_keys,
+    }
+}
+
+#[test]
+fn test_state_encrypted_with_derived_key() {
+    bc_envelope::register_tags();
+
+    let conversation = conversation();
+    let epochs = Arc::new(StateEpochs::new(&conversation.server_private_keys));
+    let continuation = conversation.issue("cursor", &epochs);
+
+    // The continuation is not encrypted to the server's identity key.
+    assert!(
+        continuation
+            .decrypt_to_recipient(&conversation.server_private_keys)
+            .is_err()
+    );
+
+    let options = ParseOptions::new().with_state_epochs(epochs);
+    let request = conversation
+        .return_to_server(&continuation, &options)
+        .unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "cursor"
+    );
+
+    // Without the epochs the state cannot be recovered.
+    assert!(matches!(
+        conversation.return_to_server(&continuation, &ParseOptions::new()),
+        Err(Error::StateEpochsRequired)
+    ));
+}
+
+#[test]
+fn test_discarded_epoch_cannot_be_decrypted() {
+    bc_envelope::register_tags();
+
+    let conversation = conversation();
+    let epochs = Arc::new(StateEpochs::new(&conversation.server_private_keys));
+    let options = ParseOptions::new().with_state_epochs(epochs.clone());
+
+    let old = conversation.issue("old", &epochs);
+    assert_eq!(epochs.rotate(), 1);
+    let current = conversation.issue("current", &epochs);
+
+    // Until it is discarded, the old epoch still decrypts.
+    conversation.return_to_server(&old, &options).unwrap();
+
+    assert!(epochs.discard(0));
+    assert_eq!(epochs.epochs(), vec![1]);
+    assert!(matches!(
+        conversation.return_to_server(&old, &options),
+        Err(Error::UnknownStateEpoch(0))
+    ));
+    let request = conversation.return_to_server(&current, &options).unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "current"
+    );
+
+    // The current epoch is never discarded.
+    assert!(!epochs.discard(1));
+    epochs.rotate();
+    epochs.rotate();
+    epochs.retain_latest(0);
+    assert_eq!(epochs.epochs(), vec![3]);
+    assert_eq!(epochs.current_epoch(), 3);
+}
+
+#[test]
+fn test_salts_are_held_by_their_manager() {
+    bc_envelope::register_tags();
+
+    let conversation = conversation();
+    let epochs = Arc::new(StateEpochs::new(&conversation.server_private_keys));
+    let continuation = conversation.issue("cursor", &epochs);
+
+    // Another manager for the same identity holds other salts, as a server
+    // that lost its salts would.
+    let other = Arc::new(StateEpochs::new(&conversation.server_private_keys));
+    let options = ParseOptions::new().with_state_epochs(other);
+    assert!(
+        conversation
+            .return_to_server(&continuation, &options)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_epochs_survive_restart() {
+    bc_envelope::register_tags();
+
+    let conversation = conversation();
+    let epochs = Arc::new(StateEpochs::new(&conversation.server_private_keys));
+    let old = conversation.issue("old", &epochs);
+    epochs.rotate();
+    let current = conversation.issue("current", &epochs);
+    epochs.rotate();
+    epochs.discard(1);
+
+    // The salts are exported encrypted, and only the identity they were
+    // exported with restores them.
+    let exported = epochs.to_encrypted_envelope().to_cbor_data();
+    let exported = Envelope::try_from_cbor_data(exported).unwrap();
+    assert!(
+        StateEpochs::try_from_encrypted_envelope(
+            &exported,
+            &conversation.client_private_keys
+        )
+        .is_err()
+    );
+    let restored = Arc::new(
+        StateEpochs::try_from_encrypted_envelope(
+            &exported,
+            &conversation.server_private_keys,
+        )
+        .unwrap(),
+    );
+    assert_eq!(restored.epochs(), vec![0, 2]);
+    assert_eq!(restored.current_epoch(), 2);
+
+    // Continuations issued before the restart decrypt unless their epoch was
+    // discarded.
+    let options = ParseOptions::new().with_state_epochs(restored);
+    let request = conversation.return_to_server(&old, &options).unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "old"
+    );
+    assert!(matches!(
+        conversation.return_to_server(&current, &options),
+        Err(Error::UnknownStateEpoch(1))
+    ));
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::{
+    ContinuationPolicy, REFRESH_STATE, RecoveryAdvisor, StateLease, consts,
+    continuation_expired_response, prelude::*, refresh_response,
+};
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+fn secs(secs: u64) -> Duration { Duration::from_secs(secs) }
+
+const POLICY: ContinuationPolicy = ContinuationPolicy {
+    max_state_lifetime: Some(Duration::from_secs(30)),
+    ..consts::DEFAULT_CONTINUATION_POLICY
+};
+
+struct Parties {
+    server: XIDDocument,
+    server_private_keys: PrivateKeys,
+    client: XIDDocument,
+    client_private_keys: PrivateKeys,
+}
+
+impl Parties {
+    fn new() -> Self {
+        let mut rng = make_fake_random_number_generator();
+        let (server, server_private_keys) = new_party(&mut rng);
+        let (client, client_private_keys) = new_party(&mut rng);
+        Self {
+            server,
+            server_private_keys,
+            client,
+            client_private_keys,
+        }
+    }
+
+    /// Seals `request` and parses it on the server at `now`.
+    fn send(
+        &self,
+        request: SealedRequest,
+        now: Date,
+    ) -> (Option<SealedRequest>, PartialParse) {
+        let envelope = request
+            .to_envelope(
+                None,
+                Some(&self.client_private_keys),
+                Some(&self.server),
+            )
+            .unwrap();
+        SealedRequest::try_from_envelope_lenient(
+            &envelope,
+            None,
+            Some(now),
+            &self.server_private_keys,
+        )
+    }
+
+    /// Seals `response` at `now` and parses it on the client.
+    fn answer(&self, response: SealedResponse, now: Date) -> SealedResponse {
+        let id = response.id();
+        let envelope = response
+            .to_envelope_with_options(
+                None,
+                Some(&self.server_private_keys),
+                &[&self.client],
+                &SealOptions::new().with_now(now),
+            )
+            .unwrap();
+        SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            id,
+            None,
+            &self.client_private_keys,
+        )
+        .unwrap()
+    }
+
+    /// Opens a session at `now` whose state the client asks to keep for a
+    /// minute, returning the client's lease.
+    fn open(&self, now: Date) -> StateLease {
+        let (request, _) = self.send(
+            SealedRequest::new("openSession", ARID::new(), &self.client)
+                .with_proposed_state_lifetime(secs(60)),
+            now,
+        );
+        let request = request.unwrap();
+        assert_eq!(request.proposed_state_lifetime(), Some(secs(60)));
+        let response = self.answer(
+            SealedResponse::new_success(request.id(), &self.server)
+                .with_state("session 1")
+                .with_negotiated_state_lifetime(&request, &POLICY),
+            now,
+        );
+        StateLease::from_response(&response, now).unwrap()
+    }
+}
+
+#[test]
+fn test_negotiated_lifetime_is_clamped() {
+    bc_envelope::register_tags();
+
+    let parties = Parties::new();
+    let start = date("2024-07-01T12:00:00Z");
+    let lease = parties.open(start);
+
+    // The server grants half of what was proposed, and the client sees it.
+    assert_eq!(lease.granted(), secs(30));
+    assert_eq!(lease.expires(), start + secs(30));
+
+    // The continuation expires when the granted lifetime lapses.
+    let resume = |now: Date| {
+        parties.send(
+            SealedRequest::new("resume", ARID::new(), &parties.client)
+                .with_peer_continuation(lease.continuation().clone()),
+            now,
+        )
+    };
+    assert!(resume(start + secs(29)).0.is_some());
+    assert!(matches!(
+        resume(start + secs(30)).1.error,
+        Some(Error::ContinuationExpired)
+    ));
+
+    assert_eq!(POLICY.grant_state_lifetime(Some(secs(10))), Some(secs(10)));
+    assert_eq!(POLICY.grant_state_lifetime(None), Some(secs(30)));
+    assert_eq!(
+        consts::DEFAULT_CONTINUATION_POLICY.grant_state_lifetime(None),
+        None
+    );
+}
+
+#[test]
+fn test_refresh_before_expiry() {
+    bc_envelope::register_tags();
+
+    let parties = Parties::new();
+    let start = date("2024-07-01T12:00:00Z");
+    let lease = parties.open(start);
+
+    // The client schedules the refresh ten seconds ahead of expiry.
+    let lead = secs(10);
+    assert_eq!(lease.refresh_at(lead), start + secs(20));
+    assert!(!lease.needs_refresh(start + secs(19), lead));
+    let now = start + secs(25);
+    assert!(lease.needs_refresh(now, lead));
+
+    let (request, _) =
+        parties.send(lease.refresh_request(ARID::new(), &parties.client), now);
+    let request = request.unwrap();
+    assert_eq!(request.function(), &Function::from(REFRESH_STATE));
+    let response = parties
+        .answer(refresh_response(&request, &parties.server, &POLICY), now);
+    let lease = StateLease::from_response(&response, now).unwrap();
+    assert_eq!(lease.expires(), now + secs(30));
+
+    // The refreshed state outlives the original.
+    let (request, _) = parties.send(
+        SealedRequest::new("resume", ARID::new(), &parties.client)
+            .with_peer_continuation(lease.continuation().clone()),
+        start + secs(50),
+    );
+    assert_eq!(
+        request
+            .unwrap()
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "session 1"
+    );
+}
+
+#[test]
+fn test_missed_refresh_leads_to_recovery() {
+    bc_envelope::register_tags();
+
+    let parties = Parties::new();
+    let start = date("2024-07-01T12:00:00Z");
+    let lease = parties.open(start);
+
+    // The refresh comes too late, so the server offers recovery.
+    let now = start + secs(40);
+    let (request, partial) =
+        parties.send(lease.refresh_request(ARID::new(), &parties.client), now);
+    assert!(request.is_none());
+    assert!(matches!(partial.error, Some(Error::ContinuationExpired)));
+    let failure = parties.answer(
+        continuation_expired_response(
+            partial.claimed_id.unwrap(),
+            &parties.server,
+            partial.returned_continuation.unwrap(),
+            "openSession",
+            secs(30),
+            &SealOptions::new().with_now(now),
+        )
+        .unwrap(),
+        now,
+    );
+    let advisor = RecoveryAdvisor::new().with_auto_recovery("openSession");
+    let hint = advisor.recovery_hint(&failure).unwrap();
+    assert!(advisor.can_auto_recover(&hint, now));
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn state() -> Envelope { Envelope::new("x".repeat(1000)) }
+
+fn state_size() -> usize { Continuation::new(state()).state_size() }
+
+fn assert_too_large<T: std::fmt::Debug>(result: gstp::Result<T>) {
+    assert!(matches!(
+        result.unwrap_err(),
+        Error::ContinuationStateTooLarge { size, limit }
+            if size == state_size() && limit == size - 1
+    ));
+}
+
+#[test]
+fn test_continuation_state_size() {
+    let continuation = Continuation::new(state());
+    assert_eq!(continuation.state_size(), state().to_cbor_data().len());
+    continuation.check_state_size(state_size()).unwrap();
+    assert_too_large(continuation.check_state_size(state_size() - 1));
+}
+
+#[test]
+fn test_seal_paths_enforce_state_size() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let options = |limit| SealOptions::new().with_max_state_size(limit);
+
+    let request = |limit| {
+        SealedRequest::new("test", ARID::new(), &client)
+            .with_state(state())
+            .to_envelope_with_options(
+                None,
+                Some(&client_private_keys),
+                &[&server],
+                &options(limit),
+            )
+    };
+    request(state_size()).unwrap();
+    assert_too_large(request(state_size() - 1));
+
+    let response = |limit| {
+        SealedResponse::new_success(ARID::new(), &server)
+            .with_state(state())
+            .to_envelope_with_options(
+                None,
+                Some(&server_private_keys),
+                &[&client],
+                &options(limit),
+            )
+    };
+    response(state_size()).unwrap();
+    assert_too_large(response(state_size() - 1));
+
+    let event = |limit| {
+        SealedEvent::<String>::new("test", ARID::new(), &server)
+            .with_state(state())
+            .to_envelope_with_options(
+                None,
+                Some(&server_private_keys),
+                &[&client],
+                &options(limit),
+            )
+    };
+    event(state_size()).unwrap();
+    assert_too_large(event(state_size() - 1));
+}
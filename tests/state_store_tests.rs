@@ -0,0 +1,119 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{ContinuationMode, MemoryStateStore, StateStore, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_state_by_reference() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let store = Arc::new(MemoryStateStore::new());
+    let state = Envelope::new("cursor").add_assertion("rows", "x".repeat(4096));
+
+    // The server keeps the state and issues a continuation referring to it.
+    let id = ARID::new();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_state(state.clone())
+        .to_envelope_with_options(
+            None,
+            Some(&server_private_keys),
+            &[&client],
+            &SealOptions::new().with_continuation_mode(
+                ContinuationMode::Reference(store.clone()),
+            ),
+        )
+        .unwrap();
+    assert_eq!(store.len(), 1);
+    let continuation = SealedResponse::try_from_encrypted_envelope(
+        &envelope,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap()
+    .peer_continuation()
+    .unwrap()
+    .clone();
+    assert!(continuation.to_cbor_data().len() < state.to_cbor_data().len());
+
+    // The state is resolved transparently when the continuation comes back.
+    let request = SealedRequest::new("nextPage", ARID::new(), &client)
+        .with_peer_continuation(continuation)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parse = |options: &ParseOptions| {
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            options,
+            &server_private_keys,
+        )
+    };
+    let options = ParseOptions::new().with_state_store(store.clone());
+    let parsed = parse(&options).unwrap();
+    assert!(parsed.state().unwrap().is_identical_to(&state));
+
+    // Without the store, or once the store has dropped the state, the
+    // workflow cannot be resumed.
+    let unavailable = |result: gstp::Result<SealedRequest>| {
+        matches!(result, Err(Error::ContinuationStateUnavailable(_)))
+    };
+    assert!(unavailable(parse(&ParseOptions::new())));
+    let empty = Arc::new(MemoryStateStore::new());
+    assert!(unavailable(parse(
+        &ParseOptions::new().with_state_store(empty)
+    )));
+}
+
+#[test]
+fn test_inline_state_is_unaffected_by_store() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let store = Arc::new(MemoryStateStore::new());
+    let id = ARID::new();
+    let request = SealedRequest::new("start", id, &client)
+        .with_state("inline")
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let request = SealedRequest::try_from_envelope_opt(
+        &request,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    let envelope = SealedResponse::new_success(id, &server)
+        .with_peer_continuation(request.peer_continuation().unwrap().clone())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_encrypted_envelope_opt(
+        &envelope,
+        &ParseOptions::new()
+            .with_expected_id(id)
+            .with_state_store(store.clone()),
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(
+        response
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "inline"
+    );
+    assert!(store.is_empty());
+    assert!(store.get(ARID::new()).is_none());
+}
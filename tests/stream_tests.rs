@@ -0,0 +1,119 @@
+mod common;
+
+use bc_components::{ARID, PrivateKeys};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{prelude::*, transport::stream};
+
+use crate::common::new_party;
+
+const MAX_SIZE: usize = 64 * 1024;
+
+fn limits() -> ParseLimits {
+    ParseLimits::default().with_max_message_size(MAX_SIZE)
+}
+
+fn sealed_requests() -> (Vec<(Envelope, ARID)>, PrivateKeys) {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let requests = (0..3)
+        .map(|i| {
+            let id = ARID::new();
+            let envelope = SealedRequest::new("test", id, &client)
+                .with_parameter("index", i)
+                .to_envelope(None, Some(&client_private_keys), Some(&server))
+                .unwrap();
+            (envelope, id)
+        })
+        .collect();
+    (requests, server_private_keys)
+}
+
+fn stream_of(requests: &[(Envelope, ARID)]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for (envelope, _) in requests {
+        stream::write_envelope(&mut stream, envelope).unwrap();
+    }
+    stream
+}
+
+#[test]
+fn test_back_to_back_requests() {
+    let (requests, server_private_keys) = sealed_requests();
+    let stream = stream_of(&requests);
+    let first_len = requests[0].0.to_cbor_data().len() as u32;
+    assert_eq!(&stream[..4], &first_len.to_be_bytes());
+
+    let mut reader = stream.as_slice();
+    for (i, (sealed, id)) in requests.iter().enumerate() {
+        let envelope = stream::read_envelope(&mut reader, &limits()).unwrap();
+        assert!(envelope.is_identical_to(sealed));
+        let request = SealedRequest::try_from_envelope(
+            &envelope,
+            Some(*id),
+            None,
+            &server_private_keys,
+        )
+        .unwrap();
+        assert_eq!(
+            request
+                .extract_object_for_parameter::<i32>("index")
+                .unwrap(),
+            i as i32
+        );
+    }
+    assert!(matches!(
+        stream::read_envelope(&mut reader, &limits()),
+        Err(Error::EndOfStream)
+    ));
+}
+
+#[test]
+fn test_truncated_stream() {
+    let (requests, _) = sealed_requests();
+    let mut stream = stream_of(&requests);
+    stream.truncate(stream.len() - 5);
+
+    let mut reader = stream.as_slice();
+    stream::read_envelope(&mut reader, &limits()).unwrap();
+    stream::read_envelope(&mut reader, &limits()).unwrap();
+    assert!(matches!(
+        stream::read_envelope(&mut reader, &limits()),
+        Err(Error::FrameTruncated)
+    ));
+
+    // A stream that ends inside the length prefix is also truncated.
+    let mut reader = &stream[..3];
+    assert!(matches!(
+        stream::read_envelope(&mut reader, &limits()),
+        Err(Error::FrameTruncated)
+    ));
+}
+
+#[test]
+fn test_oversized_length() {
+    let (requests, _) = sealed_requests();
+    let stream = stream_of(&requests);
+    let size = requests[0].0.to_cbor_data().len();
+
+    let mut reader = stream.as_slice();
+    assert!(matches!(
+        stream::read_envelope(
+            &mut reader,
+            &ParseLimits::default().with_max_message_size(size - 1),
+        ),
+        Err(Error::FrameTooLarge { size: s, limit }) if s == size && limit == size - 1
+    ));
+
+    // A hostile length is rejected before anything is allocated for it.
+    let mut hostile = u32::MAX.to_be_bytes().to_vec();
+    hostile.extend_from_slice(&[0u8; 16]);
+    assert!(matches!(
+        stream::read_envelope(&mut hostile.as_slice(), &limits()),
+        Err(Error::FrameTooLarge { size, limit: MAX_SIZE })
+            if size == u32::MAX as usize
+    ));
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use bc_components::{ARID, Signer};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{Strictness, prelude::*, set_strictness, strictness};
+
+use crate::common::new_party;
+
+// The strictness is global to the process, so both settings are covered by
+// a single test.
+#[test]
+fn test_strictness() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (client, client_private_keys) = new_party(&mut rng);
+    let (server, server_private_keys) = new_party(&mut rng);
+    let request = SealedRequest::new("test", ARID::new(), &client);
+    let response = SealedResponse::new_success(ARID::new(), &server);
+    let event = SealedEvent::<String>::new("test", ARID::new(), &server);
+    let early_failure = SealedResponse::new_early_failure(&server);
+
+    // By default anything goes.
+    assert_eq!(strictness(), Strictness::Permissive);
+    assert!(request.to_envelope(None, None, None).is_ok());
+    assert!(response.to_envelope(None, None, None).is_ok());
+    assert!(event.to_envelope(None, None, None).is_ok());
+    assert!(
+        early_failure
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .is_ok()
+    );
+
+    set_strictness(Strictness::Production);
+
+    // Unsigned or unencrypted messages are refused.
+    for (sender, recipient) in [
+        (None, None),
+        (Some(&client_private_keys), None),
+        (None, Some(&server)),
+    ] {
+        let sender = sender.map(|keys| keys as &dyn Signer);
+        assert!(matches!(
+            request.to_envelope(None, sender, recipient),
+            Err(Error::UnprotectedSealForbidden)
+        ));
+    }
+    assert!(matches!(
+        response.to_envelope(None, None, Some(&client)),
+        Err(Error::UnprotectedSealForbidden)
+    ));
+    assert!(matches!(
+        event.to_envelope(None, Some(&server_private_keys), None),
+        Err(Error::UnprotectedSealForbidden)
+    ));
+
+    // Signed and encrypted messages are sealed as before.
+    let envelope = request
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    SealedRequest::try_from_envelope(
+        &envelope,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+
+    // The escape hatch still seals unprotected messages.
+    let envelope = request
+        .to_envelope_unprotected_i_know_what_i_am_doing(
+            None,
+            None,
+            &[],
+            &SealOptions::default(),
+        )
+        .unwrap();
+    assert!(!envelope.is_encrypted());
+    assert!(
+        response
+            .to_envelope_unprotected_i_know_what_i_am_doing(
+                None,
+                None,
+                &[],
+                &SealOptions::default(),
+            )
+            .is_ok()
+    );
+    assert!(
+        event
+            .to_envelope_unprotected_i_know_what_i_am_doing(
+                None,
+                Some(&server_private_keys),
+                &[],
+                &SealOptions::default(),
+            )
+            .is_ok()
+    );
+
+    // Early failures must give a reason.
+    assert!(matches!(
+        early_failure.to_envelope(
+            None,
+            Some(&server_private_keys),
+            Some(&client)
+        ),
+        Err(Error::EarlyFailureWithoutReason)
+    ));
+    assert!(
+        early_failure
+            .clone()
+            .with_error("Unknown sender")
+            .to_envelope(None, Some(&server_private_keys), Some(&client))
+            .is_ok()
+    );
+
+    set_strictness(Strictness::Permissive);
+    assert!(request.to_envelope(None, None, None).is_ok());
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use bc_components::{ARID, SymmetricKey};
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{ContinuationSealer, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_symmetric_continuation() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, _) = new_party(&mut rng);
+    let key = SymmetricKey::new();
+
+    let continuation = Continuation::new("cursor").with_valid_id(ARID::new());
+    let envelope = continuation.to_envelope_with_symmetric_key(&key);
+    let parsed = Continuation::try_from_envelope_with_symmetric_key(
+        &envelope, None, None, &key,
+    )
+    .unwrap();
+    assert_eq!(parsed, continuation);
+
+    // It is smaller than a continuation encapsulated to a public key.
+    let encapsulated =
+        continuation.to_envelope(Some(server.encryption_key().unwrap()));
+    assert!(envelope.to_cbor_data().len() < encapsulated.to_cbor_data().len());
+
+    assert!(
+        Continuation::try_from_envelope_with_symmetric_key(
+            &envelope,
+            None,
+            None,
+            &SymmetricKey::new(),
+        )
+        .is_err()
+    );
+    assert!(matches!(
+        Continuation::try_from_envelope_with_symmetric_key(
+            &continuation.to_envelope(None),
+            None,
+            None,
+            &key,
+        ),
+        Err(Error::ContinuationNotEncrypted)
+    ));
+}
+
+#[test]
+fn test_server_with_local_secret() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let key = SymmetricKey::new();
+
+    // Returns the continuation the server issues with a response sealed
+    // with `options`.
+    let issue = |options: &SealOptions| {
+        let id = ARID::new();
+        let envelope = SealedResponse::new_success(id, &server)
+            .with_state("cursor")
+            .to_envelope_with_options(
+                None,
+                Some(&server_private_keys),
+                &[&client],
+                options,
+            )
+            .unwrap();
+        SealedResponse::try_from_encrypted_envelope(
+            &envelope,
+            Some(id),
+            None,
+            &client_private_keys,
+        )
+        .unwrap()
+        .peer_continuation()
+        .unwrap()
+        .clone()
+    };
+    let echo = |continuation: Envelope, options: &ParseOptions| {
+        let request = SealedRequest::new("nextPage", ARID::new(), &client)
+            .with_peer_continuation(continuation)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            options,
+            &server_private_keys,
+        )
+    };
+
+    let symmetric =
+        issue(&SealOptions::new().with_continuation_sealer(
+            ContinuationSealer::Symmetric(key.clone()),
+        ));
+    let encapsulated = issue(&SealOptions::new());
+    let options = ParseOptions::new().with_continuation_symmetric_key(key);
+
+    let request = echo(symmetric.clone(), &options).unwrap();
+    assert_eq!(
+        request
+            .state()
+            .unwrap()
+            .extract_subject::<String>()
+            .unwrap(),
+        "cursor"
+    );
+
+    // Continuations issued before the server adopted the secret still
+    // decrypt with its private keys.
+    assert!(echo(encapsulated, &options).is_ok());
+
+    // Without the secret, the continuation cannot be read.
+    assert!(echo(symmetric, &ParseOptions::new()).is_err());
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "test-utils")]
+
+use bc_components::{ARID, XID};
+use bc_envelope::prelude::*;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+/// A handler under development: greets the caller by name, remembering how
+/// many times it has done so in the caller's state.
+fn handle_greet(
+    request: &SealedRequest,
+    server: &XIDDocument,
+) -> SealedResponse {
+    let name: String = request.extract_object_for_parameter("name").unwrap();
+    let count = request
+        .state()
+        .map(|state| state.extract_subject::<u32>().unwrap())
+        .unwrap_or(0);
+    SealedResponse::new_success(request.id(), server)
+        .with_result(format!("Hello, {}!", name))
+        .with_state(count + 1)
+        .with_optional_peer_continuation(request.peer_continuation().cloned())
+}
+
+#[test]
+fn test_handler_without_keys() {
+    let client = XIDDocument::from(XID::from_data([1; 32]));
+    let server = XIDDocument::from(XID::from_data([2; 32]));
+    let id = ARID::new();
+    let peer_continuation = Envelope::new("Opaque.");
+
+    let request = SealedRequest::synthetic(
+        "greet",
+        id,
+        &client,
+        [("name", "Alice")],
+        Some(Envelope::new(2u32)),
+        Some(peer_continuation.clone()),
+    );
+    assert_eq!(request.sender(), &client);
+    assert_eq!(request.function(), &Function::from("greet"));
+
+    let response = handle_greet(&request, &server);
+    assert_eq!(response.id(), Some(id));
+    assert_eq!(
+        response.extract_result::<String>().unwrap(),
+        "Hello, Alice!"
+    );
+    assert_eq!(
+        response.state().unwrap().extract_subject::<u32>().unwrap(),
+        3
+    );
+    assert_eq!(response.peer_continuation(), Some(&peer_continuation));
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "taint-checks")]
+
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use bc_xid::XIDDocument;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+fn parsed_request(state: &str) -> (SealedRequest, XIDDocument) {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let now = Date::try_from("2024-07-04T11:11:11Z").unwrap();
+
+    // The server previously issued a continuation to the client.
+    let server_continuation = Continuation::new(state)
+        .with_valid_until(now + Duration::from_secs(60))
+        .to_envelope(Some(server.encryption_key().unwrap()));
+
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .with_peer_continuation(server_continuation)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parsed = SealedRequest::try_from_envelope(
+        &request,
+        None,
+        Some(now),
+        &server_private_keys,
+    )
+    .unwrap();
+    (parsed, server)
+}
+
+#[test]
+fn test_state_may_be_returned_via_with_state() {
+    let (request, server) = parsed_request("Private server cursor.");
+    let state = request.state().unwrap();
+    assert!(gstp::taint::is_tainted(state));
+
+    let response = SealedResponse::new_success(request.id(), server)
+        .with_result("ok")
+        .with_state(state.clone());
+    assert!(!gstp::taint::is_tainted(response.result().unwrap()));
+}
+
+#[test]
+#[should_panic(expected = "with_result")]
+fn test_state_rejected_in_result() {
+    let (request, server) = parsed_request("Private server result cursor.");
+    let state = request.state().unwrap();
+    let _ = SealedResponse::new_success(request.id(), server)
+        .with_result(state.clone());
+}
+
+#[test]
+#[should_panic(expected = "with_parameter")]
+fn test_nested_state_rejected_in_parameter() {
+    let (request, server) = parsed_request("Private server parameter cursor.");
+    let state = request.state().unwrap();
+    let _ = SealedRequest::new("forward", ARID::new(), server)
+        .with_parameter("echo", state.clone().add_assertion("note", "oops"));
+}
+
+#[test]
+fn test_equal_value_is_not_tainted() {
+    let (request, server) = parsed_request("Public greeting.");
+    assert!(gstp::taint::is_tainted(request.state().unwrap()));
+
+    // A value that merely equals the state is the server's own to publish.
+    let response = SealedResponse::new_success(request.id(), server)
+        .with_result("Public greeting.");
+    assert!(!gstp::taint::is_tainted(response.result().unwrap()));
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use std::sync::Arc;
+
+use bc_components::{ARID, PrivateKeys, XIDProvider, keypair_using};
+use bc_rand::{RandomNumberGenerator, make_fake_random_number_generator};
+use bc_xid::{Key, XIDDocument};
+use gstp::{MemoryTofuStore, TofuStore, prelude::*};
+
+use crate::common::new_party;
+
+/// The same XID, now signing with a new key.
+fn rotate(
+    document: &XIDDocument,
+    rng: &mut impl RandomNumberGenerator,
+) -> (XIDDocument, PrivateKeys) {
+    let (private_keys, public_keys) = keypair_using(rng).unwrap();
+    let mut document = document.clone();
+    document.remove_inception_key().unwrap();
+    document.add_key(Key::new_allow_all(public_keys)).unwrap();
+    (document, private_keys)
+}
+
+#[test]
+fn test_trust_on_first_use() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (peer, peer_private_keys) = new_party(&mut rng);
+    let (rotated, rotated_private_keys) = rotate(&peer, &mut rng);
+    assert_eq!(rotated.xid(), peer.xid());
+
+    let store = Arc::new(MemoryTofuStore::new());
+    let options = ParseOptions::new().with_tofu_store(store.clone());
+    let request = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedRequest::try_from_envelope_opt(
+            &SealedRequest::new("test", ARID::new(), sender)
+                .to_envelope(None, Some(keys), Some(&server))
+                .unwrap(),
+            &options,
+            &server_private_keys,
+        )
+    };
+    let event = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedEvent::<String>::try_from_envelope_opt(
+            &SealedEvent::<String>::new(
+                "Event.".to_string(),
+                ARID::new(),
+                sender,
+            )
+            .to_envelope(None, Some(keys), Some(&server))
+            .unwrap(),
+            &options,
+            &server_private_keys,
+        )
+    };
+
+    // The first message records the peer's key, which later ones must use.
+    assert!(request(&peer, &peer_private_keys).is_ok());
+    assert_eq!(store.len(), 1);
+    assert_eq!(
+        store.lookup(peer.xid()).unwrap().as_ref(),
+        peer.verification_key()
+    );
+    assert!(event(&peer, &peer_private_keys).is_ok());
+
+    // A validly signed document for the same XID with another key is
+    // refused.
+    assert!(matches!(
+        request(&rotated, &rotated_private_keys),
+        Err(Error::SenderKeyChanged { xid }) if xid == peer.xid()
+    ));
+    assert!(matches!(
+        event(&rotated, &rotated_private_keys),
+        Err(Error::SenderKeyChanged { .. })
+    ));
+
+    // Once the rotation is blessed, the new key is trusted in place of the
+    // old.
+    store.bless_key_change(&rotated).unwrap();
+    assert!(request(&rotated, &rotated_private_keys).is_ok());
+    assert!(matches!(
+        request(&peer, &peer_private_keys),
+        Err(Error::SenderKeyChanged { .. })
+    ));
+}
+
+#[test]
+fn test_first_contact_cannot_pin_another_xid() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (peer, peer_private_keys) = new_party(&mut rng);
+    let (mallory, mallory_private_keys) = new_party(&mut rng);
+
+    // Mallory reaches the server first, claiming the peer's XID.
+    let mut impostor = XIDDocument::from_xid(peer.xid());
+    impostor
+        .add_key(mallory.inception_key().unwrap().clone())
+        .unwrap();
+
+    let store = Arc::new(MemoryTofuStore::new());
+    let options = ParseOptions::new().with_tofu_store(store.clone());
+    let request = |sender: &XIDDocument, keys: &PrivateKeys| {
+        SealedRequest::try_from_envelope_opt(
+            &SealedRequest::new("test", ARID::new(), sender)
+                .to_envelope(None, Some(keys), Some(&server))
+                .unwrap(),
+            &options,
+            &server_private_keys,
+        )
+    };
+
+    assert!(matches!(
+        request(&impostor, &mallory_private_keys),
+        Err(Error::SenderKeyNotBound(xid)) if xid == peer.xid()
+    ));
+    assert!(store.is_empty());
+
+    // The real peer is recorded by its inception key, after which the
+    // impostor's key counts as a change.
+    assert!(request(&peer, &peer_private_keys).is_ok());
+    assert_eq!(
+        store.lookup(peer.xid()).unwrap().as_ref(),
+        peer.inception_signing_key()
+    );
+    assert!(matches!(
+        request(&impostor, &mallory_private_keys),
+        Err(Error::SenderKeyChanged { xid }) if xid == peer.xid()
+    ));
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{Transcript, prelude::*};
+
+use crate::common::new_party;
+
+#[test]
+fn test_transcript_chain() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let mut client_transcript = Transcript::new();
+    let mut server_transcript = Transcript::new();
+
+    // The client opens the conversation.
+    let id = ARID::new();
+    let first = SealedRequest::new("open", id, &client)
+        .with_optional_previous_digest(client_transcript.head())
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    client_transcript.record(None, first.digest()).unwrap();
+
+    let request = SealedRequest::try_from_envelope(
+        &first,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(request.previous_digest(), None);
+    server_transcript
+        .record(
+            request.previous_digest(),
+            request.envelope_digest().unwrap(),
+        )
+        .unwrap();
+
+    // The server answers, committing to the request.
+    let second = SealedResponse::new_success(request.id(), &server)
+        .with_previous_digest(server_transcript.head().unwrap())
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    server_transcript
+        .record(request.envelope_digest(), second.digest())
+        .unwrap();
+
+    let response = SealedResponse::try_from_encrypted_envelope(
+        &second,
+        Some(id),
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.previous_digest(), Some(first.digest()));
+    assert_eq!(response.envelope_digest(), Some(second.digest()));
+    client_transcript
+        .record(
+            response.previous_digest(),
+            response.envelope_digest().unwrap(),
+        )
+        .unwrap();
+
+    // The client follows up with an event, committing to the response.
+    let third =
+        SealedEvent::<String>::new("Done.".to_string(), ARID::new(), &client)
+            .with_optional_previous_digest(client_transcript.head())
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+    client_transcript
+        .record(Some(second.digest()), third.digest())
+        .unwrap();
+
+    let event = SealedEvent::<String>::try_from_envelope(
+        &third,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.previous_digest(), Some(second.digest()));
+    server_transcript
+        .record(event.previous_digest(), event.envelope_digest().unwrap())
+        .unwrap();
+
+    assert_eq!(client_transcript, server_transcript);
+    assert_eq!(
+        server_transcript.digests(),
+        &[first.digest(), second.digest(), third.digest()]
+    );
+
+    // Replaying the opening request out of order breaks the chain.
+    let replayed = SealedRequest::try_from_envelope(
+        &first,
+        None,
+        None,
+        &server_private_keys,
+    )
+    .unwrap();
+    assert!(matches!(
+        server_transcript.record(
+            replayed.previous_digest(),
+            replayed.envelope_digest().unwrap()
+        ),
+        Err(Error::TranscriptBroken { link: 3 })
+    ));
+    assert_eq!(server_transcript.len(), 3);
+
+    // As does receiving the same messages in the wrong order.
+    let links = [
+        (None, first.digest()),
+        (Some(second.digest()), third.digest()),
+        (Some(first.digest()), second.digest()),
+    ];
+    assert!(matches!(
+        Transcript::verify(links),
+        Err(Error::TranscriptBroken { link: 1 })
+    ));
+    assert_eq!(
+        Transcript::verify([links[0], links[2], links[1]]).unwrap(),
+        server_transcript
+    );
+}
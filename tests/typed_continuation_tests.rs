@@ -0,0 +1,100 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{TypedContinuation, prelude::*};
+
+use crate::common::new_party;
+
+fn checkout() -> Expression {
+    Expression::new("checkout").with_parameter("cart", 42)
+}
+
+#[test]
+fn test_typed_continuation_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (_, private_keys) = new_party(&mut rng);
+    let public_keys = private_keys.public_keys().unwrap();
+    let id = ARID::new();
+
+    let typed = TypedContinuation::new(checkout())
+        .map_continuation(|continuation| continuation.with_valid_id(id));
+    let envelope = typed.to_envelope(Some(&public_keys));
+    // A typed continuation is encoded exactly as an untyped one.
+    assert_eq!(
+        Continuation::try_from_envelope(
+            &envelope,
+            Some(id),
+            None,
+            Some(&private_keys),
+        )
+        .unwrap(),
+        *typed.continuation()
+    );
+
+    let parsed = TypedContinuation::<Expression>::try_from_envelope(
+        &envelope,
+        Some(id),
+        None,
+        Some(&private_keys),
+    )
+    .unwrap();
+    assert_eq!(parsed, typed);
+    assert_eq!(parsed.state(), &checkout());
+    assert_eq!(parsed.continuation().id(), Some(id));
+
+    let text = TypedContinuation::new("Step 2".to_string());
+    let parsed = TypedContinuation::<String>::try_from_envelope(
+        &text.to_envelope(None),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(parsed.state(), "Step 2");
+
+    // State of another type is reported as such.
+    assert!(matches!(
+        TypedContinuation::<Expression>::try_from(Continuation::new("Step 2")),
+        Err(Error::ContinuationStateDecoding(_))
+    ));
+}
+
+#[test]
+fn test_state_as() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let request =
+        SealedRequest::new("test", ARID::new(), &client).with_state(checkout());
+    assert_eq!(request.state_as::<Expression>().unwrap(), Some(checkout()));
+    assert!(matches!(
+        request.state_as::<String>(),
+        Err(Error::ContinuationStateDecoding(_))
+    ));
+
+    // The state of a response parsed by the client is its own, typed.
+    let continuation = Continuation::new("Step 2")
+        .to_envelope(Some(&client_private_keys.public_keys().unwrap()));
+    let response = SealedResponse::new_success(ARID::new(), &server)
+        .with_peer_continuation(continuation)
+        .to_envelope(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let parsed = SealedResponse::try_from_encrypted_envelope(
+        &response,
+        None,
+        None,
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.state_as::<String>().unwrap(), Some("Step 2".into()));
+
+    let stateless = SealedRequest::new("test", ARID::new(), &client);
+    assert_eq!(stateless.state_as::<String>().unwrap(), None);
+}
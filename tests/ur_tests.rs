@@ -0,0 +1,152 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::prelude::*;
+
+use crate::common::new_party;
+
+#[test]
+fn test_ur_round_trip() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let id = ARID::new();
+    let request = SealedRequest::new("test", id, &client)
+        .with_parameter("param1", 42)
+        .with_state("Client state.");
+    let ur_string = request
+        .to_ur_string(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    assert!(ur_string.starts_with("ur:envelope/"));
+
+    // Decoding and re-encoding reproduces the string exactly.
+    let envelope = Envelope::from_ur_string(ur_string.as_str()).unwrap();
+    assert_eq!(envelope.ur_string(), ur_string);
+
+    let parsed = SealedRequest::try_from_ur_string(
+        ur_string.as_str(),
+        &ParseOptions::new().with_expected_id(id),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(parsed.id(), id);
+    assert_eq!(
+        parsed
+            .extract_object_for_parameter::<i32>("param1")
+            .unwrap(),
+        42
+    );
+
+    let ur_string = SealedResponse::new_success(id, &server)
+        .with_result("ok")
+        .with_optional_peer_continuation(parsed.peer_continuation().cloned())
+        .to_ur_string(None, Some(&server_private_keys), Some(&client))
+        .unwrap();
+    let response = SealedResponse::try_from_ur_string(
+        ur_string,
+        &ParseOptions::new().with_expected_id(id),
+        &client_private_keys,
+    )
+    .unwrap();
+    assert_eq!(response.extract_result::<String>().unwrap(), "ok");
+    assert_eq!(response.state().unwrap(), &Envelope::new("Client state."));
+
+    let ur_string =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &client)
+            .to_ur_string(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+    let event = SealedEvent::<String>::try_from_ur_string(
+        ur_string,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap();
+    assert_eq!(event.content(), "Event.");
+}
+
+#[test]
+fn test_ur_kind_mismatch() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    // An encrypted event is recognized once it is decrypted.
+    let ur_string =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &client)
+            .to_ur_string(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+    let error = SealedRequest::try_from_ur_string(
+        ur_string,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::MessageKindMismatch {
+            expected: MessageKind::Request,
+            found: MessageKind::Event
+        }
+    ));
+
+    // A signed event is recognized without decrypting it.
+    let ur_string =
+        SealedEvent::<String>::new("Event.".to_string(), ARID::new(), &client)
+            .to_ur_string(None, Some(&client_private_keys), None)
+            .unwrap();
+    let error = SealedRequest::try_from_ur_string(
+        ur_string,
+        &ParseOptions::new(),
+        &server_private_keys,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::MessageKindMismatch {
+            expected: MessageKind::Request,
+            found: MessageKind::Event
+        }
+    ));
+}
+
+#[test]
+fn test_malformed_ur() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (_, private_keys) = new_party(&mut rng);
+    let options = ParseOptions::new();
+
+    for ur_string in [
+        "",
+        "not a ur",
+        "ur:envelope/",
+        "ur:envelope/lftpsozsgoaduyhyhdwp",
+        "ur:crypto-seed/oyadgdhkwzdtfthptokigtvwnnjsqzcxknsktdhpyljeda",
+    ] {
+        let error = SealedRequest::try_from_ur_string(
+            ur_string,
+            &options,
+            &private_keys,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(error, Error::InvalidUR(_)),
+            "{ur_string}: {error:?}"
+        );
+    }
+
+    // A valid envelope UR that is not a GSTP message.
+    let ur_string = Envelope::new("Hello.").ur_string();
+    let error =
+        SealedRequest::try_from_ur_string(ur_string, &options, &private_keys)
+            .unwrap_err();
+    assert!(matches!(error, Error::UnknownMessageKind));
+}
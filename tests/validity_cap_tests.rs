@@ -0,0 +1,101 @@
+mod common;
+
+use std::time::Duration;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{ValidityOverflow, prelude::*};
+
+use crate::common::new_party;
+
+fn date(s: &str) -> Date { Date::from_string(s).unwrap() }
+
+const MAX: Duration = Duration::from_secs(15 * 60);
+
+#[test]
+fn test_seal_caps_continuation_validity() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, _) = new_party(&mut rng);
+
+    let now = date("2024-07-01T12:00:00Z");
+    let within = now + Duration::from_secs(10 * 60);
+    let beyond = now + Duration::from_secs(60 * 60);
+    let options = SealOptions::new()
+        .with_now(now)
+        .with_max_continuation_validity(MAX);
+
+    let seal = |valid_until: Option<Date>, options: &SealOptions| {
+        SealedResponse::new_success(ARID::new(), &server)
+            .with_state("Server state.")
+            .seal_detailed(
+                valid_until,
+                Some(&server_private_keys),
+                &[&client],
+                options,
+            )
+            .map(|artifacts| artifacts.continuation_receipt.unwrap())
+    };
+
+    // A continuation valid within the maximum is sealed as requested.
+    assert_eq!(
+        seal(Some(within), &options).unwrap().valid_until,
+        Some(within)
+    );
+
+    // By default, one valid for longer, or not expiring at all, is refused.
+    for valid_until in [Some(beyond), None] {
+        assert!(matches!(
+            seal(valid_until, &options),
+            Err(Error::ContinuationValidityTooLong { max }) if max == MAX
+        ));
+    }
+
+    // Clamping seals it to expire at the maximum instead.
+    let clamping = options.with_validity_overflow(ValidityOverflow::Clamp);
+    for valid_until in [Some(beyond), None] {
+        assert_eq!(
+            seal(valid_until, &clamping).unwrap().valid_until,
+            Some(now + MAX)
+        );
+    }
+}
+
+#[test]
+fn test_parse_rejects_overlong_continuations() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+
+    let now = date("2024-07-01T12:00:00Z");
+    let parse = |valid_until: Option<Date>| {
+        let continuation = Continuation::new("Server state.")
+            .with_optional_valid_until(valid_until)
+            .to_envelope(Some(&server_private_keys.public_keys().unwrap()));
+        let request = SealedRequest::new("test", ARID::new(), &client)
+            .with_peer_continuation(continuation)
+            .to_envelope(None, Some(&client_private_keys), Some(&server))
+            .unwrap();
+        SealedRequest::try_from_envelope_opt(
+            &request,
+            &ParseOptions::new()
+                .with_now(now)
+                .with_max_continuation_validity(MAX),
+            &server_private_keys,
+        )
+    };
+
+    parse(Some(now + MAX)).unwrap();
+    let decade = now + Duration::from_secs(10 * 365 * 24 * 60 * 60);
+    for valid_until in [Some(decade), None] {
+        assert!(matches!(
+            parse(valid_until),
+            Err(Error::ContinuationValidityTooLong { max }) if max == MAX
+        ));
+    }
+}
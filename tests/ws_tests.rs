@@ -0,0 +1,159 @@
+mod common;
+
+use bc_components::ARID;
+use bc_envelope::prelude::*;
+use bc_rand::make_fake_random_number_generator;
+use gstp::{
+    prelude::*,
+    transport::ws::{self, GstpFrame, WsSink, WsSource},
+};
+use tokio::sync::mpsc;
+
+use crate::common::new_party;
+
+/// One direction of an in-memory WebSocket connection.
+struct ChannelSink(mpsc::UnboundedSender<Vec<u8>>);
+
+struct ChannelSource(mpsc::UnboundedReceiver<Vec<u8>>);
+
+impl WsSink for ChannelSink {
+    async fn send_binary(&mut self, data: Vec<u8>) -> Result<()> {
+        self.0.send(data).map_err(|_| Error::EndOfStream)
+    }
+}
+
+impl WsSource for ChannelSource {
+    async fn recv_binary(&mut self) -> Result<Vec<u8>> {
+        self.0.recv().await.ok_or(Error::EndOfStream)
+    }
+}
+
+/// The two ends of an in-memory WebSocket connection.
+fn duplex() -> ((ChannelSink, ChannelSource), (ChannelSink, ChannelSource)) {
+    let (a_tx, a_rx) = mpsc::unbounded_channel();
+    let (b_tx, b_rx) = mpsc::unbounded_channel();
+    (
+        (ChannelSink(a_tx), ChannelSource(b_rx)),
+        (ChannelSink(b_tx), ChannelSource(a_rx)),
+    )
+}
+
+#[tokio::test]
+async fn test_loopback() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let ((client_sink, client_source), (server_sink, server_source)) = duplex();
+
+    // The server answers each request and announces that it did.
+    let server_task = {
+        let server = server.clone();
+        let (mut sender, mut receiver) = ws::split(
+            server_sink,
+            server_source,
+            &server_private_keys,
+            &client,
+        );
+        tokio::spawn(async move {
+            let GstpFrame::Request(request) =
+                receiver.recv::<String>().await.unwrap()
+            else {
+                panic!("expected a request");
+            };
+            let amount: u32 =
+                request.extract_object_for_parameter("amount").unwrap();
+            let response = SealedResponse::new_success(request.id(), &server)
+                .with_result(amount * 2);
+            sender.send_response(&response).await.unwrap();
+            let event = SealedEvent::<String>::new(
+                "Request answered.".to_string(),
+                ARID::new(),
+                &server,
+            );
+            sender.send_event(&event).await.unwrap();
+
+            let GstpFrame::Event(event) =
+                receiver.recv::<String>().await.unwrap()
+            else {
+                panic!("expected an event");
+            };
+            assert_eq!(event.content(), "Goodbye.");
+            assert!(matches!(
+                receiver.recv::<String>().await,
+                Err(Error::EndOfStream)
+            ));
+        })
+    };
+
+    let (mut sender, mut receiver) =
+        ws::split(client_sink, client_source, &client_private_keys, &server);
+    let id = ARID::new();
+    let request =
+        SealedRequest::new("double", id, &client).with_parameter("amount", 21);
+    sender.send_request(&request).await.unwrap();
+
+    let frame = receiver.recv::<String>().await.unwrap();
+    assert_eq!(frame.kind(), MessageKind::Response);
+    let GstpFrame::Response(response) = frame else {
+        unreachable!()
+    };
+    assert_eq!(response.id(), Some(id));
+    assert_eq!(response.extract_result::<u32>().unwrap(), 42);
+
+    let GstpFrame::Event(event) = receiver.recv::<String>().await.unwrap()
+    else {
+        panic!("expected an event");
+    };
+    assert_eq!(event.content(), "Request answered.");
+
+    let event = SealedEvent::<String>::new(
+        "Goodbye.".to_string(),
+        ARID::new(),
+        &client,
+    );
+    sender.send_event(&event).await.unwrap();
+    drop(sender);
+    server_task.await.unwrap();
+}
+
+#[test]
+fn test_invalid_frames() {
+    bc_envelope::register_tags();
+
+    let mut rng = make_fake_random_number_generator();
+    let (server, server_private_keys) = new_party(&mut rng);
+    let (client, client_private_keys) = new_party(&mut rng);
+    let request = SealedRequest::new("test", ARID::new(), &client)
+        .to_envelope(None, Some(&client_private_keys), Some(&server))
+        .unwrap();
+    let parse = |frame: &[u8]| {
+        GstpFrame::<String>::parse(
+            frame,
+            &ParseOptions::new(),
+            &server_private_keys,
+        )
+    };
+
+    let frame = ws::encode_frame(MessageKind::Request, &request);
+    let (kind, envelope) = ws::decode_frame(&frame).unwrap();
+    assert_eq!(kind, MessageKind::Request);
+    assert!(envelope.is_identical_to(&request));
+    assert!(matches!(parse(&frame), Ok(GstpFrame::Request(_))));
+
+    assert!(matches!(parse(&[]), Err(Error::FrameTruncated)));
+    let mut unknown = frame.clone();
+    unknown[0] = 9;
+    assert!(matches!(parse(&unknown), Err(Error::UnknownFrameKind(9))));
+
+    // A frame whose kind misrepresents the message it carries.
+    let mislabeled = ws::encode_frame(MessageKind::Event, &request);
+    assert!(matches!(
+        parse(&mislabeled),
+        Err(Error::MessageKindMismatch {
+            expected: MessageKind::Event,
+            found: MessageKind::Request,
+        })
+    ));
+}